@@ -85,6 +85,17 @@ fn main() {
     let shaders = vec![
         ("assets/shaders/builtin/object.vert.slang", "main"),
         ("assets/shaders/builtin/object.frag.slang", "main"),
+        ("assets/shaders/builtin/object_bindless.frag.slang", "main"),
+        ("assets/shaders/builtin/immediate.vert.slang", "main"),
+        ("assets/shaders/builtin/immediate.frag.slang", "main"),
+        ("assets/shaders/builtin/ui.vert.slang", "main"),
+        ("assets/shaders/builtin/ui.frag.slang", "main"),
+        ("assets/shaders/builtin/fullscreen.vert.slang", "main"),
+        ("assets/shaders/builtin/copy.frag.slang", "main"),
+        ("assets/shaders/builtin/color_filter.frag.slang", "main"),
+        ("assets/shaders/builtin/tonemap.frag.slang", "main"),
+        ("assets/shaders/builtin/fxaa.frag.slang", "main"),
+        ("assets/shaders/builtin/vignette.frag.slang", "main"),
     ];
     compile_shaders(shaders);
 
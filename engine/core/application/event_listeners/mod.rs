@@ -4,6 +4,7 @@ use on_key_pressed::ApplicationOnKeyPressedListener;
 use on_key_released::ApplicationOnKeyReleasedListener;
 use on_quit::ApplicationOnQuitListener;
 use on_resize::ApplicationOnResizedListener;
+use on_suspend::ApplicationOnSuspendListener;
 
 use crate::{
     core::{
@@ -15,10 +16,11 @@ use crate::{
 
 use super::Application;
 
-pub mod on_key_pressed;
-pub mod on_key_released;
-pub mod on_quit;
-pub mod on_resize;
+pub(crate) mod on_key_pressed;
+pub(crate) mod on_key_released;
+pub(crate) mod on_quit;
+pub(crate) mod on_resize;
+pub(crate) mod on_suspend;
 
 impl Application {
     pub(super) fn init_event_listener(&self) -> Result<(), EngineError> {
@@ -30,6 +32,8 @@ impl Application {
             Arc::new(Mutex::new(ApplicationOnKeyReleasedListener {}));
         let on_resized_listener: Arc<Mutex<dyn EventListener>> =
             Arc::new(Mutex::new(ApplicationOnResizedListener {}));
+        let on_suspend_listener: Arc<Mutex<dyn EventListener>> =
+            Arc::new(Mutex::new(ApplicationOnSuspendListener {}));
 
         if let Err(err) = event_register(EventCode::ApplicationQuit, Arc::clone(&on_quit_listener))
         {
@@ -68,6 +72,28 @@ impl Application {
             return Err(EngineError::InitializationFailed);
         }
 
+        if let Err(err) = event_register(
+            EventCode::ApplicationSuspended,
+            Arc::clone(&on_suspend_listener),
+        ) {
+            error!(
+                "Failed to register the `ApplicationSuspended' event listener: {:?}",
+                err
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+
+        if let Err(err) = event_register(
+            EventCode::ApplicationResumed,
+            Arc::clone(&on_suspend_listener),
+        ) {
+            error!(
+                "Failed to register the `ApplicationResumed' event listener: {:?}",
+                err
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+
         Ok(())
     }
 }
@@ -1,19 +1,29 @@
 use crate::{
     core::{
-        debug::errors::EngineError,
+        application::{application_is_paused, application_step_frame, application_toggle_pause},
+        debug::{
+            errors::EngineError,
+            frame_history::{
+                frame_history_browse, frame_history_is_overlay_visible,
+                frame_history_set_overlay_visible,
+            },
+            profiler::{profiler_is_overlay_visible, profiler_set_overlay_visible},
+        },
         systems::{
-            events::{event_fire, EventCode, EventListener},
+            console::console_on_key_pressed,
+            events::{event_fire, EventCode, EventContext, EventListener},
             input::keyboard::Key,
         },
     },
     error,
+    renderer::renderer_frontend::{renderer_toggle_debug_camera, renderer_toggle_debug_workspace},
 };
 
 pub(super) struct ApplicationOnKeyPressedListener;
 
 impl EventListener for ApplicationOnKeyPressedListener {
-    fn on_event_callback(&mut self, code: EventCode) -> Result<bool, EngineError> {
-        let key_code = match code {
+    fn on_event_callback(&mut self, context: EventContext) -> Result<bool, EngineError> {
+        let key_code = match context.code {
             EventCode::KeyPressed { key_code } => key_code,
             wrong_code => {
                 error!(
@@ -23,6 +33,10 @@ impl EventListener for ApplicationOnKeyPressedListener {
                 return Err(EngineError::InvalidValue);
             }
         };
+        // The console takes the keys while it is open
+        if console_on_key_pressed(key_code)? {
+            return Ok(true);
+        }
         if key_code == (Key::ESCAPE as u16) {
             match event_fire(EventCode::ApplicationQuit) {
                 Ok(_) => return Ok(true),
@@ -35,6 +49,54 @@ impl EventListener for ApplicationOnKeyPressedListener {
                 }
             }
         }
+        if key_code == (Key::PAUSE as u16) {
+            if let Err(err) = application_toggle_pause() {
+                error!("Failed to toggle the application pause: {:?}", err);
+                return Err(EngineError::Unknown);
+            }
+            return Ok(true);
+        }
+        // The debug workspace is only drawn in debug builds
+        if cfg!(debug_assertions) && key_code == (Key::F2 as u16) {
+            if let Err(err) = renderer_toggle_debug_workspace() {
+                error!("Failed to toggle the debug workspace: {:?}", err);
+                return Err(EngineError::Unknown);
+            }
+            return Ok(true);
+        }
+        if key_code == (Key::F3 as u16) {
+            profiler_set_overlay_visible(!profiler_is_overlay_visible()?)?;
+            return Ok(true);
+        }
+        if key_code == (Key::F5 as u16) {
+            frame_history_set_overlay_visible(!frame_history_is_overlay_visible()?)?;
+            return Ok(true);
+        }
+        // Page up and down browse the recorded frames while the history is shown
+        if frame_history_is_overlay_visible()? {
+            if key_code == (Key::PRIOR as u16) {
+                frame_history_browse(1)?;
+                return Ok(true);
+            }
+            if key_code == (Key::NEXT as u16) {
+                frame_history_browse(-1)?;
+                return Ok(true);
+            }
+        }
+        if key_code == (Key::F4 as u16) {
+            if let Err(err) = renderer_toggle_debug_camera() {
+                error!("Failed to toggle the debug camera: {:?}", err);
+                return Err(EngineError::Unknown);
+            }
+            return Ok(true);
+        }
+        if key_code == (Key::F10 as u16) {
+            // Stepping is only meaningful while paused, ignore it otherwise
+            if application_is_paused()? {
+                application_step_frame()?;
+            }
+            return Ok(true);
+        }
         Ok(false)
     }
 }
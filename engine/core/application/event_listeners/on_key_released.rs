@@ -1,7 +1,7 @@
 use crate::{
     core::{
         debug::errors::EngineError,
-        systems::events::{EventCode, EventListener},
+        systems::events::{EventCode, EventContext, EventListener},
     },
     error,
 };
@@ -9,8 +9,8 @@ use crate::{
 pub(super) struct ApplicationOnKeyReleasedListener;
 
 impl EventListener for ApplicationOnKeyReleasedListener {
-    fn on_event_callback(&mut self, code: EventCode) -> Result<bool, EngineError> {
-        let key_code = match code {
+    fn on_event_callback(&mut self, context: EventContext) -> Result<bool, EngineError> {
+        let key_code = match context.code {
             EventCode::KeyReleased { key_code } => key_code,
             wrong_code => {
                 error!(
@@ -2,7 +2,7 @@ use crate::{
     core::{
         application::{fetch_global_application, ApplicationState},
         debug::errors::EngineError,
-        systems::events::{EventCode, EventListener},
+        systems::events::{EventCode, EventContext, EventListener},
     },
     error,
 };
@@ -10,10 +10,10 @@ use crate::{
 pub(super) struct ApplicationOnQuitListener;
 
 impl EventListener for ApplicationOnQuitListener {
-    fn on_event_callback(&mut self, code: EventCode) -> Result<bool, EngineError> {
-        match code {
+    fn on_event_callback(&mut self, context: EventContext) -> Result<bool, EngineError> {
+        match context.code {
             EventCode::ApplicationQuit => {
-                let app = fetch_global_application()?;
+                let mut app = fetch_global_application()?;
                 app.state = ApplicationState::ShuttingDown;
             }
             wrong_code => {
@@ -25,6 +25,7 @@ impl EventListener for ApplicationOnQuitListener {
             }
         };
 
-        Ok(true)
+        // The game listeners are told as well
+        Ok(false)
     }
 }
@@ -1,23 +1,22 @@
 use crate::{
     core::{
-        application::{fetch_global_application, ApplicationState},
+        application::{application_get_absolute_time, fetch_global_application, ApplicationState},
         debug::errors::EngineError,
-        systems::events::{EventCode, EventListener},
+        systems::events::{EventCode, EventContext, EventListener},
     },
     error, info,
-    renderer::renderer_frontend::fetch_global_renderer,
 };
 
 pub(super) struct ApplicationOnResizedListener;
 
 impl EventListener for ApplicationOnResizedListener {
-    fn on_event_callback(&mut self, code: EventCode) -> Result<bool, EngineError> {
-        let app = fetch_global_application()?;
+    fn on_event_callback(&mut self, context: EventContext) -> Result<bool, EngineError> {
+        let mut app = fetch_global_application()?;
         if !app.is_resizable {
-            return Ok(true);
+            return Ok(false);
         }
 
-        let (width, height) = match code {
+        let (width, height) = match context.code {
             EventCode::Resized { width, height } => (width, height),
             wrong_code => {
                 error!(
@@ -28,7 +27,6 @@ impl EventListener for ApplicationOnResizedListener {
             }
         };
 
-        let app = fetch_global_application()?;
         let old_with = app.width;
         let old_height = app.height;
         if old_with != width || old_height != height {
@@ -44,7 +42,7 @@ impl EventListener for ApplicationOnResizedListener {
             if width == 0 || height == 0 {
                 info!("Window minimized, suspending the application");
                 app.state = ApplicationState::Suspended;
-                return Ok(true);
+                return Ok(false);
             }
 
             // Quit suspended mode
@@ -53,18 +51,11 @@ impl EventListener for ApplicationOnResizedListener {
                 app.state = ApplicationState::Running;
             }
 
-            // game on resize
-            if let Err(err) = app.game.on_resize(width, height) {
-                error!(
-                    "Failed to call the `on_resize' function of the game: {:?}",
-                    err
-                );
-                return Err(EngineError::UpdateFailed);
-            }
-            let renderer = fetch_global_renderer(EngineError::UpdateFailed)?;
-            renderer.resize(width, height)?;
+            // Dragging the border fires a resize per step, the renderer only follows the last one
+            app.pending_resize = Some((width, height));
+            app.last_resize_time = application_get_absolute_time()?;
         }
 
-        Ok(true)
+        Ok(false)
     }
 }
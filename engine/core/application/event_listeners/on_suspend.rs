@@ -0,0 +1,40 @@
+use crate::{
+    core::{
+        application::{fetch_global_application, ApplicationState},
+        debug::errors::EngineError,
+        systems::events::{EventCode, EventContext, EventListener},
+    },
+    error, info,
+};
+
+pub(super) struct ApplicationOnSuspendListener;
+
+impl EventListener for ApplicationOnSuspendListener {
+    fn on_event_callback(&mut self, context: EventContext) -> Result<bool, EngineError> {
+        let mut app = fetch_global_application()?;
+        match context.code {
+            EventCode::ApplicationSuspended => {
+                if app.state == ApplicationState::Running {
+                    info!("Window hidden, suspending the application");
+                    app.state = ApplicationState::Suspended;
+                }
+            }
+            EventCode::ApplicationResumed => {
+                if app.state == ApplicationState::Suspended {
+                    info!("Window shown, resuming the application");
+                    app.state = ApplicationState::Running;
+                }
+            }
+            wrong_code => {
+                error!(
+                    "Failed to call the application 'OnSuspend' listener: got {:?} code",
+                    wrong_code
+                );
+                return Err(EngineError::InvalidValue);
+            }
+        };
+
+        // The game listeners are told as well
+        Ok(false)
+    }
+}
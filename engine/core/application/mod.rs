@@ -1,17 +1,70 @@
-use std::sync::Mutex;
-
-use once_cell::sync::Lazy;
+use std::path::PathBuf;
 
 use crate::{
     debug, error,
     game::Game,
+    info,
     platforms::platform::{platform_init, Platform},
-    renderer::{renderer_frontend::renderer_draw_frame, renderer_types::RenderFrameData},
+    renderer::{
+        accessibility::AccessibilitySettings,
+        renderer_frontend::{
+            fetch_global_renderer, renderer_add_window, renderer_draw_ecs_sprites,
+            renderer_draw_frame, renderer_draw_text, renderer_get_draw_call_count,
+            renderer_get_font, renderer_get_statistics, renderer_measure_text,
+            renderer_remove_window,
+        },
+        renderer_types::{
+            AntiAliasing, DepthStencilSettings, DisplayOutput, RenderFrameData,
+            RendererBackendType, VsyncMode,
+        },
+        scene::streaming::scene_streaming_update,
+    },
+    warn,
+};
+
+use window_geometry::WindowGeometry;
+use windows::{WindowCreatorParameters, WindowHandle};
+
+use super::{
+    debug::{
+        clock::Clock,
+        errors::EngineError,
+        frame_history::{
+            frame_history_end_frame, frame_history_get_overlay_lines,
+            frame_history_is_overlay_visible,
+        },
+        input_latency::{input_latency_consume_events, input_latency_get_overlay_lines},
+        profiler::{
+            profiler_begin_scope, profiler_end_frame, profiler_end_scope,
+            profiler_get_overlay_lines, profiler_is_overlay_visible,
+            statistics::{profiler_record_frame_statistics, FrameTimings},
+        },
+        telemetry::{telemetry_end_frame, telemetry_is_recording, TelemetryCapture},
+    },
+    ecs::{ecs_run_systems, ecs_update_animations, ecs_update_sprite_animations},
+    globals::{EngineGlobal, EngineGlobalGuard, ThreadBound},
+    systems::{
+        config::config_poll,
+        console::console_get_overlay_line,
+        events::{event_fire, events_dispatch_queued, events_hold, events_release_held, EventCode},
+        input::{input_update, mouse::CursorMode},
+        jobs::{job_is_pending, jobs_run_completions, JobHandle},
+    },
 };
 
-use super::{debug::clock::Clock, debug::errors::EngineError, systems::input::input_update};
+pub(crate) mod event_listeners;
+pub(crate) mod window_geometry;
+pub(crate) mod windows;
 
-pub mod event_listeners;
+/// Distance in pixels of the profiler overlay from the top left corner of the window
+const PROFILER_OVERLAY_MARGIN: f32 = 8.0;
+
+/// Time in seconds the window size must stay the same before the renderer is resized
+const RESIZE_DEBOUNCE_SECONDS: f64 = 0.1;
+
+/// Fixed updates run in a single frame at most, the simulation slows down past it instead of
+/// spending every frame catching up
+const FIXED_UPDATE_MAX_STEPS_PER_FRAME: u32 = 8;
 
 /// Flags for the application
 pub struct ApplicationParametersFlags {
@@ -41,6 +94,58 @@ impl Default for ApplicationParametersFlags {
     }
 }
 
+/// Window owned by the application the engine is embedded into
+/// The engine draws inside of it instead of creating its own window
+#[derive(Clone, Copy, Debug)]
+pub enum ExternalWindow {
+    /// Id of an X11 window, the engine draws in a child window following its size
+    #[cfg(target_os = "linux")]
+    Xcb { window: u32 },
+    /// `NSView` of the host, the engine attaches its metal layer to it
+    /// Its size is given by the initial size of the parameters
+    /// The host owns the event loop and forwards the resizes with `application_notify_resized`
+    #[cfg(target_os = "macos")]
+    AppKit {
+        ns_view: std::ptr::NonNull<std::ffi::c_void>,
+    },
+}
+
+impl ExternalWindow {
+    /// Only the xcb and xlib handles are supported on linux, and the appkit ones on macos
+    pub fn from_raw_window_handle(
+        handle: raw_window_handle::RawWindowHandle,
+    ) -> Result<Self, EngineError> {
+        match handle {
+            #[cfg(target_os = "linux")]
+            raw_window_handle::RawWindowHandle::Xcb(handle) => Ok(Self::Xcb {
+                window: handle.window.get(),
+            }),
+            // Xlib and xcb share the window ids of the X server
+            #[cfg(target_os = "linux")]
+            raw_window_handle::RawWindowHandle::Xlib(handle) => {
+                match u32::try_from(handle.window) {
+                    Ok(window) if window != 0 => Ok(Self::Xcb { window }),
+                    _ => {
+                        error!("Invalid xlib window to embed the engine in: {:?}", handle);
+                        Err(EngineError::InvalidValue)
+                    }
+                }
+            }
+            #[cfg(target_os = "macos")]
+            raw_window_handle::RawWindowHandle::AppKit(handle) => Ok(Self::AppKit {
+                ns_view: handle.ns_view,
+            }),
+            handle => {
+                error!(
+                    "Can't embed the engine in a window of this platform: {:?}",
+                    handle
+                );
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+}
+
 /// The application's parameters
 pub struct ApplicationParameters {
     pub application_name: String,
@@ -49,6 +154,34 @@ pub struct ApplicationParameters {
     pub initial_width: u32,
     pub initial_height: u32,
     pub flags: ApplicationParametersFlags,
+    pub accessibility: AccessibilitySettings,
+    /// Record the metrics of the first frames
+    pub telemetry_capture: Option<TelemetryCapture>,
+    /// Multisampling of the scene, default to off
+    pub anti_aliasing: AntiAliasing,
+    /// Default to sdr, the hdr output falls back to sdr if the display does not support it
+    /// See `renderer_get_output_color_space` for the granted one
+    pub display_output: DisplayOutput,
+    /// Default to the stencil formats first, then the depth only ones
+    /// Overridden by the `depth_formats' and `require_stencil' settings of the config file
+    pub depth_stencil: DepthStencilSettings,
+    /// Default to vulkan, the null backend runs without a window nor a gpu
+    pub renderer_backend: RendererBackendType,
+    /// Draw inside a window of the host application, default to none
+    /// The position is then ignored and the engine always follows the resizes of the host
+    pub external_window: Option<ExternalWindow>,
+    /// File keeping the window position and size between launches, default to none
+    /// The initial position and size are used on the first launch
+    pub window_geometry_path: Option<PathBuf>,
+    /// Number of `on_fixed_update` calls per second, default to 60
+    pub fixed_update_rate: f64,
+    /// Default to mailbox, see `renderer_set_vsync_mode` to change it while running
+    pub vsync: VsyncMode,
+    /// Maximum number of frames per second, default to none
+    /// The remaining time of the faster frames is given back to the os
+    pub frame_rate_limit: Option<f64>,
+    /// Config file read at launch and watched while running, default to none, see `config_load`
+    pub config_path: Option<PathBuf>,
 }
 
 impl ApplicationParameters {
@@ -72,6 +205,54 @@ impl ApplicationParameters {
         self.application_name = name;
         self
     }
+    pub fn accessibility(mut self, accessibility: AccessibilitySettings) -> Self {
+        self.accessibility = accessibility;
+        self
+    }
+    pub fn telemetry_capture(mut self, capture: Option<TelemetryCapture>) -> Self {
+        self.telemetry_capture = capture;
+        self
+    }
+    pub fn anti_aliasing(mut self, anti_aliasing: AntiAliasing) -> Self {
+        self.anti_aliasing = anti_aliasing;
+        self
+    }
+    pub fn display_output(mut self, display_output: DisplayOutput) -> Self {
+        self.display_output = display_output;
+        self
+    }
+    pub fn depth_stencil(mut self, depth_stencil: DepthStencilSettings) -> Self {
+        self.depth_stencil = depth_stencil;
+        self
+    }
+    pub fn renderer_backend(mut self, renderer_backend: RendererBackendType) -> Self {
+        self.renderer_backend = renderer_backend;
+        self
+    }
+    pub fn external_window(mut self, external_window: Option<ExternalWindow>) -> Self {
+        self.external_window = external_window;
+        self
+    }
+    pub fn window_geometry_path(mut self, path: Option<PathBuf>) -> Self {
+        self.window_geometry_path = path;
+        self
+    }
+    pub fn fixed_update_rate(mut self, rate: f64) -> Self {
+        self.fixed_update_rate = rate;
+        self
+    }
+    pub fn vsync(mut self, vsync: VsyncMode) -> Self {
+        self.vsync = vsync;
+        self
+    }
+    pub fn frame_rate_limit(mut self, limit: Option<f64>) -> Self {
+        self.frame_rate_limit = limit;
+        self
+    }
+    pub fn config_path(mut self, path: Option<PathBuf>) -> Self {
+        self.config_path = path;
+        self
+    }
 }
 
 impl Default for ApplicationParameters {
@@ -83,73 +264,335 @@ impl Default for ApplicationParameters {
             initial_width: 1280,
             initial_height: 720,
             flags: Default::default(),
+            accessibility: Default::default(),
+            telemetry_capture: None,
+            anti_aliasing: AntiAliasing::default(),
+            display_output: DisplayOutput::default(),
+            depth_stencil: DepthStencilSettings::default(),
+            renderer_backend: RendererBackendType::default(),
+            external_window: None,
+            window_geometry_path: None,
+            fixed_update_rate: 60.,
+            vsync: VsyncMode::default(),
+            frame_rate_limit: None,
+            config_path: None,
         }
     }
 }
 
-#[derive(PartialEq)]
+#[derive(Default, PartialEq)]
 pub(crate) enum ApplicationState {
+    #[default]
     Running,
     ShuttingDown,
     Suspended,
 }
 
+/// The platform and the game are not part of it, the game and the event listeners can then call
+/// the application functions while the engine loop runs them
+#[derive(Default)]
 pub(crate) struct Application {
-    pub platform: Box<dyn Platform>,
-    pub game: Box<dyn Game>,
-
+    pub is_initialized: bool,
     pub state: ApplicationState,
     pub clock: Clock,
     pub last_time: f64,
     pub width: u32,
     pub height: u32,
     pub is_resizable: bool,
+    /// The game is not updated while paused but the rendering continues
+    pub is_paused: bool,
+    /// Update the game once while paused
+    pub should_step_frame: bool,
+    /// Jobs the loading screen waits for, empty when not loading
+    pub loading_jobs: Vec<JobHandle>,
+    /// Number of jobs since the loading started, for the progress
+    pub loading_job_count: usize,
+    /// Where the window geometry is saved on shutdown
+    pub window_geometry_path: Option<PathBuf>,
+    /// Duration of a fixed update in seconds
+    pub fixed_delta_time: f64,
+    /// Time not simulated yet by the fixed updates
+    pub fixed_time_accumulator: f64,
+    /// Minimal duration of a frame in seconds, none when the frame rate is not limited
+    pub target_frame_seconds: Option<f64>,
+    /// Cpu timings of the current frame, for the statistics
+    pub frame_timings: FrameTimings,
+    /// Size the renderer and the game are not resized to yet
+    pub pending_resize: Option<(u32, u32)>,
+    /// Absolute time of the last resize event, in seconds
+    pub last_resize_time: f64,
 }
 
-#[derive(Default)]
-pub(crate) struct ApplicationWrapper {
-    pub application: Option<Application>,
+pub(crate) static GLOBAL_APPLICATION: EngineGlobal<Application> = EngineGlobal::new("application");
+
+pub(crate) fn fetch_global_application() -> Result<EngineGlobalGuard<Application>, EngineError> {
+    let global_application = GLOBAL_APPLICATION.fetch(EngineError::AccessFailed)?;
+    if !global_application.is_initialized {
+        error!("The application is not initialized");
+        return Err(EngineError::NotInitialized);
+    }
+    Ok(global_application)
 }
 
-unsafe impl Send for Application {}
-unsafe impl Sync for Application {}
+/// Windows and events of the os, only usable from the thread that created them
+#[derive(Default)]
+pub(crate) struct PlatformWrapper {
+    pub platform: Option<ThreadBound<Box<dyn Platform>>>,
+}
 
-pub(crate) static mut GLOBAL_APPLICATION: Lazy<Mutex<ApplicationWrapper>> =
-    Lazy::new(Mutex::default);
+impl PlatformWrapper {
+    pub fn get(&self) -> Result<&dyn Platform, EngineError> {
+        match self.platform.as_ref() {
+            Some(platform) => Ok(platform.get(EngineError::AccessFailed)?.as_ref()),
+            None => {
+                error!("The platform is not initialized");
+                Err(EngineError::NotInitialized)
+            }
+        }
+    }
 
-fn fetch_global_application_wrapper(
-    error: EngineError,
-) -> Result<&'static mut ApplicationWrapper, EngineError> {
-    unsafe {
-        match GLOBAL_APPLICATION.get_mut() {
-            Ok(wrapper) => Ok(wrapper),
-            Err(err) => {
-                error!("Failed to fetch the global application: {:?}", err);
-                Err(error)
+    pub fn get_mut(&mut self) -> Result<&mut dyn Platform, EngineError> {
+        match self.platform.as_mut() {
+            Some(platform) => Ok(platform.get_mut(EngineError::AccessFailed)?.as_mut()),
+            None => {
+                error!("The platform is not initialized");
+                Err(EngineError::NotInitialized)
             }
         }
     }
 }
 
-pub(crate) fn fetch_global_application() -> Result<&'static mut Application, EngineError> {
-    let global_application = fetch_global_application_wrapper(EngineError::AccessFailed)?;
-    Ok(global_application.application.as_mut().unwrap())
+pub(crate) static GLOBAL_PLATFORM: EngineGlobal<PlatformWrapper> = EngineGlobal::new("platform");
+
+pub(crate) fn fetch_global_platform() -> Result<EngineGlobalGuard<PlatformWrapper>, EngineError> {
+    GLOBAL_PLATFORM.fetch(EngineError::AccessFailed)
+}
+
+/// Time in seconds given by the platform, for the durations inside of a frame
+fn application_get_absolute_time() -> Result<f64, EngineError> {
+    fetch_global_platform()?
+        .get()?
+        .get_absolute_time_in_seconds()
 }
 
 pub(crate) fn application_get_framebuffer_size() -> Result<(u32, u32), EngineError> {
     fetch_global_application()?.get_framebuffer_size()
 }
 
-/// Shutdown the application
+/// Seconds elapsed since the application started running, updated once per frame
+pub fn application_get_time() -> Result<f64, EngineError> {
+    Ok(fetch_global_application()?.clock.elapsed_time)
+}
+
+/// Stop updating the game, the rendering continues
+pub fn application_pause() -> Result<(), EngineError> {
+    let mut application = fetch_global_application()?;
+    if !application.is_paused {
+        info!("Application paused");
+    }
+    application.is_paused = true;
+    Ok(())
+}
+
+pub fn application_resume() -> Result<(), EngineError> {
+    let mut application = fetch_global_application()?;
+    if application.is_paused {
+        info!("Application resumed");
+    }
+    application.is_paused = false;
+    application.should_step_frame = false;
+    Ok(())
+}
+
+pub fn application_toggle_pause() -> Result<(), EngineError> {
+    if application_is_paused()? {
+        application_resume()
+    } else {
+        application_pause()
+    }
+}
+
+pub fn application_is_paused() -> Result<bool, EngineError> {
+    Ok(fetch_global_application()?.is_paused)
+}
+
+/// Update the game for a single frame while paused
+pub fn application_step_frame() -> Result<(), EngineError> {
+    let mut application = fetch_global_application()?;
+    if !application.is_paused {
+        warn!("Can't step a single frame when the application is not paused");
+        return Err(EngineError::InvalidValue);
+    }
+    application.should_step_frame = true;
+    Ok(())
+}
+
+fn application_get_target_frame_seconds(
+    frame_rate_limit: Option<f64>,
+) -> Result<Option<f64>, EngineError> {
+    match frame_rate_limit {
+        None => Ok(None),
+        Some(limit) if limit.is_finite() && limit > 0. => Ok(Some(1. / limit)),
+        Some(limit) => {
+            error!("Invalid frame rate limit: {}, it must be positive", limit);
+            Err(EngineError::InvalidValue)
+        }
+    }
+}
+
+/// Maximum number of frames per second, none to render as fast as the vsync allows
+pub fn application_set_frame_rate_limit(frame_rate_limit: Option<f64>) -> Result<(), EngineError> {
+    let target_frame_seconds = application_get_target_frame_seconds(frame_rate_limit)?;
+    fetch_global_application()?.target_frame_seconds = target_frame_seconds;
+    Ok(())
+}
+
+/// Grab the cursor for a first person camera, see `input_get_mouse_delta`
+pub fn application_set_cursor_mode(mode: CursorMode) -> Result<(), EngineError> {
+    let mut global_platform = fetch_global_platform()?;
+    if let Err(err) = global_platform.get_mut()?.set_cursor_mode(mode) {
+        error!("Failed to set the cursor mode to {:?}: {:?}", mode, err);
+        return Err(EngineError::UpdateFailed);
+    }
+    Ok(())
+}
+
+/// Open an additional window, e.g. for the panels of a tool or an editor
+/// `EventCode::WindowClosed` is fired when the user closes it, it stays open until destroyed
+/// It gets its own swapchain, a named camera targeting `CameraTarget::Window` draws into it
+pub fn application_create_window(
+    parameters: &WindowCreatorParameters,
+) -> Result<WindowHandle, EngineError> {
+    let mut global_platform = fetch_global_platform()?;
+    let platform = global_platform.get_mut()?;
+    let window = match platform.create_window(parameters) {
+        Ok(window) => window,
+        Err(err) => {
+            error!(
+                "Failed to create the window {:?}: {:?}",
+                parameters.title, err
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+    };
+    if let Err(err) = renderer_add_window(window, platform, (parameters.width, parameters.height)) {
+        error!(
+            "Failed to draw into the window {:?}: {:?}",
+            parameters.title, err
+        );
+        if let Err(err) = platform.destroy_window(window) {
+            error!("Failed to destroy the window {:?}: {:?}", window, err);
+        }
+        return Err(EngineError::InitializationFailed);
+    }
+    Ok(window)
+}
+
+/// Close a window opened with `application_create_window`
+pub fn application_destroy_window(window: WindowHandle) -> Result<(), EngineError> {
+    if window.is_main() {
+        error!("The main window can't be destroyed, it closes with the application");
+        return Err(EngineError::InvalidValue);
+    }
+    let mut global_platform = fetch_global_platform()?;
+    let platform = global_platform.get_mut()?;
+    // The swapchain must be destroyed before its surface's window
+    if let Err(err) = renderer_remove_window(window) {
+        error!(
+            "Failed to stop drawing into the window {:?}: {:?}",
+            window, err
+        );
+        return Err(EngineError::ShutdownFailed);
+    }
+    if let Err(err) = platform.destroy_window(window) {
+        error!("Failed to destroy the window {:?}: {:?}", window, err);
+        return Err(EngineError::ShutdownFailed);
+    }
+    Ok(())
+}
+
+/// Show the loading screen of the game until the jobs are completed
+/// The platform events are still handled, the game is neither updated nor rendered meanwhile
+pub fn application_begin_loading(jobs: &[JobHandle]) -> Result<(), EngineError> {
+    let mut application = fetch_global_application()?;
+    if application.loading_jobs.is_empty() {
+        info!("Application loading");
+        application.loading_job_count = 0;
+    }
+    application.loading_jobs.extend_from_slice(jobs);
+    application.loading_job_count += jobs.len();
+    Ok(())
+}
+
+pub fn application_is_loading() -> Result<bool, EngineError> {
+    Ok(!fetch_global_application()?.loading_jobs.is_empty())
+}
+
+/// Tell the engine the host window changed size when it owns the event loop
+/// Not needed when the engine creates the window
+pub fn application_notify_resized(width: u32, height: u32) -> Result<(), EngineError> {
+    event_fire(EventCode::Resized { width, height })
+}
+
+/// Save the window geometry for the next launch
+fn application_save_window_geometry(
+    platform: &dyn Platform,
+    path: &std::path::Path,
+) -> Result<(), EngineError> {
+    let mut geometry = match platform.get_window_geometry() {
+        Some(geometry) => geometry,
+        None => return Ok(()),
+    };
+    geometry.monitor = geometry.find_monitor(&platform.get_monitors());
+    geometry.save(path)
+}
+
+/// Shutdown the application, does nothing if it is already shut down
 pub(crate) fn application_shutdown() -> Result<(), EngineError> {
-    fetch_global_application()?.shutdown()
+    let mut global_application = GLOBAL_APPLICATION.fetch(EngineError::ShutdownFailed)?;
+    if !global_application.is_initialized {
+        warn!("The application is already shut down");
+        return Ok(());
+    }
+    let window_geometry_path = global_application.window_geometry_path.take();
+    *global_application = Application::default();
+    drop(global_application);
+
+    let platform = GLOBAL_PLATFORM
+        .fetch(EngineError::ShutdownFailed)?
+        .platform
+        .take();
+    let mut platform = match platform {
+        Some(platform) => platform,
+        None => return Ok(()),
+    };
+    let platform = platform.get_mut(EngineError::ShutdownFailed)?;
+    if let Some(path) = window_geometry_path {
+        if let Err(err) = application_save_window_geometry(platform.as_ref(), &path) {
+            warn!("Failed to save the window geometry: {:?}", err);
+        }
+    }
+    match platform.shutdown() {
+        Err(err) => {
+            error!("Failed to shut down the application: {:?}", err);
+            Err(EngineError::ShutdownFailed)
+        }
+        Ok(()) => Ok(()),
+    }
 }
 
 /// Initiate the application
-pub(crate) fn application_init(
-    parameters: ApplicationParameters,
-    game: Box<dyn Game>,
-) -> Result<(), EngineError> {
+/// The parameters are checked before the platform is initialized, nothing is left open when they are invalid
+pub(crate) fn application_init(parameters: ApplicationParameters) -> Result<(), EngineError> {
+    if !parameters.fixed_update_rate.is_finite() || parameters.fixed_update_rate <= 0. {
+        error!(
+            "Invalid fixed update rate: {}, it must be positive",
+            parameters.fixed_update_rate
+        );
+        return Err(EngineError::InvalidValue);
+    }
+    let target_frame_seconds = application_get_target_frame_seconds(parameters.frame_rate_limit)?;
+
     let platform = platform_init(
         parameters.application_name.clone(),
         parameters.initial_x_position,
@@ -157,28 +600,56 @@ pub(crate) fn application_init(
         parameters.initial_width,
         parameters.initial_height,
         parameters.flags.is_window_resizable,
+        parameters.external_window,
+        parameters.renderer_backend == RendererBackendType::Null,
     );
 
-    debug!("Platform initialized");
-
-    let global_application_wrapper =
-        fetch_global_application_wrapper(EngineError::InitializationFailed)?;
-
-    let application = match platform {
+    let (mut platform, application) = match platform {
         Err(err) => {
             error!("Failed to init the platform: {:?}", err);
             return Err(EngineError::InitializationFailed);
         }
-        Ok(platform) => Application {
-            platform: Box::new(platform),
-            game,
-            state: ApplicationState::Running,
-            clock: Clock::default(),
-            last_time: 0.,
-            width: parameters.initial_width,
-            height: parameters.initial_height,
-            is_resizable: parameters.flags.is_window_resizable,
-        },
+        Ok(mut platform) => {
+            let (mut width, mut height) = platform
+                .get_embedded_window_size()
+                .unwrap_or((parameters.initial_width, parameters.initial_height));
+            if parameters.external_window.is_none() {
+                if let Some(geometry) = parameters
+                    .window_geometry_path
+                    .as_deref()
+                    .and_then(WindowGeometry::load)
+                {
+                    let geometry = geometry.fit_to_monitors(&platform.get_monitors());
+                    match platform.set_window_geometry(&geometry) {
+                        Ok(()) => (width, height) = (geometry.width, geometry.height),
+                        Err(err) => warn!("Failed to restore the window geometry: {:?}", err),
+                    }
+                }
+            }
+            let application = Application {
+                is_initialized: true,
+                state: ApplicationState::Running,
+                clock: Clock::default(),
+                last_time: 0.,
+                width,
+                height,
+                is_resizable: parameters.flags.is_window_resizable
+                    || parameters.external_window.is_some(),
+                is_paused: false,
+                should_step_frame: false,
+                loading_jobs: Vec::new(),
+                loading_job_count: 0,
+                window_geometry_path: parameters.window_geometry_path,
+                fixed_delta_time: 1. / parameters.fixed_update_rate,
+                fixed_time_accumulator: 0.,
+                target_frame_seconds,
+                frame_timings: FrameTimings::default(),
+                pending_resize: None,
+                last_resize_time: 0.,
+            };
+            debug!("Platform initialized");
+            (platform, application)
+        }
     };
 
     // register events
@@ -187,10 +658,16 @@ pub(crate) fn application_init(
             "Failed to initialize the application events listeners: {:?}",
             err
         );
+        if let Err(err) = platform.shutdown() {
+            error!("Failed to shut down the platform: {:?}", err);
+        }
         return Err(EngineError::InitializationFailed);
     }
 
-    global_application_wrapper.application = Some(application);
+    *GLOBAL_APPLICATION.fetch(EngineError::InitializationFailed)? = application;
+    GLOBAL_PLATFORM
+        .fetch(EngineError::InitializationFailed)?
+        .platform = Some(ThreadBound::new(platform));
 
     Ok(())
 }
@@ -202,109 +679,398 @@ impl Application {
         Ok((width, height))
     }
 
-    /// Run the application
-    pub fn run(&mut self) -> Result<(), EngineError> {
-        self.clock.start(self.platform.as_ref())?;
-        self.clock.update(self.platform.as_ref())?;
-        self.last_time = self.clock.elapsed_time;
+    fn update_clock(&mut self) -> Result<(), EngineError> {
+        self.clock.update(fetch_global_platform()?.get()?)
+    }
+}
 
-        let mut running_time: f64 = 0.;
-        let mut frame_count: u32 = 0;
-        let target_frame_seconds: f64 = 1. / 60.;
+/// Resize the renderer then the game once the window stopped changing size
+fn application_apply_pending_resize(game: &mut dyn Game) -> Result<(), EngineError> {
+    let (width, height) = {
+        let mut application = fetch_global_application()?;
+        let size = match application.pending_resize {
+            Some(size) => size,
+            None => return Ok(()),
+        };
+        let now = application_get_absolute_time()?;
+        if now - application.last_resize_time < RESIZE_DEBOUNCE_SECONDS {
+            return Ok(());
+        }
+        application.pending_resize = None;
+        size
+    };
 
-        'main_loop: while self.state != ApplicationState::ShuttingDown {
-            if self.state == ApplicationState::Suspended {
-                continue 'main_loop;
-            }
-            // handle the events
-            let should_quit = match self.platform.handle_events() {
-                Ok(flag) => flag,
-                Err(err) => {
-                    error!(
-                        "The application encountered an issue while running: {:?}",
-                        err
-                    );
-                    return Err(EngineError::Unknown);
-                }
+    fetch_global_renderer(EngineError::UpdateFailed)?.resize(width, height)?;
+    // game on resize, after the renderer so the game can query its new size
+    if let Err(err) = game.on_resize(width, height) {
+        error!(
+            "Failed to call the `on_resize' function of the game: {:?}",
+            err
+        );
+        return Err(EngineError::UpdateFailed);
+    }
+    Ok(())
+}
+
+/// Update and render the game for a frame, the update is skipped while paused
+fn application_update_and_render_game(game: &mut dyn Game, delta: f64) -> Result<(), EngineError> {
+    let update_start_time = application_get_absolute_time()?;
+    // update the game, a single stepped frame lasts one fixed update
+    let update = {
+        let mut application = fetch_global_application()?;
+        if !application.is_paused || application.should_step_frame {
+            let update_delta = if application.is_paused {
+                application.fixed_delta_time
+            } else {
+                delta
+            };
+            application.should_step_frame = false;
+
+            // A stepped frame runs exactly one fixed update
+            application.fixed_time_accumulator += if application.is_paused {
+                application.fixed_delta_time
+            } else {
+                delta
             };
-            if should_quit {
-                break 'main_loop;
+            application.fixed_time_accumulator = application
+                .fixed_time_accumulator
+                .min(application.fixed_delta_time * FIXED_UPDATE_MAX_STEPS_PER_FRAME as f64);
+            let mut fixed_update_count = 0;
+            while application.fixed_time_accumulator >= application.fixed_delta_time {
+                application.fixed_time_accumulator -= application.fixed_delta_time;
+                fixed_update_count += 1;
             }
+            Some((
+                update_delta,
+                fixed_update_count,
+                application.fixed_delta_time,
+            ))
+        } else {
+            None
+        }
+    };
 
-            // update clock and get delta time.
-            self.clock.update(self.platform.as_ref())?;
-            let current_time: f64 = self.clock.elapsed_time;
-            let delta: f64 = current_time - self.last_time;
-            let frame_start_time: f64 = self.platform.as_ref().get_absolute_time_in_seconds()?;
+    if let Some((update_delta, fixed_update_count, fixed_delta_time)) = update {
+        profiler_begin_scope("game_fixed_update")?;
+        for _ in 0..fixed_update_count {
+            if let Err(err) = game.on_fixed_update(fixed_delta_time) {
+                error!("Failed to run the fixed update of the game: {:?}", err);
+                return Err(EngineError::Unknown);
+            }
+        }
+        profiler_end_scope("game_fixed_update")?;
 
-            // update the game
-            match self.game.on_update(delta) {
-                Ok(()) => (),
-                Err(err) => {
-                    error!("Failed to update the game: {:?}", err);
-                    return Err(EngineError::Unknown);
-                }
+        profiler_begin_scope("game_update")?;
+        match game.on_update(update_delta) {
+            Ok(()) => (),
+            Err(err) => {
+                error!("Failed to update the game: {:?}", err);
+                return Err(EngineError::Unknown);
             }
+        }
+        profiler_end_scope("game_update")?;
 
-            // render the game
-            match self.game.on_render(delta) {
-                Ok(()) => (),
-                Err(err) => {
-                    error!("Failed to render the game: {:?}", err);
-                    return Err(EngineError::Unknown);
-                }
+        profiler_begin_scope("ecs_systems")?;
+        if let Err(err) = ecs_run_systems(update_delta) {
+            error!("Failed to run the ecs systems: {:?}", err);
+            return Err(EngineError::Unknown);
+        }
+        profiler_end_scope("ecs_systems")?;
+
+        if let Err(err) = ecs_update_animations(update_delta) {
+            error!("Failed to update the animations: {:?}", err);
+            return Err(EngineError::Unknown);
+        }
+        if let Err(err) = ecs_update_sprite_animations(update_delta) {
+            error!("Failed to update the sprite animations: {:?}", err);
+            return Err(EngineError::Unknown);
+        }
+    }
+
+    // the cells around the camera are streamed even while paused
+    if let Err(err) = scene_streaming_update() {
+        error!("Failed to stream the scene cells: {:?}", err);
+        return Err(EngineError::Unknown);
+    }
+
+    // the sprites are drawn even while paused, below the ui of the game
+    if let Err(err) = renderer_draw_ecs_sprites() {
+        error!("Failed to draw the sprites: {:?}", err);
+        return Err(EngineError::Unknown);
+    }
+
+    // render the game
+    let render_start_time = application_get_absolute_time()?;
+    let interpolation = {
+        let mut application = fetch_global_application()?;
+        application.frame_timings.update = render_start_time - update_start_time;
+        application.fixed_time_accumulator / application.fixed_delta_time
+    };
+    profiler_begin_scope("game_render")?;
+    match game.on_render(delta, interpolation) {
+        Ok(()) => (),
+        Err(err) => {
+            error!("Failed to render the game: {:?}", err);
+            return Err(EngineError::Unknown);
+        }
+    }
+    profiler_end_scope("game_render")?;
+    let render_time = application_get_absolute_time()? - render_start_time;
+    fetch_global_application()?.frame_timings.render = render_time;
+    Ok(())
+}
+
+/// Run the application until it shuts down
+/// The application and the platform are only fetched in between the calls to the game and to the
+/// other systems, those can then use them
+pub(crate) fn application_run(game: &mut dyn Game) -> Result<(), EngineError> {
+    {
+        let mut application = fetch_global_application()?;
+        let global_platform = fetch_global_platform()?;
+        application.clock.start(global_platform.get()?)?;
+        application.clock.update(global_platform.get()?)?;
+        application.last_time = application.clock.elapsed_time;
+    }
+
+    let mut running_time: f64 = 0.;
+    let mut frame_count: u32 = 0;
+
+    'main_loop: while fetch_global_application()?.state != ApplicationState::ShuttingDown {
+        // handle the events, nothing is rendered while suspended so block until the next ones
+        let was_suspended = fetch_global_application()?.state == ApplicationState::Suspended;
+        // The listeners of the platform events are called once the platform is released
+        events_hold()?;
+        let events = {
+            let mut global_platform = fetch_global_platform()?;
+            let platform = global_platform.get_mut()?;
+            if was_suspended {
+                platform.wait_for_events()
+            } else {
+                platform.handle_events()
+            }
+        };
+        events_release_held()?;
+        let should_quit = match events {
+            Ok(flag) => flag,
+            Err(err) => {
+                error!(
+                    "The application encountered an issue while running: {:?}",
+                    err
+                );
+                return Err(EngineError::Unknown);
             }
+        };
+        if should_quit {
+            break 'main_loop;
+        }
+        if fetch_global_application()?.state == ApplicationState::Suspended {
+            // The game can still queue events, e.g. from its listeners
+            if let Err(err) = events_dispatch_queued() {
+                error!("Failed to dispatch the queued events: {:?}", err);
+                return Err(EngineError::Unknown);
+            }
+            continue 'main_loop;
+        }
+        if was_suspended {
+            // The suspension does not count in the delta time of the next frame
+            let mut application = fetch_global_application()?;
+            application.update_clock()?;
+            application.last_time = application.clock.elapsed_time;
+        }
+        if let Err(err) = application_apply_pending_resize(game) {
+            error!("Failed to resize the application: {:?}", err);
+            return Err(EngineError::Unknown);
+        }
 
-            // Create frame and render
-            let frame_data = RenderFrameData { delta_time: delta };
-            renderer_draw_frame(&frame_data)?;
+        // update clock and get delta time.
+        let (current_time, delta) = {
+            let mut application = fetch_global_application()?;
+            application.update_clock()?;
+            application.frame_timings = FrameTimings::default();
+            let current_time: f64 = application.clock.elapsed_time;
+            (current_time, current_time - application.last_time)
+        };
+        let frame_start_time: f64 = application_get_absolute_time()?;
 
-            // Figure out how long the frame took and, if below
-            let frame_end_time: f64 = self.platform.get_absolute_time_in_seconds()?;
-            let frame_elapsed_time: f64 = frame_end_time - frame_start_time;
-            running_time += frame_elapsed_time;
-            let remaining_seconds: f64 = target_frame_seconds - frame_elapsed_time;
+        // The settings changed in the config file apply from this frame
+        if let Err(err) = config_poll() {
+            error!("Failed to poll the config file: {:?}", err);
+            return Err(EngineError::Unknown);
+        }
 
-            if remaining_seconds > 0. {
-                let remaining_ms: u64 = remaining_seconds as u64 * 1000;
+        // The events queued during the last frame are received after the platform ones
+        if let Err(err) = events_dispatch_queued() {
+            error!("Failed to dispatch the queued events: {:?}", err);
+            return Err(EngineError::Unknown);
+        }
 
-                // If there is time left, give it back to the OS.
-                let limit_frames = false;
-                if remaining_ms > 0 && limit_frames {
-                    self.platform.sleep_from_milliseconds(remaining_ms - 1)?;
-                }
+        // Hand the results of the finished jobs to the game before updating it
+        match jobs_run_completions() {
+            Ok(()) => (),
+            Err(err) => {
+                error!("Failed to run the completions of the jobs: {:?}", err);
+                return Err(EngineError::Unknown);
+            }
+        }
 
-                frame_count += 1;
+        // The input events received so far are seen by this update
+        input_latency_consume_events()?;
+
+        // Forget the loading jobs whose completion has run
+        let loading_progress = {
+            let mut application = fetch_global_application()?;
+            let was_loading = !application.loading_jobs.is_empty();
+            application
+                .loading_jobs
+                .retain(|job| job_is_pending(*job).unwrap_or(false));
+            if was_loading && application.loading_jobs.is_empty() {
+                info!("Application loaded");
             }
+            (!application.loading_jobs.is_empty()).then(|| {
+                1.0 - application.loading_jobs.len() as f32
+                    / application.loading_job_count.max(1) as f32
+            })
+        };
 
-            // NOTE: Input update/state copying should always be handled
-            // after any input should be recorded; I.E. before this line.
-            // As a safety, input is the last thing to be updated before
-            // this frame ends.
-            match input_update(delta) {
+        if let Some(progress) = loading_progress {
+            let render_start_time = application_get_absolute_time()?;
+            profiler_begin_scope("game_render_loading")?;
+            match game.on_render_loading(delta, progress) {
                 Ok(()) => (),
                 Err(err) => {
-                    error!("Failed to update the inputs: {:?}", err);
+                    error!("Failed to render the game loading screen: {:?}", err);
                     return Err(EngineError::Unknown);
                 }
             }
+            profiler_end_scope("game_render_loading")?;
+            let render_time = application_get_absolute_time()? - render_start_time;
+            fetch_global_application()?.frame_timings.render = render_time;
+        } else {
+            application_update_and_render_game(game, delta)?;
+        }
 
-            // debug!("delta: {}, last_time: {}", delta, self.last_time);
-            // update last time
-            self.last_time = current_time;
+        // Timings of the last frame and the browsed frame history on top of the game
+        if renderer_get_font()?.is_some() {
+            let line_height = renderer_measure_text("")?.y;
+            let mut lines = Vec::new();
+            if profiler_is_overlay_visible()? {
+                lines.extend(profiler_get_overlay_lines()?);
+                lines.extend(input_latency_get_overlay_lines()?);
+            }
+            if frame_history_is_overlay_visible()? {
+                lines.extend(frame_history_get_overlay_lines()?);
+            }
+            for (index, line) in lines.iter().enumerate() {
+                renderer_draw_text(
+                    PROFILER_OVERLAY_MARGIN,
+                    PROFILER_OVERLAY_MARGIN + index as f32 * line_height,
+                    line,
+                    glam::Vec4::ONE,
+                )?;
+            }
+        }
+
+        // Line typed in the console at the bottom of the window
+        if let Some(line) = console_get_overlay_line()? {
+            if renderer_get_font()?.is_some() {
+                let line_height = renderer_measure_text(&line)?.y;
+                let (_, height) = application_get_framebuffer_size()?;
+                renderer_draw_text(
+                    PROFILER_OVERLAY_MARGIN,
+                    height as f32 - PROFILER_OVERLAY_MARGIN - line_height,
+                    &line,
+                    glam::Vec4::ONE,
+                )?;
+            }
+        }
+
+        // Create frame and render
+        let frame_data = RenderFrameData { delta_time: delta };
+        let present_start_time = application_get_absolute_time()?;
+        profiler_begin_scope("renderer")?;
+        renderer_draw_frame(&frame_data)?;
+        profiler_end_scope("renderer")?;
+
+        // Figure out how long the frame took
+        let frame_end_time: f64 = application_get_absolute_time()?;
+        let frame_elapsed_time: f64 = frame_end_time - frame_start_time;
+        let (frame_timings, target_frame_seconds) = {
+            let mut application = fetch_global_application()?;
+            application.frame_timings.present = frame_end_time - present_start_time;
+            application.frame_timings.total = frame_elapsed_time;
+            (application.frame_timings, application.target_frame_seconds)
+        };
+        running_time += frame_elapsed_time;
+        frame_count += 1;
+
+        // If there is time left in a limited frame, give it back to the OS.
+        if let Some(target_frame_seconds) = target_frame_seconds {
+            // The negative durations saturate to 0
+            let remaining_ms = ((target_frame_seconds - frame_elapsed_time) * 1000.) as u64;
+            if remaining_ms > 0 {
+                fetch_global_platform()?
+                    .get()?
+                    .sleep_from_milliseconds(remaining_ms)?;
+            }
         }
-        Ok(())
-    }
 
-    /// Shutdown the application
-    pub fn shutdown(&mut self) -> Result<(), EngineError> {
-        self.state = ApplicationState::ShuttingDown;
-        match self.platform.shutdown() {
+        // NOTE: Input update/state copying should always be handled
+        // after any input should be recorded; I.E. before this line.
+        // As a safety, input is the last thing to be updated before
+        // this frame ends.
+        match profiler_end_frame() {
+            Ok(()) => (),
             Err(err) => {
-                error!("Failed to shut down the application: {:?}", err);
-                Err(EngineError::ShutdownFailed)
+                error!("Failed to end the profiler frame: {:?}", err);
+                return Err(EngineError::Unknown);
             }
-            Ok(()) => Ok(()),
         }
+        match profiler_record_frame_statistics(
+            frame_timings,
+            delta,
+            renderer_get_draw_call_count()?,
+            renderer_get_statistics()?.gpu_pass_timings,
+        ) {
+            Ok(()) => (),
+            Err(err) => {
+                error!("Failed to record the frame statistics: {:?}", err);
+                return Err(EngineError::Unknown);
+            }
+        }
+        // Only query the memory when it is needed, it is read from the os
+        let memory = if telemetry_is_recording()? {
+            fetch_global_platform()?
+                .get()?
+                .get_memory_usage_in_bytes()
+                .ok()
+        } else {
+            None
+        };
+        match telemetry_end_frame(delta, renderer_get_draw_call_count()?, memory) {
+            Ok(()) => (),
+            Err(err) => {
+                error!("Failed to record the frame telemetry: {:?}", err);
+                return Err(EngineError::Unknown);
+            }
+        }
+        match frame_history_end_frame(delta) {
+            Ok(()) => (),
+            Err(err) => {
+                error!("Failed to record the frame history: {:?}", err);
+                return Err(EngineError::Unknown);
+            }
+        }
+        match input_update(delta) {
+            Ok(()) => (),
+            Err(err) => {
+                error!("Failed to update the inputs: {:?}", err);
+                return Err(EngineError::Unknown);
+            }
+        }
+
+        // debug!("delta: {}, last_time: {}", delta, self.last_time);
+        // update last time
+        fetch_global_application()?.last_time = current_time;
     }
+    Ok(())
 }
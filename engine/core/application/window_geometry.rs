@@ -0,0 +1,138 @@
+use std::{fs, path::Path};
+
+use crate::{
+    core::{debug::errors::EngineError, systems::config::ConfigSystem},
+    error, warn,
+};
+
+/// Smallest restored window, a saved size can be degenerate after a crash of the window manager
+const WINDOW_GEOMETRY_MIN_SIZE: u32 = 160;
+
+/// Desktop area of a monitor, in the coordinates of the window positions
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct MonitorArea {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl MonitorArea {
+    fn contains(&self, x: i32, y: i32) -> bool {
+        x >= self.x
+            && y >= self.y
+            && x < self.x + self.width as i32
+            && y < self.y + self.height as i32
+    }
+}
+
+/// Position and size of the window when the application was closed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct WindowGeometry {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    /// Index of the monitor the window was on, in the order given by the platform
+    pub monitor: usize,
+}
+
+impl WindowGeometry {
+    /// Read a file written by `save`, None if there is no valid geometry to restore
+    pub fn load(path: &Path) -> Option<Self> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            // Nothing was saved yet on the first launch
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(err) => {
+                warn!("Failed to read the window geometry {:?}: {:?}", path, err);
+                return None;
+            }
+        };
+        let values = ConfigSystem::parse(&content);
+        let get = |name: &str| values.get(name).and_then(|value| value.parse::<i64>().ok());
+        match (
+            get("x"),
+            get("y"),
+            get("width"),
+            get("height"),
+            get("monitor"),
+        ) {
+            (Some(x), Some(y), Some(width), Some(height), Some(monitor))
+                if width > 0 && height > 0 && monitor >= 0 =>
+            {
+                Some(Self {
+                    x: x as i32,
+                    y: y as i32,
+                    width: width as u32,
+                    height: height as u32,
+                    monitor: monitor as usize,
+                })
+            }
+            _ => {
+                warn!("Ignoring the invalid window geometry {:?}", path);
+                None
+            }
+        }
+    }
+
+    pub fn save(&self, path: &Path) -> Result<(), EngineError> {
+        let content = format!(
+            "# Window geometry of the last launch\nx = {}\ny = {}\nwidth = {}\nheight = {}\nmonitor = {}\n",
+            self.x, self.y, self.width, self.height, self.monitor
+        );
+        if let Err(err) = fs::write(path, content) {
+            error!("Failed to save the window geometry {:?}: {:?}", path, err);
+            return Err(EngineError::IO);
+        }
+        Ok(())
+    }
+
+    /// Index of the monitor under the center of the window, 0 when it is off screen
+    pub fn find_monitor(&self, monitors: &[MonitorArea]) -> usize {
+        let center_x = self.x + self.width as i32 / 2;
+        let center_y = self.y + self.height as i32 / 2;
+        monitors
+            .iter()
+            .position(|monitor| monitor.contains(center_x, center_y))
+            .unwrap_or(0)
+    }
+
+    /// Move the window back on screen when the monitor layout changed since it was saved
+    /// The saved monitor is kept if it still exists, the first one is used otherwise
+    /// The geometry is kept as is when the platform can't list the monitors
+    pub fn fit_to_monitors(self, monitors: &[MonitorArea]) -> Self {
+        let (monitor_index, monitor) = match monitors.get(self.monitor) {
+            Some(monitor) => (self.monitor, monitor),
+            None => match monitors.first() {
+                Some(monitor) => {
+                    warn!(
+                        "The monitor {} of the saved window geometry is gone, using the first one",
+                        self.monitor
+                    );
+                    (0, monitor)
+                }
+                None => return self,
+            },
+        };
+        let width = self
+            .width
+            .clamp(WINDOW_GEOMETRY_MIN_SIZE.min(monitor.width), monitor.width);
+        let height = self
+            .height
+            .clamp(WINDOW_GEOMETRY_MIN_SIZE.min(monitor.height), monitor.height);
+        let x = self
+            .x
+            .clamp(monitor.x, monitor.x + (monitor.width - width) as i32);
+        let y = self
+            .y
+            .clamp(monitor.y, monitor.y + (monitor.height - height) as i32);
+        Self {
+            x,
+            y,
+            width,
+            height,
+            monitor: monitor_index,
+        }
+    }
+}
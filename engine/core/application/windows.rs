@@ -0,0 +1,57 @@
+/// Window created by the platform
+/// The main window is always `WindowHandle::MAIN`, the additional ones are created by the application
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct WindowHandle(pub(crate) u32);
+
+impl WindowHandle {
+    pub const MAIN: Self = Self(0);
+
+    pub fn get_id(&self) -> u32 {
+        self.0
+    }
+
+    pub fn is_main(&self) -> bool {
+        *self == Self::MAIN
+    }
+}
+
+/// Additional window for the tools and the editors, see `application_create_window`
+#[derive(Clone, Debug, PartialEq)]
+pub struct WindowCreatorParameters {
+    pub title: String,
+    pub x: i16,
+    pub y: i16,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for WindowCreatorParameters {
+    fn default() -> Self {
+        Self {
+            title: String::from("Window"),
+            x: 0,
+            y: 0,
+            width: 800,
+            height: 600,
+        }
+    }
+}
+
+impl WindowCreatorParameters {
+    pub fn title(mut self, title: &str) -> Self {
+        self.title = title.to_string();
+        self
+    }
+
+    pub fn position(mut self, x: i16, y: i16) -> Self {
+        self.x = x;
+        self.y = y;
+        self
+    }
+
+    pub fn size(mut self, width: u32, height: u32) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+}
@@ -13,4 +13,5 @@ pub enum EngineError {
     Synchronisation,
     UpdateFailed,
     IO,
+    DeviceLost,
 }
@@ -0,0 +1,289 @@
+use std::collections::VecDeque;
+
+use crate::{
+    core::{
+        debug::errors::EngineError,
+        globals::{EngineGlobal, EngineGlobalGuard},
+        systems::{
+            events::EventCode,
+            input::{input_get_snapshot, keyboard::KeyboardState, mouse::MouseState},
+        },
+    },
+    debug_no_details, error,
+    renderer::renderer_frontend::fetch_global_renderer,
+};
+
+/// Number of frames kept by default
+const FRAME_HISTORY_DEFAULT_CAPACITY: usize = 300;
+
+/// What happened during one frame
+#[derive(Clone)]
+pub(crate) struct FrameRecord {
+    pub frame_number: u64,
+    pub delta_time: f64,
+    pub events: Vec<EventCode>,
+    pub keyboard: Option<KeyboardState>,
+    pub mouse: Option<MouseState>,
+    pub camera_view: Option<glam::Mat4>,
+}
+
+impl FrameRecord {
+    fn new(frame_number: u64) -> Self {
+        Self {
+            frame_number,
+            delta_time: 0.0,
+            events: Vec::new(),
+            keyboard: None,
+            mouse: None,
+            camera_view: None,
+        }
+    }
+
+    /// Keys whose state changed since the previous record
+    fn get_changed_keys(&self, previous: &FrameRecord) -> Vec<usize> {
+        match (&self.keyboard, &previous.keyboard) {
+            (Some(current), Some(previous)) => (0..current.keys.len())
+                .filter(|key| current.keys[*key] != previous.keys[*key])
+                .collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+/// Ring buffer of the last frames, to see what happened right before a bug
+pub(crate) struct FrameHistory {
+    pub is_enabled: bool,
+    pub capacity: usize,
+    pub frames: VecDeque<FrameRecord>,
+    pub current: FrameRecord,
+    pub is_overlay_visible: bool,
+    /// Frame shown by the overlay, in frames ago from the last completed one
+    pub selected_frame: usize,
+}
+
+impl Default for FrameHistory {
+    fn default() -> Self {
+        Self {
+            is_enabled: false,
+            capacity: FRAME_HISTORY_DEFAULT_CAPACITY,
+            frames: VecDeque::new(),
+            current: FrameRecord::new(0),
+            is_overlay_visible: false,
+            selected_frame: 0,
+        }
+    }
+}
+
+pub(crate) static GLOBAL_FRAME_HISTORY: EngineGlobal<FrameHistory> =
+    EngineGlobal::new("frame history");
+
+fn fetch_global_frame_history(
+    error: EngineError,
+) -> Result<EngineGlobalGuard<FrameHistory>, EngineError> {
+    GLOBAL_FRAME_HISTORY.fetch(error)
+}
+
+/// Initiate the frame history
+pub(crate) fn frame_history_init() -> Result<(), EngineError> {
+    let mut global_history = fetch_global_frame_history(EngineError::InitializationFailed)?;
+    // Only record in debug builds by default, the history costs a copy of the inputs per frame
+    global_history.is_enabled = cfg!(debug_assertions);
+    Ok(())
+}
+
+/// Shutdown the frame history
+pub(crate) fn frame_history_shutdown() -> Result<(), EngineError> {
+    GLOBAL_FRAME_HISTORY.reset(EngineError::ShutdownFailed)?;
+    Ok(())
+}
+
+/// Called by the event system for every fired event
+pub(crate) fn frame_history_record_event(code: EventCode) -> Result<(), EngineError> {
+    let mut global_history = fetch_global_frame_history(EngineError::UpdateFailed)?;
+    if global_history.is_enabled {
+        global_history.current.events.push(code);
+    }
+    Ok(())
+}
+
+/// Snapshot the inputs and the camera and start recording a new frame
+/// Must be called before the inputs are updated
+pub(crate) fn frame_history_end_frame(delta_time: f64) -> Result<(), EngineError> {
+    let mut global_history = fetch_global_frame_history(EngineError::UpdateFailed)?;
+    if !global_history.is_enabled {
+        return Ok(());
+    }
+    let (keyboard, mouse) = input_get_snapshot()?;
+    let current = &mut global_history.current;
+    current.delta_time = delta_time;
+    current.keyboard = Some(keyboard);
+    current.mouse = Some(mouse);
+    current.camera_view = fetch_global_renderer(EngineError::AccessFailed)?
+        .main_camera
+        .map(|camera| camera.view);
+
+    let next = FrameRecord::new(current.frame_number + 1);
+    let finished = std::mem::replace(current, next);
+    while global_history.frames.len() >= global_history.capacity.max(1) {
+        global_history.frames.pop_front();
+    }
+    global_history.frames.push_back(finished);
+    Ok(())
+}
+
+pub fn frame_history_set_enabled(is_enabled: bool) -> Result<(), EngineError> {
+    let mut global_history = fetch_global_frame_history(EngineError::UpdateFailed)?;
+    global_history.is_enabled = is_enabled;
+    if !is_enabled {
+        global_history.frames.clear();
+    }
+    Ok(())
+}
+
+pub fn frame_history_set_capacity(capacity: usize) -> Result<(), EngineError> {
+    let mut global_history = fetch_global_frame_history(EngineError::UpdateFailed)?;
+    global_history.capacity = capacity;
+    while global_history.frames.len() > capacity {
+        global_history.frames.pop_front();
+    }
+    Ok(())
+}
+
+/// Recorded frame, 0 being the last completed one
+pub(crate) fn frame_history_get_frame(frames_ago: usize) -> Result<FrameRecord, EngineError> {
+    let global_history = fetch_global_frame_history(EngineError::AccessFailed)?;
+    let frame_count = global_history.frames.len();
+    if frames_ago >= frame_count {
+        error!(
+            "Can't access the frame {} frames ago, only {} are recorded",
+            frames_ago, frame_count
+        );
+        return Err(EngineError::InvalidValue);
+    }
+    Ok(global_history.frames[frame_count - 1 - frames_ago].clone())
+}
+
+pub fn frame_history_set_overlay_visible(is_visible: bool) -> Result<(), EngineError> {
+    let mut global_history = fetch_global_frame_history(EngineError::UpdateFailed)?;
+    global_history.is_overlay_visible = is_visible;
+    Ok(())
+}
+
+pub fn frame_history_is_overlay_visible() -> Result<bool, EngineError> {
+    let global_history = fetch_global_frame_history(EngineError::AccessFailed)?;
+    Ok(global_history.is_overlay_visible)
+}
+
+/// Frame shown by the overlay, 0 being the last completed one
+/// The history keeps recording, pause the application to browse a fixed set of frames
+pub fn frame_history_select_frame(frames_ago: usize) -> Result<(), EngineError> {
+    let mut global_history = fetch_global_frame_history(EngineError::UpdateFailed)?;
+    global_history.selected_frame = frames_ago.min(global_history.frames.len().saturating_sub(1));
+    Ok(())
+}
+
+/// Move the frame shown by the overlay, positive offsets go to older frames
+pub fn frame_history_browse(offset: isize) -> Result<(), EngineError> {
+    let selected_frame = fetch_global_frame_history(EngineError::UpdateFailed)?.selected_frame;
+    frame_history_select_frame(selected_frame.saturating_add_signed(offset))
+}
+
+/// Lines of the frame selected in the overlay, with what changed since the frame before it
+pub(crate) fn frame_history_get_overlay_lines() -> Result<Vec<String>, EngineError> {
+    let global_history = fetch_global_frame_history(EngineError::AccessFailed)?;
+    let frame_count = global_history.frames.len();
+    if !global_history.is_enabled || frame_count == 0 {
+        return Ok(vec![String::from("Frame history: nothing recorded")]);
+    }
+    let frames_ago = global_history.selected_frame.min(frame_count - 1);
+    let index = frame_count - 1 - frames_ago;
+    let frame = &global_history.frames[index];
+    let mut lines = vec![format!(
+        "Frame history: frame {} ({:.2}ms), {}/{} frames ago",
+        frame.frame_number,
+        frame.delta_time * 1000.0,
+        frames_ago,
+        frame_count - 1
+    )];
+    for event in &frame.events {
+        lines.push(format!("  event: {:?}", event));
+    }
+    if let Some(previous) = index
+        .checked_sub(1)
+        .map(|index| &global_history.frames[index])
+    {
+        let changed_keys = frame.get_changed_keys(previous);
+        if !changed_keys.is_empty() {
+            lines.push(format!("  keys changed: {:?}", changed_keys));
+        }
+    }
+    if let Some(mouse) = &frame.mouse {
+        lines.push(format!("  mouse: ({}, {})", mouse.x, mouse.y));
+    }
+    if let Some(view) = frame.camera_view {
+        lines.push(format!(
+            "  camera position: {:.2}",
+            view.inverse().w_axis.truncate()
+        ));
+    }
+    Ok(lines)
+}
+
+/// `frame_history <show|hide|dump|enable|disable|select <frames ago>>' command of the console
+pub(crate) fn frame_history_console_command(arguments: &[&str]) -> Result<(), EngineError> {
+    match arguments {
+        ["show"] => frame_history_set_overlay_visible(true),
+        ["hide"] => frame_history_set_overlay_visible(false),
+        ["dump"] => frame_history_dump(),
+        ["enable"] => frame_history_set_enabled(true),
+        ["disable"] => frame_history_set_enabled(false),
+        ["select", frames_ago] => match frames_ago.parse::<usize>() {
+            Ok(frames_ago) => frame_history_select_frame(frames_ago),
+            Err(err) => {
+                error!(
+                    "Invalid frame history selection `{}': {:?}",
+                    frames_ago, err
+                );
+                Err(EngineError::InvalidValue)
+            }
+        },
+        _ => {
+            error!("Unknown frame history command `{}'", arguments.join(" "));
+            Err(EngineError::InvalidValue)
+        }
+    }
+}
+
+/// Log the recorded frames, oldest first, only what changed between two frames
+pub fn frame_history_dump() -> Result<(), EngineError> {
+    let global_history = fetch_global_frame_history(EngineError::AccessFailed)?;
+    let mut previous: Option<&FrameRecord> = None;
+    for frame in &global_history.frames {
+        debug_no_details!(
+            "Frame {} ({:.2}ms)",
+            frame.frame_number,
+            frame.delta_time * 1000.0
+        );
+        for event in &frame.events {
+            debug_no_details!("\tevent: {:?}", event);
+        }
+        if let Some(previous) = previous {
+            let changed_keys = frame.get_changed_keys(previous);
+            if !changed_keys.is_empty() {
+                debug_no_details!("\tkeys changed: {:?}", changed_keys);
+            }
+            if let (Some(mouse), Some(previous_mouse)) = (&frame.mouse, &previous.mouse) {
+                if (mouse.x, mouse.y) != (previous_mouse.x, previous_mouse.y) {
+                    debug_no_details!("\tmouse: ({}, {})", mouse.x, mouse.y);
+                }
+            }
+            if frame.camera_view != previous.camera_view {
+                if let Some(view) = frame.camera_view {
+                    debug_no_details!("\tcamera view: {:?}", view);
+                }
+            }
+        }
+        previous = Some(frame);
+    }
+    Ok(())
+}
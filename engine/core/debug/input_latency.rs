@@ -0,0 +1,225 @@
+use std::{collections::VecDeque, time::Instant};
+
+use crate::{
+    core::{
+        debug::errors::EngineError,
+        globals::{EngineGlobal, EngineGlobalGuard},
+    },
+    debug_no_details,
+};
+
+/// Number of frames the latency statistics are computed over
+const INPUT_LATENCY_WINDOW_FRAMES: usize = 120;
+
+/// Input event read from the platform but not consumed by a frame yet
+struct InputLatencyPendingEvent {
+    received: Instant,
+    /// In seconds, from the receipt to the input state being updated
+    visibility_latency: f64,
+    /// In seconds, see `InputLatencyStats::average_queue_latency`
+    queue_latency: Option<f64>,
+}
+
+/// Latencies of the input events consumed during one frame
+#[derive(Clone, Default)]
+struct InputLatencyFrame {
+    event_count: u32,
+    queue_latency_sum: f64,
+    queue_latency_count: u32,
+    queue_latency_max: f64,
+    visibility_latency_sum: f64,
+    visibility_latency_max: f64,
+    consumption_latency_sum: f64,
+    consumption_latency_max: f64,
+}
+
+/// Input latencies over the last `INPUT_LATENCY_WINDOW_FRAMES` frames, in seconds
+#[derive(Clone, Debug, Default)]
+pub struct InputLatencyStats {
+    pub event_count: u32,
+    /// From the platform timestamp to the receipt by the engine, minus the fastest delivery seen
+    /// Mostly the time the event waited for the engine to poll the platform
+    /// None if the platform doesn't timestamp its events
+    pub average_queue_latency: Option<f64>,
+    pub max_queue_latency: Option<f64>,
+    /// From the receipt to the input state being updated
+    pub average_visibility_latency: f64,
+    pub max_visibility_latency: f64,
+    /// From the receipt to the start of the game update of the frame consuming the event
+    pub average_consumption_latency: f64,
+    pub max_consumption_latency: f64,
+}
+
+/// Measures how long the input events take to reach the game
+pub(crate) struct InputLatency {
+    /// Reference for converting the receipt times to milliseconds
+    origin: Instant,
+    /// Smallest difference between the receipt and the platform timestamp, in milliseconds
+    /// The platform clock has another origin so only the delays above it are meaningful
+    min_timestamp_offset: Option<i64>,
+    pending_events: Vec<InputLatencyPendingEvent>,
+    frames: VecDeque<InputLatencyFrame>,
+}
+
+impl Default for InputLatency {
+    fn default() -> Self {
+        Self {
+            origin: Instant::now(),
+            min_timestamp_offset: None,
+            pending_events: Vec::new(),
+            frames: VecDeque::new(),
+        }
+    }
+}
+
+impl InputLatency {
+    fn get_queue_latency(&mut self, received: Instant, platform_timestamp_ms: u32) -> f64 {
+        let received_ms = received.duration_since(self.origin).as_millis() as i64;
+        let offset = received_ms - platform_timestamp_ms as i64;
+        // A smaller offset is a faster delivery, or the platform clock wrapping around
+        let min_offset = match self.min_timestamp_offset {
+            Some(min_offset) if min_offset <= offset => min_offset,
+            _ => {
+                self.min_timestamp_offset = Some(offset);
+                offset
+            }
+        };
+        (offset - min_offset) as f64 / 1000.0
+    }
+
+    fn consume_pending_events(&mut self, now: Instant) {
+        let mut frame = InputLatencyFrame::default();
+        for event in self.pending_events.drain(..) {
+            let consumption_latency = now.duration_since(event.received).as_secs_f64();
+            frame.event_count += 1;
+            frame.visibility_latency_sum += event.visibility_latency;
+            frame.visibility_latency_max =
+                frame.visibility_latency_max.max(event.visibility_latency);
+            frame.consumption_latency_sum += consumption_latency;
+            frame.consumption_latency_max = frame.consumption_latency_max.max(consumption_latency);
+            if let Some(queue_latency) = event.queue_latency {
+                frame.queue_latency_count += 1;
+                frame.queue_latency_sum += queue_latency;
+                frame.queue_latency_max = frame.queue_latency_max.max(queue_latency);
+            }
+        }
+        if self.frames.len() >= INPUT_LATENCY_WINDOW_FRAMES {
+            self.frames.pop_front();
+        }
+        self.frames.push_back(frame);
+    }
+
+    fn get_stats(&self) -> InputLatencyStats {
+        let mut total = InputLatencyFrame::default();
+        for frame in &self.frames {
+            total.event_count += frame.event_count;
+            total.queue_latency_count += frame.queue_latency_count;
+            total.queue_latency_sum += frame.queue_latency_sum;
+            total.queue_latency_max = total.queue_latency_max.max(frame.queue_latency_max);
+            total.visibility_latency_sum += frame.visibility_latency_sum;
+            total.visibility_latency_max = total
+                .visibility_latency_max
+                .max(frame.visibility_latency_max);
+            total.consumption_latency_sum += frame.consumption_latency_sum;
+            total.consumption_latency_max = total
+                .consumption_latency_max
+                .max(frame.consumption_latency_max);
+        }
+        let event_count = total.event_count.max(1) as f64;
+        let has_queue_latency = total.queue_latency_count > 0;
+        InputLatencyStats {
+            event_count: total.event_count,
+            average_queue_latency: has_queue_latency
+                .then(|| total.queue_latency_sum / total.queue_latency_count as f64),
+            max_queue_latency: has_queue_latency.then_some(total.queue_latency_max),
+            average_visibility_latency: total.visibility_latency_sum / event_count,
+            max_visibility_latency: total.visibility_latency_max,
+            average_consumption_latency: total.consumption_latency_sum / event_count,
+            max_consumption_latency: total.consumption_latency_max,
+        }
+    }
+}
+
+pub(crate) static GLOBAL_INPUT_LATENCY: EngineGlobal<InputLatency> =
+    EngineGlobal::new("input latency");
+
+fn fetch_global_input_latency(
+    error: EngineError,
+) -> Result<EngineGlobalGuard<InputLatency>, EngineError> {
+    GLOBAL_INPUT_LATENCY.fetch(error)
+}
+
+/// Initiate the input latency measurements
+pub(crate) fn input_latency_init() -> Result<(), EngineError> {
+    let mut global_input_latency = fetch_global_input_latency(EngineError::InitializationFailed)?;
+    *global_input_latency = InputLatency::default();
+    Ok(())
+}
+
+/// Shutdown the input latency measurements
+pub(crate) fn input_latency_shutdown() -> Result<(), EngineError> {
+    GLOBAL_INPUT_LATENCY.reset(EngineError::ShutdownFailed)?;
+    Ok(())
+}
+
+/// Called by the platform once the input state has been updated with an event
+/// The timestamp is the one of the platform in milliseconds, if it has one
+pub(crate) fn input_latency_record_event(
+    received: Instant,
+    platform_timestamp_ms: Option<u32>,
+) -> Result<(), EngineError> {
+    let mut global_input_latency = fetch_global_input_latency(EngineError::UpdateFailed)?;
+    let queue_latency = platform_timestamp_ms.map(|platform_timestamp_ms| {
+        global_input_latency.get_queue_latency(received, platform_timestamp_ms)
+    });
+    global_input_latency
+        .pending_events
+        .push(InputLatencyPendingEvent {
+            received,
+            visibility_latency: received.elapsed().as_secs_f64(),
+            queue_latency,
+        });
+    Ok(())
+}
+
+/// Called right before the game update, the events received so far are consumed by this frame
+pub(crate) fn input_latency_consume_events() -> Result<(), EngineError> {
+    let mut global_input_latency = fetch_global_input_latency(EngineError::UpdateFailed)?;
+    global_input_latency.consume_pending_events(Instant::now());
+    Ok(())
+}
+
+/// Input latencies over the last frames
+pub fn input_latency_get_stats() -> Result<InputLatencyStats, EngineError> {
+    let global_input_latency = fetch_global_input_latency(EngineError::AccessFailed)?;
+    Ok(global_input_latency.get_stats())
+}
+
+/// Lines of the latencies, as shown by the overlay
+pub fn input_latency_get_overlay_lines() -> Result<Vec<String>, EngineError> {
+    let stats = input_latency_get_stats()?;
+    let mut lines = vec![format!(
+        "input x{}: state {:.3}ms (max {:.3}ms), frame {:.3}ms (max {:.3}ms)",
+        stats.event_count,
+        stats.average_visibility_latency * 1000.0,
+        stats.max_visibility_latency * 1000.0,
+        stats.average_consumption_latency * 1000.0,
+        stats.max_consumption_latency * 1000.0
+    )];
+    if let (Some(average), Some(max)) = (stats.average_queue_latency, stats.max_queue_latency) {
+        lines.push(format!(
+            "  platform queue {:.3}ms (max {:.3}ms)",
+            average * 1000.0,
+            max * 1000.0
+        ));
+    }
+    Ok(lines)
+}
+
+/// Log the latencies over the last frames
+pub fn input_latency_dump() -> Result<(), EngineError> {
+    for line in input_latency_get_overlay_lines()? {
+        debug_no_details!("{}", line);
+    }
+    Ok(())
+}
@@ -1,2 +1,6 @@
-pub mod clock;
-pub mod errors;
+pub(crate) mod clock;
+pub(crate) mod errors;
+pub(crate) mod frame_history;
+pub(crate) mod input_latency;
+pub(crate) mod profiler;
+pub(crate) mod telemetry;
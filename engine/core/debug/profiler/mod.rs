@@ -0,0 +1,355 @@
+use std::{collections::HashMap, time::Instant};
+
+use statistics::StatisticsCollector;
+
+use crate::{
+    core::{
+        debug::errors::EngineError,
+        globals::{EngineGlobal, EngineGlobalGuard},
+    },
+    debug_no_details, error, warn,
+};
+
+pub(crate) mod statistics;
+
+/// Number of consecutive frames over budget before warning by default
+const PROFILER_DEFAULT_BUDGET_WARNING_FRAMES: u32 = 10;
+
+/// Time spent in a scope during the last frame
+#[derive(Clone, Debug)]
+pub struct ProfilerScopeView {
+    pub name: &'static str,
+    /// 0 for the scopes opened outside of any other scope
+    pub depth: usize,
+    /// Number of times the scope was opened during the frame
+    pub call_count: u32,
+    /// In seconds, including the children scopes
+    pub inclusive_time: f64,
+    /// In seconds, without the children scopes
+    pub exclusive_time: f64,
+    pub is_over_budget: bool,
+}
+
+struct ProfilerScope {
+    name: &'static str,
+    parent: Option<usize>,
+    children: Vec<usize>,
+    call_count: u32,
+    inclusive_time: f64,
+    // Set while the scope is open
+    start: Option<Instant>,
+}
+
+/// Maximum time a system can take per frame
+struct ProfilerBudget {
+    budget: f64,
+    consecutive_frames_over: u32,
+}
+
+/// Hierarchical cpu timings of the frame
+pub(crate) struct Profiler {
+    pub is_enabled: bool,
+    /// Shows the hierarchical view on screen, once a font is loaded
+    pub is_overlay_visible: bool,
+    /// Warn when a system exceeds its budget for more than this many frames in a row
+    pub budget_warning_frames: u32,
+    scopes: Vec<ProfilerScope>,
+    open_scopes: Vec<usize>,
+    budgets: HashMap<&'static str, ProfilerBudget>,
+    last_frame: Vec<ProfilerScopeView>,
+    statistics: StatisticsCollector,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self {
+            is_enabled: false,
+            is_overlay_visible: false,
+            budget_warning_frames: PROFILER_DEFAULT_BUDGET_WARNING_FRAMES,
+            scopes: Vec::new(),
+            open_scopes: Vec::new(),
+            budgets: HashMap::new(),
+            last_frame: Vec::new(),
+            statistics: StatisticsCollector::default(),
+        }
+    }
+}
+
+impl Profiler {
+    fn begin_scope(&mut self, name: &'static str) {
+        let parent = self.open_scopes.last().copied();
+        let siblings = match parent {
+            Some(parent) => self.scopes[parent].children.clone(),
+            None => self.get_roots(),
+        };
+        // A scope opened several times in the same parent is accumulated
+        let existing = siblings
+            .iter()
+            .copied()
+            .find(|sibling| self.scopes[*sibling].name == name);
+        let index = match existing {
+            Some(index) => index,
+            None => {
+                self.scopes.push(ProfilerScope {
+                    name,
+                    parent,
+                    children: Vec::new(),
+                    call_count: 0,
+                    inclusive_time: 0.0,
+                    start: None,
+                });
+                let index = self.scopes.len() - 1;
+                if let Some(parent) = parent {
+                    self.scopes[parent].children.push(index);
+                }
+                index
+            }
+        };
+        let scope = &mut self.scopes[index];
+        scope.call_count += 1;
+        scope.start = Some(Instant::now());
+        self.open_scopes.push(index);
+    }
+
+    fn end_scope(&mut self, name: &'static str) -> Result<(), EngineError> {
+        let index = match self.open_scopes.pop() {
+            Some(index) => index,
+            None => {
+                error!("Can't end the profiler scope `{}', no scope is open", name);
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        let scope = &mut self.scopes[index];
+        if scope.name != name {
+            error!(
+                "Can't end the profiler scope `{}', the scope `{}' is still open",
+                name, scope.name
+            );
+            return Err(EngineError::InvalidValue);
+        }
+        if let Some(start) = scope.start.take() {
+            scope.inclusive_time += start.elapsed().as_secs_f64();
+        }
+        Ok(())
+    }
+
+    fn get_roots(&self) -> Vec<usize> {
+        (0..self.scopes.len())
+            .filter(|index| self.scopes[*index].parent.is_none())
+            .collect()
+    }
+
+    fn get_exclusive_time(&self, index: usize) -> f64 {
+        let scope = &self.scopes[index];
+        let children_time: f64 = scope
+            .children
+            .iter()
+            .map(|child| self.scopes[*child].inclusive_time)
+            .sum();
+        (scope.inclusive_time - children_time).max(0.0)
+    }
+
+    /// Flatten the scopes depth first, siblings sorted by decreasing exclusive time
+    fn build_view(&self) -> Vec<ProfilerScopeView> {
+        let sort = |scopes: &mut Vec<usize>| {
+            scopes.sort_by(|a, b| {
+                self.get_exclusive_time(*a)
+                    .total_cmp(&self.get_exclusive_time(*b))
+            })
+        };
+        let mut view = Vec::with_capacity(self.scopes.len());
+        let mut roots = self.get_roots();
+        sort(&mut roots);
+        let mut to_visit: Vec<(usize, usize)> = roots.into_iter().map(|root| (root, 0)).collect();
+        while let Some((index, depth)) = to_visit.pop() {
+            let scope = &self.scopes[index];
+            view.push(ProfilerScopeView {
+                name: scope.name,
+                depth,
+                call_count: scope.call_count,
+                inclusive_time: scope.inclusive_time,
+                exclusive_time: self.get_exclusive_time(index),
+                is_over_budget: self
+                    .budgets
+                    .get(scope.name)
+                    .is_some_and(|budget| scope.inclusive_time > budget.budget),
+            });
+            let mut children = scope.children.clone();
+            sort(&mut children);
+            to_visit.extend(children.into_iter().map(|child| (child, depth + 1)));
+        }
+        view
+    }
+
+    fn check_budgets(&mut self) {
+        let mut frame_times: HashMap<&'static str, f64> = HashMap::new();
+        for scope in &self.scopes {
+            *frame_times.entry(scope.name).or_default() += scope.inclusive_time;
+        }
+        for (name, budget) in self.budgets.iter_mut() {
+            let frame_time = frame_times.get(name).copied().unwrap_or_default();
+            if frame_time <= budget.budget {
+                budget.consecutive_frames_over = 0;
+                continue;
+            }
+            budget.consecutive_frames_over += 1;
+            // Only warn once per streak
+            if budget.consecutive_frames_over == self.budget_warning_frames + 1 {
+                warn!(
+                    "The system `{}' exceeded its budget of {:.2}ms for more than {} frames ({:.2}ms this frame)",
+                    name,
+                    budget.budget * 1000.0,
+                    self.budget_warning_frames,
+                    frame_time * 1000.0
+                );
+            }
+        }
+    }
+
+    fn end_frame(&mut self) {
+        if !self.open_scopes.is_empty() {
+            let names: Vec<&str> = self
+                .open_scopes
+                .iter()
+                .map(|index| self.scopes[*index].name)
+                .collect();
+            warn!("The profiler scopes {:?} were not closed this frame", names);
+            self.open_scopes.clear();
+        }
+        self.check_budgets();
+        self.last_frame = self.build_view();
+        self.scopes.clear();
+    }
+}
+
+pub(crate) static GLOBAL_PROFILER: EngineGlobal<Profiler> = EngineGlobal::new("profiler");
+
+fn fetch_global_profiler(error: EngineError) -> Result<EngineGlobalGuard<Profiler>, EngineError> {
+    GLOBAL_PROFILER.fetch(error)
+}
+
+/// Initiate the profiler
+pub(crate) fn profiler_init() -> Result<(), EngineError> {
+    let mut global_profiler = fetch_global_profiler(EngineError::InitializationFailed)?;
+    global_profiler.is_enabled = cfg!(debug_assertions);
+    Ok(())
+}
+
+/// Shutdown the profiler
+pub(crate) fn profiler_shutdown() -> Result<(), EngineError> {
+    GLOBAL_PROFILER.reset(EngineError::ShutdownFailed)?;
+    Ok(())
+}
+
+/// Start timing a scope, nested in the last scope still open
+pub fn profiler_begin_scope(name: &'static str) -> Result<(), EngineError> {
+    let mut global_profiler = fetch_global_profiler(EngineError::UpdateFailed)?;
+    if global_profiler.is_enabled {
+        global_profiler.begin_scope(name);
+    }
+    Ok(())
+}
+
+/// Stop timing the last scope opened, which must have the given name
+pub fn profiler_end_scope(name: &'static str) -> Result<(), EngineError> {
+    let mut global_profiler = fetch_global_profiler(EngineError::UpdateFailed)?;
+    if global_profiler.is_enabled {
+        global_profiler.end_scope(name)?;
+    }
+    Ok(())
+}
+
+/// Check the budgets and keep the timings of the finished frame
+pub(crate) fn profiler_end_frame() -> Result<(), EngineError> {
+    let mut global_profiler = fetch_global_profiler(EngineError::UpdateFailed)?;
+    if global_profiler.is_enabled {
+        global_profiler.end_frame();
+    }
+    Ok(())
+}
+
+pub fn profiler_set_enabled(is_enabled: bool) -> Result<(), EngineError> {
+    let mut global_profiler = fetch_global_profiler(EngineError::UpdateFailed)?;
+    global_profiler.is_enabled = is_enabled;
+    if !is_enabled {
+        global_profiler.scopes.clear();
+        global_profiler.open_scopes.clear();
+        global_profiler.last_frame.clear();
+    }
+    Ok(())
+}
+
+pub fn profiler_set_overlay_visible(is_visible: bool) -> Result<(), EngineError> {
+    let mut global_profiler = fetch_global_profiler(EngineError::UpdateFailed)?;
+    global_profiler.is_overlay_visible = is_visible;
+    Ok(())
+}
+
+pub fn profiler_is_overlay_visible() -> Result<bool, EngineError> {
+    let global_profiler = fetch_global_profiler(EngineError::AccessFailed)?;
+    Ok(global_profiler.is_overlay_visible)
+}
+
+/// Budget in seconds for every scope with the given name, None removes it
+pub fn profiler_set_budget(name: &'static str, budget: Option<f64>) -> Result<(), EngineError> {
+    let mut global_profiler = fetch_global_profiler(EngineError::UpdateFailed)?;
+    match budget {
+        Some(budget) if budget <= 0.0 => {
+            error!("The profiler budget of `{}' must be positive", name);
+            return Err(EngineError::InvalidValue);
+        }
+        Some(budget) => {
+            global_profiler.budgets.insert(
+                name,
+                ProfilerBudget {
+                    budget,
+                    consecutive_frames_over: 0,
+                },
+            );
+        }
+        None => {
+            global_profiler.budgets.remove(name);
+        }
+    }
+    Ok(())
+}
+
+pub fn profiler_set_budget_warning_frames(frame_count: u32) -> Result<(), EngineError> {
+    let mut global_profiler = fetch_global_profiler(EngineError::UpdateFailed)?;
+    global_profiler.budget_warning_frames = frame_count;
+    Ok(())
+}
+
+/// Scopes of the last frame, children right after their parent, siblings sorted by exclusive time
+pub fn profiler_get_last_frame() -> Result<Vec<ProfilerScopeView>, EngineError> {
+    let global_profiler = fetch_global_profiler(EngineError::AccessFailed)?;
+    Ok(global_profiler.last_frame.clone())
+}
+
+/// Lines of the hierarchical view, as shown by the overlay
+pub fn profiler_get_overlay_lines() -> Result<Vec<String>, EngineError> {
+    let global_profiler = fetch_global_profiler(EngineError::AccessFailed)?;
+    Ok(global_profiler
+        .last_frame
+        .iter()
+        .map(|scope| {
+            format!(
+                "{}{}{} x{}: {:.3}ms ({:.3}ms total)",
+                "  ".repeat(scope.depth),
+                if scope.is_over_budget { "! " } else { "" },
+                scope.name,
+                scope.call_count,
+                scope.exclusive_time * 1000.0,
+                scope.inclusive_time * 1000.0
+            )
+        })
+        .collect())
+}
+
+/// Log the hierarchical view of the last frame
+pub fn profiler_dump() -> Result<(), EngineError> {
+    for line in profiler_get_overlay_lines()? {
+        debug_no_details!("{}", line);
+    }
+    Ok(())
+}
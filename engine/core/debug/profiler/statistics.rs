@@ -0,0 +1,185 @@
+use std::collections::VecDeque;
+
+use crate::{
+    core::debug::errors::EngineError, error, info, renderer::renderer_types::GpuPassTimings,
+};
+
+use super::fetch_global_profiler;
+
+/// Number of frames the rolling statistics are computed on by default
+const STATISTICS_DEFAULT_WINDOW: usize = 120;
+
+/// Cpu time spent in each part of a frame, in seconds
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FrameTimings {
+    /// Fixed and variable updates of the game
+    pub update: f64,
+    /// Game render or loading screen callbacks
+    pub render: f64,
+    /// Recording and submitting the frame to the gpu
+    pub present: f64,
+    /// Whole frame, without the time given back by the frame rate limiter
+    pub total: f64,
+}
+
+/// Statistics of the last frames, read with `profiler_get_statistics`
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct EngineStatistics {
+    pub frame_number: u64,
+    pub last_frame: FrameTimings,
+    pub draw_call_count: u32,
+    /// Frames per second over the rolling window, from the time between the frames
+    pub fps: f64,
+    /// Frame time percentiles over the rolling window, in seconds
+    pub frame_time_p50: f64,
+    pub frame_time_p95: f64,
+    pub frame_time_p99: f64,
+    /// Gpu time of the passes of the last frame read back, a few frames behind the cpu timings
+    /// None when the device has no timestamps
+    pub gpu_pass_timings: Option<GpuPassTimings>,
+}
+
+/// Rolling window of the frame statistics, collected even when the profiler is disabled
+pub(crate) struct StatisticsCollector {
+    pub window: usize,
+    /// Log the statistics every that many seconds
+    pub log_interval: Option<f64>,
+    frame_times: VecDeque<f64>,
+    /// Time between the frames, including the sleeps of the limiter
+    frame_deltas: VecDeque<f64>,
+    time_since_log: f64,
+    statistics: EngineStatistics,
+}
+
+impl Default for StatisticsCollector {
+    fn default() -> Self {
+        Self {
+            window: STATISTICS_DEFAULT_WINDOW,
+            log_interval: None,
+            frame_times: VecDeque::new(),
+            frame_deltas: VecDeque::new(),
+            time_since_log: 0.0,
+            statistics: EngineStatistics::default(),
+        }
+    }
+}
+
+impl StatisticsCollector {
+    /// Nearest rank percentile of sorted values, `percentile` in [0, 1]
+    fn get_percentile(sorted: &[f64], percentile: f64) -> f64 {
+        if sorted.is_empty() {
+            return 0.0;
+        }
+        let rank = (percentile * sorted.len() as f64).ceil() as usize;
+        sorted[rank.clamp(1, sorted.len()) - 1]
+    }
+
+    fn push(values: &mut VecDeque<f64>, value: f64, window: usize) {
+        values.push_back(value);
+        while values.len() > window {
+            values.pop_front();
+        }
+    }
+
+    fn record_frame(
+        &mut self,
+        timings: FrameTimings,
+        delta_time: f64,
+        draw_call_count: u32,
+        gpu_pass_timings: Option<GpuPassTimings>,
+    ) {
+        Self::push(&mut self.frame_times, timings.total, self.window);
+        Self::push(&mut self.frame_deltas, delta_time, self.window);
+
+        let mut sorted: Vec<f64> = self.frame_times.iter().copied().collect();
+        sorted.sort_by(f64::total_cmp);
+        let delta_sum: f64 = self.frame_deltas.iter().sum();
+        self.statistics = EngineStatistics {
+            frame_number: self.statistics.frame_number + 1,
+            last_frame: timings,
+            draw_call_count,
+            fps: if delta_sum > 0.0 {
+                self.frame_deltas.len() as f64 / delta_sum
+            } else {
+                0.0
+            },
+            frame_time_p50: Self::get_percentile(&sorted, 0.50),
+            frame_time_p95: Self::get_percentile(&sorted, 0.95),
+            frame_time_p99: Self::get_percentile(&sorted, 0.99),
+            gpu_pass_timings,
+        };
+
+        if let Some(log_interval) = self.log_interval {
+            self.time_since_log += delta_time;
+            if self.time_since_log >= log_interval {
+                self.time_since_log = 0.0;
+                self.log();
+            }
+        }
+    }
+
+    fn log(&self) {
+        let statistics = &self.statistics;
+        info!(
+            "{:.1} fps, frame {:.2}ms (p50 {:.2}ms, p95 {:.2}ms, p99 {:.2}ms), update {:.2}ms, render {:.2}ms, present {:.2}ms, {} draw calls",
+            statistics.fps,
+            statistics.last_frame.total * 1000.0,
+            statistics.frame_time_p50 * 1000.0,
+            statistics.frame_time_p95 * 1000.0,
+            statistics.frame_time_p99 * 1000.0,
+            statistics.last_frame.update * 1000.0,
+            statistics.last_frame.render * 1000.0,
+            statistics.last_frame.present * 1000.0,
+            statistics.draw_call_count
+        );
+        if let Some(gpu) = statistics.gpu_pass_timings {
+            info!(
+                "gpu world pass {:.2}ms, ui pass {:.2}ms",
+                gpu.world * 1000.0,
+                gpu.ui * 1000.0
+            );
+        }
+    }
+}
+
+/// Add a finished frame to the rolling statistics
+pub(crate) fn profiler_record_frame_statistics(
+    timings: FrameTimings,
+    delta_time: f64,
+    draw_call_count: u32,
+    gpu_pass_timings: Option<GpuPassTimings>,
+) -> Result<(), EngineError> {
+    let mut global_profiler = fetch_global_profiler(EngineError::UpdateFailed)?;
+    global_profiler
+        .statistics
+        .record_frame(timings, delta_time, draw_call_count, gpu_pass_timings);
+    Ok(())
+}
+
+pub fn profiler_get_statistics() -> Result<EngineStatistics, EngineError> {
+    let global_profiler = fetch_global_profiler(EngineError::AccessFailed)?;
+    Ok(global_profiler.statistics.statistics)
+}
+
+/// Number of frames the fps and the percentiles are computed on
+pub fn profiler_set_statistics_window(frame_count: usize) -> Result<(), EngineError> {
+    if frame_count == 0 {
+        error!("The statistics window needs at least one frame");
+        return Err(EngineError::InvalidValue);
+    }
+    let mut global_profiler = fetch_global_profiler(EngineError::UpdateFailed)?;
+    global_profiler.statistics.window = frame_count;
+    Ok(())
+}
+
+/// Log the statistics every `interval` seconds, None stops the logging
+pub fn profiler_set_statistics_log_interval(interval: Option<f64>) -> Result<(), EngineError> {
+    if interval.is_some_and(|interval| !interval.is_finite() || interval <= 0.0) {
+        error!("The statistics log interval must be positive");
+        return Err(EngineError::InvalidValue);
+    }
+    let mut global_profiler = fetch_global_profiler(EngineError::UpdateFailed)?;
+    global_profiler.statistics.log_interval = interval;
+    global_profiler.statistics.time_since_log = 0.0;
+    Ok(())
+}
@@ -0,0 +1,261 @@
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    core::{
+        debug::errors::EngineError,
+        globals::{EngineGlobal, EngineGlobalGuard},
+    },
+    error, info,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TelemetryFormat {
+    #[default]
+    Csv,
+    Json,
+}
+
+/// Recording started automatically when the engine starts
+#[derive(Clone, Debug, PartialEq)]
+pub struct TelemetryCapture {
+    pub path: PathBuf,
+    pub format: TelemetryFormat,
+    /// Number of frames to record before writing the file
+    pub frame_count: u64,
+}
+
+/// Metrics of one frame
+#[derive(Clone, Copy, Debug, Default)]
+pub struct TelemetryFrame {
+    pub frame_number: u64,
+    /// In seconds
+    pub frame_time: f64,
+    pub draw_calls: u32,
+    /// Resident memory of the process in bytes, none if the platform can't tell
+    pub memory: Option<u64>,
+    pub event_count: u32,
+}
+
+struct TelemetryRecording {
+    path: PathBuf,
+    format: TelemetryFormat,
+    /// Stop after this many frames, never if none
+    frame_count: Option<u64>,
+    frames: Vec<TelemetryFrame>,
+}
+
+/// Opt-in per frame metrics written to a file for offline analysis
+#[derive(Default)]
+pub(crate) struct Telemetry {
+    recording: Option<TelemetryRecording>,
+    frame_number: u64,
+    current_event_count: u32,
+}
+
+impl TelemetryRecording {
+    fn write_csv(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writeln!(writer, "frame,frame_time_ms,draw_calls,memory_bytes,events")?;
+        for frame in &self.frames {
+            writeln!(
+                writer,
+                "{},{:.4},{},{},{}",
+                frame.frame_number,
+                frame.frame_time * 1000.0,
+                frame.draw_calls,
+                frame
+                    .memory
+                    .map_or(String::new(), |memory| memory.to_string()),
+                frame.event_count
+            )?;
+        }
+        Ok(())
+    }
+
+    fn write_json(&self, writer: &mut impl Write) -> std::io::Result<()> {
+        writeln!(writer, "[")?;
+        for (index, frame) in self.frames.iter().enumerate() {
+            writeln!(
+                writer,
+                "  {{\"frame\": {}, \"frame_time_ms\": {:.4}, \"draw_calls\": {}, \"memory_bytes\": {}, \"events\": {}}}{}",
+                frame.frame_number,
+                frame.frame_time * 1000.0,
+                frame.draw_calls,
+                frame.memory.map_or(String::from("null"), |memory| memory.to_string()),
+                frame.event_count,
+                if index + 1 < self.frames.len() { "," } else { "" }
+            )?;
+        }
+        writeln!(writer, "]")?;
+        Ok(())
+    }
+
+    fn write(&self) -> Result<(), EngineError> {
+        let file = match File::create(&self.path) {
+            Ok(file) => file,
+            Err(err) => {
+                error!(
+                    "Failed to create the telemetry file {:?}: {:?}",
+                    self.path, err
+                );
+                return Err(EngineError::IO);
+            }
+        };
+        let mut writer = BufWriter::new(file);
+        let result = match self.format {
+            TelemetryFormat::Csv => self.write_csv(&mut writer),
+            TelemetryFormat::Json => self.write_json(&mut writer),
+        };
+        if let Err(err) = result.and_then(|()| writer.flush()) {
+            error!(
+                "Failed to write the telemetry file {:?}: {:?}",
+                self.path, err
+            );
+            return Err(EngineError::IO);
+        }
+        info!(
+            "Wrote {} frames of telemetry to {:?}",
+            self.frames.len(),
+            self.path
+        );
+        Ok(())
+    }
+}
+
+pub(crate) static GLOBAL_TELEMETRY: EngineGlobal<Telemetry> = EngineGlobal::new("telemetry");
+
+fn fetch_global_telemetry(error: EngineError) -> Result<EngineGlobalGuard<Telemetry>, EngineError> {
+    GLOBAL_TELEMETRY.fetch(error)
+}
+
+/// Initiate the telemetry, nothing is recorded until a recording starts
+pub(crate) fn telemetry_init() -> Result<(), EngineError> {
+    fetch_global_telemetry(EngineError::InitializationFailed)?;
+    Ok(())
+}
+
+/// Shutdown the telemetry, writing the current recording if any
+pub(crate) fn telemetry_shutdown() -> Result<(), EngineError> {
+    if telemetry_is_recording()? {
+        telemetry_stop()?;
+    }
+    GLOBAL_TELEMETRY.reset(EngineError::ShutdownFailed)?;
+    Ok(())
+}
+
+/// Called by the event system for every fired event
+pub(crate) fn telemetry_record_event() -> Result<(), EngineError> {
+    let mut global_telemetry = fetch_global_telemetry(EngineError::UpdateFailed)?;
+    global_telemetry.current_event_count += 1;
+    Ok(())
+}
+
+/// Record the metrics of the finished frame
+pub(crate) fn telemetry_end_frame(
+    frame_time: f64,
+    draw_calls: u32,
+    memory: Option<u64>,
+) -> Result<(), EngineError> {
+    let mut global_telemetry = fetch_global_telemetry(EngineError::UpdateFailed)?;
+    let frame = TelemetryFrame {
+        frame_number: global_telemetry.frame_number,
+        frame_time,
+        draw_calls,
+        memory,
+        event_count: global_telemetry.current_event_count,
+    };
+    global_telemetry.frame_number += 1;
+    global_telemetry.current_event_count = 0;
+
+    let recording = match &mut global_telemetry.recording {
+        Some(recording) => recording,
+        None => return Ok(()),
+    };
+    recording.frames.push(frame);
+    if recording
+        .frame_count
+        .is_some_and(|frame_count| recording.frames.len() as u64 >= frame_count)
+    {
+        if let Some(recording) = global_telemetry.recording.take() {
+            recording.write()?;
+        }
+    }
+    Ok(())
+}
+
+/// Start recording the metrics of every frame, written to the file when the recording stops
+/// A frame count stops the recording automatically after that many frames
+pub fn telemetry_start(
+    path: &Path,
+    format: TelemetryFormat,
+    frame_count: Option<u64>,
+) -> Result<(), EngineError> {
+    let mut global_telemetry = fetch_global_telemetry(EngineError::UpdateFailed)?;
+    if let Some(recording) = &global_telemetry.recording {
+        error!(
+            "Can't start a telemetry recording, one is already recording to {:?}",
+            recording.path
+        );
+        return Err(EngineError::Duplicate);
+    }
+    global_telemetry.recording = Some(TelemetryRecording {
+        path: path.to_path_buf(),
+        format,
+        frame_count,
+        frames: Vec::new(),
+    });
+    Ok(())
+}
+
+/// Stop the current recording and write it
+pub fn telemetry_stop() -> Result<(), EngineError> {
+    let mut global_telemetry = fetch_global_telemetry(EngineError::UpdateFailed)?;
+    match global_telemetry.recording.take() {
+        Some(recording) => recording.write(),
+        None => {
+            error!("Can't stop the telemetry, nothing is being recorded");
+            Err(EngineError::InvalidValue)
+        }
+    }
+}
+
+pub fn telemetry_is_recording() -> Result<bool, EngineError> {
+    let global_telemetry = fetch_global_telemetry(EngineError::AccessFailed)?;
+    Ok(global_telemetry.recording.is_some())
+}
+
+/// `telemetry` command of the console
+pub(crate) fn telemetry_console_command(arguments: &[&str]) -> Result<(), EngineError> {
+    let (path, format, frame_count) = match arguments {
+        ["stop"] => return telemetry_stop(),
+        ["start", path, format] => (path, format, None),
+        ["start", path, format, frame_count] => (path, format, Some(frame_count)),
+        _ => {
+            error!("Unknown telemetry command `{}'", arguments.join(" "));
+            return Err(EngineError::InvalidValue);
+        }
+    };
+    let format = match *format {
+        "csv" => TelemetryFormat::Csv,
+        "json" => TelemetryFormat::Json,
+        _ => {
+            error!(
+                "Invalid telemetry format `{}', expected csv or json",
+                format
+            );
+            return Err(EngineError::InvalidValue);
+        }
+    };
+    let frame_count = match frame_count.map(|frame_count| frame_count.parse::<u64>()) {
+        None => None,
+        Some(Ok(frame_count)) => Some(frame_count),
+        Some(Err(err)) => {
+            error!("Invalid telemetry frame count: {:?}", err);
+            return Err(EngineError::InvalidValue);
+        }
+    };
+    telemetry_start(Path::new(path), format, frame_count)
+}
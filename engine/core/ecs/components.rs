@@ -0,0 +1,226 @@
+use std::sync::Arc;
+
+use crate::{
+    core::debug::errors::EngineError,
+    renderer::scene::{
+        camera::{Camera, CameraCreatorParameters},
+        culling::BoundingSphere,
+        graph::SceneRenderable,
+    },
+    resources::{
+        animation::{AnimationClip, AnimationPlayback},
+        inverse_kinematics::TwoBoneIkChain,
+        sprite_animation::SpriteAnimation,
+        texture_system::TextureHandle,
+    },
+};
+
+/// Position, rotation and scale of an entity in the world
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transform {
+    pub position: glam::Vec3,
+    pub rotation: glam::Quat,
+    pub scale: glam::Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            position: glam::Vec3::ZERO,
+            rotation: glam::Quat::IDENTITY,
+            scale: glam::Vec3::ONE,
+        }
+    }
+}
+
+impl Transform {
+    pub fn position(mut self, position: glam::Vec3) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn rotation(mut self, rotation: glam::Quat) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn scale(mut self, scale: glam::Vec3) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn get_matrix(&self) -> glam::Mat4 {
+        glam::Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.position)
+    }
+
+    /// View matrix of a camera placed at the transform, the scale is ignored
+    pub fn get_view_matrix(&self) -> glam::Mat4 {
+        glam::Mat4::from_rotation_translation(self.rotation, self.position).inverse()
+    }
+}
+
+/// Geometry and material drawn at the entity's transform
+#[derive(Clone, Debug)]
+pub struct MeshRenderer {
+    /// Shader resources acquired with `renderer_acquire_object`
+    pub object_id: u32,
+    /// Id returned when the geometry was created by the renderer
+    pub geometry_id: u32,
+    /// The default material is used when missing
+    pub material_id: Option<u32>,
+    /// Object space bounds, the entity is never culled without them
+    pub bounding_sphere: Option<BoundingSphere>,
+    pub is_visible: bool,
+    /// Weights of the morph targets of the geometry, applied before each frame
+    /// Empty to keep the current ones, the entities sharing a geometry share its weights
+    pub morph_weights: Vec<f32>,
+}
+
+impl MeshRenderer {
+    pub fn new(object_id: u32, geometry_id: u32) -> Self {
+        Self {
+            object_id,
+            geometry_id,
+            material_id: None,
+            bounding_sphere: None,
+            is_visible: true,
+            morph_weights: Vec::new(),
+        }
+    }
+
+    pub fn material(mut self, material_id: u32) -> Self {
+        self.material_id = Some(material_id);
+        self
+    }
+
+    pub fn bounding_sphere(mut self, bounding_sphere: BoundingSphere) -> Self {
+        self.bounding_sphere = Some(bounding_sphere);
+        self
+    }
+
+    pub fn is_visible(mut self, is_visible: bool) -> Self {
+        self.is_visible = is_visible;
+        self
+    }
+
+    pub fn morph_weights(mut self, morph_weights: Vec<f32>) -> Self {
+        self.morph_weights = morph_weights;
+        self
+    }
+
+    pub(crate) fn get_renderable(&self) -> SceneRenderable {
+        SceneRenderable {
+            object_id: self.object_id,
+            geometry_id: self.geometry_id,
+            material_id: self.material_id,
+            bounding_sphere: self.bounding_sphere,
+        }
+    }
+}
+
+/// Camera looking from the entity's transform, see `ecs_set_active_camera`
+/// The eye, center and up of the parameters are replaced by the transform
+#[derive(Clone, Copy, Debug)]
+pub struct CameraComponent {
+    /// The aspect ratio follows the framebuffer's
+    pub camera: Camera,
+}
+
+impl CameraComponent {
+    pub fn new(parameters: CameraCreatorParameters) -> Self {
+        Self {
+            camera: Camera::new(parameters, 1.0),
+        }
+    }
+}
+
+impl Default for CameraComponent {
+    fn default() -> Self {
+        Self::new(CameraCreatorParameters::default())
+    }
+}
+
+/// Animated sprite drawn on top of the frame, below the ui of the game
+/// The top left corner is at the x and y of the transform in screen pixels, the size is scaled by it
+#[derive(Clone, Debug)]
+pub struct SpriteRenderer {
+    /// Ticked by the engine each frame the game is updated
+    pub animation: SpriteAnimation,
+    /// Atlas the frames of the animation are read from
+    pub texture: TextureHandle,
+    pub size: glam::Vec2,
+    /// Multiplied with the texture
+    pub color: glam::Vec4,
+    pub is_visible: bool,
+}
+
+impl SpriteRenderer {
+    pub fn new(animation: SpriteAnimation, texture: TextureHandle, size: glam::Vec2) -> Self {
+        Self {
+            animation,
+            texture,
+            size,
+            color: glam::Vec4::ONE,
+            is_visible: true,
+        }
+    }
+
+    pub fn color(mut self, color: glam::Vec4) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn is_visible(mut self, is_visible: bool) -> Self {
+        self.is_visible = is_visible;
+        self
+    }
+}
+
+/// Skeletal animation of the entity, updated by the engine each frame the game is updated
+/// The clip is sampled at the playback time, then the ik chains are applied in order
+#[derive(Clone, Debug)]
+pub struct Animator {
+    /// Shared by the entities playing the same clip
+    pub clip: Arc<AnimationClip>,
+    /// The events crossed by the playback are fired as `AnimationNotify'
+    pub playback: AnimationPlayback,
+    pub ik_chains: Vec<TwoBoneIkChain>,
+    /// Joint positions of the last update, after the ik
+    /// In the space of the clip keyframes, the ik targets and poles must use it too
+    pub pose: Vec<glam::Vec3>,
+}
+
+impl Animator {
+    /// The playback lasts as long as the clip
+    pub fn new(clip_id: u32, clip: Arc<AnimationClip>) -> Self {
+        let playback = AnimationPlayback::new(clip_id, clip.get_duration());
+        let mut pose = Vec::with_capacity(clip.get_joint_count());
+        clip.sample(0.0, &mut pose);
+        Self {
+            clip,
+            playback,
+            ik_chains: Vec::new(),
+            pose,
+        }
+    }
+
+    pub fn playback(mut self, playback: AnimationPlayback) -> Self {
+        self.playback = playback;
+        self
+    }
+
+    pub fn ik_chain(mut self, ik_chain: TwoBoneIkChain) -> Self {
+        self.ik_chains.push(ik_chain);
+        self
+    }
+
+    /// Advance the playback, sample the clip and solve the ik chains
+    pub(crate) fn update(&mut self, delta_time: f64) -> Result<(), EngineError> {
+        self.playback.advance(delta_time)?;
+        self.clip.sample(self.playback.time, &mut self.pose);
+        for ik_chain in &self.ik_chains {
+            ik_chain.apply(&mut self.pose)?;
+        }
+        Ok(())
+    }
+}
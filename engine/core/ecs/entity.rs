@@ -0,0 +1,89 @@
+use crate::{core::debug::errors::EngineError, error};
+
+/// Handle of an entity of the ecs world
+/// The generation tells apart the entities reusing the slot of a despawned one
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Entity {
+    pub(crate) index: u32,
+    pub(crate) generation: u32,
+}
+
+impl Entity {
+    pub fn get_index(&self) -> u32 {
+        self.index
+    }
+
+    pub fn get_generation(&self) -> u32 {
+        self.generation
+    }
+}
+
+/// Gives out the entity handles, the slots of the despawned entities are reused first
+#[derive(Default)]
+pub(crate) struct EntityAllocator {
+    generations: Vec<u32>,
+    is_alive: Vec<bool>,
+    free_indices: Vec<u32>,
+    alive_count: usize,
+}
+
+impl EntityAllocator {
+    pub fn allocate(&mut self) -> Entity {
+        self.alive_count += 1;
+        if let Some(index) = self.free_indices.pop() {
+            self.is_alive[index as usize] = true;
+            return Entity {
+                index,
+                generation: self.generations[index as usize],
+            };
+        }
+        self.generations.push(0);
+        self.is_alive.push(true);
+        Entity {
+            index: self.generations.len() as u32 - 1,
+            generation: 0,
+        }
+    }
+
+    /// The handles of the entity become stale
+    pub fn free(&mut self, entity: Entity) -> Result<(), EngineError> {
+        self.check(entity)?;
+        let index = entity.index as usize;
+        self.is_alive[index] = false;
+        self.generations[index] = self.generations[index].wrapping_add(1);
+        self.free_indices.push(entity.index);
+        self.alive_count -= 1;
+        Ok(())
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        let index = entity.index as usize;
+        index < self.generations.len()
+            && self.is_alive[index]
+            && self.generations[index] == entity.generation
+    }
+
+    pub fn check(&self, entity: Entity) -> Result<(), EngineError> {
+        if !self.is_alive(entity) {
+            error!("The entity {:?} doesn't exist anymore", entity);
+            return Err(EngineError::InvalidValue);
+        }
+        Ok(())
+    }
+
+    pub fn get_alive_count(&self) -> usize {
+        self.alive_count
+    }
+
+    pub fn get_alive_entities(&self) -> impl Iterator<Item = Entity> + '_ {
+        self.is_alive
+            .iter()
+            .zip(&self.generations)
+            .enumerate()
+            .filter(|(_, (is_alive, _))| **is_alive)
+            .map(|(index, (_, generation))| Entity {
+                index: index as u32,
+                generation: *generation,
+            })
+    }
+}
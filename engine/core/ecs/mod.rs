@@ -0,0 +1,221 @@
+use components::{Animator, CameraComponent, SpriteRenderer, Transform};
+use entity::Entity;
+use system::{SystemScheduler, SystemStage};
+use world::World;
+
+use crate::{
+    core::{
+        debug::errors::EngineError,
+        globals::{EngineGlobal, EngineGlobalGuard},
+    },
+    error,
+    renderer::scene::camera::Camera,
+};
+
+pub(crate) mod components;
+pub(crate) mod entity;
+pub(crate) mod storage;
+pub(crate) mod system;
+pub(crate) mod world;
+
+/// Entities of the game and the systems updating them
+/// The renderer draws the entities with a transform and a mesh renderer each frame
+#[derive(Default)]
+pub(crate) struct Ecs {
+    pub world: World,
+    pub scheduler: SystemScheduler,
+    /// Entity the frame is rendered from when no debug or named camera is active
+    pub active_camera: Option<Entity>,
+}
+
+pub(crate) static GLOBAL_ECS: EngineGlobal<Ecs> = EngineGlobal::new("ecs");
+
+pub(crate) fn fetch_global_ecs(error: EngineError) -> Result<EngineGlobalGuard<Ecs>, EngineError> {
+    GLOBAL_ECS.fetch(error)
+}
+
+pub(crate) fn ecs_init() -> Result<(), EngineError> {
+    fetch_global_ecs(EngineError::InitializationFailed)?;
+    Ok(())
+}
+
+/// The entities and the systems are dropped
+pub(crate) fn ecs_shutdown() -> Result<(), EngineError> {
+    GLOBAL_ECS.reset(EngineError::ShutdownFailed)
+}
+
+/// Run the enabled systems, called once per frame after the update of the game
+pub(crate) fn ecs_run_systems(delta_time: f64) -> Result<(), EngineError> {
+    let mut global_ecs = fetch_global_ecs(EngineError::UpdateFailed)?;
+    let ecs = &mut *global_ecs;
+    ecs.scheduler.run(&mut ecs.world, delta_time)
+}
+
+/// Move the skeletal animations forward and fire their events, called once per frame after the systems
+pub(crate) fn ecs_update_animations(delta_time: f64) -> Result<(), EngineError> {
+    let mut ecs = fetch_global_ecs(EngineError::UpdateFailed)?;
+    for (entity, animator) in ecs.world.query_mut::<Animator>() {
+        if let Err(err) = animator.update(delta_time) {
+            error!(
+                "Failed to update the animation of the entity {:?}: {:?}",
+                entity, err
+            );
+            return Err(EngineError::UpdateFailed);
+        }
+    }
+    Ok(())
+}
+
+/// Move the sprite animations forward, called once per frame after the systems
+pub(crate) fn ecs_update_sprite_animations(delta_time: f64) -> Result<(), EngineError> {
+    let mut ecs = fetch_global_ecs(EngineError::UpdateFailed)?;
+    for (entity, sprite) in ecs.world.query_mut::<SpriteRenderer>() {
+        if let Err(err) = sprite.animation.update(delta_time) {
+            error!(
+                "Failed to update the sprite animation of the entity {:?}: {:?}",
+                entity, err
+            );
+            return Err(EngineError::UpdateFailed);
+        }
+    }
+    Ok(())
+}
+
+/// Camera of the active camera entity, placed at its transform
+/// None when there is no active camera entity or it lost its components
+pub(crate) fn ecs_get_active_camera(aspect_ratio: f32) -> Result<Option<Camera>, EngineError> {
+    let ecs = fetch_global_ecs(EngineError::AccessFailed)?;
+    let entity = match ecs.active_camera {
+        Some(entity) => entity,
+        None => return Ok(None),
+    };
+    let (component, transform) = match (
+        ecs.world.get_component::<CameraComponent>(entity),
+        ecs.world.get_component::<Transform>(entity),
+    ) {
+        (Some(component), Some(transform)) => (component, transform),
+        _ => return Ok(None),
+    };
+    let mut camera = component.camera;
+    camera.update_aspect_ratio(aspect_ratio);
+    camera.set_view(transform.get_view_matrix());
+    Ok(Some(camera))
+}
+
+pub fn ecs_spawn() -> Result<Entity, EngineError> {
+    let mut ecs = fetch_global_ecs(EngineError::UpdateFailed)?;
+    Ok(ecs.world.spawn())
+}
+
+/// The components of the entity are dropped, it stops being the active camera if it was
+pub fn ecs_despawn(entity: Entity) -> Result<(), EngineError> {
+    let mut ecs = fetch_global_ecs(EngineError::UpdateFailed)?;
+    ecs.world.despawn(entity)?;
+    if ecs.active_camera == Some(entity) {
+        ecs.active_camera = None;
+    }
+    Ok(())
+}
+
+pub fn ecs_is_alive(entity: Entity) -> Result<bool, EngineError> {
+    let ecs = fetch_global_ecs(EngineError::AccessFailed)?;
+    Ok(ecs.world.is_alive(entity))
+}
+
+/// Replaces the component of the same type if the entity already has one
+pub fn ecs_add_component<T: Send + 'static>(
+    entity: Entity,
+    component: T,
+) -> Result<(), EngineError> {
+    let mut ecs = fetch_global_ecs(EngineError::UpdateFailed)?;
+    ecs.world.add_component(entity, component)
+}
+
+pub fn ecs_remove_component<T: 'static>(entity: Entity) -> Result<T, EngineError> {
+    let mut ecs = fetch_global_ecs(EngineError::UpdateFailed)?;
+    ecs.world.remove_component(entity)
+}
+
+/// Copy of the component, none if the entity doesn't have one
+pub fn ecs_get_component<T: Clone + 'static>(entity: Entity) -> Result<Option<T>, EngineError> {
+    let ecs = fetch_global_ecs(EngineError::AccessFailed)?;
+    Ok(ecs.world.get_component::<T>(entity).cloned())
+}
+
+/// Change a component in place
+pub fn ecs_update_component<T, F>(entity: Entity, update: F) -> Result<(), EngineError>
+where
+    T: 'static,
+    F: FnOnce(&mut T),
+{
+    let mut ecs = fetch_global_ecs(EngineError::UpdateFailed)?;
+    match ecs.world.get_component_mut::<T>(entity) {
+        Some(component) => {
+            update(component);
+            Ok(())
+        }
+        None => {
+            error!(
+                "The entity {:?} has no {} component to update",
+                entity,
+                std::any::type_name::<T>()
+            );
+            Err(EngineError::InvalidValue)
+        }
+    }
+}
+
+/// Access the whole world, e.g. to run a query outside of a system
+/// The `ecs_*` functions must not be called from the closure
+pub fn ecs_with_world<R, F>(function: F) -> Result<R, EngineError>
+where
+    F: FnOnce(&mut World) -> R,
+{
+    let mut ecs = fetch_global_ecs(EngineError::UpdateFailed)?;
+    Ok(function(&mut ecs.world))
+}
+
+/// Run a system each frame at the given stage, after the update of the game
+/// The system gets the world, the `ecs_*` functions must not be called from it
+pub fn ecs_add_system<F>(name: &str, stage: SystemStage, system: F) -> Result<(), EngineError>
+where
+    F: FnMut(&mut World, f64) -> Result<(), EngineError> + Send + 'static,
+{
+    let mut ecs = fetch_global_ecs(EngineError::UpdateFailed)?;
+    ecs.scheduler.add(name, stage, Box::new(system))
+}
+
+pub fn ecs_remove_system(name: &str) -> Result<(), EngineError> {
+    let mut ecs = fetch_global_ecs(EngineError::UpdateFailed)?;
+    ecs.scheduler.remove(name)
+}
+
+/// A disabled system keeps its place in the schedule
+pub fn ecs_set_system_enabled(name: &str, is_enabled: bool) -> Result<(), EngineError> {
+    let mut ecs = fetch_global_ecs(EngineError::UpdateFailed)?;
+    ecs.scheduler.set_enabled(name, is_enabled)
+}
+
+pub fn ecs_get_system_names() -> Result<Vec<String>, EngineError> {
+    let ecs = fetch_global_ecs(EngineError::AccessFailed)?;
+    Ok(ecs.scheduler.get_names())
+}
+
+/// Render the frame from an entity with a transform and a camera component
+/// None goes back to the main camera of the renderer
+pub fn ecs_set_active_camera(entity: Option<Entity>) -> Result<(), EngineError> {
+    let mut ecs = fetch_global_ecs(EngineError::UpdateFailed)?;
+    if let Some(entity) = entity {
+        if !ecs.world.has_component::<CameraComponent>(entity)
+            || !ecs.world.has_component::<Transform>(entity)
+        {
+            error!(
+                "The entity {:?} needs a transform and a camera component to render the frame",
+                entity
+            );
+            return Err(EngineError::InvalidValue);
+        }
+    }
+    ecs.active_camera = entity;
+    Ok(())
+}
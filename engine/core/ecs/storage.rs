@@ -0,0 +1,103 @@
+use std::any::Any;
+
+use super::entity::Entity;
+
+/// Components of a single type, packed together so the queries go through them in order
+/// The sparse array maps the entity indices to the packed slots
+pub(crate) struct ComponentStorage<T> {
+    sparse: Vec<Option<usize>>,
+    entities: Vec<Entity>,
+    components: Vec<T>,
+}
+
+impl<T> Default for ComponentStorage<T> {
+    fn default() -> Self {
+        Self {
+            sparse: Vec::new(),
+            entities: Vec::new(),
+            components: Vec::new(),
+        }
+    }
+}
+
+impl<T> ComponentStorage<T> {
+    fn get_slot(&self, entity: Entity) -> Option<usize> {
+        self.sparse
+            .get(entity.index as usize)
+            .copied()
+            .flatten()
+            .filter(|slot| self.entities[*slot] == entity)
+    }
+
+    /// Returns the previous component of the entity, if any
+    pub fn insert(&mut self, entity: Entity, component: T) -> Option<T> {
+        if let Some(slot) = self.get_slot(entity) {
+            return Some(std::mem::replace(&mut self.components[slot], component));
+        }
+        let index = entity.index as usize;
+        if index >= self.sparse.len() {
+            self.sparse.resize(index + 1, None);
+        }
+        self.sparse[index] = Some(self.components.len());
+        self.entities.push(entity);
+        self.components.push(component);
+        None
+    }
+
+    /// The last component takes the slot of the removed one
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let slot = self.get_slot(entity)?;
+        self.sparse[entity.index as usize] = None;
+        self.entities.swap_remove(slot);
+        let component = self.components.swap_remove(slot);
+        if let Some(moved_entity) = self.entities.get(slot) {
+            self.sparse[moved_entity.index as usize] = Some(slot);
+        }
+        Some(component)
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        self.get_slot(entity).map(|slot| &self.components[slot])
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        self.get_slot(entity).map(|slot| &mut self.components[slot])
+    }
+
+    pub fn contains(&self, entity: Entity) -> bool {
+        self.get_slot(entity).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.components.len()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.entities.iter().copied().zip(&self.components)
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.entities.iter().copied().zip(&mut self.components)
+    }
+}
+
+/// Storage whose component type is only known by the world
+pub(crate) trait AnyComponentStorage: Send {
+    fn remove_entity(&mut self, entity: Entity);
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl<T: Send + 'static> AnyComponentStorage for ComponentStorage<T> {
+    fn remove_entity(&mut self, entity: Entity) {
+        self.remove(entity);
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
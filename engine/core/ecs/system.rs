@@ -0,0 +1,89 @@
+use crate::{core::debug::errors::EngineError, error};
+
+use super::world::World;
+
+/// Moments of the frame the systems run at, after the update of the game
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum SystemStage {
+    PreUpdate,
+    Update,
+    /// Right before the renderer reads the transforms and the cameras
+    PostUpdate,
+}
+
+pub(crate) type SystemCallback = Box<dyn FnMut(&mut World, f64) -> Result<(), EngineError> + Send>;
+
+struct System {
+    name: String,
+    stage: SystemStage,
+    is_enabled: bool,
+    callback: SystemCallback,
+}
+
+/// Systems run by stage, in the order they were added within a stage
+#[derive(Default)]
+pub(crate) struct SystemScheduler {
+    systems: Vec<System>,
+}
+
+impl SystemScheduler {
+    fn find(&mut self, name: &str) -> Result<&mut System, EngineError> {
+        match self.systems.iter_mut().find(|system| system.name == name) {
+            Some(system) => Ok(system),
+            None => {
+                error!("Unknown system {:?}", name);
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    pub fn add(
+        &mut self,
+        name: &str,
+        stage: SystemStage,
+        callback: SystemCallback,
+    ) -> Result<(), EngineError> {
+        if self.systems.iter().any(|system| system.name == name) {
+            error!("A system named {:?} already exists", name);
+            return Err(EngineError::Duplicate);
+        }
+        self.systems.push(System {
+            name: name.to_string(),
+            stage,
+            is_enabled: true,
+            callback,
+        });
+        // Stable, keeps the order of addition within a stage
+        self.systems.sort_by_key(|system| system.stage);
+        Ok(())
+    }
+
+    pub fn remove(&mut self, name: &str) -> Result<(), EngineError> {
+        self.find(name)?;
+        self.systems.retain(|system| system.name != name);
+        Ok(())
+    }
+
+    pub fn set_enabled(&mut self, name: &str, is_enabled: bool) -> Result<(), EngineError> {
+        self.find(name)?.is_enabled = is_enabled;
+        Ok(())
+    }
+
+    pub fn get_names(&self) -> Vec<String> {
+        self.systems
+            .iter()
+            .map(|system| system.name.clone())
+            .collect()
+    }
+
+    /// A failing system stops the frame's run
+    pub fn run(&mut self, world: &mut World, delta_time: f64) -> Result<(), EngineError> {
+        for system in self.systems.iter_mut().filter(|system| system.is_enabled) {
+            if let Err(err) = (system.callback)(world, delta_time) {
+                error!("Failed to run the system {:?}: {:?}", system.name, err);
+                return Err(EngineError::UpdateFailed);
+            }
+        }
+        Ok(())
+    }
+}
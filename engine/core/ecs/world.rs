@@ -0,0 +1,134 @@
+use std::{
+    any::{type_name, TypeId},
+    collections::HashMap,
+};
+
+use crate::{core::debug::errors::EngineError, error};
+
+use super::{
+    entity::{Entity, EntityAllocator},
+    storage::{AnyComponentStorage, ComponentStorage},
+};
+
+/// Entities and their components, any `'static` type can be a component
+#[derive(Default)]
+pub struct World {
+    entities: EntityAllocator,
+    storages: HashMap<TypeId, Box<dyn AnyComponentStorage>>,
+}
+
+impl World {
+    fn get_storage<T: 'static>(&self) -> Option<&ComponentStorage<T>> {
+        self.storages
+            .get(&TypeId::of::<T>())
+            .and_then(|storage| storage.as_any().downcast_ref())
+    }
+
+    fn get_storage_mut<T: 'static>(&mut self) -> Option<&mut ComponentStorage<T>> {
+        self.storages
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|storage| storage.as_any_mut().downcast_mut())
+    }
+
+    pub fn spawn(&mut self) -> Entity {
+        self.entities.allocate()
+    }
+
+    /// The components of the entity are dropped
+    pub fn despawn(&mut self, entity: Entity) -> Result<(), EngineError> {
+        self.entities.free(entity)?;
+        for storage in self.storages.values_mut() {
+            storage.remove_entity(entity);
+        }
+        Ok(())
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.entities.is_alive(entity)
+    }
+
+    pub fn get_entity_count(&self) -> usize {
+        self.entities.get_alive_count()
+    }
+
+    pub fn get_entities(&self) -> Vec<Entity> {
+        self.entities.get_alive_entities().collect()
+    }
+
+    /// Replaces the component of the same type if the entity already has one
+    pub fn add_component<T: Send + 'static>(
+        &mut self,
+        entity: Entity,
+        component: T,
+    ) -> Result<(), EngineError> {
+        self.entities.check(entity)?;
+        self.storages
+            .entry(TypeId::of::<T>())
+            .or_insert_with(|| Box::new(ComponentStorage::<T>::default()));
+        self.get_storage_mut::<T>()
+            .unwrap()
+            .insert(entity, component);
+        Ok(())
+    }
+
+    pub fn remove_component<T: 'static>(&mut self, entity: Entity) -> Result<T, EngineError> {
+        self.entities.check(entity)?;
+        match self
+            .get_storage_mut::<T>()
+            .and_then(|storage| storage.remove(entity))
+        {
+            Some(component) => Ok(component),
+            None => {
+                error!(
+                    "The entity {:?} has no {} component to remove",
+                    entity,
+                    type_name::<T>()
+                );
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    pub fn has_component<T: 'static>(&self, entity: Entity) -> bool {
+        self.get_storage::<T>()
+            .is_some_and(|storage| storage.contains(entity))
+    }
+
+    pub fn get_component<T: 'static>(&self, entity: Entity) -> Option<&T> {
+        self.get_storage::<T>()
+            .and_then(|storage| storage.get(entity))
+    }
+
+    pub fn get_component_mut<T: 'static>(&mut self, entity: Entity) -> Option<&mut T> {
+        self.get_storage_mut::<T>()
+            .and_then(|storage| storage.get_mut(entity))
+    }
+
+    pub fn get_component_count<T: 'static>(&self) -> usize {
+        self.get_storage::<T>().map_or(0, ComponentStorage::len)
+    }
+
+    /// Every entity with a component of the given type
+    pub fn query<T: 'static>(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.get_storage::<T>()
+            .into_iter()
+            .flat_map(|storage| storage.iter())
+    }
+
+    pub fn query_mut<T: 'static>(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.get_storage_mut::<T>()
+            .into_iter()
+            .flat_map(|storage| storage.iter_mut())
+    }
+
+    /// Every entity with components of both types
+    /// The entities are found by going through the first type, it should be the rarest one
+    pub fn query_pair<A: 'static, B: 'static>(&self) -> impl Iterator<Item = (Entity, &A, &B)> {
+        let second = self.get_storage::<B>();
+        self.query::<A>().filter_map(move |(entity, a)| {
+            second
+                .and_then(|storage| storage.get(entity))
+                .map(|b| (entity, a, b))
+        })
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    mem::ManuallyDrop,
+    ops::{Deref, DerefMut},
+    sync::{Mutex, MutexGuard, PoisonError},
+    thread::{self, ThreadId},
+};
+
+use once_cell::sync::Lazy;
+
+use crate::{core::debug::errors::EngineError, error};
+
+/// State of an engine system, shared by the engine loop and the other threads, e.g. the job workers
+/// Fetching it locks it until the returned guard is dropped, the other threads wait for it meanwhile
+/// Fetching it again from the thread holding it is an error instead of a deadlock
+pub(crate) struct EngineGlobal<T> {
+    name: &'static str,
+    value: Lazy<Mutex<T>>,
+    /// Thread holding the guard, if any
+    owner: Mutex<Option<ThreadId>>,
+}
+
+impl<T: Default + Send> EngineGlobal<T> {
+    pub const fn new(name: &'static str) -> Self {
+        Self {
+            name,
+            value: Lazy::new(Self::create),
+            owner: Mutex::new(None),
+        }
+    }
+
+    fn create() -> Mutex<T> {
+        Mutex::new(T::default())
+    }
+
+    /// The guard must be dropped before calling a system that fetches the same global
+    pub fn fetch(&'static self, error: EngineError) -> Result<EngineGlobalGuard<T>, EngineError> {
+        let current = thread::current().id();
+        if *self.lock_owner() == Some(current) {
+            error!(
+                "Failed to fetch the global {}: this thread already holds it",
+                self.name
+            );
+            return Err(error);
+        }
+        // A panic while the global was held must not prevent the next accesses
+        let guard = self.value.lock().unwrap_or_else(PoisonError::into_inner);
+        *self.lock_owner() = Some(current);
+        Ok(EngineGlobalGuard {
+            global: self,
+            guard,
+        })
+    }
+
+    /// Drop the state, the next access starts from the default one
+    pub fn reset(&'static self, error: EngineError) -> Result<(), EngineError> {
+        *self.fetch(error)? = T::default();
+        Ok(())
+    }
+}
+
+impl<T> EngineGlobal<T> {
+    fn lock_owner(&self) -> MutexGuard<'_, Option<ThreadId>> {
+        self.owner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+/// Exclusive access to an engine global, released when dropped
+pub(crate) struct EngineGlobalGuard<T: 'static> {
+    global: &'static EngineGlobal<T>,
+    guard: MutexGuard<'static, T>,
+}
+
+impl<T> Deref for EngineGlobalGuard<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.guard
+    }
+}
+
+impl<T> DerefMut for EngineGlobalGuard<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.guard
+    }
+}
+
+impl<T> Drop for EngineGlobalGuard<T> {
+    /// The owner is cleared before the value is unlocked, the next thread then sets itself
+    fn drop(&mut self) {
+        *self.global.lock_owner() = None;
+    }
+}
+
+/// Value only usable from the thread that created it, e.g. the windows of the platform
+/// Lets a global hold it, the other threads get an error instead of using it
+pub(crate) struct ThreadBound<T> {
+    thread: ThreadId,
+    value: ManuallyDrop<T>,
+}
+
+// Safety: the value is only accessed and dropped from its thread, see `get_mut` and `drop`
+unsafe impl<T> Send for ThreadBound<T> {}
+
+impl<T> ThreadBound<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            thread: thread::current().id(),
+            value: ManuallyDrop::new(value),
+        }
+    }
+
+    fn is_owner_thread(&self) -> bool {
+        thread::current().id() == self.thread
+    }
+
+    pub fn get(&self, error: EngineError) -> Result<&T, EngineError> {
+        if !self.is_owner_thread() {
+            error!("Failed to access a value from another thread than the one creating it");
+            return Err(error);
+        }
+        Ok(&self.value)
+    }
+
+    pub fn get_mut(&mut self, error: EngineError) -> Result<&mut T, EngineError> {
+        if !self.is_owner_thread() {
+            error!("Failed to access a value from another thread than the one creating it");
+            return Err(error);
+        }
+        Ok(&mut self.value)
+    }
+}
+
+impl<T> Drop for ThreadBound<T> {
+    /// The value is leaked when dropped from another thread
+    fn drop(&mut self) {
+        if !self.is_owner_thread() {
+            error!("Failed to drop a value from another thread than the one creating it");
+            return;
+        }
+        // Safety: the value is never used again
+        unsafe { ManuallyDrop::drop(&mut self.value) }
+    }
+}
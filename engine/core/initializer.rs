@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+
+use crate::{core::debug::errors::EngineError, debug, error};
+
+/// Initialize a part of the engine, the parameters are only needed during the initialization
+pub(crate) type InitFunction<C, P> = fn(&mut C, &P) -> Result<(), EngineError>;
+pub(crate) type ShutdownFunction<C> = fn(&mut C) -> Result<(), EngineError>;
+
+/// Part of the engine initialized after the ones it depends on
+pub(crate) struct Initializer<C, P> {
+    pub name: &'static str,
+    pub dependencies: Vec<&'static str>,
+    pub init: InitFunction<C, P>,
+    pub shutdown: ShutdownFunction<C>,
+}
+
+/// Parts of the engine declaring their dependencies, the initialization order is computed from them
+/// The context is what is being initialized, e.g. the renderer backend
+pub(crate) struct InitializerRegistry<C, P = ()> {
+    initializers: Vec<Initializer<C, P>>,
+}
+
+impl<C, P> Default for InitializerRegistry<C, P> {
+    fn default() -> Self {
+        Self {
+            initializers: Vec::new(),
+        }
+    }
+}
+
+impl<C, P> InitializerRegistry<C, P> {
+    /// The registration order is kept between the parts that don't depend on each other
+    pub fn register(
+        mut self,
+        name: &'static str,
+        dependencies: &[&'static str],
+        init: InitFunction<C, P>,
+        shutdown: ShutdownFunction<C>,
+    ) -> Self {
+        self.initializers.push(Initializer {
+            name,
+            dependencies: dependencies.to_vec(),
+            init,
+            shutdown,
+        });
+        self
+    }
+
+    /// Indices of the initializers, each one after all of its dependencies
+    pub fn compute_order(&self) -> Result<Vec<usize>, EngineError> {
+        let mut indices = HashMap::new();
+        for (index, initializer) in self.initializers.iter().enumerate() {
+            if indices.insert(initializer.name, index).is_some() {
+                error!("The `{}' initializer is registered twice", initializer.name);
+                return Err(EngineError::Duplicate);
+            }
+        }
+
+        let mut dependencies = Vec::with_capacity(self.initializers.len());
+        for initializer in &self.initializers {
+            let mut initializer_dependencies = Vec::new();
+            for dependency in &initializer.dependencies {
+                match indices.get(dependency) {
+                    Some(index) => initializer_dependencies.push(*index),
+                    None => {
+                        error!(
+                            "The `{}' initializer depends on the unknown `{}' initializer",
+                            initializer.name, dependency
+                        );
+                        return Err(EngineError::InvalidValue);
+                    }
+                }
+            }
+            dependencies.push(initializer_dependencies);
+        }
+
+        // Repeatedly take the first registered initializer whose dependencies are all ordered
+        let mut is_ordered = vec![false; self.initializers.len()];
+        let mut order = Vec::with_capacity(self.initializers.len());
+        while order.len() < self.initializers.len() {
+            let next = (0..self.initializers.len()).find(|index| {
+                !is_ordered[*index]
+                    && dependencies[*index]
+                        .iter()
+                        .all(|dependency| is_ordered[*dependency])
+            });
+            match next {
+                Some(index) => {
+                    is_ordered[index] = true;
+                    order.push(index);
+                }
+                None => {
+                    let cycle: Vec<&str> = self
+                        .initializers
+                        .iter()
+                        .enumerate()
+                        .filter(|(index, _)| !is_ordered[*index])
+                        .map(|(_, initializer)| initializer.name)
+                        .collect();
+                    error!(
+                        "The initializers have cyclic dependencies between: {:?}",
+                        cycle
+                    );
+                    return Err(EngineError::InvalidValue);
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    /// Shut down the given initializers in order
+    /// Returns false if one of them failed, it doesn't prevent the others from shutting down
+    fn shutdown_ordered(&self, context: &mut C, order: impl Iterator<Item = usize>) -> bool {
+        let mut is_complete = true;
+        for index in order {
+            let initializer = &self.initializers[index];
+            match (initializer.shutdown)(context) {
+                Ok(()) => debug!("Shutted down the {}", initializer.name),
+                Err(err) => {
+                    error!("Failed to shutdown the {}: {:?}", initializer.name, err);
+                    is_complete = false;
+                }
+            }
+        }
+        is_complete
+    }
+
+    /// Initialize everything in dependency order
+    /// On failure, what has already been initialized is shut down in the reverse order
+    pub fn init(&self, context: &mut C, parameters: &P) -> Result<(), EngineError> {
+        let order = match self.compute_order() {
+            Ok(order) => order,
+            Err(err) => {
+                error!("Failed to compute the initialization order: {:?}", err);
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+
+        for (position, index) in order.iter().enumerate() {
+            let initializer = &self.initializers[*index];
+            if let Err(err) = (initializer.init)(context, parameters) {
+                error!("Failed to initialize the {}: {:?}", initializer.name, err);
+                let initialized = order[..position].iter().rev().copied();
+                if !self.shutdown_ordered(context, initialized) {
+                    error!(
+                        "Failed to shutdown what was initialized before the {}",
+                        initializer.name
+                    );
+                }
+                return Err(EngineError::InitializationFailed);
+            }
+            debug!("Initialized the {}", initializer.name);
+        }
+        Ok(())
+    }
+
+    /// Shut down everything in the reverse dependency order
+    /// A failing part doesn't prevent the others from shutting down
+    pub fn shutdown(&self, context: &mut C) -> Result<(), EngineError> {
+        let order = match self.compute_order() {
+            Ok(order) => order,
+            Err(err) => {
+                error!("Failed to compute the shutdown order: {:?}", err);
+                return Err(EngineError::ShutdownFailed);
+            }
+        };
+
+        if !self.shutdown_ordered(context, order.into_iter().rev()) {
+            return Err(EngineError::ShutdownFailed);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use crate::{
+    core::{
+        debug::errors::EngineError,
+        globals::{EngineGlobal, EngineGlobalGuard},
+    },
+    error,
+};
+
+/// Parts of the engine, in initialization order, they are shut down in the reverse order
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub enum EngineStage {
+    Subsystems,
+    Application,
+    Renderer,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum EngineLifecycleState {
+    #[default]
+    Uninitialized,
+    Initializing,
+    Running,
+    ShuttingDown,
+    /// The engine can't be initialized again
+    ShutDown,
+}
+
+#[derive(Default)]
+pub(crate) struct EngineLifecycle {
+    pub state: EngineLifecycleState,
+    /// Stages initialized so far, in order
+    initialized_stages: Vec<EngineStage>,
+}
+
+impl EngineLifecycle {
+    /// Can only be done once
+    pub fn begin_init(&mut self) -> Result<(), EngineError> {
+        if self.state != EngineLifecycleState::Uninitialized {
+            error!(
+                "The engine can't be initialized, it is already {:?}",
+                self.state
+            );
+            return Err(EngineError::MultipleInstantiation);
+        }
+        self.state = EngineLifecycleState::Initializing;
+        Ok(())
+    }
+
+    /// The stages must be initialized in order, each one only once
+    pub fn set_stage_initialized(&mut self, stage: EngineStage) -> Result<(), EngineError> {
+        if self.state != EngineLifecycleState::Initializing {
+            error!(
+                "Can't initialize the {:?} stage while the engine is {:?}",
+                stage, self.state
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+        if let Some(last) = self.initialized_stages.last() {
+            if *last >= stage {
+                error!(
+                    "The {:?} stage must be initialized before the {:?} stage",
+                    stage, last
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        }
+        self.initialized_stages.push(stage);
+        Ok(())
+    }
+
+    pub fn end_init(&mut self) -> Result<(), EngineError> {
+        if self.state != EngineLifecycleState::Initializing {
+            error!("Can't end the initialization of a {:?} engine", self.state);
+            return Err(EngineError::InitializationFailed);
+        }
+        self.state = EngineLifecycleState::Running;
+        Ok(())
+    }
+
+    pub fn is_stage_initialized(&self, stage: EngineStage) -> bool {
+        self.initialized_stages.contains(&stage)
+    }
+
+    /// Stages to shut down, last initialized first
+    /// Returns none when the engine is already shutting down or was never initialized
+    pub fn begin_shutdown(&mut self) -> Option<Vec<EngineStage>> {
+        match self.state {
+            EngineLifecycleState::Initializing | EngineLifecycleState::Running => {
+                self.state = EngineLifecycleState::ShuttingDown;
+                Some(self.initialized_stages.iter().rev().copied().collect())
+            }
+            EngineLifecycleState::Uninitialized
+            | EngineLifecycleState::ShuttingDown
+            | EngineLifecycleState::ShutDown => None,
+        }
+    }
+
+    /// The stage is considered shut down even if it failed, it is never shut down twice
+    pub fn set_stage_shut_down(&mut self, stage: EngineStage) {
+        self.initialized_stages
+            .retain(|initialized| *initialized != stage);
+    }
+
+    pub fn end_shutdown(&mut self) {
+        self.state = EngineLifecycleState::ShutDown;
+    }
+}
+
+pub(crate) static GLOBAL_LIFECYCLE: EngineGlobal<EngineLifecycle> =
+    EngineGlobal::new("engine lifecycle");
+
+pub(crate) fn fetch_global_lifecycle(
+    error: EngineError,
+) -> Result<EngineGlobalGuard<EngineLifecycle>, EngineError> {
+    GLOBAL_LIFECYCLE.fetch(error)
+}
+
+pub fn engine_get_lifecycle_state() -> Result<EngineLifecycleState, EngineError> {
+    Ok(fetch_global_lifecycle(EngineError::AccessFailed)?.state)
+}
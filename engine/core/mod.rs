@@ -1,3 +1,7 @@
-pub mod application;
-pub mod debug;
-pub mod systems;
+pub(crate) mod application;
+pub(crate) mod debug;
+pub(crate) mod ecs;
+pub(crate) mod globals;
+pub(crate) mod initializer;
+pub(crate) mod lifecycle;
+pub(crate) mod systems;
@@ -0,0 +1,162 @@
+/// One pole low pass filter on interleaved stereo samples
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LowPassFilter {
+    /// Last output of each channel
+    state: [f32; 2],
+}
+
+impl LowPassFilter {
+    pub fn reset(&mut self) {
+        self.state = [0.0; 2];
+    }
+
+    pub fn process(&mut self, samples: &mut [f32], cutoff_frequency: f32, sample_rate: u32) {
+        let time_constant = 1.0 / (2.0 * std::f32::consts::PI * cutoff_frequency.max(1.0));
+        let delta = 1.0 / sample_rate as f32;
+        let alpha = delta / (time_constant + delta);
+        for frame in samples.chunks_exact_mut(2) {
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                self.state[channel] += alpha * (*sample - self.state[channel]);
+                *sample = self.state[channel];
+            }
+        }
+    }
+}
+
+/// Feedback comb filter with a damped feedback loop
+#[derive(Clone, Debug)]
+struct CombFilter {
+    buffer: Vec<f32>,
+    index: usize,
+    filter_state: f32,
+}
+
+impl CombFilter {
+    fn new(length: usize) -> Self {
+        Self {
+            buffer: vec![0.0; length.max(1)],
+            index: 0,
+            filter_state: 0.0,
+        }
+    }
+
+    fn process(&mut self, input: f32, feedback: f32, damping: f32) -> f32 {
+        let output = self.buffer[self.index];
+        self.filter_state = output * (1.0 - damping) + self.filter_state * damping;
+        self.buffer[self.index] = input + self.filter_state * feedback;
+        self.index = (self.index + 1) % self.buffer.len();
+        output
+    }
+}
+
+#[derive(Clone, Debug)]
+struct AllPassFilter {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+impl AllPassFilter {
+    fn new(length: usize) -> Self {
+        Self {
+            buffer: vec![0.0; length.max(1)],
+            index: 0,
+        }
+    }
+
+    fn process(&mut self, input: f32) -> f32 {
+        let buffered = self.buffer[self.index];
+        let output = buffered - input;
+        self.buffer[self.index] = input + buffered * 0.5;
+        self.index = (self.index + 1) % self.buffer.len();
+        output
+    }
+}
+
+/// Delay lengths in samples at 44.1kHz, from the freeverb tuning
+const REVERB_COMB_LENGTHS: [usize; 4] = [1116, 1188, 1277, 1356];
+const REVERB_ALL_PASS_LENGTHS: [usize; 2] = [556, 441];
+/// Offset between the left and right delay lines to widen the stereo image
+const REVERB_STEREO_SPREAD: usize = 23;
+
+#[derive(Clone, Copy, Debug)]
+pub struct ReverbParameters {
+    /// How long the tail lasts, in [0, 1)
+    pub room_size: f32,
+    /// How fast the high frequencies fade in the tail, in [0, 1]
+    pub damping: f32,
+}
+
+impl Default for ReverbParameters {
+    fn default() -> Self {
+        Self {
+            room_size: 0.8,
+            damping: 0.5,
+        }
+    }
+}
+
+impl ReverbParameters {
+    pub fn room_size(mut self, room_size: f32) -> Self {
+        self.room_size = room_size.clamp(0.0, 0.98);
+        self
+    }
+
+    pub fn damping(mut self, damping: f32) -> Self {
+        self.damping = damping.clamp(0.0, 1.0);
+        self
+    }
+}
+
+/// Small schroeder reverb shared by the buses through their send amount
+#[derive(Clone, Debug)]
+pub struct Reverb {
+    pub parameters: ReverbParameters,
+    combs: [Vec<CombFilter>; 2],
+    all_passes: [Vec<AllPassFilter>; 2],
+}
+
+impl Reverb {
+    pub fn new(parameters: ReverbParameters, sample_rate: u32) -> Self {
+        let scale = sample_rate as f32 / 44100.0;
+        let scaled = |length: usize, channel: usize| {
+            ((length + channel * REVERB_STEREO_SPREAD) as f32 * scale) as usize
+        };
+        let combs = [0, 1].map(|channel| {
+            REVERB_COMB_LENGTHS
+                .iter()
+                .map(|length| CombFilter::new(scaled(*length, channel)))
+                .collect()
+        });
+        let all_passes = [0, 1].map(|channel| {
+            REVERB_ALL_PASS_LENGTHS
+                .iter()
+                .map(|length| AllPassFilter::new(scaled(*length, channel)))
+                .collect()
+        });
+        Self {
+            parameters,
+            combs,
+            all_passes,
+        }
+    }
+
+    /// Replace the interleaved stereo input by the wet signal
+    pub fn process(&mut self, samples: &mut [f32]) {
+        let feedback = self.parameters.room_size;
+        let damping = self.parameters.damping;
+        for frame in samples.chunks_exact_mut(2) {
+            // The reverb input is mono, the stereo comes from the different delay lengths
+            let input = (frame[0] + frame[1]) * 0.5;
+            for (channel, sample) in frame.iter_mut().enumerate() {
+                let mut output = 0.0;
+                for comb in self.combs[channel].iter_mut() {
+                    output += comb.process(input, feedback, damping);
+                }
+                for all_pass in self.all_passes[channel].iter_mut() {
+                    output = all_pass.process(output);
+                }
+                *sample = output / REVERB_COMB_LENGTHS.len() as f32;
+            }
+        }
+    }
+}
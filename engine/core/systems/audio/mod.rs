@@ -0,0 +1,343 @@
+use std::sync::Arc;
+
+use crate::{
+    core::{
+        debug::errors::EngineError,
+        globals::{EngineGlobal, EngineGlobalGuard},
+    },
+    error,
+    platforms::audio_output::{audio_output_init, AudioOutput},
+    warn,
+};
+use dsp::{LowPassFilter, Reverb, ReverbParameters};
+use music::{AudioStreamDecoder, MusicPlayer, MusicTrackParameters};
+
+pub(crate) mod dsp;
+pub(crate) mod music;
+pub(crate) mod vorbis;
+
+/// Sample rate of the mixer output, every sound is expected to be resampled to it
+pub const AUDIO_SAMPLE_RATE: u32 = 48000;
+/// Sounds and mixer output are interleaved stereo
+pub const AUDIO_CHANNEL_COUNT: usize = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AudioBus {
+    Master = 0,
+    Music = 1,
+    Sfx = 2,
+}
+
+const AUDIO_BUS_COUNT: usize = 3;
+
+#[derive(Clone, Copy, Debug)]
+pub struct AudioBusParameters {
+    pub volume: f32,
+    pub is_muted: bool,
+    /// Cutoff frequency in Hz of the bus low pass filter
+    pub low_pass_cutoff: Option<f32>,
+    /// Amount of the bus sent to the shared reverb, ignored on the master bus
+    pub reverb_send: f32,
+}
+
+impl Default for AudioBusParameters {
+    fn default() -> Self {
+        Self {
+            volume: 1.0,
+            is_muted: false,
+            low_pass_cutoff: None,
+            reverb_send: 0.0,
+        }
+    }
+}
+
+impl AudioBusParameters {
+    pub fn volume(mut self, volume: f32) -> Self {
+        self.volume = volume.max(0.0);
+        self
+    }
+
+    pub fn is_muted(mut self, is_muted: bool) -> Self {
+        self.is_muted = is_muted;
+        self
+    }
+
+    pub fn low_pass_cutoff(mut self, low_pass_cutoff: Option<f32>) -> Self {
+        self.low_pass_cutoff = low_pass_cutoff;
+        self
+    }
+
+    pub fn reverb_send(mut self, reverb_send: f32) -> Self {
+        self.reverb_send = reverb_send.clamp(0.0, 1.0);
+        self
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct AudioBusState {
+    parameters: AudioBusParameters,
+    low_pass_filter: LowPassFilter,
+    buffer: Vec<f32>,
+}
+
+/// A sound playing on a bus
+struct AudioVoice {
+    id: u32,
+    bus: AudioBus,
+    samples: Arc<Vec<f32>>,
+    /// Index of the next sample to read
+    cursor: usize,
+    volume: f32,
+    is_looping: bool,
+}
+
+pub(crate) struct AudioSystem {
+    pub is_initialized: bool,
+    buses: [AudioBusState; AUDIO_BUS_COUNT],
+    reverb: Reverb,
+    reverb_buffer: Vec<f32>,
+    voices: Vec<AudioVoice>,
+    next_voice_id: u32,
+    music: MusicPlayer,
+    /// Device calling `audio_mix`, None when no device could be opened
+    output: Option<Box<dyn AudioOutput>>,
+}
+
+impl Default for AudioSystem {
+    fn default() -> Self {
+        Self {
+            is_initialized: false,
+            buses: Default::default(),
+            reverb: Reverb::new(ReverbParameters::default(), AUDIO_SAMPLE_RATE),
+            reverb_buffer: Vec::new(),
+            voices: Vec::new(),
+            next_voice_id: 0,
+            music: MusicPlayer::default(),
+            output: None,
+        }
+    }
+}
+
+impl AudioSystem {
+    /// Fill the output with the next interleaved stereo samples
+    fn mix(&mut self, output: &mut [f32]) {
+        let sample_count = output.len();
+        for bus in self.buses.iter_mut() {
+            bus.buffer.clear();
+            bus.buffer.resize(sample_count, 0.0);
+        }
+        self.reverb_buffer.clear();
+        self.reverb_buffer.resize(sample_count, 0.0);
+
+        // Sum the voices into their bus
+        for voice in self.voices.iter_mut() {
+            let buffer = &mut self.buses[voice.bus as usize].buffer;
+            let mut written = 0;
+            while written < sample_count && voice.cursor < voice.samples.len() {
+                let count = (sample_count - written).min(voice.samples.len() - voice.cursor);
+                for (out, sample) in buffer[written..written + count]
+                    .iter_mut()
+                    .zip(&voice.samples[voice.cursor..voice.cursor + count])
+                {
+                    *out += sample * voice.volume;
+                }
+                written += count;
+                voice.cursor += count;
+                if voice.is_looping && voice.cursor >= voice.samples.len() {
+                    voice.cursor = 0;
+                }
+            }
+        }
+        self.voices
+            .retain(|voice| voice.cursor < voice.samples.len());
+        self.music
+            .mix(&mut self.buses[AudioBus::Music as usize].buffer);
+
+        // Process the child buses and route them to the master bus and the reverb
+        let (master, children) = self.buses.split_at_mut(1);
+        let master = &mut master[0];
+        for bus in children.iter_mut() {
+            if bus.parameters.is_muted {
+                continue;
+            }
+            if let Some(cutoff) = bus.parameters.low_pass_cutoff {
+                bus.low_pass_filter
+                    .process(&mut bus.buffer, cutoff, AUDIO_SAMPLE_RATE);
+            }
+            let volume = bus.parameters.volume;
+            let send = bus.parameters.reverb_send;
+            for ((out, reverb), sample) in master
+                .buffer
+                .iter_mut()
+                .zip(self.reverb_buffer.iter_mut())
+                .zip(&bus.buffer)
+            {
+                *out += sample * volume;
+                *reverb += sample * volume * send;
+            }
+        }
+        self.reverb.process(&mut self.reverb_buffer);
+        for (out, wet) in master.buffer.iter_mut().zip(&self.reverb_buffer) {
+            *out += wet;
+        }
+
+        // Master bus
+        if let Some(cutoff) = master.parameters.low_pass_cutoff {
+            master
+                .low_pass_filter
+                .process(&mut master.buffer, cutoff, AUDIO_SAMPLE_RATE);
+        }
+        let volume = if master.parameters.is_muted {
+            0.0
+        } else {
+            master.parameters.volume
+        };
+        for (out, sample) in output.iter_mut().zip(&master.buffer) {
+            *out = (sample * volume).clamp(-1.0, 1.0);
+        }
+    }
+}
+
+pub(crate) static GLOBAL_AUDIO: EngineGlobal<AudioSystem> = EngineGlobal::new("audio system");
+
+fn fetch_global_audio(error: EngineError) -> Result<EngineGlobalGuard<AudioSystem>, EngineError> {
+    GLOBAL_AUDIO.fetch(error)
+}
+
+/// Initiate the engine audio subsystem
+pub(crate) fn audio_init() -> Result<(), EngineError> {
+    let mut global_audio = fetch_global_audio(EngineError::InitializationFailed)?;
+    global_audio.is_initialized = true;
+    // The device may call the mixer right away
+    drop(global_audio);
+    let output = match audio_output_init(
+        AUDIO_SAMPLE_RATE,
+        AUDIO_CHANNEL_COUNT,
+        Box::new(audio_output_fill),
+    ) {
+        Ok(output) => Some(output),
+        // The game still runs without sound, e.g. on the machines without a sound card
+        Err(err) => {
+            warn!(
+                "Failed to open the audio output, no sound is played: {:?}",
+                err
+            );
+            None
+        }
+    };
+    fetch_global_audio(EngineError::InitializationFailed)?.output = output;
+    Ok(())
+}
+
+/// Shutdown the engine audio subsystem
+pub(crate) fn audio_shutdown() -> Result<(), EngineError> {
+    // The device is stopped first, its callback uses the audio system
+    let output = fetch_global_audio(EngineError::ShutdownFailed)?
+        .output
+        .take();
+    if let Some(mut output) = output {
+        output.shutdown()?;
+    }
+    GLOBAL_AUDIO.reset(EngineError::ShutdownFailed)?;
+    Ok(())
+}
+
+pub fn audio_get_bus_parameters(bus: AudioBus) -> Result<AudioBusParameters, EngineError> {
+    let global_audio = fetch_global_audio(EngineError::AccessFailed)?;
+    Ok(global_audio.buses[bus as usize].parameters)
+}
+
+pub fn audio_set_bus_parameters(
+    bus: AudioBus,
+    parameters: AudioBusParameters,
+) -> Result<(), EngineError> {
+    let mut global_audio = fetch_global_audio(EngineError::UpdateFailed)?;
+    let bus_state = &mut global_audio.buses[bus as usize];
+    // Avoid a click when the filter is turned back on
+    if bus_state.parameters.low_pass_cutoff.is_none() {
+        bus_state.low_pass_filter.reset();
+    }
+    bus_state.parameters = parameters;
+    Ok(())
+}
+
+pub fn audio_set_bus_volume(bus: AudioBus, volume: f32) -> Result<(), EngineError> {
+    let parameters = audio_get_bus_parameters(bus)?.volume(volume);
+    audio_set_bus_parameters(bus, parameters)
+}
+
+pub fn audio_set_reverb_parameters(parameters: ReverbParameters) -> Result<(), EngineError> {
+    let mut global_audio = fetch_global_audio(EngineError::UpdateFailed)?;
+    global_audio.reverb.parameters = parameters;
+    Ok(())
+}
+
+/// Play interleaved stereo samples at the mixer sample rate on a bus
+/// Returns the id of the voice
+pub fn audio_play(
+    bus: AudioBus,
+    samples: Arc<Vec<f32>>,
+    volume: f32,
+    is_looping: bool,
+) -> Result<u32, EngineError> {
+    let mut global_audio = fetch_global_audio(EngineError::UpdateFailed)?;
+    if !global_audio.is_initialized {
+        error!("Can't play a sound before initializing the audio subsystem");
+        return Err(EngineError::NotInitialized);
+    }
+    let id = global_audio.next_voice_id;
+    global_audio.next_voice_id = global_audio.next_voice_id.wrapping_add(1);
+    global_audio.voices.push(AudioVoice {
+        id,
+        bus,
+        samples,
+        cursor: 0,
+        volume,
+        is_looping,
+    });
+    Ok(id)
+}
+
+pub fn audio_stop(voice_id: u32) -> Result<(), EngineError> {
+    let mut global_audio = fetch_global_audio(EngineError::UpdateFailed)?;
+    global_audio.voices.retain(|voice| voice.id != voice_id);
+    Ok(())
+}
+
+/// Stream a long track on the music bus, crossfading with the current one
+pub fn audio_play_music(
+    decoder: Box<dyn AudioStreamDecoder>,
+    parameters: MusicTrackParameters,
+) -> Result<(), EngineError> {
+    let mut global_audio = fetch_global_audio(EngineError::UpdateFailed)?;
+    if !global_audio.is_initialized {
+        error!("Can't play a music before initializing the audio subsystem");
+        return Err(EngineError::NotInitialized);
+    }
+    global_audio.music.play(decoder, parameters)
+}
+
+pub fn audio_stop_music(fade_duration: f32) -> Result<(), EngineError> {
+    let mut global_audio = fetch_global_audio(EngineError::UpdateFailed)?;
+    global_audio.music.stop(fade_duration);
+    Ok(())
+}
+
+pub fn audio_is_music_playing() -> Result<bool, EngineError> {
+    let global_audio = fetch_global_audio(EngineError::AccessFailed)?;
+    Ok(global_audio.music.is_playing())
+}
+
+/// Mix the playing voices into the output
+pub(crate) fn audio_mix(output: &mut [f32]) -> Result<(), EngineError> {
+    let mut global_audio = fetch_global_audio(EngineError::UpdateFailed)?;
+    global_audio.mix(output);
+    Ok(())
+}
+
+/// Callback of the audio output, called from its own thread
+fn audio_output_fill(output: &mut [f32]) {
+    if audio_mix(output).is_err() {
+        output.fill(0.0);
+    }
+}
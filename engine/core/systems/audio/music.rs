@@ -0,0 +1,243 @@
+use std::sync::mpsc::{sync_channel, Receiver, TryRecvError};
+
+use crate::{core::debug::errors::EngineError, error, warn};
+
+use super::{AUDIO_CHANNEL_COUNT, AUDIO_SAMPLE_RATE};
+
+/// Number of frames decoded at once by the streaming thread
+const MUSIC_STREAM_CHUNK_FRAME_COUNT: usize = 4096;
+/// Number of decoded chunks waiting to be played, bounds the memory used by a stream
+const MUSIC_STREAM_QUEUED_CHUNK_COUNT: usize = 4;
+
+/// Decodes a long sound progressively, called from the streaming thread
+/// See `VorbisStreamDecoder' for the ogg vorbis files
+pub trait AudioStreamDecoder: Send {
+    /// Decode up to `frame_count` interleaved stereo frames at the mixer sample rate
+    /// Returns an empty vector at the end of the stream
+    fn decode(&mut self, frame_count: usize) -> Result<Vec<f32>, EngineError>;
+
+    /// Move the read position to the given frame
+    fn seek(&mut self, frame: u64) -> Result<(), EngineError>;
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct MusicTrackParameters {
+    pub volume: f32,
+    /// Frames where the loop starts and ends, the track plays once if None
+    pub loop_points: Option<(u64, u64)>,
+    /// Duration of the crossfade with the previous track in seconds
+    pub crossfade_duration: f32,
+}
+
+impl MusicTrackParameters {
+    pub fn new() -> Self {
+        Self {
+            volume: 1.0,
+            ..Default::default()
+        }
+    }
+
+    pub fn volume(mut self, volume: f32) -> Self {
+        self.volume = volume.max(0.0);
+        self
+    }
+
+    pub fn loop_points(mut self, loop_points: Option<(u64, u64)>) -> Self {
+        self.loop_points = loop_points;
+        self
+    }
+
+    pub fn crossfade_duration(mut self, crossfade_duration: f32) -> Self {
+        self.crossfade_duration = crossfade_duration.max(0.0);
+        self
+    }
+}
+
+/// Decode the stream until the receiver is dropped or the track ends
+fn music_stream_decode(
+    decoder: &mut dyn AudioStreamDecoder,
+    loop_points: Option<(u64, u64)>,
+    sender: std::sync::mpsc::SyncSender<Vec<f32>>,
+) -> Result<(), EngineError> {
+    let mut position = 0_u64;
+    loop {
+        // Stop exactly on the loop end so the loop is sample accurate
+        let frame_count = match loop_points {
+            Some((_, loop_end)) => (MUSIC_STREAM_CHUNK_FRAME_COUNT as u64)
+                .min(loop_end.saturating_sub(position)) as usize,
+            None => MUSIC_STREAM_CHUNK_FRAME_COUNT,
+        };
+        let chunk = if frame_count > 0 {
+            decoder.decode(frame_count)?
+        } else {
+            Vec::new()
+        };
+        position += (chunk.len() / AUDIO_CHANNEL_COUNT) as u64;
+
+        if chunk.is_empty() || loop_points.is_some_and(|(_, loop_end)| position >= loop_end) {
+            match loop_points {
+                Some((loop_start, _)) if chunk.is_empty() && position == loop_start => {
+                    warn!("The music loop starts after the end of the stream");
+                    return Ok(());
+                }
+                Some((loop_start, _)) => {
+                    decoder.seek(loop_start)?;
+                    position = loop_start;
+                }
+                None => {
+                    if !chunk.is_empty() {
+                        let _ = sender.send(chunk);
+                    }
+                    return Ok(());
+                }
+            }
+        }
+        if !chunk.is_empty() && sender.send(chunk).is_err() {
+            // The track was stopped
+            return Ok(());
+        }
+    }
+}
+
+/// A track decoded on its own thread
+struct MusicStream {
+    receiver: Receiver<Vec<f32>>,
+    chunk: Vec<f32>,
+    cursor: usize,
+    volume: f32,
+    is_finished: bool,
+}
+
+impl MusicStream {
+    fn new(
+        mut decoder: Box<dyn AudioStreamDecoder>,
+        parameters: &MusicTrackParameters,
+    ) -> Result<Self, EngineError> {
+        if let Some((loop_start, loop_end)) = parameters.loop_points {
+            if loop_start >= loop_end {
+                error!(
+                    "Invalid music loop points: {} is not before {}",
+                    loop_start, loop_end
+                );
+                return Err(EngineError::InvalidValue);
+            }
+        }
+        let (sender, receiver) = sync_channel(MUSIC_STREAM_QUEUED_CHUNK_COUNT);
+        let loop_points = parameters.loop_points;
+        std::thread::spawn(move || {
+            if let Err(err) = music_stream_decode(decoder.as_mut(), loop_points, sender) {
+                error!("Failed to decode the music stream: {:?}", err);
+            }
+        });
+        Ok(Self {
+            receiver,
+            chunk: Vec::new(),
+            cursor: 0,
+            volume: parameters.volume,
+            is_finished: false,
+        })
+    }
+
+    /// Next sample of the stream, None if the decoder is late or the stream is over
+    fn next_sample(&mut self) -> Option<f32> {
+        while self.cursor >= self.chunk.len() {
+            match self.receiver.try_recv() {
+                Ok(chunk) => {
+                    self.chunk = chunk;
+                    self.cursor = 0;
+                }
+                Err(TryRecvError::Empty) => return None,
+                Err(TryRecvError::Disconnected) => {
+                    self.is_finished = true;
+                    return None;
+                }
+            }
+        }
+        let sample = self.chunk[self.cursor];
+        self.cursor += 1;
+        Some(sample * self.volume)
+    }
+}
+
+/// Plays one music track at a time and crossfades between them
+#[derive(Default)]
+pub(crate) struct MusicPlayer {
+    current: Option<MusicStream>,
+    /// Track fading out
+    previous: Option<MusicStream>,
+    fade_frame_count: u64,
+    fade_frame: u64,
+}
+
+impl MusicPlayer {
+    pub fn play(
+        &mut self,
+        decoder: Box<dyn AudioStreamDecoder>,
+        parameters: MusicTrackParameters,
+    ) -> Result<(), EngineError> {
+        let stream = match MusicStream::new(decoder, &parameters) {
+            Ok(stream) => stream,
+            Err(err) => {
+                error!("Failed to start the music stream: {:?}", err);
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        if self.previous.is_some() {
+            warn!("A music crossfade was interrupted by a new track");
+        }
+        self.previous = self.current.take();
+        self.current = Some(stream);
+        self.fade_frame_count = (parameters.crossfade_duration * AUDIO_SAMPLE_RATE as f32) as u64;
+        self.fade_frame = 0;
+        Ok(())
+    }
+
+    /// Fade out the current track
+    pub fn stop(&mut self, fade_duration: f32) {
+        self.previous = self.current.take();
+        self.fade_frame_count = (fade_duration * AUDIO_SAMPLE_RATE as f32) as u64;
+        self.fade_frame = 0;
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.current.is_some()
+    }
+
+    /// Add the music to an interleaved stereo buffer
+    pub fn mix(&mut self, buffer: &mut [f32]) {
+        if self.current.is_none() && self.previous.is_none() {
+            return;
+        }
+        for frame in buffer.chunks_exact_mut(AUDIO_CHANNEL_COUNT) {
+            let fade_in = if self.fade_frame < self.fade_frame_count {
+                self.fade_frame as f32 / self.fade_frame_count as f32
+            } else {
+                self.previous = None;
+                1.0
+            };
+            for sample in frame.iter_mut() {
+                if let Some(current) = self.current.as_mut() {
+                    *sample += current.next_sample().unwrap_or(0.0) * fade_in;
+                }
+                if let Some(previous) = self.previous.as_mut() {
+                    *sample += previous.next_sample().unwrap_or(0.0) * (1.0 - fade_in);
+                }
+            }
+            self.fade_frame += 1;
+        }
+        if self
+            .current
+            .as_ref()
+            .is_some_and(|current| current.is_finished)
+        {
+            self.current = None;
+        }
+        if self
+            .previous
+            .as_ref()
+            .is_some_and(|previous| previous.is_finished)
+        {
+            self.previous = None;
+        }
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+};
+
+use lewton::inside_ogg::OggStreamReader;
+
+use crate::{core::debug::errors::EngineError, error};
+
+use super::{music::AudioStreamDecoder, AUDIO_CHANNEL_COUNT, AUDIO_SAMPLE_RATE};
+
+/// Streams an ogg vorbis file, converted to interleaved stereo at the mixer sample rate
+/// The file is opened by the first decode, on the streaming thread
+pub struct VorbisStreamDecoder {
+    path: PathBuf,
+    reader: Option<OggStreamReader<BufReader<File>>>,
+    /// Decoded stereo frames at the sample rate of the file, not resampled yet
+    source_frames: Vec<f32>,
+    /// Position of the next output frame in the source frames
+    source_cursor: f64,
+}
+
+impl VorbisStreamDecoder {
+    pub fn new(path: &Path) -> Result<Self, EngineError> {
+        if !path.is_file() {
+            error!("The vorbis file {:?} does not exist", path);
+            return Err(EngineError::IO);
+        }
+        Ok(Self {
+            path: path.to_path_buf(),
+            reader: None,
+            source_frames: Vec::new(),
+            source_cursor: 0.0,
+        })
+    }
+
+    fn get_reader(&mut self) -> Result<&mut OggStreamReader<BufReader<File>>, EngineError> {
+        if self.reader.is_none() {
+            let file = match File::open(&self.path) {
+                Ok(file) => file,
+                Err(err) => {
+                    error!("Failed to open the vorbis file {:?}: {:?}", self.path, err);
+                    return Err(EngineError::IO);
+                }
+            };
+            match OggStreamReader::new(BufReader::new(file)) {
+                Ok(reader) => self.reader = Some(reader),
+                Err(err) => {
+                    error!(
+                        "Failed to read the vorbis headers of {:?}: {:?}",
+                        self.path, err
+                    );
+                    return Err(EngineError::InvalidValue);
+                }
+            }
+        }
+        Ok(self.reader.as_mut().unwrap())
+    }
+
+    fn get_source_sample_rate(&mut self) -> Result<u32, EngineError> {
+        Ok(self.get_reader()?.ident_hdr.audio_sample_rate)
+    }
+
+    /// Decode the next packet into the source frames, returns false at the end of the stream
+    fn read_packet(&mut self) -> Result<bool, EngineError> {
+        let reader = self.get_reader()?;
+        let channel_count = reader.ident_hdr.audio_channels as usize;
+        let samples = match reader.read_dec_packet_itl() {
+            Ok(Some(samples)) => samples,
+            Ok(None) => return Ok(false),
+            Err(err) => {
+                error!(
+                    "Failed to decode the vorbis file {:?}: {:?}",
+                    self.path, err
+                );
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        // Mono is played on both sides, the channels after the front ones are dropped
+        for frame in samples.chunks_exact(channel_count) {
+            let left = frame[0] as f32 / 32768.0;
+            let right = frame.get(1).map_or(left, |right| *right as f32 / 32768.0);
+            self.source_frames.extend([left, right]);
+        }
+        Ok(true)
+    }
+
+    fn get_source_frame_count(&self) -> usize {
+        self.source_frames.len() / AUDIO_CHANNEL_COUNT
+    }
+}
+
+impl AudioStreamDecoder for VorbisStreamDecoder {
+    fn decode(&mut self, frame_count: usize) -> Result<Vec<f32>, EngineError> {
+        let step = self.get_source_sample_rate()? as f64 / AUDIO_SAMPLE_RATE as f64;
+        let mut output = Vec::with_capacity(frame_count * AUDIO_CHANNEL_COUNT);
+        let mut is_stream_over = false;
+        while output.len() < frame_count * AUDIO_CHANNEL_COUNT {
+            let index = self.source_cursor as usize;
+            // The linear resampling needs the frame after the cursor
+            if index + 1 >= self.get_source_frame_count() && !is_stream_over {
+                is_stream_over = !self.read_packet()?;
+                continue;
+            }
+            if index >= self.get_source_frame_count() {
+                break;
+            }
+            let next = (index + 1).min(self.get_source_frame_count() - 1);
+            let factor = self.source_cursor.fract() as f32;
+            for channel in 0..AUDIO_CHANNEL_COUNT {
+                let current = self.source_frames[index * AUDIO_CHANNEL_COUNT + channel];
+                let following = self.source_frames[next * AUDIO_CHANNEL_COUNT + channel];
+                output.push(current + (following - current) * factor);
+            }
+            self.source_cursor += step;
+        }
+        // Keep the frames still needed by the next decode
+        let consumed = (self.source_cursor as usize).min(self.get_source_frame_count());
+        self.source_frames.drain(..consumed * AUDIO_CHANNEL_COUNT);
+        self.source_cursor -= consumed as f64;
+        Ok(output)
+    }
+
+    fn seek(&mut self, frame: u64) -> Result<(), EngineError> {
+        let source_rate = self.get_source_sample_rate()? as u64;
+        let source_frame = frame * source_rate / AUDIO_SAMPLE_RATE as u64;
+        if let Err(err) = self.get_reader()?.seek_absgp_pg(source_frame) {
+            error!(
+                "Failed to seek the frame {} of the vorbis file {:?}: {:?}",
+                frame, self.path, err
+            );
+            return Err(EngineError::InvalidValue);
+        }
+        self.source_frames.clear();
+        self.source_cursor = 0.0;
+        // The seek stops on the page before the frame, the position is known once a page is decoded
+        while self.read_packet()? {
+            if let Some(page_end) = self.get_reader()?.get_last_absgp() {
+                let page_start = page_end.saturating_sub(self.get_source_frame_count() as u64);
+                let skipped = (source_frame.saturating_sub(page_start) as usize)
+                    .min(self.get_source_frame_count());
+                self.source_frames.drain(..skipped * AUDIO_CHANNEL_COUNT);
+                break;
+            }
+        }
+        Ok(())
+    }
+}
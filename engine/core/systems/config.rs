@@ -0,0 +1,353 @@
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::{
+    core::{
+        application::{application_set_frame_rate_limit, ApplicationParameters},
+        debug::{
+            errors::EngineError,
+            profiler::{
+                profiler_set_overlay_visible, statistics::profiler_set_statistics_log_interval,
+            },
+        },
+        globals::{EngineGlobal, EngineGlobalGuard},
+        systems::logger::{logger_set_level, LogLevel},
+    },
+    debug, error, info,
+    renderer::{
+        accessibility::{ColorFilterMode, ColorFilterType},
+        renderer_frontend::{
+            renderer_get_accessibility_settings, renderer_set_accessibility_settings,
+            renderer_set_render_scale, renderer_set_vsync_mode,
+        },
+        renderer_types::{DepthFormat, VsyncMode},
+    },
+    warn,
+};
+
+/// Time between two checks of the config file modification time
+const CONFIG_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Engine settings read from a `name = value` file, `#` starts a comment
+/// The file is watched and the changed settings are applied while running
+/// The settings not used by the engine are kept for the game, see `config_get`
+#[derive(Default)]
+pub(crate) struct ConfigSystem {
+    path: Option<PathBuf>,
+    /// Modification time of the file when it was last read
+    last_modified: Option<SystemTime>,
+    last_poll_time: Option<Instant>,
+    /// The file could not be found at the last check, it is only reported once until it is back
+    is_missing: bool,
+    values: HashMap<String, String>,
+}
+
+impl ConfigSystem {
+    pub(crate) fn parse(content: &str) -> HashMap<String, String> {
+        let mut values = HashMap::new();
+        for (line_index, line) in content.lines().enumerate() {
+            let line = match line.split_once('#') {
+                Some((line, _comment)) => line,
+                None => line,
+            }
+            .trim();
+            if line.is_empty() {
+                continue;
+            }
+            match line.split_once('=') {
+                Some((name, value)) => {
+                    values.insert(name.trim().to_string(), value.trim().to_string());
+                }
+                None => warn!(
+                    "Ignoring the line {} of the config file, expected `name = value'",
+                    line_index + 1
+                ),
+            }
+        }
+        values
+    }
+
+    /// Read the file again if it changed since the last time
+    /// Returns the settings whose value changed
+    fn reload(&mut self) -> Result<Vec<(String, String)>, EngineError> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(Vec::new()),
+        };
+        let last_modified = match fs::metadata(path).and_then(|metadata| metadata.modified()) {
+            Ok(last_modified) => last_modified,
+            Err(_) if self.is_missing => return Ok(Vec::new()),
+            Err(err) => {
+                error!("Failed to read the config file {:?}: {:?}", path, err);
+                self.is_missing = true;
+                return Err(EngineError::IO);
+            }
+        };
+        if self.is_missing {
+            self.is_missing = false;
+            info!("The config file {:?} can be read again", path);
+        }
+        if self.last_modified == Some(last_modified) {
+            return Ok(Vec::new());
+        }
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => {
+                error!("Failed to read the config file {:?}: {:?}", path, err);
+                return Err(EngineError::IO);
+            }
+        };
+        self.last_modified = Some(last_modified);
+
+        let values = ConfigSystem::parse(&content);
+        let changed_values = values
+            .iter()
+            .filter(|(name, value)| self.values.get(*name) != Some(*value))
+            .map(|(name, value)| (name.clone(), value.clone()))
+            .collect();
+        self.values = values;
+        Ok(changed_values)
+    }
+}
+
+fn config_parse_bool(name: &str, value: &str) -> Result<bool, EngineError> {
+    match value {
+        "true" | "on" | "1" => Ok(true),
+        "false" | "off" | "0" => Ok(false),
+        _ => {
+            error!("Invalid value `{}' for the `{}' setting", value, name);
+            Err(EngineError::InvalidValue)
+        }
+    }
+}
+
+fn config_parse_depth_formats(name: &str, value: &str) -> Result<Vec<DepthFormat>, EngineError> {
+    value
+        .split(',')
+        .map(|format| match format.trim() {
+            "d16" => Ok(DepthFormat::D16),
+            "d32" => Ok(DepthFormat::D32),
+            "d24_s8" => Ok(DepthFormat::D24S8),
+            "d32_s8" => Ok(DepthFormat::D32S8),
+            _ => {
+                error!("Invalid value `{}' for the `{}' setting", value, name);
+                Err(EngineError::InvalidValue)
+            }
+        })
+        .collect()
+}
+
+/// Override the parameters with the settings that can't change once the engine is running
+pub(crate) fn config_read_launch_settings(
+    path: &Path,
+    parameters: &mut ApplicationParameters,
+) -> Result<(), EngineError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) => {
+            error!("Failed to read the config file {:?}: {:?}", path, err);
+            return Err(EngineError::IO);
+        }
+    };
+    let values = ConfigSystem::parse(&content);
+    if let Some(value) = values.get("depth_formats") {
+        parameters.depth_stencil.formats = config_parse_depth_formats("depth_formats", value)?;
+    }
+    if let Some(value) = values.get("require_stencil") {
+        parameters.depth_stencil.require_stencil = config_parse_bool("require_stencil", value)?;
+    }
+    Ok(())
+}
+
+/// Apply a setting used by the engine, the other ones are left to the game
+/// Also used by the `set` command of the console
+pub(crate) fn config_apply(name: &str, value: &str) -> Result<(), EngineError> {
+    match name {
+        "log_level" => {
+            let level = match value {
+                "error" => LogLevel::Error,
+                "warning" => LogLevel::Warning,
+                "info" => LogLevel::Info,
+                "debug" => LogLevel::Debug,
+                _ => {
+                    error!("Invalid value `{}' for the `{}' setting", value, name);
+                    return Err(EngineError::InvalidValue);
+                }
+            };
+            logger_set_level(level);
+        }
+        "profiler_overlay" => profiler_set_overlay_visible(config_parse_bool(name, value)?)?,
+        "vsync" => {
+            let mode = match value {
+                "on" => VsyncMode::On,
+                "mailbox" => VsyncMode::Mailbox,
+                "off" => VsyncMode::Off,
+                _ => {
+                    error!("Invalid value `{}' for the `{}' setting", value, name);
+                    return Err(EngineError::InvalidValue);
+                }
+            };
+            renderer_set_vsync_mode(mode)?;
+        }
+        "frame_rate_limit" => {
+            let limit = match value {
+                "off" => None,
+                _ => match value.parse::<f64>() {
+                    Ok(limit) => Some(limit),
+                    Err(_) => {
+                        error!("Invalid value `{}' for the `{}' setting", value, name);
+                        return Err(EngineError::InvalidValue);
+                    }
+                },
+            };
+            application_set_frame_rate_limit(limit)?;
+        }
+        "statistics_log_interval" => {
+            let interval = match value {
+                "off" => None,
+                _ => match value.parse::<f64>() {
+                    Ok(interval) => Some(interval),
+                    Err(_) => {
+                        error!("Invalid value `{}' for the `{}' setting", value, name);
+                        return Err(EngineError::InvalidValue);
+                    }
+                },
+            };
+            profiler_set_statistics_log_interval(interval)?;
+        }
+        "color_filter" => {
+            let mode = match value {
+                "none" => ColorFilterMode::None,
+                "protanopia" => ColorFilterMode::Protanopia,
+                "deuteranopia" => ColorFilterMode::Deuteranopia,
+                "tritanopia" => ColorFilterMode::Tritanopia,
+                _ => {
+                    error!("Invalid value `{}' for the `{}' setting", value, name);
+                    return Err(EngineError::InvalidValue);
+                }
+            };
+            renderer_set_accessibility_settings(
+                renderer_get_accessibility_settings()?.color_filter_mode(mode),
+            )?;
+        }
+        "color_filter_type" => {
+            let filter_type = match value {
+                "simulation" => ColorFilterType::Simulation,
+                "correction" => ColorFilterType::Correction,
+                _ => {
+                    error!("Invalid value `{}' for the `{}' setting", value, name);
+                    return Err(EngineError::InvalidValue);
+                }
+            };
+            renderer_set_accessibility_settings(
+                renderer_get_accessibility_settings()?.color_filter_type(filter_type),
+            )?;
+        }
+        "render_scale" => {
+            let render_scale = match value.parse::<f32>() {
+                Ok(render_scale) => render_scale,
+                Err(_) => {
+                    error!("Invalid value `{}' for the `{}' setting", value, name);
+                    return Err(EngineError::InvalidValue);
+                }
+            };
+            renderer_set_render_scale(render_scale)?;
+        }
+        "ui_scale" => {
+            let ui_scale = match value.parse::<f32>() {
+                Ok(ui_scale) => ui_scale,
+                Err(_) => {
+                    error!("Invalid value `{}' for the `{}' setting", value, name);
+                    return Err(EngineError::InvalidValue);
+                }
+            };
+            renderer_set_accessibility_settings(
+                renderer_get_accessibility_settings()?.ui_scale(ui_scale),
+            )?;
+        }
+        // Only read at launch, see `config_read_launch_settings`
+        "depth_formats" | "require_stencil" => (),
+        // Not a warning, the game reads its own settings with `config_get`
+        _ => debug!(
+            "Setting `{}' is not used by the engine, it is only kept for `config_get'",
+            name
+        ),
+    }
+    Ok(())
+}
+
+pub(crate) static GLOBAL_CONFIG: EngineGlobal<ConfigSystem> = EngineGlobal::new("config");
+
+fn fetch_global_config(error: EngineError) -> Result<EngineGlobalGuard<ConfigSystem>, EngineError> {
+    GLOBAL_CONFIG.fetch(error)
+}
+
+/// Read and apply the changed settings
+/// A setting failing to apply doesn't prevent the others from being applied
+fn config_reload() -> Result<(), EngineError> {
+    let changed_values = fetch_global_config(EngineError::UpdateFailed)?.reload()?;
+    for (name, value) in changed_values {
+        match config_apply(&name, &value) {
+            Ok(()) => info!("Config setting `{}' set to `{}'", name, value),
+            Err(err) => error!("Failed to apply the config setting `{}': {:?}", name, err),
+        }
+    }
+    Ok(())
+}
+
+/// Initiate the engine config, nothing is read until `config_load` is called
+pub(crate) fn config_init() -> Result<(), EngineError> {
+    let mut global_config = fetch_global_config(EngineError::InitializationFailed)?;
+    *global_config = ConfigSystem::default();
+    Ok(())
+}
+
+/// Shutdown the engine config
+pub(crate) fn config_shutdown() -> Result<(), EngineError> {
+    GLOBAL_CONFIG.reset(EngineError::ShutdownFailed)?;
+    Ok(())
+}
+
+/// Apply the settings of the file and keep watching it for changes
+pub fn config_load(path: PathBuf) -> Result<(), EngineError> {
+    let mut global_config = fetch_global_config(EngineError::InitializationFailed)?;
+    *global_config = ConfigSystem {
+        path: Some(path),
+        ..Default::default()
+    };
+    drop(global_config);
+    config_reload()
+}
+
+/// Value of a setting of the config file, None if it is not set
+pub fn config_get(name: &str) -> Result<Option<String>, EngineError> {
+    let global_config = fetch_global_config(EngineError::AccessFailed)?;
+    Ok(global_config.values.get(name).cloned())
+}
+
+/// Apply the changes of the config file, called once per frame
+/// The file is only checked every `CONFIG_POLL_INTERVAL`
+pub(crate) fn config_poll() -> Result<(), EngineError> {
+    let mut global_config = fetch_global_config(EngineError::UpdateFailed)?;
+    if global_config.path.is_none() {
+        return Ok(());
+    }
+    let now = Instant::now();
+    if global_config
+        .last_poll_time
+        .is_some_and(|last_poll_time| now.duration_since(last_poll_time) < CONFIG_POLL_INTERVAL)
+    {
+        return Ok(());
+    }
+    global_config.last_poll_time = Some(now);
+    drop(global_config);
+    // The file may be in the middle of being saved, the old settings are kept until it can be read
+    if let Err(err) = config_reload() {
+        warn!("Failed to reload the config file: {:?}", err);
+    }
+    Ok(())
+}
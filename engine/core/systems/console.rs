@@ -0,0 +1,233 @@
+use std::collections::BTreeMap;
+
+use crate::{
+    core::{
+        debug::{
+            errors::EngineError, frame_history::frame_history_console_command,
+            telemetry::telemetry_console_command,
+        },
+        globals::{EngineGlobal, EngineGlobalGuard},
+        systems::{
+            config::config_apply,
+            events::{
+                event_register_fn, event_unregister_fn, EventCode, EventContext,
+                EventListenerHandle,
+            },
+            input::keyboard::{input_is_text_input_enabled, input_set_text_input, Key},
+        },
+    },
+    error, info, warn,
+};
+
+/// Called with the words typed after the name of the command
+pub type ConsoleCommandCallback = Box<dyn FnMut(&[&str]) -> Result<(), EngineError> + Send>;
+
+struct ConsoleCommand {
+    help: String,
+    callback: ConsoleCommandCallback,
+}
+
+/// Line of commands typed in game, opened with the grave key
+/// The engine settings are changed with `set <name> <value>', like in the config file
+#[derive(Default)]
+pub(crate) struct ConsoleSystem {
+    /// Sorted by name for the help
+    commands: BTreeMap<String, ConsoleCommand>,
+    pub is_open: bool,
+    /// Line being typed
+    pub input: String,
+    /// Receives the typed characters while the console is open
+    char_listener: Option<EventListenerHandle>,
+    /// Text input of the game before the console opened, restored once it closes
+    was_text_input_enabled: bool,
+}
+
+pub(crate) static GLOBAL_CONSOLE: EngineGlobal<ConsoleSystem> = EngineGlobal::new("console");
+
+fn fetch_global_console(
+    error: EngineError,
+) -> Result<EngineGlobalGuard<ConsoleSystem>, EngineError> {
+    GLOBAL_CONSOLE.fetch(error)
+}
+
+/// Takes the typed characters while the console is open, the game UI receives them otherwise
+fn console_on_char_typed(context: EventContext) -> bool {
+    let character = match context.code {
+        EventCode::CharTyped { character } => character,
+        _ => return false,
+    };
+    let mut console = match fetch_global_console(EngineError::UpdateFailed) {
+        Ok(console) => console,
+        Err(_) => return false,
+    };
+    if !console.is_open {
+        return false;
+    }
+    // The key opening the console also types its character
+    if character != '`' && character != '~' {
+        console.input.push(character);
+    }
+    true
+}
+
+/// Initiate the console with the `set` command of the engine settings
+pub(crate) fn console_init() -> Result<(), EngineError> {
+    let mut console = fetch_global_console(EngineError::InitializationFailed)?;
+    *console = ConsoleSystem::default();
+    console.char_listener = Some(event_register_fn(
+        EventCode::any_char_typed(),
+        console_on_char_typed,
+    )?);
+    drop(console);
+    console_register_command(
+        "set",
+        "set <name> <value>, change an engine setting of the config file",
+        |arguments| match arguments {
+            [name, value] => config_apply(name, value),
+            _ => {
+                error!(
+                    "Expected `set <name> <value>', got {} arguments",
+                    arguments.len()
+                );
+                Err(EngineError::InvalidValue)
+            }
+        },
+    )?;
+    console_register_command(
+        "frame_history",
+        "frame_history <show|hide|dump|enable|disable|select <frames ago>>, browse the last frames",
+        frame_history_console_command,
+    )?;
+    console_register_command(
+        "telemetry",
+        "telemetry <start <path> <csv|json> [frames]|stop>, record the metrics of every frame to a file",
+        telemetry_console_command,
+    )
+}
+
+/// Shutdown the console
+pub(crate) fn console_shutdown() -> Result<(), EngineError> {
+    let mut console = fetch_global_console(EngineError::ShutdownFailed)?;
+    if let Some(handle) = console.char_listener.take() {
+        event_unregister_fn(handle)?;
+    }
+    drop(console);
+    GLOBAL_CONSOLE.reset(EngineError::ShutdownFailed)
+}
+
+/// Add a command to the console, its name is the first word of the line
+pub fn console_register_command<F>(name: &str, help: &str, callback: F) -> Result<(), EngineError>
+where
+    F: FnMut(&[&str]) -> Result<(), EngineError> + Send + 'static,
+{
+    let mut console = fetch_global_console(EngineError::InitializationFailed)?;
+    if name.is_empty() || name.contains(char::is_whitespace) {
+        error!("Invalid console command name `{}'", name);
+        return Err(EngineError::InvalidValue);
+    }
+    if name == "help" || console.commands.contains_key(name) {
+        error!("The console command `{}' already exists", name);
+        return Err(EngineError::Duplicate);
+    }
+    console.commands.insert(
+        name.to_string(),
+        ConsoleCommand {
+            help: help.to_string(),
+            callback: Box::new(callback),
+        },
+    );
+    Ok(())
+}
+
+pub fn console_unregister_command(name: &str) -> Result<(), EngineError> {
+    let mut console = fetch_global_console(EngineError::UpdateFailed)?;
+    if console.commands.remove(name).is_none() {
+        error!("Can't unregister the unknown console command `{}'", name);
+        return Err(EngineError::InvalidValue);
+    }
+    Ok(())
+}
+
+/// Run a line as if it was typed in the console, `help` lists the commands
+pub fn console_execute(line: &str) -> Result<(), EngineError> {
+    let mut console = fetch_global_console(EngineError::UpdateFailed)?;
+    let words: Vec<&str> = line.split_whitespace().collect();
+    let (name, arguments) = match words.split_first() {
+        Some((name, arguments)) => (*name, arguments),
+        None => return Ok(()),
+    };
+    if name == "help" {
+        for (name, command) in &console.commands {
+            info!("{}: {}", name, command.help);
+        }
+        return Ok(());
+    }
+    // Taken out while it runs, the callback may use the console
+    let mut command = match console.commands.remove(name) {
+        Some(command) => command,
+        None => {
+            error!("Unknown console command `{}', see `help'", name);
+            return Err(EngineError::InvalidValue);
+        }
+    };
+    drop(console);
+    let result = (command.callback)(arguments);
+    let mut console = fetch_global_console(EngineError::UpdateFailed)?;
+    console.commands.entry(name.to_string()).or_insert(command);
+    result
+}
+
+/// The typed characters go to the console while it is open
+pub fn console_set_open(is_open: bool) -> Result<(), EngineError> {
+    let mut console = fetch_global_console(EngineError::UpdateFailed)?;
+    console.input.clear();
+    if console.is_open == is_open {
+        return Ok(());
+    }
+    console.is_open = is_open;
+    if is_open {
+        console.was_text_input_enabled = input_is_text_input_enabled()?;
+        input_set_text_input(true)
+    } else {
+        input_set_text_input(console.was_text_input_enabled)
+    }
+}
+
+pub fn console_is_open() -> Result<bool, EngineError> {
+    Ok(fetch_global_console(EngineError::AccessFailed)?.is_open)
+}
+
+/// Keys editing the line while the console is open, returns true if the key was used
+pub(crate) fn console_on_key_pressed(key_code: u16) -> Result<bool, EngineError> {
+    let mut console = fetch_global_console(EngineError::UpdateFailed)?;
+    if !console.is_open {
+        drop(console);
+        if key_code == Key::GRAVE as u16 {
+            console_set_open(true)?;
+            return Ok(true);
+        }
+        return Ok(false);
+    }
+    if key_code == Key::GRAVE as u16 || key_code == Key::ESCAPE as u16 {
+        drop(console);
+        console_set_open(false)?;
+    } else if key_code == Key::BACKSPACE as u16 {
+        console.input.pop();
+    } else if key_code == Key::ENTER as u16 {
+        let line = std::mem::take(&mut console.input);
+        drop(console);
+        info!("> {}", line);
+        // A failing command is reported and the console stays usable
+        if let Err(err) = console_execute(&line) {
+            warn!("The console command `{}' failed: {:?}", line, err);
+        }
+    }
+    // The other keys are not given to the engine shortcuts while typing
+    Ok(true)
+}
+
+/// Line drawn at the bottom of the window, None when the console is closed
+pub(crate) fn console_get_overlay_line() -> Result<Option<String>, EngineError> {
+    let console = fetch_global_console(EngineError::AccessFailed)?;
+    Ok(console.is_open.then(|| format!("> {}_", console.input)))
+}
@@ -1,12 +1,22 @@
-use std::sync::{Arc, Mutex};
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
 
-use once_cell::sync::Lazy;
-
-use crate::{core::debug::errors::EngineError, error, warn};
+use crate::{
+    core::{
+        debug::{
+            errors::EngineError, frame_history::frame_history_record_event,
+            telemetry::telemetry_record_event,
+        },
+        globals::{EngineGlobal, EngineGlobalGuard},
+    },
+    error, warn,
+};
 
 /// System internal event codes
-#[derive(Clone, Copy, Debug)]
-pub(crate) enum EventCode {
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EventCode {
     /// Shuts the application down on the next frame
     ApplicationQuit,
     /// Keyboard key pressed
@@ -23,6 +33,22 @@ pub(crate) enum EventCode {
     MouseWheel { z_delta: i8 },
     /// Resized/resolution changed from the OS
     Resized { width: u32, height: u32 },
+    /// A streamed world chunk finished loading
+    ChunkLoaded { x: i32, z: i32 },
+    /// A streamed world chunk has been unloaded
+    ChunkUnloaded { x: i32, z: i32 },
+    /// An animation playback crossed one of its events
+    AnimationNotify { clip_id: u32, event_id: u32 },
+    /// A sprite animation moved to a new frame
+    SpriteFrameChanged { sprite_id: u32, frame: u32 },
+    /// Character typed while the text input is enabled, see `input_set_text_input`
+    CharTyped { character: char },
+    /// Window minimized or hidden, nothing is updated nor rendered until it is resumed
+    ApplicationSuspended,
+    /// Window shown again after a suspension
+    ApplicationResumed,
+    /// An additional window was closed by the user, closing the main window quits instead
+    WindowClosed { window: u32 },
 }
 
 impl EventCode {
@@ -50,37 +76,192 @@ impl EventCode {
             height: 0,
         }
     }
+    pub fn any_chunk_loaded() -> Self {
+        EventCode::ChunkLoaded { x: 0, z: 0 }
+    }
+    pub fn any_chunk_unloaded() -> Self {
+        EventCode::ChunkUnloaded { x: 0, z: 0 }
+    }
+    pub fn any_animation_notify() -> Self {
+        EventCode::AnimationNotify {
+            clip_id: 0,
+            event_id: 0,
+        }
+    }
+    pub fn any_sprite_frame_changed() -> Self {
+        EventCode::SpriteFrameChanged {
+            sprite_id: 0,
+            frame: 0,
+        }
+    }
+    pub fn any_char_typed() -> Self {
+        EventCode::CharTyped { character: '\0' }
+    }
+    pub fn any_window_closed() -> Self {
+        EventCode::WindowClosed { window: 0 }
+    }
 }
 
-pub(crate) const NUMBER_OF_EVENT_CODES: usize = 8;
+pub(crate) const NUMBER_OF_EVENT_CODES: usize = 16;
+
+/// What the listeners receive when an event is dispatched
+#[derive(Clone, Copy, Debug)]
+pub struct EventContext {
+    /// The fired code with its payload
+    pub code: EventCode,
+    /// Queued with `event_queue` and dispatched at the start of the frame
+    pub is_deferred: bool,
+}
+
+impl EventContext {
+    /// Key of the keyboard events
+    pub fn get_key_code(&self) -> Option<u16> {
+        match self.code {
+            EventCode::KeyPressed { key_code } | EventCode::KeyReleased { key_code } => {
+                Some(key_code)
+            }
+            _ => None,
+        }
+    }
+
+    /// Button of the mouse button events
+    pub fn get_mouse_button(&self) -> Option<u16> {
+        match self.code {
+            EventCode::MouseButtonPressed { button }
+            | EventCode::MouseButtonReleased { button } => Some(button),
+            _ => None,
+        }
+    }
+
+    /// Position of the mouse moved events
+    pub fn get_mouse_position(&self) -> Option<(i16, i16)> {
+        match self.code {
+            EventCode::MouseMoved { x, y } => Some((x, y)),
+            _ => None,
+        }
+    }
+
+    /// Character of the char typed events
+    pub fn get_character(&self) -> Option<char> {
+        match self.code {
+            EventCode::CharTyped { character } => Some(character),
+            _ => None,
+        }
+    }
+
+    /// New size of the resized events
+    pub fn get_size(&self) -> Option<(u32, u32)> {
+        match self.code {
+            EventCode::Resized { width, height } => Some((width, height)),
+            _ => None,
+        }
+    }
+}
 
-pub(crate) trait EventListener {
+/// Listeners can be registered from any thread, so they must be sendable
+pub trait EventListener: Send {
     /// Callback to be called when an event is received
-    /// Return true if don't want any other listener to handle the event
-    fn on_event_callback(&mut self, code: EventCode) -> Result<bool, EngineError>;
+    /// Return true if the event is handled, the next listeners don't receive it
+    fn on_event_callback(&mut self, context: EventContext) -> Result<bool, EngineError>;
+}
+
+/// Listener registered with `event_register_fn`, see `event_unregister_fn`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct EventListenerHandle(u64);
+
+/// Closure called as a listener
+struct EventListenerFn<F: FnMut(EventContext) -> bool + Send>(F);
+
+impl<F: FnMut(EventContext) -> bool + Send> EventListener for EventListenerFn<F> {
+    fn on_event_callback(&mut self, context: EventContext) -> Result<bool, EngineError> {
+        Ok((self.0)(context))
+    }
 }
 
 /// Register to listen for when events are sent with the provided code
-pub(crate) fn event_register(
+/// The listeners are called in the order they registered, the engine ones come first
+/// and only handle their own keys, see `EventListener::on_event_callback`
+pub fn event_register(
     code: EventCode,
     listener: Arc<Mutex<dyn EventListener>>,
 ) -> Result<(), EngineError> {
-    let global_events_system = match fetch_global_events(EngineError::Unknown) {
+    let mut global_events_system = match fetch_global_events(EngineError::Unknown) {
         Ok(events_system) => events_system,
         Err(err) => {
             error!("Failed to register the event");
             return Err(err);
         }
     };
-    global_events_system.event_register(code, listener)
+    global_events_system.event_register(code, listener, false)
 }
 
-/// Register to listen for when events are sent with the provided code
-pub(crate) fn event_unregister(
+/// Register to listen for the events sent with exactly the provided code and payload
+/// e.g. `EventCode::KeyPressed { key_code: Key::SPACE as u16 }` only receives the space key
+pub fn event_register_exact(
+    code: EventCode,
+    listener: Arc<Mutex<dyn EventListener>>,
+) -> Result<(), EngineError> {
+    let mut global_events_system = match fetch_global_events(EngineError::Unknown) {
+        Ok(events_system) => events_system,
+        Err(err) => {
+            error!("Failed to register the exact event");
+            return Err(err);
+        }
+    };
+    global_events_system.event_register(code, listener, true)
+}
+
+/// Register a closure to listen for when events are sent with the provided code
+/// Same as `event_register` without a listener struct, the handle is needed to unregister it
+/// The closure returns true if it handled the event, false to pass it to the next listeners
+pub fn event_register_fn<F>(
+    code: EventCode,
+    callback: F,
+) -> Result<EventListenerHandle, EngineError>
+where
+    F: FnMut(EventContext) -> bool + Send + 'static,
+{
+    let mut global_events_system = match fetch_global_events(EngineError::Unknown) {
+        Ok(events_system) => events_system,
+        Err(err) => {
+            error!("Failed to register the event closure");
+            return Err(err);
+        }
+    };
+    let listener: Arc<Mutex<dyn EventListener>> = Arc::new(Mutex::new(EventListenerFn(callback)));
+    global_events_system.event_register(code, Arc::clone(&listener), false)?;
+    let handle = EventListenerHandle(global_events_system.next_listener_handle);
+    global_events_system.next_listener_handle += 1;
+    global_events_system
+        .listener_handles
+        .insert(handle, (code, listener));
+    Ok(handle)
+}
+
+/// Stop calling a closure registered with `event_register_fn`
+pub fn event_unregister_fn(handle: EventListenerHandle) -> Result<(), EngineError> {
+    let mut global_events_system = match fetch_global_events(EngineError::Unknown) {
+        Ok(events_system) => events_system,
+        Err(err) => {
+            error!("Failed to unregister the event closure");
+            return Err(err);
+        }
+    };
+    match global_events_system.listener_handles.remove(&handle) {
+        Some((code, listener)) => global_events_system.event_unregister(code, listener),
+        None => {
+            warn!("The event closure {:?} is not registered", handle);
+            Err(EngineError::InvalidValue)
+        }
+    }
+}
+
+/// Stop listening for the events sent with the provided code
+pub fn event_unregister(
     code: EventCode,
     listener: Arc<Mutex<dyn EventListener>>,
 ) -> Result<(), EngineError> {
-    let global_events_system = match fetch_global_events(EngineError::Unknown) {
+    let mut global_events_system = match fetch_global_events(EngineError::Unknown) {
         Ok(events_system) => events_system,
         Err(err) => {
             error!("Failed to unregister the event");
@@ -90,20 +271,144 @@ pub(crate) fn event_unregister(
     global_events_system.event_unregister(code, listener)
 }
 
-/// Fires an event to listeners of the given code
-pub(crate) fn event_fire(code: EventCode) -> Result<(), EngineError> {
-    let global_events_system = match fetch_global_events(EngineError::Unknown) {
+/// Fires an event to listeners of the given code right away
+/// The ones fired while the platform handles its events are received once it is done
+pub fn event_fire(code: EventCode) -> Result<(), EngineError> {
+    let listeners = {
+        let mut global_events_system = match fetch_global_events(EngineError::Unknown) {
+            Ok(events_system) => events_system,
+            Err(err) => {
+                error!("Failed to fire the event");
+                return Err(err);
+            }
+        };
+        if let Some(held_events) = global_events_system.held_events.as_mut() {
+            held_events.push(code);
+            return Ok(());
+        }
+        global_events_system.get_listeners(code)
+    };
+    event_record(code)?;
+    events_call_listeners(
+        &listeners,
+        EventContext {
+            code,
+            is_deferred: false,
+        },
+    )
+}
+
+/// Queue an event, the listeners receive it at the start of the next frame
+/// Safe to call from anywhere in the frame, including from a listener
+pub fn event_queue(code: EventCode) -> Result<(), EngineError> {
+    let mut global_events_system = match fetch_global_events(EngineError::Unknown) {
         Ok(events_system) => events_system,
         Err(err) => {
-            error!("Failed to fire the event");
+            error!("Failed to queue the event");
             return Err(err);
         }
     };
-    global_events_system.event_fire(code)
+    if !global_events_system.is_initialized {
+        let err = EngineError::NotInitialized;
+        error!("The events system is not initialized : {:?}", err);
+        return Err(err);
+    }
+    global_events_system.queued_events.push(code);
+    Ok(())
+}
+
+/// Fires the queued events in order, called once per frame after the platform events
+/// The events queued by the listeners meanwhile wait for the next frame
+pub(crate) fn events_dispatch_queued() -> Result<(), EngineError> {
+    let queued_events =
+        std::mem::take(&mut fetch_global_events(EngineError::UpdateFailed)?.queued_events);
+    for code in queued_events {
+        event_record(code)?;
+        let listeners = fetch_global_events(EngineError::UpdateFailed)?.get_listeners(code);
+        if let Err(err) = events_call_listeners(
+            &listeners,
+            EventContext {
+                code,
+                is_deferred: true,
+            },
+        ) {
+            error!("Failed to fire the queued event {:?}: {:?}", code, err);
+            return Err(EngineError::UpdateFailed);
+        }
+    }
+    Ok(())
+}
+
+/// Keep the fired events until `events_release_held`, while the platform handles its events
+/// Their listeners can then use the platform
+pub(crate) fn events_hold() -> Result<(), EngineError> {
+    fetch_global_events(EngineError::UpdateFailed)?
+        .held_events
+        .get_or_insert_with(Vec::new);
+    Ok(())
+}
+
+/// Fires the events held since `events_hold` in order
+pub(crate) fn events_release_held() -> Result<(), EngineError> {
+    let held_events = fetch_global_events(EngineError::UpdateFailed)?
+        .held_events
+        .take()
+        .unwrap_or_default();
+    for code in held_events {
+        if let Err(err) = event_fire(code) {
+            error!("Failed to fire the held event {:?}: {:?}", code, err);
+            return Err(EngineError::UpdateFailed);
+        }
+    }
+    Ok(())
+}
+
+/// Calls the listeners in order until one of them returns true, handling the event
+/// The events system is not held meanwhile, the listeners can fire and register events
+fn events_call_listeners(
+    listeners: &[Arc<Mutex<dyn EventListener>>],
+    context: EventContext,
+) -> Result<(), EngineError> {
+    for listener in listeners {
+        let listener_lock = listener.lock();
+        if let Ok(mut listener) = listener_lock {
+            match listener.on_event_callback(context) {
+                Ok(is_handled) => {
+                    if is_handled {
+                        return Ok(());
+                    }
+                }
+                Err(err) => {
+                    error!("Failed to run the listener callback: {:?}", err);
+                    return Err(err);
+                }
+            }
+            // MutexGuard listener is dropped here, releasing the lock
+        } else {
+            // Handle case where lock cannot be acquired
+            warn!("Failed to acquire lock for listener");
+            return Err(EngineError::Synchronisation);
+        }
+    }
+    Ok(())
+}
+
+fn event_record(code: EventCode) -> Result<(), EngineError> {
+    if let Err(err) = frame_history_record_event(code) {
+        error!("Failed to record the event in the frame history: {:?}", err);
+        return Err(EngineError::Unknown);
+    }
+    if let Err(err) = telemetry_record_event() {
+        error!("Failed to record the event in the telemetry: {:?}", err);
+        return Err(EngineError::Unknown);
+    }
+    Ok(())
 }
 
 pub(crate) struct EventListenerRegistered {
     listener: Arc<Mutex<dyn EventListener>>,
+    /// Only receives the events with this exact code and payload if set
+    exact_code: Option<EventCode>,
 }
 
 impl PartialEq for EventListenerRegistered {
@@ -121,6 +426,13 @@ pub(crate) struct EventSystem {
     pub is_initialized: bool,
     /// Lookup table for event codes
     pub lookup_table: [Vec<EventListenerRegistered>; NUMBER_OF_EVENT_CODES],
+    /// Events waiting for `events_dispatch_queued`
+    pub queued_events: Vec<EventCode>,
+    /// Closures registered with `event_register_fn`
+    pub listener_handles: HashMap<EventListenerHandle, (EventCode, Arc<Mutex<dyn EventListener>>)>,
+    pub next_listener_handle: u64,
+    /// Events fired since `events_hold`, none when they are not held
+    pub held_events: Option<Vec<EventCode>>,
 }
 
 impl EventSystem {
@@ -137,6 +449,20 @@ impl EventSystem {
                 width: _,
                 height: _,
             } => 7,
+            EventCode::ChunkLoaded { x: _, z: _ } => 8,
+            EventCode::ChunkUnloaded { x: _, z: _ } => 9,
+            EventCode::AnimationNotify {
+                clip_id: _,
+                event_id: _,
+            } => 10,
+            EventCode::SpriteFrameChanged {
+                sprite_id: _,
+                frame: _,
+            } => 11,
+            EventCode::CharTyped { character: _ } => 12,
+            EventCode::ApplicationSuspended => 13,
+            EventCode::ApplicationResumed => 14,
+            EventCode::WindowClosed { window: _ } => 15,
         }
     }
 
@@ -145,13 +471,17 @@ impl EventSystem {
         &mut self,
         code: EventCode,
         listener: Arc<Mutex<dyn EventListener>>,
+        is_exact: bool,
     ) -> Result<(), EngineError> {
         if !self.is_initialized {
             let err = EngineError::NotInitialized;
             error!("The events system is not initialized : {:?}", err);
             return Err(err);
         }
-        let listener_to_register = EventListenerRegistered { listener };
+        let listener_to_register = EventListenerRegistered {
+            listener,
+            exact_code: if is_exact { Some(code) } else { None },
+        };
         let registered_listeners =
             &mut self.lookup_table[EventSystem::get_lookup_table_index(code)];
         if !registered_listeners.contains(&listener_to_register) {
@@ -177,7 +507,10 @@ impl EventSystem {
             error!("The events system is not initialized : {:?}", err);
             return Err(err);
         }
-        let listener_to_register = EventListenerRegistered { listener };
+        let listener_to_register = EventListenerRegistered {
+            listener,
+            exact_code: None,
+        };
         let registered_listeners =
             &mut self.lookup_table[EventSystem::get_lookup_table_index(code)];
         registered_listeners
@@ -185,67 +518,38 @@ impl EventSystem {
         Ok(())
     }
 
-    /// Fires an event to listeners of the given code
-    pub fn event_fire(&mut self, code: EventCode) -> Result<(), EngineError> {
-        let registered_listeners =
-            &mut self.lookup_table[EventSystem::get_lookup_table_index(code)];
-        for registered_listener in registered_listeners {
-            let listener_lock = registered_listener.listener.lock();
-            if let Ok(mut listener) = listener_lock {
-                match listener.on_event_callback(code) {
-                    Ok(keep_handling) => {
-                        if !keep_handling {
-                            return Ok(());
-                        }
-                    }
-                    Err(err) => {
-                        error!("Failed to run the listener callback: {:?}", err);
-                        return Err(err);
-                    }
-                }
-                // MutexGuard listener is dropped here, releasing the lock
-            } else {
-                // Handle case where lock cannot be acquired
-                warn!("Failed to acquire lock for listener");
-                return Err(EngineError::Synchronisation);
-            }
-        }
-        Ok(())
+    /// Listeners of the given code, in their registration order
+    pub fn get_listeners(&self, code: EventCode) -> Vec<Arc<Mutex<dyn EventListener>>> {
+        self.lookup_table[EventSystem::get_lookup_table_index(code)]
+            .iter()
+            .filter(|registered_listener| {
+                !registered_listener
+                    .exact_code
+                    .is_some_and(|exact_code| exact_code != code)
+            })
+            .map(|registered_listener| Arc::clone(&registered_listener.listener))
+            .collect()
     }
 }
 
-pub(crate) static mut GLOBAL_EVENTS: Lazy<Mutex<EventSystem>> = Lazy::new(Mutex::default);
+pub(crate) static GLOBAL_EVENTS: EngineGlobal<EventSystem> = EngineGlobal::new("events table");
 
-fn fetch_global_events(error: EngineError) -> Result<&'static mut EventSystem, EngineError> {
-    unsafe {
-        match GLOBAL_EVENTS.get_mut() {
-            Ok(events) => Ok(events),
-            Err(err) => {
-                error!("Failed to fetch the global events table: {:?}", err);
-                Err(error)
-            }
-        }
-    }
+fn fetch_global_events(error: EngineError) -> Result<EngineGlobalGuard<EventSystem>, EngineError> {
+    GLOBAL_EVENTS.fetch(error)
 }
 
 /// Initiate the engine events
 pub(crate) fn events_init() -> Result<(), EngineError> {
-    let global_events = fetch_global_events(EngineError::InitializationFailed)?;
+    let mut global_events = fetch_global_events(EngineError::InitializationFailed)?;
     global_events.lookup_table = Default::default();
+    global_events.queued_events = Vec::new();
+    global_events.listener_handles = HashMap::new();
     global_events.is_initialized = true;
     Ok(())
 }
 
 /// Shutdown the engine events
 pub(crate) fn events_shutdown() -> Result<(), EngineError> {
-    unsafe {
-        if let Ok(mut global_events) = GLOBAL_EVENTS.lock() {
-            for vec in &mut global_events.lookup_table {
-                vec.clear();
-            }
-        }
-        // Empty GLOBAL_EVENTS
-        GLOBAL_EVENTS = Lazy::new(Mutex::default);
-    }
-    Ok(())
+    // The listeners are dropped with the lookup table
+    GLOBAL_EVENTS.reset(EngineError::ShutdownFailed)
 }
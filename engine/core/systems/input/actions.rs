@@ -0,0 +1,298 @@
+use std::{collections::BTreeMap, fmt, fs, path::Path};
+
+use crate::{core::debug::errors::EngineError, error, warn};
+
+use super::{
+    fetch_global_input_state,
+    gamepad::{GamepadAxis, GamepadButton, GamepadInput},
+    keyboard::{Key, KeyState},
+    mouse::{MouseButton, MouseButtonState},
+    InputState,
+};
+
+/// Value above which an action counts as down
+pub const ACTION_PRESS_THRESHOLD: f32 = 0.5;
+
+/// Physical input driving an action
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum InputBinding {
+    Key(Key),
+    MouseButton(MouseButton),
+    /// Wheel steps of the frame
+    MouseWheel,
+    /// Button of any gamepad
+    GamepadButton(GamepadButton),
+    /// Axis of any gamepad, the one pushed the furthest wins
+    GamepadAxis(GamepadAxis),
+}
+
+/// Input with the factor applied to its value, e.g. -1 for the key moving backward
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ActionBinding {
+    pub input: InputBinding,
+    pub scale: f32,
+}
+
+impl ActionBinding {
+    pub fn new(input: InputBinding) -> Self {
+        Self { input, scale: 1.0 }
+    }
+
+    pub fn scale(mut self, scale: f32) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    fn get_gamepad_value(inputs: &[GamepadInput], read: impl Fn(&GamepadInput) -> f32) -> f32 {
+        inputs.iter().map(read).fold(0.0, |best, value| {
+            if value.abs() > best.abs() {
+                value
+            } else {
+                best
+            }
+        })
+    }
+
+    /// Value of the input during the current or the previous frame
+    fn get_value(&self, state: &InputState, is_previous: bool) -> f32 {
+        let (keyboard, mouse) = if is_previous {
+            (&state.keyboard_previous_state, &state.mouse_previous_state)
+        } else {
+            (&state.keyboard_current_state, &state.mouse_current_state)
+        };
+        let gamepads = if is_previous {
+            &state.gamepads.previous_inputs
+        } else {
+            &state.gamepads.current_inputs
+        };
+        let value = match self.input {
+            InputBinding::Key(key) => {
+                (keyboard.keys[key as usize] == KeyState::Pressed) as u8 as f32
+            }
+            InputBinding::MouseButton(button) => {
+                (mouse.buttons[button as usize] == MouseButtonState::Pressed) as u8 as f32
+            }
+            InputBinding::MouseWheel => mouse.wheel_delta as f32,
+            InputBinding::GamepadButton(button) => Self::get_gamepad_value(gamepads, |input| {
+                input.buttons[button as usize] as u8 as f32
+            }),
+            InputBinding::GamepadAxis(axis) => {
+                Self::get_gamepad_value(gamepads, |input| input.axes[axis as usize])
+            }
+        };
+        value * self.scale
+    }
+
+    /// Written as `key:SPACE`, `mouse:Left`, `wheel`, `gamepad:South` or `axis:LeftX`
+    /// followed by `*scale` when the scale is not 1
+    fn parse(text: &str) -> Option<Self> {
+        let (input, scale) = match text.split_once('*') {
+            Some((input, scale)) => (input.trim(), scale.trim().parse().ok()?),
+            None => (text.trim(), 1.0),
+        };
+        let input = match input.split_once(':') {
+            Some(("key", name)) => InputBinding::Key(find_by_name(name, &Key::ALL)?),
+            Some(("mouse", name)) => {
+                InputBinding::MouseButton(find_by_name(name, &MouseButton::ALL)?)
+            }
+            Some(("gamepad", name)) => {
+                InputBinding::GamepadButton(find_by_name(name, &GamepadButton::ALL)?)
+            }
+            Some(("axis", name)) => {
+                InputBinding::GamepadAxis(find_by_name(name, &GamepadAxis::ALL)?)
+            }
+            None if input == "wheel" => InputBinding::MouseWheel,
+            _ => return None,
+        };
+        Some(Self { input, scale })
+    }
+}
+
+/// The bindings files use the names of the enum variants
+fn find_by_name<T: Copy + fmt::Debug>(name: &str, values: &[T]) -> Option<T> {
+    values
+        .iter()
+        .find(|value| format!("{:?}", value) == name)
+        .copied()
+}
+
+impl fmt::Display for ActionBinding {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.input {
+            InputBinding::Key(key) => write!(f, "key:{:?}", key)?,
+            InputBinding::MouseButton(button) => write!(f, "mouse:{:?}", button)?,
+            InputBinding::MouseWheel => write!(f, "wheel")?,
+            InputBinding::GamepadButton(button) => write!(f, "gamepad:{:?}", button)?,
+            InputBinding::GamepadAxis(axis) => write!(f, "axis:{:?}", axis)?,
+        }
+        if self.scale != 1.0 {
+            write!(f, "*{}", self.scale)?;
+        }
+        Ok(())
+    }
+}
+
+/// Named actions of the game and their bindings, sorted by name so the saved files are stable
+#[derive(Default)]
+pub(crate) struct ActionMap {
+    actions: BTreeMap<String, Vec<ActionBinding>>,
+}
+
+impl ActionMap {
+    /// Sum of the bindings clamped to [-1, 1], e.g. two opposite keys cancel each other
+    fn get_value(&self, state: &InputState, name: &str, is_previous: bool) -> Option<f32> {
+        let bindings = self.actions.get(name)?;
+        let value: f32 = bindings
+            .iter()
+            .map(|binding| binding.get_value(state, is_previous))
+            .sum();
+        Some(value.clamp(-1.0, 1.0))
+    }
+
+    fn to_file_content(&self) -> String {
+        let mut content = String::from("# action = binding, binding\n");
+        for (name, bindings) in &self.actions {
+            let bindings: Vec<String> =
+                bindings.iter().map(|binding| binding.to_string()).collect();
+            content.push_str(&format!("{} = {}\n", name, bindings.join(", ")));
+        }
+        content
+    }
+
+    /// Same `name = value` syntax as the config file
+    fn parse(content: &str) -> BTreeMap<String, Vec<ActionBinding>> {
+        let mut actions = BTreeMap::new();
+        for (line_index, line) in content.lines().enumerate() {
+            let line = match line.split_once('#') {
+                Some((line, _comment)) => line,
+                None => line,
+            }
+            .trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (name, bindings) = match line.split_once('=') {
+                Some(assignment) => assignment,
+                None => {
+                    warn!(
+                        "Ignoring the line {} of the bindings file, expected `action = bindings'",
+                        line_index + 1
+                    );
+                    continue;
+                }
+            };
+            let bindings = bindings
+                .split(',')
+                .filter(|binding| !binding.trim().is_empty())
+                .filter_map(|binding| {
+                    let parsed = ActionBinding::parse(binding);
+                    if parsed.is_none() {
+                        warn!(
+                            "Ignoring the unknown binding `{}' at the line {} of the bindings file",
+                            binding.trim(),
+                            line_index + 1
+                        );
+                    }
+                    parsed
+                })
+                .collect();
+            actions.insert(name.trim().to_string(), bindings);
+        }
+        actions
+    }
+}
+
+fn action_get_value_of_frame(name: &str, is_previous: bool) -> Result<f32, EngineError> {
+    let global_state = fetch_global_input_state(EngineError::AccessFailed)?;
+    match global_state
+        .actions
+        .get_value(&global_state, name, is_previous)
+    {
+        Some(value) => Ok(value),
+        None => {
+            error!("Can't read the unknown action `{}'", name);
+            Err(EngineError::InvalidValue)
+        }
+    }
+}
+
+/// Create or rebind the action, e.g. `action_set_bindings("Jump", &[...])`
+pub fn action_set_bindings(name: &str, bindings: &[ActionBinding]) -> Result<(), EngineError> {
+    let mut global_state = fetch_global_input_state(EngineError::UpdateFailed)?;
+    global_state
+        .actions
+        .actions
+        .insert(name.to_string(), bindings.to_vec());
+    Ok(())
+}
+
+/// Add a binding to the action, creating it if needed
+pub fn action_add_binding(name: &str, binding: ActionBinding) -> Result<(), EngineError> {
+    let mut global_state = fetch_global_input_state(EngineError::UpdateFailed)?;
+    global_state
+        .actions
+        .actions
+        .entry(name.to_string())
+        .or_default()
+        .push(binding);
+    Ok(())
+}
+
+pub fn action_get_bindings(name: &str) -> Result<Option<Vec<ActionBinding>>, EngineError> {
+    let global_state = fetch_global_input_state(EngineError::AccessFailed)?;
+    Ok(global_state.actions.actions.get(name).cloned())
+}
+
+pub fn action_remove(name: &str) -> Result<(), EngineError> {
+    let mut global_state = fetch_global_input_state(EngineError::UpdateFailed)?;
+    global_state.actions.actions.remove(name);
+    Ok(())
+}
+
+/// Value of the action this frame in [-1, 1], 0 or 1 for the buttons
+pub fn action_get_value(name: &str) -> Result<f32, EngineError> {
+    action_get_value_of_frame(name, false)
+}
+
+pub fn action_is_down(name: &str) -> Result<bool, EngineError> {
+    Ok(action_get_value(name)?.abs() >= ACTION_PRESS_THRESHOLD)
+}
+
+/// Down this frame but not the previous one
+pub fn action_was_pressed(name: &str) -> Result<bool, EngineError> {
+    let was_down = action_get_value_of_frame(name, true)?.abs() >= ACTION_PRESS_THRESHOLD;
+    Ok(action_is_down(name)? && !was_down)
+}
+
+/// Down the previous frame but not this one
+pub fn action_was_released(name: &str) -> Result<bool, EngineError> {
+    let was_down = action_get_value_of_frame(name, true)?.abs() >= ACTION_PRESS_THRESHOLD;
+    Ok(!action_is_down(name)? && was_down)
+}
+
+/// Write every action with its bindings, one `action = binding, binding` line per action
+pub fn action_save_bindings(path: &Path) -> Result<(), EngineError> {
+    let global_state = fetch_global_input_state(EngineError::AccessFailed)?;
+    if let Err(err) = fs::write(path, global_state.actions.to_file_content()) {
+        error!("Failed to save the action bindings {:?}: {:?}", path, err);
+        return Err(EngineError::IO);
+    }
+    Ok(())
+}
+
+/// Replace the bindings of the actions in the file, the other actions keep theirs
+pub fn action_load_bindings(path: &Path) -> Result<(), EngineError> {
+    let content = match fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) => {
+            error!("Failed to read the action bindings {:?}: {:?}", path, err);
+            return Err(EngineError::IO);
+        }
+    };
+    let mut global_state = fetch_global_input_state(EngineError::UpdateFailed)?;
+    global_state
+        .actions
+        .actions
+        .extend(ActionMap::parse(&content));
+    Ok(())
+}
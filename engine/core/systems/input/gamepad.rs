@@ -0,0 +1,382 @@
+use std::collections::VecDeque;
+
+use crate::core::debug::errors::EngineError;
+
+/// Force feedback request, the motor speeds are in [0, 1]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct GamepadRumble {
+    /// Heavy motor
+    pub low_frequency: f32,
+    /// Light motor
+    pub high_frequency: f32,
+    /// In seconds
+    pub duration: f32,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GamepadButton {
+    /// A on xbox pads, cross on playstation pads
+    South,
+    East,
+    West,
+    North,
+    LeftShoulder,
+    RightShoulder,
+    Select,
+    Start,
+    LeftStick,
+    RightStick,
+    DPadUp,
+    DPadDown,
+    DPadLeft,
+    DPadRight,
+}
+
+pub(crate) const NUMBER_OF_GAMEPAD_BUTTONS: usize = 14;
+
+impl GamepadButton {
+    pub const ALL: [GamepadButton; NUMBER_OF_GAMEPAD_BUTTONS] = [
+        GamepadButton::South,
+        GamepadButton::East,
+        GamepadButton::West,
+        GamepadButton::North,
+        GamepadButton::LeftShoulder,
+        GamepadButton::RightShoulder,
+        GamepadButton::Select,
+        GamepadButton::Start,
+        GamepadButton::LeftStick,
+        GamepadButton::RightStick,
+        GamepadButton::DPadUp,
+        GamepadButton::DPadDown,
+        GamepadButton::DPadLeft,
+        GamepadButton::DPadRight,
+    ];
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GamepadAxis {
+    /// Sticks are in [-1, 1], right and down are positive
+    LeftX,
+    LeftY,
+    RightX,
+    RightY,
+    /// Triggers are in [0, 1]
+    LeftTrigger,
+    RightTrigger,
+}
+
+pub(crate) const NUMBER_OF_GAMEPAD_AXES: usize = 6;
+
+impl GamepadAxis {
+    pub const ALL: [GamepadAxis; NUMBER_OF_GAMEPAD_AXES] = [
+        GamepadAxis::LeftX,
+        GamepadAxis::LeftY,
+        GamepadAxis::RightX,
+        GamepadAxis::RightY,
+        GamepadAxis::LeftTrigger,
+        GamepadAxis::RightTrigger,
+    ];
+}
+
+/// Buttons and raw axes of a gamepad, the dead zones are applied by the game
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) struct GamepadInput {
+    pub buttons: [bool; NUMBER_OF_GAMEPAD_BUTTONS],
+    pub axes: [f32; NUMBER_OF_GAMEPAD_AXES],
+}
+
+/// Platform specific access to the gamepads
+pub(crate) trait GamepadDevices: Send {
+    fn get_gamepad_count(&self) -> usize;
+
+    /// Current state of the gamepad, none if it was unplugged
+    fn read_input(&self, id: usize) -> Option<GamepadInput>;
+
+    /// Returns false if the gamepad can't rumble
+    fn start_rumble(&mut self, id: usize, rumble: &GamepadRumble) -> Result<bool, EngineError>;
+
+    fn stop_rumble(&mut self, id: usize) -> Result<(), EngineError>;
+}
+
+#[derive(Default)]
+pub(crate) struct GamepadState {
+    pub devices: Option<Box<dyn GamepadDevices>>,
+    /// Requests made since the last update
+    pub pending_rumbles: VecDeque<(usize, GamepadRumble)>,
+    /// Gamepads currently rumbling with their remaining time in seconds
+    pub active_rumbles: Vec<(usize, f32)>,
+    /// State of every gamepad read during the last update, by id
+    pub current_inputs: Vec<GamepadInput>,
+    pub previous_inputs: Vec<GamepadInput>,
+}
+
+impl GamepadState {
+    pub fn init(&mut self) {
+        #[cfg(target_os = "linux")]
+        {
+            self.devices = Some(Box::new(linux::EvdevGamepads::new()));
+        }
+    }
+
+    fn stop(&mut self, id: usize) -> Result<(), EngineError> {
+        self.active_rumbles.retain(|(active, _)| *active != id);
+        match &mut self.devices {
+            Some(devices) => devices.stop_rumble(id),
+            None => Ok(()),
+        }
+    }
+
+    pub fn get_current_input(&self, id: usize) -> GamepadInput {
+        self.current_inputs.get(id).copied().unwrap_or_default()
+    }
+
+    pub fn get_previous_input(&self, id: usize) -> GamepadInput {
+        self.previous_inputs.get(id).copied().unwrap_or_default()
+    }
+
+    /// Read the buttons and the axes, start the queued rumbles and stop the finished ones
+    pub fn update(&mut self, delta_time: f64) -> Result<(), EngineError> {
+        self.previous_inputs = std::mem::take(&mut self.current_inputs);
+        if let Some(devices) = &self.devices {
+            self.current_inputs = (0..devices.get_gamepad_count())
+                .map(|id| devices.read_input(id).unwrap_or_default())
+                .collect();
+        }
+
+        let mut finished = Vec::new();
+        for (id, remaining) in self.active_rumbles.iter_mut() {
+            *remaining -= delta_time as f32;
+            if *remaining <= 0.0 {
+                finished.push(*id);
+            }
+        }
+        for id in finished {
+            self.stop(id)?;
+        }
+
+        while let Some((id, rumble)) = self.pending_rumbles.pop_front() {
+            // A new request replaces the current one
+            self.stop(id)?;
+            let devices = match &mut self.devices {
+                Some(devices) => devices,
+                None => continue,
+            };
+            if devices.start_rumble(id, &rumble)? {
+                self.active_rumbles.push((id, rumble.duration));
+            }
+        }
+        Ok(())
+    }
+
+    pub fn shutdown(&mut self) -> Result<(), EngineError> {
+        let active: Vec<usize> = self.active_rumbles.iter().map(|(id, _)| *id).collect();
+        for id in active {
+            self.stop(id)?;
+        }
+        self.devices = None;
+        Ok(())
+    }
+
+    pub fn get_gamepad_count(&self) -> usize {
+        self.devices
+            .as_ref()
+            .map_or(0, |devices| devices.get_gamepad_count())
+    }
+}
+
+#[cfg(target_os = "linux")]
+mod linux {
+    use evdev::{
+        AbsoluteAxisType, Device, FFEffect, FFEffectData, FFEffectKind, FFEffectType, FFReplay,
+        FFTrigger, Key,
+    };
+
+    use crate::{core::debug::errors::EngineError, debug, warn};
+
+    use super::{
+        GamepadAxis, GamepadButton, GamepadDevices, GamepadInput, GamepadRumble,
+        NUMBER_OF_GAMEPAD_AXES,
+    };
+
+    /// Evdev key of each `GamepadButton`, the dpad is also read from the hat axes
+    const EVDEV_GAMEPAD_BUTTONS: [Key; super::NUMBER_OF_GAMEPAD_BUTTONS] = [
+        Key::BTN_SOUTH,
+        Key::BTN_EAST,
+        Key::BTN_WEST,
+        Key::BTN_NORTH,
+        Key::BTN_TL,
+        Key::BTN_TR,
+        Key::BTN_SELECT,
+        Key::BTN_START,
+        Key::BTN_THUMBL,
+        Key::BTN_THUMBR,
+        Key::BTN_DPAD_UP,
+        Key::BTN_DPAD_DOWN,
+        Key::BTN_DPAD_LEFT,
+        Key::BTN_DPAD_RIGHT,
+    ];
+
+    /// Evdev axis of each `GamepadAxis`
+    const EVDEV_GAMEPAD_AXES: [AbsoluteAxisType; NUMBER_OF_GAMEPAD_AXES] = [
+        AbsoluteAxisType::ABS_X,
+        AbsoluteAxisType::ABS_Y,
+        AbsoluteAxisType::ABS_RX,
+        AbsoluteAxisType::ABS_RY,
+        AbsoluteAxisType::ABS_Z,
+        AbsoluteAxisType::ABS_RZ,
+    ];
+
+    struct EvdevGamepad {
+        device: Device,
+        effect: Option<FFEffect>,
+        can_rumble: bool,
+    }
+
+    /// Gamepads read and rumbled through the evdev interfaces
+    pub(super) struct EvdevGamepads {
+        gamepads: Vec<EvdevGamepad>,
+    }
+
+    impl EvdevGamepads {
+        pub fn new() -> Self {
+            // The devices without gamepad buttons are not gamepads, even if they can rumble
+            let mut devices: Vec<_> = evdev::enumerate()
+                .filter(|(_, device)| {
+                    device
+                        .supported_keys()
+                        .is_some_and(|keys| keys.contains(Key::BTN_SOUTH))
+                })
+                .collect();
+            // The id of a gamepad is its index, both for its inputs and its rumble
+            devices.sort_by(|(first, _), (second, _)| first.cmp(second));
+            let gamepads = devices
+                .into_iter()
+                .map(|(path, device)| {
+                    let can_rumble = device
+                        .supported_ff()
+                        .is_some_and(|effects| effects.contains(FFEffectType::FF_RUMBLE));
+                    debug!(
+                        "Found a gamepad: {:?} ({:?}), rumble: {}",
+                        device.name().unwrap_or("unknown"),
+                        path,
+                        can_rumble
+                    );
+                    EvdevGamepad {
+                        device,
+                        effect: None,
+                        can_rumble,
+                    }
+                })
+                .collect();
+            Self { gamepads }
+        }
+    }
+
+    impl GamepadDevices for EvdevGamepads {
+        fn get_gamepad_count(&self) -> usize {
+            self.gamepads.len()
+        }
+
+        fn read_input(&self, id: usize) -> Option<GamepadInput> {
+            let device = &self.gamepads.get(id)?.device;
+            // The device may have been unplugged
+            let keys = device.get_key_state().ok()?;
+            let axes = device.get_abs_state().ok()?;
+            let has_axis = |axis: AbsoluteAxisType| {
+                device
+                    .supported_absolute_axes()
+                    .is_some_and(|axes| axes.contains(axis))
+            };
+
+            let mut input = GamepadInput::default();
+            for (index, key) in EVDEV_GAMEPAD_BUTTONS.iter().enumerate() {
+                input.buttons[index] = keys.contains(*key);
+            }
+            for (index, axis) in EVDEV_GAMEPAD_AXES.iter().enumerate() {
+                if !has_axis(*axis) {
+                    continue;
+                }
+                let info = axes[axis.0 as usize];
+                let range = (info.maximum - info.minimum).max(1) as f32;
+                let normalized = (info.value - info.minimum) as f32 / range;
+                input.axes[index] = match GamepadAxis::ALL[index] {
+                    GamepadAxis::LeftTrigger | GamepadAxis::RightTrigger => normalized,
+                    _ => normalized * 2.0 - 1.0,
+                };
+            }
+            // Most pads report the dpad as a hat instead of buttons
+            let hat_x = axes[AbsoluteAxisType::ABS_HAT0X.0 as usize].value;
+            let hat_y = axes[AbsoluteAxisType::ABS_HAT0Y.0 as usize].value;
+            input.buttons[GamepadButton::DPadLeft as usize] |= hat_x < 0;
+            input.buttons[GamepadButton::DPadRight as usize] |= hat_x > 0;
+            input.buttons[GamepadButton::DPadUp as usize] |= hat_y < 0;
+            input.buttons[GamepadButton::DPadDown as usize] |= hat_y > 0;
+            Some(input)
+        }
+
+        fn start_rumble(&mut self, id: usize, rumble: &GamepadRumble) -> Result<bool, EngineError> {
+            let gamepad = match self.gamepads.get_mut(id) {
+                Some(gamepad) => gamepad,
+                None => {
+                    warn!("Can't rumble the unknown gamepad {}", id);
+                    return Ok(false);
+                }
+            };
+            if !gamepad.can_rumble {
+                return Ok(false);
+            }
+            let to_magnitude = |speed: f32| (speed.clamp(0.0, 1.0) * u16::MAX as f32) as u16;
+            let data = FFEffectData {
+                direction: 0,
+                trigger: FFTrigger::default(),
+                replay: FFReplay {
+                    length: (rumble.duration * 1000.0).clamp(0.0, u16::MAX as f32) as u16,
+                    delay: 0,
+                },
+                kind: FFEffectKind::Rumble {
+                    strong_magnitude: to_magnitude(rumble.low_frequency),
+                    weak_magnitude: to_magnitude(rumble.high_frequency),
+                },
+            };
+            // Dropping the previous effect removes it from the device
+            gamepad.effect = None;
+            let mut effect = match gamepad.device.upload_ff_effect(data) {
+                Ok(effect) => effect,
+                Err(err) => {
+                    // Usually a missing write permission on the device
+                    warn!(
+                        "Failed to upload a rumble effect to the gamepad {}: {:?}",
+                        id, err
+                    );
+                    return Ok(false);
+                }
+            };
+            // The gamepad may have been unplugged, the frame goes on without the rumble
+            if let Err(err) = effect.play(1) {
+                warn!(
+                    "Failed to play a rumble effect on the gamepad {}: {:?}",
+                    id, err
+                );
+                return Ok(false);
+            }
+            gamepad.effect = Some(effect);
+            Ok(true)
+        }
+
+        fn stop_rumble(&mut self, id: usize) -> Result<(), EngineError> {
+            let effect = match self
+                .gamepads
+                .get_mut(id)
+                .and_then(|gamepad| gamepad.effect.as_mut())
+            {
+                Some(effect) => effect,
+                None => return Ok(()),
+            };
+            if let Err(err) = effect.stop() {
+                warn!("Failed to stop the rumble of the gamepad {}: {:?}", id, err);
+            }
+            self.gamepads[id].effect = None;
+            Ok(())
+        }
+    }
+}
@@ -8,7 +8,7 @@ use crate::{
 
 use super::fetch_global_input_state;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Key {
     BACKSPACE = 0x08,
     ENTER = 0x0D,
@@ -157,7 +157,127 @@ impl Default for KeyboardState {
     }
 }
 
+pub(crate) const NUMBER_OF_KEYS: usize = 114;
+
 impl Key {
+    /// Every key, e.g. to find one from its name
+    pub const ALL: [Key; NUMBER_OF_KEYS] = [
+        Key::BACKSPACE,
+        Key::ENTER,
+        Key::TAB,
+        Key::SHIFT,
+        Key::CONTROL,
+        Key::PAUSE,
+        Key::CAPITAL,
+        Key::ESCAPE,
+        Key::CONVERT,
+        Key::NONCONVERT,
+        Key::ACCEPT,
+        Key::MODECHANGE,
+        Key::SPACE,
+        Key::PRIOR,
+        Key::NEXT,
+        Key::END,
+        Key::HOME,
+        Key::LEFT,
+        Key::UP,
+        Key::RIGHT,
+        Key::DOWN,
+        Key::SELECT,
+        Key::PRINT,
+        Key::EXECUTE,
+        Key::SNAPSHOT,
+        Key::INSERT,
+        Key::DELETE,
+        Key::HELP,
+        Key::A,
+        Key::B,
+        Key::C,
+        Key::D,
+        Key::E,
+        Key::F,
+        Key::G,
+        Key::H,
+        Key::I,
+        Key::J,
+        Key::K,
+        Key::L,
+        Key::M,
+        Key::N,
+        Key::O,
+        Key::P,
+        Key::Q,
+        Key::R,
+        Key::S,
+        Key::T,
+        Key::U,
+        Key::V,
+        Key::W,
+        Key::X,
+        Key::Y,
+        Key::Z,
+        Key::LWIN,
+        Key::RWIN,
+        Key::APPS,
+        Key::SLEEP,
+        Key::NUMPAD0,
+        Key::NUMPAD1,
+        Key::NUMPAD2,
+        Key::NUMPAD3,
+        Key::NUMPAD4,
+        Key::NUMPAD5,
+        Key::NUMPAD6,
+        Key::NUMPAD7,
+        Key::NUMPAD8,
+        Key::NUMPAD9,
+        Key::MULTIPLY,
+        Key::ADD,
+        Key::SEPARATOR,
+        Key::SUBTRACT,
+        Key::DECIMAL,
+        Key::DIVIDE,
+        Key::F1,
+        Key::F2,
+        Key::F3,
+        Key::F4,
+        Key::F5,
+        Key::F6,
+        Key::F7,
+        Key::F8,
+        Key::F9,
+        Key::F10,
+        Key::F11,
+        Key::F12,
+        Key::F13,
+        Key::F14,
+        Key::F15,
+        Key::F16,
+        Key::F17,
+        Key::F18,
+        Key::F19,
+        Key::F20,
+        Key::F21,
+        Key::F22,
+        Key::F23,
+        Key::F24,
+        Key::NUMLOCK,
+        Key::SCROLL,
+        Key::NUMPADEQUAL,
+        Key::LSHIFT,
+        Key::RSHIFT,
+        Key::LCONTROL,
+        Key::RCONTROL,
+        Key::LMENU,
+        Key::RMENU,
+        Key::SEMICOLON,
+        Key::PLUS,
+        Key::COMMA,
+        Key::MINUS,
+        Key::PERIOD,
+        Key::SLASH,
+        Key::GRAVE,
+    ];
+
     pub fn get_current_state(&self) -> Result<KeyState, EngineError> {
         let global_state = fetch_global_input_state(EngineError::Unknown)?;
         if !global_state.is_initialized {
@@ -179,7 +299,7 @@ impl Key {
 
 /// Process a key
 pub(crate) fn intput_process_key(key: Key, state: KeyState) -> Result<(), EngineError> {
-    let global_state = fetch_global_input_state(EngineError::Unknown)?;
+    let mut global_state = fetch_global_input_state(EngineError::Unknown)?;
     // handle if the state changed
     if global_state.get_current_key_state(key) != state {
         // update internal state
@@ -194,8 +314,33 @@ pub(crate) fn intput_process_key(key: Key, state: KeyState) -> Result<(), Engine
                 key_code: key as u16,
             },
         };
+        drop(global_state);
         event_fire(code)?;
     }
 
     Ok(())
 }
+
+/// Fire `EventCode::CharTyped` for the key presses producing a character, e.g. for a console
+/// The key events are still fired, the game should ignore the bound keys while typing
+pub fn input_set_text_input(enabled: bool) -> Result<(), EngineError> {
+    let mut global_state = fetch_global_input_state(EngineError::UpdateFailed)?;
+    global_state.is_text_input_enabled = enabled;
+    Ok(())
+}
+
+pub fn input_is_text_input_enabled() -> Result<bool, EngineError> {
+    let global_state = fetch_global_input_state(EngineError::AccessFailed)?;
+    Ok(global_state.is_text_input_enabled)
+}
+
+/// Process a character translated by the platform, with the modifiers already applied
+pub(crate) fn input_process_char(character: char) -> Result<(), EngineError> {
+    let global_state = fetch_global_input_state(EngineError::Unknown)?;
+    // Backspace, enter and the other controls are handled through their keys
+    if !global_state.is_text_input_enabled || character.is_control() {
+        return Ok(());
+    }
+    drop(global_state);
+    event_fire(EventCode::CharTyped { character })
+}
@@ -1,13 +1,20 @@
-use std::sync::Mutex;
-
+use crate::{
+    core::{
+        debug::errors::EngineError,
+        globals::{EngineGlobal, EngineGlobalGuard},
+    },
+    error,
+};
+use actions::ActionMap;
+use gamepad::{GamepadAxis, GamepadButton, GamepadRumble, GamepadState};
 use keyboard::{Key, KeyState, KeyboardState};
 use mouse::{MouseButton, MouseButtonState, MouseState};
-use once_cell::sync::Lazy;
-
-use crate::{core::debug::errors::EngineError, error};
 
-pub mod keyboard;
-pub mod mouse;
+pub(crate) mod actions;
+pub(crate) mod gamepad;
+pub(crate) mod keyboard;
+pub(crate) mod mouse;
+pub(crate) mod smoothing;
 
 #[derive(Default)]
 pub(crate) struct InputState {
@@ -16,6 +23,11 @@ pub(crate) struct InputState {
     pub keyboard_previous_state: KeyboardState,
     pub mouse_current_state: MouseState,
     pub mouse_previous_state: MouseState,
+    pub gamepads: GamepadState,
+    /// The platform translates the key presses to characters
+    pub is_text_input_enabled: bool,
+    /// Named actions of the game bound to the inputs above
+    pub actions: ActionMap,
 }
 
 impl InputState {
@@ -80,38 +92,56 @@ impl InputState {
 
 /// Initiate the engine input subsystem
 pub(crate) fn input_init() -> Result<(), EngineError> {
-    let global_state = fetch_global_input_state(EngineError::InitializationFailed)?;
+    let mut global_state = fetch_global_input_state(EngineError::InitializationFailed)?;
+    global_state.gamepads.init();
     global_state.is_initialized = true;
     Ok(())
 }
 
 /// Shutdown the engine input subsystem
 pub(crate) fn input_shutdown() -> Result<(), EngineError> {
-    unsafe { GLOBAL_INPUT_STATE = Lazy::new(Mutex::default) };
+    let mut global_state = fetch_global_input_state(EngineError::ShutdownFailed)?;
+    if let Err(err) = global_state.gamepads.shutdown() {
+        error!("Failed to shutdown the gamepads: {:?}", err);
+        return Err(EngineError::ShutdownFailed);
+    }
+    drop(global_state);
+    GLOBAL_INPUT_STATE.reset(EngineError::ShutdownFailed)?;
     Ok(())
 }
 
 /// Update the engine input subsystem
-pub(crate) fn input_update(_delta_time: f64) -> Result<(), EngineError> {
-    let global_state = fetch_global_input_state(EngineError::Unknown)?;
+pub(crate) fn input_update(delta_time: f64) -> Result<(), EngineError> {
+    let mut global_state = fetch_global_input_state(EngineError::Unknown)?;
+    if let Err(err) = global_state.gamepads.update(delta_time) {
+        error!("Failed to update the gamepads: {:?}", err);
+        return Err(EngineError::UpdateFailed);
+    }
     // copy current states to previous states
     global_state.keyboard_previous_state = global_state.keyboard_current_state;
     global_state.mouse_previous_state = global_state.mouse_current_state;
+    // The wheel steps and the motion are counted per frame
+    global_state.mouse_current_state.wheel_delta = 0;
+    global_state.mouse_current_state.delta_x = 0;
+    global_state.mouse_current_state.delta_y = 0;
     Ok(())
 }
 
-pub(crate) static mut GLOBAL_INPUT_STATE: Lazy<Mutex<InputState>> = Lazy::new(Mutex::default);
+pub(crate) static GLOBAL_INPUT_STATE: EngineGlobal<InputState> = EngineGlobal::new("input state");
 
-fn fetch_global_input_state(error: EngineError) -> Result<&'static mut InputState, EngineError> {
-    unsafe {
-        match GLOBAL_INPUT_STATE.get_mut() {
-            Ok(state) => Ok(state),
-            Err(err) => {
-                error!("Failed to fetch the global input state: {:?}", err);
-                Err(error)
-            }
-        }
-    }
+fn fetch_global_input_state(
+    error: EngineError,
+) -> Result<EngineGlobalGuard<InputState>, EngineError> {
+    GLOBAL_INPUT_STATE.fetch(error)
+}
+
+/// Copy of the current keyboard and mouse states
+pub(crate) fn input_get_snapshot() -> Result<(KeyboardState, MouseState), EngineError> {
+    let global_state = fetch_global_input_state(EngineError::AccessFailed)?;
+    Ok((
+        global_state.keyboard_current_state,
+        global_state.mouse_current_state,
+    ))
 }
 
 pub fn input_is_key_up(key: Key) -> Result<bool, EngineError> {
@@ -133,3 +163,50 @@ pub fn input_was_key_down(key: Key) -> Result<bool, EngineError> {
     let global_state = fetch_global_input_state(EngineError::AccessFailed)?;
     Ok(global_state.get_previous_key_state(key) == KeyState::Pressed)
 }
+
+/// Number of gamepads found when the input subsystem started
+pub fn gamepad_get_count() -> Result<usize, EngineError> {
+    let global_state = fetch_global_input_state(EngineError::AccessFailed)?;
+    Ok(global_state.gamepads.get_gamepad_count())
+}
+
+/// Queue a rumble started on the next input update, motor speeds are in [0, 1]
+/// Does nothing on gamepads without force feedback
+pub fn gamepad_rumble(
+    id: usize,
+    low_frequency: f32,
+    high_frequency: f32,
+    duration: f32,
+) -> Result<(), EngineError> {
+    let mut global_state = fetch_global_input_state(EngineError::UpdateFailed)?;
+    global_state.gamepads.pending_rumbles.push_back((
+        id,
+        GamepadRumble {
+            low_frequency,
+            high_frequency,
+            duration,
+        },
+    ));
+    Ok(())
+}
+
+pub fn gamepad_is_button_down(id: usize, button: GamepadButton) -> Result<bool, EngineError> {
+    let global_state = fetch_global_input_state(EngineError::AccessFailed)?;
+    Ok(global_state.gamepads.get_current_input(id).buttons[button as usize])
+}
+
+pub fn gamepad_was_button_down(id: usize, button: GamepadButton) -> Result<bool, EngineError> {
+    let global_state = fetch_global_input_state(EngineError::AccessFailed)?;
+    Ok(global_state.gamepads.get_previous_input(id).buttons[button as usize])
+}
+
+/// Sticks are in [-1, 1] with y pointing down, triggers in [0, 1]
+/// Reads 0 for a disconnected gamepad
+pub fn gamepad_get_axis(id: usize, axis: GamepadAxis) -> Result<f32, EngineError> {
+    let global_state = fetch_global_input_state(EngineError::AccessFailed)?;
+    Ok(global_state.gamepads.get_current_input(id).axes[axis as usize])
+}
+
+pub fn gamepad_stop_rumble(id: usize) -> Result<(), EngineError> {
+    gamepad_rumble(id, 0.0, 0.0, 0.0)
+}
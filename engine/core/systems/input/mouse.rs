@@ -8,7 +8,7 @@ use crate::{
 
 use super::fetch_global_input_state;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum MouseButton {
     Left,
     Right,
@@ -17,11 +17,32 @@ pub enum MouseButton {
 
 const NUMBER_OF_MOUSE_BUTTONS: usize = 3;
 
+impl MouseButton {
+    pub const ALL: [MouseButton; NUMBER_OF_MOUSE_BUTTONS] =
+        [MouseButton::Left, MouseButton::Right, MouseButton::Middle];
+}
+
+/// How the cursor behaves over the window
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum CursorMode {
+    #[default]
+    Normal,
+    /// Invisible over the window but free to leave it
+    Hidden,
+    /// Invisible and kept inside the window, only the mouse delta is meaningful
+    Grabbed,
+}
+
 #[derive(Clone, Copy)]
 pub(crate) struct MouseState {
     pub x: i16,
     pub y: i16,
     pub buttons: [MouseButtonState; NUMBER_OF_MOUSE_BUTTONS],
+    /// Wheel steps since the last input update, positive when scrolling up
+    pub wheel_delta: i16,
+    /// Mouse motion since the last input update, in pixels
+    pub delta_x: i32,
+    pub delta_y: i32,
 }
 
 impl Default for MouseState {
@@ -30,6 +51,9 @@ impl Default for MouseState {
             x: 0,
             y: 0,
             buttons: [MouseButtonState::Released; NUMBER_OF_MOUSE_BUTTONS],
+            wheel_delta: 0,
+            delta_x: 0,
+            delta_y: 0,
         }
     }
 }
@@ -82,12 +106,35 @@ pub fn intput_get_mouse_previous_position() -> Result<(i16, i16), EngineError> {
     Ok(global_state.get_previous_mouse_position())
 }
 
+/// Wheel steps since the last frame, positive when scrolling up
+pub fn input_get_mouse_wheel_delta() -> Result<i16, EngineError> {
+    let global_state = fetch_global_input_state(EngineError::Unknown)?;
+    if !global_state.is_initialized {
+        error!("Failed to get the mouse wheel delta:\nthe global input state is not initialized");
+        return Err(EngineError::NotInitialized);
+    }
+    Ok(global_state.mouse_current_state.wheel_delta)
+}
+
+/// Mouse motion since the last frame in pixels, also valid when the cursor is grabbed
+pub fn input_get_mouse_delta() -> Result<(i32, i32), EngineError> {
+    let global_state = fetch_global_input_state(EngineError::Unknown)?;
+    if !global_state.is_initialized {
+        error!("Failed to get the mouse delta:\nthe global input state is not initialized");
+        return Err(EngineError::NotInitialized);
+    }
+    Ok((
+        global_state.mouse_current_state.delta_x,
+        global_state.mouse_current_state.delta_y,
+    ))
+}
+
 /// Process a mouse
 pub(crate) fn input_process_mouse_button(
     button: MouseButton,
     state: MouseButtonState,
 ) -> Result<(), EngineError> {
-    let global_state = fetch_global_input_state(EngineError::Unknown)?;
+    let mut global_state = fetch_global_input_state(EngineError::Unknown)?;
     // handle if the state changed
     if global_state.get_current_mouse_button_state(button) != state {
         // update internal state
@@ -102,6 +149,7 @@ pub(crate) fn input_process_mouse_button(
                 button: button as u16,
             },
         };
+        drop(global_state);
         event_fire(code)?;
     }
 
@@ -109,12 +157,16 @@ pub(crate) fn input_process_mouse_button(
 }
 
 pub(crate) fn input_process_mouse_move(x: i16, y: i16) -> Result<(), EngineError> {
-    let global_state = fetch_global_input_state(EngineError::Unknown)?;
+    let mut global_state = fetch_global_input_state(EngineError::Unknown)?;
     // handle if the state changed
-    if global_state.get_current_mouse_position() != (x, y) {
+    let (previous_x, previous_y) = global_state.get_current_mouse_position();
+    if (previous_x, previous_y) != (x, y) {
         // update internal state
+        global_state.mouse_current_state.delta_x += x as i32 - previous_x as i32;
+        global_state.mouse_current_state.delta_y += y as i32 - previous_y as i32;
         global_state.set_current_mouse_position(x, y);
 
+        drop(global_state);
         // fire an event
         event_fire(EventCode::MouseMoved { x, y })?;
     }
@@ -122,7 +174,23 @@ pub(crate) fn input_process_mouse_move(x: i16, y: i16) -> Result<(), EngineError
     Ok(())
 }
 
+/// Move the cursor without any motion, used when the platform warps it
+pub(crate) fn input_process_mouse_warp(x: i16, y: i16) -> Result<(), EngineError> {
+    let mut global_state = fetch_global_input_state(EngineError::Unknown)?;
+    global_state.set_current_mouse_position(x, y);
+    Ok(())
+}
+
+/// Process a wheel step, positive when scrolling up
 pub(crate) fn input_process_mouse_wheel(z_delta: i8) -> Result<(), EngineError> {
+    let mut global_state = fetch_global_input_state(EngineError::Unknown)?;
+    // update internal state
+    global_state.mouse_current_state.wheel_delta = global_state
+        .mouse_current_state
+        .wheel_delta
+        .saturating_add(z_delta as i16);
+
+    drop(global_state);
     // fire an event
     event_fire(EventCode::MouseWheel { z_delta })?;
     Ok(())
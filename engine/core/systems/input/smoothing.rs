@@ -0,0 +1,115 @@
+//! Helpers shaping the raw inputs before a game uses them as axes
+//! They keep no global state, a controller owns one per axis it reads
+
+/// Value following its target with a frame rate independent exponential smoothing
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SmoothedAxis {
+    pub value: f32,
+    /// Seconds to cover 63% of the distance to the target, 0 disables the smoothing
+    pub time_constant: f32,
+}
+
+impl SmoothedAxis {
+    pub fn new(time_constant: f32) -> Self {
+        Self {
+            value: 0.0,
+            time_constant,
+        }
+    }
+
+    /// Move the value toward the target and return it
+    pub fn update(&mut self, target: f32, delta_time: f64) -> f32 {
+        if self.time_constant <= 0.0 {
+            self.value = target;
+            return self.value;
+        }
+        let factor = 1.0 - (-(delta_time as f32) / self.time_constant).exp();
+        self.value += (target - self.value) * factor;
+        self.value
+    }
+
+    /// Jump to the value, after a teleport or when the input device changes
+    pub fn reset(&mut self, value: f32) {
+        self.value = value;
+    }
+}
+
+/// Dead zone and response curve of an analog axis in [-1, 1]
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AxisResponse {
+    /// Magnitude below which the axis reads 0, hides the stick drift
+    pub inner_dead_zone: f32,
+    /// Magnitude above which the axis reads 1, the sticks rarely reach their edges
+    pub outer_dead_zone: f32,
+    /// 1 is linear, above 1 gives more precision around the center
+    pub exponent: f32,
+}
+
+impl Default for AxisResponse {
+    fn default() -> Self {
+        Self {
+            inner_dead_zone: 0.15,
+            outer_dead_zone: 0.95,
+            exponent: 2.0,
+        }
+    }
+}
+
+impl AxisResponse {
+    /// Remaps the magnitude between the dead zones to [0, 1] then applies the curve
+    pub fn apply(&self, value: f32) -> f32 {
+        value.signum() * self.shape_magnitude(value.abs())
+    }
+
+    /// Radial version for a stick, the direction is kept and the corners aren't faster
+    pub fn apply_2d(&self, x: f32, y: f32) -> (f32, f32) {
+        let magnitude = (x * x + y * y).sqrt();
+        if magnitude <= f32::EPSILON {
+            return (0.0, 0.0);
+        }
+        let scale = self.shape_magnitude(magnitude) / magnitude;
+        (x * scale, y * scale)
+    }
+
+    fn shape_magnitude(&self, magnitude: f32) -> f32 {
+        let range = (self.outer_dead_zone - self.inner_dead_zone).max(f32::EPSILON);
+        let normalized = ((magnitude - self.inner_dead_zone) / range).clamp(0.0, 1.0);
+        normalized.powf(self.exponent.max(f32::EPSILON))
+    }
+}
+
+/// Mouse look curve, fast motions turn further than slow ones over the same distance
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MouseAcceleration {
+    /// Radians per pixel at low speed
+    pub sensitivity: f32,
+    /// Extra gain per pixel per second of mouse speed, 0 is a linear mouse
+    pub acceleration: f32,
+    /// Upper bound of the gain, relative to the sensitivity
+    pub max_gain: f32,
+}
+
+impl Default for MouseAcceleration {
+    fn default() -> Self {
+        Self {
+            sensitivity: 0.002,
+            acceleration: 0.0,
+            max_gain: 4.0,
+        }
+    }
+}
+
+impl MouseAcceleration {
+    /// Turns the mouse delta of the frame into angles, see `input_get_mouse_delta`
+    pub fn apply(&self, delta_x: i32, delta_y: i32, delta_time: f64) -> (f32, f32) {
+        let (delta_x, delta_y) = (delta_x as f32, delta_y as f32);
+        let mut gain = 1.0;
+        if self.acceleration > 0.0 && delta_time > 0.0 {
+            // The speed is what makes the curve independent of the frame rate
+            let speed = (delta_x * delta_x + delta_y * delta_y).sqrt() / delta_time as f32;
+            gain = (1.0 + speed * self.acceleration).min(self.max_gain.max(1.0));
+        }
+        let scale = self.sensitivity * gain;
+        (delta_x * scale, delta_y * scale)
+    }
+}
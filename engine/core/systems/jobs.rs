@@ -0,0 +1,223 @@
+use std::{
+    any::Any,
+    collections::HashMap,
+    num::NonZeroUsize,
+    panic::{catch_unwind, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver, Sender},
+        Arc, Mutex,
+    },
+    thread::{self, JoinHandle},
+};
+
+use crate::{
+    core::{
+        debug::errors::EngineError,
+        globals::{EngineGlobal, EngineGlobalGuard},
+    },
+    error,
+};
+
+type JobResult = Result<Box<dyn Any + Send>, EngineError>;
+
+/// Run on a worker thread
+type JobWork = Box<dyn FnOnce() -> JobResult + Send>;
+
+/// Run on the main thread with the result of the work
+type JobCompletion = Box<dyn FnOnce(JobResult) -> Result<(), EngineError> + Send>;
+
+/// Identifies a submitted job, see `job_is_pending`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct JobHandle(u64);
+
+/// Pool of worker threads running the jobs in the order they are submitted
+/// The completions are run on the main thread by `jobs_run_completions`, once per frame
+#[derive(Default)]
+pub(crate) struct JobSystem {
+    work_sender: Option<Sender<(JobHandle, JobWork)>>,
+    result_receiver: Option<Receiver<(JobHandle, JobResult)>>,
+    workers: Vec<JoinHandle<()>>,
+    /// The queued jobs are skipped once set
+    is_shutting_down: Arc<AtomicBool>,
+    /// Completions of the jobs not done yet
+    completions: HashMap<JobHandle, JobCompletion>,
+    next_job_id: u64,
+}
+
+impl JobSystem {
+    fn submit(
+        &mut self,
+        work: JobWork,
+        completion: JobCompletion,
+    ) -> Result<JobHandle, EngineError> {
+        let work_sender = match &self.work_sender {
+            Some(sender) => sender,
+            None => {
+                error!("Can't submit a job before initializing the job system");
+                return Err(EngineError::NotInitialized);
+            }
+        };
+        let handle = JobHandle(self.next_job_id);
+        self.next_job_id += 1;
+        if let Err(err) = work_sender.send((handle, work)) {
+            error!("Failed to send a job to the workers: {:?}", err);
+            return Err(EngineError::Synchronisation);
+        }
+        self.completions.insert(handle, completion);
+        Ok(handle)
+    }
+
+    /// Completions of the finished jobs with their results
+    fn take_finished_jobs(&mut self) -> Vec<(JobCompletion, JobResult)> {
+        let result_receiver = match &self.result_receiver {
+            Some(receiver) => receiver,
+            None => return Vec::new(),
+        };
+        result_receiver
+            .try_iter()
+            .filter_map(|(handle, result)| {
+                self.completions
+                    .remove(&handle)
+                    .map(|completion| (completion, result))
+            })
+            .collect()
+    }
+}
+
+fn job_worker_run(
+    work_receiver: Arc<Mutex<Receiver<(JobHandle, JobWork)>>>,
+    result_sender: Sender<(JobHandle, JobResult)>,
+    is_shutting_down: Arc<AtomicBool>,
+) {
+    loop {
+        // The lock is only held while waiting for the next job
+        let job = match work_receiver.lock() {
+            Ok(receiver) => receiver.recv(),
+            Err(err) => {
+                error!("Failed to lock the job queue of a worker: {:?}", err);
+                return;
+            }
+        };
+        // The queue is closed when shutting down
+        let (handle, work) = match job {
+            Ok(job) => job,
+            Err(_) => return,
+        };
+        if is_shutting_down.load(Ordering::Relaxed) {
+            continue;
+        }
+        let result = match catch_unwind(AssertUnwindSafe(work)) {
+            Ok(result) => result,
+            Err(_) => {
+                error!("A job panicked on a worker thread");
+                Err(EngineError::Unknown)
+            }
+        };
+        // The job system may be gone in the meantime
+        if result_sender.send((handle, result)).is_err() {
+            return;
+        }
+    }
+}
+
+pub(crate) static GLOBAL_JOBS: EngineGlobal<JobSystem> = EngineGlobal::new("job system");
+
+fn fetch_global_jobs(error: EngineError) -> Result<EngineGlobalGuard<JobSystem>, EngineError> {
+    GLOBAL_JOBS.fetch(error)
+}
+
+/// Start a worker per core, the main thread keeps its own
+pub(crate) fn jobs_init() -> Result<(), EngineError> {
+    let mut global_jobs = fetch_global_jobs(EngineError::InitializationFailed)?;
+    let worker_count = thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(2)
+        .saturating_sub(1)
+        .max(1);
+
+    let (work_sender, work_receiver) = channel();
+    let (result_sender, result_receiver) = channel();
+    let work_receiver = Arc::new(Mutex::new(work_receiver));
+    let is_shutting_down = Arc::new(AtomicBool::new(false));
+    let mut workers = Vec::with_capacity(worker_count);
+    for index in 0..worker_count {
+        let work_receiver = Arc::clone(&work_receiver);
+        let result_sender = result_sender.clone();
+        let is_shutting_down = Arc::clone(&is_shutting_down);
+        let worker = thread::Builder::new()
+            .name(format!("job worker {}", index))
+            .spawn(move || job_worker_run(work_receiver, result_sender, is_shutting_down));
+        match worker {
+            Ok(worker) => workers.push(worker),
+            Err(err) => {
+                error!("Failed to spawn the job worker {}: {:?}", index, err);
+                return Err(EngineError::InitializationFailed);
+            }
+        }
+    }
+
+    global_jobs.work_sender = Some(work_sender);
+    global_jobs.result_receiver = Some(result_receiver);
+    global_jobs.workers = workers;
+    global_jobs.is_shutting_down = is_shutting_down;
+    Ok(())
+}
+
+/// The queued jobs are dropped and the running ones are waited for, no completion is run
+pub(crate) fn jobs_shutdown() -> Result<(), EngineError> {
+    let mut global_jobs = fetch_global_jobs(EngineError::ShutdownFailed)?;
+    global_jobs.is_shutting_down.store(true, Ordering::Relaxed);
+    // Closing the queue stops the workers
+    global_jobs.work_sender = None;
+    // The running jobs may use the job system until they finish
+    let workers = std::mem::take(&mut global_jobs.workers);
+    drop(global_jobs);
+    for worker in workers {
+        if worker.join().is_err() {
+            error!("Failed to join a job worker");
+        }
+    }
+    GLOBAL_JOBS.reset(EngineError::ShutdownFailed)?;
+    Ok(())
+}
+
+/// Run the work on a worker thread and its completion on the main thread with the result
+/// The completion is run even if the work fails, it gets the error instead
+pub fn job_submit<T, W, C>(work: W, completion: C) -> Result<JobHandle, EngineError>
+where
+    T: Send + 'static,
+    W: FnOnce() -> Result<T, EngineError> + Send + 'static,
+    C: FnOnce(Result<T, EngineError>) -> Result<(), EngineError> + Send + 'static,
+{
+    let mut global_jobs = fetch_global_jobs(EngineError::InitializationFailed)?;
+    let work: JobWork =
+        Box::new(move || work().map(|value| Box::new(value) as Box<dyn Any + Send>));
+    let completion: JobCompletion = Box::new(move |result: JobResult| {
+        let result = result.map(|value| match value.downcast::<T>() {
+            Ok(value) => *value,
+            Err(_) => unreachable!("A job result always has the type of its work"),
+        });
+        completion(result)
+    });
+    global_jobs.submit(work, completion)
+}
+
+/// Whether the completion of the job has not been run yet
+pub fn job_is_pending(handle: JobHandle) -> Result<bool, EngineError> {
+    let global_jobs = fetch_global_jobs(EngineError::AccessFailed)?;
+    Ok(global_jobs.completions.contains_key(&handle))
+}
+
+/// Run the completions of the finished jobs, called once per frame on the main thread
+/// A failing completion doesn't prevent the others from running
+pub(crate) fn jobs_run_completions() -> Result<(), EngineError> {
+    // The completions can submit new jobs so the job system is not borrowed while running them
+    let finished_jobs = fetch_global_jobs(EngineError::UpdateFailed)?.take_finished_jobs();
+    for (completion, result) in finished_jobs {
+        if let Err(err) = completion(result) {
+            error!("Failed to complete a job: {:?}", err);
+        }
+    }
+    Ok(())
+}
@@ -1,13 +1,25 @@
-use std::{fs::File, io::Write, path::PathBuf, sync::Mutex};
+use std::{
+    collections::VecDeque,
+    fs::File,
+    io::Write,
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicU8, Ordering},
+        Mutex, MutexGuard, PoisonError,
+    },
+};
 
 use once_cell::sync::Lazy;
 
-use crate::{
-    core::debug::errors::EngineError,
-    platforms::{platform::Platform, platform_linux::PlatformLinux},
-};
+use crate::{core::debug::errors::EngineError, platforms::platform::Platform};
+
+#[cfg(target_os = "linux")]
+use crate::platforms::platform_linux::PlatformLinux;
+#[cfg(target_os = "macos")]
+use crate::platforms::platform_macos::PlatformMacOS;
 
 /// The log levels for the application
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum LogLevel {
     /// Fatal errors resulting in a panic
     Error,
@@ -30,6 +42,31 @@ impl LogLevel {
             LogLevel::Info => false,
         }
     }
+
+    /// Higher for the less important messages
+    fn get_verbosity(&self) -> u8 {
+        match self {
+            LogLevel::Error => 0,
+            LogLevel::Warning => 1,
+            LogLevel::Info => 2,
+            LogLevel::Debug => 3,
+        }
+    }
+}
+
+/// Verbosity of the least important messages logged, everything is logged by default
+/// Atomic since it is read before any log
+static LOGGER_MAX_VERBOSITY: AtomicU8 = AtomicU8::new(u8::MAX);
+
+/// Only log the messages at least as important as the given level
+/// Errors are always logged
+pub fn logger_set_level(level: LogLevel) {
+    LOGGER_MAX_VERBOSITY.store(level.get_verbosity(), Ordering::Relaxed);
+}
+
+/// Whether the messages of this level are logged, see `logger_set_level`
+pub fn logger_is_level_enabled(level: &LogLevel) -> bool {
+    level.get_verbosity() <= LOGGER_MAX_VERBOSITY.load(Ordering::Relaxed)
 }
 
 impl std::fmt::Display for LogLevel {
@@ -50,7 +87,12 @@ pub fn print_console() -> fn(&str, LogLevel) {
         PlatformLinux::console_write
     }
 
-    #[cfg(not(any(target_os = "linux")))]
+    #[cfg(target_os = "macos")]
+    {
+        PlatformMacOS::console_write
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
     {
         Platform::console_write
     }
@@ -63,7 +105,12 @@ pub fn print_console_error() -> fn(&str, LogLevel) {
         PlatformLinux::console_write_error
     }
 
-    #[cfg(not(any(target_os = "linux")))]
+    #[cfg(target_os = "macos")]
+    {
+        PlatformMacOS::console_write_error
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
     {
         Platform::console_write_error
     }
@@ -74,25 +121,29 @@ pub fn print_console_error() -> fn(&str, LogLevel) {
 #[macro_export]
 macro_rules! log {
     ($level:expr) => {
-        if $level.is_an_error() {
-            let msg = format!("[{}] ({}:{})\n", $level, file!(), line!());
-            $crate::core::systems::logger::print_console_error()(&msg, $level);
-            $crate::core::systems::logger::append_to_log_file(&msg);
-        } else {
-            let msg = format!("[{}] ({}:{})\n", $level, file!(), line!());
-            $crate::core::systems::logger::print_console()(&msg, $level);
-            $crate::core::systems::logger::append_to_log_file(&msg);
+        if $crate::__logger::logger_is_level_enabled(&$level) {
+            if $level.is_an_error() {
+                let msg = format!("[{}] ({}:{})\n", $level, file!(), line!());
+                $crate::__logger::print_console_error()(&msg, $level);
+                $crate::__logger::append_to_log_file(&msg);
+            } else {
+                let msg = format!("[{}] ({}:{})\n", $level, file!(), line!());
+                $crate::__logger::print_console()(&msg, $level);
+                $crate::__logger::append_to_log_file(&msg);
+            }
         }
     };
     ($level:expr, $($arg:tt)*) => {
-        if $level.is_an_error() {
-            let msg = format!("[{}] ({}:{}) {}\n", $level, file!(), line!(), format!($($arg)*));
-            $crate::core::systems::logger::print_console_error()(&msg, $level);
-            $crate::core::systems::logger::append_to_log_file(&msg);
-        } else {
-            let msg = format!("[{}] ({}:{}) {}\n", $level, file!(), line!(), format!($($arg)*));
-            $crate::core::systems::logger::print_console()(&msg, $level);
-            $crate::core::systems::logger::append_to_log_file(&msg);
+        if $crate::__logger::logger_is_level_enabled(&$level) {
+            if $level.is_an_error() {
+                let msg = format!("[{}] ({}:{}) {}\n", $level, file!(), line!(), format!($($arg)*));
+                $crate::__logger::print_console_error()(&msg, $level);
+                $crate::__logger::append_to_log_file(&msg);
+            } else {
+                let msg = format!("[{}] ({}:{}) {}\n", $level, file!(), line!(), format!($($arg)*));
+                $crate::__logger::print_console()(&msg, $level);
+                $crate::__logger::append_to_log_file(&msg);
+            }
         }
     };
 }
@@ -101,25 +152,29 @@ macro_rules! log {
 #[macro_export]
 macro_rules! log_no_details {
     ($level:expr) => {
-        if $level.is_an_error() {
-            let msg = format!("[{}]\n", $level);
-            $crate::core::systems::logger::print_console_error()(&msg, $level);
-            $crate::core::systems::logger::append_to_log_file(&msg);
-        } else {
-            let msg = format!("[{}]\n", $level);
-            $crate::core::systems::logger::print_console()(&msg, $level);
-            $crate::core::systems::logger::append_to_log_file(&msg);
+        if $crate::__logger::logger_is_level_enabled(&$level) {
+            if $level.is_an_error() {
+                let msg = format!("[{}]\n", $level);
+                $crate::__logger::print_console_error()(&msg, $level);
+                $crate::__logger::append_to_log_file(&msg);
+            } else {
+                let msg = format!("[{}]\n", $level);
+                $crate::__logger::print_console()(&msg, $level);
+                $crate::__logger::append_to_log_file(&msg);
+            }
         }
     };
     ($level:expr, $($arg:tt)*) => {
-        if $level.is_an_error() {
-            let msg = format!("[{}] {}\n", $level, format!($($arg)*));
-            $crate::core::systems::logger::print_console_error()(&msg, $level);
-            $crate::core::systems::logger::append_to_log_file(&msg);
-        } else {
-            let msg = format!("[{}] {}\n", $level, format!($($arg)*));
-            $crate::core::systems::logger::print_console()(&msg, $level);
-            $crate::core::systems::logger::append_to_log_file(&msg);
+        if $crate::__logger::logger_is_level_enabled(&$level) {
+            if $level.is_an_error() {
+                let msg = format!("[{}] {}\n", $level, format!($($arg)*));
+                $crate::__logger::print_console_error()(&msg, $level);
+                $crate::__logger::append_to_log_file(&msg);
+            } else {
+                let msg = format!("[{}] {}\n", $level, format!($($arg)*));
+                $crate::__logger::print_console()(&msg, $level);
+                $crate::__logger::append_to_log_file(&msg);
+            }
         }
     };
 }
@@ -127,107 +182,110 @@ macro_rules! log_no_details {
 #[macro_export]
 macro_rules! error {
     () => {
-        $crate::log!($crate::core::systems::logger::LogLevel::Error);
+        $crate::log!($crate::__logger::LogLevel::Error);
     };
     ($($arg:tt)*) => {{
-        $crate::log!($crate::core::systems::logger::LogLevel::Error, $($arg)*);
+        $crate::log!($crate::__logger::LogLevel::Error, $($arg)*);
     }};
 }
 
 #[macro_export]
 macro_rules! warn {
     () => {
-        $crate::log!($crate::core::systems::logger::LogLevel::Warning)
+        $crate::log!($crate::__logger::LogLevel::Warning)
     };
     ($($arg:tt)*) => {{
-        $crate::log!($crate::core::systems::logger::LogLevel::Warning, $($arg)*)
+        $crate::log!($crate::__logger::LogLevel::Warning, $($arg)*)
     }};
 }
 
 #[macro_export]
 macro_rules! debug {
     () => {
-        $crate::log!($crate::core::systems::logger::LogLevel::Debug)
+        $crate::log!($crate::__logger::LogLevel::Debug)
     };
     ($($arg:tt)*) => {{
-        $crate::log!($crate::core::systems::logger::LogLevel::Debug, $($arg)*)
+        $crate::log!($crate::__logger::LogLevel::Debug, $($arg)*)
     }};
 }
 
 #[macro_export]
 macro_rules! info {
     () => {
-        $crate::log!($crate::core::systems::logger::LogLevel::Info)
+        $crate::log!($crate::__logger::LogLevel::Info)
     };
     ($($arg:tt)*) => {{
-        $crate::log!($crate::core::systems::logger::LogLevel::Info, $($arg)*)
+        $crate::log!($crate::__logger::LogLevel::Info, $($arg)*)
     }};
 }
 
 #[macro_export]
 macro_rules! error_no_details {
     () => {
-        $crate::log_no_details!($crate::core::systems::logger::LogLevel::Error);
+        $crate::log_no_details!($crate::__logger::LogLevel::Error);
     };
     ($($arg:tt)*) => {{
-        $crate::log_no_details!($crate::core::systems::logger::LogLevel::Error, $($arg)*);
+        $crate::log_no_details!($crate::__logger::LogLevel::Error, $($arg)*);
     }};
 }
 
 #[macro_export]
 macro_rules! warn_no_details {
     () => {
-        $crate::log_no_details!($crate::core::systems::logger::LogLevel::Warning)
+        $crate::log_no_details!($crate::__logger::LogLevel::Warning)
     };
     ($($arg:tt)*) => {{
-        $crate::log_no_details!($crate::core::systems::logger::LogLevel::Warning, $($arg)*)
+        $crate::log_no_details!($crate::__logger::LogLevel::Warning, $($arg)*)
     }};
 }
 
 #[macro_export]
 macro_rules! debug_no_details {
     () => {
-        $crate::log_no_details!($crate::core::systems::logger::LogLevel::Debug)
+        $crate::log_no_details!($crate::__logger::LogLevel::Debug)
     };
     ($($arg:tt)*) => {{
-        $crate::log_no_details!($crate::core::systems::logger::LogLevel::Debug, $($arg)*)
+        $crate::log_no_details!($crate::__logger::LogLevel::Debug, $($arg)*)
     }};
 }
 
 #[macro_export]
 macro_rules! info_no_details {
     () => {
-        $crate::log_no_details!($crate::core::systems::logger::LogLevel::Info)
+        $crate::log_no_details!($crate::__logger::LogLevel::Info)
     };
     ($($arg:tt)*) => {{
-        $crate::log_no_details!($crate::core::systems::logger::LogLevel::Info, $($arg)*)
+        $crate::log_no_details!($crate::__logger::LogLevel::Info, $($arg)*)
     }};
 }
 
+/// Number of messages kept in memory for the debug console
+const LOGGER_RECENT_MESSAGE_COUNT: usize = 256;
+
 #[derive(Default)]
 pub(crate) struct Logger {
     pub log_file_path: Option<PathBuf>,
+    /// Last logged messages, the oldest first
+    pub recent_messages: VecDeque<String>,
 }
 
-pub(crate) static mut GLOBAL_LOGGER: Lazy<Mutex<Logger>> = Lazy::new(Mutex::default);
+/// Locked for real since the job workers and the decoding threads log too
+/// No message must be logged while the lock is held
+pub(crate) static GLOBAL_LOGGER: Lazy<Mutex<Logger>> = Lazy::new(Mutex::default);
 
-pub(crate) fn fetch_global_logger(error: EngineError) -> Result<&'static mut Logger, EngineError> {
-    unsafe {
-        match GLOBAL_LOGGER.get_mut() {
-            Ok(logger) => Ok(logger),
-            Err(err) => {
-                error!("Failed to fetch the global logger: {:?}", err);
-                Err(error)
-            }
-        }
-    }
+/// A panic while logging must not prevent the next messages from being logged
+fn lock_global_logger() -> MutexGuard<'static, Logger> {
+    GLOBAL_LOGGER.lock().unwrap_or_else(PoisonError::into_inner)
 }
 
 pub fn append_to_log_file(msg: &String) {
-    let global_logger = match fetch_global_logger(EngineError::InitializationFailed) {
-        Ok(logger) => logger,
-        Err(_) => panic!("Failed to fetch the global logger!"),
-    };
+    let mut global_logger = lock_global_logger();
+    if global_logger.recent_messages.len() >= LOGGER_RECENT_MESSAGE_COUNT {
+        global_logger.recent_messages.pop_front();
+    }
+    global_logger
+        .recent_messages
+        .push_back(msg.trim_end().to_string());
     if let Some(path) = &global_logger.log_file_path {
         // append to log file
         let mut file = match File::options().append(true).open(path) {
@@ -248,25 +306,56 @@ pub fn append_to_log_file(msg: &String) {
     }
 }
 
+/// Make sure everything logged so far reached the disk
+/// Used before aborting on fatal errors
+pub fn logger_flush() -> Result<(), EngineError> {
+    let log_file_path = lock_global_logger().log_file_path.clone();
+    if let Some(path) = &log_file_path {
+        let file = match File::options().append(true).open(path) {
+            Ok(file) => file,
+            Err(err) => {
+                error!("Failed to open the log file {:?}: {:?}", path, err);
+                return Err(EngineError::IO);
+            }
+        };
+        if let Err(err) = file.sync_all() {
+            error!("Failed to flush the log file {:?}: {:?}", path, err);
+            return Err(EngineError::IO);
+        }
+    }
+    Ok(())
+}
+
+/// Last logged messages, the oldest first
+pub(crate) fn logger_get_recent_messages(count: usize) -> Result<Vec<String>, EngineError> {
+    let global_logger = lock_global_logger();
+    let skipped = global_logger.recent_messages.len().saturating_sub(count);
+    Ok(global_logger
+        .recent_messages
+        .iter()
+        .skip(skipped)
+        .cloned()
+        .collect())
+}
+
 /// Initiate the engine logger
 pub(crate) fn logger_init() -> Result<(), EngineError> {
-    let global_logger = fetch_global_logger(EngineError::InitializationFailed)?;
     let crate_path = env!("CARGO_MANIFEST_DIR");
     let logger_file_name = "console.log";
     // Create a PathBuf to handle the file path
     let logger_file: PathBuf = [crate_path, logger_file_name].iter().collect();
-    global_logger.log_file_path = Some(logger_file.clone());
 
     // clear file
     if let Err(err) = File::create(&logger_file) {
         error!("Failed to initialize the logger: {:?}", err);
         return Err(EngineError::InitializationFailed);
     }
+    lock_global_logger().log_file_path = Some(logger_file);
     Ok(())
 }
 
 /// Shutdown the engine logger
 pub(crate) fn logger_shutdown() -> Result<(), EngineError> {
-    unsafe { GLOBAL_LOGGER = Lazy::new(Mutex::default) };
+    *lock_global_logger() = Logger::default();
     Ok(())
 }
@@ -1,70 +1,123 @@
-use super::debug::errors::EngineError;
-use crate::{debug, error};
+use super::{
+    debug::{
+        errors::EngineError,
+        frame_history::{frame_history_init, frame_history_shutdown},
+        input_latency::{input_latency_init, input_latency_shutdown},
+        profiler::{profiler_init, profiler_shutdown},
+        telemetry::{telemetry_init, telemetry_shutdown},
+    },
+    ecs::{ecs_init, ecs_shutdown},
+    initializer::InitializerRegistry,
+};
+use crate::error;
 
-pub mod events;
-pub mod input;
+pub(crate) mod audio;
+pub(crate) mod config;
+pub(crate) mod console;
+pub(crate) mod events;
+pub(crate) mod input;
+pub(crate) mod jobs;
+// Public for the logging macros, see `__logger`
 pub mod logger;
 
+/// Subsystems and the ones they need, the initialization order is computed from the dependencies
+fn subsystems_initializers() -> InitializerRegistry<()> {
+    InitializerRegistry::default()
+        .register(
+            "logger system",
+            &[],
+            |_, _| logger::logger_init(),
+            |_| logger::logger_shutdown(),
+        )
+        .register(
+            "events system",
+            &["logger system"],
+            |_, _| events::events_init(),
+            |_| events::events_shutdown(),
+        )
+        .register(
+            "frame history",
+            &["logger system"],
+            |_, _| frame_history_init(),
+            |_| frame_history_shutdown(),
+        )
+        .register(
+            "profiler",
+            &["logger system", "frame history"],
+            |_, _| profiler_init(),
+            |_| profiler_shutdown(),
+        )
+        .register(
+            "telemetry",
+            &["logger system", "frame history"],
+            |_, _| telemetry_init(),
+            |_| telemetry_shutdown(),
+        )
+        .register(
+            "input latency",
+            &["logger system"],
+            |_, _| input_latency_init(),
+            |_| input_latency_shutdown(),
+        )
+        .register(
+            "config system",
+            &["logger system", "profiler"],
+            |_, _| config::config_init(),
+            |_| config::config_shutdown(),
+        )
+        .register(
+            "console",
+            &[
+                "logger system",
+                "events system",
+                "input system",
+                "config system",
+            ],
+            |_, _| console::console_init(),
+            |_| console::console_shutdown(),
+        )
+        .register(
+            "job system",
+            &["logger system"],
+            |_, _| jobs::jobs_init(),
+            |_| jobs::jobs_shutdown(),
+        )
+        .register(
+            "input system",
+            &["logger system", "events system"],
+            |_, _| input::input_init(),
+            |_| input::input_shutdown(),
+        )
+        .register(
+            "ecs",
+            &["logger system"],
+            |_, _| ecs_init(),
+            |_| ecs_shutdown(),
+        )
+        .register(
+            "audio system",
+            &["logger system"],
+            |_, _| audio::audio_init(),
+            |_| audio::audio_shutdown(),
+        )
+}
+
 /// Initialize the different subsystems
+/// On failure, the already initialized ones are shut down
 pub(crate) fn subsystems_init() -> Result<(), EngineError> {
-    match logger::logger_init() {
-        Ok(()) => (),
-        Err(err) => {
-            error!("Failed to initialize the logger system: {:?}", err);
-            return Err(EngineError::InitializationFailed);
-        }
-    }
-    debug!("Logger subsystem initialized");
-
-    match events::events_init() {
-        Ok(()) => (),
-        Err(err) => {
-            error!("Failed to initialize the events system: {:?}", err);
-            return Err(EngineError::InitializationFailed);
-        }
-    }
-    debug!("Events subsystem initialized");
-
-    match input::input_init() {
-        Ok(()) => (),
-        Err(err) => {
-            error!("Failed to initialize the input system: {:?}", err);
-            return Err(EngineError::InitializationFailed);
-        }
+    if let Err(err) = subsystems_initializers().init(&mut (), &()) {
+        error!("Failed to initialize the subsystems: {:?}", err);
+        return Err(EngineError::InitializationFailed);
     }
-    debug!("Input subsystem initialized");
-
     Ok(())
 }
 
-/// Shutdown the different subsystems
+/// Shutdown the different subsystems, in the reverse order of their initialization
+/// A failing subsystem doesn't prevent the others from shutting down
 pub(crate) fn subsystems_shutdown() -> Result<(), EngineError> {
-    match input::input_shutdown() {
-        Ok(()) => (),
-        Err(err) => {
-            error!("Failed to shutdown the input system: {:?}", err);
-            return Err(EngineError::ShutdownFailed);
-        }
-    }
-    debug!("Input subsystem shutted down");
-
-    match events::events_shutdown() {
-        Ok(()) => (),
-        Err(err) => {
-            error!("Failed to shutdown the events system: {:?}", err);
-            return Err(EngineError::ShutdownFailed);
-        }
+    if let Err(err) = subsystems_initializers().shutdown(&mut ()) {
+        error!("Failed to shutdown the subsystems: {:?}", err);
+        return Err(EngineError::ShutdownFailed);
     }
-    debug!("Events subsystem shutted down");
-
-    match logger::logger_shutdown() {
-        Ok(()) => (),
-        Err(err) => {
-            error!("Failed to shutdown the logger system: {:?}", err);
-            return Err(EngineError::ShutdownFailed);
-        }
-    }
-    debug!("Logger subsystem shutted down");
-
     Ok(())
 }
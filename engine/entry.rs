@@ -1,29 +1,30 @@
 use crate::{
     core::{
         application::{
-            application_init, application_shutdown, fetch_global_application, ApplicationParameters,
+            application_init, application_run, application_shutdown, fetch_global_platform,
+            ApplicationParameters,
+        },
+        debug::{errors::EngineError, telemetry::telemetry_start},
+        lifecycle::{fetch_global_lifecycle, EngineStage},
+        systems::{
+            config::{config_load, config_read_launch_settings},
+            subsystems_init, subsystems_shutdown,
         },
-        debug::errors::EngineError,
-        systems::{subsystems_init, subsystems_shutdown},
     },
     debug, error,
     game::Game,
-    renderer::renderer_frontend::{renderer_init, renderer_shutdown},
+    renderer::renderer_frontend::{
+        renderer_init, renderer_set_accessibility_settings, renderer_set_vsync_mode,
+        renderer_shutdown,
+    },
 };
 
-/// Static variable to allow only a single instantiation of the engine
-static mut IS_ENGINE_INITIALIZED: bool = false;
-
 /// Initiatlize the engine
-/// Can only be called once
-fn engine_init(parameters: ApplicationParameters, game: Box<dyn Game>) -> Result<(), EngineError> {
+/// Can only be called once, the stages initialized before a failure are still recorded
+fn engine_init(parameters: ApplicationParameters) -> Result<(), EngineError> {
     // Initialization
-    if unsafe { IS_ENGINE_INITIALIZED } {
-        error!("The engine is already initialized!");
-        return Err(EngineError::MultipleInstantiation);
-    }
-
-    let app_name = parameters.application_name.clone();
+    let mut lifecycle = fetch_global_lifecycle(EngineError::InitializationFailed)?;
+    lifecycle.begin_init()?;
 
     match subsystems_init() {
         Ok(()) => (),
@@ -32,34 +33,88 @@ fn engine_init(parameters: ApplicationParameters, game: Box<dyn Game>) -> Result
             return Err(EngineError::InitializationFailed);
         }
     }
+    lifecycle.set_stage_initialized(EngineStage::Subsystems)?;
     debug!("Subsystems initialized");
 
-    if let Err(err) = application_init(parameters, game) {
+    let mut parameters = parameters;
+    let config_path = parameters.config_path.clone();
+    if let Some(path) = &config_path {
+        if let Err(err) = config_read_launch_settings(path, &mut parameters) {
+            error!(
+                "Failed to read the launch settings of the config file: {:?}",
+                err
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+    }
+
+    let app_name = parameters.application_name.clone();
+    let accessibility = parameters.accessibility;
+    let anti_aliasing = parameters.anti_aliasing;
+    let display_output = parameters.display_output;
+    let depth_stencil = parameters.depth_stencil.clone();
+    let renderer_backend = parameters.renderer_backend;
+    let vsync = parameters.vsync;
+    let telemetry_capture = parameters.telemetry_capture.clone();
+
+    if let Err(err) = application_init(parameters) {
         error!("Failed to create the application: {:?}", err);
         return Err(EngineError::InitializationFailed);
     };
+    lifecycle.set_stage_initialized(EngineStage::Application)?;
     debug!("Application initialized");
 
-    let platform = fetch_global_application()?.platform.as_ref();
-
-    match renderer_init(&app_name.clone(), platform) {
+    let global_platform = fetch_global_platform()?;
+    match renderer_init(
+        renderer_backend,
+        &app_name.clone(),
+        global_platform.get()?,
+        anti_aliasing,
+        display_output,
+        &depth_stencil,
+    ) {
         Ok(()) => (),
         Err(err) => {
             error!("Failed to initialize the renderer: {:?}", err);
             return Err(EngineError::InitializationFailed);
         }
     }
+    drop(global_platform);
+    lifecycle.set_stage_initialized(EngineStage::Renderer)?;
     debug!("Renderer initialized");
 
-    unsafe { IS_ENGINE_INITIALIZED = true };
+    if let Err(err) = renderer_set_accessibility_settings(accessibility) {
+        error!("Failed to apply the accessibility settings: {:?}", err);
+        return Err(EngineError::InitializationFailed);
+    }
 
-    Ok(())
+    if let Err(err) = renderer_set_vsync_mode(vsync) {
+        error!("Failed to apply the vsync setting: {:?}", err);
+        return Err(EngineError::InitializationFailed);
+    }
+
+    // The file settings override the parameters applied above
+    if let Some(path) = config_path {
+        if let Err(err) = config_load(path) {
+            error!("Failed to load the config file: {:?}", err);
+            return Err(EngineError::InitializationFailed);
+        }
+    }
+
+    if let Some(capture) = telemetry_capture {
+        if let Err(err) = telemetry_start(&capture.path, capture.format, Some(capture.frame_count))
+        {
+            error!("Failed to start the startup telemetry capture: {:?}", err);
+            return Err(EngineError::InitializationFailed);
+        }
+    }
+
+    lifecycle.end_init()
 }
 
 /// Main loop
-fn game_loop() -> Result<(), EngineError> {
-    let application = fetch_global_application()?;
-    match application.run() {
+fn game_loop(game: &mut dyn Game) -> Result<(), EngineError> {
+    match application_run(game) {
         Ok(()) => Ok(()),
         Err(err) => {
             error!("The application failed to run: {:?}", err);
@@ -68,50 +123,50 @@ fn game_loop() -> Result<(), EngineError> {
     }
 }
 
-/// Cleanup the engine
-fn engine_shutdown() -> Result<(), EngineError> {
-    match renderer_shutdown() {
-        Ok(()) => (),
-        Err(err) => {
-            error!("Failed to shutdown the renderer: {:?}", err);
-            return Err(EngineError::ShutdownFailed);
-        }
+fn engine_shutdown_stage(stage: EngineStage) -> Result<(), EngineError> {
+    match stage {
+        EngineStage::Renderer => renderer_shutdown(),
+        EngineStage::Application => application_shutdown(),
+        EngineStage::Subsystems => subsystems_shutdown(),
     }
-    debug!("Renderer shutted down");
+}
 
-    if let Err(err) = application_shutdown() {
-        error!("Failed to shutdown the application: {:?}", err);
-        return Err(EngineError::ShutdownFailed);
+/// Cleanup the initialized parts of the engine, in the reverse order of their initialization
+/// Every stage is shut down even if a previous one failed, calling it again does nothing
+fn engine_shutdown() -> Result<(), EngineError> {
+    let mut lifecycle = fetch_global_lifecycle(EngineError::ShutdownFailed)?;
+    let stages = match lifecycle.begin_shutdown() {
+        Some(stages) => stages,
+        None => {
+            debug!("The engine is not running, nothing to shut down");
+            return Ok(());
+        }
     };
-    debug!("Application shutted down");
 
-    match subsystems_shutdown() {
-        Ok(()) => (),
-        Err(err) => {
-            error!("Failed to shutdown the subsystems: {:?}", err);
-            return Err(EngineError::ShutdownFailed);
+    let mut is_complete = true;
+    for stage in stages {
+        match engine_shutdown_stage(stage) {
+            Ok(()) => debug!("{:?} shutted down", stage),
+            Err(err) => {
+                error!("Failed to shutdown the {:?}: {:?}", stage, err);
+                is_complete = false;
+            }
         }
+        lifecycle.set_stage_shut_down(stage);
     }
-    debug!("Subsystems shutted down");
+    lifecycle.end_shutdown();
 
+    if !is_complete {
+        return Err(EngineError::ShutdownFailed);
+    }
     Ok(())
 }
 
-/// Entry point of the game engine
-pub fn engine_start(
-    parameters: ApplicationParameters,
-    game: Box<dyn Game>,
-) -> Result<(), EngineError> {
-    // Initialization
-    if let Err(err) = engine_init(parameters, game) {
-        error!("Failed to initialize the engine: {:?}", err);
-        return Err(EngineError::InitializationFailed);
-    };
-    debug!("Engine initialized");
-
+/// Run the game from its start to its shutdown
+/// The engine owns the game, it is only lent to the application while it runs
+fn engine_run(game: &mut dyn Game) -> Result<(), EngineError> {
     // game on start
-    let application = fetch_global_application()?;
-    if let Err(err) = application.game.on_start() {
+    if let Err(err) = game.on_start() {
         error!(
             "Failed to call the `on_start' method of the game: {:?}",
             err
@@ -119,17 +174,50 @@ pub fn engine_start(
         return Err(EngineError::InitializationFailed);
     }
 
-    // Game loop
-    game_loop()?;
+    // Game loop, the game is shut down even if it fails
+    let result = game_loop(game);
 
     // game on shutdown
-    let application = fetch_global_application()?;
-    if let Err(err) = application.game.on_shutdown() {
+    if let Err(err) = game.on_shutdown() {
         error!(
             "Failed to call the `on_shutdown' method of the game: {:?}",
             err
         );
-        return Err(EngineError::InitializationFailed);
+        return Err(EngineError::ShutdownFailed);
+    }
+    result
+}
+
+/// Entry point of the game engine
+pub fn engine_start(
+    parameters: ApplicationParameters,
+    mut game: Box<dyn Game>,
+) -> Result<(), EngineError> {
+    // Initialization
+    match engine_init(parameters) {
+        Ok(()) => (),
+        // The running engine must not be shut down
+        Err(EngineError::MultipleInstantiation) => {
+            error!("Failed to initialize the engine, it can only be started once");
+            return Err(EngineError::MultipleInstantiation);
+        }
+        Err(err) => {
+            error!("Failed to initialize the engine: {:?}", err);
+            // Cleanup the stages initialized before the failure
+            if let Err(err) = engine_shutdown() {
+                error!(
+                    "Failed to shutdown the partially initialized engine: {:?}",
+                    err
+                );
+            }
+            return Err(EngineError::InitializationFailed);
+        }
+    };
+    debug!("Engine initialized");
+
+    let result = engine_run(game.as_mut());
+    if let Err(err) = &result {
+        error!("The engine failed to run the game: {:?}", err);
     }
 
     // Cleanup
@@ -140,5 +228,5 @@ pub fn engine_start(
 
     debug!("Engine shutted down");
 
-    Ok(())
+    result
 }
@@ -3,27 +3,44 @@ use crate::core::debug::errors::EngineError;
 /// Game state
 /// Called by the application
 pub trait Game {
-    /// Initializer
+    /// Initializer, called once the subsystems, the application and the renderer are up
+    /// The assets can be loaded through the renderer from here
     fn on_start(&mut self) -> Result<(), EngineError> {
         Ok(())
     }
 
+    /// Simulation step, called at the fixed rate of the application parameters
+    /// Zero or several times per frame, before `on_update`, so the physics and gameplay are deterministic
+    fn on_fixed_update(&mut self, fixed_delta_time: f64) -> Result<(), EngineError> {
+        Ok(())
+    }
+
     /// Update
     fn on_update(&mut self, delta_time: f64) -> Result<(), EngineError> {
         Ok(())
     }
 
     /// Render
-    fn on_render(&self, delta_time: f64) -> Result<(), EngineError> {
+    /// Interpolation is the fraction of a fixed step elapsed since the last `on_fixed_update`, in [0, 1)
+    /// Blending the previous and current simulation states with it keeps the motion smooth
+    fn on_render(&self, delta_time: f64, interpolation: f64) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    /// Render while the application is loading, instead of updating and rendering the game
+    /// Progress is the fraction of the loading jobs done, in [0, 1]
+    fn on_render_loading(&self, delta_time: f64, progress: f32) -> Result<(), EngineError> {
         Ok(())
     }
 
-    /// Resize
+    /// Resize, called once the renderer is resized
+    /// Not called while the window is minimized
     fn on_resize(&mut self, new_width: u32, new_height: u32) -> Result<(), EngineError> {
         Ok(())
     }
 
-    /// Shutdown
+    /// Shutdown, called after the last frame even if the game loop failed
+    /// The renderer is still up so its resources can be released
     fn on_shutdown(&mut self) -> Result<(), EngineError> {
         Ok(())
     }
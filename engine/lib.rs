@@ -1,8 +1,13 @@
 #![allow(dead_code, unused_variables, unused_attributes)]
 
-pub mod core;
-pub mod entry;
-pub mod game;
-pub mod platforms;
-pub mod renderer;
-pub mod resources;
+pub(crate) mod core;
+pub(crate) mod entry;
+pub(crate) mod game;
+pub(crate) mod platforms;
+pub mod prelude;
+pub(crate) mod renderer;
+pub(crate) mod resources;
+
+/// Reached by the logging macros expanded in the games, not part of the api
+#[doc(hidden)]
+pub use crate::core::systems::logger as __logger;
@@ -0,0 +1,37 @@
+use crate::core::debug::errors::EngineError;
+
+/// Fills the interleaved samples of the device, called from the audio thread of the platform
+pub(crate) type AudioOutputCallback = Box<dyn FnMut(&mut [f32]) + Send>;
+
+/// Sound device of the platform, the callback is called until it is shut down
+pub(crate) trait AudioOutput: Send {
+    /// Stop the device, the callback is never called again once it returns
+    fn shutdown(&mut self) -> Result<(), EngineError>;
+}
+
+/// Open the default sound device of the OS with float samples
+pub(crate) fn audio_output_init(
+    sample_rate: u32,
+    channel_count: usize,
+    callback: AudioOutputCallback,
+) -> Result<Box<dyn AudioOutput>, EngineError> {
+    #[cfg(target_os = "linux")]
+    {
+        let output = super::audio_output_linux::AudioOutputLinux::init(
+            sample_rate,
+            channel_count,
+            callback,
+        )?;
+        Ok(Box::new(output))
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let output = super::audio_output_macos::AudioOutputMacOS::init(
+            sample_rate,
+            channel_count,
+            callback,
+        )?;
+        Ok(Box::new(output))
+    }
+}
@@ -0,0 +1,244 @@
+/// Linux implementation of the audio output, through alsa
+/// libasound is loaded at runtime, the engine still starts without sound on the machines missing it
+use std::{
+    ffi::{c_char, c_int, c_long, c_uint, c_ulong, c_void, CStr},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread::JoinHandle,
+};
+
+use crate::{core::debug::errors::EngineError, error};
+
+use super::audio_output::{AudioOutput, AudioOutputCallback};
+
+const ALSA_LIBRARY_NAME: &CStr = c"libasound.so.2";
+const ALSA_DEVICE_NAME: &CStr = c"default";
+
+const SND_PCM_STREAM_PLAYBACK: c_int = 0;
+const SND_PCM_ACCESS_RW_INTERLEAVED: c_int = 3;
+#[cfg(target_endian = "little")]
+const SND_PCM_FORMAT_FLOAT: c_int = 14;
+#[cfg(target_endian = "big")]
+const SND_PCM_FORMAT_FLOAT: c_int = 15;
+
+/// Latency asked to the device, the mixer runs this far ahead of the speakers
+const ALSA_LATENCY_MICROSECONDS: c_uint = 40_000;
+/// Frames mixed before each write
+const ALSA_PERIOD_FRAMES: usize = 512;
+
+/// Opaque `snd_pcm_t`
+type SndPcm = c_void;
+
+/// Functions of libasound used by the output
+struct AlsaLibrary {
+    handle: *mut c_void,
+    snd_pcm_open: unsafe extern "C" fn(*mut *mut SndPcm, *const c_char, c_int, c_int) -> c_int,
+    snd_pcm_set_params:
+        unsafe extern "C" fn(*mut SndPcm, c_int, c_int, c_uint, c_uint, c_int, c_uint) -> c_int,
+    snd_pcm_writei: unsafe extern "C" fn(*mut SndPcm, *const c_void, c_ulong) -> c_long,
+    snd_pcm_recover: unsafe extern "C" fn(*mut SndPcm, c_int, c_int) -> c_int,
+    snd_pcm_drop: unsafe extern "C" fn(*mut SndPcm) -> c_int,
+    snd_pcm_close: unsafe extern "C" fn(*mut SndPcm) -> c_int,
+    snd_strerror: unsafe extern "C" fn(c_int) -> *const c_char,
+}
+
+/// Safety: `T` must be the function pointer type of the symbol
+unsafe fn alsa_load_symbol<T>(handle: *mut c_void, name: &CStr) -> Result<T, EngineError> {
+    let symbol = libc::dlsym(handle, name.as_ptr());
+    if symbol.is_null() {
+        error!("Failed to find {:?} in {:?}", name, ALSA_LIBRARY_NAME);
+        return Err(EngineError::InitializationFailed);
+    }
+    Ok(std::mem::transmute_copy(&symbol))
+}
+
+impl AlsaLibrary {
+    fn load() -> Result<Self, EngineError> {
+        let handle = unsafe { libc::dlopen(ALSA_LIBRARY_NAME.as_ptr(), libc::RTLD_NOW) };
+        if handle.is_null() {
+            error!(
+                "Failed to load {:?}, alsa may not be installed",
+                ALSA_LIBRARY_NAME
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+        // Safety: the signatures are the ones of the alsa headers
+        let library = unsafe { Self::load_symbols(handle) };
+        if library.is_err() {
+            unsafe { libc::dlclose(handle) };
+        }
+        library
+    }
+
+    unsafe fn load_symbols(handle: *mut c_void) -> Result<Self, EngineError> {
+        Ok(Self {
+            handle,
+            snd_pcm_open: alsa_load_symbol(handle, c"snd_pcm_open")?,
+            snd_pcm_set_params: alsa_load_symbol(handle, c"snd_pcm_set_params")?,
+            snd_pcm_writei: alsa_load_symbol(handle, c"snd_pcm_writei")?,
+            snd_pcm_recover: alsa_load_symbol(handle, c"snd_pcm_recover")?,
+            snd_pcm_drop: alsa_load_symbol(handle, c"snd_pcm_drop")?,
+            snd_pcm_close: alsa_load_symbol(handle, c"snd_pcm_close")?,
+            snd_strerror: alsa_load_symbol(handle, c"snd_strerror")?,
+        })
+    }
+
+    fn get_error_message(&self, code: c_int) -> String {
+        unsafe { CStr::from_ptr((self.snd_strerror)(code)) }
+            .to_string_lossy()
+            .into_owned()
+    }
+}
+
+impl Drop for AlsaLibrary {
+    fn drop(&mut self) {
+        unsafe { libc::dlclose(self.handle) };
+    }
+}
+
+/// Opened playback device, closed when dropped
+struct AlsaPcm {
+    library: AlsaLibrary,
+    pcm: *mut SndPcm,
+}
+
+// Safety: a pcm can be used from any thread as long as only one uses it at a time
+unsafe impl Send for AlsaPcm {}
+
+impl AlsaPcm {
+    fn open(sample_rate: u32, channel_count: usize) -> Result<Self, EngineError> {
+        let library = AlsaLibrary::load()?;
+        let mut pcm = std::ptr::null_mut();
+        let code = unsafe {
+            (library.snd_pcm_open)(
+                &mut pcm,
+                ALSA_DEVICE_NAME.as_ptr(),
+                SND_PCM_STREAM_PLAYBACK,
+                0,
+            )
+        };
+        if code < 0 {
+            error!(
+                "Failed to open the alsa device {:?}: {}",
+                ALSA_DEVICE_NAME,
+                library.get_error_message(code)
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+        let pcm = Self { library, pcm };
+        // Alsa resamples when the device runs at another rate
+        let code = unsafe {
+            (pcm.library.snd_pcm_set_params)(
+                pcm.pcm,
+                SND_PCM_FORMAT_FLOAT,
+                SND_PCM_ACCESS_RW_INTERLEAVED,
+                channel_count as c_uint,
+                sample_rate,
+                1,
+                ALSA_LATENCY_MICROSECONDS,
+            )
+        };
+        if code < 0 {
+            error!(
+                "Failed to set up the alsa device for {} channels at {}Hz: {}",
+                channel_count,
+                sample_rate,
+                pcm.library.get_error_message(code)
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+        Ok(pcm)
+    }
+
+    /// Block until all the frames are given to the device
+    fn write(&self, samples: &[f32], channel_count: usize) -> Result<(), EngineError> {
+        let frame_count = samples.len() / channel_count;
+        let mut written = 0;
+        while written < frame_count {
+            let result = unsafe {
+                (self.library.snd_pcm_writei)(
+                    self.pcm,
+                    samples[written * channel_count..].as_ptr() as *const c_void,
+                    (frame_count - written) as c_ulong,
+                )
+            };
+            if result >= 0 {
+                written += result as usize;
+                continue;
+            }
+            // An underrun when the mixer is late, the device is restarted
+            let code = unsafe { (self.library.snd_pcm_recover)(self.pcm, result as c_int, 1) };
+            if code < 0 {
+                error!(
+                    "Failed to write to the alsa device: {}",
+                    self.library.get_error_message(code)
+                );
+                return Err(EngineError::UpdateFailed);
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Drop for AlsaPcm {
+    fn drop(&mut self) {
+        unsafe {
+            (self.library.snd_pcm_drop)(self.pcm);
+            (self.library.snd_pcm_close)(self.pcm);
+        }
+    }
+}
+
+/// Thread mixing a period then writing it, the blocking write keeps it in step with the device
+pub(crate) struct AudioOutputLinux {
+    is_running: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl AudioOutputLinux {
+    pub fn init(
+        sample_rate: u32,
+        channel_count: usize,
+        mut callback: AudioOutputCallback,
+    ) -> Result<Self, EngineError> {
+        let pcm = AlsaPcm::open(sample_rate, channel_count)?;
+        let is_running = Arc::new(AtomicBool::new(true));
+        let thread_is_running = Arc::clone(&is_running);
+        let thread = std::thread::Builder::new()
+            .name(String::from("audio output"))
+            .spawn(move || {
+                let mut samples = vec![0.0; ALSA_PERIOD_FRAMES * channel_count];
+                while thread_is_running.load(Ordering::Relaxed) {
+                    callback(&mut samples);
+                    if pcm.write(&samples, channel_count).is_err() {
+                        break;
+                    }
+                }
+            });
+        match thread {
+            Ok(thread) => Ok(Self {
+                is_running,
+                thread: Some(thread),
+            }),
+            Err(err) => {
+                error!("Failed to spawn the audio output thread: {:?}", err);
+                Err(EngineError::InitializationFailed)
+            }
+        }
+    }
+}
+
+impl AudioOutput for AudioOutputLinux {
+    fn shutdown(&mut self) -> Result<(), EngineError> {
+        self.is_running.store(false, Ordering::Relaxed);
+        if let Some(thread) = self.thread.take() {
+            if thread.join().is_err() {
+                error!("Failed to join the audio output thread");
+                return Err(EngineError::ShutdownFailed);
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,178 @@
+/// MacOS implementation of the audio output, through an audio queue of AudioToolbox
+use std::ffi::c_void;
+
+use crate::{core::debug::errors::EngineError, error};
+
+use super::audio_output::{AudioOutput, AudioOutputCallback};
+
+type OSStatus = i32;
+/// Opaque `AudioQueueRef`
+type AudioQueueRef = *mut c_void;
+type AudioQueueBufferRef = *mut AudioQueueBuffer;
+type AudioQueueOutputCallback = extern "C" fn(*mut c_void, AudioQueueRef, AudioQueueBufferRef);
+
+const AUDIO_FORMAT_LINEAR_PCM: u32 = u32::from_be_bytes(*b"lpcm");
+const AUDIO_FORMAT_FLAG_IS_FLOAT: u32 = 1 << 0;
+const AUDIO_FORMAT_FLAG_IS_PACKED: u32 = 1 << 3;
+
+/// Buffers cycling between the mixer and the device
+const AUDIO_QUEUE_BUFFER_COUNT: usize = 3;
+/// Frames mixed per buffer
+const AUDIO_QUEUE_BUFFER_FRAMES: usize = 512;
+
+#[repr(C)]
+struct AudioStreamBasicDescription {
+    sample_rate: f64,
+    format_id: u32,
+    format_flags: u32,
+    bytes_per_packet: u32,
+    frames_per_packet: u32,
+    bytes_per_frame: u32,
+    channels_per_frame: u32,
+    bits_per_channel: u32,
+    reserved: u32,
+}
+
+#[repr(C)]
+struct AudioQueueBuffer {
+    audio_data_bytes_capacity: u32,
+    audio_data: *mut c_void,
+    audio_data_byte_size: u32,
+    user_data: *mut c_void,
+    packet_description_capacity: u32,
+    packet_descriptions: *mut c_void,
+    packet_description_count: u32,
+}
+
+#[link(name = "AudioToolbox", kind = "framework")]
+extern "C" {
+    fn AudioQueueNewOutput(
+        format: *const AudioStreamBasicDescription,
+        callback: AudioQueueOutputCallback,
+        user_data: *mut c_void,
+        callback_run_loop: *const c_void,
+        callback_run_loop_mode: *const c_void,
+        flags: u32,
+        queue: *mut AudioQueueRef,
+    ) -> OSStatus;
+    fn AudioQueueAllocateBuffer(
+        queue: AudioQueueRef,
+        byte_size: u32,
+        buffer: *mut AudioQueueBufferRef,
+    ) -> OSStatus;
+    fn AudioQueueEnqueueBuffer(
+        queue: AudioQueueRef,
+        buffer: AudioQueueBufferRef,
+        packet_description_count: u32,
+        packet_descriptions: *const c_void,
+    ) -> OSStatus;
+    fn AudioQueueStart(queue: AudioQueueRef, start_time: *const c_void) -> OSStatus;
+    fn AudioQueueDispose(queue: AudioQueueRef, immediate: u8) -> OSStatus;
+}
+
+/// Fill the buffer with the callback and give it back to the queue
+extern "C" fn audio_queue_fill_buffer(
+    user_data: *mut c_void,
+    queue: AudioQueueRef,
+    buffer: AudioQueueBufferRef,
+) {
+    // Safety: the user data is the callback owned by the output, it outlives the queue
+    let callback = unsafe { &mut *(user_data as *mut AudioOutputCallback) };
+    let buffer = unsafe { &mut *buffer };
+    let sample_count = buffer.audio_data_bytes_capacity as usize / std::mem::size_of::<f32>();
+    let samples =
+        unsafe { std::slice::from_raw_parts_mut(buffer.audio_data as *mut f32, sample_count) };
+    callback(samples);
+    buffer.audio_data_byte_size = buffer.audio_data_bytes_capacity;
+    unsafe { AudioQueueEnqueueBuffer(queue, buffer, 0, std::ptr::null()) };
+}
+
+/// Audio queue calling the callback from its own thread whenever a buffer was played
+pub(crate) struct AudioOutputMacOS {
+    queue: Option<AudioQueueRef>,
+    /// Boxed so that its address given to the queue doesn't move
+    callback: Box<AudioOutputCallback>,
+}
+
+// Safety: the queue is thread safe, the callback is only used by the queue thread while it runs
+unsafe impl Send for AudioOutputMacOS {}
+
+impl AudioOutputMacOS {
+    pub fn init(
+        sample_rate: u32,
+        channel_count: usize,
+        callback: AudioOutputCallback,
+    ) -> Result<Self, EngineError> {
+        let bytes_per_frame = (channel_count * std::mem::size_of::<f32>()) as u32;
+        let format = AudioStreamBasicDescription {
+            sample_rate: sample_rate as f64,
+            format_id: AUDIO_FORMAT_LINEAR_PCM,
+            format_flags: AUDIO_FORMAT_FLAG_IS_FLOAT | AUDIO_FORMAT_FLAG_IS_PACKED,
+            bytes_per_packet: bytes_per_frame,
+            frames_per_packet: 1,
+            bytes_per_frame,
+            channels_per_frame: channel_count as u32,
+            bits_per_channel: 32,
+            reserved: 0,
+        };
+        let mut output = Self {
+            queue: None,
+            callback: Box::new(callback),
+        };
+        let user_data = output.callback.as_mut() as *mut AudioOutputCallback as *mut c_void;
+        let mut queue = std::ptr::null_mut();
+        // Without a run loop the callback runs on a thread of the queue
+        let status = unsafe {
+            AudioQueueNewOutput(
+                &format,
+                audio_queue_fill_buffer,
+                user_data,
+                std::ptr::null(),
+                std::ptr::null(),
+                0,
+                &mut queue,
+            )
+        };
+        if status != 0 {
+            error!("Failed to create the audio queue: {}", status);
+            return Err(EngineError::InitializationFailed);
+        }
+        output.queue = Some(queue);
+
+        // The buffers are filled once before starting, then refilled by the queue as they are played
+        let buffer_byte_size = AUDIO_QUEUE_BUFFER_FRAMES as u32 * bytes_per_frame;
+        for _ in 0..AUDIO_QUEUE_BUFFER_COUNT {
+            let mut buffer = std::ptr::null_mut();
+            let status = unsafe { AudioQueueAllocateBuffer(queue, buffer_byte_size, &mut buffer) };
+            if status != 0 {
+                error!("Failed to allocate an audio queue buffer: {}", status);
+                output.shutdown()?;
+                return Err(EngineError::InitializationFailed);
+            }
+            audio_queue_fill_buffer(user_data, queue, buffer);
+        }
+        let status = unsafe { AudioQueueStart(queue, std::ptr::null()) };
+        if status != 0 {
+            error!("Failed to start the audio queue: {}", status);
+            output.shutdown()?;
+            return Err(EngineError::InitializationFailed);
+        }
+        Ok(output)
+    }
+}
+
+impl AudioOutput for AudioOutputMacOS {
+    fn shutdown(&mut self) -> Result<(), EngineError> {
+        let queue = match self.queue.take() {
+            Some(queue) => queue,
+            None => return Ok(()),
+        };
+        // Disposing immediately waits for the running callback, the buffers are freed with the queue
+        let status = unsafe { AudioQueueDispose(queue, 1) };
+        if status != 0 {
+            error!("Failed to dispose of the audio queue: {}", status);
+            return Err(EngineError::ShutdownFailed);
+        }
+        Ok(())
+    }
+}
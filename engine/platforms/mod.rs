@@ -1,4 +1,13 @@
-pub mod platform;
+pub(crate) mod audio_output;
+pub(crate) mod platform;
+pub(crate) mod platform_headless;
 
 #[cfg(target_os = "linux")]
-pub mod platform_linux;
+pub(crate) mod audio_output_linux;
+#[cfg(target_os = "linux")]
+pub(crate) mod platform_linux;
+
+#[cfg(target_os = "macos")]
+pub(crate) mod audio_output_macos;
+#[cfg(target_os = "macos")]
+pub(crate) mod platform_macos;
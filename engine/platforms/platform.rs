@@ -1,12 +1,25 @@
 use crate::{
-    core::{debug::errors::EngineError, systems::logger::LogLevel},
+    core::{
+        application::{
+            window_geometry::{MonitorArea, WindowGeometry},
+            windows::{WindowCreatorParameters, WindowHandle},
+            ExternalWindow,
+        },
+        debug::errors::EngineError,
+        systems::{input::mouse::CursorMode, logger::LogLevel},
+    },
     error,
-    renderer::vulkan::vulkan_types::VulkanContext,
+    renderer::vulkan::vulkan_types::VulkanCoreContext,
 };
 
+/// Time between two event checks of the platforms that can't block on their events
+pub(crate) const PLATFORM_WAIT_EVENTS_FALLBACK_MS: u64 = 50;
+
 /// Abstract trait for the platform (os) specific code
 pub(crate) trait Platform {
     /// Initiate the internal structure of the platform
+    /// Draw inside the external window instead of creating one if it is given
+    #[allow(clippy::too_many_arguments)]
     fn init(
         &mut self,
         window_title: String,
@@ -15,8 +28,14 @@ pub(crate) trait Platform {
         width: u32,
         height: u32,
         resizable: bool,
+        external_window: Option<ExternalWindow>,
     ) -> Result<(), EngineError>;
 
+    /// Size of the external window when the platform can query it
+    fn get_embedded_window_size(&self) -> Option<(u32, u32)> {
+        None
+    }
+
     /// Shutdown the platform
     fn shutdown(&mut self) -> Result<(), EngineError>;
 
@@ -24,6 +43,14 @@ pub(crate) trait Platform {
     /// Return true if should quit
     fn handle_events(&mut self) -> Result<bool, EngineError>;
 
+    /// Block until an event is received then consume all of them, used while suspended
+    /// Return true if should quit
+    fn wait_for_events(&mut self) -> Result<bool, EngineError> {
+        // Polling fallback, the cpu is still given back between two checks
+        self.sleep_from_milliseconds(PLATFORM_WAIT_EVENTS_FALLBACK_MS)?;
+        self.handle_events()
+    }
+
     /// Ellapsed time in seconds since the UNIX_EPOCH
     /// Panic if an error occurs
     fn get_absolute_time_in_seconds(&self) -> Result<f64, EngineError> {
@@ -37,8 +64,87 @@ pub(crate) trait Platform {
         Err(EngineError::NotImplemented)
     }
 
+    /// Resident memory of the process in bytes
+    fn get_memory_usage_in_bytes(&self) -> Result<u64, EngineError> {
+        error!("Function `get_memory_usage_in_bytes' is not implemented for this platform");
+        Err(EngineError::NotImplemented)
+    }
+
+    /// Show, hide or grab the cursor over the window
+    fn set_cursor_mode(&mut self, mode: CursorMode) -> Result<(), EngineError> {
+        error!(
+            "Function `set_cursor_mode' is not implemented for this platform, can't set {:?}",
+            mode
+        );
+        Err(EngineError::NotImplemented)
+    }
+
+    /// Current position and size of the window, None when embedded or unknown
+    /// The monitor index is left to 0, see `WindowGeometry::find_monitor`
+    fn get_window_geometry(&self) -> Option<WindowGeometry> {
+        None
+    }
+
+    /// Move and resize the window, the resize event follows like for a user resize
+    fn set_window_geometry(&mut self, geometry: &WindowGeometry) -> Result<(), EngineError> {
+        error!(
+            "Function `set_window_geometry' is not implemented for this platform, can't set {:?}",
+            geometry
+        );
+        Err(EngineError::NotImplemented)
+    }
+
+    /// Open an additional window, its events are reported with its handle
+    /// The main window is the one created by `init`
+    fn create_window(
+        &mut self,
+        parameters: &WindowCreatorParameters,
+    ) -> Result<WindowHandle, EngineError> {
+        error!(
+            "Function `create_window' is not implemented for this platform, can't create {:?}",
+            parameters.title
+        );
+        Err(EngineError::NotImplemented)
+    }
+
+    /// Vulkan surface of a window, the additional windows get their own swapchain
+    fn get_window_vulkan_surface(
+        &self,
+        window: WindowHandle,
+        vulkan_context: &VulkanCoreContext,
+    ) -> Result<ash::vk::SurfaceKHR, EngineError> {
+        if window.is_main() {
+            return self.get_vulkan_surface(vulkan_context);
+        }
+        error!(
+            "Function `get_window_vulkan_surface' is not implemented for this platform, can't draw into {:?}",
+            window
+        );
+        Err(EngineError::NotImplemented)
+    }
+
+    /// Close an additional window, the main window lives as long as the platform
+    fn destroy_window(&mut self, window: WindowHandle) -> Result<(), EngineError> {
+        error!(
+            "Function `destroy_window' is not implemented for this platform, can't destroy {:?}",
+            window
+        );
+        Err(EngineError::NotImplemented)
+    }
+
+    /// Areas of the connected monitors, empty if the platform can't list them
+    fn get_monitors(&self) -> Vec<MonitorArea> {
+        Vec::new()
+    }
+
     /// Get the required extensions for the renderer
     fn get_required_extensions(&self) -> Result<Vec<*const i8>, EngineError>;
+
+    /// Flags of the vulkan instance, needed by the non conformant implementations
+    fn get_instance_create_flags(&self) -> ash::vk::InstanceCreateFlags {
+        ash::vk::InstanceCreateFlags::empty()
+    }
+
     /// Defaut output on the console
     fn console_write(message: &str, _log_level: LogLevel)
     where
@@ -50,7 +156,7 @@ pub(crate) trait Platform {
     /// Get the vulkan surface
     fn get_vulkan_surface(
         &self,
-        vulkan_context: &VulkanContext,
+        vulkan_context: &VulkanCoreContext,
     ) -> Result<ash::vk::SurfaceKHR, EngineError>;
 
     /// Defaut output on the console for errors
@@ -63,6 +169,8 @@ pub(crate) trait Platform {
 }
 
 /// Initiate the engine platform depending on the OS
+/// The headless platform opens no window, it goes with the null renderer backend
+#[allow(clippy::too_many_arguments)]
 pub(crate) fn platform_init(
     window_title: String,
     x: i16,
@@ -70,14 +178,59 @@ pub(crate) fn platform_init(
     width: u32,
     height: u32,
     resizable: bool,
-) -> Result<impl Platform, EngineError> {
+    external_window: Option<ExternalWindow>,
+    headless: bool,
+) -> Result<Box<dyn Platform>, EngineError> {
+    if headless {
+        let mut platform_headless = super::platform_headless::PlatformHeadless::default();
+        let result = platform_headless.init(
+            window_title,
+            x,
+            y,
+            width,
+            height,
+            resizable,
+            external_window,
+        );
+        return match result {
+            Err(_) => Err(EngineError::InitializationFailed),
+            Ok(_) => Ok(Box::new(platform_headless)),
+        };
+    }
+
     #[cfg(target_os = "linux")]
     {
         let mut platform_linux = super::platform_linux::PlatformLinux::default();
-        let result = platform_linux.init(window_title, x, y, width, height, resizable);
+        let result = platform_linux.init(
+            window_title,
+            x,
+            y,
+            width,
+            height,
+            resizable,
+            external_window,
+        );
+        match result {
+            Err(_) => Err(EngineError::InitializationFailed),
+            Ok(_) => Ok(Box::new(platform_linux)),
+        }
+    }
+
+    #[cfg(target_os = "macos")]
+    {
+        let mut platform_macos = super::platform_macos::PlatformMacOS::default();
+        let result = platform_macos.init(
+            window_title,
+            x,
+            y,
+            width,
+            height,
+            resizable,
+            external_window,
+        );
         match result {
             Err(_) => Err(EngineError::InitializationFailed),
-            Ok(_) => Ok(platform_linux),
+            Ok(_) => Ok(Box::new(platform_macos)),
         }
     }
 }
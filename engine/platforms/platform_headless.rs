@@ -0,0 +1,88 @@
+/// Platform without a window, used with the null renderer backend
+/// for the servers and the tests running the engine loop without a display
+use crate::{
+    core::{
+        application::{window_geometry::WindowGeometry, ExternalWindow},
+        debug::errors::EngineError,
+    },
+    error,
+    renderer::vulkan::vulkan_types::VulkanCoreContext,
+    warn,
+};
+
+use super::platform::Platform;
+
+#[derive(Default)]
+pub(crate) struct PlatformHeadless {
+    /// Size reported to the renderer as if a window existed
+    pub window_size: (u32, u32),
+}
+
+impl Platform for PlatformHeadless {
+    fn init(
+        &mut self,
+        _window_title: String,
+        _x: i16,
+        _y: i16,
+        width: u32,
+        height: u32,
+        _resizable: bool,
+        external_window: Option<ExternalWindow>,
+    ) -> Result<(), EngineError> {
+        if external_window.is_some() {
+            warn!("The headless platform ignores the external window");
+        }
+        self.window_size = (width, height);
+        Ok(())
+    }
+
+    fn shutdown(&mut self) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn handle_events(&mut self) -> Result<bool, EngineError> {
+        Ok(false)
+    }
+
+    fn get_absolute_time_in_seconds(&self) -> Result<f64, EngineError> {
+        match std::time::SystemTime::now().duration_since(std::time::SystemTime::UNIX_EPOCH) {
+            Ok(duration) => Ok(duration.as_secs_f64()),
+            Err(_) => {
+                error!("SystemTime before UNIX EPOCH!");
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    fn sleep_from_milliseconds(&self, ms: u64) -> Result<(), EngineError> {
+        std::thread::sleep(std::time::Duration::from_millis(ms));
+        Ok(())
+    }
+
+    fn get_window_geometry(&self) -> Option<WindowGeometry> {
+        Some(WindowGeometry {
+            x: 0,
+            y: 0,
+            width: self.window_size.0,
+            height: self.window_size.1,
+            monitor: 0,
+        })
+    }
+
+    fn set_window_geometry(&mut self, geometry: &WindowGeometry) -> Result<(), EngineError> {
+        self.window_size = (geometry.width, geometry.height);
+        Ok(())
+    }
+
+    fn get_required_extensions(&self) -> Result<Vec<*const i8>, EngineError> {
+        Ok(Vec::new())
+    }
+
+    fn get_vulkan_surface(
+        &self,
+        _vulkan_context: &VulkanCoreContext,
+    ) -> Result<ash::vk::SurfaceKHR, EngineError> {
+        error!("The headless platform has no window to create a vulkan surface from");
+        Err(EngineError::NotImplemented)
+    }
+}
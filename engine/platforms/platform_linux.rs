@@ -1,29 +1,36 @@
 use std::{
+    collections::HashMap,
     ffi::{c_char, CStr},
     os::raw::c_void,
+    time::Instant,
 };
 
 use ash::vk::XcbSurfaceCreateInfoKHR;
 /// Linux implementation of the platform trait
-use xcb::Xid;
+use xcb::{Xid, XidNew};
 
 use crate::{
     core::{
-        debug::errors::EngineError,
+        application::{
+            window_geometry::{MonitorArea, WindowGeometry},
+            windows::{WindowCreatorParameters, WindowHandle},
+            ExternalWindow,
+        },
+        debug::{errors::EngineError, input_latency::input_latency_record_event},
         systems::{
             events::{event_fire, EventCode},
             input::{
-                keyboard::{intput_process_key, Key, KeyState},
+                keyboard::{input_process_char, intput_process_key, Key, KeyState},
                 mouse::{
-                    input_process_mouse_button, input_process_mouse_move, MouseButton,
-                    MouseButtonState,
+                    input_process_mouse_button, input_process_mouse_move, input_process_mouse_warp,
+                    input_process_mouse_wheel, CursorMode, MouseButton, MouseButtonState,
                 },
             },
             logger::LogLevel,
         },
     },
     error,
-    renderer::vulkan::vulkan_types::VulkanContext,
+    renderer::vulkan::vulkan_types::VulkanCoreContext,
     warn,
 };
 
@@ -39,6 +46,18 @@ pub(crate) struct PlatformLinux {
     pub window_manager_protocols: Option<xcb::x::Atom>,
     pub window_manager_delete_window: Option<xcb::x::Atom>,
     pub key_symbols: Option<*mut xcb_util::ffi::keysyms::xcb_key_symbols_t>,
+    /// Window of the host application when embedded, our window is its child
+    pub parent_window: Option<xcb::x::Window>,
+    /// Size of the parent window when the platform was initialized
+    pub embedded_window_size: Option<(u32, u32)>,
+    /// Last known size of our window, the grabbed cursor is warped back to its center
+    pub window_size: (u16, u16),
+    pub cursor_mode: CursorMode,
+    /// Created the first time the cursor is hidden
+    pub invisible_cursor: Option<xcb::x::Cursor>,
+    /// Windows opened after the main one, the input events only come from the main window
+    pub secondary_windows: HashMap<WindowHandle, xcb::x::Window>,
+    pub last_window_id: u32,
 }
 
 impl Platform for PlatformLinux {
@@ -50,6 +69,7 @@ impl Platform for PlatformLinux {
         width: u32,
         height: u32,
         resizable: bool,
+        external_window: Option<ExternalWindow>,
     ) -> Result<(), EngineError> {
         // Connect to the X server
         let (connection, screen_number) = match xcb::Connection::connect(None) {
@@ -60,70 +80,81 @@ impl Platform for PlatformLinux {
             Ok((connection, screen_number)) => (connection, screen_number),
         };
 
-        self.connection = Some(connection);
-
         // Fetch the `x::Setup` and get the main `x::Screen` object.
-        let setup = self.connection.as_ref().unwrap().get_setup();
-        let screen = setup.roots().nth(screen_number as usize).unwrap();
+        let setup = connection.get_setup();
+        let screen = match setup.roots().nth(screen_number as usize) {
+            Some(screen) => screen,
+            None => {
+                error!(
+                    "Failed to find the screen {} of the X server",
+                    screen_number
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+
+        // When embedded, the window fills the host window instead of the given rectangle
+        let (parent, x, y, width, height) = match external_window {
+            Some(ExternalWindow::Xcb { window }) => {
+                let parent = xcb::x::Window::new(window);
+                let (width, height) = Self::init_parent_window(&connection, parent)?;
+                self.parent_window = Some(parent);
+                self.embedded_window_size = Some((width, height));
+                (parent, 0, 0, width, height)
+            }
+            None => (screen.root(), x, y, width, height),
+        };
 
         // Generate an `Xid` for the client window.
         // The type inference is needed here.
-        let window: xcb::x::Window = self.connection.as_ref().unwrap().generate_id();
+        let window: xcb::x::Window = connection.generate_id();
 
         // We can now create a window. For this we pass a `Request`
         // object to the `send_request_checked` method. The method
         // returns a cookie that will be used to check for success.
-        let cookie =
-            self.connection
-                .as_ref()
-                .unwrap()
-                .send_request_checked(&xcb::x::CreateWindow {
-                    depth: xcb::x::COPY_FROM_PARENT as u8,
-                    wid: window,
-                    parent: screen.root(),
-                    x,
-                    y,
-                    width: width as u16,
-                    height: height as u16,
-                    border_width: 0, // no border
-                    class: xcb::x::WindowClass::InputOutput,
-                    visual: screen.root_visual(),
-                    // this list must be in same order than `Cw` enum order
-                    value_list: &[
-                        xcb::x::Cw::BackPixel(screen.black_pixel()),
-                        xcb::x::Cw::EventMask(
-                            xcb::x::EventMask::EXPOSURE
-                                | xcb::x::EventMask::POINTER_MOTION
-                                | xcb::x::EventMask::STRUCTURE_NOTIFY
-                                | xcb::x::EventMask::KEY_PRESS
-                                | xcb::x::EventMask::KEY_RELEASE
-                                | xcb::x::EventMask::BUTTON_PRESS
-                                | xcb::x::EventMask::BUTTON_RELEASE,
-                        ),
-                    ],
-                });
+        let cookie = connection.send_request_checked(&xcb::x::CreateWindow {
+            depth: xcb::x::COPY_FROM_PARENT as u8,
+            wid: window,
+            parent,
+            x,
+            y,
+            width: width as u16,
+            height: height as u16,
+            border_width: 0, // no border
+            class: xcb::x::WindowClass::InputOutput,
+            visual: screen.root_visual(),
+            // this list must be in same order than `Cw` enum order
+            value_list: &[
+                xcb::x::Cw::BackPixel(screen.black_pixel()),
+                xcb::x::Cw::EventMask(
+                    xcb::x::EventMask::EXPOSURE
+                        | xcb::x::EventMask::POINTER_MOTION
+                        | xcb::x::EventMask::STRUCTURE_NOTIFY
+                        | xcb::x::EventMask::KEY_PRESS
+                        | xcb::x::EventMask::KEY_RELEASE
+                        | xcb::x::EventMask::BUTTON_PRESS
+                        | xcb::x::EventMask::BUTTON_RELEASE,
+                ),
+            ],
+        });
 
         // We now check if the window creation worked.
         // A cookie can't be cloned; it is moved to the function.
-        if let Err(err) = self.connection.as_ref().unwrap().check_request(cookie) {
+        if let Err(err) = connection.check_request(cookie) {
             error!("Failed to create the window: {:?}", err);
             return Err(EngineError::InitializationFailed);
         };
 
         // Let's change the window title
-        let cookie =
-            self.connection
-                .as_ref()
-                .unwrap()
-                .send_request_checked(&xcb::x::ChangeProperty {
-                    mode: xcb::x::PropMode::Replace,
-                    window,
-                    property: xcb::x::ATOM_WM_NAME,
-                    r#type: xcb::x::ATOM_STRING,
-                    data: window_title.as_bytes(),
-                });
+        let cookie = connection.send_request_checked(&xcb::x::ChangeProperty {
+            mode: xcb::x::PropMode::Replace,
+            window,
+            property: xcb::x::ATOM_WM_NAME,
+            r#type: xcb::x::ATOM_STRING,
+            data: window_title.as_bytes(),
+        });
         // And check for success again
-        if let Err(err) = self.connection.as_ref().unwrap().check_request(cookie) {
+        if let Err(err) = connection.check_request(cookie) {
             error!("Failed to set the window title: {:?}", err);
             return Err(EngineError::InitializationFailed);
         };
@@ -134,39 +165,30 @@ impl Platform for PlatformLinux {
 
         // We now show ("map" in X terminology) the window.
         // This time we do not check for success, so we discard the cookie.
-        self.connection
-            .as_ref()
-            .unwrap()
-            .send_request(&xcb::x::MapWindow { window });
+        connection.send_request(&xcb::x::MapWindow { window });
 
         // We need a few atoms for our application.
         // We send a few requests in a row and wait for the replies after.
         let (wm_protocols, wm_del_window) = {
             let cookies = (
-                self.connection
-                    .as_ref()
-                    .unwrap()
-                    .send_request(&xcb::x::InternAtom {
-                        only_if_exists: true,
-                        name: b"WM_PROTOCOLS",
-                    }),
-                self.connection
-                    .as_ref()
-                    .unwrap()
-                    .send_request(&xcb::x::InternAtom {
-                        only_if_exists: true,
-                        name: b"WM_DELETE_WINDOW",
-                    }),
+                connection.send_request(&xcb::x::InternAtom {
+                    only_if_exists: true,
+                    name: b"WM_PROTOCOLS",
+                }),
+                connection.send_request(&xcb::x::InternAtom {
+                    only_if_exists: true,
+                    name: b"WM_DELETE_WINDOW",
+                }),
             );
             (
-                match self.connection.as_ref().unwrap().wait_for_reply(cookies.0) {
+                match connection.wait_for_reply(cookies.0) {
                     Err(err) => {
                         error!("Failed to get the protocols atom: {:?}", err);
                         return Err(EngineError::InitializationFailed);
                     }
                     Ok(reply) => reply.atom(),
                 },
-                match self.connection.as_ref().unwrap().wait_for_reply(cookies.1) {
+                match connection.wait_for_reply(cookies.1) {
                     Err(err) => {
                         error!("Failed to get the delete window atom: {:?}", err);
                         return Err(EngineError::InitializationFailed);
@@ -182,22 +204,15 @@ impl Platform for PlatformLinux {
         // We now activate the window close event by sending the following request.
         // If we don't do this we can still close the window by clicking on the "x" button,
         // but the event loop is notified through a connection shutdown error.
-        if let Err(err) = self.connection.as_ref().unwrap().check_request(
-            self.connection
-                .as_ref()
-                .unwrap()
-                .send_request_checked(&xcb::x::ChangeProperty {
-                    mode: xcb::x::PropMode::Replace,
-                    window,
-                    property: *self.window_manager_protocols.as_mut().unwrap(),
-                    r#type: xcb::x::ATOM_ATOM,
-                    data: &[self
-                        .window_manager_delete_window
-                        .as_mut()
-                        .unwrap()
-                        .resource_id()],
-                }),
-        ) {
+        if let Err(err) =
+            connection.check_request(connection.send_request_checked(&xcb::x::ChangeProperty {
+                mode: xcb::x::PropMode::Replace,
+                window,
+                property: wm_protocols,
+                r#type: xcb::x::ATOM_ATOM,
+                data: &[wm_del_window.resource_id()],
+            }))
+        {
             error!("Failed to activate the window close event: {:?}", err);
             return Err(EngineError::InitializationFailed);
         };
@@ -205,12 +220,11 @@ impl Platform for PlatformLinux {
         self.screen_number = screen_number;
         self.screen = Some(screen.to_owned());
         self.window = Some(window);
+        self.window_size = (width as u16, height as u16);
 
         // Init keysym
         let key_symbols = unsafe {
-            xcb_util::ffi::keysyms::xcb_key_symbols_alloc(
-                self.connection.as_ref().unwrap().get_raw_conn() as *mut _,
-            )
+            xcb_util::ffi::keysyms::xcb_key_symbols_alloc(connection.get_raw_conn() as *mut _)
         };
 
         if key_symbols.is_null() {
@@ -219,22 +233,46 @@ impl Platform for PlatformLinux {
         }
 
         self.key_symbols = Some(key_symbols);
+        self.connection = Some(connection);
 
         Ok(())
     }
 
+    fn get_embedded_window_size(&self) -> Option<(u32, u32)> {
+        self.embedded_window_size
+    }
+
     fn shutdown(&mut self) -> Result<(), EngineError> {
         // close the keysym
-        unsafe { xcb_util::ffi::keysyms::xcb_key_symbols_free(self.key_symbols.unwrap()) };
+        if let Some(key_symbols) = self.key_symbols.take() {
+            unsafe { xcb_util::ffi::keysyms::xcb_key_symbols_free(key_symbols) };
+        }
+
+        // Give the pointer back before the window disappears
+        if self.cursor_mode == CursorMode::Grabbed {
+            self.get_connection()?.send_request(&xcb::x::UngrabPointer {
+                time: xcb::x::CURRENT_TIME,
+            });
+        }
+        if let Some(cursor) = self.invisible_cursor.take() {
+            self.get_connection()?
+                .send_request(&xcb::x::FreeCursor { cursor });
+        }
+
+        // The additional windows left open by the application
+        let secondary_windows: Vec<WindowHandle> = self.secondary_windows.keys().copied().collect();
+        for handle in secondary_windows {
+            if let Err(err) = self.destroy_window(handle) {
+                warn!("Failed to close the window {:?}: {:?}", handle, err);
+            }
+        }
 
         // We close the window
-        let window = self.window.unwrap();
-        match self.connection.as_ref().unwrap().check_request(
-            self.connection
-                .as_ref()
-                .unwrap()
-                .send_request_checked(&xcb::x::DestroyWindow { window }),
-        ) {
+        let window = self.get_window()?;
+        let connection = self.get_connection()?;
+        match connection
+            .check_request(connection.send_request_checked(&xcb::x::DestroyWindow { window }))
+        {
             Err(err) => {
                 error!("Failed to destroy the window: {:?}", err);
                 Err(EngineError::InitializationFailed)
@@ -243,6 +281,101 @@ impl Platform for PlatformLinux {
         }
     }
 
+    fn create_window(
+        &mut self,
+        parameters: &WindowCreatorParameters,
+    ) -> Result<WindowHandle, EngineError> {
+        let connection = self.get_connection()?;
+        let screen = self.get_screen()?;
+        let (wm_protocols, wm_del_window) = self.get_window_manager_atoms()?;
+        let window: xcb::x::Window = connection.generate_id();
+        if let Err(err) =
+            connection.check_request(connection.send_request_checked(&xcb::x::CreateWindow {
+                depth: xcb::x::COPY_FROM_PARENT as u8,
+                wid: window,
+                parent: screen.root(),
+                x: parameters.x,
+                y: parameters.y,
+                width: parameters.width as u16,
+                height: parameters.height as u16,
+                border_width: 0,
+                class: xcb::x::WindowClass::InputOutput,
+                visual: screen.root_visual(),
+                value_list: &[
+                    xcb::x::Cw::BackPixel(screen.black_pixel()),
+                    xcb::x::Cw::EventMask(
+                        xcb::x::EventMask::EXPOSURE | xcb::x::EventMask::STRUCTURE_NOTIFY,
+                    ),
+                ],
+            }))
+        {
+            error!(
+                "Failed to create the window {:?}: {:?}",
+                parameters.title, err
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+
+        // Title and close event like for the main window
+        let title_cookie = connection.send_request_checked(&xcb::x::ChangeProperty {
+            mode: xcb::x::PropMode::Replace,
+            window,
+            property: xcb::x::ATOM_WM_NAME,
+            r#type: xcb::x::ATOM_STRING,
+            data: parameters.title.as_bytes(),
+        });
+        let protocols_cookie = connection.send_request_checked(&xcb::x::ChangeProperty {
+            mode: xcb::x::PropMode::Replace,
+            window,
+            property: wm_protocols,
+            r#type: xcb::x::ATOM_ATOM,
+            data: &[wm_del_window.resource_id()],
+        });
+        if let Err(err) = connection
+            .check_request(title_cookie)
+            .and(connection.check_request(protocols_cookie))
+        {
+            error!(
+                "Failed to set the properties of the window {:?}: {:?}",
+                parameters.title, err
+            );
+            connection.send_request(&xcb::x::DestroyWindow { window });
+            return Err(EngineError::InitializationFailed);
+        }
+
+        connection.send_request(&xcb::x::MapWindow { window });
+        if let Err(err) = connection.flush() {
+            error!(
+                "Failed to show the window {:?}: {:?}",
+                parameters.title, err
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+
+        self.last_window_id += 1;
+        let handle = WindowHandle(self.last_window_id);
+        self.secondary_windows.insert(handle, window);
+        Ok(handle)
+    }
+
+    fn destroy_window(&mut self, window: WindowHandle) -> Result<(), EngineError> {
+        let Some(xcb_window) = self.secondary_windows.remove(&window) else {
+            error!(
+                "Can't destroy the window {:?}, it isn't an additional window",
+                window
+            );
+            return Err(EngineError::InvalidValue);
+        };
+        let connection = self.get_connection()?;
+        if let Err(err) = connection.check_request(
+            connection.send_request_checked(&xcb::x::DestroyWindow { window: xcb_window }),
+        ) {
+            error!("Failed to destroy the window {:?}: {:?}", window, err);
+            return Err(EngineError::ShutdownFailed);
+        }
+        Ok(())
+    }
+
     fn get_absolute_time_in_seconds(&self) -> Result<f64, EngineError> {
         match std::time::SystemTime::now().duration_since(std::time::SystemTime::UNIX_EPOCH) {
             Ok(duration) => Ok(duration.as_secs_f64()),
@@ -259,138 +392,64 @@ impl Platform for PlatformLinux {
         Ok(())
     }
 
+    fn get_memory_usage_in_bytes(&self) -> Result<u64, EngineError> {
+        let status = match std::fs::read_to_string("/proc/self/status") {
+            Ok(status) => status,
+            Err(err) => {
+                error!("Failed to read the process status: {:?}", err);
+                return Err(EngineError::IO);
+            }
+        };
+        // Given in kB
+        let resident_memory = status
+            .lines()
+            .find_map(|line| line.strip_prefix("VmRSS:"))
+            .and_then(|value| {
+                value
+                    .trim()
+                    .trim_end_matches("kB")
+                    .trim()
+                    .parse::<u64>()
+                    .ok()
+            });
+        match resident_memory {
+            Some(resident_memory) => Ok(resident_memory * 1024),
+            None => {
+                error!("Failed to find the resident memory in the process status");
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
     fn handle_events(&mut self) -> Result<bool, EngineError> {
-        let mut quit_flag = false;
-
-        'infinite_loop: loop {
-            let event = self.connection.as_ref().unwrap().poll_for_event().unwrap();
-            match event {
-                // leave loop when no more events to process
-                None => break 'infinite_loop,
-                Some(event) => {
-                    match event {
-                        // Input events
-                        xcb::Event::Unknown(_) => continue 'infinite_loop,
-                        xcb::Event::X(event) => {
-                            match event {
-                                // Keyboard press / release
-                                xcb::x::Event::KeyPress(event) => {
-                                    let key_code = event.detail();
-                                    let key_mask =
-                                        if event.state().contains(xcb::x::KeyButMask::SHIFT) {
-                                            1
-                                        } else {
-                                            0
-                                        };
-                                    if let Some(key) = self.translate_keycode(key_code, key_mask) {
-                                        // debug!("code pressed: {:?}", key);
-                                        intput_process_key(key, KeyState::Pressed)?;
-                                    };
-                                }
-                                xcb::x::Event::KeyRelease(event) => {
-                                    let key_code = event.detail();
-                                    let key_mask =
-                                        if event.state().contains(xcb::x::KeyButMask::SHIFT) {
-                                            1
-                                        } else {
-                                            0
-                                        };
-                                    if let Some(key) = self.translate_keycode(key_code, key_mask) {
-                                        // debug!("code release: {:?}", key);
-                                        intput_process_key(key, KeyState::Released)?;
-                                    };
-                                }
-
-                                // Mouse press / release
-                                xcb::x::Event::ButtonPress(event) => {
-                                    let button = event.detail() as u32;
-                                    if button == xcb::x::ButtonIndex::N1 as u32 {
-                                        input_process_mouse_button(
-                                            MouseButton::Left,
-                                            MouseButtonState::Pressed,
-                                        )?;
-                                        // debug!("left button pressed");
-                                    } else if button == xcb::x::ButtonIndex::N2 as u32 {
-                                        input_process_mouse_button(
-                                            MouseButton::Middle,
-                                            MouseButtonState::Pressed,
-                                        )?;
-                                        // debug!("middle button pressed");
-                                    } else if button == xcb::x::ButtonIndex::N3 as u32 {
-                                        input_process_mouse_button(
-                                            MouseButton::Right,
-                                            MouseButtonState::Pressed,
-                                        )?;
-                                        // debug!("right button pressed");
-                                    } else {
-                                        warn!("Unknown mouse button: {:?}", button);
-                                    };
-                                }
-                                xcb::x::Event::ButtonRelease(event) => {
-                                    let button = event.detail() as u32;
-                                    if button == xcb::x::ButtonIndex::N1 as u32 {
-                                        input_process_mouse_button(
-                                            MouseButton::Left,
-                                            MouseButtonState::Released,
-                                        )?;
-                                        // debug!("left button released");
-                                    } else if button == xcb::x::ButtonIndex::N2 as u32 {
-                                        input_process_mouse_button(
-                                            MouseButton::Middle,
-                                            MouseButtonState::Released,
-                                        )?;
-                                        // debug!("middle button released");
-                                    } else if button == xcb::x::ButtonIndex::N3 as u32 {
-                                        input_process_mouse_button(
-                                            MouseButton::Right,
-                                            MouseButtonState::Released,
-                                        )?;
-                                        // debug!("right button released");
-                                    } else {
-                                        warn!("Unknown mouse button: {:?}", button);
-                                    };
-                                }
-
-                                // Mouse movement
-                                xcb::x::Event::MotionNotify(event) => {
-                                    // debug!("mouse pos: ({}, {})", event.event_x(), event.event_y());
-                                    input_process_mouse_move(event.event_x(), event.event_y())?;
-                                }
-
-                                // Resizing
-                                xcb::x::Event::ConfigureNotify(event) => {
-                                    // This is also triggered by moving the window
-                                    let new_event = EventCode::Resized {
-                                        width: event.width() as u32,
-                                        height: event.height() as u32,
-                                    };
-                                    event_fire(new_event)?;
-                                }
-
-                                xcb::x::Event::ClientMessage(client_message_event) => {
-                                    // Window closing
-                                    let message_index_zero = match client_message_event.data() {
-                                        xcb::x::ClientMessageData::Data8(data) => data[0] as u32,
-                                        xcb::x::ClientMessageData::Data16(data) => data[0] as u32,
-                                        xcb::x::ClientMessageData::Data32(data) => data[0],
-                                    };
-                                    if message_index_zero
-                                        == self.window_manager_delete_window.unwrap().resource_id()
-                                    {
-                                        quit_flag = true;
-                                    }
-                                }
-
-                                // Other events
-                                _ => continue 'infinite_loop, // Ignore other events
-                            }
-                        }
-                    }
+        // Consume the pending events without blocking
+        loop {
+            let event = match self.get_connection()?.poll_for_event() {
+                Ok(Some(event)) => event,
+                Ok(None) => return Ok(false),
+                Err(err) => {
+                    error!("Failed to poll the next xcb event: {:?}", err);
+                    return Err(EngineError::Unknown);
                 }
+            };
+            if self.handle_event(event)? {
+                return Ok(true);
             }
         }
+    }
 
-        Ok(quit_flag)
+    fn wait_for_events(&mut self) -> Result<bool, EngineError> {
+        let event = match self.get_connection()?.wait_for_event() {
+            Ok(event) => event,
+            Err(err) => {
+                error!("Failed to wait for the next xcb event: {:?}", err);
+                return Err(EngineError::Unknown);
+            }
+        };
+        if self.handle_event(event)? {
+            return Ok(true);
+        }
+        self.handle_events()
     }
 
     fn console_write(message: &str, log_level: LogLevel) {
@@ -409,6 +468,157 @@ impl Platform for PlatformLinux {
         );
     }
 
+    fn get_window_geometry(&self) -> Option<WindowGeometry> {
+        if self.parent_window.is_some() {
+            return None;
+        }
+        let connection = self.connection.as_ref()?;
+        // The window is reparented by the window manager, its position is relative to the frame
+        let cookie = connection.send_request(&xcb::x::TranslateCoordinates {
+            src_window: self.window?,
+            dst_window: self.screen.as_ref()?.root(),
+            src_x: 0,
+            src_y: 0,
+        });
+        let reply = match connection.wait_for_reply(cookie) {
+            Ok(reply) => reply,
+            Err(err) => {
+                error!("Failed to get the window position: {:?}", err);
+                return None;
+            }
+        };
+        Some(WindowGeometry {
+            x: reply.dst_x() as i32,
+            y: reply.dst_y() as i32,
+            width: self.window_size.0 as u32,
+            height: self.window_size.1 as u32,
+            monitor: 0,
+        })
+    }
+
+    fn set_window_geometry(&mut self, geometry: &WindowGeometry) -> Result<(), EngineError> {
+        if self.parent_window.is_some() {
+            error!("Can't move the window, it follows the host window");
+            return Err(EngineError::InvalidValue);
+        }
+        let connection = self.get_connection()?;
+        connection.send_request(&xcb::x::ConfigureWindow {
+            window: self.get_window()?,
+            value_list: &[
+                xcb::x::ConfigWindow::X(geometry.x),
+                xcb::x::ConfigWindow::Y(geometry.y),
+                xcb::x::ConfigWindow::Width(geometry.width),
+                xcb::x::ConfigWindow::Height(geometry.height),
+            ],
+        });
+        if let Err(err) = connection.flush() {
+            error!("Failed to set the window geometry: {:?}", err);
+            return Err(EngineError::UpdateFailed);
+        }
+        // The size is updated by the configure notification, which fires the resize
+        Ok(())
+    }
+
+    /// The monitors are listed through randr, the whole screen is used if the server can't list them
+    fn get_monitors(&self) -> Vec<MonitorArea> {
+        let (connection, screen) = match (self.connection.as_ref(), self.screen.as_ref()) {
+            (Some(connection), Some(screen)) => (connection, screen),
+            _ => return Vec::new(),
+        };
+        let screen_area = MonitorArea {
+            x: 0,
+            y: 0,
+            width: screen.width_in_pixels() as u32,
+            height: screen.height_in_pixels() as u32,
+        };
+        let cookie = connection.send_request(&xcb::randr::GetMonitors {
+            window: screen.root(),
+            get_active: true,
+        });
+        let reply = match connection.wait_for_reply(cookie) {
+            Ok(reply) => reply,
+            Err(err) => {
+                warn!("Failed to list the monitors through randr: {:?}", err);
+                return vec![screen_area];
+            }
+        };
+        let monitors: Vec<MonitorArea> = reply
+            .monitors()
+            .map(|monitor| MonitorArea {
+                x: monitor.x() as i32,
+                y: monitor.y() as i32,
+                width: monitor.width() as u32,
+                height: monitor.height() as u32,
+            })
+            .collect();
+        if monitors.is_empty() {
+            return vec![screen_area];
+        }
+        monitors
+    }
+
+    fn set_cursor_mode(&mut self, mode: CursorMode) -> Result<(), EngineError> {
+        if mode == self.cursor_mode {
+            return Ok(());
+        }
+        let window = self.get_window()?;
+        let cursor = match mode {
+            CursorMode::Normal => xcb::x::Cursor::none(),
+            CursorMode::Hidden | CursorMode::Grabbed => self.get_invisible_cursor()?,
+        };
+        let connection = self.get_connection()?;
+
+        if self.cursor_mode == CursorMode::Grabbed {
+            connection.send_request(&xcb::x::UngrabPointer {
+                time: xcb::x::CURRENT_TIME,
+            });
+        }
+
+        if let Err(err) = connection.check_request(connection.send_request_checked(
+            &xcb::x::ChangeWindowAttributes {
+                window,
+                value_list: &[xcb::x::Cw::Cursor(cursor)],
+            },
+        )) {
+            error!("Failed to change the window cursor: {:?}", err);
+            return Err(EngineError::UpdateFailed);
+        }
+
+        if mode == CursorMode::Grabbed {
+            let cookie = connection.send_request(&xcb::x::GrabPointer {
+                owner_events: true,
+                grab_window: window,
+                event_mask: xcb::x::EventMask::POINTER_MOTION
+                    | xcb::x::EventMask::BUTTON_PRESS
+                    | xcb::x::EventMask::BUTTON_RELEASE,
+                pointer_mode: xcb::x::GrabMode::Async,
+                keyboard_mode: xcb::x::GrabMode::Async,
+                confine_to: window,
+                cursor,
+                time: xcb::x::CURRENT_TIME,
+            });
+            match connection.wait_for_reply(cookie) {
+                Ok(reply) if reply.status() == xcb::x::GrabStatus::Success => (),
+                Ok(reply) => {
+                    // Another client holds the pointer, the window may not be focused yet
+                    error!("Failed to grab the pointer: {:?}", reply.status());
+                    return Err(EngineError::UpdateFailed);
+                }
+                Err(err) => {
+                    error!("Failed to grab the pointer: {:?}", err);
+                    return Err(EngineError::UpdateFailed);
+                }
+            }
+        }
+        self.cursor_mode = mode;
+
+        if mode == CursorMode::Grabbed {
+            let (center_x, center_y) = self.get_window_center();
+            self.warp_cursor(center_x, center_y)?;
+        }
+        Ok(())
+    }
+
     fn get_required_extensions(&self) -> Result<Vec<*const i8>, EngineError> {
         let required_extensions_cstr =
             [unsafe { CStr::from_bytes_with_nul_unchecked(b"VK_KHR_xcb_surface\0") }];
@@ -423,9 +633,8 @@ impl Platform for PlatformLinux {
 
     fn get_vulkan_surface(
         &self,
-        vulkan_context: &VulkanContext,
+        vulkan_context: &VulkanCoreContext,
     ) -> Result<ash::vk::SurfaceKHR, EngineError> {
-        let connection = self.connection.as_ref().unwrap();
         let window = match self.window {
             Some(window) => window,
             None => {
@@ -433,7 +642,83 @@ impl Platform for PlatformLinux {
                 return Err(EngineError::Unknown);
             }
         };
+        self.create_xcb_vulkan_surface(window, vulkan_context)
+    }
+
+    fn get_window_vulkan_surface(
+        &self,
+        window: WindowHandle,
+        vulkan_context: &VulkanCoreContext,
+    ) -> Result<ash::vk::SurfaceKHR, EngineError> {
+        if window.is_main() {
+            return self.get_vulkan_surface(vulkan_context);
+        }
+        let xcb_window = match self.secondary_windows.get(&window) {
+            Some(xcb_window) => *xcb_window,
+            None => {
+                error!(
+                    "Can't create the vulkan surface of the unknown window {:?}",
+                    window
+                );
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        self.create_xcb_vulkan_surface(xcb_window, vulkan_context)
+    }
+}
+
+impl PlatformLinux {
+    /// The connection is only open between the initialization and the shutdown of the platform
+    fn get_connection(&self) -> Result<&xcb::Connection, EngineError> {
+        match &self.connection {
+            Some(connection) => Ok(connection),
+            None => {
+                error!("The connection to the X server is not initialized");
+                Err(EngineError::NotInitialized)
+            }
+        }
+    }
+
+    fn get_screen(&self) -> Result<&xcb::x::ScreenBuf, EngineError> {
+        match &self.screen {
+            Some(screen) => Ok(screen),
+            None => {
+                error!("The X screen is not initialized");
+                Err(EngineError::NotInitialized)
+            }
+        }
+    }
 
+    /// The protocols and delete window atoms, used to be told when a window is closed
+    fn get_window_manager_atoms(&self) -> Result<(xcb::x::Atom, xcb::x::Atom), EngineError> {
+        match (
+            self.window_manager_protocols,
+            self.window_manager_delete_window,
+        ) {
+            (Some(protocols), Some(delete_window)) => Ok((protocols, delete_window)),
+            _ => {
+                error!("The window manager atoms are not initialized");
+                Err(EngineError::NotInitialized)
+            }
+        }
+    }
+
+    fn get_window(&self) -> Result<xcb::x::Window, EngineError> {
+        match self.window {
+            Some(window) => Ok(window),
+            None => {
+                error!("The xcb window is not initialized");
+                Err(EngineError::NotInitialized)
+            }
+        }
+    }
+
+    fn create_xcb_vulkan_surface(
+        &self,
+        window: xcb::x::Window,
+        vulkan_context: &VulkanCoreContext,
+    ) -> Result<ash::vk::SurfaceKHR, EngineError> {
+        let connection = self.get_connection()?;
         let create_info_khr = XcbSurfaceCreateInfoKHR::default()
             .connection(connection.get_raw_conn() as *mut c_void)
             .window(window.resource_id());
@@ -467,9 +752,300 @@ impl Platform for PlatformLinux {
 
         Ok(surface)
     }
-}
 
-impl PlatformLinux {
+    /// Handle a single event, return true if should quit
+    fn handle_event(&mut self, event: xcb::Event) -> Result<bool, EngineError> {
+        let received = Instant::now();
+        match event {
+            // Input events
+            xcb::Event::Unknown(_) => (),
+            // The monitors are queried when needed, their change events are not selected
+            xcb::Event::RandR(_) => (),
+            xcb::Event::X(event) => {
+                match event {
+                    // Keyboard press / release
+                    xcb::x::Event::KeyPress(event) => {
+                        let key_code = event.detail();
+                        let key_mask = if event.state().contains(xcb::x::KeyButMask::SHIFT) {
+                            1
+                        } else {
+                            0
+                        };
+                        if let Some(key) = self.translate_keycode(key_code, key_mask) {
+                            // debug!("code pressed: {:?}", key);
+                            intput_process_key(key, KeyState::Pressed)?;
+                        };
+                        if let Some(character) = self.translate_character(key_code, event.state()) {
+                            input_process_char(character)?;
+                        }
+                        input_latency_record_event(received, Some(event.time()))?;
+                    }
+                    xcb::x::Event::KeyRelease(event) => {
+                        let key_code = event.detail();
+                        let key_mask = if event.state().contains(xcb::x::KeyButMask::SHIFT) {
+                            1
+                        } else {
+                            0
+                        };
+                        if let Some(key) = self.translate_keycode(key_code, key_mask) {
+                            // debug!("code release: {:?}", key);
+                            intput_process_key(key, KeyState::Released)?;
+                        };
+                        input_latency_record_event(received, Some(event.time()))?;
+                    }
+
+                    // Mouse press / release
+                    xcb::x::Event::ButtonPress(event) => {
+                        let button = event.detail() as u32;
+                        if button == xcb::x::ButtonIndex::N1 as u32 {
+                            input_process_mouse_button(
+                                MouseButton::Left,
+                                MouseButtonState::Pressed,
+                            )?;
+                            // debug!("left button pressed");
+                        } else if button == xcb::x::ButtonIndex::N2 as u32 {
+                            input_process_mouse_button(
+                                MouseButton::Middle,
+                                MouseButtonState::Pressed,
+                            )?;
+                            // debug!("middle button pressed");
+                        } else if button == xcb::x::ButtonIndex::N3 as u32 {
+                            input_process_mouse_button(
+                                MouseButton::Right,
+                                MouseButtonState::Pressed,
+                            )?;
+                            // debug!("right button pressed");
+                        } else if button == xcb::x::ButtonIndex::N4 as u32 {
+                            // X11 sends each wheel step as a click of the buttons 4 and 5
+                            input_process_mouse_wheel(1)?;
+                        } else if button == xcb::x::ButtonIndex::N5 as u32 {
+                            input_process_mouse_wheel(-1)?;
+                        } else {
+                            warn!("Unknown mouse button: {:?}", button);
+                        };
+                        input_latency_record_event(received, Some(event.time()))?;
+                    }
+                    xcb::x::Event::ButtonRelease(event) => {
+                        let button = event.detail() as u32;
+                        if button == xcb::x::ButtonIndex::N1 as u32 {
+                            input_process_mouse_button(
+                                MouseButton::Left,
+                                MouseButtonState::Released,
+                            )?;
+                            // debug!("left button released");
+                        } else if button == xcb::x::ButtonIndex::N2 as u32 {
+                            input_process_mouse_button(
+                                MouseButton::Middle,
+                                MouseButtonState::Released,
+                            )?;
+                            // debug!("middle button released");
+                        } else if button == xcb::x::ButtonIndex::N3 as u32 {
+                            input_process_mouse_button(
+                                MouseButton::Right,
+                                MouseButtonState::Released,
+                            )?;
+                            // debug!("right button released");
+                        } else if button == xcb::x::ButtonIndex::N4 as u32
+                            || button == xcb::x::ButtonIndex::N5 as u32
+                        {
+                            // The wheel step was handled by the press
+                        } else {
+                            warn!("Unknown mouse button: {:?}", button);
+                        };
+                        input_latency_record_event(received, Some(event.time()))?;
+                    }
+
+                    // Mouse movement
+                    xcb::x::Event::MotionNotify(event) => {
+                        // debug!("mouse pos: ({}, {})", event.event_x(), event.event_y());
+                        input_process_mouse_move(event.event_x(), event.event_y())?;
+                        input_latency_record_event(received, Some(event.time()))?;
+                        if self.cursor_mode == CursorMode::Grabbed {
+                            self.recenter_grabbed_cursor(event.event_x(), event.event_y())?;
+                        }
+                    }
+
+                    // Resizing
+                    xcb::x::Event::ConfigureNotify(event)
+                        if Some(event.window()) == self.parent_window =>
+                    {
+                        // Our window follows the host window, its own event fires the resize
+                        let connection = self.get_connection()?;
+                        connection.send_request(&xcb::x::ConfigureWindow {
+                            window: self.get_window()?,
+                            value_list: &[
+                                xcb::x::ConfigWindow::Width(event.width() as u32),
+                                xcb::x::ConfigWindow::Height(event.height() as u32),
+                            ],
+                        });
+                        if let Err(err) = connection.flush() {
+                            error!("Failed to resize the embedded window: {:?}", err);
+                            return Err(EngineError::UpdateFailed);
+                        }
+                    }
+                    // This is also triggered by moving the window
+                    xcb::x::Event::ConfigureNotify(event)
+                        if Some(event.window()) == self.window
+                            && (event.width(), event.height()) != self.window_size =>
+                    {
+                        self.window_size = (event.width(), event.height());
+                        let new_event = EventCode::Resized {
+                            width: event.width() as u32,
+                            height: event.height() as u32,
+                        };
+                        event_fire(new_event)?;
+                    }
+
+                    // Minimizing or hiding the window unmaps it
+                    xcb::x::Event::UnmapNotify(event) if Some(event.window()) == self.window => {
+                        event_fire(EventCode::ApplicationSuspended)?;
+                    }
+                    xcb::x::Event::MapNotify(event) if Some(event.window()) == self.window => {
+                        event_fire(EventCode::ApplicationResumed)?;
+                    }
+
+                    xcb::x::Event::ClientMessage(client_message_event) => {
+                        // Window closing
+                        let message_index_zero = match client_message_event.data() {
+                            xcb::x::ClientMessageData::Data8(data) => data[0] as u32,
+                            xcb::x::ClientMessageData::Data16(data) => data[0] as u32,
+                            xcb::x::ClientMessageData::Data32(data) => data[0],
+                        };
+                        if Some(message_index_zero)
+                            == self
+                                .window_manager_delete_window
+                                .map(|atom| atom.resource_id())
+                        {
+                            // The application decides when to destroy the additional windows
+                            let closed = self
+                                .secondary_windows
+                                .iter()
+                                .find(|(_, window)| **window == client_message_event.window());
+                            match closed {
+                                Some((handle, _)) => event_fire(EventCode::WindowClosed {
+                                    window: handle.get_id(),
+                                })?,
+                                None => return Ok(true),
+                            }
+                        }
+                    }
+
+                    // Other events
+                    _ => (), // Ignore other events
+                }
+            }
+        }
+        Ok(false)
+    }
+
+    fn get_window_center(&self) -> (i16, i16) {
+        (
+            (self.window_size.0 / 2) as i16,
+            (self.window_size.1 / 2) as i16,
+        )
+    }
+
+    /// An empty 1x1 cursor, X has no request to simply hide the cursor
+    fn get_invisible_cursor(&mut self) -> Result<xcb::x::Cursor, EngineError> {
+        if let Some(cursor) = self.invisible_cursor {
+            return Ok(cursor);
+        }
+        let connection = self.get_connection()?;
+        let pixmap: xcb::x::Pixmap = connection.generate_id();
+        let cursor: xcb::x::Cursor = connection.generate_id();
+        connection.send_request(&xcb::x::CreatePixmap {
+            depth: 1,
+            pid: pixmap,
+            drawable: xcb::x::Drawable::Window(self.get_window()?),
+            width: 1,
+            height: 1,
+        });
+        // The mask pixmap content is undefined but a 1x1 cursor is not noticeable
+        let cookie = connection.send_request_checked(&xcb::x::CreateCursor {
+            cid: cursor,
+            source: pixmap,
+            mask: pixmap,
+            fore_red: 0,
+            fore_green: 0,
+            fore_blue: 0,
+            back_red: 0,
+            back_green: 0,
+            back_blue: 0,
+            x: 0,
+            y: 0,
+        });
+        let result = connection.check_request(cookie);
+        connection.send_request(&xcb::x::FreePixmap { pixmap });
+        if let Err(err) = result {
+            error!("Failed to create the invisible cursor: {:?}", err);
+            return Err(EngineError::InitializationFailed);
+        }
+        self.invisible_cursor = Some(cursor);
+        Ok(cursor)
+    }
+
+    /// Move the pointer inside our window, the input system doesn't see it as a motion
+    fn warp_cursor(&self, x: i16, y: i16) -> Result<(), EngineError> {
+        let connection = self.get_connection()?;
+        connection.send_request(&xcb::x::WarpPointer {
+            src_window: xcb::x::Window::none(),
+            dst_window: self.get_window()?,
+            src_x: 0,
+            src_y: 0,
+            src_width: 0,
+            src_height: 0,
+            dst_x: x,
+            dst_y: y,
+        });
+        if let Err(err) = connection.flush() {
+            error!("Failed to warp the pointer: {:?}", err);
+            return Err(EngineError::UpdateFailed);
+        }
+        input_process_mouse_warp(x, y)
+    }
+
+    /// Keep the grabbed pointer away from the window borders so the motion never stops
+    fn recenter_grabbed_cursor(&self, x: i16, y: i16) -> Result<(), EngineError> {
+        let (center_x, center_y) = self.get_window_center();
+        if (x, y) == (center_x, center_y) {
+            // The motion generated by our own warp
+            return Ok(());
+        }
+        self.warp_cursor(center_x, center_y)
+    }
+
+    /// Listen to the resizes of the host window and fetch its size
+    fn init_parent_window(
+        connection: &xcb::Connection,
+        parent: xcb::x::Window,
+    ) -> Result<(u32, u32), EngineError> {
+        let cookie = connection.send_request(&xcb::x::GetGeometry {
+            drawable: xcb::x::Drawable::Window(parent),
+        });
+        let (width, height) = match connection.wait_for_reply(cookie) {
+            Ok(reply) => (reply.width() as u32, reply.height() as u32),
+            Err(err) => {
+                error!(
+                    "Failed to fetch the geometry of the external window: {:?}",
+                    err
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+
+        // Other clients can listen to the structure of a window they don't own
+        let cookie = connection.send_request_checked(&xcb::x::ChangeWindowAttributes {
+            window: parent,
+            value_list: &[xcb::x::Cw::EventMask(xcb::x::EventMask::STRUCTURE_NOTIFY)],
+        });
+        if let Err(err) = connection.check_request(cookie) {
+            error!("Failed to listen to the external window events: {:?}", err);
+            return Err(EngineError::InitializationFailed);
+        }
+
+        Ok((width, height))
+    }
+
     pub fn get_color(log_level: LogLevel) -> &'static str {
         match log_level {
             // https://www.lihaoyi.com/post/BuildyourownCommandLinewithANSIescapecodes.html for other ANSI codes
@@ -481,15 +1057,48 @@ impl PlatformLinux {
     }
 
     // Key translation
-    fn translate_keycode(&self, xcb_keycode: u8, col: i32) -> Option<Key> {
+    /// Character typed by the key, none for the shortcuts and the keys without one
+    fn translate_character(&self, xcb_keycode: u8, state: xcb::x::KeyButMask) -> Option<char> {
+        if state.intersects(xcb::x::KeyButMask::CONTROL | xcb::x::KeyButMask::MOD1) {
+            return None;
+        }
+        let is_shifted = state.contains(xcb::x::KeyButMask::SHIFT);
         let keysym: u32 = unsafe {
             xcb_util::ffi::keysyms::xcb_key_symbols_get_keysym(
-                self.key_symbols.unwrap(),
+                self.key_symbols?,
                 xcb_keycode,
-                col,
+                is_shifted as i32,
             )
         };
 
+        let character = match keysym {
+            // Latin 1 keysyms are their own code point
+            0x0020..=0x007E | 0x00A0..=0x00FF => char::from_u32(keysym)?,
+            // Unicode keysyms
+            0x0100_0100..=0x0110_FFFF => char::from_u32(keysym - 0x0100_0000)?,
+            // Keypad digits and operators
+            0xFFAA..=0xFFB9 => char::from_u32(keysym - 0xFF80)?,
+            _ => return None,
+        };
+        // Caps lock only affects the letters, and shift cancels it
+        if state.contains(xcb::x::KeyButMask::LOCK) && character.is_alphabetic() {
+            let swapped: Vec<char> = if character.is_lowercase() {
+                character.to_uppercase().collect()
+            } else {
+                character.to_lowercase().collect()
+            };
+            if let [swapped] = swapped[..] {
+                return Some(swapped);
+            }
+        }
+        Some(character)
+    }
+
+    fn translate_keycode(&self, xcb_keycode: u8, col: i32) -> Option<Key> {
+        let keysym: u32 = unsafe {
+            xcb_util::ffi::keysyms::xcb_key_symbols_get_keysym(self.key_symbols?, xcb_keycode, col)
+        };
+
         match keysym {
             0xFF08 => Some(Key::BACKSPACE),
             0xFF0D => Some(Key::ENTER),
@@ -0,0 +1,654 @@
+/// MacOS implementation of the platform trait, vulkan runs on top of metal through MoltenVK
+use std::{
+    ffi::{c_char, CStr},
+    time::{Duration, Instant},
+};
+
+use ash::vk::{InstanceCreateFlags, MetalSurfaceCreateInfoEXT};
+use raw_window_handle::{HasWindowHandle, RawWindowHandle};
+use raw_window_metal::Layer;
+use winit::{
+    application::ApplicationHandler,
+    dpi::{LogicalPosition, LogicalSize},
+    event::{
+        DeviceEvent, DeviceId, ElementState, KeyEvent, MouseButton as WinitMouseButton,
+        MouseScrollDelta, WindowEvent,
+    },
+    event_loop::{ActiveEventLoop, EventLoop},
+    keyboard::{KeyCode, PhysicalKey},
+    platform::pump_events::{EventLoopExtPumpEvents, PumpStatus},
+    window::{CursorGrabMode, Window, WindowAttributes, WindowId},
+};
+
+use crate::{
+    core::{
+        application::{
+            window_geometry::{MonitorArea, WindowGeometry},
+            ExternalWindow,
+        },
+        debug::{errors::EngineError, input_latency::input_latency_record_event},
+        systems::{
+            events::{event_fire, EventCode},
+            input::{
+                keyboard::{input_process_char, intput_process_key, Key, KeyState},
+                mouse::{
+                    input_process_mouse_button, input_process_mouse_move, input_process_mouse_warp,
+                    input_process_mouse_wheel, intput_get_mouse_position, CursorMode, MouseButton,
+                    MouseButtonState,
+                },
+            },
+            logger::LogLevel,
+        },
+    },
+    error,
+    renderer::vulkan::vulkan_types::VulkanCoreContext,
+    warn,
+};
+
+use super::platform::{Platform, PLATFORM_WAIT_EVENTS_FALLBACK_MS};
+
+/// State modified by the winit callbacks
+#[derive(Default)]
+struct PlatformMacOSState {
+    window_attributes: WindowAttributes,
+    window: Option<Window>,
+    should_quit: bool,
+    /// First error raised in a callback, returned once the events are pumped
+    error: Option<EngineError>,
+    /// The locked cursor doesn't move, the motion comes from the device events
+    cursor_mode: CursorMode,
+}
+
+#[derive(Default)]
+pub(crate) struct PlatformMacOS {
+    // Internal state
+    event_loop: Option<EventLoop<()>>,
+    state: PlatformMacOSState,
+    /// Metal layer backing the window view, must outlive the vulkan surface
+    layer: Option<Layer>,
+    /// The host application owns the window and the event loop
+    is_embedded: bool,
+}
+
+impl PlatformMacOSState {
+    fn process(&mut self, result: Result<(), EngineError>) {
+        if let Err(err) = result {
+            self.error.get_or_insert(err);
+        }
+    }
+
+    fn process_key(&mut self, event: KeyEvent) {
+        let key_code = match event.physical_key {
+            PhysicalKey::Code(key_code) => key_code,
+            PhysicalKey::Unidentified(_) => return,
+        };
+        let state = match event.state {
+            ElementState::Pressed => KeyState::Pressed,
+            ElementState::Released => KeyState::Released,
+        };
+        if let Some(key) = PlatformMacOS::translate_keycode(key_code) {
+            self.process(intput_process_key(key, state));
+        }
+        // Winit already applied the modifiers and the keyboard layout
+        if let (KeyState::Pressed, Some(text)) = (state, event.text) {
+            for character in text.chars() {
+                self.process(input_process_char(character));
+            }
+        }
+    }
+
+    fn process_mouse_button(&mut self, button: WinitMouseButton, state: ElementState) {
+        let button = match button {
+            WinitMouseButton::Left => MouseButton::Left,
+            WinitMouseButton::Middle => MouseButton::Middle,
+            WinitMouseButton::Right => MouseButton::Right,
+            _ => {
+                warn!("Unknown mouse button: {:?}", button);
+                return;
+            }
+        };
+        let state = match state {
+            ElementState::Pressed => MouseButtonState::Pressed,
+            ElementState::Released => MouseButtonState::Released,
+        };
+        self.process(input_process_mouse_button(button, state));
+    }
+
+    /// Move the locked cursor by the delta and put it back where it was
+    fn process_locked_mouse_motion(&mut self, delta_x: f64, delta_y: f64) {
+        let (x, y) = match intput_get_mouse_position() {
+            Ok(position) => position,
+            Err(err) => return self.process(Err(err)),
+        };
+        self.process(input_process_mouse_move(
+            x.saturating_add(delta_x as i16),
+            y.saturating_add(delta_y as i16),
+        ));
+        self.process(input_process_mouse_warp(x, y));
+    }
+}
+
+impl ApplicationHandler for PlatformMacOSState {
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        // Called once when the application starts on macos
+        if self.window.is_some() {
+            return;
+        }
+        match event_loop.create_window(self.window_attributes.clone()) {
+            Ok(window) => self.window = Some(window),
+            Err(err) => {
+                error!("Failed to create the window: {:?}", err);
+                self.error = Some(EngineError::InitializationFailed);
+            }
+        }
+    }
+
+    fn window_event(&mut self, _event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        // Winit doesn't give the time of the events
+        let received = Instant::now();
+        match event {
+            // Keyboard press / release
+            WindowEvent::KeyboardInput { event, .. } => {
+                self.process_key(event);
+                self.process(input_latency_record_event(received, None));
+            }
+
+            // Mouse press / release
+            WindowEvent::MouseInput { state, button, .. } => {
+                self.process_mouse_button(button, state);
+                self.process(input_latency_record_event(received, None));
+            }
+
+            // Mouse movement
+            WindowEvent::CursorMoved { position, .. } => {
+                self.process(input_process_mouse_move(
+                    position.x as i16,
+                    position.y as i16,
+                ));
+                self.process(input_latency_record_event(received, None));
+            }
+
+            // Mouse wheel, one step per line
+            WindowEvent::MouseWheel { delta, .. } => {
+                let z_delta = match delta {
+                    MouseScrollDelta::LineDelta(_, y) => y,
+                    MouseScrollDelta::PixelDelta(position) => position.y as f32,
+                };
+                if z_delta != 0.0 {
+                    self.process(input_process_mouse_wheel(z_delta.signum() as i8));
+                }
+                self.process(input_latency_record_event(received, None));
+            }
+
+            // Resizing
+            WindowEvent::Resized(size) => {
+                let new_event = EventCode::Resized {
+                    width: size.width,
+                    height: size.height,
+                };
+                self.process(event_fire(new_event));
+            }
+
+            // Minimized or fully covered
+            WindowEvent::Occluded(is_occluded) => {
+                self.process(event_fire(if is_occluded {
+                    EventCode::ApplicationSuspended
+                } else {
+                    EventCode::ApplicationResumed
+                }));
+            }
+
+            // Window closing
+            WindowEvent::CloseRequested => self.should_quit = true,
+
+            // Other events
+            _ => (),
+        }
+    }
+
+    fn device_event(
+        &mut self,
+        _event_loop: &ActiveEventLoop,
+        _device_id: DeviceId,
+        event: DeviceEvent,
+    ) {
+        if let DeviceEvent::MouseMotion { delta } = event {
+            if self.cursor_mode == CursorMode::Grabbed {
+                self.process_locked_mouse_motion(delta.0, delta.1);
+                self.process(input_latency_record_event(Instant::now(), None));
+            }
+        }
+    }
+}
+
+impl Platform for PlatformMacOS {
+    fn init(
+        &mut self,
+        window_title: String,
+        x: i16,
+        y: i16,
+        width: u32,
+        height: u32,
+        resizable: bool,
+        external_window: Option<ExternalWindow>,
+    ) -> Result<(), EngineError> {
+        if let Some(ExternalWindow::AppKit { ns_view }) = external_window {
+            // Safe as the host keeps the view alive while the engine runs
+            self.layer = Some(unsafe { Layer::from_ns_view(ns_view) });
+            self.is_embedded = true;
+            return Ok(());
+        }
+
+        let event_loop = match EventLoop::new() {
+            Ok(event_loop) => event_loop,
+            Err(err) => {
+                error!("Failed to create the macos event loop: {:?}", err);
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        self.event_loop = Some(event_loop);
+
+        self.state.window_attributes = Window::default_attributes()
+            .with_title(window_title)
+            .with_position(LogicalPosition::new(x, y))
+            .with_inner_size(LogicalSize::new(width, height))
+            .with_resizable(resizable);
+
+        // The window is created by the first resumed event
+        self.handle_events()?;
+        if self.state.window.is_none() {
+            error!("Failed to create the window: the application was never resumed");
+            return Err(EngineError::InitializationFailed);
+        }
+
+        self.init_metal_layer()
+    }
+
+    fn shutdown(&mut self) -> Result<(), EngineError> {
+        // The layer and the window are released when dropped
+        self.layer = None;
+        self.state.window = None;
+        self.event_loop = None;
+        Ok(())
+    }
+
+    fn get_absolute_time_in_seconds(&self) -> Result<f64, EngineError> {
+        match std::time::SystemTime::now().duration_since(std::time::SystemTime::UNIX_EPOCH) {
+            Ok(duration) => Ok(duration.as_secs_f64()),
+            Err(_) => {
+                error!("SystemTime before UNIX EPOCH!");
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    fn sleep_from_milliseconds(&self, ms: u64) -> Result<(), EngineError> {
+        let duration_from_milliseconds = std::time::Duration::from_millis(ms);
+        std::thread::sleep(duration_from_milliseconds);
+        Ok(())
+    }
+
+    fn handle_events(&mut self) -> Result<bool, EngineError> {
+        // The host pumps its own events
+        if self.is_embedded {
+            return Ok(false);
+        }
+        // Consume the pending events without blocking
+        self.pump_events(Some(Duration::ZERO))
+    }
+
+    fn wait_for_events(&mut self) -> Result<bool, EngineError> {
+        if self.is_embedded {
+            self.sleep_from_milliseconds(PLATFORM_WAIT_EVENTS_FALLBACK_MS)?;
+            return Ok(false);
+        }
+        // No timeout blocks until the next event
+        self.pump_events(None)
+    }
+
+    fn console_write(message: &str, log_level: LogLevel) {
+        print!(
+            "\x1B[{}m{}\x1B[0m",
+            PlatformMacOS::get_color(log_level),
+            message
+        );
+    }
+
+    fn console_write_error(message: &str, log_level: LogLevel) {
+        eprint!(
+            "\x1B[{}m{}\x1B[0m",
+            PlatformMacOS::get_color(log_level),
+            message
+        );
+    }
+
+    /// In logical points like the initial window parameters
+    fn get_window_geometry(&self) -> Option<WindowGeometry> {
+        if self.is_embedded {
+            return None;
+        }
+        let window = self.state.window.as_ref()?;
+        let scale_factor = window.scale_factor();
+        let position: LogicalPosition<i32> = match window.outer_position() {
+            Ok(position) => position.to_logical(scale_factor),
+            Err(err) => {
+                error!("Failed to get the window position: {:?}", err);
+                return None;
+            }
+        };
+        let size: LogicalSize<u32> = window.inner_size().to_logical(scale_factor);
+        Some(WindowGeometry {
+            x: position.x,
+            y: position.y,
+            width: size.width,
+            height: size.height,
+            monitor: 0,
+        })
+    }
+
+    fn set_window_geometry(&mut self, geometry: &WindowGeometry) -> Result<(), EngineError> {
+        if self.is_embedded {
+            error!("Can't move the window, it is owned by the host application");
+            return Err(EngineError::InvalidValue);
+        }
+        let window = match self.state.window.as_ref() {
+            Some(window) => window,
+            None => {
+                error!("Failed to set the window geometry: the macos window is not created");
+                return Err(EngineError::NotInitialized);
+            }
+        };
+        window.set_outer_position(LogicalPosition::new(geometry.x, geometry.y));
+        // The new size comes back with a resize event once it is applied
+        let _ = window.request_inner_size(LogicalSize::new(geometry.width, geometry.height));
+        Ok(())
+    }
+
+    fn get_monitors(&self) -> Vec<MonitorArea> {
+        let window = match self.state.window.as_ref() {
+            Some(window) => window,
+            None => return Vec::new(),
+        };
+        window
+            .available_monitors()
+            .map(|monitor| {
+                let scale_factor = monitor.scale_factor();
+                let position: LogicalPosition<i32> = monitor.position().to_logical(scale_factor);
+                let size: LogicalSize<u32> = monitor.size().to_logical(scale_factor);
+                MonitorArea {
+                    x: position.x,
+                    y: position.y,
+                    width: size.width,
+                    height: size.height,
+                }
+            })
+            .collect()
+    }
+
+    fn set_cursor_mode(&mut self, mode: CursorMode) -> Result<(), EngineError> {
+        let window = match self.state.window.as_ref() {
+            Some(window) => window,
+            None => {
+                error!("Failed to set the cursor mode: the macos window is not created");
+                return Err(EngineError::NotInitialized);
+            }
+        };
+        // Macos can't confine the cursor, it is locked in place instead
+        let grab_mode = match mode {
+            CursorMode::Grabbed => CursorGrabMode::Locked,
+            CursorMode::Normal | CursorMode::Hidden => CursorGrabMode::None,
+        };
+        if let Err(err) = window.set_cursor_grab(grab_mode) {
+            error!("Failed to grab the cursor: {:?}", err);
+            return Err(EngineError::UpdateFailed);
+        }
+        window.set_cursor_visible(mode == CursorMode::Normal);
+        self.state.cursor_mode = mode;
+        Ok(())
+    }
+
+    fn get_required_extensions(&self) -> Result<Vec<*const i8>, EngineError> {
+        let required_extensions_cstr = [
+            unsafe { CStr::from_bytes_with_nul_unchecked(b"VK_EXT_metal_surface\0") },
+            // MoltenVK is not a conformant implementation, it is only listed with this extension
+            unsafe { CStr::from_bytes_with_nul_unchecked(b"VK_KHR_portability_enumeration\0") },
+            unsafe {
+                CStr::from_bytes_with_nul_unchecked(b"VK_KHR_get_physical_device_properties2\0")
+            },
+        ];
+
+        let required_extensions: Vec<*const c_char> = required_extensions_cstr
+            .iter()
+            .map(|raw_name| raw_name.as_ptr())
+            .collect();
+
+        Ok(required_extensions)
+    }
+
+    fn get_instance_create_flags(&self) -> InstanceCreateFlags {
+        InstanceCreateFlags::ENUMERATE_PORTABILITY_KHR
+    }
+
+    fn get_vulkan_surface(
+        &self,
+        vulkan_context: &VulkanCoreContext,
+    ) -> Result<ash::vk::SurfaceKHR, EngineError> {
+        let layer = match &self.layer {
+            Some(layer) => layer,
+            None => {
+                error!("Failed to fetch the metal layer when creating the vulkan surface on macos");
+                return Err(EngineError::NotInitialized);
+            }
+        };
+
+        let create_info_ext = MetalSurfaceCreateInfoEXT::default().layer(layer.as_ptr().as_ptr());
+
+        let vk_entry = match &vulkan_context.entry {
+            Some(entry) => entry,
+            None => {
+                error!("Failed to fetch the vulkan entry when creating the surface on macos");
+                return Err(EngineError::NotInitialized);
+            }
+        };
+        let vk_instance = match &vulkan_context.instance {
+            Some(instance) => instance,
+            None => {
+                error!("Failed to fetch the vulkan instance when creating the surface on macos");
+                return Err(EngineError::NotInitialized);
+            }
+        };
+        // create surface instance
+        let surface_instance = ash::ext::metal_surface::Instance::new(vk_entry, vk_instance);
+        // create the surface
+        let surface = unsafe {
+            match surface_instance.create_metal_surface(&create_info_ext, vulkan_context.allocator)
+            {
+                Ok(surface) => surface,
+                Err(err) => {
+                    error!("Failed to create the metal surface: {:?}", err);
+                    return Err(EngineError::VulkanFailed);
+                }
+            }
+        };
+
+        Ok(surface)
+    }
+}
+
+impl PlatformMacOS {
+    pub fn get_color(log_level: LogLevel) -> &'static str {
+        match log_level {
+            LogLevel::Error => "1;31",   // Red foreground
+            LogLevel::Warning => "1;33", // Yellow foreground
+            LogLevel::Debug => "1;34",   // Blue foreground
+            LogLevel::Info => "1;32",    // Green foreground
+        }
+    }
+
+    /// Run the event loop until the timeout, None waits for the next event
+    /// Return true if should quit
+    fn pump_events(&mut self, timeout: Option<Duration>) -> Result<bool, EngineError> {
+        let event_loop = match self.event_loop.as_mut() {
+            Some(event_loop) => event_loop,
+            None => {
+                error!("Failed to fetch the macos event loop when handling the events");
+                return Err(EngineError::NotInitialized);
+            }
+        };
+
+        if let PumpStatus::Exit(code) = event_loop.pump_app_events(timeout, &mut self.state) {
+            warn!("The macos event loop exited with the code {}", code);
+            self.state.should_quit = true;
+        }
+
+        match self.state.error.take() {
+            Some(err) => Err(err),
+            None => Ok(self.state.should_quit),
+        }
+    }
+
+    /// Attach a metal layer to the window view, needed to create the vulkan surface
+    fn init_metal_layer(&mut self) -> Result<(), EngineError> {
+        let window = match &self.state.window {
+            Some(window) => window,
+            None => {
+                error!("Failed to fetch the window when creating the metal layer");
+                return Err(EngineError::NotInitialized);
+            }
+        };
+        let handle = match window.window_handle() {
+            Ok(handle) => handle.as_raw(),
+            Err(err) => {
+                error!("Failed to fetch the window handle: {:?}", err);
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        let layer = match handle {
+            // Safe as the view is owned by the window which is alive
+            RawWindowHandle::AppKit(handle) => unsafe { Layer::from_ns_view(handle.ns_view) },
+            _ => {
+                error!("The window handle is not an appkit handle: {:?}", handle);
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        self.layer = Some(layer);
+        Ok(())
+    }
+
+    // Key translation
+    fn translate_keycode(key_code: KeyCode) -> Option<Key> {
+        match key_code {
+            KeyCode::Backspace => Some(Key::BACKSPACE),
+            KeyCode::Enter => Some(Key::ENTER),
+            KeyCode::Tab => Some(Key::TAB),
+            KeyCode::ShiftLeft => Some(Key::LSHIFT),
+            KeyCode::ShiftRight => Some(Key::RSHIFT),
+            KeyCode::ControlLeft => Some(Key::LCONTROL),
+            KeyCode::ControlRight => Some(Key::RCONTROL),
+            KeyCode::Pause => Some(Key::PAUSE),
+            KeyCode::CapsLock => Some(Key::CAPITAL),
+            KeyCode::Escape => Some(Key::ESCAPE),
+            KeyCode::Convert => Some(Key::CONVERT),
+            KeyCode::NonConvert => Some(Key::NONCONVERT),
+            KeyCode::Space => Some(Key::SPACE),
+            KeyCode::PageUp => Some(Key::PRIOR),
+            KeyCode::PageDown => Some(Key::NEXT),
+            KeyCode::End => Some(Key::END),
+            KeyCode::Home => Some(Key::HOME),
+            KeyCode::ArrowLeft => Some(Key::LEFT),
+            KeyCode::ArrowUp => Some(Key::UP),
+            KeyCode::ArrowRight => Some(Key::RIGHT),
+            KeyCode::ArrowDown => Some(Key::DOWN),
+            KeyCode::PrintScreen => Some(Key::PRINT),
+            KeyCode::Insert => Some(Key::INSERT),
+            KeyCode::Delete => Some(Key::DELETE),
+            KeyCode::Help => Some(Key::HELP),
+            KeyCode::SuperLeft => Some(Key::LWIN),
+            KeyCode::SuperRight => Some(Key::RWIN),
+            KeyCode::ContextMenu => Some(Key::APPS),
+            KeyCode::Sleep => Some(Key::SLEEP),
+            KeyCode::Numpad0 => Some(Key::NUMPAD0),
+            KeyCode::Numpad1 => Some(Key::NUMPAD1),
+            KeyCode::Numpad2 => Some(Key::NUMPAD2),
+            KeyCode::Numpad3 => Some(Key::NUMPAD3),
+            KeyCode::Numpad4 => Some(Key::NUMPAD4),
+            KeyCode::Numpad5 => Some(Key::NUMPAD5),
+            KeyCode::Numpad6 => Some(Key::NUMPAD6),
+            KeyCode::Numpad7 => Some(Key::NUMPAD7),
+            KeyCode::Numpad8 => Some(Key::NUMPAD8),
+            KeyCode::Numpad9 => Some(Key::NUMPAD9),
+            KeyCode::NumpadMultiply => Some(Key::MULTIPLY),
+            KeyCode::NumpadAdd => Some(Key::ADD),
+            KeyCode::NumpadComma => Some(Key::SEPARATOR),
+            KeyCode::NumpadSubtract => Some(Key::SUBTRACT),
+            KeyCode::NumpadDecimal => Some(Key::DECIMAL),
+            KeyCode::NumpadDivide => Some(Key::DIVIDE),
+            KeyCode::NumpadEqual => Some(Key::NUMPADEQUAL),
+            KeyCode::F1 => Some(Key::F1),
+            KeyCode::F2 => Some(Key::F2),
+            KeyCode::F3 => Some(Key::F3),
+            KeyCode::F4 => Some(Key::F4),
+            KeyCode::F5 => Some(Key::F5),
+            KeyCode::F6 => Some(Key::F6),
+            KeyCode::F7 => Some(Key::F7),
+            KeyCode::F8 => Some(Key::F8),
+            KeyCode::F9 => Some(Key::F9),
+            KeyCode::F10 => Some(Key::F10),
+            KeyCode::F11 => Some(Key::F11),
+            KeyCode::F12 => Some(Key::F12),
+            KeyCode::F13 => Some(Key::F13),
+            KeyCode::F14 => Some(Key::F14),
+            KeyCode::F15 => Some(Key::F15),
+            KeyCode::F16 => Some(Key::F16),
+            KeyCode::F17 => Some(Key::F17),
+            KeyCode::F18 => Some(Key::F18),
+            KeyCode::F19 => Some(Key::F19),
+            KeyCode::F20 => Some(Key::F20),
+            KeyCode::F21 => Some(Key::F21),
+            KeyCode::F22 => Some(Key::F22),
+            KeyCode::F23 => Some(Key::F23),
+            KeyCode::F24 => Some(Key::F24),
+            KeyCode::NumLock => Some(Key::NUMLOCK),
+            KeyCode::ScrollLock => Some(Key::SCROLL),
+            KeyCode::AltLeft => Some(Key::LMENU),
+            KeyCode::AltRight => Some(Key::RMENU),
+            KeyCode::Semicolon => Some(Key::SEMICOLON),
+            KeyCode::Equal => Some(Key::PLUS),
+            KeyCode::Comma => Some(Key::COMMA),
+            KeyCode::Minus => Some(Key::MINUS),
+            KeyCode::Period => Some(Key::PERIOD),
+            KeyCode::Slash => Some(Key::SLASH),
+            KeyCode::Backquote => Some(Key::GRAVE),
+            KeyCode::KeyA => Some(Key::A),
+            KeyCode::KeyB => Some(Key::B),
+            KeyCode::KeyC => Some(Key::C),
+            KeyCode::KeyD => Some(Key::D),
+            KeyCode::KeyE => Some(Key::E),
+            KeyCode::KeyF => Some(Key::F),
+            KeyCode::KeyG => Some(Key::G),
+            KeyCode::KeyH => Some(Key::H),
+            KeyCode::KeyI => Some(Key::I),
+            KeyCode::KeyJ => Some(Key::J),
+            KeyCode::KeyK => Some(Key::K),
+            KeyCode::KeyL => Some(Key::L),
+            KeyCode::KeyM => Some(Key::M),
+            KeyCode::KeyN => Some(Key::N),
+            KeyCode::KeyO => Some(Key::O),
+            KeyCode::KeyP => Some(Key::P),
+            KeyCode::KeyQ => Some(Key::Q),
+            KeyCode::KeyR => Some(Key::R),
+            KeyCode::KeyS => Some(Key::S),
+            KeyCode::KeyT => Some(Key::T),
+            KeyCode::KeyU => Some(Key::U),
+            KeyCode::KeyV => Some(Key::V),
+            KeyCode::KeyW => Some(Key::W),
+            KeyCode::KeyX => Some(Key::X),
+            KeyCode::KeyY => Some(Key::Y),
+            KeyCode::KeyZ => Some(Key::Z),
+            _ => {
+                warn!("Unknown key code: {:?}", key_code);
+                None
+            }
+        }
+    }
+}
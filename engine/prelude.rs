@@ -0,0 +1,217 @@
+//! Public api of the engine, a game only needs `use engine::prelude::*;`
+//! The items not exported here are internals that can change at any time
+
+pub use crate::{
+    core::{
+        application::{
+            application_begin_loading, application_create_window, application_destroy_window,
+            application_get_time, application_is_loading, application_is_paused,
+            application_notify_resized, application_pause, application_resume,
+            application_set_cursor_mode, application_set_frame_rate_limit, application_step_frame,
+            application_toggle_pause,
+            windows::{WindowCreatorParameters, WindowHandle},
+            ApplicationParameters, ApplicationParametersFlags, ExternalWindow,
+        },
+        debug::{
+            errors::EngineError,
+            frame_history::{
+                frame_history_browse, frame_history_dump, frame_history_is_overlay_visible,
+                frame_history_select_frame, frame_history_set_capacity, frame_history_set_enabled,
+                frame_history_set_overlay_visible,
+            },
+            input_latency::{input_latency_dump, input_latency_get_stats, InputLatencyStats},
+            profiler::{
+                profiler_begin_scope, profiler_dump, profiler_end_scope, profiler_get_last_frame,
+                profiler_is_overlay_visible, profiler_set_budget,
+                profiler_set_budget_warning_frames, profiler_set_enabled,
+                profiler_set_overlay_visible,
+                statistics::{
+                    profiler_get_statistics, profiler_set_statistics_log_interval,
+                    profiler_set_statistics_window, EngineStatistics, FrameTimings,
+                },
+                ProfilerScopeView,
+            },
+            telemetry::{telemetry_is_recording, telemetry_start, telemetry_stop, TelemetryFormat},
+        },
+        ecs::{
+            components::{Animator, CameraComponent, MeshRenderer, SpriteRenderer, Transform},
+            ecs_add_component, ecs_add_system, ecs_despawn, ecs_get_component,
+            ecs_get_system_names, ecs_is_alive, ecs_remove_component, ecs_remove_system,
+            ecs_set_active_camera, ecs_set_system_enabled, ecs_spawn, ecs_update_component,
+            ecs_with_world,
+            entity::Entity,
+            system::SystemStage,
+            world::World,
+        },
+        lifecycle::{engine_get_lifecycle_state, EngineLifecycleState},
+        systems::{
+            audio::{
+                audio_get_bus_parameters, audio_is_music_playing, audio_play, audio_play_music,
+                audio_set_bus_parameters, audio_set_bus_volume, audio_set_reverb_parameters,
+                audio_stop, audio_stop_music,
+                dsp::ReverbParameters,
+                music::{AudioStreamDecoder, MusicTrackParameters},
+                vorbis::VorbisStreamDecoder,
+                AudioBus, AudioBusParameters, AUDIO_CHANNEL_COUNT, AUDIO_SAMPLE_RATE,
+            },
+            config::{config_get, config_load},
+            console::{
+                console_execute, console_is_open, console_register_command, console_set_open,
+                console_unregister_command,
+            },
+            events::{
+                event_fire, event_queue, event_register, event_register_exact, event_register_fn,
+                event_unregister, event_unregister_fn, EventCode, EventContext, EventListener,
+                EventListenerHandle,
+            },
+            input::{
+                actions::{
+                    action_add_binding, action_get_bindings, action_get_value, action_is_down,
+                    action_load_bindings, action_remove, action_save_bindings, action_set_bindings,
+                    action_was_pressed, action_was_released, ActionBinding, InputBinding,
+                },
+                gamepad::{GamepadAxis, GamepadButton},
+                gamepad_get_axis, gamepad_get_count, gamepad_is_button_down, gamepad_rumble,
+                gamepad_stop_rumble, gamepad_was_button_down, input_is_key_down, input_is_key_up,
+                input_was_key_down, input_was_key_up,
+                keyboard::{input_is_text_input_enabled, input_set_text_input, Key, KeyState},
+                mouse::{
+                    input_get_mouse_delta, input_get_mouse_wheel_delta, intput_get_mouse_position,
+                    intput_get_mouse_previous_position, CursorMode, MouseButton, MouseButtonState,
+                },
+                smoothing::{AxisResponse, MouseAcceleration, SmoothedAxis},
+            },
+            jobs::{job_is_pending, job_submit, JobHandle},
+            logger::{logger_flush, logger_set_level, LogLevel},
+        },
+    },
+    debug,
+    entry::engine_start,
+    error,
+    game::Game,
+    info,
+    renderer::{
+        accessibility::{AccessibilitySettings, ColorFilterMode, ColorFilterType},
+        exposure::{AutoExposure, AutoExposureParameters},
+        lighting::{DirectionalLight, PointLight},
+        lightmap::{
+            lightmap_bake, LightmapBakeMesh, LightmapBakeParameters, LightmapImage,
+            LightmapPointLight,
+        },
+        object_names::ObjectName,
+        post_process::{
+            FxaaSettings, PostProcessSettings, TonemapOperator, TonemapSettings, VignetteSettings,
+        },
+        reflection_probe::{
+            reflection_probe_bake, reflection_probes_load_or_bake, EnvironmentMap,
+            EnvironmentMapLevel, ReflectionProbe, ReflectionProbeBakeParameters,
+        },
+        render_hooks::{RenderHookContext, RenderHookHandle, RenderStage},
+        renderer_frontend::{
+            renderer_acquire_material, renderer_acquire_object, renderer_acquire_texture,
+            renderer_acquire_texture_by_name, renderer_add_camera, renderer_add_point_light,
+            renderer_camera_screen_to_ray, renderer_create_compute_shader,
+            renderer_create_geometry, renderer_create_storage_buffer,
+            renderer_debug_workspace_dock_panel, renderer_debug_workspace_select_node,
+            renderer_debug_workspace_set_panel_open, renderer_destroy_compute_shader,
+            renderer_destroy_geometry, renderer_destroy_storage_buffer, renderer_dispatch_compute,
+            renderer_draw_circle, renderer_draw_line, renderer_draw_rect, renderer_draw_text,
+            renderer_get_accessibility_settings, renderer_get_auto_exposure, renderer_get_camera,
+            renderer_get_camera_names, renderer_get_closest_reflection_probe,
+            renderer_get_directional_light, renderer_get_draw_call_count, renderer_get_exposure,
+            renderer_get_font, renderer_get_instanced_object_count, renderer_get_main_camera,
+            renderer_get_material, renderer_get_morph_target_names, renderer_get_object_name,
+            renderer_get_output_color_space, renderer_get_point_light,
+            renderer_get_post_process_settings, renderer_get_statistics,
+            renderer_get_transparent_draw_call_count, renderer_get_ui_camera,
+            renderer_instantiate_gltf, renderer_is_debug_camera_active,
+            renderer_is_debug_workspace_visible, renderer_load_font, renderer_load_gltf_async,
+            renderer_load_reflection_probes, renderer_load_texture, renderer_load_texture_async,
+            renderer_measure_text, renderer_pick, renderer_raycast, renderer_read_storage_buffer,
+            renderer_register_render_hook, renderer_release_material, renderer_release_object,
+            renderer_release_texture, renderer_remove_camera, renderer_remove_point_light,
+            renderer_replace_texture, renderer_replace_texture_async, renderer_scene_create_node,
+            renderer_scene_destroy_node, renderer_scene_get_transform,
+            renderer_scene_get_world_transform, renderer_scene_set_parent,
+            renderer_scene_set_renderable, renderer_scene_set_transform, renderer_screen_to_ray,
+            renderer_set_accessibility_settings, renderer_set_active_camera,
+            renderer_set_ambient_light, renderer_set_auto_exposure_enabled,
+            renderer_set_auto_exposure_parameters, renderer_set_camera_projection,
+            renderer_set_camera_target, renderer_set_camera_view, renderer_set_camera_viewport,
+            renderer_set_directional_light, renderer_set_font, renderer_set_geometry_morph_targets,
+            renderer_set_instancing_enabled, renderer_set_main_camera,
+            renderer_set_main_camera_orthographic_height, renderer_set_main_camera_projection,
+            renderer_set_material_diffuse, renderer_set_material_lightmap,
+            renderer_set_material_normal_map, renderer_set_material_render_state,
+            renderer_set_material_specular, renderer_set_morph_weights, renderer_set_object_name,
+            renderer_set_post_process_settings, renderer_set_render_scale,
+            renderer_set_small_object_culling, renderer_set_ui_camera, renderer_set_vsync_mode,
+            renderer_take_screenshot, renderer_texture_read_pixels, renderer_texture_update_region,
+            renderer_toggle_debug_camera, renderer_toggle_debug_workspace,
+            renderer_ui_create_element, renderer_ui_destroy_element, renderer_ui_draw_nine_slice,
+            renderer_ui_draw_quad, renderer_ui_get_element_rect,
+            renderer_ui_set_element_parameters, renderer_unload_font, renderer_unload_gltf,
+            renderer_unregister_render_hook, renderer_update_point_light,
+            renderer_write_storage_buffer,
+        },
+        renderer_types::{
+            AntiAliasing, ComputeResource, DepthFormat, DepthStencilSettings, DisplayOutput,
+            GpuPassTimings, OutputColorSpace, RendererBackendType, RendererStatistics, VertexData,
+            VsyncMode,
+        },
+        scene::{
+            camera::{Camera, CameraCreatorParameters, CameraViewport, ProjectionType},
+            camera_set::{CameraTarget, NamedCamera},
+            culling::{BoundingSphere, SmallObjectCullingParameters},
+            graph::{SceneNodeId, SceneRenderable, SceneTransform},
+            picking::PickedObject,
+            spatial::Ray,
+            streaming::{
+                scene_streaming_get_loaded_cell_count, scene_streaming_start, scene_streaming_stop,
+                ChunkCoordinates, ChunkLoader, ChunkStreamer, ChunkStreamerParameters, SceneCell,
+                SceneCellLoader,
+            },
+        },
+        screenshot::{HdrScreenshotPixels, ScreenshotPixels, ScreenshotRequest},
+        ui::{
+            debug_workspace::{DebugDockArea, DebugPanelKind},
+            layout::{
+                UiAlignment, UiContainer, UiElementId, UiElementParameters, UiPadding, UiRect,
+                UiStackDirection,
+            },
+            nine_slice::{NineSliceInsets, NineSlicePanel},
+            overlay::UiQuad,
+        },
+    },
+    resources::{
+        animation::{
+            AnimationClip, AnimationEvent, AnimationEventTrack, AnimationKeyframe,
+            AnimationPlayback,
+        },
+        geometry::{
+            primitives::{
+                primitive_capsule, primitive_cube, primitive_cylinder, primitive_heightfield,
+                primitive_icosphere, primitive_plane, primitive_torus, primitive_uv_sphere,
+                PrimitiveMesh,
+            },
+            processing::{
+                mesh_compute_normals, mesh_compute_tangents, mesh_generate_lods, mesh_simplify,
+                mesh_weld_vertices,
+            },
+            GeometryCreatorParameters,
+        },
+        gltf::{gltf_load, GltfInstance, GltfScene},
+        inverse_kinematics::{solve_two_bone_ik, TwoBoneIkChain},
+        material::{
+            BlendMode, MaterialCreatorParameters, MaterialRenderState, MATERIAL_DEFAULT_SHININESS,
+            MATERIAL_DEFAULT_SPECULAR_STRENGTH,
+        },
+        morph_targets::{MorphTarget, MorphTargetSet},
+        sprite_animation::{
+            SpriteAnimation, SpriteAnimationLoopMode, SpriteFrameRect, SpriteSheet,
+        },
+        texture::TextureCreatorParameters,
+        texture_system::TextureHandle,
+    },
+    warn,
+};
@@ -0,0 +1,94 @@
+/// Color vision deficiency handled by the color filter
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorFilterMode {
+    #[default]
+    None,
+    /// Missing red cones
+    Protanopia,
+    /// Missing green cones
+    Deuteranopia,
+    /// Missing blue cones
+    Tritanopia,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ColorFilterType {
+    /// Show how the frame looks to someone with the deficiency
+    Simulation,
+    /// Shift the colors lost by the deficiency towards the ones still perceived
+    #[default]
+    Correction,
+}
+
+/// Engine wide accessibility options
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AccessibilitySettings {
+    pub color_filter_mode: ColorFilterMode,
+    pub color_filter_type: ColorFilterType,
+    /// Scale applied to every ui size, 1 is the size given by the game
+    pub ui_scale: f32,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self {
+            color_filter_mode: ColorFilterMode::None,
+            color_filter_type: ColorFilterType::Correction,
+            ui_scale: 1.0,
+        }
+    }
+}
+
+impl AccessibilitySettings {
+    pub fn color_filter_mode(mut self, color_filter_mode: ColorFilterMode) -> Self {
+        self.color_filter_mode = color_filter_mode;
+        self
+    }
+
+    pub fn color_filter_type(mut self, color_filter_type: ColorFilterType) -> Self {
+        self.color_filter_type = color_filter_type;
+        self
+    }
+
+    pub fn ui_scale(mut self, ui_scale: f32) -> Self {
+        self.ui_scale = ui_scale;
+        self
+    }
+
+    /// Matrix applied to the linear rgb output of the shaders
+    pub fn get_color_filter_matrix(&self) -> glam::Mat4 {
+        // Machado et al. 2009, full severity, given row by row
+        let simulation_rows = match self.color_filter_mode {
+            ColorFilterMode::None => return glam::Mat4::IDENTITY,
+            ColorFilterMode::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            ColorFilterMode::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            ColorFilterMode::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        };
+        let simulation = glam::Mat3::from_cols_array_2d(&simulation_rows).transpose();
+        let filter = match self.color_filter_type {
+            ColorFilterType::Simulation => simulation,
+            ColorFilterType::Correction => {
+                // Daltonization: the lost information is redistributed on the green and blue channels
+                let error_shift = glam::Mat3::from_cols_array_2d(&[
+                    [0.0, 0.7, 0.7],
+                    [0.0, 1.0, 0.0],
+                    [0.0, 0.0, 1.0],
+                ]);
+                glam::Mat3::IDENTITY + error_shift * (glam::Mat3::IDENTITY - simulation)
+            }
+        };
+        glam::Mat4::from_mat3(filter)
+    }
+}
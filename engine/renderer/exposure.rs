@@ -0,0 +1,232 @@
+/// Number of luminance buckets of the histogram, spread evenly in log2 space
+pub const EXPOSURE_HISTOGRAM_BIN_COUNT: usize = 64;
+
+/// Luminance of the pixels below this threshold is ignored, they would drag the average to black
+const EXPOSURE_MIN_LUMINANCE: f32 = 1e-5;
+
+/// Luminance distribution of a frame, filled from a downsampled copy of the hdr target
+#[derive(Clone, Debug)]
+pub struct LuminanceHistogram {
+    pub bins: [u32; EXPOSURE_HISTOGRAM_BIN_COUNT],
+    /// Log2 luminance of the first bin
+    pub min_log_luminance: f32,
+    /// Log2 luminance of the last bin
+    pub max_log_luminance: f32,
+}
+
+impl LuminanceHistogram {
+    /// Histogram covering the luminances of the given exposure values
+    pub fn new(min_ev: f32, max_ev: f32) -> Self {
+        Self {
+            bins: [0; EXPOSURE_HISTOGRAM_BIN_COUNT],
+            min_log_luminance: exposure_ev_to_log_luminance(min_ev),
+            max_log_luminance: exposure_ev_to_log_luminance(max_ev),
+        }
+    }
+
+    fn get_log_luminance_range(&self) -> f32 {
+        (self.max_log_luminance - self.min_log_luminance).max(f32::EPSILON)
+    }
+
+    fn get_bin_log_luminance(&self, bin: usize) -> f32 {
+        self.min_log_luminance
+            + (bin as f32 + 0.5) / EXPOSURE_HISTOGRAM_BIN_COUNT as f32
+                * self.get_log_luminance_range()
+    }
+
+    pub fn add_sample(&mut self, luminance: f32) {
+        if luminance < EXPOSURE_MIN_LUMINANCE || !luminance.is_finite() {
+            return;
+        }
+        let position = (luminance.log2() - self.min_log_luminance) / self.get_log_luminance_range();
+        let bin = (position * EXPOSURE_HISTOGRAM_BIN_COUNT as f32)
+            .clamp(0.0, (EXPOSURE_HISTOGRAM_BIN_COUNT - 1) as f32) as usize;
+        self.bins[bin] += 1;
+    }
+
+    /// Add every `step`th linear rgb pixel, a cheap cpu downsample of the frame
+    pub fn add_pixels(&mut self, pixels: &[glam::Vec3], step: usize) {
+        for pixel in pixels.iter().step_by(step.max(1)) {
+            self.add_sample(exposure_get_luminance(*pixel));
+        }
+    }
+
+    pub fn get_sample_count(&self) -> u32 {
+        self.bins.iter().sum()
+    }
+
+    pub fn clear(&mut self) {
+        self.bins = [0; EXPOSURE_HISTOGRAM_BIN_COUNT];
+    }
+
+    /// Average log2 luminance of the samples between the two percentiles
+    /// The darkest and brightest pixels are ignored so a few highlights don't change the exposure
+    /// Returns none without samples
+    pub fn get_average_log_luminance(
+        &self,
+        low_percentile: f32,
+        high_percentile: f32,
+    ) -> Option<f32> {
+        let sample_count = self.get_sample_count();
+        if sample_count == 0 {
+            return None;
+        }
+        // Sanitized first, the bounds of a clamp must be ordered
+        let low_percentile = low_percentile.clamp(0.0, 1.0);
+        let high_percentile = high_percentile.max(low_percentile).min(1.0);
+        let low = low_percentile * sample_count as f32;
+        let high = high_percentile * sample_count as f32;
+
+        let mut skipped = 0.0;
+        let mut weight_sum = 0.0;
+        let mut log_luminance_sum = 0.0;
+        for (bin, count) in self.bins.iter().enumerate() {
+            // Part of the bin between the two percentiles
+            let count = *count as f32;
+            let start = skipped;
+            let end = skipped + count;
+            skipped = end;
+            let weight = (end.min(high) - start.max(low)).max(0.0);
+            weight_sum += weight;
+            log_luminance_sum += weight * self.get_bin_log_luminance(bin);
+        }
+        if weight_sum <= 0.0 {
+            return None;
+        }
+        Some(log_luminance_sum / weight_sum)
+    }
+}
+
+/// Rec. 709 relative luminance of a linear rgb color
+pub fn exposure_get_luminance(color: glam::Vec3) -> f32 {
+    color.dot(glam::Vec3::new(0.2126, 0.7152, 0.0722))
+}
+
+/// Exposure value at iso 100 of a scene with the given log2 average luminance
+pub fn exposure_log_luminance_to_ev(log_luminance: f32) -> f32 {
+    // Reflected light meter calibration constant K = 12.5
+    log_luminance + (100.0f32 / 12.5).log2()
+}
+
+pub fn exposure_ev_to_log_luminance(ev: f32) -> f32 {
+    ev - (100.0f32 / 12.5).log2()
+}
+
+/// Scale applied to the hdr color before the tonemapping
+pub fn exposure_ev_to_exposure(ev: f32) -> f32 {
+    // Saturation based sensitivity, the lens and vignetting factor is 1.2
+    1.0 / (1.2 * ev.exp2())
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AutoExposureParameters {
+    /// Darkest exposure value the camera adapts to
+    pub min_ev: f32,
+    /// Brightest exposure value the camera adapts to
+    pub max_ev: f32,
+    /// Adaptation rate when the scene gets brighter, per second
+    pub speed_up: f32,
+    /// Adaptation rate when the scene gets darker, per second, eyes adapt slower to the dark
+    pub speed_down: f32,
+    /// Fraction of the darkest samples ignored
+    pub low_percentile: f32,
+    /// Fraction of the samples, from the darkest, kept before ignoring the brightest ones
+    pub high_percentile: f32,
+    /// Added to the measured exposure value, negative values brighten the frame
+    pub compensation: f32,
+}
+
+impl Default for AutoExposureParameters {
+    fn default() -> Self {
+        Self {
+            min_ev: -4.0,
+            max_ev: 16.0,
+            speed_up: 3.0,
+            speed_down: 1.0,
+            low_percentile: 0.1,
+            high_percentile: 0.9,
+            compensation: 0.0,
+        }
+    }
+}
+
+impl AutoExposureParameters {
+    pub fn ev_limits(mut self, min_ev: f32, max_ev: f32) -> Self {
+        self.min_ev = min_ev;
+        self.max_ev = max_ev;
+        self
+    }
+
+    pub fn speed(mut self, speed_up: f32, speed_down: f32) -> Self {
+        self.speed_up = speed_up;
+        self.speed_down = speed_down;
+        self
+    }
+
+    pub fn percentiles(mut self, low_percentile: f32, high_percentile: f32) -> Self {
+        self.low_percentile = low_percentile;
+        self.high_percentile = high_percentile;
+        self
+    }
+
+    pub fn compensation(mut self, compensation: f32) -> Self {
+        self.compensation = compensation;
+        self
+    }
+}
+
+/// Exposure of the camera adapting over time to the luminance of the frames
+#[derive(Clone, Copy, Debug)]
+pub struct AutoExposure {
+    pub parameters: AutoExposureParameters,
+    /// The exposure value stays fixed while disabled
+    pub is_enabled: bool,
+    /// Current exposure value at iso 100
+    pub ev: f32,
+}
+
+impl Default for AutoExposure {
+    fn default() -> Self {
+        Self {
+            parameters: AutoExposureParameters::default(),
+            is_enabled: true,
+            // Overcast daylight
+            ev: 12.0,
+        }
+    }
+}
+
+impl AutoExposure {
+    /// Histogram matching the exposure limits, to be filled with the frame
+    pub fn create_histogram(&self) -> LuminanceHistogram {
+        LuminanceHistogram::new(self.parameters.min_ev, self.parameters.max_ev)
+    }
+
+    /// Move the exposure value towards the one measured in the histogram
+    pub fn update(&mut self, histogram: &LuminanceHistogram, delta_time: f64) {
+        if !self.is_enabled {
+            return;
+        }
+        let parameters = &self.parameters;
+        let log_luminance = match histogram
+            .get_average_log_luminance(parameters.low_percentile, parameters.high_percentile)
+        {
+            Some(log_luminance) => log_luminance,
+            None => return,
+        };
+        let target_ev = (exposure_log_luminance_to_ev(log_luminance) + parameters.compensation)
+            .clamp(parameters.min_ev, parameters.max_ev.max(parameters.min_ev));
+        let speed = if target_ev > self.ev {
+            parameters.speed_up
+        } else {
+            parameters.speed_down
+        };
+        // Frame rate independent exponential adaptation
+        let factor = 1.0 - (-(delta_time as f32) * speed.max(0.0)).exp();
+        self.ev += (target_ev - self.ev) * factor;
+    }
+
+    pub fn get_exposure(&self) -> f32 {
+        exposure_ev_to_exposure(self.ev)
+    }
+}
@@ -0,0 +1,112 @@
+use std::collections::BTreeMap;
+
+use crate::{core::debug::errors::EngineError, error};
+
+/// Maximum number of point lights affecting the objects, must match the object shaders
+pub const LIGHTING_MAX_POINT_LIGHTS: usize = 8;
+
+/// Light coming from infinitely far away, like the sun
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DirectionalLight {
+    /// Direction the light travels in, in world space
+    pub direction: glam::Vec3,
+    pub color: glam::Vec3,
+    pub intensity: f32,
+}
+
+impl Default for DirectionalLight {
+    fn default() -> Self {
+        Self {
+            direction: glam::Vec3::NEG_Y,
+            color: glam::Vec3::ONE,
+            intensity: 1.0,
+        }
+    }
+}
+
+/// Light emitted in every direction from a position
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct PointLight {
+    pub position: glam::Vec3,
+    pub color: glam::Vec3,
+    pub intensity: f32,
+    /// Distance at which the light fades out completely
+    pub range: f32,
+}
+
+impl Default for PointLight {
+    fn default() -> Self {
+        Self {
+            position: glam::Vec3::ZERO,
+            color: glam::Vec3::ONE,
+            intensity: 1.0,
+            range: 10.0,
+        }
+    }
+}
+
+/// Dynamic lights of the scene, uploaded with the global state every frame
+pub(crate) struct LightingSystem {
+    /// Light reaching every surface, white keeps the objects unlit
+    pub ambient_color: glam::Vec4,
+    pub directional_light: Option<DirectionalLight>,
+    /// Ordered by id so the lights keep their slot in the uniform buffer
+    point_lights: BTreeMap<u32, PointLight>,
+    next_point_light_id: u32,
+}
+
+impl Default for LightingSystem {
+    fn default() -> Self {
+        Self {
+            ambient_color: glam::Vec4::ONE,
+            directional_light: None,
+            point_lights: BTreeMap::new(),
+            next_point_light_id: 0,
+        }
+    }
+}
+
+impl LightingSystem {
+    pub fn add_point_light(&mut self, light: PointLight) -> Result<u32, EngineError> {
+        if self.point_lights.len() >= LIGHTING_MAX_POINT_LIGHTS {
+            error!(
+                "Can't add more than {} point lights",
+                LIGHTING_MAX_POINT_LIGHTS
+            );
+            return Err(EngineError::InvalidValue);
+        }
+        let id = self.next_point_light_id;
+        self.next_point_light_id += 1;
+        self.point_lights.insert(id, light);
+        Ok(id)
+    }
+
+    pub fn update_point_light(&mut self, id: u32, light: PointLight) -> Result<(), EngineError> {
+        match self.point_lights.get_mut(&id) {
+            Some(point_light) => {
+                *point_light = light;
+                Ok(())
+            }
+            None => {
+                error!("Can't update the unknown point light {}", id);
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    pub fn remove_point_light(&mut self, id: u32) -> Result<(), EngineError> {
+        if self.point_lights.remove(&id).is_none() {
+            error!("Can't remove the unknown point light {}", id);
+            return Err(EngineError::InvalidValue);
+        }
+        Ok(())
+    }
+
+    pub fn get_point_light(&self, id: u32) -> Option<&PointLight> {
+        self.point_lights.get(&id)
+    }
+
+    pub fn get_point_lights(&self) -> Vec<PointLight> {
+        self.point_lights.values().copied().collect()
+    }
+}
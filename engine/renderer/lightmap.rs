@@ -0,0 +1,453 @@
+use std::path::Path;
+
+use crate::{
+    core::debug::errors::EngineError,
+    error, info,
+    renderer::{
+        renderer_types::VertexData,
+        scene::spatial::{Aabb, Ray},
+    },
+    resources::texture::TextureCreatorParameters,
+};
+
+/// Offset applied to the origin of the rays leaving a surface to avoid hitting it again
+const LIGHTMAP_RAY_EPSILON: f32 = 1e-3;
+
+/// Static mesh of the scene, every mesh blocks the light but only the ones flagged receive a lightmap
+pub struct LightmapBakeMesh<'a> {
+    pub vertices: &'a [VertexData],
+    pub indices: &'a [u32],
+    pub model: glam::Mat4,
+    pub receives_lightmap: bool,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LightmapPointLight {
+    pub position: glam::Vec3,
+    pub color: glam::Vec3,
+    pub intensity: f32,
+}
+
+#[derive(Clone, Debug)]
+pub struct LightmapBakeParameters {
+    /// Width and height of every lightmap in texels
+    pub resolution: u32,
+    pub samples_per_texel: u32,
+    /// Number of indirect bounces, 0 only bakes the direct lighting
+    pub bounce_count: u32,
+    /// Radiance of the rays leaving the scene
+    pub sky_color: glam::Vec3,
+    /// Reflectance used for every surface during the bounces
+    pub albedo: f32,
+    pub lights: Vec<LightmapPointLight>,
+}
+
+impl Default for LightmapBakeParameters {
+    fn default() -> Self {
+        Self {
+            resolution: 128,
+            samples_per_texel: 64,
+            bounce_count: 2,
+            sky_color: glam::Vec3::splat(0.1),
+            albedo: 0.5,
+            lights: Vec::new(),
+        }
+    }
+}
+
+impl LightmapBakeParameters {
+    pub fn resolution(mut self, resolution: u32) -> Self {
+        self.resolution = resolution;
+        self
+    }
+
+    pub fn samples_per_texel(mut self, samples_per_texel: u32) -> Self {
+        self.samples_per_texel = samples_per_texel;
+        self
+    }
+
+    pub fn bounce_count(mut self, bounce_count: u32) -> Self {
+        self.bounce_count = bounce_count;
+        self
+    }
+
+    pub fn sky_color(mut self, sky_color: glam::Vec3) -> Self {
+        self.sky_color = sky_color;
+        self
+    }
+
+    pub fn albedo(mut self, albedo: f32) -> Self {
+        self.albedo = albedo;
+        self
+    }
+
+    pub fn light(mut self, light: LightmapPointLight) -> Self {
+        self.lights.push(light);
+        self
+    }
+}
+
+/// Baked lighting of one mesh, rgba8
+pub struct LightmapImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl LightmapImage {
+    pub fn get_texture_parameters<'a>(&'a self, name: &'a str) -> TextureCreatorParameters<'a> {
+        TextureCreatorParameters {
+            name,
+            auto_release: true,
+            width: self.width,
+            height: self.height,
+            nb_channels: 4,
+            pixels: &self.pixels,
+            has_transparency: false,
+            is_default: false,
+        }
+    }
+
+    /// Store the lightmap as an asset to load it at runtime instead of baking it again
+    pub fn save(&self, path: &Path) -> Result<(), EngineError> {
+        if let Err(err) = image::save_buffer(
+            path,
+            &self.pixels,
+            self.width,
+            self.height,
+            image::ColorType::Rgba8,
+        ) {
+            error!("Failed to save the lightmap {:?}: {:?}", path, err);
+            return Err(EngineError::IO);
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Copy)]
+struct LightmapTriangle {
+    positions: [glam::Vec3; 3],
+    normal: glam::Vec3,
+}
+
+impl LightmapTriangle {
+    /// Möller–Trumbore, returns the distance along the ray
+    fn intersects_ray(&self, ray: &Ray, max_distance: f32) -> Option<f32> {
+        let edge_1 = self.positions[1] - self.positions[0];
+        let edge_2 = self.positions[2] - self.positions[0];
+        let p = ray.direction.cross(edge_2);
+        let determinant = edge_1.dot(p);
+        if determinant.abs() < f32::EPSILON {
+            return None;
+        }
+        let inverse_determinant = 1.0 / determinant;
+        let t = ray.origin - self.positions[0];
+        let u = t.dot(p) * inverse_determinant;
+        if !(0.0..=1.0).contains(&u) {
+            return None;
+        }
+        let q = t.cross(edge_1);
+        let v = ray.direction.dot(q) * inverse_determinant;
+        if v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        let distance = edge_2.dot(q) * inverse_determinant;
+        if distance > LIGHTMAP_RAY_EPSILON && distance < max_distance {
+            Some(distance)
+        } else {
+            None
+        }
+    }
+}
+
+/// Triangles of a mesh with their bounds to skip most of the meshes quickly
+struct LightmapOccluder {
+    bounds: Aabb,
+    triangles: Vec<LightmapTriangle>,
+}
+
+/// Small deterministic generator, the bakes are reproducible
+pub(crate) struct LightmapRandom(u32);
+
+impl Default for LightmapRandom {
+    fn default() -> Self {
+        Self(0x9e37_79b9)
+    }
+}
+
+impl LightmapRandom {
+    pub fn next(&mut self) -> f32 {
+        // xorshift32
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 >> 8) as f32 / (1u32 << 24) as f32
+    }
+
+    /// Cosine weighted direction around the normal
+    fn hemisphere_direction(&mut self, normal: glam::Vec3) -> glam::Vec3 {
+        let (u, v) = (self.next(), self.next());
+        let radius = u.sqrt();
+        let angle = 2.0 * std::f32::consts::PI * v;
+        let (tangent, bitangent) = normal.any_orthonormal_pair();
+        (tangent * radius * angle.cos()
+            + bitangent * radius * angle.sin()
+            + normal * (1.0 - u).max(0.0).sqrt())
+        .normalize()
+    }
+}
+
+/// Static meshes and lights the bakes trace rays against
+pub(crate) struct LightmapScene<'a> {
+    occluders: Vec<LightmapOccluder>,
+    parameters: &'a LightmapBakeParameters,
+}
+
+impl<'a> LightmapScene<'a> {
+    pub fn new(meshes: &[LightmapBakeMesh], parameters: &'a LightmapBakeParameters) -> Self {
+        let occluders = meshes
+            .iter()
+            .filter_map(|mesh| {
+                let triangles: Vec<LightmapTriangle> = lightmap_get_world_triangles(mesh)
+                    .into_iter()
+                    .map(|(triangle, _)| triangle)
+                    .collect();
+                let bounds = triangles
+                    .iter()
+                    .flat_map(|triangle| triangle.positions)
+                    .map(|position| Aabb::new(position, position))
+                    .reduce(|a, b| a.union(&b))?;
+                Some(LightmapOccluder {
+                    bounds: bounds.expanded(LIGHTMAP_RAY_EPSILON),
+                    triangles,
+                })
+            })
+            .collect();
+        Self {
+            occluders,
+            parameters,
+        }
+    }
+
+    /// Light seen along the ray, the sky if nothing is hit
+    pub fn sample_radiance(&self, ray: &Ray, random: &mut LightmapRandom) -> glam::Vec3 {
+        match self.trace(ray, f32::MAX) {
+            None => self.parameters.sky_color,
+            Some((distance, triangle)) => {
+                let normal = if triangle.normal.dot(ray.direction) > 0.0 {
+                    -triangle.normal
+                } else {
+                    triangle.normal
+                };
+                self.parameters.albedo * self.sample_light(ray.at(distance), normal, random)
+            }
+        }
+    }
+
+    /// Closest triangle hit by the ray
+    fn trace(&self, ray: &Ray, max_distance: f32) -> Option<(f32, LightmapTriangle)> {
+        let mut closest: Option<(f32, LightmapTriangle)> = None;
+        for occluder in &self.occluders {
+            let max_distance = closest.map_or(max_distance, |(distance, _)| distance);
+            if occluder.bounds.intersects_ray(ray, max_distance).is_none() {
+                continue;
+            }
+            for triangle in &occluder.triangles {
+                let max_distance = closest.map_or(max_distance, |(distance, _)| distance);
+                if let Some(distance) = triangle.intersects_ray(ray, max_distance) {
+                    closest = Some((distance, *triangle));
+                }
+            }
+        }
+        closest
+    }
+
+    /// Light received from the point lights, divided by pi to be comparable with radiance
+    fn direct_light(&self, position: glam::Vec3, normal: glam::Vec3) -> glam::Vec3 {
+        let mut light = glam::Vec3::ZERO;
+        for point_light in &self.parameters.lights {
+            let to_light = point_light.position - position;
+            let distance = to_light.length();
+            let cosine = normal.dot(to_light / distance);
+            if cosine <= 0.0 {
+                continue;
+            }
+            let shadow_ray = Ray::new(position + normal * LIGHTMAP_RAY_EPSILON, to_light);
+            if self.trace(&shadow_ray, distance).is_some() {
+                continue;
+            }
+            light += point_light.color * point_light.intensity * cosine
+                / (distance * distance * std::f32::consts::PI);
+        }
+        light
+    }
+
+    /// One path traced estimate of the light reaching a surface
+    fn sample_light(
+        &self,
+        position: glam::Vec3,
+        normal: glam::Vec3,
+        random: &mut LightmapRandom,
+    ) -> glam::Vec3 {
+        let mut light = glam::Vec3::ZERO;
+        let mut throughput = glam::Vec3::ONE;
+        let (mut position, mut normal) = (position, normal);
+        for bounce in 0..=self.parameters.bounce_count {
+            light += throughput * self.direct_light(position, normal);
+            if bounce == self.parameters.bounce_count {
+                break;
+            }
+            let ray = Ray::new(
+                position + normal * LIGHTMAP_RAY_EPSILON,
+                random.hemisphere_direction(normal),
+            );
+            match self.trace(&ray, f32::MAX) {
+                None => {
+                    light += throughput * self.parameters.sky_color;
+                    break;
+                }
+                Some((distance, triangle)) => {
+                    position = ray.at(distance);
+                    // Surfaces are lit from both sides
+                    normal = if triangle.normal.dot(ray.direction) > 0.0 {
+                        -triangle.normal
+                    } else {
+                        triangle.normal
+                    };
+                    throughput *= self.parameters.albedo;
+                }
+            }
+        }
+        light
+    }
+}
+
+fn lightmap_get_world_triangles(
+    mesh: &LightmapBakeMesh,
+) -> Vec<(LightmapTriangle, [glam::Vec2; 3])> {
+    mesh.indices
+        .chunks_exact(3)
+        .filter_map(|indices| {
+            let a = mesh.vertices.get(indices[0] as usize)?;
+            let b = mesh.vertices.get(indices[1] as usize)?;
+            let c = mesh.vertices.get(indices[2] as usize)?;
+            let positions = [a, b, c].map(|vertex| mesh.model.transform_point3(vertex.position));
+            let normal = (positions[1] - positions[0])
+                .cross(positions[2] - positions[0])
+                .try_normalize()?;
+            Some((
+                LightmapTriangle { positions, normal },
+                [a.lightmap_texture, b.lightmap_texture, c.lightmap_texture],
+            ))
+        })
+        .collect()
+}
+
+/// Texels left empty take the mean of their filled neighbours so bilinear filtering doesn't bleed black
+fn lightmap_dilate(texels: &mut [Option<glam::Vec3>], resolution: usize) {
+    let source = texels.to_vec();
+    for y in 0..resolution {
+        for x in 0..resolution {
+            if source[y * resolution + x].is_some() {
+                continue;
+            }
+            let mut sum = glam::Vec3::ZERO;
+            let mut count = 0;
+            for (dx, dy) in [(-1, 0), (1, 0), (0, -1), (0, 1)] {
+                let (nx, ny) = (x as i64 + dx, y as i64 + dy);
+                if nx < 0 || ny < 0 || nx >= resolution as i64 || ny >= resolution as i64 {
+                    continue;
+                }
+                if let Some(texel) = source[ny as usize * resolution + nx as usize] {
+                    sum += texel;
+                    count += 1;
+                }
+            }
+            if count > 0 {
+                texels[y * resolution + x] = Some(sum / count as f32);
+            }
+        }
+    }
+}
+
+fn lightmap_bake_mesh(
+    scene: &LightmapScene,
+    mesh: &LightmapBakeMesh,
+    random: &mut LightmapRandom,
+) -> LightmapImage {
+    let resolution = scene.parameters.resolution as usize;
+    let samples = scene.parameters.samples_per_texel.max(1);
+    let mut texels: Vec<Option<glam::Vec3>> = vec![None; resolution * resolution];
+
+    // Rasterize the triangles in lightmap space, every covered texel center is path traced
+    for (triangle, uvs) in lightmap_get_world_triangles(mesh) {
+        let texel_uvs = uvs.map(|uv| uv * resolution as f32);
+        let min = texel_uvs[0].min(texel_uvs[1]).min(texel_uvs[2]).floor();
+        let max = texel_uvs[0].max(texel_uvs[1]).max(texel_uvs[2]).ceil();
+        let area = (texel_uvs[1] - texel_uvs[0]).perp_dot(texel_uvs[2] - texel_uvs[0]);
+        if area.abs() < f32::EPSILON {
+            continue;
+        }
+        for y in (min.y.max(0.0) as usize)..(max.y as usize).min(resolution) {
+            for x in (min.x.max(0.0) as usize)..(max.x as usize).min(resolution) {
+                let center = glam::Vec2::new(x as f32 + 0.5, y as f32 + 0.5);
+                let weight_0 = (texel_uvs[2] - texel_uvs[1]).perp_dot(center - texel_uvs[1]) / area;
+                let weight_1 = (texel_uvs[0] - texel_uvs[2]).perp_dot(center - texel_uvs[2]) / area;
+                let weight_2 = 1.0 - weight_0 - weight_1;
+                if weight_0 < 0.0 || weight_1 < 0.0 || weight_2 < 0.0 {
+                    continue;
+                }
+                let position = triangle.positions[0] * weight_0
+                    + triangle.positions[1] * weight_1
+                    + triangle.positions[2] * weight_2;
+                let mut light = glam::Vec3::ZERO;
+                for _ in 0..samples {
+                    light += scene.sample_light(position, triangle.normal, random);
+                }
+                texels[y * resolution + x] = Some(light / samples as f32);
+            }
+        }
+    }
+    lightmap_dilate(&mut texels, resolution);
+
+    let pixels = texels
+        .iter()
+        .flat_map(|texel| {
+            let color = texel
+                .unwrap_or_default()
+                .clamp(glam::Vec3::ZERO, glam::Vec3::ONE)
+                * 255.0;
+            [color.x as u8, color.y as u8, color.z as u8, 255]
+        })
+        .collect();
+    LightmapImage {
+        width: resolution as u32,
+        height: resolution as u32,
+        pixels,
+    }
+}
+
+/// Offline cpu path tracer baking the static lighting of the meshes flagged as receivers
+/// Returns one lightmap per mesh, none for the meshes that don't receive one
+pub fn lightmap_bake(
+    meshes: &[LightmapBakeMesh],
+    parameters: &LightmapBakeParameters,
+) -> Result<Vec<Option<LightmapImage>>, EngineError> {
+    if parameters.resolution == 0 {
+        error!("Can't bake lightmaps with a resolution of 0");
+        return Err(EngineError::InvalidValue);
+    }
+    let scene = LightmapScene::new(meshes, parameters);
+
+    let mut random = LightmapRandom::default();
+    let mut lightmaps = Vec::with_capacity(meshes.len());
+    for (index, mesh) in meshes.iter().enumerate() {
+        if !mesh.receives_lightmap {
+            lightmaps.push(None);
+            continue;
+        }
+        info!("Baking the lightmap {}/{}", index + 1, meshes.len());
+        lightmaps.push(Some(lightmap_bake_mesh(&scene, mesh, &mut random)));
+    }
+    Ok(lightmaps)
+}
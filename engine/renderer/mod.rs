@@ -1,6 +1,18 @@
-pub mod renderer_backend;
-pub mod renderer_frontend;
-pub mod renderer_types;
-pub mod scene;
-pub mod utils;
-pub mod vulkan;
+pub(crate) mod accessibility;
+pub(crate) mod exposure;
+pub(crate) mod lighting;
+pub(crate) mod lightmap;
+pub(crate) mod null_backend;
+pub(crate) mod object_names;
+pub(crate) mod post_process;
+pub(crate) mod reflection_probe;
+pub(crate) mod render_hooks;
+pub(crate) mod render_queue;
+pub(crate) mod renderer_backend;
+pub(crate) mod renderer_frontend;
+pub(crate) mod renderer_types;
+pub(crate) mod scene;
+pub(crate) mod screenshot;
+pub(crate) mod ui;
+pub(crate) mod utils;
+pub(crate) mod vulkan;
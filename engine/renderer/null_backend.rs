@@ -0,0 +1,480 @@
+use std::collections::HashMap;
+
+use crate::{
+    core::{application::windows::WindowHandle, debug::errors::EngineError},
+    error,
+    platforms::platform::Platform,
+    resources::{
+        geometry::{Geometry, GeometryCreatorParameters},
+        texture::{Texture, TextureCreatorParameters},
+        texture_system::TextureSystem,
+    },
+};
+
+use super::{
+    lighting::LightingSystem,
+    post_process::PostProcessSettings,
+    reflection_probe::EnvironmentMap,
+    renderer_backend::RendererBackend,
+    renderer_types::{
+        AntiAliasing, CameraPassTarget, ComputeBinding, DepthStencilSettings, DisplayOutput,
+        GeometryRenderData, ImmediateVertexData, OutputColorSpace, RendererStatistics,
+        UiRenderData, UiVertexData, VertexData, VsyncMode,
+    },
+    scene::camera::CameraViewport,
+    screenshot::{HdrScreenshotPixels, ScreenshotPixels},
+};
+
+/// Framebuffer size when the platform doesn't give one
+const NULL_BACKEND_DEFAULT_SIZE: (u32, u32) = (1280, 720);
+
+/// Texture of the null backend, only its description is kept
+#[derive(Clone, Copy)]
+pub(crate) struct NullTexture {
+    pub id: u32,
+    pub width: u32,
+    pub height: u32,
+    pub nb_channels: u8,
+    pub has_transparency: bool,
+    pub generation: Option<u32>,
+}
+
+impl Texture for NullTexture {
+    fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    fn get_width(&self) -> u32 {
+        self.width
+    }
+
+    fn get_height(&self) -> u32 {
+        self.height
+    }
+
+    fn get_nb_channels(&self) -> u8 {
+        self.nb_channels
+    }
+
+    fn has_transparency(&self) -> bool {
+        self.has_transparency
+    }
+
+    fn get_generation(&self) -> Option<u32> {
+        self.generation
+    }
+
+    fn set_generation(&mut self, generation: Option<u32>) {
+        self.generation = generation;
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Texture> {
+        Box::new(*self)
+    }
+}
+
+/// Geometry of the null backend, only its counts are kept
+#[derive(Clone, Copy)]
+pub(crate) struct NullGeometry {
+    pub id: u32,
+    pub vertex_count: u32,
+    pub index_count: u32,
+    pub generation: Option<u32>,
+}
+
+impl Geometry for NullGeometry {
+    fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    fn get_vertex_count(&self) -> u32 {
+        self.vertex_count
+    }
+
+    fn get_index_count(&self) -> u32 {
+        self.index_count
+    }
+
+    fn get_generation(&self) -> Option<u32> {
+        self.generation
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn Geometry> {
+        Box::new(*self)
+    }
+}
+
+/// Backend drawing nothing, for the servers and the tests without a gpu
+/// The resources get ids like on a real backend so the frontend runs unchanged
+#[derive(Default)]
+pub(crate) struct NullRendererBackend {
+    pub framebuffer_size: (u32, u32),
+    pub frame_number: u64,
+    pub last_texture_id: u32,
+    pub last_geometry_id: u32,
+    /// Ids of the released object resources, reused first
+    pub free_object_ids: Vec<u32>,
+    pub object_count: u32,
+    pub last_compute_shader_id: u32,
+    /// The writes are kept so they read back, the dispatches change nothing
+    pub storage_buffers: HashMap<u32, Vec<u8>>,
+    pub last_storage_buffer_id: u32,
+}
+
+impl NullRendererBackend {
+    fn get_storage_buffer_mut(
+        &mut self,
+        storage_buffer_id: u32,
+    ) -> Result<&mut Vec<u8>, EngineError> {
+        match self.storage_buffers.get_mut(&storage_buffer_id) {
+            Some(buffer) => Ok(buffer),
+            None => {
+                error!("Unknown null storage buffer {}", storage_buffer_id);
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+}
+
+impl RendererBackend for NullRendererBackend {
+    fn init(
+        &mut self,
+        _application_name: &str,
+        platform: &dyn Platform,
+        _anti_aliasing: AntiAliasing,
+        _display_output: DisplayOutput,
+        _depth_stencil: &DepthStencilSettings,
+    ) -> Result<(), EngineError> {
+        self.framebuffer_size = platform
+            .get_window_geometry()
+            .map(|geometry| (geometry.width, geometry.height))
+            .unwrap_or(NULL_BACKEND_DEFAULT_SIZE);
+        Ok(())
+    }
+
+    fn shutdown(&mut self) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn resize(&mut self, width: u32, height: u32) -> Result<(), EngineError> {
+        self.framebuffer_size = (width, height);
+        Ok(())
+    }
+
+    fn set_vsync_mode(&mut self, _mode: VsyncMode) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn set_render_scale(&mut self, _scale: f32) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn get_output_color_space(&self) -> Result<OutputColorSpace, EngineError> {
+        Ok(OutputColorSpace::Srgb)
+    }
+
+    fn begin_frame(&mut self, _delta_time: f64) -> Result<bool, EngineError> {
+        Ok(true)
+    }
+
+    fn add_camera_pass(
+        &mut self,
+        _target: CameraPassTarget<'_>,
+        _viewport: Option<CameraViewport>,
+    ) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn end_camera_pass(&mut self) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn create_window_surface(
+        &mut self,
+        _window: WindowHandle,
+        _platform: &dyn Platform,
+    ) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn destroy_window_surface(&mut self, _window: WindowHandle) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn set_world_viewport(&mut self, _viewport: Option<CameraViewport>) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn end_frame(&mut self, _delta_time: f64) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn increase_frame_number(&mut self) -> Result<(), EngineError> {
+        self.frame_number += 1;
+        Ok(())
+    }
+
+    fn get_frame_number(&self) -> Result<u64, EngineError> {
+        Ok(self.frame_number)
+    }
+
+    fn update_global_state(
+        &mut self,
+        _projection: glam::Mat4,
+        _view: glam::Mat4,
+        _view_position: glam::Vec3,
+        _lighting: &LightingSystem,
+        _mode: i32,
+    ) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn update_post_process(
+        &mut self,
+        _settings: &PostProcessSettings,
+        _color_filter: glam::Mat4,
+    ) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn update_object(
+        &mut self,
+        _data: &GeometryRenderData,
+        _textures: &TextureSystem,
+    ) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn draw_instances(
+        &mut self,
+        _data: &GeometryRenderData,
+        _models: &[glam::Mat4],
+        _textures: &TextureSystem,
+    ) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn begin_ui_pass(&mut self) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn draw_ui(
+        &mut self,
+        _projection: glam::Mat4,
+        _vertices: &[UiVertexData],
+        _batches: &[UiRenderData],
+    ) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn draw_immediate(
+        &mut self,
+        _projection: glam::Mat4,
+        _vertices: &[ImmediateVertexData],
+    ) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn acquire_object_resources(&mut self) -> Result<u32, EngineError> {
+        if let Some(object_id) = self.free_object_ids.pop() {
+            return Ok(object_id);
+        }
+        self.object_count += 1;
+        Ok(self.object_count - 1)
+    }
+
+    fn release_object_resources(&mut self, object_id: u32) -> Result<(), EngineError> {
+        self.free_object_ids.push(object_id);
+        Ok(())
+    }
+
+    fn get_aspect_ratio(&self) -> Result<f32, EngineError> {
+        let (width, height) = self.framebuffer_size;
+        Ok(width as f32 / height.max(1) as f32)
+    }
+
+    fn get_framebuffer_size(&self) -> Result<(u32, u32), EngineError> {
+        Ok(self.framebuffer_size)
+    }
+
+    fn get_sample_count(&self) -> Result<u32, EngineError> {
+        Ok(1)
+    }
+
+    fn get_statistics(&self) -> Result<RendererStatistics, EngineError> {
+        Ok(RendererStatistics {
+            frame_number: self.frame_number,
+            ..Default::default()
+        })
+    }
+
+    fn request_screenshot(&mut self, _hdr: bool) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    /// Nothing is drawn, the screenshots never come
+    fn take_screenshot(&mut self) -> Result<Option<ScreenshotPixels>, EngineError> {
+        Ok(None)
+    }
+
+    fn take_hdr_screenshot(&mut self) -> Result<Option<HdrScreenshotPixels>, EngineError> {
+        Ok(None)
+    }
+
+    fn set_luminance_readback_enabled(&mut self, _is_enabled: bool) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    /// Nothing is drawn, the exposure keeps its value
+    fn take_luminance_samples(&mut self) -> Result<Option<Vec<glam::Vec3>>, EngineError> {
+        Ok(None)
+    }
+
+    fn upload_reflection_probes(
+        &mut self,
+        _environment_maps: &[&EnvironmentMap],
+    ) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn create_texture(
+        &mut self,
+        params: TextureCreatorParameters,
+    ) -> Result<Box<dyn Texture>, EngineError> {
+        self.last_texture_id += 1;
+        Ok(Box::new(NullTexture {
+            id: self.last_texture_id,
+            width: params.width,
+            height: params.height,
+            nb_channels: params.nb_channels,
+            has_transparency: params.has_transparency,
+            generation: None,
+        }))
+    }
+
+    fn destroy_texture(&self, _texture: &dyn Texture) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn retire_texture(&mut self, _texture: Box<dyn Texture>) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn update_texture_region(
+        &self,
+        _texture: &dyn Texture,
+        _x: u32,
+        _y: u32,
+        _width: u32,
+        _height: u32,
+        _pixels: &[u8],
+    ) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    /// The pixels aren't kept, the texture reads back as transparent black
+    fn read_texture_pixels(&self, texture: &dyn Texture) -> Result<Vec<u8>, EngineError> {
+        let size = texture.get_width() as usize * texture.get_height() as usize * 4;
+        Ok(vec![0; size])
+    }
+
+    fn create_geometry(
+        &mut self,
+        params: GeometryCreatorParameters,
+    ) -> Result<Box<dyn Geometry>, EngineError> {
+        self.last_geometry_id += 1;
+        Ok(Box::new(NullGeometry {
+            id: self.last_geometry_id,
+            vertex_count: params.vertices.len() as u32,
+            index_count: params.indices.len() as u32,
+            generation: None,
+        }))
+    }
+
+    fn destroy_geometry(&mut self, _geometry: &dyn Geometry) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn update_geometry_vertices(
+        &mut self,
+        _geometry: &dyn Geometry,
+        _vertices: &[VertexData],
+    ) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn create_compute_shader(&mut self, _path: &str) -> Result<u32, EngineError> {
+        self.last_compute_shader_id += 1;
+        Ok(self.last_compute_shader_id)
+    }
+
+    fn destroy_compute_shader(&mut self, _shader_id: u32) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    fn create_storage_buffer(&mut self, size: usize) -> Result<u32, EngineError> {
+        self.last_storage_buffer_id += 1;
+        self.storage_buffers
+            .insert(self.last_storage_buffer_id, vec![0; size]);
+        Ok(self.last_storage_buffer_id)
+    }
+
+    fn destroy_storage_buffer(&mut self, storage_buffer_id: u32) -> Result<(), EngineError> {
+        self.get_storage_buffer_mut(storage_buffer_id)?;
+        self.storage_buffers.remove(&storage_buffer_id);
+        Ok(())
+    }
+
+    fn write_storage_buffer(
+        &mut self,
+        storage_buffer_id: u32,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<(), EngineError> {
+        let buffer = self.get_storage_buffer_mut(storage_buffer_id)?;
+        let offset = offset as usize;
+        match buffer.get_mut(offset..offset + data.len()) {
+            Some(range) => {
+                range.copy_from_slice(data);
+                Ok(())
+            }
+            None => {
+                error!(
+                    "Can't write {} bytes at {} in a null storage buffer of {} bytes",
+                    data.len(),
+                    offset,
+                    buffer.len()
+                );
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    fn read_storage_buffer(&self, storage_buffer_id: u32) -> Result<Vec<u8>, EngineError> {
+        match self.storage_buffers.get(&storage_buffer_id) {
+            Some(buffer) => Ok(buffer.clone()),
+            None => {
+                error!("Unknown null storage buffer {}", storage_buffer_id);
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    fn dispatch_compute(
+        &mut self,
+        _shader_id: u32,
+        _bindings: &[ComputeBinding],
+        _group_count: glam::UVec3,
+        _push_constants: &[u8],
+    ) -> Result<(), EngineError> {
+        Ok(())
+    }
+}
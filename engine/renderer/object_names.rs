@@ -0,0 +1,51 @@
+use std::collections::HashMap;
+
+use super::scene::graph::SceneNodeId;
+
+/// Debug name of a renderer object
+#[derive(Clone, Debug, PartialEq)]
+pub struct ObjectName {
+    pub name: String,
+    /// Scene node drawing the object, if any
+    pub node: Option<SceneNodeId>,
+}
+
+/// Names of the objects whose shader resources are acquired, by object id
+/// Only used to make the logs and the debug panels readable
+#[derive(Default)]
+pub(crate) struct ObjectNames {
+    names: HashMap<u32, ObjectName>,
+}
+
+impl ObjectNames {
+    pub fn set(&mut self, object_id: u32, name: &str, node: Option<SceneNodeId>) {
+        self.names.insert(
+            object_id,
+            ObjectName {
+                name: name.to_string(),
+                node,
+            },
+        );
+    }
+
+    /// The ids are reused, the name must go with the released resources
+    pub fn remove(&mut self, object_id: u32) {
+        self.names.remove(&object_id);
+    }
+
+    pub fn get(&self, object_id: u32) -> Option<&ObjectName> {
+        self.names.get(&object_id)
+    }
+
+    /// Id followed by the name and the node when known, e.g. `3 "wheel" (SceneNodeId(12))`
+    pub fn describe(&self, object_id: u32) -> String {
+        match self.names.get(&object_id) {
+            None => object_id.to_string(),
+            Some(ObjectName { name, node: None }) => format!("{} {:?}", object_id, name),
+            Some(ObjectName {
+                name,
+                node: Some(node),
+            }) => format!("{} {:?} ({:?})", object_id, name, node),
+        }
+    }
+}
@@ -0,0 +1,139 @@
+/// Curve bringing the hdr colors of the world into the displayable range
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TonemapOperator {
+    /// Keeps the colors below 1 almost unchanged, the highlights are compressed
+    Reinhard,
+    /// Filmic curve fitted on the academy color encoding system
+    #[default]
+    Aces,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct TonemapSettings {
+    /// The hdr colors are clamped when disabled
+    pub is_enabled: bool,
+    pub operator: TonemapOperator,
+    /// Scale applied to the hdr colors before the curve
+    pub exposure: f32,
+    /// Take the exposure of the auto exposure instead of the fixed one
+    pub use_auto_exposure: bool,
+    /// Brightness of the sdr white on the hdr outputs in nits, the ui is shown at it too
+    pub hdr_paper_white: f32,
+    /// Brightness the highlights are compressed to on the hdr outputs in nits
+    pub hdr_peak_brightness: f32,
+}
+
+impl Default for TonemapSettings {
+    fn default() -> Self {
+        Self {
+            is_enabled: true,
+            operator: TonemapOperator::Aces,
+            exposure: 1.0,
+            use_auto_exposure: false,
+            hdr_paper_white: 200.0,
+            hdr_peak_brightness: 1000.0,
+        }
+    }
+}
+
+/// Fast approximate anti aliasing, smooths the edges found in the luminance of the frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FxaaSettings {
+    pub is_enabled: bool,
+    /// Contrast of the luminance, relative to the brightest neighbour, needed to find an edge
+    pub edge_threshold: f32,
+    /// Contrast ignored in the dark areas
+    pub edge_threshold_min: f32,
+    /// Amount of the blur of the single pixel details, between 0 and 1
+    pub subpixel_quality: f32,
+}
+
+impl Default for FxaaSettings {
+    fn default() -> Self {
+        Self {
+            is_enabled: true,
+            edge_threshold: 0.166,
+            edge_threshold_min: 0.0833,
+            subpixel_quality: 0.75,
+        }
+    }
+}
+
+/// Darkening of the borders of the frame
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VignetteSettings {
+    pub is_enabled: bool,
+    /// Darkening of the corners, between 0 and 1
+    pub intensity: f32,
+    /// Distance from the center where the darkening starts, 1 is the middle of the borders
+    pub radius: f32,
+    /// Distance over which the darkening fades in
+    pub smoothness: f32,
+}
+
+impl Default for VignetteSettings {
+    fn default() -> Self {
+        Self {
+            is_enabled: false,
+            intensity: 0.4,
+            radius: 0.75,
+            smoothness: 0.45,
+        }
+    }
+}
+
+/// Fullscreen passes applied to the world before the ui is drawn, in the order of the fields
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PostProcessSettings {
+    pub tonemap: TonemapSettings,
+    pub fxaa: FxaaSettings,
+    pub vignette: VignetteSettings,
+}
+
+impl PostProcessSettings {
+    pub fn tonemap(mut self, tonemap: TonemapSettings) -> Self {
+        self.tonemap = tonemap;
+        self
+    }
+
+    pub fn fxaa(mut self, fxaa: FxaaSettings) -> Self {
+        self.fxaa = fxaa;
+        self
+    }
+
+    pub fn vignette(mut self, vignette: VignetteSettings) -> Self {
+        self.vignette = vignette;
+        self
+    }
+
+    /// Returns the name of the first invalid parameter
+    pub(crate) fn validate(&self) -> Option<&'static str> {
+        if !self.tonemap.exposure.is_finite() || self.tonemap.exposure <= 0.0 {
+            return Some("tonemap exposure");
+        }
+        if !self.tonemap.hdr_paper_white.is_finite() || self.tonemap.hdr_paper_white <= 0.0 {
+            return Some("tonemap hdr paper white");
+        }
+        if !self.tonemap.hdr_peak_brightness.is_finite()
+            || self.tonemap.hdr_peak_brightness < self.tonemap.hdr_paper_white
+        {
+            return Some("tonemap hdr peak brightness");
+        }
+        if !(0.0..=1.0).contains(&self.fxaa.edge_threshold) {
+            return Some("fxaa edge threshold");
+        }
+        if !(0.0..=1.0).contains(&self.fxaa.edge_threshold_min) {
+            return Some("fxaa minimum edge threshold");
+        }
+        if !(0.0..=1.0).contains(&self.fxaa.subpixel_quality) {
+            return Some("fxaa subpixel quality");
+        }
+        if !(0.0..=1.0).contains(&self.vignette.intensity) {
+            return Some("vignette intensity");
+        }
+        if self.vignette.radius < 0.0 || self.vignette.smoothness <= 0.0 {
+            return Some("vignette radius or smoothness");
+        }
+        None
+    }
+}
@@ -0,0 +1,326 @@
+use std::path::{Path, PathBuf};
+
+use image::ImageReader;
+
+use crate::{
+    core::debug::errors::EngineError,
+    error, info,
+    renderer::{
+        lightmap::{LightmapBakeMesh, LightmapBakeParameters, LightmapRandom, LightmapScene},
+        scene::spatial::Ray,
+    },
+};
+
+/// Number of faces of a cubemap, in the +X, -X, +Y, -Y, +Z, -Z order
+pub const REFLECTION_PROBE_FACE_COUNT: usize = 6;
+
+/// Location of the scene where the surroundings are captured
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReflectionProbe {
+    /// Used to name the baked files
+    pub name: String,
+    pub position: glam::Vec3,
+}
+
+#[derive(Clone, Debug)]
+pub struct ReflectionProbeBakeParameters {
+    /// Width and height of the faces of the sharpest level
+    pub face_size: u32,
+    /// Number of prefiltered levels, from mirror like to fully rough
+    pub level_count: u32,
+    /// Rays traced per texel of the sharpest level
+    pub samples_per_texel: u32,
+    /// Directions averaged per texel of the rougher levels
+    pub prefilter_sample_count: u32,
+    /// Only the lights, sky, albedo and bounces are used
+    pub lighting: LightmapBakeParameters,
+}
+
+impl Default for ReflectionProbeBakeParameters {
+    fn default() -> Self {
+        Self {
+            face_size: 64,
+            level_count: 5,
+            samples_per_texel: 4,
+            prefilter_sample_count: 64,
+            lighting: LightmapBakeParameters::default(),
+        }
+    }
+}
+
+impl ReflectionProbeBakeParameters {
+    pub fn face_size(mut self, face_size: u32) -> Self {
+        self.face_size = face_size;
+        self
+    }
+
+    pub fn level_count(mut self, level_count: u32) -> Self {
+        self.level_count = level_count;
+        self
+    }
+
+    pub fn samples_per_texel(mut self, samples_per_texel: u32) -> Self {
+        self.samples_per_texel = samples_per_texel;
+        self
+    }
+
+    pub fn prefilter_sample_count(mut self, prefilter_sample_count: u32) -> Self {
+        self.prefilter_sample_count = prefilter_sample_count;
+        self
+    }
+
+    pub fn lighting(mut self, lighting: LightmapBakeParameters) -> Self {
+        self.lighting = lighting;
+        self
+    }
+}
+
+/// One roughness level of an environment map
+#[derive(Clone, Debug)]
+pub struct EnvironmentMapLevel {
+    pub face_size: u32,
+    /// Linear colors, row by row
+    pub faces: [Vec<glam::Vec3>; REFLECTION_PROBE_FACE_COUNT],
+}
+
+impl EnvironmentMapLevel {
+    fn new(face_size: u32) -> Self {
+        let texel_count = (face_size * face_size) as usize;
+        Self {
+            face_size,
+            faces: std::array::from_fn(|_| vec![glam::Vec3::ZERO; texel_count]),
+        }
+    }
+
+    /// Direction going through the center of a texel
+    fn get_direction(&self, face: usize, x: u32, y: u32) -> glam::Vec3 {
+        let u = 2.0 * (x as f32 + 0.5) / self.face_size as f32 - 1.0;
+        let v = 2.0 * (y as f32 + 0.5) / self.face_size as f32 - 1.0;
+        let direction = match face {
+            0 => glam::Vec3::new(1.0, -v, -u),
+            1 => glam::Vec3::new(-1.0, -v, u),
+            2 => glam::Vec3::new(u, 1.0, v),
+            3 => glam::Vec3::new(u, -1.0, -v),
+            4 => glam::Vec3::new(u, -v, 1.0),
+            _ => glam::Vec3::new(-u, -v, -1.0),
+        };
+        direction.normalize()
+    }
+
+    /// Nearest texel seen in the given direction
+    pub fn sample(&self, direction: glam::Vec3) -> glam::Vec3 {
+        let absolute = direction.abs();
+        let (face, major, u, v) = if absolute.x >= absolute.y && absolute.x >= absolute.z {
+            if direction.x > 0.0 {
+                (0, absolute.x, -direction.z, -direction.y)
+            } else {
+                (1, absolute.x, direction.z, -direction.y)
+            }
+        } else if absolute.y >= absolute.z {
+            if direction.y > 0.0 {
+                (2, absolute.y, direction.x, direction.z)
+            } else {
+                (3, absolute.y, direction.x, -direction.z)
+            }
+        } else if direction.z > 0.0 {
+            (4, absolute.z, direction.x, -direction.y)
+        } else {
+            (5, absolute.z, -direction.x, -direction.y)
+        };
+        let to_texel = |coordinate: f32| {
+            (((coordinate / major + 1.0) * 0.5 * self.face_size as f32) as u32)
+                .min(self.face_size - 1)
+        };
+        self.faces[face][(to_texel(v) * self.face_size + to_texel(u)) as usize]
+    }
+}
+
+/// Prefiltered surroundings of a probe, the level matching the roughness is used for reflections
+#[derive(Clone, Debug)]
+pub struct EnvironmentMap {
+    /// Level 0 is mirror like, the last level is fully rough
+    pub levels: Vec<EnvironmentMapLevel>,
+}
+
+impl EnvironmentMap {
+    fn get_level_path(directory: &Path, name: &str, level: usize) -> PathBuf {
+        directory.join(format!("{}_{}.png", name, level))
+    }
+
+    /// Store every level as a strip of the six faces
+    pub fn save(&self, directory: &Path, name: &str) -> Result<(), EngineError> {
+        for (index, level) in self.levels.iter().enumerate() {
+            let size = level.face_size;
+            let width = size * REFLECTION_PROBE_FACE_COUNT as u32;
+            let mut pixels = vec![255u8; (width * size * 4) as usize];
+            for (face, texels) in level.faces.iter().enumerate() {
+                for (texel_index, texel) in texels.iter().enumerate() {
+                    let x = face as u32 * size + texel_index as u32 % size;
+                    let y = texel_index as u32 / size;
+                    let color = texel.clamp(glam::Vec3::ZERO, glam::Vec3::ONE) * 255.0;
+                    let pixel = ((y * width + x) * 4) as usize;
+                    pixels[pixel..pixel + 3].copy_from_slice(&[
+                        color.x as u8,
+                        color.y as u8,
+                        color.z as u8,
+                    ]);
+                }
+            }
+            let path = Self::get_level_path(directory, name, index);
+            if let Err(err) =
+                image::save_buffer(&path, &pixels, width, size, image::ColorType::Rgba8)
+            {
+                error!("Failed to save the environment map {:?}: {:?}", path, err);
+                return Err(EngineError::IO);
+            }
+        }
+        Ok(())
+    }
+
+    /// Load the levels saved with the given name, returns none if there are none
+    pub fn load(directory: &Path, name: &str) -> Result<Option<Self>, EngineError> {
+        let mut levels = Vec::new();
+        loop {
+            let path = Self::get_level_path(directory, name, levels.len());
+            if !path.exists() {
+                break;
+            }
+            let image = match ImageReader::open(&path).map(|reader| reader.decode()) {
+                Ok(Ok(image)) => image.to_rgb8(),
+                Ok(Err(err)) => {
+                    error!("Failed to decode the environment map {:?}: {:?}", path, err);
+                    return Err(EngineError::IO);
+                }
+                Err(err) => {
+                    error!("Failed to open the environment map {:?}: {:?}", path, err);
+                    return Err(EngineError::IO);
+                }
+            };
+            let size = image.height();
+            if image.width() != size * REFLECTION_PROBE_FACE_COUNT as u32 {
+                error!(
+                    "The environment map {:?} is not a strip of {} square faces",
+                    path, REFLECTION_PROBE_FACE_COUNT
+                );
+                return Err(EngineError::InvalidValue);
+            }
+            let mut level = EnvironmentMapLevel::new(size);
+            for (face, texels) in level.faces.iter_mut().enumerate() {
+                for (texel_index, texel) in texels.iter_mut().enumerate() {
+                    let x = face as u32 * size + texel_index as u32 % size;
+                    let y = texel_index as u32 / size;
+                    let pixel = image.get_pixel(x, y);
+                    *texel =
+                        glam::Vec3::new(pixel[0] as f32, pixel[1] as f32, pixel[2] as f32) / 255.0;
+                }
+            }
+            levels.push(level);
+        }
+        if levels.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Self { levels }))
+    }
+}
+
+/// Average of the sharpest level over a lobe around every texel direction
+fn reflection_probe_prefilter(
+    source: &EnvironmentMapLevel,
+    face_size: u32,
+    roughness: f32,
+    sample_count: u32,
+    random: &mut LightmapRandom,
+) -> EnvironmentMapLevel {
+    // Phong lobe equivalent to the roughness
+    let exponent = 2.0 / (roughness * roughness).max(1e-4) - 2.0;
+    let mut level = EnvironmentMapLevel::new(face_size);
+    for face in 0..REFLECTION_PROBE_FACE_COUNT {
+        for y in 0..face_size {
+            for x in 0..face_size {
+                let normal = level.get_direction(face, x, y);
+                let (tangent, bitangent) = normal.any_orthonormal_pair();
+                let mut color = glam::Vec3::ZERO;
+                for _ in 0..sample_count.max(1) {
+                    let cosine = random.next().powf(1.0 / (exponent + 1.0));
+                    let sine = (1.0 - cosine * cosine).max(0.0).sqrt();
+                    let angle = 2.0 * std::f32::consts::PI * random.next();
+                    let direction = tangent * sine * angle.cos()
+                        + bitangent * sine * angle.sin()
+                        + normal * cosine;
+                    color += source.sample(direction);
+                }
+                level.faces[face][(y * face_size + x) as usize] =
+                    color / sample_count.max(1) as f32;
+            }
+        }
+    }
+    level
+}
+
+/// Render the surroundings of a probe on the cpu and prefilter them
+pub fn reflection_probe_bake(
+    probe: &ReflectionProbe,
+    meshes: &[LightmapBakeMesh],
+    parameters: &ReflectionProbeBakeParameters,
+) -> Result<EnvironmentMap, EngineError> {
+    if parameters.face_size == 0 || parameters.level_count == 0 {
+        error!(
+            "Can't bake the reflection probe `{}' without faces or levels",
+            probe.name
+        );
+        return Err(EngineError::InvalidValue);
+    }
+    let scene = LightmapScene::new(meshes, &parameters.lighting);
+    let mut random = LightmapRandom::default();
+
+    let mut sharpest = EnvironmentMapLevel::new(parameters.face_size);
+    let samples = parameters.samples_per_texel.max(1);
+    for face in 0..REFLECTION_PROBE_FACE_COUNT {
+        for y in 0..parameters.face_size {
+            for x in 0..parameters.face_size {
+                let ray = Ray::new(probe.position, sharpest.get_direction(face, x, y));
+                let mut color = glam::Vec3::ZERO;
+                for _ in 0..samples {
+                    color += scene.sample_radiance(&ray, &mut random);
+                }
+                sharpest.faces[face][(y * parameters.face_size + x) as usize] =
+                    color / samples as f32;
+            }
+        }
+    }
+
+    let mut levels = Vec::with_capacity(parameters.level_count as usize);
+    for level in 1..parameters.level_count {
+        let roughness = level as f32 / (parameters.level_count - 1) as f32;
+        levels.push(reflection_probe_prefilter(
+            &sharpest,
+            (parameters.face_size >> level).max(1),
+            roughness,
+            parameters.prefilter_sample_count,
+            &mut random,
+        ));
+    }
+    levels.insert(0, sharpest);
+    Ok(EnvironmentMap { levels })
+}
+
+/// Load the baked probes from the directory, the missing ones are baked and saved there
+pub fn reflection_probes_load_or_bake(
+    directory: &Path,
+    probes: &[ReflectionProbe],
+    meshes: &[LightmapBakeMesh],
+    parameters: &ReflectionProbeBakeParameters,
+) -> Result<Vec<EnvironmentMap>, EngineError> {
+    let mut environment_maps = Vec::with_capacity(probes.len());
+    for probe in probes {
+        if let Some(environment_map) = EnvironmentMap::load(directory, &probe.name)? {
+            environment_maps.push(environment_map);
+            continue;
+        }
+        info!("Baking the reflection probe `{}'", probe.name);
+        let environment_map = reflection_probe_bake(probe, meshes, parameters)?;
+        environment_map.save(directory, &probe.name)?;
+        environment_maps.push(environment_map);
+    }
+    Ok(environment_maps)
+}
@@ -0,0 +1,210 @@
+use crate::{core::debug::errors::EngineError, error};
+
+use super::{
+    renderer_frontend::RendererFrontend, renderer_types::GeometryRenderData, scene::camera::Camera,
+    ui::immediate::ImmediateBatch,
+};
+
+/// Points of the frame where the games can draw
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum RenderStage {
+    /// In the world pass, after the opaque objects and before the transparent ones
+    AfterOpaque,
+    /// In the ui pass, over the post processed image and under the `BeforeUi` hooks
+    AfterPostProcessing,
+    /// In the ui pass, under the ui quads and shapes
+    BeforeUi,
+}
+
+/// Hook registered with `renderer_register_render_hook`
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct RenderHookHandle(u64);
+
+type RenderHookCallback = Box<dyn FnMut(&mut RenderHookContext) -> Result<(), EngineError> + Send>;
+
+struct RenderHook {
+    handle: RenderHookHandle,
+    stage: RenderStage,
+    callback: RenderHookCallback,
+}
+
+#[derive(Default)]
+pub(crate) struct RenderHooks {
+    hooks: Vec<RenderHook>,
+    next_handle: u64,
+}
+
+impl RenderHooks {
+    pub fn register(
+        &mut self,
+        stage: RenderStage,
+        callback: RenderHookCallback,
+    ) -> RenderHookHandle {
+        self.next_handle += 1;
+        let handle = RenderHookHandle(self.next_handle);
+        self.hooks.push(RenderHook {
+            handle,
+            stage,
+            callback,
+        });
+        handle
+    }
+
+    /// Returns false if the hook was not registered
+    pub fn unregister(&mut self, handle: RenderHookHandle) -> bool {
+        let hook_count = self.hooks.len();
+        self.hooks.retain(|hook| hook.handle != handle);
+        self.hooks.len() != hook_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    /// Add the hooks registered while the others were running
+    pub fn merge(&mut self, mut other: RenderHooks) {
+        self.next_handle = self.next_handle.max(other.next_handle);
+        self.hooks.append(&mut other.hooks);
+    }
+}
+
+/// What a hook can record, the draws go through the engine pipelines so the backend state stays valid
+/// The `renderer_*` functions must not be called from a hook, the renderer is in the middle of a frame
+pub struct RenderHookContext<'a> {
+    frontend: &'a mut RendererFrontend,
+    stage: RenderStage,
+    camera: Camera,
+    /// Shapes of the ui pass stages, drawn once the hooks return
+    immediate_batch: ImmediateBatch,
+}
+
+impl RenderHookContext<'_> {
+    pub fn get_stage(&self) -> RenderStage {
+        self.stage
+    }
+
+    /// Camera the frame is rendered from
+    pub fn get_camera(&self) -> Camera {
+        self.camera
+    }
+
+    /// Draw a geometry of the renderer in the world stage, with the default material when none is given
+    pub fn draw_geometry(
+        &mut self,
+        geometry_id: u32,
+        model: glam::Mat4,
+        material_id: Option<u32>,
+    ) -> Result<(), EngineError> {
+        if self.stage != RenderStage::AfterOpaque {
+            error!(
+                "Can't draw the geometry {} in the {:?} stage",
+                geometry_id, self.stage
+            );
+            return Err(EngineError::InvalidValue);
+        }
+        let geometry = match self.frontend.geometries.get(&geometry_id) {
+            Some(geometry) => geometry.clone_box(),
+            None => {
+                error!("Can't draw the unknown geometry {}", geometry_id);
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        let material = material_id
+            .and_then(|id| self.frontend.get_material(id))
+            .or(self.frontend.default_material.as_ref())
+            .cloned();
+        let geometry_data = GeometryRenderData::default()
+            .model(model)
+            .geometry(Some(geometry))
+            .material(material);
+        let frontend = &mut *self.frontend;
+        frontend
+            .backend
+            .as_mut()
+            .unwrap()
+            .update_object(&geometry_data, &frontend.texture_system)?;
+        self.frontend.draw_call_count += 1;
+        Ok(())
+    }
+
+    fn check_ui_stage(&self) -> Result<(), EngineError> {
+        if self.stage == RenderStage::AfterOpaque {
+            error!("The 2d shapes can only be drawn in the ui pass stages");
+            return Err(EngineError::InvalidValue);
+        }
+        Ok(())
+    }
+
+    /// Filled rectangle in screen pixels, see `ImmediateBatch::draw_rect`
+    pub fn draw_rect(
+        &mut self,
+        position: glam::Vec2,
+        size: glam::Vec2,
+        color: glam::Vec4,
+    ) -> Result<(), EngineError> {
+        self.check_ui_stage()?;
+        self.immediate_batch.draw_rect(position, size, color);
+        Ok(())
+    }
+
+    pub fn draw_circle(
+        &mut self,
+        center: glam::Vec2,
+        radius: f32,
+        color: glam::Vec4,
+    ) -> Result<(), EngineError> {
+        self.check_ui_stage()?;
+        self.immediate_batch.draw_circle(center, radius, color);
+        Ok(())
+    }
+
+    pub fn draw_line(
+        &mut self,
+        start: glam::Vec2,
+        end: glam::Vec2,
+        thickness: f32,
+        color: glam::Vec4,
+    ) -> Result<(), EngineError> {
+        self.check_ui_stage()?;
+        self.immediate_batch.draw_line(start, end, thickness, color);
+        Ok(())
+    }
+}
+
+impl RendererFrontend {
+    /// Run the hooks of the stage in their registration order
+    pub(crate) fn run_render_hooks(
+        &mut self,
+        stage: RenderStage,
+        camera: Camera,
+    ) -> Result<(), EngineError> {
+        if self.render_hooks.is_empty() {
+            return Ok(());
+        }
+        let mut hooks = std::mem::take(&mut self.render_hooks);
+        let mut context = RenderHookContext {
+            frontend: self,
+            stage,
+            camera,
+            immediate_batch: ImmediateBatch::default(),
+        };
+        let mut result = Ok(());
+        for hook in hooks.hooks.iter_mut().filter(|hook| hook.stage == stage) {
+            if let Err(err) = (hook.callback)(&mut context) {
+                error!("Failed to run the render hook {:?}: {:?}", hook.handle, err);
+                result = Err(EngineError::UpdateFailed);
+                break;
+            }
+        }
+        let immediate_batch = context.immediate_batch;
+        hooks.merge(std::mem::take(&mut self.render_hooks));
+        self.render_hooks = hooks;
+        result?;
+
+        let ui_camera = self.get_ui_camera()?;
+        self.backend.as_mut().unwrap().draw_immediate(
+            ui_camera.projection * ui_camera.view,
+            immediate_batch.get_vertices(),
+        )
+    }
+}
@@ -0,0 +1,124 @@
+use crate::resources::material::{BlendMode, MaterialRenderState};
+
+use super::renderer_types::GeometryRenderData;
+
+/// Opaque objects sharing a geometry and a material, drawn in a single call
+pub(crate) struct InstanceBatch {
+    /// The first object of the batch, its model is the first of the models
+    pub data: GeometryRenderData,
+    pub models: Vec<glam::Mat4>,
+}
+
+/// Draw calls of a frame, the opaque objects are drawn first then the transparent ones
+pub(crate) struct RenderQueue {
+    opaque: Vec<GeometryRenderData>,
+    /// With their squared distance to the camera
+    transparent: Vec<(f32, GeometryRenderData)>,
+    /// The opaque objects are drawn one by one when disabled
+    pub is_instancing_enabled: bool,
+}
+
+impl Default for RenderQueue {
+    fn default() -> Self {
+        Self {
+            opaque: Vec::new(),
+            transparent: Vec::new(),
+            is_instancing_enabled: true,
+        }
+    }
+}
+
+impl RenderQueue {
+    /// Blended materials and fading objects need what is behind them to be drawn first
+    pub fn is_transparent(data: &GeometryRenderData) -> bool {
+        data.fade < 1.0
+            || data.material.as_ref().is_some_and(|material| {
+                material.render_state.blend_mode != BlendMode::Opaque
+                    || material.diffuse_color.w < 1.0
+            })
+    }
+
+    /// Transparent objects don't write the depth so the ones behind them are still drawn
+    fn get_transparent_render_state(render_state: &MaterialRenderState) -> MaterialRenderState {
+        let blend_mode = match render_state.blend_mode {
+            BlendMode::Opaque => BlendMode::AlphaBlend,
+            blend_mode => blend_mode,
+        };
+        render_state
+            .depth(render_state.is_depth_test_enabled, false)
+            .blend_mode(blend_mode)
+    }
+
+    pub fn push(&mut self, mut data: GeometryRenderData, camera_position: glam::Vec3) {
+        if !Self::is_transparent(&data) {
+            self.opaque.push(data);
+            return;
+        }
+        if let Some(material) = data.material.as_mut() {
+            material.render_state = Self::get_transparent_render_state(&material.render_state);
+        }
+        let center = match &data.bounding_sphere {
+            Some(sphere) => data.model.transform_point3(sphere.center),
+            None => data.model.w_axis.truncate(),
+        };
+        self.transparent
+            .push((center.distance_squared(camera_position), data));
+    }
+
+    /// Objects drawn the same way apart from their model
+    fn can_be_instanced(first: &GeometryRenderData, second: &GeometryRenderData) -> bool {
+        let geometry_id =
+            |data: &GeometryRenderData| data.geometry.as_ref().map(|geometry| geometry.get_id());
+        let material_key = |data: &GeometryRenderData| {
+            data.material
+                .as_ref()
+                .map(|material| (material.id, material.generation))
+        };
+        geometry_id(first).is_some()
+            && geometry_id(first) == geometry_id(second)
+            && material_key(first) == material_key(second)
+            && first.reflection_probe == second.reflection_probe
+    }
+
+    /// Group the opaque objects by pipeline, then by material and geometry to instance them,
+    /// and order the transparent ones back to front
+    pub fn sort(&mut self) {
+        self.opaque.sort_by_cached_key(|data| {
+            (
+                data.material
+                    .as_ref()
+                    .map(|material| (material.render_state.get_hash(), material.id)),
+                data.geometry.as_ref().map(|geometry| geometry.get_id()),
+            )
+        });
+        self.transparent
+            .sort_by(|(first, _), (second, _)| second.total_cmp(first));
+    }
+
+    pub fn get_transparent_count(&self) -> usize {
+        self.transparent.len()
+    }
+
+    /// Drawn first, in drawing order, the neighbours sharing a geometry and a material are batched
+    pub fn drain_opaque(&mut self) -> Vec<InstanceBatch> {
+        let mut batches: Vec<InstanceBatch> = Vec::new();
+        for data in self.opaque.drain(..) {
+            if let Some(batch) = batches.last_mut() {
+                if self.is_instancing_enabled && Self::can_be_instanced(&batch.data, &data) {
+                    batch.models.push(data.model);
+                    continue;
+                }
+            }
+            batches.push(InstanceBatch {
+                models: vec![data.model],
+                data,
+            });
+        }
+        batches
+    }
+
+    /// Drawn once the opaque objects are done, back to front
+    pub fn drain_transparent(&mut self) -> impl Iterator<Item = GeometryRenderData> + '_ {
+        self.transparent.drain(..).map(|(_, data)| data)
+    }
+}
@@ -1,25 +1,77 @@
 use crate::{
-    core::debug::errors::EngineError,
+    core::{application::windows::WindowHandle, debug::errors::EngineError},
     error,
     platforms::platform::Platform,
-    resources::texture::{Texture, TextureCreatorParameters},
+    resources::{
+        geometry::{Geometry, GeometryCreatorParameters},
+        texture::{Texture, TextureCreatorParameters},
+        texture_system::TextureSystem,
+    },
 };
 
 use super::{
-    renderer_types::{GeometryRenderData, RendererBackendType},
+    lighting::LightingSystem,
+    null_backend::NullRendererBackend,
+    post_process::PostProcessSettings,
+    reflection_probe::EnvironmentMap,
+    renderer_types::{
+        AntiAliasing, CameraPassTarget, ComputeBinding, DepthStencilSettings, DisplayOutput,
+        GeometryRenderData, ImmediateVertexData, OutputColorSpace, RendererBackendType,
+        RendererStatistics, UiRenderData, UiVertexData, VertexData, VsyncMode,
+    },
+    scene::camera::CameraViewport,
+    screenshot::{HdrScreenshotPixels, ScreenshotPixels},
     vulkan::vulkan_types::VulkanRendererBackend,
 };
 
-pub(crate) trait RendererBackend {
-    fn init(&mut self, application_name: &str, platform: &dyn Platform) -> Result<(), EngineError>;
+pub(crate) trait RendererBackend: Send {
+    /// The anti aliasing is lowered and the hdr output falls back to sdr if the device does not support them
+    /// The depth formats are tried in order, the initialization fails if none is supported
+    fn init(
+        &mut self,
+        application_name: &str,
+        platform: &dyn Platform,
+        anti_aliasing: AntiAliasing,
+        display_output: DisplayOutput,
+        depth_stencil: &DepthStencilSettings,
+    ) -> Result<(), EngineError>;
 
     fn shutdown(&mut self) -> Result<(), EngineError>;
 
     fn resize(&mut self, width: u32, height: u32) -> Result<(), EngineError>;
 
+    /// Recreates the swapchain with the present mode of the new setting
+    fn set_vsync_mode(&mut self, mode: VsyncMode) -> Result<(), EngineError>;
+
+    /// Fraction of the framebuffer size the world is drawn at, it is upscaled before the post processing
+    fn set_render_scale(&mut self, scale: f32) -> Result<(), EngineError>;
+
+    /// Encoding of the presented images, srgb unless the hdr output was granted
+    fn get_output_color_space(&self) -> Result<OutputColorSpace, EngineError>;
+
     /// Returns true if the frame had begun correctly
     fn begin_frame(&mut self, delta_time: f64) -> Result<bool, EngineError>;
 
+    /// Draw a camera into an area of the texture or of the additional window in the next frame, before the world
+    /// The frame begins with the pass of the first camera, every camera pass is ended in the order they were added
+    fn add_camera_pass(
+        &mut self,
+        target: CameraPassTarget<'_>,
+        viewport: Option<CameraViewport>,
+    ) -> Result<(), EngineError>;
+    /// Copy the open camera pass into its texture or its window, the next camera pass or the world pass is open after it
+    fn end_camera_pass(&mut self) -> Result<(), EngineError>;
+    /// Give the additional window its own surface and swapchain, the cameras can then draw into it
+    fn create_window_surface(
+        &mut self,
+        window: WindowHandle,
+        platform: &dyn Platform,
+    ) -> Result<(), EngineError>;
+    /// Destroy the surface and the swapchain of the additional window, before the platform closes it
+    fn destroy_window_surface(&mut self, window: WindowHandle) -> Result<(), EngineError>;
+    /// Clip the draws of the world to an area of the framebuffer, the whole framebuffer when none
+    fn set_world_viewport(&mut self, viewport: Option<CameraViewport>) -> Result<(), EngineError>;
+
     fn end_frame(&mut self, delta_time: f64) -> Result<(), EngineError>;
 
     fn increase_frame_number(&mut self) -> Result<(), EngineError>;
@@ -31,37 +83,166 @@ pub(crate) trait RendererBackend {
         projection: glam::Mat4,
         view: glam::Mat4,
         view_position: glam::Vec3,
-        ambient_colour: glam::Vec4,
+        lighting: &LightingSystem,
         mode: i32,
     ) -> Result<(), EngineError>;
 
-    fn update_object(&mut self, data: &GeometryRenderData) -> Result<(), EngineError>;
+    /// Fullscreen passes applied to the world of this frame, they run when the ui pass begins
+    /// The accessibility color filter is applied first, to the linear colors
+    fn update_post_process(
+        &mut self,
+        settings: &PostProcessSettings,
+        color_filter: glam::Mat4,
+    ) -> Result<(), EngineError>;
+
+    /// The textures of the material are looked up in the given texture system
+    fn update_object(
+        &mut self,
+        data: &GeometryRenderData,
+        textures: &TextureSystem,
+    ) -> Result<(), EngineError>;
+    /// Draw the geometry and the material of the data once per model, in a single draw call
+    /// The model of the data is ignored
+    fn draw_instances(
+        &mut self,
+        data: &GeometryRenderData,
+        models: &[glam::Mat4],
+        textures: &TextureSystem,
+    ) -> Result<(), EngineError>;
+    /// Must be called once the world is drawn, the following draws are on top of it without depth
+    fn begin_ui_pass(&mut self) -> Result<(), EngineError>;
+    /// Draw the textured triangles of the ui overlay seen through the 2d camera projection
+    fn draw_ui(
+        &mut self,
+        projection: glam::Mat4,
+        vertices: &[UiVertexData],
+        batches: &[UiRenderData],
+    ) -> Result<(), EngineError>;
+    /// Draw triangles in the ui pass seen through the 2d camera projection
+    fn draw_immediate(
+        &mut self,
+        projection: glam::Mat4,
+        vertices: &[ImmediateVertexData],
+    ) -> Result<(), EngineError>;
+
+    /// Returns the id of the shader resources needed to draw a new object
+    fn acquire_object_resources(&mut self) -> Result<u32, EngineError>;
+    fn release_object_resources(&mut self, object_id: u32) -> Result<(), EngineError>;
 
     fn get_aspect_ratio(&self) -> Result<f32, EngineError>;
 
+    /// Returns the (width, height) of the framebuffer
+    fn get_framebuffer_size(&self) -> Result<(u32, u32), EngineError>;
+
+    /// Number of samples per pixel of the scene, 1 without anti aliasing
+    fn get_sample_count(&self) -> Result<u32, EngineError>;
+
+    /// Statistics of the last frame completed by the gpu
+    fn get_statistics(&self) -> Result<RendererStatistics, EngineError>;
+
+    /// Copy the image presented by the next frame, resolved and ready to be displayed
+    /// With `hdr', the linear hdr target is also copied before the post processing
+    fn request_screenshot(&mut self, hdr: bool) -> Result<(), EngineError>;
+    /// The copy requested before, once its frame is done on the gpu
+    fn take_screenshot(&mut self) -> Result<Option<ScreenshotPixels>, EngineError>;
+    /// The hdr copy of the same frame, taken along with `take_screenshot'
+    fn take_hdr_screenshot(&mut self) -> Result<Option<HdrScreenshotPixels>, EngineError>;
+
+    /// Downsample the hdr target of every frame, before the post processing, to measure its luminance
+    fn set_luminance_readback_enabled(&mut self, is_enabled: bool) -> Result<(), EngineError>;
+    /// Linear colors of the last downsample done on the gpu, none until a new one is read
+    fn take_luminance_samples(&mut self) -> Result<Option<Vec<glam::Vec3>>, EngineError>;
+
+    /// Replace the environment maps sampled by the objects, the probe index of an object is its index here
+    /// Every map must have the same face size and number of levels
+    fn upload_reflection_probes(
+        &mut self,
+        environment_maps: &[&EnvironmentMap],
+    ) -> Result<(), EngineError>;
+
     fn create_texture(
-        &self,
+        &mut self,
         params: TextureCreatorParameters,
     ) -> Result<Box<dyn Texture>, EngineError>;
     fn destroy_texture(&self, texture: &dyn Texture) -> Result<(), EngineError>;
+    /// Destroy the texture once the frames in flight are done with it, without waiting for the gpu
+    fn retire_texture(&mut self, texture: Box<dyn Texture>) -> Result<(), EngineError>;
+    /// Overwrite a sub-rectangle of the texture with tightly packed pixels
+    fn update_texture_region(
+        &self,
+        texture: &dyn Texture,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Result<(), EngineError>;
+    /// Copy the texture back to the cpu as tightly packed rgba8 rows
+    fn read_texture_pixels(&self, texture: &dyn Texture) -> Result<Vec<u8>, EngineError>;
+
+    fn create_geometry(
+        &mut self,
+        params: GeometryCreatorParameters,
+    ) -> Result<Box<dyn Geometry>, EngineError>;
+    fn destroy_geometry(&mut self, geometry: &dyn Geometry) -> Result<(), EngineError>;
+    /// Replace the vertices of a geometry, e.g. after blending its morph targets
+    /// The vertex count can't change
+    fn update_geometry_vertices(
+        &mut self,
+        geometry: &dyn Geometry,
+        vertices: &[VertexData],
+    ) -> Result<(), EngineError>;
+
+    /// Returns the id of a compute shader from the assets/shaders/ folder
+    fn create_compute_shader(&mut self, path: &str) -> Result<u32, EngineError>;
+    fn destroy_compute_shader(&mut self, shader_id: u32) -> Result<(), EngineError>;
+    /// Returns the id of a buffer read and written by the compute shaders
+    fn create_storage_buffer(&mut self, size: usize) -> Result<u32, EngineError>;
+    fn destroy_storage_buffer(&mut self, storage_buffer_id: u32) -> Result<(), EngineError>;
+    /// Must be called between the beginning and the end of a frame, ordered with its dispatches
+    fn write_storage_buffer(
+        &mut self,
+        storage_buffer_id: u32,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<(), EngineError>;
+    /// Copy the storage buffer back to the cpu once the frames submitted so far are done
+    fn read_storage_buffer(&self, storage_buffer_id: u32) -> Result<Vec<u8>, EngineError>;
+    /// Must be called between the beginning and the end of a frame, the frame draws after it
+    fn dispatch_compute(
+        &mut self,
+        shader_id: u32,
+        bindings: &[ComputeBinding],
+        group_count: glam::UVec3,
+        push_constants: &[u8],
+    ) -> Result<(), EngineError>;
 }
 
 pub(crate) fn renderer_backend_init(
     renderer_type: RendererBackendType,
     application_name: &str,
     platform: &dyn Platform,
-) -> Result<impl RendererBackend, EngineError> {
+    anti_aliasing: AntiAliasing,
+    display_output: DisplayOutput,
+    depth_stencil: &DepthStencilSettings,
+) -> Result<Box<dyn RendererBackend>, EngineError> {
     match renderer_type {
         RendererBackendType::Vulkan => {
             let mut backend = VulkanRendererBackend::default();
-            match backend.init(application_name, platform) {
+            match backend.init(
+                application_name,
+                platform,
+                anti_aliasing,
+                display_output,
+                depth_stencil,
+            ) {
                 Ok(backend) => backend,
                 Err(err) => {
                     error!("Failed to init the Vulkan renderer backend: {:?}", err);
                     return Err(EngineError::InitializationFailed);
                 }
             }
-            Ok(backend)
+            Ok(Box::new(backend))
         }
         RendererBackendType::OpenGl => {
             error!("The OpenGL backend is not yet implemented");
@@ -71,5 +252,16 @@ pub(crate) fn renderer_backend_init(
             error!("The DirectX backend is not yet implemented");
             Err(EngineError::NotImplemented)
         }
+        RendererBackendType::Null => {
+            let mut backend = NullRendererBackend::default();
+            backend.init(
+                application_name,
+                platform,
+                anti_aliasing,
+                display_output,
+                depth_stencil,
+            )?;
+            Ok(Box::new(backend))
+        }
     }
 }
@@ -1,33 +1,160 @@
 use std::{
+    collections::{HashMap, HashSet},
     path::{Path, PathBuf},
-    sync::Mutex,
 };
 
-use image::ImageReader;
-use once_cell::sync::Lazy;
-
 use crate::{
-    core::debug::errors::EngineError,
-    error,
+    core::{
+        application::windows::WindowHandle,
+        debug::errors::EngineError,
+        ecs::{
+            components::{MeshRenderer, SpriteRenderer, Transform},
+            ecs_get_active_camera,
+            entity::Entity,
+            fetch_global_ecs,
+        },
+        globals::{EngineGlobal, EngineGlobalGuard},
+        systems::{
+            jobs::{job_submit, JobHandle},
+            logger::logger_get_recent_messages,
+        },
+    },
+    error, info,
     platforms::platform::Platform,
-    renderer::renderer_types::GeometryRenderData,
-    resources::texture::{Texture, TextureCreatorParameters},
+    renderer::renderer_types::{GeometryRenderData, VertexData},
+    resources::{
+        font::{font_load_fnt, BitmapFont},
+        geometry::{Geometry, GeometryCreatorParameters},
+        gltf::{gltf_load, GltfInstance, GltfScene},
+        material::{
+            BlendMode, Material, MaterialCreatorParameters, MaterialRenderState,
+            MaterialTextureSlot, MATERIAL_DEFAULT_SHININESS, MATERIAL_DEFAULT_SPECULAR_STRENGTH,
+        },
+        morph_targets::{MorphGeometry, MorphTargetSet},
+        texture::TextureCreatorParameters,
+        texture_system::{texture_load_image, TextureHandle, TextureSystem},
+    },
     warn,
 };
 
 use super::{
+    accessibility::AccessibilitySettings,
+    exposure::{AutoExposure, AutoExposureParameters},
+    lighting::{DirectionalLight, LightingSystem, PointLight},
+    lightmap::LightmapBakeMesh,
+    object_names::{ObjectName, ObjectNames},
+    post_process::PostProcessSettings,
+    reflection_probe::{
+        reflection_probes_load_or_bake, EnvironmentMap, ReflectionProbe,
+        ReflectionProbeBakeParameters,
+    },
+    render_hooks::{RenderHookContext, RenderHookHandle, RenderHooks, RenderStage},
+    render_queue::RenderQueue,
     renderer_backend::{renderer_backend_init, RendererBackend},
-    renderer_types::{RenderFrameData, RendererBackendType},
-    scene::camera::{Camera, CameraCreatorParameters},
+    renderer_types::{
+        AntiAliasing, CameraPassTarget, ComputeBinding, ComputeCommand, ComputeResource,
+        DepthStencilSettings, DisplayOutput, OutputColorSpace, RenderFrameData,
+        RendererBackendType, RendererStatistics, UiRenderData, VsyncMode,
+    },
+    scene::{
+        camera::{Camera, CameraCreatorParameters, CameraViewport, ProjectionType},
+        camera_set::{CameraSet, CameraTarget, NamedCamera},
+        culling::{BoundingSphere, SmallObjectCullingParameters},
+        debug_camera::DebugCamera,
+        graph::{SceneGraph, SceneNodeId, SceneRenderable, SceneTransform},
+        picking::PickedObject,
+        spatial::{Aabb, Ray, SpatialHandle, SpatialTree},
+        streaming::scene_streaming_stop,
+    },
+    screenshot::ScreenshotRequest,
+    ui::{
+        debug_workspace::{DebugDockArea, DebugPanelKind, DebugWorkspace},
+        immediate::ImmediateBatch,
+        layout::{UiElementId, UiElementParameters, UiLayout, UiRect},
+        nine_slice::NineSlicePanel,
+        overlay::{UiOverlay, UiQuad},
+        text::{text_build_quads, text_measure},
+    },
 };
 
+struct MaterialReference {
+    material: Material,
+    reference_count: u32,
+    auto_release: bool,
+}
+
 #[derive(Default)]
 pub(crate) struct RendererFrontend {
     pub backend: Option<Box<dyn RendererBackend>>,
     pub main_camera: Option<Camera>,
-
-    // TODO: temporary
-    pub default_texture: Option<Box<dyn Texture>>,
+    /// Replaces the main camera for the rendering while it is active
+    pub debug_camera: DebugCamera,
+    /// Cameras added by the game next to the main one
+    pub cameras: CameraSet,
+    /// Camera of the active ecs camera entity, updated at the start of each frame
+    pub ecs_camera: Option<Camera>,
+    /// Replaces the screen camera of the ui pass, e.g. to pan and zoom a 2d game
+    pub ui_camera: Option<Camera>,
+    pub small_object_culling: SmallObjectCullingParameters,
+    pub scene_graph: SceneGraph,
+    /// World bounds of the visible entities with a mesh renderer, synced at the start of each frame
+    ecs_spatial_tree: SpatialTree<Entity>,
+    /// Handle in the tree and world bounds of the entities
+    ecs_spatial_handles: HashMap<Entity, (SpatialHandle, BoundingSphere)>,
+    /// Visible entities with a mesh renderer but no bounds, never culled
+    ecs_unbounded_entities: Vec<Entity>,
+    /// Every geometry created through the renderer, by id
+    pub geometries: HashMap<u32, Box<dyn Geometry>>,
+    /// Geometries blended from morph targets, by geometry id
+    morph_geometries: HashMap<u32, MorphGeometry>,
+    /// Every acquired material, by id
+    materials: HashMap<u32, MaterialReference>,
+    next_material_id: u32,
+    /// Used by the renderables without material
+    pub default_material: Option<Material>,
+    pub ui_layout: UiLayout,
+    /// Textured quads submitted since the last frame
+    pub ui_overlay: UiOverlay,
+    /// Sampled by the untextured ui quads
+    pub ui_white_texture: Option<TextureHandle>,
+    /// Every loaded font, by id
+    fonts: HashMap<u32, BitmapFont>,
+    next_font_id: u32,
+    /// Font of the text drawn, the first loaded font by default
+    pub current_font: Option<u32>,
+    /// 2d shapes submitted since the last frame
+    pub immediate_batch: ImmediateBatch,
+    pub accessibility: AccessibilitySettings,
+    /// Dynamic lights shading the objects
+    pub lighting: LightingSystem,
+    /// Exposure adapting to the luminance of the frames, used by the tonemapping when the settings ask for it
+    pub auto_exposure: AutoExposure,
+    /// Fullscreen passes between the world and the ui
+    pub post_process: PostProcessSettings,
+    /// Number of draw calls sent to the backend during the last frame, a batch counts once
+    pub draw_call_count: u32,
+    /// Number of those objects drawn after the opaque ones
+    pub transparent_draw_call_count: u32,
+    /// Opaque objects drawn in a batch with others during the last frame
+    pub instanced_object_count: u32,
+    /// Visible objects of the frame in drawing order
+    render_queue: RenderQueue,
+    /// Baked surroundings used for the reflections, uploaded to the backend in this order
+    pub reflection_probes: Vec<(ReflectionProbe, EnvironmentMap)>,
+    /// Every texture used by the materials, and the default one
+    pub texture_system: TextureSystem,
+    /// Docked debug panels, only drawn in debug builds
+    pub debug_workspace: DebugWorkspace,
+    /// Saved once the backend read the presented frame back
+    pub pending_screenshot: Option<ScreenshotRequest>,
+    /// Game callbacks drawing at fixed points of the frame
+    pub render_hooks: RenderHooks,
+    /// Debug names of the objects, shown in the logs and the entity inspector
+    pub object_names: ObjectNames,
+    /// Storage buffer writes and dispatches run before the next frame is drawn
+    compute_commands: Vec<ComputeCommand>,
+    /// Additional windows the cameras can draw into, with the size they were opened at
+    windows: HashMap<WindowHandle, (u32, u32)>,
 }
 
 impl RendererFrontend {
@@ -36,6 +163,142 @@ impl RendererFrontend {
         camera.set_view(new_camera.view);
     }
 
+    /// The camera the frame is rendered from, the debug one if it is active,
+    /// then the active named one, then the active ecs camera entity
+    pub fn get_render_camera(&self) -> Option<Camera> {
+        if self.debug_camera.is_active {
+            Some(self.debug_camera.camera)
+        } else if let Some(named_camera) = self.cameras.get_active() {
+            Some(named_camera.camera)
+        } else if self.ecs_camera.is_some() {
+            self.ecs_camera
+        } else {
+            self.main_camera
+        }
+    }
+
+    /// Orthographic camera of the ui pass, looking at the screen pixels by default
+    pub fn get_ui_camera(&self) -> Result<Camera, EngineError> {
+        if let Some(camera) = self.ui_camera {
+            return Ok(camera);
+        }
+        let (width, height) = get_backend(&self.backend)?.get_framebuffer_size()?;
+        Ok(Camera::new_screen(width as f32, height as f32))
+    }
+
+    /// Visible renderables of the scene with their opacity factor, the small objects fade out
+    fn cull_scene(
+        &self,
+        camera: &Camera,
+    ) -> Result<Vec<(glam::Mat4, SceneRenderable, f32)>, EngineError> {
+        let (_, viewport_height) = get_backend(&self.backend)?.get_framebuffer_size()?;
+        let frustum = camera.get_frustum();
+        let small_object_culling = &self.small_object_culling;
+        let visibility = |sphere: Option<&BoundingSphere>| match sphere {
+            // Objects without bounds are never culled
+            None => Some(1.0),
+            Some(sphere) if !frustum.intersects_sphere(sphere) => None,
+            Some(sphere) => {
+                small_object_culling.get_visibility(camera, sphere, viewport_height as f32)
+            }
+        };
+        let mut renderables = self.scene_graph.cull_renderables(&frustum, visibility);
+
+        // The entities with a transform and a mesh renderer are drawn like the scene nodes
+        let ecs = fetch_global_ecs(EngineError::AccessFailed)?;
+        let entities = self
+            .ecs_spatial_tree
+            .query_frustum(&frustum)
+            .into_iter()
+            .filter_map(|handle| self.ecs_spatial_tree.get(handle).copied())
+            .chain(self.ecs_unbounded_entities.iter().copied());
+        for entity in entities {
+            let (mesh_renderer, transform) = match (
+                ecs.world.get_component::<MeshRenderer>(entity),
+                ecs.world.get_component::<Transform>(entity),
+            ) {
+                (Some(mesh_renderer), Some(transform)) => (mesh_renderer, transform),
+                _ => continue,
+            };
+            let bounds = self
+                .ecs_spatial_handles
+                .get(&entity)
+                .map(|(_, bounds)| bounds);
+            if let Some(fade) = visibility(bounds) {
+                renderables.push((transform.get_matrix(), mesh_renderer.get_renderable(), fade));
+            }
+        }
+        Ok(renderables)
+    }
+
+    /// Move the visible entities with a mesh renderer to their world bounds in the spatial tree,
+    /// the tree is only restructured for the entities leaving their enlarged box
+    fn update_ecs_spatial_tree(&mut self) -> Result<(), EngineError> {
+        let ecs = fetch_global_ecs(EngineError::UpdateFailed)?;
+        let mut bounded_entities = HashSet::new();
+        self.ecs_unbounded_entities.clear();
+        for (entity, mesh_renderer, transform) in ecs.world.query_pair::<MeshRenderer, Transform>()
+        {
+            if !mesh_renderer.is_visible {
+                continue;
+            }
+            let bounds = match mesh_renderer.bounding_sphere {
+                Some(sphere) => sphere.transform(&transform.get_matrix()),
+                None => {
+                    self.ecs_unbounded_entities.push(entity);
+                    continue;
+                }
+            };
+            bounded_entities.insert(entity);
+            match self.ecs_spatial_handles.get_mut(&entity) {
+                Some((handle, old_bounds)) => {
+                    self.ecs_spatial_tree
+                        .update(*handle, Aabb::from_sphere(&bounds));
+                    *old_bounds = bounds;
+                }
+                None => {
+                    let handle = self
+                        .ecs_spatial_tree
+                        .insert(Aabb::from_sphere(&bounds), entity);
+                    self.ecs_spatial_handles.insert(entity, (handle, bounds));
+                }
+            }
+        }
+        // The despawned, hidden and unbounded entities leave the tree
+        let tree = &mut self.ecs_spatial_tree;
+        self.ecs_spatial_handles.retain(|entity, (handle, _)| {
+            let is_kept = bounded_entities.contains(entity);
+            if !is_kept {
+                tree.remove(*handle);
+            }
+            is_kept
+        });
+        Ok(())
+    }
+
+    /// Closest scene node or entity whose world bounds of the last frame are hit by the ray,
+    /// with the hit distance
+    pub fn raycast(&self, ray: &Ray, max_distance: f32) -> Option<(PickedObject, f32)> {
+        let node = self
+            .scene_graph
+            .raycast(ray, max_distance)
+            .map(|(node, distance)| (PickedObject::SceneNode(node), distance));
+        let max_distance = node.map_or(max_distance, |(_, distance)| distance);
+        let entity = self
+            .ecs_spatial_tree
+            .raycast_with(ray, max_distance, |entity, _| {
+                self.ecs_spatial_handles
+                    .get(entity)
+                    .and_then(|(_, bounds)| bounds.intersects_ray(ray, max_distance))
+            })
+            .and_then(|(handle, distance)| {
+                self.ecs_spatial_tree
+                    .get(handle)
+                    .map(|entity| (PickedObject::Entity(*entity), distance))
+            });
+        entity.or(node)
+    }
+
     fn init_default_texture(&mut self) -> Result<(), EngineError> {
         // NOTE: Create default texture, a 256x256 blue/white checkerboard pattern
         // This is done in code to eliminate asset dependencies
@@ -62,72 +325,142 @@ impl RendererFrontend {
             has_transparency: false,
             is_default: true,
         };
-        let texture = match self.create_texture(texture_params) {
+        let backend = get_backend_mut(&mut self.backend)?;
+        let texture = match backend.create_texture(texture_params) {
             Ok(texture) => texture,
             Err(err) => {
                 error!("Failed to create the default texture: {:?}", err);
                 return Err(EngineError::InitializationFailed);
             }
         };
-        self.default_texture = Some(texture);
+        self.texture_system.set_default_texture(backend, texture)
+    }
+
+    fn init_ui_white_texture(&mut self) -> Result<(), EngineError> {
+        let pixels = [255u8; 4];
+        let texture_params = TextureCreatorParameters {
+            name: "ui white texture",
+            auto_release: false,
+            width: 1,
+            height: 1,
+            nb_channels: 4,
+            pixels: &pixels,
+            has_transparency: false,
+            is_default: false,
+        };
+        match self.acquire_texture(texture_params) {
+            Ok(texture) => self.ui_white_texture = Some(texture),
+            Err(err) => {
+                error!("Failed to create the ui white texture: {:?}", err);
+                return Err(EngineError::InitializationFailed);
+            }
+        }
         Ok(())
     }
 
+    fn init_default_material(&mut self) {
+        // Without diffuse texture the backend uses the default one
+        self.default_material = Some(Material {
+            id: u32::MAX,
+            name: String::from("default material"),
+            generation: 0,
+            diffuse_color: glam::Vec4::ONE,
+            diffuse_texture: None,
+            lightmap_texture: None,
+            normal_texture: None,
+            specular_texture: None,
+            specular_strength: MATERIAL_DEFAULT_SPECULAR_STRENGTH,
+            shininess: MATERIAL_DEFAULT_SHININESS,
+            render_state: MaterialRenderState::default(),
+        });
+    }
+
     fn init_renderer_backend(
         &mut self,
+        backend_type: RendererBackendType,
         application_name: &str,
         platform: &dyn Platform,
+        anti_aliasing: AntiAliasing,
+        display_output: DisplayOutput,
+        depth_stencil: &DepthStencilSettings,
     ) -> Result<(), EngineError> {
-        // TODO: make this configurable
-        let backend =
-            match renderer_backend_init(RendererBackendType::Vulkan, application_name, platform) {
-                Ok(backend) => backend,
-                Err(err) => {
-                    error!("Failed to initialize the renderer backend: {:?}", err);
-                    return Err(EngineError::InitializationFailed);
-                }
-            };
-        self.backend = Some(Box::new(backend));
+        let backend = match renderer_backend_init(
+            backend_type,
+            application_name,
+            platform,
+            anti_aliasing,
+            display_output,
+            depth_stencil,
+        ) {
+            Ok(backend) => backend,
+            Err(err) => {
+                error!("Failed to initialize the renderer backend: {:?}", err);
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        self.backend = Some(backend);
         Ok(())
     }
 
     fn init_default_camera(&mut self) -> Result<(), EngineError> {
         self.main_camera = Some(Camera::new(
             CameraCreatorParameters::default(),
-            self.backend.as_ref().unwrap().get_aspect_ratio()?,
+            get_backend(&self.backend)?.get_aspect_ratio()?,
         ));
         Ok(())
     }
 
     pub(crate) fn init(
         &mut self,
+        backend_type: RendererBackendType,
         application_name: &str,
         platform: &dyn Platform,
+        anti_aliasing: AntiAliasing,
+        display_output: DisplayOutput,
+        depth_stencil: &DepthStencilSettings,
     ) -> Result<(), EngineError> {
-        self.init_renderer_backend(application_name, platform)?;
+        self.init_renderer_backend(
+            backend_type,
+            application_name,
+            platform,
+            anti_aliasing,
+            display_output,
+            depth_stencil,
+        )?;
         // Default camera
         self.init_default_camera()?;
         // Default texture
         self.init_default_texture()?;
+        // Default material
+        self.init_default_material();
+        // Ui
+        self.init_ui_white_texture()?;
+        let (width, height) = get_backend(&self.backend)?.get_framebuffer_size()?;
+        self.ui_layout.set_screen_size(width, height);
         Ok(())
     }
 
-    fn destroy_default_texture(&mut self) -> Result<(), EngineError> {
-        match &self.default_texture {
-            Some(texture) => {
-                if let Err(err) = self
-                    .backend
-                    .as_ref()
-                    .unwrap()
-                    .destroy_texture(texture.as_ref())
-                {
-                    error!("Failed to destroy the default texture: {:?}", err);
-                    return Err(EngineError::ShutdownFailed);
-                }
-                Ok(())
+    fn destroy_textures(&mut self) -> Result<(), EngineError> {
+        if let Err(err) = self
+            .texture_system
+            .shutdown(get_backend_mut(&mut self.backend)?)
+        {
+            error!("Failed to destroy the textures: {:?}", err);
+            return Err(EngineError::ShutdownFailed);
+        }
+        Ok(())
+    }
+
+    fn destroy_geometries(&mut self) -> Result<(), EngineError> {
+        for (id, geometry) in self.geometries.drain() {
+            if let Err(err) =
+                get_backend_mut(&mut self.backend)?.destroy_geometry(geometry.as_ref())
+            {
+                error!("Failed to destroy the geometry {}: {:?}", id, err);
+                return Err(EngineError::ShutdownFailed);
             }
-            None => Ok(()),
         }
+        Ok(())
     }
 
     fn destroy_default_camera(&mut self) -> Result<(), EngineError> {
@@ -136,22 +469,38 @@ impl RendererFrontend {
     }
 
     fn destroy_renderer_backend(&mut self) -> Result<(), EngineError> {
-        if let Err(err) = self.backend.as_mut().unwrap().shutdown() {
+        if let Err(err) = get_backend_mut(&mut self.backend)?.shutdown() {
             error!("Failed to shutdown the renderer backend: {:?}", err);
             return Err(EngineError::ShutdownFailed);
         }
         Ok(())
     }
 
+    /// Index of the probe the closest to the position in the probes uploaded to the backend
+    fn get_closest_reflection_probe_index(&self, position: glam::Vec3) -> Option<u32> {
+        self.reflection_probes
+            .iter()
+            .enumerate()
+            .min_by(|(_, (a, _)), (_, (b, _))| {
+                a.position
+                    .distance_squared(position)
+                    .total_cmp(&b.position.distance_squared(position))
+            })
+            .map(|(index, _)| index as u32)
+    }
+
     pub(crate) fn shutdown(&mut self) -> Result<(), EngineError> {
-        self.destroy_default_texture()?;
+        self.materials.clear();
+        self.reflection_probes.clear();
+        self.destroy_geometries()?;
+        self.destroy_textures()?;
         self.destroy_default_camera()?;
         self.destroy_renderer_backend()?;
         Ok(())
     }
 
     fn begin_frame(&mut self, delta_time: f64) -> Result<bool, EngineError> {
-        match self.backend.as_mut().unwrap().begin_frame(delta_time) {
+        match get_backend_mut(&mut self.backend)?.begin_frame(delta_time) {
             Ok(val) => Ok(val),
             Err(err) => {
                 error!("Failed to begin the renderer backend frame: {:?}", err);
@@ -161,14 +510,14 @@ impl RendererFrontend {
     }
 
     fn end_frame(&mut self, delta_time: f64) -> Result<(), EngineError> {
-        match self.backend.as_mut().unwrap().end_frame(delta_time) {
+        match get_backend_mut(&mut self.backend)?.end_frame(delta_time) {
             Ok(()) => (),
             Err(err) => {
                 error!("Failed to end the renderer backend frame: {:?}", err);
                 return Err(EngineError::Unknown);
             }
         };
-        match self.backend.as_mut().unwrap().increase_frame_number() {
+        match get_backend_mut(&mut self.backend)?.increase_frame_number() {
             Ok(()) => (),
             Err(err) => {
                 error!(
@@ -178,10 +527,324 @@ impl RendererFrontend {
                 return Err(EngineError::Unknown);
             }
         };
+        if let Err(err) = self.save_screenshot() {
+            error!("Failed to save the screenshot: {:?}", err);
+            return Err(EngineError::Unknown);
+        }
+        Ok(())
+    }
+
+    pub fn request_screenshot(&mut self, request: ScreenshotRequest) -> Result<(), EngineError> {
+        if self.pending_screenshot.is_some() {
+            warn!(
+                "A screenshot is already pending, {:?} is ignored",
+                request.path
+            );
+            return Ok(());
+        }
+        get_backend_mut(&mut self.backend)?.request_screenshot(request.hdr_path.is_some())?;
+        self.pending_screenshot = Some(request);
+        Ok(())
+    }
+
+    /// Encode the screenshot on a worker thread once the backend read it
+    fn save_screenshot(&mut self) -> Result<(), EngineError> {
+        if self.pending_screenshot.is_none() {
+            return Ok(());
+        }
+        let backend = get_backend_mut(&mut self.backend)?;
+        let pixels = match backend.take_screenshot()? {
+            Some(pixels) => pixels,
+            None => return Ok(()),
+        };
+        let hdr_pixels = backend.take_hdr_screenshot()?;
+        let request = self.pending_screenshot.take().unwrap();
+        let path = request.path.clone();
+        job_submit(
+            move || pixels.save(&request.path),
+            move |result| {
+                match result {
+                    Ok(()) => info!("Screenshot saved to {:?}", path),
+                    Err(err) => error!("Failed to save the screenshot {:?}: {:?}", path, err),
+                }
+                Ok(())
+            },
+        )?;
+
+        let (hdr_path, hdr_pixels) = match (request.hdr_path, hdr_pixels) {
+            (Some(hdr_path), Some(hdr_pixels)) => (hdr_path, hdr_pixels),
+            (Some(hdr_path), None) => {
+                warn!(
+                    "The hdr target was not read back, {:?} won't be written",
+                    hdr_path
+                );
+                return Ok(());
+            }
+            (None, _) => return Ok(()),
+        };
+        let path = hdr_path.clone();
+        job_submit(
+            move || hdr_pixels.save_exr(&hdr_path),
+            move |result| {
+                match result {
+                    Ok(()) => info!("Hdr screenshot saved to {:?}", path),
+                    Err(err) => error!("Failed to save the hdr screenshot {:?}: {:?}", path, err),
+                }
+                Ok(())
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Draw the ui quads then the immediate shapes in the ui pass
+    fn draw_ui_overlay(&mut self) -> Result<(), EngineError> {
+        get_backend_mut(&mut self.backend)?.begin_ui_pass()?;
+        // The post processing passes are done once the ui pass begins
+        if let Some(camera) = self.get_render_camera() {
+            self.run_render_hooks(RenderStage::AfterPostProcessing, camera)?;
+            self.run_render_hooks(RenderStage::BeforeUi, camera)?;
+        }
+        let backend = get_backend_mut(&mut self.backend)?;
+
+        let default_texture = match self.texture_system.get_default_texture() {
+            Some(texture) => texture,
+            None => {
+                error!("The default texture is not set when drawing the ui overlay");
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        let batches: Vec<UiRenderData> = self
+            .ui_overlay
+            .get_batches()
+            .iter()
+            .map(|batch| UiRenderData {
+                // Released textures fall back to the default one
+                texture: batch
+                    .texture
+                    .or(self.ui_white_texture)
+                    .and_then(|handle| self.texture_system.get(handle))
+                    .unwrap_or(default_texture),
+                first_vertex: batch.first_vertex,
+                vertex_count: batch.vertex_count,
+            })
+            .collect();
+        let ui_camera = self.get_ui_camera()?;
+        let projection = ui_camera.projection * ui_camera.view;
+        let backend = get_backend_mut(&mut self.backend)?;
+        backend.draw_ui(projection, self.ui_overlay.get_vertices(), &batches)?;
+        backend.draw_immediate(projection, self.immediate_batch.get_vertices())
+    }
+
+    /// Hand the queued compute work to the backend, once the frame began
+    fn record_compute_commands(&mut self) -> Result<(), EngineError> {
+        let backend = get_backend_mut(&mut self.backend)?;
+        for command in std::mem::take(&mut self.compute_commands) {
+            match command {
+                ComputeCommand::WriteStorageBuffer {
+                    storage_buffer_id,
+                    offset,
+                    data,
+                } => backend.write_storage_buffer(storage_buffer_id, offset, &data)?,
+                ComputeCommand::Dispatch {
+                    shader_id,
+                    resources,
+                    group_count,
+                    push_constants,
+                } => {
+                    let mut bindings = Vec::new();
+                    for resource in resources {
+                        bindings.push(match resource {
+                            ComputeResource::StorageBuffer(id) => ComputeBinding::StorageBuffer(id),
+                            ComputeResource::StorageImage(handle) => {
+                                match self.texture_system.get(handle) {
+                                    Some(texture) => ComputeBinding::StorageImage(texture),
+                                    None => {
+                                        error!(
+                                            "Can't bind the unknown texture {:?} to a compute shader",
+                                            handle
+                                        );
+                                        return Err(EngineError::InvalidValue);
+                                    }
+                                }
+                            }
+                        });
+                    }
+                    backend.dispatch_compute(shader_id, &bindings, group_count, &push_constants)?
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Cull the scene seen from the camera and queue the visible objects, sorted for drawing
+    fn queue_scene(&mut self, camera: &Camera) -> Result<(), EngineError> {
+        let camera_position = camera.get_position();
+        for (world, renderable, fade) in self.cull_scene(camera)? {
+            let geometry_data = GeometryRenderData::default()
+                .model(world)
+                .geometry(
+                    self.geometries
+                        .get(&renderable.geometry_id)
+                        .map(|geometry| geometry.clone_box()),
+                )
+                .material(
+                    renderable
+                        .material_id
+                        .and_then(|id| self.get_material(id))
+                        .or(self.default_material.as_ref())
+                        .cloned(),
+                )
+                .bounding_sphere(renderable.bounding_sphere)
+                .object_id(Some(renderable.object_id))
+                .fade(fade)
+                .reflection_probe(self.get_closest_reflection_probe_index(
+                    match &renderable.bounding_sphere {
+                        Some(sphere) => world.transform_point3(sphere.center),
+                        None => world.w_axis.truncate(),
+                    },
+                ));
+            self.render_queue.push(geometry_data, camera_position);
+        }
+        self.render_queue.sort();
+        self.transparent_draw_call_count += self.render_queue.get_transparent_count() as u32;
+        Ok(())
+    }
+
+    /// Draw the queued opaque objects, the batches of several models are instanced
+    fn draw_opaque_queue(&mut self) -> Result<(), EngineError> {
+        for batch in self.render_queue.drain_opaque() {
+            let backend = get_backend_mut(&mut self.backend)?;
+            let result = if batch.models.len() > 1 {
+                self.instanced_object_count += batch.models.len() as u32;
+                backend.draw_instances(&batch.data, &batch.models, &self.texture_system)
+            } else {
+                backend.update_object(&batch.data, &self.texture_system)
+            };
+            if let Err(err) = result {
+                error!(
+                    "Failed to update the renderer backend object {}: {:?}",
+                    batch
+                        .data
+                        .object_id
+                        .map_or(String::from("without id"), |id| {
+                            self.object_names.describe(id)
+                        }),
+                    err
+                );
+                return Err(EngineError::Unknown);
+            }
+            self.draw_call_count += 1;
+        }
+        Ok(())
+    }
+
+    /// Draw the queued transparent objects, back to front
+    fn draw_transparent_queue(&mut self) -> Result<(), EngineError> {
+        for geometry_data in self.render_queue.drain_transparent() {
+            if let Err(err) = get_backend_mut(&mut self.backend)?
+                .update_object(&geometry_data, &self.texture_system)
+            {
+                error!(
+                    "Failed to update the renderer backend object {}: {:?}",
+                    geometry_data
+                        .object_id
+                        .map_or(String::from("without id"), |id| {
+                            self.object_names.describe(id)
+                        }),
+                    err
+                );
+                return Err(EngineError::Unknown);
+            }
+            self.draw_call_count += 1;
+        }
+        Ok(())
+    }
+
+    /// Give the backend the cameras drawn into textures or windows by the next frame
+    /// Returns their cameras in the order of their passes
+    fn add_camera_passes(&mut self) -> Result<Vec<Camera>, EngineError> {
+        let backend = get_backend_mut(&mut self.backend)?;
+        let mut cameras = Vec::new();
+        for named_camera in self.cameras.get_offscreen_cameras() {
+            let target = match named_camera.target {
+                CameraTarget::Texture(handle) => match self.texture_system.get(handle) {
+                    Some(texture) => CameraPassTarget::Texture(texture),
+                    None => {
+                        warn!(
+                            "The texture {:?} of a camera was released, the camera isn't drawn",
+                            handle
+                        );
+                        continue;
+                    }
+                },
+                CameraTarget::Window(window) if self.windows.contains_key(&window) => {
+                    CameraPassTarget::Window(window)
+                }
+                CameraTarget::Window(window) => {
+                    warn!(
+                        "The window {:?} of a camera was destroyed, the camera isn't drawn",
+                        window
+                    );
+                    continue;
+                }
+                CameraTarget::Screen => continue,
+            };
+            backend.add_camera_pass(target, named_camera.viewport)?;
+            cameras.push(named_camera.camera);
+        }
+        Ok(cameras)
+    }
+
+    /// Draw the scene from the cameras drawn into textures or windows, each in the pass the backend left open for it
+    fn draw_camera_passes(&mut self, cameras: &[Camera]) -> Result<(), EngineError> {
+        for camera in cameras {
+            get_backend_mut(&mut self.backend)?.update_global_state(
+                camera.projection,
+                camera.view,
+                camera.view.inverse().w_axis.truncate(),
+                &self.lighting,
+                0,
+            )?;
+            self.queue_scene(camera)?;
+            self.draw_opaque_queue()?;
+            self.draw_transparent_queue()?;
+            get_backend_mut(&mut self.backend)?.end_camera_pass()?;
+        }
         Ok(())
     }
 
+    /// Size of the framebuffer, of the texture or of the window the camera draws into
+    fn get_camera_target_size(&self, target: CameraTarget) -> Result<(u32, u32), EngineError> {
+        match target {
+            CameraTarget::Screen => get_backend(&self.backend)?.get_framebuffer_size(),
+            CameraTarget::Texture(handle) => match self.texture_system.get(handle) {
+                Some(texture) => Ok((texture.get_width(), texture.get_height())),
+                None => {
+                    error!("Unknown camera target texture {:?}", handle);
+                    Err(EngineError::InvalidValue)
+                }
+            },
+            CameraTarget::Window(window) => match self.windows.get(&window) {
+                Some(size) => Ok(*size),
+                None => {
+                    error!("Unknown camera target window {:?}", window);
+                    Err(EngineError::InvalidValue)
+                }
+            },
+        }
+    }
+
     pub(crate) fn draw_frame(&mut self, frame_data: &RenderFrameData) -> Result<(), EngineError> {
+        let offscreen_cameras = match self.add_camera_passes() {
+            Ok(cameras) => cameras,
+            Err(err) => {
+                error!(
+                    "Failed to add the passes of the cameras drawn into textures or windows: {:?}",
+                    err
+                );
+                return Err(EngineError::Unknown);
+            }
+        };
         // If the begin frame returned successfully, mid-frame operations may continue.
         match self.begin_frame(frame_data.delta_time) {
             Err(err) => {
@@ -189,14 +852,43 @@ impl RendererFrontend {
                 Err(EngineError::Unknown)
             }
             Ok(true) => {
+                if let Err(err) = self.record_compute_commands() {
+                    error!("Failed to record the compute work of the frame: {:?}", err);
+                    return Err(EngineError::UpdateFailed);
+                }
+                let aspect_ratio = get_backend(&self.backend)?.get_aspect_ratio()?;
+                self.ecs_camera = ecs_get_active_camera(aspect_ratio)?;
+                if let Err(err) = self.update_ecs_morph_weights() {
+                    error!("Failed to update the morph targets of the frame: {:?}", err);
+                    return Err(EngineError::UpdateFailed);
+                }
                 // TODO: temporary test code
                 {
-                    let camera = self.main_camera.unwrap();
-                    if let Err(err) = self.backend.as_mut().unwrap().update_global_state(
+                    if let Err(err) = self.debug_camera.update(frame_data.delta_time) {
+                        error!("Failed to update the debug camera: {:?}", err);
+                        return Err(EngineError::UpdateFailed);
+                    }
+                    self.draw_call_count = 0;
+                    self.transparent_draw_call_count = 0;
+                    self.instanced_object_count = 0;
+                    self.scene_graph.update_world_transforms();
+                    self.update_ecs_spatial_tree()?;
+                    self.ui_layout.update();
+                    // The backend began the frame with their passes, before the world
+                    if let Err(err) = self.draw_camera_passes(&offscreen_cameras) {
+                        error!(
+                            "Failed to draw the cameras into their textures or windows: {:?}",
+                            err
+                        );
+                        return Err(EngineError::Unknown);
+                    }
+
+                    let camera = self.get_render_camera().unwrap();
+                    if let Err(err) = get_backend_mut(&mut self.backend)?.update_global_state(
                         camera.projection,
                         camera.view,
-                        glam::Vec3::ZERO,
-                        glam::Vec4::ONE,
+                        camera.view.inverse().w_axis.truncate(),
+                        &self.lighting,
                         0,
                     ) {
                         error!(
@@ -205,31 +897,65 @@ impl RendererFrontend {
                         );
                         return Err(EngineError::Unknown);
                     }
+                    // The active named camera only draws in its viewport
+                    let world_viewport = match self.cameras.get_active() {
+                        Some(named_camera) if !self.debug_camera.is_active => named_camera.viewport,
+                        _ => None,
+                    };
+                    if let Err(err) =
+                        get_backend_mut(&mut self.backend)?.set_world_viewport(world_viewport)
+                    {
+                        error!("Failed to set the viewport of the world: {:?}", err);
+                        return Err(EngineError::Unknown);
+                    }
+                    if let Err(err) = self.update_auto_exposure(frame_data.delta_time) {
+                        error!("Failed to update the auto exposure: {:?}", err);
+                        return Err(EngineError::Unknown);
+                    }
+                    let mut post_process = self.post_process;
+                    if post_process.tonemap.use_auto_exposure {
+                        post_process.tonemap.exposure = self.auto_exposure.get_exposure();
+                    }
+                    if let Err(err) = get_backend_mut(&mut self.backend)?.update_post_process(
+                        &post_process,
+                        self.accessibility.get_color_filter_matrix(),
+                    ) {
+                        error!(
+                            "Failed to update the renderer backend post processing: {:?}",
+                            err
+                        );
+                        return Err(EngineError::Unknown);
+                    }
 
-                    // mat4 model = mat4_translation((vec3){0, 0, 0});
-                    // static mut ANGLE: f32 = 0.01;
-                    // unsafe { ANGLE += 0.001 };
-                    // let rotation =
-                    //     glam::Quat::from_axis_angle(glam::Vec3::new(0.0, 0.0, -1.0), unsafe {
-                    //         ANGLE
-                    //     });
-                    // let model = glam::Mat4::from_quat(rotation);
-                    let default_texture = self
-                        .default_texture
-                        .as_ref()
-                        .map(|texture| texture.clone_box());
-                    let geometry_data = GeometryRenderData::default()
-                        .model(glam::Mat4::IDENTITY)
-                        .texture(0, default_texture)
-                        .object_id(Some(0)) // TODO: actual object id
-                    ;
-                    if let Err(err) = self.backend.as_mut().unwrap().update_object(&geometry_data) {
-                        error!("Failed to update the renderer backend objects: {:?}", err);
+                    self.queue_scene(&camera)?;
+                    self.draw_opaque_queue()?;
+                    if let Err(err) = self.run_render_hooks(RenderStage::AfterOpaque, camera) {
+                        error!(
+                            "Failed to run the render hooks after the opaque objects: {:?}",
+                            err
+                        );
                         return Err(EngineError::Unknown);
                     }
+                    self.draw_transparent_queue()?;
                 }
                 // TODO: temporary test code
 
+                if cfg!(debug_assertions) {
+                    if let Err(err) = self.draw_debug_workspace() {
+                        error!("Failed to draw the debug workspace: {:?}", err);
+                        return Err(EngineError::Unknown);
+                    }
+                }
+
+                // The overlay is drawn last, on top of the scene
+                let result = self.draw_ui_overlay();
+                self.ui_overlay.clear();
+                self.immediate_batch.clear();
+                if let Err(err) = result {
+                    error!("Failed to draw the ui overlay: {:?}", err);
+                    return Err(EngineError::Unknown);
+                }
+
                 // End the frame. If this fails, it is likely unrecoverable
                 match self.end_frame(frame_data.delta_time) {
                     Err(err) => {
@@ -241,17 +967,25 @@ impl RendererFrontend {
             }
             Ok(false) => {
                 warn!("Could not begin the frame, skipping it");
+                self.ui_overlay.clear();
+                self.immediate_batch.clear();
                 Ok(())
             }
         }
     }
 
     pub(crate) fn resize(&mut self, width: u32, height: u32) -> Result<(), EngineError> {
-        if let Err(err) = self.backend.as_mut().unwrap().resize(width, height) {
+        if let Err(err) = get_backend_mut(&mut self.backend)?.resize(width, height) {
             error!("Failed to resize the renderer frontend: {:?}", err);
             return Err(EngineError::Unknown);
         }
-        let new_aspect_ratio = self.backend.as_ref().unwrap().get_aspect_ratio()?;
+        self.ui_layout.set_screen_size(width, height);
+        let new_aspect_ratio = get_backend(&self.backend)?.get_aspect_ratio()?;
+        self.debug_camera.update_aspect_ratio(new_aspect_ratio);
+        self.cameras.update_aspect_ratios(width, height);
+        if let Some(ui_camera) = self.ui_camera.as_mut() {
+            ui_camera.update_aspect_ratio(width as f32 / height.max(1) as f32);
+        }
         let camera: &mut Camera = match self.main_camera.as_mut() {
             None => return Ok(()),
             Some(camera) => camera,
@@ -260,164 +994,864 @@ impl RendererFrontend {
         Ok(())
     }
 
-    pub fn create_texture(
-        &self,
+    /// Returns the handle of the texture with the given name, creating it if it does not exist yet
+    pub fn acquire_texture(
+        &mut self,
         params: TextureCreatorParameters,
-    ) -> Result<Box<dyn Texture>, EngineError> {
-        self.backend.as_ref().unwrap().create_texture(params)
+    ) -> Result<TextureHandle, EngineError> {
+        self.texture_system
+            .acquire(get_backend_mut(&mut self.backend)?, params)
     }
 
-    pub fn load_texture(&self, path: &Path, name: &str) -> Result<Box<dyn Texture>, EngineError> {
-        // TODO: Better path handling
-        let image = match ImageReader::open(path) {
-            Ok(image) => image,
-            Err(err) => {
-                error!(
-                    "Failed to open the file: {:?}, when trying to load a texture: {:?}",
-                    path, err
-                );
-                return Err(EngineError::IO);
-            }
-        };
-        let image = match image.decode() {
-            Ok(image) => image,
-            Err(err) => {
-                error!(
-                    "Failed to decode the file: {:?}, when trying to load a texture: {:?}",
-                    path, err
-                );
-                return Err(EngineError::IO);
+    /// Returns the handle of the texture with the given name, loading the file if it does not exist yet
+    pub fn load_texture(
+        &mut self,
+        path: &Path,
+        name: &str,
+        auto_release: bool,
+    ) -> Result<TextureHandle, EngineError> {
+        self.texture_system.load(
+            get_backend_mut(&mut self.backend)?,
+            path,
+            name,
+            auto_release,
+        )
+    }
+
+    /// Give the texture new pixels without stalling the frames using the old ones
+    pub fn replace_texture(
+        &mut self,
+        handle: TextureHandle,
+        params: TextureCreatorParameters,
+    ) -> Result<(), EngineError> {
+        self.texture_system
+            .replace(get_backend_mut(&mut self.backend)?, handle, params)
+    }
+
+    pub fn release_texture(&mut self, handle: TextureHandle) -> Result<(), EngineError> {
+        self.texture_system
+            .release(get_backend_mut(&mut self.backend)?, handle)
+    }
+
+    /// Overwrite a sub-rectangle of the texture, used by procedural and dynamic textures
+    pub fn update_texture_region(
+        &self,
+        handle: TextureHandle,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Result<(), EngineError> {
+        let texture = match self.texture_system.get(handle) {
+            Some(texture) => texture,
+            None => {
+                error!("Can't update the unknown texture {:?}", handle);
+                return Err(EngineError::InvalidValue);
             }
         };
-        // TODO: handle different formats
-        let image = image.to_rgba8();
-        let mut has_transparency = false;
-        for pixel in image.pixels() {
-            if pixel[3] < 255 {
-                has_transparency = true; // Transparency found
-            }
-        }
+        get_backend(&self.backend)?.update_texture_region(texture, x, y, width, height, pixels)
+    }
 
-        let texture_parameters = TextureCreatorParameters {
-            name,
-            auto_release: true,
-            width: image.width(),
-            height: image.height(),
-            nb_channels: 4, // for now
-            pixels: image.as_raw(),
-            has_transparency,
-            is_default: self.default_texture.is_some()
-                && self
-                    .default_texture
-                    .as_ref()
-                    .unwrap()
-                    .get_generation()
-                    .is_some(),
+    /// Copy the texture back to the cpu, slow as it waits for the gpu
+    pub fn read_texture_pixels(&self, handle: TextureHandle) -> Result<Vec<u8>, EngineError> {
+        let texture = match self.texture_system.get(handle) {
+            Some(texture) => texture,
+            None => {
+                error!("Can't read the unknown texture {:?}", handle);
+                return Err(EngineError::InvalidValue);
+            }
         };
+        get_backend(&self.backend)?.read_texture_pixels(texture)
+    }
 
-        // Acquire internal texture resources and upload to GPU
-        let new_texture = match self.create_texture(texture_parameters) {
+    /// Load a .fnt file and its atlas, the first loaded font becomes the current one
+    pub fn load_font(&mut self, path: &Path) -> Result<u32, EngineError> {
+        let mut font = font_load_fnt(path)?;
+        let texture_name = format!("font atlas {:?}", font.atlas_path);
+        let texture = match self.load_texture(&font.atlas_path, &texture_name, true) {
             Ok(texture) => texture,
             Err(err) => {
-                error!(
-                    "Failed to create the backend texture when creating a frontend texture: {:?}",
-                    err
-                );
-                return Err(EngineError::InitializationFailed);
+                error!("Failed to load the atlas of the font {:?}: {:?}", path, err);
+                return Err(EngineError::IO);
             }
         };
-        Ok(new_texture)
+        font.texture = Some(texture);
+        let id = self.next_font_id;
+        self.next_font_id += 1;
+        self.fonts.insert(id, font);
+        if self.current_font.is_none() {
+            self.current_font = Some(id);
+        }
+        Ok(id)
     }
 
-    fn update_default_texture(&mut self, new_texture: Box<dyn Texture>) -> Result<(), EngineError> {
-        // Destroy Old texture
-        if let Some(texture) = &self.default_texture {
-            if let Err(err) = self
-                .backend
-                .as_ref()
-                .unwrap()
-                .destroy_texture(texture.as_ref())
-            {
-                error!("Failed to destroy the old texture when updating the renderer default's texture: {:?}", err);
-                return Err(EngineError::ShutdownFailed);
+    pub fn unload_font(&mut self, id: u32) -> Result<(), EngineError> {
+        let font = match self.fonts.remove(&id) {
+            Some(font) => font,
+            None => {
+                error!("Can't unload the unknown font {}", id);
+                return Err(EngineError::InvalidValue);
             }
+        };
+        if self.current_font == Some(id) {
+            self.current_font = self.fonts.keys().min().copied();
+        }
+        match font.texture {
+            Some(texture) => self.release_texture(texture),
+            None => Ok(()),
         }
-        self.default_texture = Some(new_texture);
-        Ok(())
     }
 
-    // TODO: temporary test code
-    pub fn swap_default_texture(&mut self) -> Result<(), EngineError> {
-        // Get the current working directory
-        let crate_dir = env!("CARGO_MANIFEST_DIR");
-        let paths: [PathBuf; 2] = [
-            Path::new(crate_dir).join("assets/textures/cobblestone.png"),
-            Path::new(crate_dir).join("assets/textures/paving.png"),
-        ];
-        let names = ["cobblestone", "paving"];
-
-        static mut CUR_CHOICE: usize = 0;
-        unsafe { CUR_CHOICE = (CUR_CHOICE + 1) % names.len() };
-
-        let new_texture =
-            match self.load_texture(&paths[unsafe { CUR_CHOICE }], names[unsafe { CUR_CHOICE }]) {
-                Ok(texture) => texture,
-                Err(err) => {
-                    error!(
-                        "Failed to load a new texture when swapping the default texture: {:?}",
-                        err
-                    );
-                    return Err(EngineError::InitializationFailed);
-                }
-            };
-
-        if let Err(err) = self.update_default_texture(new_texture) {
-            error!(
-                "Failed to update the default texture when swapping the default texture: {:?}",
-                err
-            );
-            return Err(EngineError::InitializationFailed);
-        };
-
+    pub fn set_current_font(&mut self, id: u32) -> Result<(), EngineError> {
+        if !self.fonts.contains_key(&id) {
+            error!("Can't use the unknown font {}", id);
+            return Err(EngineError::InvalidValue);
+        }
+        self.current_font = Some(id);
         Ok(())
     }
-    // TODO: end of temporary code
-}
-
-pub(crate) static mut GLOBAL_RENDERER: Lazy<Mutex<RendererFrontend>> = Lazy::new(Mutex::default);
 
-pub(crate) fn fetch_global_renderer(
-    error: EngineError,
-) -> Result<&'static mut RendererFrontend, EngineError> {
-    unsafe {
-        match GLOBAL_RENDERER.get_mut() {
-            Ok(renderer) => Ok(renderer),
-            Err(err) => {
-                error!("Failed to fetch the global renderer: {:?}", err);
-                Err(error)
+    fn get_current_font(&self) -> Result<&BitmapFont, EngineError> {
+        match self.current_font.and_then(|id| self.fonts.get(&id)) {
+            Some(font) => Ok(font),
+            None => {
+                error!("No font is loaded to draw text");
+                Err(EngineError::NotInitialized)
             }
         }
     }
-}
 
-/// Initiate the engine renderer
-pub(crate) fn renderer_init(
-    application_name: &str,
-    platform: &dyn Platform,
-) -> Result<(), EngineError> {
-    let global_renderer = fetch_global_renderer(EngineError::InitializationFailed)?;
-    match global_renderer.init(application_name, platform) {
-        Ok(()) => (),
-        Err(err) => {
-            error!("Failed to initialize the renderer: {:?}", err);
-            return Err(EngineError::InitializationFailed);
+    /// Queue the glyphs of the text in the ui overlay, the position is its top left corner
+    pub fn draw_text(
+        &mut self,
+        position: glam::Vec2,
+        text: &str,
+        color: glam::Vec4,
+    ) -> Result<(), EngineError> {
+        let font = self.get_current_font()?;
+        for quad in text_build_quads(font, position, text, color, self.ui_layout.get_scale()) {
+            self.ui_overlay.draw_quad(&quad);
         }
+        Ok(())
     }
-    Ok(())
-}
+
+    /// Size in screen pixels of the text drawn with the current font
+    pub fn measure_text(&self, text: &str) -> Result<glam::Vec2, EngineError> {
+        let font = self.get_current_font()?;
+        Ok(text_measure(font, text, self.ui_layout.get_scale()))
+    }
+
+    /// Text lines shown by a debug panel
+    fn get_debug_panel_lines(&self, kind: DebugPanelKind) -> Result<Vec<String>, EngineError> {
+        let mut lines = Vec::new();
+        match kind {
+            DebugPanelKind::RendererStats => {
+                let statistics = get_backend(&self.backend)?.get_statistics()?;
+                lines.push(format!("Frame: {}", statistics.frame_number));
+                lines.push(format!(
+                    "Samples per pixel: {}",
+                    get_backend(&self.backend)?.get_sample_count()?
+                ));
+                lines.push(format!("Draw calls: {}", self.draw_call_count));
+                lines.push(format!(
+                    "Transparent draw calls: {}",
+                    self.transparent_draw_call_count
+                ));
+                lines.push(format!(
+                    "Instanced objects: {}",
+                    self.instanced_object_count
+                ));
+                lines.push(format!(
+                    "Visible objects: {}/{}",
+                    statistics.visible_objects, statistics.tested_objects
+                ));
+                for (name, value) in [
+                    ("Vertices", statistics.input_assembly_vertices),
+                    ("Vertex invocations", statistics.vertex_shader_invocations),
+                    ("Clipped primitives", statistics.clipping_primitives),
+                    (
+                        "Fragment invocations",
+                        statistics.fragment_shader_invocations,
+                    ),
+                ] {
+                    if let Some(value) = value {
+                        lines.push(format!("{}: {}", name, value));
+                    }
+                }
+                lines.push(format!(
+                    "Point lights: {}",
+                    self.lighting.get_point_lights().len()
+                ));
+                lines.push(format!(
+                    "Exposure: {:.3}",
+                    self.auto_exposure.get_exposure()
+                ));
+            }
+            DebugPanelKind::LogConsole => {
+                lines = logger_get_recent_messages(usize::MAX)?;
+            }
+            DebugPanelKind::EntityInspector => {
+                lines.push(format!(
+                    "Scene nodes: {}",
+                    self.scene_graph.get_node_count()
+                ));
+                match self.debug_workspace.selected_node {
+                    None => lines.push(String::from("No node selected")),
+                    Some(node) => {
+                        let transform = self.scene_graph.get_local_transform(node)?;
+                        lines.push(format!("Node: {:?}", node));
+                        lines.push(format!("Parent: {:?}", self.scene_graph.get_parent(node)?));
+                        lines.push(format!(
+                            "Children: {:?}",
+                            self.scene_graph.get_children(node)?
+                        ));
+                        lines.push(format!("Position: {:.3}", transform.position));
+                        lines.push(format!("Rotation: {:.3}", transform.rotation));
+                        lines.push(format!("Scale: {:.3}", transform.scale));
+                        match self.scene_graph.get_renderable(node)? {
+                            None => lines.push(String::from("Renderable: none")),
+                            Some(renderable) => {
+                                lines.push(format!(
+                                    "Object: {}",
+                                    self.object_names.describe(renderable.object_id)
+                                ));
+                                lines.push(format!("Geometry: {}", renderable.geometry_id));
+                                lines.push(format!("Material: {:?}", renderable.material_id));
+                            }
+                        }
+                    }
+                }
+            }
+            DebugPanelKind::ResourceBrowser => {
+                let textures = self.texture_system.get_summaries();
+                lines.push(format!("Textures: {}", textures.len()));
+                for (name, reference_count) in textures {
+                    lines.push(format!("  {} ({})", name, reference_count));
+                }
+                let mut materials: Vec<(u32, &MaterialReference)> = self
+                    .materials
+                    .iter()
+                    .map(|(id, reference)| (*id, reference))
+                    .collect();
+                materials.sort_by_key(|(id, _)| *id);
+                lines.push(format!("Materials: {}", materials.len()));
+                for (id, reference) in materials {
+                    lines.push(format!(
+                        "  {} {} ({})",
+                        id, reference.material.name, reference.reference_count
+                    ));
+                }
+                lines.push(format!("Geometries: {}", self.geometries.len()));
+                let mut fonts: Vec<(&u32, &BitmapFont)> = self.fonts.iter().collect();
+                fonts.sort_by_key(|(id, _)| **id);
+                lines.push(format!("Fonts: {}", fonts.len()));
+                for (id, font) in fonts {
+                    lines.push(format!("  {} {}", id, font.name));
+                }
+            }
+        }
+        Ok(lines)
+    }
+
+    /// Queue the open debug panels in the ui overlay, does nothing without a font
+    fn draw_debug_workspace(&mut self) -> Result<(), EngineError> {
+        if !self.debug_workspace.is_visible {
+            return Ok(());
+        }
+        let line_height = match self.current_font.and_then(|id| self.fonts.get(&id)) {
+            Some(font) => font.line_height * self.ui_layout.get_scale(),
+            None => return Ok(()),
+        };
+        let padding = 0.25 * line_height;
+        let panel_color = glam::Vec4::new(0.05, 0.05, 0.05, 0.85);
+        let title_color = glam::Vec4::new(0.2, 0.25, 0.35, 0.95);
+        let text_color = glam::Vec4::ONE;
+
+        let rects = self
+            .debug_workspace
+            .compute_panel_rects(self.ui_layout.get_screen_size());
+        for (kind, rect) in rects {
+            self.ui_overlay
+                .draw_quad(&UiQuad::new(rect.position, rect.size).color(panel_color));
+            let title_size = glam::Vec2::new(rect.size.x, line_height + 2.0 * padding);
+            self.ui_overlay
+                .draw_quad(&UiQuad::new(rect.position, title_size).color(title_color));
+            self.draw_text(
+                rect.position + glam::Vec2::splat(padding),
+                kind.get_title(),
+                text_color,
+            )?;
+
+            // Only the lines fitting in the panel are drawn, the console shows the latest ones
+            let mut lines = self.get_debug_panel_lines(kind)?;
+            let content_height = rect.size.y - title_size.y - padding;
+            let max_line_count = (content_height / line_height).max(0.0) as usize;
+            if kind == DebugPanelKind::LogConsole {
+                let skipped = lines.len().saturating_sub(max_line_count);
+                lines.drain(..skipped);
+            } else {
+                lines.truncate(max_line_count);
+            }
+            let max_width = rect.size.x - 2.0 * padding;
+            for (index, mut line) in lines.into_iter().enumerate() {
+                while !line.is_empty() && self.measure_text(&line)?.x > max_width {
+                    line.pop();
+                }
+                let position = rect.position
+                    + glam::Vec2::new(padding, title_size.y + index as f32 * line_height);
+                self.draw_text(position, &line, text_color)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Upload a mesh to the gpu, returns the id used to draw it
+    pub fn create_geometry(
+        &mut self,
+        params: GeometryCreatorParameters,
+    ) -> Result<u32, EngineError> {
+        let geometry = get_backend_mut(&mut self.backend)?.create_geometry(params)?;
+        let id = geometry.get_id();
+        self.geometries.insert(id, geometry);
+        Ok(id)
+    }
+
+    pub fn destroy_geometry(&mut self, id: u32) -> Result<(), EngineError> {
+        let geometry = match self.geometries.remove(&id) {
+            Some(geometry) => geometry,
+            None => {
+                error!("Can't destroy the unknown geometry {}", id);
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        self.morph_geometries.remove(&id);
+        get_backend_mut(&mut self.backend)?.destroy_geometry(geometry.as_ref())
+    }
+
+    /// Blend the vertices of the geometry from the targets, the vertices are the unmorphed ones
+    pub fn set_geometry_morph_targets(
+        &mut self,
+        id: u32,
+        vertices: Vec<VertexData>,
+        targets: MorphTargetSet,
+    ) -> Result<(), EngineError> {
+        let geometry = match self.geometries.get(&id) {
+            Some(geometry) => geometry,
+            None => {
+                error!("Can't set the morph targets of the unknown geometry {}", id);
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        let morph_geometry = MorphGeometry::new(vertices, targets)?;
+        get_backend_mut(&mut self.backend)?
+            .update_geometry_vertices(geometry.as_ref(), &morph_geometry.vertices)?;
+        self.morph_geometries.insert(id, morph_geometry);
+        Ok(())
+    }
+
+    /// The vertices are blended and uploaded again only if the weights changed
+    pub fn set_morph_weights(&mut self, id: u32, weights: &[f32]) -> Result<(), EngineError> {
+        let (geometry, morph_geometry) =
+            match (self.geometries.get(&id), self.morph_geometries.get_mut(&id)) {
+                (Some(geometry), Some(morph_geometry)) => (geometry, morph_geometry),
+                _ => {
+                    error!("The geometry {} has no morph targets", id);
+                    return Err(EngineError::InvalidValue);
+                }
+            };
+        if morph_geometry.set_weights(weights)? {
+            get_backend_mut(&mut self.backend)?
+                .update_geometry_vertices(geometry.as_ref(), &morph_geometry.vertices)?;
+        }
+        Ok(())
+    }
+
+    pub fn get_morph_target_names(&self, id: u32) -> Result<Vec<String>, EngineError> {
+        match self.morph_geometries.get(&id) {
+            Some(morph_geometry) => Ok(morph_geometry
+                .targets
+                .targets
+                .iter()
+                .map(|target| target.name.clone())
+                .collect()),
+            None => {
+                error!("The geometry {} has no morph targets", id);
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    /// Apply the morph weights of the mesh renderer entities
+    fn update_ecs_morph_weights(&mut self) -> Result<(), EngineError> {
+        let ecs = fetch_global_ecs(EngineError::AccessFailed)?;
+        for (_, mesh_renderer) in ecs.world.query::<MeshRenderer>() {
+            if !mesh_renderer.morph_weights.is_empty() {
+                self.set_morph_weights(mesh_renderer.geometry_id, &mesh_renderer.morph_weights)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Adapt the exposure to the luminance histogram of the last downsample of the hdr target
+    /// The hdr target is only read back while the tonemapping uses the adapting exposure
+    fn update_auto_exposure(&mut self, delta_time: f64) -> Result<(), EngineError> {
+        let backend = get_backend_mut(&mut self.backend)?;
+        backend.set_luminance_readback_enabled(
+            self.post_process.tonemap.use_auto_exposure && self.auto_exposure.is_enabled,
+        )?;
+        if let Some(samples) = backend.take_luminance_samples()? {
+            let mut histogram = self.auto_exposure.create_histogram();
+            histogram.add_pixels(&samples, 1);
+            self.auto_exposure.update(&histogram, delta_time);
+        }
+        Ok(())
+    }
+
+    /// Feed the displayed frame of the visible sprites to the ui overlay
+    fn draw_ecs_sprites(&mut self) -> Result<(), EngineError> {
+        let ecs = fetch_global_ecs(EngineError::AccessFailed)?;
+        for (_, transform, sprite) in ecs.world.query_pair::<Transform, SpriteRenderer>() {
+            if !sprite.is_visible {
+                continue;
+            }
+            let rect = match sprite.animation.get_current_frame_rect() {
+                Some(rect) => rect,
+                None => continue,
+            };
+            let quad = UiQuad::new(
+                transform.position.truncate(),
+                sprite.size * transform.scale.truncate(),
+            )
+            .uv(rect.min, rect.max)
+            .color(sprite.color)
+            .texture(sprite.texture);
+            self.ui_overlay.draw_quad(&quad);
+        }
+        Ok(())
+    }
+
+    /// Returns the id of the material with the given name, creating it if it does not exist yet
+    pub fn acquire_material(
+        &mut self,
+        params: MaterialCreatorParameters,
+    ) -> Result<u32, EngineError> {
+        if let Some((id, reference)) = self
+            .materials
+            .iter_mut()
+            .find(|(_, reference)| reference.material.name == params.name)
+        {
+            reference.reference_count += 1;
+            return Ok(*id);
+        }
+        let id = self.next_material_id;
+        let material = Material {
+            id,
+            name: String::from(params.name),
+            generation: 0,
+            diffuse_color: params.diffuse_color,
+            diffuse_texture: params.diffuse_texture,
+            lightmap_texture: params.lightmap_texture,
+            normal_texture: params.normal_texture,
+            specular_texture: params.specular_texture,
+            specular_strength: params.specular_strength,
+            shininess: params.shininess,
+            render_state: params.render_state,
+        };
+        for texture in material.get_textures() {
+            self.texture_system.add_reference(texture)?;
+        }
+        self.next_material_id += 1;
+        self.materials.insert(
+            id,
+            MaterialReference {
+                material,
+                reference_count: 1,
+                auto_release: params.auto_release,
+            },
+        );
+        Ok(id)
+    }
+
+    /// Destroy the material once it is not referenced anymore, if it was created with auto release
+    pub fn release_material(&mut self, id: u32) -> Result<(), EngineError> {
+        let reference = match self.materials.get_mut(&id) {
+            Some(reference) => reference,
+            None => {
+                error!("Can't release the unknown material {}", id);
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        if reference.reference_count == 0 {
+            warn!(
+                "The material `{}' is released more times than it was acquired",
+                reference.material.name
+            );
+            return Ok(());
+        }
+        reference.reference_count -= 1;
+        if reference.reference_count == 0 && reference.auto_release {
+            let material = self.materials.remove(&id).unwrap().material;
+            for texture in material.get_textures() {
+                self.release_texture(texture)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Swap the texture referenced by a material
+    fn replace_material_texture(
+        &mut self,
+        old_texture: Option<TextureHandle>,
+        new_texture: Option<TextureHandle>,
+    ) -> Result<(), EngineError> {
+        if let Some(texture) = new_texture {
+            self.texture_system.add_reference(texture)?;
+        }
+        if let Some(texture) = old_texture {
+            self.release_texture(texture)?;
+        }
+        Ok(())
+    }
+
+    pub fn get_material(&self, id: u32) -> Option<&Material> {
+        self.materials.get(&id).map(|reference| &reference.material)
+    }
+
+    pub fn set_material_diffuse(
+        &mut self,
+        id: u32,
+        diffuse_color: glam::Vec4,
+        diffuse_texture: Option<TextureHandle>,
+    ) -> Result<(), EngineError> {
+        self.set_material_texture(id, MaterialTextureSlot::Diffuse, diffuse_texture)?;
+        let material = &mut self.materials.get_mut(&id).unwrap().material;
+        material.diffuse_color = diffuse_color;
+        Ok(())
+    }
+
+    /// The objects drawn with the material switch to the pipeline matching the new state
+    pub fn set_material_render_state(
+        &mut self,
+        id: u32,
+        render_state: MaterialRenderState,
+    ) -> Result<(), EngineError> {
+        let material = match self.materials.get_mut(&id) {
+            Some(reference) => &mut reference.material,
+            None => {
+                error!("Can't update the unknown material {}", id);
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        material.render_state = render_state;
+        material.generation = material.generation.wrapping_add(1);
+        Ok(())
+    }
+
+    /// The material holds a reference to the new texture and releases the old one
+    pub fn set_material_texture(
+        &mut self,
+        id: u32,
+        slot: MaterialTextureSlot,
+        texture: Option<TextureHandle>,
+    ) -> Result<(), EngineError> {
+        let old_texture = match self.materials.get(&id) {
+            Some(reference) => reference.material.get_texture(slot),
+            None => {
+                error!("Can't update the unknown material {}", id);
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        self.replace_material_texture(old_texture, texture)?;
+        let material = &mut self.materials.get_mut(&id).unwrap().material;
+        material.set_texture(slot, texture);
+        material.generation = material.generation.wrapping_add(1);
+        Ok(())
+    }
+
+    pub fn set_material_specular(
+        &mut self,
+        id: u32,
+        specular_strength: f32,
+        shininess: f32,
+        specular_texture: Option<TextureHandle>,
+    ) -> Result<(), EngineError> {
+        if specular_strength < 0.0 || shininess <= 0.0 {
+            error!(
+                "The specular strength must be positive and the shininess strictly positive, got {} and {}",
+                specular_strength, shininess
+            );
+            return Err(EngineError::InvalidValue);
+        }
+        self.set_material_texture(id, MaterialTextureSlot::Specular, specular_texture)?;
+        let material = &mut self.materials.get_mut(&id).unwrap().material;
+        material.specular_strength = specular_strength;
+        material.shininess = shininess;
+        Ok(())
+    }
+
+    /// Create the renderer resources of a gltf scene and add its nodes under the given parent
+    pub fn instantiate_gltf(
+        &mut self,
+        scene: &GltfScene,
+        parent: Option<SceneNodeId>,
+    ) -> Result<GltfInstance, EngineError> {
+        let root = self.scene_graph.create_node(parent)?;
+        let mut instance = GltfInstance {
+            root,
+            geometry_ids: Vec::new(),
+            material_ids: Vec::new(),
+            object_ids: Vec::new(),
+            textures: Vec::new(),
+        };
+        if let Err(err) = self.instantiate_gltf_resources(scene, &mut instance) {
+            error!("Failed to instantiate a gltf scene: {:?}", err);
+            self.unload_gltf(instance)?;
+            return Err(EngineError::InitializationFailed);
+        }
+        Ok(instance)
+    }
+
+    fn instantiate_gltf_resources(
+        &mut self,
+        scene: &GltfScene,
+        instance: &mut GltfInstance,
+    ) -> Result<(), EngineError> {
+        let mut textures = Vec::with_capacity(scene.images.len());
+        for (index, image) in scene.images.iter().enumerate() {
+            // Images of different files or instances must not be shared
+            let texture = self.acquire_texture(TextureCreatorParameters {
+                name: &format!("gltf {:?} image {}", instance.root, index),
+                auto_release: true,
+                width: image.width,
+                height: image.height,
+                nb_channels: 4,
+                pixels: &image.pixels,
+                has_transparency: image.has_transparency,
+                is_default: false,
+            })?;
+            instance.textures.push(texture);
+            textures.push(texture);
+        }
+
+        // One geometry per primitive, a mesh with several materials is split in several geometries
+        let mut meshes_geometries = Vec::with_capacity(scene.meshes.len());
+        for mesh in &scene.meshes {
+            let mut geometries = Vec::with_capacity(mesh.primitives.len());
+            for primitive in &mesh.primitives {
+                let geometry_id = self.create_geometry(GeometryCreatorParameters {
+                    name: &mesh.name,
+                    vertices: &primitive.vertices,
+                    indices: &primitive.indices,
+                })?;
+                instance.geometry_ids.push(geometry_id);
+                geometries.push(geometry_id);
+                if let Some(targets) = &primitive.morph_targets {
+                    self.set_geometry_morph_targets(
+                        geometry_id,
+                        primitive.vertices.clone(),
+                        targets.clone(),
+                    )?;
+                }
+            }
+            meshes_geometries.push(geometries);
+        }
+
+        let mut materials = Vec::with_capacity(scene.materials.len());
+        for (index, material) in scene.materials.iter().enumerate() {
+            // Materials of different files or instances must not be shared
+            let material_id = self.acquire_material(MaterialCreatorParameters {
+                name: &format!(
+                    "gltf {:?} material {} {}",
+                    instance.root, index, material.name
+                ),
+                auto_release: true,
+                diffuse_color: material.base_color,
+                diffuse_texture: material
+                    .base_color_image
+                    .and_then(|image| textures.get(image))
+                    .copied(),
+                lightmap_texture: None,
+                normal_texture: material
+                    .normal_image
+                    .and_then(|image| textures.get(image))
+                    .copied(),
+                specular_texture: None,
+                specular_strength: MATERIAL_DEFAULT_SPECULAR_STRENGTH,
+                shininess: MATERIAL_DEFAULT_SHININESS,
+                render_state: MaterialRenderState::default()
+                    .two_sided(material.is_double_sided)
+                    .blend_mode(if material.is_blended {
+                        BlendMode::AlphaBlend
+                    } else {
+                        BlendMode::Opaque
+                    }),
+            })?;
+            instance.material_ids.push(material_id);
+            materials.push(material_id);
+        }
+
+        let mut to_create: Vec<(usize, SceneNodeId)> = scene
+            .roots
+            .iter()
+            .map(|root| (*root, instance.root))
+            .collect();
+        while let Some((node_index, parent)) = to_create.pop() {
+            let node = match scene.nodes.get(node_index) {
+                Some(node) => node,
+                None => {
+                    error!("The gltf node {} does not exist", node_index);
+                    return Err(EngineError::InvalidValue);
+                }
+            };
+            let scene_node = self.scene_graph.create_node(Some(parent))?;
+            self.scene_graph
+                .set_local_transform(scene_node, node.transform)?;
+            if let Some(mesh_index) = node.mesh {
+                let mesh = &scene.meshes[mesh_index];
+                for (primitive, geometry_id) in
+                    mesh.primitives.iter().zip(&meshes_geometries[mesh_index])
+                {
+                    let object_id =
+                        get_backend_mut(&mut self.backend)?.acquire_object_resources()?;
+                    instance.object_ids.push(object_id);
+                    let primitive_node = self.scene_graph.create_node(Some(scene_node))?;
+                    self.object_names.set(
+                        object_id,
+                        &format!("{} {}", node.name, mesh.name),
+                        Some(primitive_node),
+                    );
+                    self.scene_graph.set_renderable(
+                        primitive_node,
+                        Some(SceneRenderable {
+                            object_id,
+                            geometry_id: *geometry_id,
+                            material_id: primitive
+                                .material
+                                .and_then(|material| materials.get(material))
+                                .copied(),
+                            bounding_sphere: Some(primitive.bounding_sphere),
+                        }),
+                    )?;
+                }
+            }
+            to_create.extend(node.children.iter().map(|child| (*child, scene_node)));
+        }
+        Ok(())
+    }
+
+    /// Shader resources of an object drawn by a scene node, named in the logs and the debug panels
+    pub fn acquire_object(
+        &mut self,
+        name: &str,
+        node: Option<SceneNodeId>,
+    ) -> Result<u32, EngineError> {
+        let object_id = match get_backend_mut(&mut self.backend)?.acquire_object_resources() {
+            Ok(object_id) => object_id,
+            Err(err) => {
+                error!("Failed to acquire the object {:?}: {:?}", name, err);
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        self.object_names.set(object_id, name, node);
+        Ok(object_id)
+    }
+
+    pub fn release_object(&mut self, object_id: u32) -> Result<(), EngineError> {
+        if let Err(err) = get_backend_mut(&mut self.backend)?.release_object_resources(object_id) {
+            error!(
+                "Failed to release the object {}: {:?}",
+                self.object_names.describe(object_id),
+                err
+            );
+            return Err(EngineError::ShutdownFailed);
+        }
+        self.object_names.remove(object_id);
+        Ok(())
+    }
+
+    /// Remove the nodes of a gltf scene and destroy its renderer resources
+    pub fn unload_gltf(&mut self, instance: GltfInstance) -> Result<(), EngineError> {
+        self.scene_graph.destroy_node(instance.root)?;
+        for object_id in instance.object_ids {
+            self.release_object(object_id)?;
+        }
+        for material_id in instance.material_ids {
+            self.release_material(material_id)?;
+        }
+        for geometry_id in instance.geometry_ids {
+            self.destroy_geometry(geometry_id)?;
+        }
+        for texture in instance.textures {
+            self.release_texture(texture)?;
+        }
+        Ok(())
+    }
+}
+
+pub(crate) static GLOBAL_RENDERER: EngineGlobal<RendererFrontend> = EngineGlobal::new("renderer");
+
+pub(crate) fn fetch_global_renderer(
+    error: EngineError,
+) -> Result<EngineGlobalGuard<RendererFrontend>, EngineError> {
+    GLOBAL_RENDERER.fetch(error)
+}
+
+/// The backend is missing before the renderer is initialized and once it is shut down
+/// Takes the field, the other fields of the frontend can be borrowed at the same time
+fn get_backend(
+    backend: &Option<Box<dyn RendererBackend>>,
+) -> Result<&dyn RendererBackend, EngineError> {
+    match backend.as_deref() {
+        Some(backend) => Ok(backend),
+        None => {
+            error!("The renderer backend is not initialized");
+            Err(EngineError::NotInitialized)
+        }
+    }
+}
+
+fn get_backend_mut(
+    backend: &mut Option<Box<dyn RendererBackend>>,
+) -> Result<&mut dyn RendererBackend, EngineError> {
+    match backend.as_deref_mut() {
+        Some(backend) => Ok(backend),
+        None => {
+            error!("The renderer backend is not initialized");
+            Err(EngineError::NotInitialized)
+        }
+    }
+}
+
+/// Initiate the engine renderer
+pub(crate) fn renderer_init(
+    backend_type: RendererBackendType,
+    application_name: &str,
+    platform: &dyn Platform,
+    anti_aliasing: AntiAliasing,
+    display_output: DisplayOutput,
+    depth_stencil: &DepthStencilSettings,
+) -> Result<(), EngineError> {
+    let mut global_renderer = fetch_global_renderer(EngineError::InitializationFailed)?;
+    match global_renderer.init(
+        backend_type,
+        application_name,
+        platform,
+        anti_aliasing,
+        display_output,
+        depth_stencil,
+    ) {
+        Ok(()) => (),
+        Err(err) => {
+            error!("Failed to initialize the renderer: {:?}", err);
+            return Err(EngineError::InitializationFailed);
+        }
+    }
+    Ok(())
+}
 
 pub(crate) fn renderer_draw_frame(frame_data: &RenderFrameData) -> Result<(), EngineError> {
-    let global_renderer = fetch_global_renderer(EngineError::InitializationFailed)?;
+    let mut global_renderer = fetch_global_renderer(EngineError::InitializationFailed)?;
     match global_renderer.draw_frame(frame_data) {
         Ok(()) => (),
         Err(err) => {
@@ -428,26 +1862,65 @@ pub(crate) fn renderer_draw_frame(frame_data: &RenderFrameData) -> Result<(), En
     Ok(())
 }
 
-/// Shutdown the engine renderer
+/// Give an additional window its own swapchain, the cameras can then target it
+pub(crate) fn renderer_add_window(
+    window: WindowHandle,
+    platform: &dyn Platform,
+    (width, height): (u32, u32),
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::InitializationFailed)?;
+    if let Err(err) =
+        get_backend_mut(&mut front_end.backend)?.create_window_surface(window, platform)
+    {
+        error!(
+            "Failed to create the renderer surface of the window {:?}: {:?}",
+            window, err
+        );
+        return Err(EngineError::InitializationFailed);
+    }
+    front_end.windows.insert(window, (width, height));
+    Ok(())
+}
+
+/// Destroy the swapchain of an additional window, the cameras targeting it aren't drawn anymore
+pub(crate) fn renderer_remove_window(window: WindowHandle) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::ShutdownFailed)?;
+    front_end.windows.remove(&window);
+    if let Err(err) = get_backend_mut(&mut front_end.backend)?.destroy_window_surface(window) {
+        error!(
+            "Failed to destroy the renderer surface of the window {:?}: {:?}",
+            window, err
+        );
+        return Err(EngineError::ShutdownFailed);
+    }
+    Ok(())
+}
+
+/// Shutdown the engine renderer, does nothing if it is already shut down
 pub(crate) fn renderer_shutdown() -> Result<(), EngineError> {
-    let global_renderer = fetch_global_renderer(EngineError::InitializationFailed)?;
-    match global_renderer.shutdown() {
-        Ok(()) => (),
-        Err(err) => {
-            error!("Failed to shutdown the renderer: {:?}", err);
-            return Err(EngineError::ShutdownFailed);
-        }
+    // The streamed cells release their renderer resources first
+    if let Err(err) = scene_streaming_stop() {
+        error!("Failed to unload the streamed scene cells: {:?}", err);
     }
-    unsafe {
-        // Empty GLOBAL_EVENTS
-        GLOBAL_RENDERER = Lazy::new(Mutex::default);
+    let mut global_renderer = fetch_global_renderer(EngineError::ShutdownFailed)?;
+    if global_renderer.backend.is_none() {
+        warn!("The renderer is already shut down");
+        return Ok(());
+    }
+    let result = global_renderer.shutdown();
+    drop(global_renderer);
+    // Empty GLOBAL_RENDERER, even after a partial shutdown the resources left can't be used
+    GLOBAL_RENDERER.reset(EngineError::ShutdownFailed)?;
+    if let Err(err) = result {
+        error!("Failed to shutdown the renderer: {:?}", err);
+        return Err(EngineError::ShutdownFailed);
     }
     Ok(())
 }
 
 // TODO: put it back to crate visibility
 pub fn renderer_set_main_camera(new_camera: &Camera) -> Result<(), EngineError> {
-    let front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
     front_end.set_main_camera(new_camera);
     Ok(())
 }
@@ -457,14 +1930,1066 @@ pub fn renderer_get_main_camera() -> Result<Camera, EngineError> {
     Ok(front_end.main_camera.unwrap())
 }
 
-pub fn renderer_get_default_texture() -> Result<&'static dyn Texture, EngineError> {
-    let front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
-    Ok(front_end.default_texture.as_ref().unwrap().as_ref())
+/// Switch the main camera between the perspective and the orthographic projections
+pub fn renderer_set_main_camera_projection(
+    projection_type: ProjectionType,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    match front_end.main_camera.as_mut() {
+        Some(camera) => camera.set_projection_type(projection_type),
+        None => {
+            error!("Can't change the projection without a main camera");
+            return Err(EngineError::NotInitialized);
+        }
+    }
+    Ok(())
 }
 
-// TODO: temporary code
-pub fn renderer_swap_default_texture() -> Result<(), EngineError> {
-    let front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
-    front_end.swap_default_texture()
+/// Height of the view of the main camera in world units when orthographic
+pub fn renderer_set_main_camera_orthographic_height(height: f32) -> Result<(), EngineError> {
+    if !height.is_finite() || height <= 0.0 {
+        error!(
+            "Invalid orthographic height: {}, it must be positive",
+            height
+        );
+        return Err(EngineError::InvalidValue);
+    }
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    match front_end.main_camera.as_mut() {
+        Some(camera) => camera.set_orthographic_height(height),
+        None => {
+            error!("Can't change the orthographic height without a main camera");
+            return Err(EngineError::NotInitialized);
+        }
+    }
+    Ok(())
+}
+
+/// Camera of the ui and the 2d shapes, none goes back to the screen pixels
+pub fn renderer_set_ui_camera(camera: Option<Camera>) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.ui_camera = camera;
+    Ok(())
+}
+
+pub fn renderer_get_ui_camera() -> Result<Camera, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    front_end.get_ui_camera()
+}
+
+/// Add a camera next to the main one, drawn on the screen by default
+/// Its aspect ratio is the viewport's if any, else the framebuffer's
+pub fn renderer_add_camera(
+    name: &str,
+    parameters: CameraCreatorParameters,
+    viewport: Option<CameraViewport>,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    let (width, height) = get_backend(&front_end.backend)?.get_framebuffer_size()?;
+    let area = viewport.unwrap_or(CameraViewport::new(width as f32, height as f32));
+    let named_camera = NamedCamera {
+        camera: Camera::new(parameters, area.width / area.height.max(1.0)),
+        viewport,
+        target: CameraTarget::Screen,
+    };
+    front_end.cameras.add(name, named_camera)
+}
+
+pub fn renderer_remove_camera(name: &str) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.cameras.remove(name)?;
+    Ok(())
+}
+
+pub fn renderer_get_camera(name: &str) -> Result<NamedCamera, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    Ok(*front_end.cameras.get(name)?)
+}
+
+/// Names of the cameras added next to the main one, sorted
+pub fn renderer_get_camera_names() -> Result<Vec<String>, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    Ok(front_end.cameras.get_names())
+}
+
+pub fn renderer_set_camera_projection(
+    name: &str,
+    projection_type: ProjectionType,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end
+        .cameras
+        .get_mut(name)?
+        .camera
+        .set_projection_type(projection_type);
+    Ok(())
+}
+
+/// Move a named camera, like `renderer_set_main_camera` only its view is used
+pub fn renderer_set_camera_view(name: &str, view: glam::Mat4) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.cameras.get_mut(name)?.camera.set_view(view);
+    Ok(())
+}
+
+/// None covers the whole target again
+pub fn renderer_set_camera_viewport(
+    name: &str,
+    viewport: Option<CameraViewport>,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    let target = front_end.cameras.get(name)?.target;
+    let (width, height) = front_end.get_camera_target_size(target)?;
+    let named_camera = front_end.cameras.get_mut(name)?;
+    named_camera.viewport = viewport;
+    let area = named_camera.get_viewport(width, height);
+    named_camera
+        .camera
+        .update_aspect_ratio(area.width / area.height.max(1.0));
+    Ok(())
+}
+
+/// Draw the camera into a texture instead of the screen, the texture can be used by materials
+/// Or into an additional window, e.g. for the viewport of an editor
+/// The target is drawn every frame before the world, its aspect ratio becomes the camera's
+pub fn renderer_set_camera_target(name: &str, target: CameraTarget) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    let (width, height) = match front_end.get_camera_target_size(target) {
+        Ok(size) => size,
+        Err(err) => {
+            error!(
+                "Can't draw the camera {:?} into the target {:?}: {:?}",
+                name, target, err
+            );
+            return Err(EngineError::InvalidValue);
+        }
+    };
+    if target != CameraTarget::Screen && front_end.cameras.is_active(name) {
+        // The frame is rendered from the main camera again
+        front_end.cameras.set_active(None)?;
+    }
+    let named_camera = front_end.cameras.get_mut(name)?;
+    named_camera.target = target;
+    let area = named_camera.get_viewport(width, height);
+    named_camera
+        .camera
+        .update_aspect_ratio(area.width / area.height.max(1.0));
+    Ok(())
+}
+
+/// Render the frame from a named camera, none goes back to the main camera
+pub fn renderer_set_active_camera(name: Option<&str>) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.cameras.set_active(name)
+}
+
+/// Ray of a named camera going through the given pixel of its viewport, y goes down
+pub fn renderer_camera_screen_to_ray(name: &str, x: f32, y: f32) -> Result<Ray, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    let named_camera = front_end.cameras.get(name)?;
+    let (width, height) = front_end.get_camera_target_size(named_camera.target)?;
+    let viewport = named_camera.get_viewport(width, height);
+    Ok(named_camera.camera.screen_to_ray(x, y, &viewport))
+}
+
+/// Filled rectangle drawn on top of the next frame, in screen pixels from the top left corner
+pub fn renderer_draw_rect(
+    position: glam::Vec2,
+    size: glam::Vec2,
+    color: glam::Vec4,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.immediate_batch.draw_rect(position, size, color);
+    Ok(())
+}
+
+/// Filled circle drawn on top of the next frame, in screen pixels from the top left corner
+pub fn renderer_draw_circle(
+    center: glam::Vec2,
+    radius: f32,
+    color: glam::Vec4,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.immediate_batch.draw_circle(center, radius, color);
+    Ok(())
+}
+
+/// Line drawn on top of the next frame, in screen pixels from the top left corner
+pub fn renderer_draw_line(
+    start: glam::Vec2,
+    end: glam::Vec2,
+    thickness: f32,
+    color: glam::Vec4,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end
+        .immediate_batch
+        .draw_line(start, end, thickness, color);
+    Ok(())
+}
+
+/// Draw the sprites of the ecs, before the ui of the game so it stays on top
+pub(crate) fn renderer_draw_ecs_sprites() -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.draw_ecs_sprites()
+}
+
+/// Textured quad drawn on top of the next frame, the quads are drawn in submission order
+pub fn renderer_ui_draw_quad(quad: &UiQuad) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.ui_overlay.draw_quad(quad);
+    Ok(())
+}
+
+/// 9-slice panel drawn on top of the next frame, plain colored without texture
+pub fn renderer_ui_draw_nine_slice(
+    panel: &NineSlicePanel,
+    texture: Option<TextureHandle>,
+    color: glam::Vec4,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    if let Err(err) = front_end.ui_overlay.draw_nine_slice(panel, texture, color) {
+        error!(
+            "Failed to draw a 9-slice panel in the ui overlay: {:?}",
+            err
+        );
+        return Err(EngineError::InvalidValue);
+    }
+    Ok(())
+}
+
+/// Load a font from a .fnt file, the atlas image is searched next to it
+pub fn renderer_load_font(path: &Path) -> Result<u32, EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::InitializationFailed)?;
+    front_end.load_font(path)
+}
+
+pub fn renderer_unload_font(id: u32) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::ShutdownFailed)?;
+    front_end.unload_font(id)
+}
+
+/// Font used by the next texts drawn
+pub fn renderer_set_font(id: u32) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.set_current_font(id)
+}
+
+/// None until a font is loaded
+pub fn renderer_get_font() -> Result<Option<u32>, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    Ok(front_end.current_font)
+}
+
+/// Text drawn on top of the next frame with the current font, from the top left corner of its
+/// first line in screen pixels, the text is scaled with the ui
+pub fn renderer_draw_text(
+    x: f32,
+    y: f32,
+    text: &str,
+    color: glam::Vec4,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.draw_text(glam::Vec2::new(x, y), text, color)
+}
+
+pub fn renderer_measure_text(text: &str) -> Result<glam::Vec2, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    front_end.measure_text(text)
+}
+
+/// Switch between the main camera and the free flying debug camera
+pub fn renderer_toggle_debug_camera() -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    if front_end.debug_camera.is_active {
+        front_end.debug_camera.deactivate();
+    } else {
+        let camera = match front_end.main_camera {
+            Some(camera) => camera,
+            None => {
+                error!("Can't activate the debug camera without a main camera");
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        front_end.debug_camera.activate(&camera);
+    }
+    Ok(())
+}
+
+pub fn renderer_is_debug_camera_active() -> Result<bool, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    Ok(front_end.debug_camera.is_active)
+}
+
+/// Show or hide the debug panels, they are only drawn in debug builds
+pub fn renderer_toggle_debug_workspace() -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.debug_workspace.is_visible = !front_end.debug_workspace.is_visible;
+    Ok(())
+}
+
+pub fn renderer_is_debug_workspace_visible() -> Result<bool, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    Ok(front_end.debug_workspace.is_visible)
+}
+
+pub fn renderer_debug_workspace_dock_panel(
+    kind: DebugPanelKind,
+    dock_area: DebugDockArea,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.debug_workspace.dock_panel(kind, dock_area);
+    Ok(())
+}
+
+pub fn renderer_debug_workspace_set_panel_open(
+    kind: DebugPanelKind,
+    is_open: bool,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.debug_workspace.set_panel_open(kind, is_open);
+    Ok(())
+}
+
+/// Node shown by the entity inspector panel
+pub fn renderer_debug_workspace_select_node(node: Option<SceneNodeId>) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.debug_workspace.selected_node = node;
+    Ok(())
+}
+
+/// Position of the camera the frame is rendered from, none without any camera
+pub(crate) fn renderer_get_render_camera_position() -> Result<Option<glam::Vec3>, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    Ok(front_end
+        .get_render_camera()
+        .map(|camera| camera.get_position()))
+}
+
+/// Ray of the main camera going through the given pixel of the framebuffer, y goes down
+pub fn renderer_screen_to_ray(x: f32, y: f32) -> Result<Ray, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    let (width, height) = get_backend(&front_end.backend)?.get_framebuffer_size()?;
+    let viewport = CameraViewport::new(width as f32, height as f32);
+    Ok(front_end
+        .main_camera
+        .unwrap()
+        .screen_to_ray(x, y, &viewport))
+}
+
+/// Closest scene node or entity hit by the ray, tested against the bounds of the last drawn frame
+pub fn renderer_raycast(
+    ray: &Ray,
+    max_distance: f32,
+) -> Result<Option<(PickedObject, f32)>, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    Ok(front_end.raycast(ray, max_distance))
+}
+
+/// Closest scene node or entity under the given pixel of the framebuffer, y goes down
+/// The ray starts from the camera the frame is rendered from
+pub fn renderer_pick(x: f32, y: f32) -> Result<Option<PickedObject>, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    let camera = match front_end.get_render_camera() {
+        Some(camera) => camera,
+        None => {
+            error!("Can't pick without a camera");
+            return Err(EngineError::AccessFailed);
+        }
+    };
+    let (width, height) = get_backend(&front_end.backend)?.get_framebuffer_size()?;
+    let viewport = CameraViewport::new(width as f32, height as f32);
+    let ray = camera.screen_to_ray(x, y, &viewport);
+    Ok(front_end
+        .raycast(&ray, f32::INFINITY)
+        .map(|(object, _)| object))
+}
+
+pub fn renderer_acquire_texture(
+    params: TextureCreatorParameters,
+) -> Result<TextureHandle, EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::InitializationFailed)?;
+    front_end.acquire_texture(params)
+}
+
+/// Returns none if there is no texture with the given name
+pub fn renderer_acquire_texture_by_name(name: &str) -> Result<Option<TextureHandle>, EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    Ok(front_end.texture_system.acquire_by_name(name))
+}
+
+pub fn renderer_load_texture(
+    path: &Path,
+    name: &str,
+    auto_release: bool,
+) -> Result<TextureHandle, EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::InitializationFailed)?;
+    front_end.load_texture(path, name, auto_release)
+}
+
+/// Decode the image on a worker thread and create the texture on the main thread
+/// The callback gets the texture once it can be used, or the error of the loading
+pub fn renderer_load_texture_async<C>(
+    path: PathBuf,
+    name: String,
+    auto_release: bool,
+    on_loaded: C,
+) -> Result<JobHandle, EngineError>
+where
+    C: FnOnce(Result<TextureHandle, EngineError>) -> Result<(), EngineError> + Send + 'static,
+{
+    job_submit(
+        move || texture_load_image(&path),
+        move |result| {
+            let texture = result.and_then(|(image, has_transparency)| {
+                renderer_acquire_texture(TextureCreatorParameters {
+                    name: &name,
+                    auto_release,
+                    width: image.width(),
+                    height: image.height(),
+                    nb_channels: 4, // for now
+                    pixels: image.as_raw(),
+                    has_transparency,
+                    is_default: false,
+                })
+            });
+            on_loaded(texture)
+        },
+    )
+}
+
+/// Give the texture new rgba8 pixels, the materials using the handle show them from the next frame
+/// The old pixels are destroyed once the frames in flight are done with them
+pub fn renderer_replace_texture(
+    handle: TextureHandle,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+    has_transparency: bool,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.replace_texture(
+        handle,
+        TextureCreatorParameters {
+            name: "",
+            auto_release: false,
+            width,
+            height,
+            nb_channels: 4, // for now
+            pixels,
+            has_transparency,
+            is_default: false,
+        },
+    )
+}
+
+/// Decode the image on a worker thread and replace the pixels of the texture on the main thread
+/// The callback gets the result of the replacement
+pub fn renderer_replace_texture_async<C>(
+    handle: TextureHandle,
+    path: PathBuf,
+    on_replaced: C,
+) -> Result<JobHandle, EngineError>
+where
+    C: FnOnce(Result<(), EngineError>) -> Result<(), EngineError> + Send + 'static,
+{
+    job_submit(
+        move || texture_load_image(&path),
+        move |result| {
+            let result = result.and_then(|(image, has_transparency)| {
+                renderer_replace_texture(
+                    handle,
+                    image.width(),
+                    image.height(),
+                    image.as_raw(),
+                    has_transparency,
+                )
+            });
+            on_replaced(result)
+        },
+    )
+}
+
+pub fn renderer_release_texture(handle: TextureHandle) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::ShutdownFailed)?;
+    front_end.release_texture(handle)
+}
+
+/// Overwrite a sub-rectangle of the texture with tightly packed rows of pixels
+pub fn renderer_texture_update_region(
+    handle: TextureHandle,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+    pixels: &[u8],
+) -> Result<(), EngineError> {
+    let front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.update_texture_region(handle, x, y, width, height, pixels)
+}
+
+/// Copy the texture back to the cpu as tightly packed rgba8 rows, waits for the gpu
+pub fn renderer_texture_read_pixels(handle: TextureHandle) -> Result<Vec<u8>, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    front_end.read_texture_pixels(handle)
+}
+
+/// Load a compute shader from the assets/shaders/ folder, returns the id used to dispatch it
+/// Its source is compiled when present, it can only use storage buffers and images in the set 0
+pub fn renderer_create_compute_shader(path: &str) -> Result<u32, EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    get_backend_mut(&mut front_end.backend)?.create_compute_shader(path)
+}
+
+/// Waits for the gpu, the queued dispatches of the shader are dropped
+pub fn renderer_destroy_compute_shader(shader_id: u32) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.compute_commands.retain(|command| {
+        !matches!(command, ComputeCommand::Dispatch { shader_id: id, .. } if *id == shader_id)
+    });
+    get_backend_mut(&mut front_end.backend)?.destroy_compute_shader(shader_id)
+}
+
+/// Returns the id of a gpu buffer for the compute shaders, its content is undefined until written
+pub fn renderer_create_storage_buffer(size: usize) -> Result<u32, EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    get_backend_mut(&mut front_end.backend)?.create_storage_buffer(size)
+}
+
+/// Waits for the gpu, the queued writes and dispatches using the buffer are dropped
+pub fn renderer_destroy_storage_buffer(storage_buffer_id: u32) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.compute_commands.retain(|command| match command {
+        ComputeCommand::WriteStorageBuffer {
+            storage_buffer_id: id,
+            ..
+        } => *id != storage_buffer_id,
+        ComputeCommand::Dispatch { resources, .. } => {
+            !resources.contains(&ComputeResource::StorageBuffer(storage_buffer_id))
+        }
+    });
+    get_backend_mut(&mut front_end.backend)?.destroy_storage_buffer(storage_buffer_id)
+}
+
+/// Overwrite a range of the storage buffer before the dispatches queued after it
+/// The offset and the size of the data must be multiples of 4
+pub fn renderer_write_storage_buffer(
+    storage_buffer_id: u32,
+    offset: u64,
+    data: &[u8],
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end
+        .compute_commands
+        .push(ComputeCommand::WriteStorageBuffer {
+            storage_buffer_id,
+            offset,
+            data: data.to_vec(),
+        });
+    Ok(())
+}
+
+/// Copy the storage buffer back to the cpu, slow as it waits for the gpu
+/// The work queued for the next frame isn't done yet
+pub fn renderer_read_storage_buffer(storage_buffer_id: u32) -> Result<Vec<u8>, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    get_backend(&front_end.backend)?.read_storage_buffer(storage_buffer_id)
+}
+
+/// Run the compute shader on the compute queue before the next frame is drawn
+/// The resources are bound in order from the binding 0, the push constants are copied as is
+pub fn renderer_dispatch_compute(
+    shader_id: u32,
+    resources: &[ComputeResource],
+    group_count: glam::UVec3,
+    push_constants: &[u8],
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.compute_commands.push(ComputeCommand::Dispatch {
+        shader_id,
+        resources: resources.to_vec(),
+        group_count,
+        push_constants: push_constants.to_vec(),
+    });
+    Ok(())
+}
+
+pub fn renderer_get_statistics() -> Result<RendererStatistics, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    get_backend(&front_end.backend)?.get_statistics()
+}
+
+pub fn renderer_get_draw_call_count() -> Result<u32, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    Ok(front_end.draw_call_count)
+}
+
+/// Number of opaque objects drawn in a batch with others sharing their geometry and material
+/// during the last frame
+pub fn renderer_get_instanced_object_count() -> Result<u32, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    Ok(front_end.instanced_object_count)
+}
+
+/// The opaque objects sharing a geometry and a material are drawn in a single call when enabled
+/// Their occlusion isn't queried
+pub fn renderer_set_instancing_enabled(is_enabled: bool) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.render_queue.is_instancing_enabled = is_enabled;
+    Ok(())
+}
+
+/// Number of objects drawn back to front after the opaque ones during the last frame
+pub fn renderer_get_transparent_draw_call_count() -> Result<u32, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    Ok(front_end.transparent_draw_call_count)
+}
+
+pub fn renderer_set_small_object_culling(
+    parameters: SmallObjectCullingParameters,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.small_object_culling = parameters;
+    Ok(())
+}
+
+pub fn renderer_create_geometry(params: GeometryCreatorParameters) -> Result<u32, EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::InitializationFailed)?;
+    front_end.create_geometry(params)
+}
+
+pub fn renderer_destroy_geometry(id: u32) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::ShutdownFailed)?;
+    front_end.destroy_geometry(id)
+}
+
+/// The vertices are the unmorphed ones, the gltf meshes get the targets of their file
+pub fn renderer_set_geometry_morph_targets(
+    id: u32,
+    vertices: Vec<VertexData>,
+    targets: MorphTargetSet,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.set_geometry_morph_targets(id, vertices, targets)
+}
+
+/// One weight per target, see `MeshRenderer::morph_weights` for the entities
+pub fn renderer_set_morph_weights(id: u32, weights: &[f32]) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.set_morph_weights(id, weights)
+}
+
+/// Names of the targets in the order of the weights, the gltf targets are named after their index
+pub fn renderer_get_morph_target_names(id: u32) -> Result<Vec<String>, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    front_end.get_morph_target_names(id)
+}
+
+pub fn renderer_scene_create_node(parent: Option<SceneNodeId>) -> Result<SceneNodeId, EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.scene_graph.create_node(parent)
+}
+
+pub fn renderer_scene_destroy_node(node: SceneNodeId) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.scene_graph.destroy_node(node)
+}
+
+pub fn renderer_scene_set_parent(
+    node: SceneNodeId,
+    parent: Option<SceneNodeId>,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.scene_graph.set_parent(node, parent)
+}
+
+pub fn renderer_scene_set_transform(
+    node: SceneNodeId,
+    transform: SceneTransform,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.scene_graph.set_local_transform(node, transform)
+}
+
+pub fn renderer_scene_get_transform(node: SceneNodeId) -> Result<SceneTransform, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    front_end.scene_graph.get_local_transform(node)
+}
+
+/// World transform as of the last drawn frame
+pub fn renderer_scene_get_world_transform(node: SceneNodeId) -> Result<glam::Mat4, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    front_end.scene_graph.get_world_transform(node)
+}
+
+pub fn renderer_scene_set_renderable(
+    node: SceneNodeId,
+    renderable: Option<SceneRenderable>,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.scene_graph.set_renderable(node, renderable)
+}
+
+pub fn renderer_ui_create_element(
+    parent: Option<UiElementId>,
+    parameters: UiElementParameters,
+) -> Result<UiElementId, EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.ui_layout.create_element(parent, parameters)
+}
+
+pub fn renderer_ui_destroy_element(element: UiElementId) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.ui_layout.destroy_element(element)
+}
+
+pub fn renderer_ui_set_element_parameters(
+    element: UiElementId,
+    parameters: UiElementParameters,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end
+        .ui_layout
+        .set_element_parameters(element, parameters)
+}
+
+/// Screen rectangle of the element, reflowed at the start of every frame
+pub fn renderer_ui_get_element_rect(element: UiElementId) -> Result<UiRect, EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    front_end.ui_layout.update();
+    front_end.ui_layout.get_element_rect(element)
+}
+
+pub fn renderer_get_accessibility_settings() -> Result<AccessibilitySettings, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    Ok(front_end.accessibility)
+}
+
+/// Takes effect immediately, the swapchain is recreated
+pub fn renderer_set_vsync_mode(mode: VsyncMode) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    get_backend_mut(&mut front_end.backend)?.set_vsync_mode(mode)
+}
+
+/// Fraction of the window size the world is drawn at before being upscaled, the ui keeps the full resolution
+/// Takes effect from the next frame, one draws at full resolution
+pub fn renderer_set_render_scale(scale: f32) -> Result<(), EngineError> {
+    if !(scale > 0.0 && scale <= 1.0) {
+        error!(
+            "The render scale must be more than 0 and at most 1, got {}",
+            scale
+        );
+        return Err(EngineError::InvalidValue);
+    }
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    get_backend_mut(&mut front_end.backend)?.set_render_scale(scale)
+}
+
+/// The display output is chosen at launch, srgb when the hdr output was not requested or not supported
+pub fn renderer_get_output_color_space() -> Result<OutputColorSpace, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    get_backend(&front_end.backend)?.get_output_color_space()
+}
+
+/// Also set by the `color_filter', `color_filter_type' and `ui_scale' settings of the config and the console
+pub fn renderer_set_accessibility_settings(
+    settings: AccessibilitySettings,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    if settings.ui_scale <= 0.0 {
+        error!("The ui scale must be positive, got {}", settings.ui_scale);
+        return Err(EngineError::InvalidValue);
+    }
+    front_end.accessibility = settings;
+    front_end.ui_layout.set_scale(settings.ui_scale);
+    Ok(())
+}
+
+/// White keeps the objects unlit, it should be darkened once lights are added
+pub fn renderer_set_ambient_light(color: glam::Vec4) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.lighting.ambient_color = color;
+    Ok(())
+}
+
+/// None removes the directional light
+pub fn renderer_set_directional_light(light: Option<DirectionalLight>) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.lighting.directional_light = light;
+    Ok(())
+}
+
+pub fn renderer_get_directional_light() -> Result<Option<DirectionalLight>, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    Ok(front_end.lighting.directional_light)
+}
+
+/// Returns the id of the light, fails once LIGHTING_MAX_POINT_LIGHTS lights are added
+pub fn renderer_add_point_light(light: PointLight) -> Result<u32, EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.lighting.add_point_light(light)
+}
+
+pub fn renderer_update_point_light(id: u32, light: PointLight) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.lighting.update_point_light(id, light)
+}
+
+pub fn renderer_remove_point_light(id: u32) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.lighting.remove_point_light(id)
+}
+
+pub fn renderer_get_point_light(id: u32) -> Result<Option<PointLight>, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    Ok(front_end.lighting.get_point_light(id).copied())
+}
+
+/// Scale applied to the hdr color before the tonemapping
+pub fn renderer_get_exposure() -> Result<f32, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    Ok(front_end.auto_exposure.get_exposure())
+}
+
+pub fn renderer_get_auto_exposure() -> Result<AutoExposure, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    Ok(front_end.auto_exposure)
+}
+
+pub fn renderer_set_auto_exposure_parameters(
+    parameters: AutoExposureParameters,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    if !parameters.min_ev.is_finite() || !parameters.max_ev.is_finite() {
+        error!(
+            "Invalid auto exposure limits {} and {}, they must be finite",
+            parameters.min_ev, parameters.max_ev
+        );
+        return Err(EngineError::InvalidValue);
+    }
+    if parameters.min_ev > parameters.max_ev {
+        error!(
+            "The minimum exposure value {} is above the maximum one {}",
+            parameters.min_ev, parameters.max_ev
+        );
+        return Err(EngineError::InvalidValue);
+    }
+    if !(0.0..=1.0).contains(&parameters.low_percentile)
+        || !(parameters.low_percentile..=1.0).contains(&parameters.high_percentile)
+    {
+        error!(
+            "Invalid auto exposure percentiles {} and {}",
+            parameters.low_percentile, parameters.high_percentile
+        );
+        return Err(EngineError::InvalidValue);
+    }
+    let auto_exposure = &mut front_end.auto_exposure;
+    auto_exposure.parameters = parameters;
+    auto_exposure.ev = auto_exposure.ev.clamp(parameters.min_ev, parameters.max_ev);
+    Ok(())
+}
+
+/// Freeze the exposure at its current value, or let it adapt again
+pub fn renderer_set_auto_exposure_enabled(is_enabled: bool) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.auto_exposure.is_enabled = is_enabled;
+    Ok(())
+}
+
+pub fn renderer_get_post_process_settings() -> Result<PostProcessSettings, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    Ok(front_end.post_process)
+}
+
+/// Takes effect from the next frame, the disabled passes are skipped
+pub fn renderer_set_post_process_settings(
+    settings: PostProcessSettings,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    if let Some(parameter) = settings.validate() {
+        error!(
+            "Invalid post processing settings, the {} is out of range",
+            parameter
+        );
+        return Err(EngineError::InvalidValue);
+    }
+    front_end.post_process = settings;
+    Ok(())
+}
+
+pub fn renderer_acquire_material(params: MaterialCreatorParameters) -> Result<u32, EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::InitializationFailed)?;
+    front_end.acquire_material(params)
+}
+
+pub fn renderer_release_material(id: u32) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::ShutdownFailed)?;
+    front_end.release_material(id)
+}
+
+pub fn renderer_get_material(id: u32) -> Result<Material, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    match front_end.get_material(id) {
+        Some(material) => Ok(material.clone()),
+        None => {
+            error!("The material {} does not exist", id);
+            Err(EngineError::InvalidValue)
+        }
+    }
+}
+
+pub fn renderer_set_material_diffuse(
+    id: u32,
+    diffuse_color: glam::Vec4,
+    diffuse_texture: Option<TextureHandle>,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.set_material_diffuse(id, diffuse_color, diffuse_texture)
+}
+
+pub fn renderer_set_material_lightmap(
+    id: u32,
+    lightmap_texture: Option<TextureHandle>,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.set_material_texture(id, MaterialTextureSlot::Lightmap, lightmap_texture)
+}
+
+/// None draws the material with the vertex normals
+pub fn renderer_set_material_normal_map(
+    id: u32,
+    normal_texture: Option<TextureHandle>,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.set_material_texture(id, MaterialTextureSlot::Normal, normal_texture)
+}
+
+/// The red channel of the specular texture scales the strength
+pub fn renderer_set_material_specular(
+    id: u32,
+    specular_strength: f32,
+    shininess: f32,
+    specular_texture: Option<TextureHandle>,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.set_material_specular(id, specular_strength, shininess, specular_texture)
+}
+
+pub fn renderer_set_material_render_state(
+    id: u32,
+    render_state: MaterialRenderState,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.set_material_render_state(id, render_state)
+}
+
+/// Replace the reflection probes by the ones baked in the directory,
+/// the probes never baked before are baked from the meshes and saved there
+pub fn renderer_load_reflection_probes(
+    directory: &Path,
+    probes: &[ReflectionProbe],
+    meshes: &[LightmapBakeMesh],
+    params: &ReflectionProbeBakeParameters,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::InitializationFailed)?;
+    let environment_maps = reflection_probes_load_or_bake(directory, probes, meshes, params)?;
+    get_backend_mut(&mut front_end.backend)?
+        .upload_reflection_probes(&environment_maps.iter().collect::<Vec<_>>())?;
+    front_end.reflection_probes = probes.iter().cloned().zip(environment_maps).collect();
+    Ok(())
+}
+
+/// Probe the closest to the position, none if there are no probes
+pub fn renderer_get_closest_reflection_probe(
+    position: glam::Vec3,
+) -> Result<Option<ReflectionProbe>, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    Ok(front_end
+        .get_closest_reflection_probe_index(position)
+        .map(|index| front_end.reflection_probes[index as usize].0.clone()))
+}
+
+pub fn renderer_instantiate_gltf(
+    scene: &GltfScene,
+    parent: Option<SceneNodeId>,
+) -> Result<GltfInstance, EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::InitializationFailed)?;
+    front_end.instantiate_gltf(scene, parent)
+}
+
+/// Read the gltf file on a worker thread and instantiate it on the main thread
+/// The callback gets the instance once its nodes are in the scene, or the error of the loading
+pub fn renderer_load_gltf_async<C>(
+    path: PathBuf,
+    parent: Option<SceneNodeId>,
+    on_loaded: C,
+) -> Result<JobHandle, EngineError>
+where
+    C: FnOnce(Result<GltfInstance, EngineError>) -> Result<(), EngineError> + Send + 'static,
+{
+    job_submit(
+        move || gltf_load(&path),
+        move |result| on_loaded(result.and_then(|scene| renderer_instantiate_gltf(&scene, parent))),
+    )
+}
+
+pub fn renderer_unload_gltf(instance: GltfInstance) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::ShutdownFailed)?;
+    front_end.unload_gltf(instance)
+}
+
+/// Call the callback every frame at the stage, until it is unregistered
+pub fn renderer_register_render_hook<F>(
+    stage: RenderStage,
+    callback: F,
+) -> Result<RenderHookHandle, EngineError>
+where
+    F: FnMut(&mut RenderHookContext) -> Result<(), EngineError> + Send + 'static,
+{
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    Ok(front_end.render_hooks.register(stage, Box::new(callback)))
+}
+
+/// Shader resources for a renderable created by the game, the gltf scenes acquire their own
+pub fn renderer_acquire_object(name: &str, node: Option<SceneNodeId>) -> Result<u32, EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::InitializationFailed)?;
+    front_end.acquire_object(name, node)
+}
+
+/// The renderable using the object must be removed from the scene first
+pub fn renderer_release_object(object_id: u32) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::ShutdownFailed)?;
+    front_end.release_object(object_id)
+}
+
+/// Name an object in the logs and the debug panels
+/// The objects of the gltf scenes are named after their node and mesh
+pub fn renderer_set_object_name(
+    object_id: u32,
+    name: &str,
+    node: Option<SceneNodeId>,
+) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.object_names.set(object_id, name, node);
+    Ok(())
+}
+
+pub fn renderer_get_object_name(object_id: u32) -> Result<Option<ObjectName>, EngineError> {
+    let front_end = fetch_global_renderer(EngineError::AccessFailed)?;
+    Ok(front_end.object_names.get(object_id).cloned())
+}
+
+pub fn renderer_unregister_render_hook(handle: RenderHookHandle) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    if !front_end.render_hooks.unregister(handle) {
+        error!("Can't unregister the unknown render hook {:?}", handle);
+        return Err(EngineError::InvalidValue);
+    }
+    Ok(())
+}
+
+/// Save the next presented frame, the file is written on a worker thread a few frames later
+pub fn renderer_take_screenshot(request: ScreenshotRequest) -> Result<(), EngineError> {
+    let mut front_end = fetch_global_renderer(EngineError::UpdateFailed)?;
+    front_end.request_screenshot(request)
 }
-// TODO: end of temporary code
@@ -1,25 +1,179 @@
-use crate::{resources::texture::Texture, warn};
+use crate::{
+    core::application::windows::WindowHandle,
+    resources::{
+        geometry::Geometry,
+        material::{Material, MATERIAL_DEFAULT_SHININESS, MATERIAL_DEFAULT_SPECULAR_STRENGTH},
+        texture::Texture,
+        texture_system::TextureHandle,
+    },
+};
 
-pub(crate) enum RendererBackendType {
+use super::{
+    lighting::{PointLight, LIGHTING_MAX_POINT_LIGHTS},
+    scene::culling::BoundingSphere,
+};
+
+/// Graphics api drawing the frames, chosen with `ApplicationParameters::renderer_backend`
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RendererBackendType {
+    #[default]
     Vulkan,
     OpenGl,
     DirectX,
+    /// Draws nothing and opens no window, for the servers and the tests without a gpu
+    Null,
+}
+
+/// Multisampling of the scene, lowered to the highest count supported by the gpu
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AntiAliasing {
+    #[default]
+    Off,
+    Msaa2x,
+    Msaa4x,
+    Msaa8x,
+}
+
+impl AntiAliasing {
+    /// Number of samples per pixel
+    pub fn get_sample_count(&self) -> u32 {
+        match self {
+            Self::Off => 1,
+            Self::Msaa2x => 2,
+            Self::Msaa4x => 4,
+            Self::Msaa8x => 8,
+        }
+    }
+}
+
+/// Synchronization of the presented frames with the display
+/// The mode falls back to vsync when the gpu does not support it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VsyncMode {
+    /// Every frame is shown, the rendering waits for the display
+    On,
+    /// The latest frame is shown at the display refresh without blocking the rendering
+    #[default]
+    Mailbox,
+    /// The frames are shown as soon as they are done, may tear
+    Off,
+}
+
+/// Range of the colors sent to the display
+/// Hdr falls back to sdr when the surface does not support it
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DisplayOutput {
+    #[default]
+    Sdr,
+    Hdr,
+}
+
+/// Depth attachment formats the renderer can pick from
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DepthFormat {
+    D16,
+    D32,
+    /// 24 bits of depth and 8 bits of stencil
+    D24S8,
+    /// 32 bits of depth and 8 bits of stencil
+    D32S8,
+}
+
+impl DepthFormat {
+    pub fn has_stencil(&self) -> bool {
+        matches!(self, Self::D24S8 | Self::D32S8)
+    }
+}
+
+/// Selection of the depth attachment format, the first one supported by the gpu is used
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DepthStencilSettings {
+    /// Formats by order of preference
+    pub formats: Vec<DepthFormat>,
+    /// Skip the formats without stencil, the initialization fails if none is supported
+    pub require_stencil: bool,
+}
+
+impl Default for DepthStencilSettings {
+    fn default() -> Self {
+        // Stencil is preferred so the stencil pipelines (outlines, portals) are available
+        Self {
+            formats: vec![DepthFormat::D32S8, DepthFormat::D24S8, DepthFormat::D32],
+            require_stencil: false,
+        }
+    }
+}
+
+/// Encoding of the presented images, selected from the display output and the surface formats
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutputColorSpace {
+    /// 8 bits per channel, the colors are between 0 and 1
+    #[default]
+    Srgb,
+    /// 10 bits per channel, rec 2020 primaries encoded with the perceptual quantizer curve
+    Hdr10,
+    /// Linear half floats with the srgb primaries, 1 is the sdr white and the colors can exceed it
+    ScRgb,
 }
 
 pub(crate) struct RenderFrameData {
     pub delta_time: f64,
 }
 
+/// Gpu statistics of the last completed frame
+/// Pipeline statistics are none when the device does not support them
+#[derive(Default, Debug, Clone, Copy)]
+pub struct RendererStatistics {
+    pub frame_number: u64,
+    pub input_assembly_vertices: Option<u64>,
+    pub vertex_shader_invocations: Option<u64>,
+    pub clipping_primitives: Option<u64>,
+    pub fragment_shader_invocations: Option<u64>,
+    // Occlusion queries
+    pub tested_objects: u32,
+    pub visible_objects: u32,
+    /// None when the device has no timestamps for the graphics queue
+    pub gpu_pass_timings: Option<GpuPassTimings>,
+}
+
+/// Gpu time spent in each pass of a frame, in seconds
+#[derive(Default, Debug, Clone, Copy, PartialEq)]
+pub struct GpuPassTimings {
+    pub world: f64,
+    pub ui: f64,
+}
+
 /// Max 3 for triple-buffering
 pub const RENDERER_MAX_IN_FLIGHT_FRAMES: usize = 3;
 
+/// Point light as seen by the shaders
+#[derive(Clone, Copy, Default)]
+#[repr(C)]
+pub(crate) struct RendererPointLightData {
+    pub position: glam::Vec4, // 16 bytes, w is the range
+    pub color: glam::Vec4,    // 16 bytes, premultiplied by the intensity
+}
+
+impl From<&PointLight> for RendererPointLightData {
+    fn from(light: &PointLight) -> Self {
+        Self {
+            position: light.position.extend(light.range),
+            color: (light.color * light.intensity).extend(1.0),
+        }
+    }
+}
+
 /// Uploaded once per frame
 #[repr(C)]
 pub(crate) struct RendererGlobalUniformObject {
-    pub projection: glam::Mat4,  // 64 bytes
-    pub view: glam::Mat4,        // 64 bytes
-    pub reserved_01: glam::Mat4, // 64 bytes reserved for future use
-    pub reserved_02: glam::Mat4, // 64 bytes reserved for future use
+    pub projection: glam::Mat4,                  // 64 bytes
+    pub view: glam::Mat4,                        // 64 bytes
+    pub view_position: glam::Vec4,               // 16 bytes, w is unused
+    pub ambient_color: glam::Vec4,               // 16 bytes
+    pub directional_light_direction: glam::Vec4, // 16 bytes, w is 1 when the light is enabled
+    pub directional_light_color: glam::Vec4,     // 16 bytes, premultiplied by the intensity
+    pub point_light_count: glam::UVec4,          // 16 bytes, only x is used
+    pub point_lights: [RendererPointLightData; LIGHTING_MAX_POINT_LIGHTS], // 32 bytes each
 }
 
 impl Default for RendererGlobalUniformObject {
@@ -27,8 +181,12 @@ impl Default for RendererGlobalUniformObject {
         Self {
             projection: glam::Mat4::IDENTITY,
             view: glam::Mat4::IDENTITY,
-            reserved_01: glam::Mat4::ZERO,
-            reserved_02: glam::Mat4::ZERO,
+            view_position: glam::Vec4::ZERO,
+            ambient_color: glam::Vec4::ONE,
+            directional_light_direction: glam::Vec4::ZERO,
+            directional_light_color: glam::Vec4::ZERO,
+            point_light_count: glam::UVec4::ZERO,
+            point_lights: [RendererPointLightData::default(); LIGHTING_MAX_POINT_LIGHTS],
         }
     }
 }
@@ -36,10 +194,12 @@ impl Default for RendererGlobalUniformObject {
 /// Uploaded once per object per frame
 #[repr(C)]
 pub(crate) struct RendererPerObjectUniformObject {
-    pub diffuse: glam::Vec4,     // 16 bytes
-    pub reserved_01: glam::Vec4, // 16 bytes reserved for future use
-    pub reserved_02: glam::Vec4, // 16 bytes reserved for future use
-    pub reserved_03: glam::Vec4, // 16 bytes reserved for future use
+    pub diffuse: glam::Vec4,          // 16 bytes
+    pub lightmap: glam::Vec4,         // 16 bytes, x is 1 when a lightmap is bound
+    pub specular: glam::Vec4, // 16 bytes, x is the strength, y the shininess and z 1 when a specular map is bound
+    pub normal_map: glam::Vec4, // 16 bytes, x is 1 when a normal map is bound
+    pub texture_indices: glam::UVec4, // 16 bytes, bindless index of the textures in the order of the material slots
+    pub reflection: glam::Vec4, // 16 bytes, x is the probe, y 1 when a probe is used and z the number of probe levels
 }
 
 impl RendererPerObjectUniformObject {
@@ -47,25 +207,59 @@ impl RendererPerObjectUniformObject {
         self.diffuse = diffuse;
         self
     }
+    pub fn lightmap(mut self, lightmap: glam::Vec4) -> Self {
+        self.lightmap = lightmap;
+        self
+    }
+    pub fn specular(mut self, specular: glam::Vec4) -> Self {
+        self.specular = specular;
+        self
+    }
+    pub fn normal_map(mut self, normal_map: glam::Vec4) -> Self {
+        self.normal_map = normal_map;
+        self
+    }
+    pub fn texture_indices(mut self, texture_indices: glam::UVec4) -> Self {
+        self.texture_indices = texture_indices;
+        self
+    }
+    pub fn reflection(mut self, reflection: glam::Vec4) -> Self {
+        self.reflection = reflection;
+        self
+    }
 }
 
 impl Default for RendererPerObjectUniformObject {
     fn default() -> Self {
         Self {
             diffuse: glam::Vec4::ONE,
-            reserved_01: glam::Vec4::ZERO,
-            reserved_02: glam::Vec4::ZERO,
-            reserved_03: glam::Vec4::ZERO,
+            lightmap: glam::Vec4::ZERO,
+            specular: glam::Vec4::new(
+                MATERIAL_DEFAULT_SPECULAR_STRENGTH,
+                MATERIAL_DEFAULT_SHININESS,
+                0.0,
+                0.0,
+            ),
+            normal_map: glam::Vec4::ZERO,
+            texture_indices: glam::UVec4::ZERO,
+            reflection: glam::Vec4::ZERO,
         }
     }
 }
 
-pub const RENDERER_MAX_NUMBER_OF_TEXTURES_PER_OBJECT: usize = 16;
-
 pub(crate) struct GeometryRenderData {
     pub object_id: Option<u32>,
     pub model: glam::Mat4,
-    pub textures: [Option<Box<dyn Texture>>; RENDERER_MAX_NUMBER_OF_TEXTURES_PER_OBJECT],
+    /// Nothing is drawn without a geometry
+    pub geometry: Option<Box<dyn Geometry>>,
+    /// The renderer default material is used when missing
+    pub material: Option<Material>,
+    /// Object space bounds, objects without bounds are never culled
+    pub bounding_sphere: Option<BoundingSphere>,
+    /// Opacity factor applied on top of the diffuse colour, used to fade out culled objects
+    pub fade: f32,
+    /// Index of the reflection probe closest to the object, no reflections when missing
+    pub reflection_probe: Option<u32>,
 }
 
 impl GeometryRenderData {
@@ -73,23 +267,28 @@ impl GeometryRenderData {
         self.model = model;
         self
     }
+    pub fn geometry(mut self, geometry: Option<Box<dyn Geometry>>) -> Self {
+        self.geometry = geometry;
+        self
+    }
     pub fn object_id(mut self, id: Option<u32>) -> Self {
         self.object_id = id;
         self
     }
-    pub fn textures(
-        mut self,
-        textures: [Option<Box<dyn Texture>>; RENDERER_MAX_NUMBER_OF_TEXTURES_PER_OBJECT],
-    ) -> Self {
-        self.textures = textures;
+    pub fn material(mut self, material: Option<Material>) -> Self {
+        self.material = material;
         self
     }
-    pub fn texture(mut self, index: usize, texture: Option<Box<dyn Texture>>) -> Self {
-        if index >= RENDERER_MAX_NUMBER_OF_TEXTURES_PER_OBJECT {
-            warn!("The index of the texture set for the geometry render data is too big, setup cancelled");
-            return self;
-        }
-        self.textures[index] = texture;
+    pub fn bounding_sphere(mut self, bounding_sphere: Option<BoundingSphere>) -> Self {
+        self.bounding_sphere = bounding_sphere;
+        self
+    }
+    pub fn fade(mut self, fade: f32) -> Self {
+        self.fade = fade;
+        self
+    }
+    pub fn reflection_probe(mut self, reflection_probe: Option<u32>) -> Self {
+        self.reflection_probe = reflection_probe;
         self
     }
 }
@@ -99,13 +298,84 @@ impl Default for GeometryRenderData {
         Self {
             object_id: None,
             model: glam::Mat4::IDENTITY,
-            textures: Default::default(),
+            geometry: None,
+            material: None,
+            bounding_sphere: None,
+            fade: 1.0,
+            reflection_probe: None,
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
 #[repr(C)]
-pub(crate) struct VertexData {
+pub struct VertexData {
     pub position: glam::Vec3,
     pub texture: glam::Vec2,
+    /// Second uv channel, unique for every surface of the object so lightmaps can be baked
+    pub lightmap_texture: glam::Vec2,
+    pub normal: glam::Vec3,
+    /// Direction of increasing u, w is the handedness of the bitangent
+    pub tangent: glam::Vec4,
+}
+
+/// Vertex of the ui overlay, in screen pixels with y going down
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct UiVertexData {
+    pub position: glam::Vec2,
+    pub texture: glam::Vec2,
+    pub color: glam::Vec4,
+}
+
+/// Range of the ui vertices sharing a texture
+pub(crate) struct UiRenderData<'a> {
+    pub texture: &'a dyn Texture,
+    pub first_vertex: u32,
+    pub vertex_count: u32,
+}
+
+/// Vertex of the immediate 2d shapes, in screen pixels with y going down
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[repr(C)]
+pub struct ImmediateVertexData {
+    pub position: glam::Vec2,
+    pub color: glam::Vec4,
+}
+
+/// Resource bound to a compute shader, its index in the bindings is its binding in the set 0
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ComputeResource {
+    /// Id returned by `renderer_create_storage_buffer`
+    StorageBuffer(u32),
+    /// Rgba8 texture read and written in place, sampled again by the frame once the dispatch is done
+    StorageImage(TextureHandle),
+}
+
+/// Compute resource resolved by the frontend for the backend
+pub(crate) enum ComputeBinding<'a> {
+    StorageBuffer(u32),
+    StorageImage(&'a dyn Texture),
+}
+
+/// Where the backend draws a camera pass, resolved by the frontend
+pub(crate) enum CameraPassTarget<'a> {
+    Texture(&'a dyn Texture),
+    /// Additional window with its own swapchain, the pass is presented in it
+    Window(WindowHandle),
+}
+
+/// Work recorded on the compute queue by the next frame, in the order it was queued
+pub(crate) enum ComputeCommand {
+    WriteStorageBuffer {
+        storage_buffer_id: u32,
+        offset: u64,
+        data: Vec<u8>,
+    },
+    Dispatch {
+        shader_id: u32,
+        resources: Vec<ComputeResource>,
+        group_count: glam::UVec3,
+        push_constants: Vec<u8>,
+    },
 }
@@ -1,4 +1,6 @@
-#[derive(Clone, Copy, Debug)]
+use super::spatial::Ray;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum ProjectionType {
     Orthographic,
     Perspective,
@@ -12,6 +14,8 @@ pub struct Camera {
     pub near_clip: f32,
     pub far_clip: f32,
     pub fov: f32,
+    /// Height of the view in world units when orthographic, the width follows the aspect ratio
+    pub orthographic_height: f32,
     pub aspect_ratio: f32,
     pub eye: glam::Vec3,
     pub center: glam::Vec3,
@@ -22,6 +26,7 @@ pub struct CameraCreatorParameters {
     pub near_clip: f32,
     pub far_clip: f32,
     pub fov: f32,
+    pub orthographic_height: f32,
     pub eye: glam::Vec3,
     pub center: glam::Vec3,
     pub up: glam::Vec3,
@@ -34,6 +39,7 @@ impl Default for CameraCreatorParameters {
             near_clip: 0.1,
             far_clip: 1000.0,
             fov: (45f32).to_radians(),
+            orthographic_height: 10.0,
             eye: glam::Vec3::new(0.0, 0.0, -1.0),
             center: glam::Vec3::ZERO,
             up: glam::Vec3::new(0.0, 1.0, 0.0),
@@ -58,6 +64,11 @@ impl CameraCreatorParameters {
         self
     }
 
+    pub fn orthographic_height(mut self, orthographic_height: f32) -> Self {
+        self.orthographic_height = orthographic_height;
+        self
+    }
+
     pub fn eye(mut self, eye: glam::Vec3) -> Self {
         self.eye = eye;
         self
@@ -79,6 +90,27 @@ impl CameraCreatorParameters {
     }
 }
 
+/// Area of the framebuffer the camera is drawn to, in pixels from the top left corner
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CameraViewport {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl CameraViewport {
+    /// Viewport covering the whole framebuffer
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            width,
+            height,
+        }
+    }
+}
+
 impl Default for Camera {
     fn default() -> Self {
         Camera::new(CameraCreatorParameters::default(), 16.0 / 9.0)
@@ -88,41 +120,121 @@ impl Default for Camera {
 impl Camera {
     pub fn new(parameters: CameraCreatorParameters, aspect_ratio: f32) -> Self {
         let view = glam::Mat4::look_at_lh(parameters.eye, parameters.center, parameters.up);
-        let projection = match parameters.projection {
-            ProjectionType::Orthographic => todo!("Orthographic not implemented"),
-            ProjectionType::Perspective => glam::Mat4::perspective_lh(
-                parameters.fov,
-                aspect_ratio,
-                parameters.near_clip,
-                parameters.far_clip,
-            ),
-        };
-        Self {
+        let mut camera = Self {
             view,
             projection_type: parameters.projection,
-            projection,
+            projection: glam::Mat4::IDENTITY,
             near_clip: parameters.near_clip,
             far_clip: parameters.far_clip,
             fov: parameters.fov,
+            orthographic_height: parameters.orthographic_height,
             aspect_ratio,
             eye: parameters.eye,
             center: parameters.center,
             up: parameters.up,
-        }
+        };
+        camera.update_projection();
+        camera
     }
 
-    pub fn update_aspect_ratio(&mut self, aspect_ratio: f32) {
-        let projection = match self.projection_type {
-            ProjectionType::Orthographic => todo!("Orthographic not implemented"),
-            ProjectionType::Perspective => {
-                glam::Mat4::perspective_lh(self.fov, aspect_ratio, self.near_clip, self.far_clip)
+    /// Orthographic camera looking at the screen pixels, y going down from the top left corner
+    /// The ui and the 2d shapes are drawn with it unless the game gives its own
+    pub fn new_screen(width: f32, height: f32) -> Self {
+        let center = glam::Vec3::new(width * 0.5, height * 0.5, 0.0);
+        let parameters = CameraCreatorParameters::default()
+            .projection(ProjectionType::Orthographic)
+            .orthographic_height(height)
+            .near_clip(-1.0)
+            .far_clip(1.0)
+            .eye(center)
+            .center(center + glam::Vec3::Z);
+        let mut camera = Camera::new(parameters, width / height.max(1.0));
+        // Mirrored around the center of the screen since the pixels go down
+        camera.set_view(
+            glam::Mat4::from_scale(glam::Vec3::new(1.0, -1.0, 1.0))
+                * glam::Mat4::from_translation(-center),
+        );
+        camera
+    }
+
+    fn update_projection(&mut self) {
+        self.projection = match self.projection_type {
+            ProjectionType::Orthographic => {
+                let half_height = self.orthographic_height * 0.5;
+                let half_width = half_height * self.aspect_ratio;
+                glam::Mat4::orthographic_lh(
+                    -half_width,
+                    half_width,
+                    -half_height,
+                    half_height,
+                    self.near_clip,
+                    self.far_clip,
+                )
             }
+            ProjectionType::Perspective => glam::Mat4::perspective_lh(
+                self.fov,
+                self.aspect_ratio,
+                self.near_clip,
+                self.far_clip,
+            ),
         };
-        self.projection = projection;
+    }
+
+    pub fn update_aspect_ratio(&mut self, aspect_ratio: f32) {
         self.aspect_ratio = aspect_ratio;
+        self.update_projection();
+    }
+
+    /// Switch between the perspective and the orthographic projections, e.g. for a 2d mode
+    pub fn set_projection_type(&mut self, projection_type: ProjectionType) {
+        self.projection_type = projection_type;
+        self.update_projection();
+    }
+
+    /// Zoom of the orthographic projection, kept when switching to the perspective one
+    pub fn set_orthographic_height(&mut self, orthographic_height: f32) {
+        self.orthographic_height = orthographic_height;
+        self.update_projection();
+    }
+
+    pub fn set_clip_planes(&mut self, near_clip: f32, far_clip: f32) {
+        self.near_clip = near_clip;
+        self.far_clip = far_clip;
+        self.update_projection();
     }
 
     pub fn set_view(&mut self, view: glam::Mat4) {
         self.view = view;
     }
+
+    /// Ray starting on the near plane going through the given pixel, y goes down like the cursor
+    pub fn screen_to_ray(&self, x: f32, y: f32, viewport: &CameraViewport) -> Ray {
+        // The pipeline flips the viewport so the ndc y goes up
+        let ndc_x = 2.0 * (x - viewport.x) / viewport.width - 1.0;
+        let ndc_y = 1.0 - 2.0 * (y - viewport.y) / viewport.height;
+        let inverse = (self.projection * self.view).inverse();
+        // Depth goes from 0 on the near plane to 1 on the far plane
+        let near = inverse.project_point3(glam::Vec3::new(ndc_x, ndc_y, 0.0));
+        let far = inverse.project_point3(glam::Vec3::new(ndc_x, ndc_y, 1.0));
+        Ray::new(near, far - near)
+    }
+
+    /// Pixel where the point is drawn and its depth between 0 and 1,
+    /// returns none if the point is behind the camera
+    pub fn world_to_screen(
+        &self,
+        point: glam::Vec3,
+        viewport: &CameraViewport,
+    ) -> Option<glam::Vec3> {
+        let clip = self.projection * self.view * point.extend(1.0);
+        if clip.w <= 0.0 {
+            return None;
+        }
+        let ndc = clip.truncate() / clip.w;
+        Some(glam::Vec3::new(
+            viewport.x + (ndc.x + 1.0) * 0.5 * viewport.width,
+            viewport.y + (1.0 - ndc.y) * 0.5 * viewport.height,
+            ndc.z,
+        ))
+    }
 }
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+
+use crate::{
+    core::{application::windows::WindowHandle, debug::errors::EngineError},
+    error,
+    resources::texture_system::TextureHandle,
+};
+
+use super::camera::{Camera, CameraViewport};
+
+/// Where a named camera is drawn
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CameraTarget {
+    #[default]
+    Screen,
+    /// Offscreen texture sampled by the materials, e.g. for a mirror or a minimap
+    /// Drawn every frame before the world, its colors are clamped by the texture format
+    Texture(TextureHandle),
+    /// Additional window opened by `application_create_window`, drawn every frame before the world
+    Window(WindowHandle),
+}
+
+/// Camera added next to the main one, e.g. for an editor view or a security camera
+#[derive(Clone, Copy, Debug)]
+pub struct NamedCamera {
+    pub camera: Camera,
+    /// Area of the target in pixels the camera is drawn to, the whole target when none
+    pub viewport: Option<CameraViewport>,
+    pub target: CameraTarget,
+}
+
+impl NamedCamera {
+    /// Area drawn to in a target of the given size
+    pub fn get_viewport(&self, width: u32, height: u32) -> CameraViewport {
+        self.viewport
+            .unwrap_or(CameraViewport::new(width as f32, height as f32))
+    }
+}
+
+/// Named cameras of the renderer, the main camera is kept apart
+#[derive(Default)]
+pub(crate) struct CameraSet {
+    cameras: HashMap<String, NamedCamera>,
+    /// Named camera the frame is rendered from instead of the main one
+    active: Option<String>,
+}
+
+impl CameraSet {
+    pub fn add(&mut self, name: &str, camera: NamedCamera) -> Result<(), EngineError> {
+        if self.cameras.contains_key(name) {
+            error!("A camera named {:?} already exists", name);
+            return Err(EngineError::Duplicate);
+        }
+        self.cameras.insert(name.to_string(), camera);
+        Ok(())
+    }
+
+    /// The camera stops being the active one if it was
+    pub fn remove(&mut self, name: &str) -> Result<NamedCamera, EngineError> {
+        let camera = match self.cameras.remove(name) {
+            Some(camera) => camera,
+            None => {
+                error!("Can't remove the unknown camera {:?}", name);
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        if self.is_active(name) {
+            self.active = None;
+        }
+        Ok(camera)
+    }
+
+    pub fn get(&self, name: &str) -> Result<&NamedCamera, EngineError> {
+        match self.cameras.get(name) {
+            Some(camera) => Ok(camera),
+            None => {
+                error!("Unknown camera {:?}", name);
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    pub fn get_mut(&mut self, name: &str) -> Result<&mut NamedCamera, EngineError> {
+        match self.cameras.get_mut(name) {
+            Some(camera) => Ok(camera),
+            None => {
+                error!("Unknown camera {:?}", name);
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    /// Cameras drawn into a texture or a window, sorted by name
+    pub fn get_offscreen_cameras(&self) -> Vec<NamedCamera> {
+        self.get_names()
+            .iter()
+            .map(|name| self.cameras[name])
+            .filter(|named_camera| named_camera.target != CameraTarget::Screen)
+            .collect()
+    }
+
+    pub fn get_names(&self) -> Vec<String> {
+        let mut names: Vec<String> = self.cameras.keys().cloned().collect();
+        names.sort();
+        names
+    }
+
+    /// None renders the frame from the main camera again
+    pub fn set_active(&mut self, name: Option<&str>) -> Result<(), EngineError> {
+        if let Some(name) = name {
+            if self.get(name)?.target != CameraTarget::Screen {
+                error!(
+                    "The camera {:?} draws into a texture or a window, it can't render the frame",
+                    name
+                );
+                return Err(EngineError::InvalidValue);
+            }
+        }
+        self.active = name.map(str::to_string);
+        Ok(())
+    }
+
+    pub fn is_active(&self, name: &str) -> bool {
+        self.active.as_deref() == Some(name)
+    }
+
+    pub fn get_active(&self) -> Option<&NamedCamera> {
+        self.active.as_ref().and_then(|name| self.cameras.get(name))
+    }
+
+    /// The cameras filling the screen follow its aspect ratio, the others keep their viewport's
+    pub fn update_aspect_ratios(&mut self, width: u32, height: u32) {
+        for named_camera in self.cameras.values_mut() {
+            if named_camera.target == CameraTarget::Screen && named_camera.viewport.is_none() {
+                named_camera
+                    .camera
+                    .update_aspect_ratio(width as f32 / height.max(1) as f32);
+            }
+        }
+    }
+}
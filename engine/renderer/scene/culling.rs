@@ -0,0 +1,116 @@
+use super::camera::{Camera, ProjectionType};
+
+/// Sphere enclosing an object
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BoundingSphere {
+    pub center: glam::Vec3,
+    pub radius: f32,
+}
+
+impl BoundingSphere {
+    pub fn new(center: glam::Vec3, radius: f32) -> Self {
+        Self { center, radius }
+    }
+
+    /// Bring the sphere from object space to world space
+    pub fn transform(&self, model: &glam::Mat4) -> Self {
+        let max_scale = model
+            .x_axis
+            .truncate()
+            .length()
+            .max(model.y_axis.truncate().length())
+            .max(model.z_axis.truncate().length());
+        Self {
+            center: model.transform_point3(self.center),
+            radius: self.radius * max_scale,
+        }
+    }
+}
+
+/// Skip objects too small on screen to be worth drawing
+#[derive(Clone, Copy, Debug)]
+pub struct SmallObjectCullingParameters {
+    pub is_enabled: bool,
+    /// Objects with a projected radius below this value are culled
+    pub pixel_threshold: f32,
+    /// Objects fade out over this many pixels above the threshold instead of popping
+    pub fade_range_in_pixels: Option<f32>,
+}
+
+impl Default for SmallObjectCullingParameters {
+    fn default() -> Self {
+        Self {
+            is_enabled: true,
+            pixel_threshold: 1.0,
+            fade_range_in_pixels: None,
+        }
+    }
+}
+
+impl SmallObjectCullingParameters {
+    pub fn is_enabled(mut self, is_enabled: bool) -> Self {
+        self.is_enabled = is_enabled;
+        self
+    }
+
+    pub fn pixel_threshold(mut self, pixel_threshold: f32) -> Self {
+        self.pixel_threshold = pixel_threshold;
+        self
+    }
+
+    pub fn fade_range_in_pixels(mut self, fade_range_in_pixels: Option<f32>) -> Self {
+        self.fade_range_in_pixels = fade_range_in_pixels;
+        self
+    }
+
+    /// Returns None if the object should be culled, else its opacity factor
+    pub fn get_visibility(
+        &self,
+        camera: &Camera,
+        sphere: &BoundingSphere,
+        viewport_height: f32,
+    ) -> Option<f32> {
+        if !self.is_enabled {
+            return Some(1.0);
+        }
+        let projected_radius = camera.get_projected_radius_in_pixels(sphere, viewport_height);
+        if projected_radius < self.pixel_threshold {
+            return None;
+        }
+        match self.fade_range_in_pixels {
+            Some(range) if range > 0.0 => {
+                Some(((projected_radius - self.pixel_threshold) / range).min(1.0))
+            }
+            _ => Some(1.0),
+        }
+    }
+}
+
+impl Camera {
+    pub fn get_position(&self) -> glam::Vec3 {
+        self.view.inverse().w_axis.truncate()
+    }
+
+    /// Approximate radius in pixels of a world space sphere once projected on screen
+    pub fn get_projected_radius_in_pixels(
+        &self,
+        sphere: &BoundingSphere,
+        viewport_height: f32,
+    ) -> f32 {
+        match self.projection_type {
+            ProjectionType::Perspective => {
+                let distance = (sphere.center - self.get_position()).length();
+                // The camera is inside the sphere
+                if distance <= sphere.radius {
+                    return f32::INFINITY;
+                }
+                let half_fov_tan = (self.fov * 0.5).tan();
+                sphere.radius / (distance * half_fov_tan) * viewport_height * 0.5
+            }
+            // Size does not depend on the distance, use the projection scale directly
+            ProjectionType::Orthographic => {
+                sphere.radius * self.projection.y_axis.y.abs() * viewport_height * 0.5
+            }
+        }
+    }
+}
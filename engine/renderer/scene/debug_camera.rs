@@ -0,0 +1,125 @@
+use crate::core::{
+    debug::errors::EngineError,
+    systems::input::{
+        input_is_key_down,
+        keyboard::Key,
+        mouse::{intput_get_mouse_position, MouseButton, MouseButtonState},
+    },
+};
+
+use super::camera::Camera;
+
+/// Free flying camera replacing the main camera while it is active, the game camera is kept as is
+#[derive(Clone, Copy, Debug)]
+pub struct DebugCamera {
+    pub camera: Camera,
+    pub is_active: bool,
+    /// Units per second
+    pub speed: f32,
+    /// Speed factor while the left shift is down
+    pub fast_factor: f32,
+    /// Speed factor while the left control is down
+    pub slow_factor: f32,
+    /// Radians per pixel moved while the right mouse button is down
+    pub mouse_sensitivity: f32,
+    pub eye: glam::Vec3,
+    /// Radians, turning left is positive
+    pub yaw: f32,
+    /// Radians, looking down is positive
+    pub pitch: f32,
+    /// Mouse position of the previous update, none when not looking around
+    previous_mouse_position: Option<(i16, i16)>,
+}
+
+impl Default for DebugCamera {
+    fn default() -> Self {
+        Self {
+            camera: Camera::default(),
+            is_active: false,
+            speed: 20.0,
+            fast_factor: 5.0,
+            slow_factor: 0.2,
+            mouse_sensitivity: 0.003,
+            eye: glam::Vec3::ZERO,
+            yaw: 0.0,
+            pitch: 0.0,
+            previous_mouse_position: None,
+        }
+    }
+}
+
+impl DebugCamera {
+    fn get_rotation(&self) -> glam::Quat {
+        glam::Quat::from_axis_angle(glam::Vec3::NEG_Y, self.yaw)
+            * glam::Quat::from_axis_angle(glam::Vec3::X, self.pitch)
+    }
+
+    fn update_view(&mut self) {
+        let transform = glam::Mat4::from_rotation_translation(self.get_rotation(), self.eye);
+        self.camera.view = transform.inverse();
+    }
+
+    /// Start flying from the point of view of the given camera
+    pub fn activate(&mut self, from: &Camera) {
+        let transform = from.view.inverse();
+        self.camera = *from;
+        self.eye = transform.w_axis.truncate();
+        let forward = transform.z_axis.truncate().normalize_or_zero();
+        self.pitch = (-forward.y).clamp(-1.0, 1.0).asin();
+        self.yaw = (-forward.x).atan2(forward.z);
+        self.previous_mouse_position = None;
+        self.is_active = true;
+        self.update_view();
+    }
+
+    pub fn deactivate(&mut self) {
+        self.is_active = false;
+    }
+
+    pub fn update_aspect_ratio(&mut self, aspect_ratio: f32) {
+        self.camera.update_aspect_ratio(aspect_ratio);
+    }
+
+    /// Move with WASD, up and down with E and Q, and look around with the right mouse button
+    pub fn update(&mut self, delta_time: f64) -> Result<(), EngineError> {
+        if !self.is_active {
+            return Ok(());
+        }
+
+        if MouseButton::Right.get_current_state()? == MouseButtonState::Pressed {
+            let (x, y) = intput_get_mouse_position()?;
+            if let Some((previous_x, previous_y)) = self.previous_mouse_position {
+                self.yaw -= (x - previous_x) as f32 * self.mouse_sensitivity;
+                self.pitch = (self.pitch + (y - previous_y) as f32 * self.mouse_sensitivity)
+                    .clamp(-89f32.to_radians(), 89f32.to_radians());
+            }
+            self.previous_mouse_position = Some((x, y));
+        } else {
+            self.previous_mouse_position = None;
+        }
+
+        let mut direction = glam::Vec3::ZERO;
+        for (key, axis) in [
+            (Key::W, glam::Vec3::Z),
+            (Key::S, glam::Vec3::NEG_Z),
+            (Key::D, glam::Vec3::X),
+            (Key::A, glam::Vec3::NEG_X),
+            (Key::E, glam::Vec3::Y),
+            (Key::Q, glam::Vec3::NEG_Y),
+        ] {
+            if input_is_key_down(key)? {
+                direction += axis;
+            }
+        }
+        let mut speed = self.speed;
+        if input_is_key_down(Key::LSHIFT)? {
+            speed *= self.fast_factor;
+        }
+        if input_is_key_down(Key::LCONTROL)? {
+            speed *= self.slow_factor;
+        }
+        self.eye += self.get_rotation() * direction.normalize_or_zero() * speed * delta_time as f32;
+        self.update_view();
+        Ok(())
+    }
+}
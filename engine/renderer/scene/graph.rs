@@ -0,0 +1,399 @@
+use crate::{core::debug::errors::EngineError, error, renderer::utils::parallel::parallel_map};
+
+use super::{
+    culling::BoundingSphere,
+    spatial::{Aabb, Frustum, Ray, SpatialHandle, SpatialTree},
+};
+
+/// Handle to a node of the scene graph
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SceneNodeId(usize);
+
+/// Position, rotation and scale of a node relative to its parent
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SceneTransform {
+    pub position: glam::Vec3,
+    pub rotation: glam::Quat,
+    pub scale: glam::Vec3,
+}
+
+impl Default for SceneTransform {
+    fn default() -> Self {
+        Self {
+            position: glam::Vec3::ZERO,
+            rotation: glam::Quat::IDENTITY,
+            scale: glam::Vec3::ONE,
+        }
+    }
+}
+
+impl SceneTransform {
+    pub fn position(mut self, position: glam::Vec3) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn rotation(mut self, rotation: glam::Quat) -> Self {
+        self.rotation = rotation;
+        self
+    }
+
+    pub fn scale(mut self, scale: glam::Vec3) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn get_matrix(&self) -> glam::Mat4 {
+        glam::Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.position)
+    }
+}
+
+/// What the renderer needs to draw a node
+#[derive(Clone, Copy, Debug)]
+pub struct SceneRenderable {
+    /// Id of the shader resources acquired for the node
+    pub object_id: u32,
+    /// Id returned when the geometry was created by the renderer
+    pub geometry_id: u32,
+    /// Id returned when the material was acquired, the default material is used when missing
+    pub material_id: Option<u32>,
+    /// Object space bounds, used for culling
+    pub bounding_sphere: Option<BoundingSphere>,
+}
+
+/// Hierarchy of nodes whose world transforms are the product of their parents' transforms
+/// Each property of the nodes is stored in its own array indexed by the node, so the per frame
+/// passes only go through the data they need
+#[derive(Default)]
+pub struct SceneGraph {
+    /// Destroyed nodes keep their slot until it is reused
+    is_alive: Vec<bool>,
+    parents: Vec<Option<usize>>,
+    children: Vec<Vec<usize>>,
+    local_transforms: Vec<SceneTransform>,
+    world_transforms: Vec<glam::Mat4>,
+    /// The world transform must be recomputed for this node and its children
+    dirty_flags: Vec<bool>,
+    renderables: Vec<Option<SceneRenderable>>,
+    /// World space bounds of the renderables, recomputed with the world transforms
+    world_bounds: Vec<Option<BoundingSphere>>,
+    free_nodes: Vec<usize>,
+    roots: Vec<usize>,
+    /// Alive nodes grouped by depth, the nodes of a level only depend on the previous levels
+    levels: Vec<Vec<usize>>,
+    /// Renderables with world bounds, kept up to date with the world transforms
+    spatial_tree: SpatialTree<usize>,
+    spatial_handles: Vec<Option<SpatialHandle>>,
+    /// Alive nodes that can be drawn but have no bounds, never culled
+    unbounded_renderable_nodes: Vec<usize>,
+    /// The levels and the unbounded renderable nodes must be rebuilt
+    is_layout_dirty: bool,
+}
+
+impl SceneGraph {
+    fn check_node(&self, id: SceneNodeId) -> Result<usize, EngineError> {
+        match self.is_alive.get(id.0) {
+            Some(true) => Ok(id.0),
+            _ => {
+                error!("The scene node {:?} does not exist", id);
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    fn attach(&mut self, node: usize, parent: Option<usize>) {
+        match parent {
+            Some(parent) => self.children[parent].push(node),
+            None => self.roots.push(node),
+        }
+        self.parents[node] = parent;
+        self.dirty_flags[node] = true;
+        self.is_layout_dirty = true;
+    }
+
+    fn detach(&mut self, node: usize) {
+        let siblings = match self.parents[node] {
+            Some(parent) => &mut self.children[parent],
+            None => &mut self.roots,
+        };
+        siblings.retain(|sibling| *sibling != node);
+        self.is_layout_dirty = true;
+    }
+
+    /// Create an empty node, at the root of the scene if there is no parent
+    pub fn create_node(&mut self, parent: Option<SceneNodeId>) -> Result<SceneNodeId, EngineError> {
+        if let Some(parent) = parent {
+            self.check_node(parent)?;
+        }
+        let index = match self.free_nodes.pop() {
+            Some(index) => {
+                self.is_alive[index] = true;
+                self.local_transforms[index] = SceneTransform::default();
+                self.world_transforms[index] = glam::Mat4::IDENTITY;
+                index
+            }
+            None => {
+                self.is_alive.push(true);
+                self.parents.push(None);
+                self.children.push(Vec::new());
+                self.local_transforms.push(SceneTransform::default());
+                self.world_transforms.push(glam::Mat4::IDENTITY);
+                self.dirty_flags.push(true);
+                self.renderables.push(None);
+                self.world_bounds.push(None);
+                self.spatial_handles.push(None);
+                self.is_alive.len() - 1
+            }
+        };
+        self.attach(index, parent.map(|parent| parent.0));
+        Ok(SceneNodeId(index))
+    }
+
+    /// Destroy a node and all of its children
+    pub fn destroy_node(&mut self, id: SceneNodeId) -> Result<(), EngineError> {
+        self.check_node(id)?;
+        self.detach(id.0);
+        let mut to_destroy = vec![id.0];
+        while let Some(index) = to_destroy.pop() {
+            if self.is_alive[index] {
+                self.is_alive[index] = false;
+                self.parents[index] = None;
+                to_destroy.append(&mut self.children[index]);
+                self.renderables[index] = None;
+                self.world_bounds[index] = None;
+                self.update_spatial_tree(index);
+                self.free_nodes.push(index);
+            }
+        }
+        Ok(())
+    }
+
+    /// Move a node under another one, or at the root of the scene
+    pub fn set_parent(
+        &mut self,
+        id: SceneNodeId,
+        parent: Option<SceneNodeId>,
+    ) -> Result<(), EngineError> {
+        self.check_node(id)?;
+        if let Some(parent) = parent {
+            self.check_node(parent)?;
+            // Refuse to create cycles
+            let mut ancestor = Some(parent.0);
+            while let Some(index) = ancestor {
+                if index == id.0 {
+                    error!(
+                        "Can't attach the scene node {:?} to its own descendant {:?}",
+                        id, parent
+                    );
+                    return Err(EngineError::InvalidValue);
+                }
+                ancestor = self.parents[index];
+            }
+        }
+        self.detach(id.0);
+        self.attach(id.0, parent.map(|parent| parent.0));
+        Ok(())
+    }
+
+    pub fn get_node_count(&self) -> usize {
+        self.is_alive.len() - self.free_nodes.len()
+    }
+
+    /// Renderable of the node, none for the empty nodes
+    pub fn get_renderable(&self, id: SceneNodeId) -> Result<Option<SceneRenderable>, EngineError> {
+        Ok(self.renderables[self.check_node(id)?])
+    }
+
+    pub fn get_parent(&self, id: SceneNodeId) -> Result<Option<SceneNodeId>, EngineError> {
+        Ok(self.parents[self.check_node(id)?].map(SceneNodeId))
+    }
+
+    pub fn get_children(&self, id: SceneNodeId) -> Result<Vec<SceneNodeId>, EngineError> {
+        Ok(self.children[self.check_node(id)?]
+            .iter()
+            .map(|child| SceneNodeId(*child))
+            .collect())
+    }
+
+    pub fn set_local_transform(
+        &mut self,
+        id: SceneNodeId,
+        transform: SceneTransform,
+    ) -> Result<(), EngineError> {
+        let index = self.check_node(id)?;
+        self.local_transforms[index] = transform;
+        self.dirty_flags[index] = true;
+        Ok(())
+    }
+
+    pub fn get_local_transform(&self, id: SceneNodeId) -> Result<SceneTransform, EngineError> {
+        Ok(self.local_transforms[self.check_node(id)?])
+    }
+
+    /// World transform computed by the last update
+    pub fn get_world_transform(&self, id: SceneNodeId) -> Result<glam::Mat4, EngineError> {
+        Ok(self.world_transforms[self.check_node(id)?])
+    }
+
+    pub fn set_renderable(
+        &mut self,
+        id: SceneNodeId,
+        renderable: Option<SceneRenderable>,
+    ) -> Result<(), EngineError> {
+        let index = self.check_node(id)?;
+        let is_unbounded = |renderable: Option<SceneRenderable>| {
+            renderable.is_some_and(|renderable| renderable.bounding_sphere.is_none())
+        };
+        if is_unbounded(self.renderables[index]) != is_unbounded(renderable) {
+            self.is_layout_dirty = true;
+        }
+        self.renderables[index] = renderable;
+        self.world_bounds[index] = renderable
+            .and_then(|renderable| renderable.bounding_sphere)
+            .map(|sphere| sphere.transform(&self.world_transforms[index]));
+        self.update_spatial_tree(index);
+        Ok(())
+    }
+
+    /// Move the node in the spatial tree to its world bounds, removes it when it has none
+    fn update_spatial_tree(&mut self, index: usize) {
+        match (self.world_bounds[index], self.spatial_handles[index]) {
+            (Some(bounds), Some(handle)) => {
+                self.spatial_tree.update(handle, Aabb::from_sphere(&bounds));
+            }
+            (Some(bounds), None) => {
+                self.spatial_handles[index] =
+                    Some(self.spatial_tree.insert(Aabb::from_sphere(&bounds), index));
+            }
+            (None, Some(handle)) => {
+                self.spatial_tree.remove(handle);
+                self.spatial_handles[index] = None;
+            }
+            (None, None) => {}
+        }
+    }
+
+    /// Group the nodes by depth and list the renderable ones without bounds
+    fn update_layout(&mut self) {
+        self.levels.clear();
+        let mut level = self.roots.clone();
+        while !level.is_empty() {
+            let next_level = level
+                .iter()
+                .flat_map(|node| self.children[*node].iter().copied())
+                .collect();
+            self.levels.push(level);
+            level = next_level;
+        }
+        self.unbounded_renderable_nodes = (0..self.renderables.len())
+            .filter(|index| {
+                self.is_alive[*index]
+                    && self.renderables[*index]
+                        .is_some_and(|renderable| renderable.bounding_sphere.is_none())
+            })
+            .collect();
+        self.is_layout_dirty = false;
+    }
+
+    /// Recompute the world transforms and bounds of the nodes that moved and of their children
+    /// The nodes of a level are updated on several threads in large scenes
+    pub fn update_world_transforms(&mut self) {
+        if self.is_layout_dirty {
+            self.update_layout();
+        }
+        let mut moved_nodes = Vec::new();
+        for level in &self.levels {
+            let updates = parallel_map(level, |index| {
+                let parent = self.parents[*index];
+                let is_dirty = self.dirty_flags[*index]
+                    || parent.is_some_and(|parent| self.dirty_flags[parent]);
+                if !is_dirty {
+                    return None;
+                }
+                let parent_world = parent
+                    .map(|parent| self.world_transforms[parent])
+                    .unwrap_or(glam::Mat4::IDENTITY);
+                let world = parent_world * self.local_transforms[*index].get_matrix();
+                let bounds = self.renderables[*index]
+                    .and_then(|renderable| renderable.bounding_sphere)
+                    .map(|sphere| sphere.transform(&world));
+                Some((world, bounds))
+            });
+            for (index, update) in level.iter().zip(updates) {
+                if let Some((world, bounds)) = update {
+                    self.world_transforms[*index] = world;
+                    self.world_bounds[*index] = bounds;
+                    // Marks the children of the next level
+                    self.dirty_flags[*index] = true;
+                    moved_nodes.push(*index);
+                }
+            }
+        }
+        self.dirty_flags.fill(false);
+        for index in moved_nodes {
+            self.update_spatial_tree(index);
+        }
+    }
+
+    /// Every node that can be drawn with its world transform
+    pub fn get_renderables(&self) -> impl Iterator<Item = (glam::Mat4, &SceneRenderable)> {
+        self.renderables
+            .iter()
+            .zip(&self.world_transforms)
+            .filter_map(|(renderable, world)| {
+                renderable.as_ref().map(|renderable| (*world, renderable))
+            })
+    }
+
+    /// Renderables whose world bounds from the last update are in the frustum and pass the
+    /// visibility test, the candidates come from the spatial tree and are tested on several threads
+    /// in large scenes
+    /// The test receives no bounds for the renderables without any and returns None to cull them
+    pub fn cull_renderables<R, F>(
+        &self,
+        frustum: &Frustum,
+        visibility: F,
+    ) -> Vec<(glam::Mat4, SceneRenderable, R)>
+    where
+        R: Send,
+        F: Fn(Option<&BoundingSphere>) -> Option<R> + Sync,
+    {
+        let candidates: Vec<usize> = self
+            .spatial_tree
+            .query_frustum(frustum)
+            .into_iter()
+            .filter_map(|handle| self.spatial_tree.get(handle).copied())
+            .chain(self.unbounded_renderable_nodes.iter().copied())
+            .collect();
+        parallel_map(&candidates, |index| {
+            visibility(self.world_bounds[*index].as_ref())
+        })
+        .into_iter()
+        .zip(&candidates)
+        .filter_map(|(visibility, index)| {
+            visibility.map(|visibility| {
+                (
+                    self.world_transforms[*index],
+                    self.renderables[*index].unwrap(),
+                    visibility,
+                )
+            })
+        })
+        .collect()
+    }
+
+    /// Closest renderable whose world bounds from the last update are hit by the ray, with the
+    /// hit distance
+    pub fn raycast(&self, ray: &Ray, max_distance: f32) -> Option<(SceneNodeId, f32)> {
+        self.spatial_tree
+            .raycast_with(ray, max_distance, |index, _| {
+                self.world_bounds[*index]
+                    .and_then(|bounds| bounds.intersects_ray(ray, max_distance))
+            })
+            .and_then(|(handle, distance)| {
+                self.spatial_tree
+                    .get(handle)
+                    .map(|index| (SceneNodeId(*index), distance))
+            })
+    }
+}
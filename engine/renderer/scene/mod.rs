@@ -1 +1,8 @@
-pub mod camera;
+pub(crate) mod camera;
+pub(crate) mod camera_set;
+pub(crate) mod culling;
+pub(crate) mod debug_camera;
+pub(crate) mod graph;
+pub(crate) mod picking;
+pub(crate) mod spatial;
+pub(crate) mod streaming;
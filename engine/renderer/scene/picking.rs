@@ -0,0 +1,10 @@
+use crate::core::ecs::entity::Entity;
+
+use super::graph::SceneNodeId;
+
+/// Object of the scene hit by a picking ray
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum PickedObject {
+    SceneNode(SceneNodeId),
+    Entity(Entity),
+}
@@ -0,0 +1,492 @@
+use super::{camera::Camera, culling::BoundingSphere};
+
+/// Axis aligned bounding box
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Aabb {
+    pub min: glam::Vec3,
+    pub max: glam::Vec3,
+}
+
+impl Aabb {
+    pub fn new(min: glam::Vec3, max: glam::Vec3) -> Self {
+        Self {
+            min: min.min(max),
+            max: min.max(max),
+        }
+    }
+
+    pub fn from_sphere(sphere: &BoundingSphere) -> Self {
+        Self {
+            min: sphere.center - glam::Vec3::splat(sphere.radius),
+            max: sphere.center + glam::Vec3::splat(sphere.radius),
+        }
+    }
+
+    pub fn union(&self, other: &Aabb) -> Self {
+        Self {
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    pub fn expanded(&self, margin: f32) -> Self {
+        Self {
+            min: self.min - glam::Vec3::splat(margin),
+            max: self.max + glam::Vec3::splat(margin),
+        }
+    }
+
+    pub fn contains(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.min).all() && self.max.cmpge(other.max).all()
+    }
+
+    pub fn intersects(&self, other: &Aabb) -> bool {
+        self.min.cmple(other.max).all() && self.max.cmpge(other.min).all()
+    }
+
+    pub fn surface_area(&self) -> f32 {
+        let extent = self.max - self.min;
+        2.0 * (extent.x * extent.y + extent.y * extent.z + extent.z * extent.x)
+    }
+
+    /// Returns the distance along the ray of the first intersection if any
+    pub fn intersects_ray(&self, ray: &Ray, max_distance: f32) -> Option<f32> {
+        let inverse_direction = ray.direction.recip();
+        let t0 = (self.min - ray.origin) * inverse_direction;
+        let t1 = (self.max - ray.origin) * inverse_direction;
+        let t_min = t0.min(t1).max_element().max(0.0);
+        let t_max = t0.max(t1).min_element().min(max_distance);
+        if t_min <= t_max {
+            Some(t_min)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Ray {
+    pub origin: glam::Vec3,
+    pub direction: glam::Vec3,
+}
+
+impl Ray {
+    pub fn new(origin: glam::Vec3, direction: glam::Vec3) -> Self {
+        Self {
+            origin,
+            direction: direction.normalize_or_zero(),
+        }
+    }
+
+    pub fn at(&self, distance: f32) -> glam::Vec3 {
+        self.origin + self.direction * distance
+    }
+}
+
+/// Planes pointing inside the view volume
+#[derive(Clone, Copy, Debug)]
+pub struct Frustum {
+    pub planes: [glam::Vec4; 6],
+}
+
+impl Frustum {
+    /// Extract the planes of a projection with a [0, 1] depth range
+    pub fn from_view_projection(view_projection: &glam::Mat4) -> Self {
+        let row_0 = view_projection.row(0);
+        let row_1 = view_projection.row(1);
+        let row_2 = view_projection.row(2);
+        let row_3 = view_projection.row(3);
+        let planes = [
+            row_3 + row_0, // left
+            row_3 - row_0, // right
+            row_3 + row_1, // bottom
+            row_3 - row_1, // top
+            row_2,         // near
+            row_3 - row_2, // far
+        ]
+        .map(|plane| plane / plane.truncate().length());
+        Self { planes }
+    }
+
+    pub fn intersects_aabb(&self, aabb: &Aabb) -> bool {
+        for plane in &self.planes {
+            // Corner of the box the furthest along the plane normal
+            let normal = plane.truncate();
+            let positive_vertex =
+                glam::Vec3::select(normal.cmpge(glam::Vec3::ZERO), aabb.max, aabb.min);
+            if normal.dot(positive_vertex) + plane.w < 0.0 {
+                return false;
+            }
+        }
+        true
+    }
+
+    pub fn intersects_sphere(&self, sphere: &BoundingSphere) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(sphere.center) + plane.w >= -sphere.radius)
+    }
+}
+
+impl Camera {
+    pub fn get_frustum(&self) -> Frustum {
+        Frustum::from_view_projection(&(self.projection * self.view))
+    }
+}
+
+impl BoundingSphere {
+    /// Returns the distance along the ray of the first intersection if any, 0 from inside
+    pub fn intersects_ray(&self, ray: &Ray, max_distance: f32) -> Option<f32> {
+        let to_center = self.center - ray.origin;
+        let projection = to_center.dot(ray.direction);
+        let distance_squared = to_center.length_squared() - projection * projection;
+        let radius_squared = self.radius * self.radius;
+        if distance_squared > radius_squared {
+            return None;
+        }
+        let half_chord = (radius_squared - distance_squared).sqrt();
+        let distance = if projection - half_chord >= 0.0 {
+            projection - half_chord
+        } else if projection + half_chord >= 0.0 {
+            0.0
+        } else {
+            return None;
+        };
+        (distance <= max_distance).then_some(distance)
+    }
+}
+
+/// Handle to an object stored in a spatial tree
+/// The generation tells apart the objects reusing the node of a removed one
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SpatialHandle {
+    index: usize,
+    generation: u32,
+}
+
+struct SpatialNode<T> {
+    // Enlarged box for leaves so small moves don't require tree updates
+    aabb: Aabb,
+    parent: Option<usize>,
+    children: Option<[usize; 2]>,
+    data: Option<T>,
+    height: u32,
+    /// Incremented every time the node is freed
+    generation: u32,
+}
+
+/// Dynamic bounding volume hierarchy
+/// Leaves are updated incrementally when their object moves outside of their enlarged box
+pub struct SpatialTree<T> {
+    nodes: Vec<SpatialNode<T>>,
+    free_nodes: Vec<usize>,
+    root: Option<usize>,
+    margin: f32,
+}
+
+impl<T> Default for SpatialTree<T> {
+    fn default() -> Self {
+        Self::new(0.1)
+    }
+}
+
+impl<T> SpatialTree<T> {
+    /// The margin enlarges every leaf box to avoid reinserting slowly moving objects
+    pub fn new(margin: f32) -> Self {
+        Self {
+            nodes: Vec::new(),
+            free_nodes: Vec::new(),
+            root: None,
+            margin,
+        }
+    }
+
+    fn allocate_node(&mut self, aabb: Aabb, data: Option<T>) -> usize {
+        let mut node = SpatialNode {
+            aabb,
+            parent: None,
+            children: None,
+            data,
+            height: 0,
+            generation: 0,
+        };
+        match self.free_nodes.pop() {
+            Some(index) => {
+                node.generation = self.nodes[index].generation;
+                self.nodes[index] = node;
+                index
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        }
+    }
+
+    fn free_node(&mut self, index: usize) -> Option<T> {
+        let node = &mut self.nodes[index];
+        node.parent = None;
+        node.children = None;
+        node.generation = node.generation.wrapping_add(1);
+        self.free_nodes.push(index);
+        node.data.take()
+    }
+
+    fn get_handle(&self, index: usize) -> SpatialHandle {
+        SpatialHandle {
+            index,
+            generation: self.nodes[index].generation,
+        }
+    }
+
+    /// Index of the leaf of the handle, none if its object was removed
+    fn check_handle(&self, handle: SpatialHandle) -> Option<usize> {
+        self.nodes
+            .get(handle.index)
+            .filter(|node| node.generation == handle.generation && node.data.is_some())
+            .map(|_| handle.index)
+    }
+
+    fn is_leaf(&self, index: usize) -> bool {
+        self.nodes[index].children.is_none()
+    }
+
+    /// Recompute the boxes and heights from the given node up to the root
+    fn refit(&mut self, start: Option<usize>) {
+        let mut current = start;
+        while let Some(index) = current {
+            if let Some([left, right]) = self.nodes[index].children {
+                self.nodes[index].aabb = self.nodes[left].aabb.union(&self.nodes[right].aabb);
+                self.nodes[index].height =
+                    1 + self.nodes[left].height.max(self.nodes[right].height);
+            }
+            current = self.nodes[index].parent;
+        }
+    }
+
+    fn insert_leaf(&mut self, leaf: usize) {
+        let mut sibling = match self.root {
+            None => {
+                self.root = Some(leaf);
+                self.nodes[leaf].parent = None;
+                return;
+            }
+            Some(root) => root,
+        };
+
+        // Walk down choosing the child whose box grows the least
+        let leaf_aabb = self.nodes[leaf].aabb;
+        while let Some([left, right]) = self.nodes[sibling].children {
+            let area = self.nodes[sibling].aabb.surface_area();
+            let combined_area = self.nodes[sibling].aabb.union(&leaf_aabb).surface_area();
+            // Cost of making a new parent here
+            let cost = 2.0 * combined_area;
+            // Minimum cost of pushing the leaf further down
+            let inheritance_cost = 2.0 * (combined_area - area);
+            let child_cost = |child: usize| {
+                let union_area = self.nodes[child].aabb.union(&leaf_aabb).surface_area();
+                if self.is_leaf(child) {
+                    union_area + inheritance_cost
+                } else {
+                    union_area - self.nodes[child].aabb.surface_area() + inheritance_cost
+                }
+            };
+            let left_cost = child_cost(left);
+            let right_cost = child_cost(right);
+            if cost < left_cost && cost < right_cost {
+                break;
+            }
+            sibling = if left_cost < right_cost { left } else { right };
+        }
+
+        // Create a new parent for the sibling and the leaf
+        let old_parent = self.nodes[sibling].parent;
+        let new_parent = self.allocate_node(self.nodes[sibling].aabb.union(&leaf_aabb), None);
+        self.nodes[new_parent].parent = old_parent;
+        self.nodes[new_parent].children = Some([sibling, leaf]);
+        self.nodes[sibling].parent = Some(new_parent);
+        self.nodes[leaf].parent = Some(new_parent);
+        match old_parent {
+            Some(old_parent) => {
+                if let Some(children) = self.nodes[old_parent].children.as_mut() {
+                    if children[0] == sibling {
+                        children[0] = new_parent;
+                    } else {
+                        children[1] = new_parent;
+                    }
+                }
+            }
+            None => self.root = Some(new_parent),
+        }
+        self.refit(Some(new_parent));
+    }
+
+    fn remove_leaf(&mut self, leaf: usize) {
+        if self.root == Some(leaf) {
+            self.root = None;
+            return;
+        }
+        let parent = match self.nodes[leaf].parent {
+            Some(parent) => parent,
+            None => return,
+        };
+        let grand_parent = self.nodes[parent].parent;
+        let sibling = match self.nodes[parent].children {
+            Some([left, right]) => {
+                if left == leaf {
+                    right
+                } else {
+                    left
+                }
+            }
+            None => return,
+        };
+
+        // The sibling takes the place of the parent
+        match grand_parent {
+            Some(grand_parent) => {
+                if let Some(children) = self.nodes[grand_parent].children.as_mut() {
+                    if children[0] == parent {
+                        children[0] = sibling;
+                    } else {
+                        children[1] = sibling;
+                    }
+                }
+                self.nodes[sibling].parent = Some(grand_parent);
+                self.refit(Some(grand_parent));
+            }
+            None => {
+                self.root = Some(sibling);
+                self.nodes[sibling].parent = None;
+            }
+        }
+        self.free_node(parent);
+        self.nodes[leaf].parent = None;
+    }
+
+    pub fn insert(&mut self, aabb: Aabb, data: T) -> SpatialHandle {
+        let leaf = self.allocate_node(aabb.expanded(self.margin), Some(data));
+        self.insert_leaf(leaf);
+        self.get_handle(leaf)
+    }
+
+    /// Returns none if the object was already removed
+    pub fn remove(&mut self, handle: SpatialHandle) -> Option<T> {
+        let leaf = self.check_handle(handle)?;
+        self.remove_leaf(leaf);
+        self.free_node(leaf)
+    }
+
+    /// Move an object, returns true if the tree had to be restructured
+    pub fn update(&mut self, handle: SpatialHandle, aabb: Aabb) -> bool {
+        let leaf = match self.check_handle(handle) {
+            Some(leaf) => leaf,
+            None => return false,
+        };
+        if self.nodes[leaf].aabb.contains(&aabb) {
+            return false;
+        }
+        self.remove_leaf(leaf);
+        self.nodes[leaf].aabb = aabb.expanded(self.margin);
+        self.insert_leaf(leaf);
+        true
+    }
+
+    /// Returns none if the object was removed
+    pub fn get(&self, handle: SpatialHandle) -> Option<&T> {
+        self.check_handle(handle)
+            .and_then(|leaf| self.nodes[leaf].data.as_ref())
+    }
+
+    pub fn get_height(&self) -> u32 {
+        self.root.map_or(0, |root| self.nodes[root].height)
+    }
+
+    /// Visit every leaf whose node passes the given test
+    fn query<F: Fn(&Aabb) -> bool>(&self, test: F) -> Vec<SpatialHandle> {
+        let mut result = Vec::new();
+        let mut stack: Vec<usize> = self.root.into_iter().collect();
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            if !test(&node.aabb) {
+                continue;
+            }
+            match node.children {
+                Some([left, right]) => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+                None => result.push(self.get_handle(index)),
+            }
+        }
+        result
+    }
+
+    pub fn query_aabb(&self, aabb: &Aabb) -> Vec<SpatialHandle> {
+        self.query(|node_aabb| node_aabb.intersects(aabb))
+    }
+
+    pub fn query_frustum(&self, frustum: &Frustum) -> Vec<SpatialHandle> {
+        self.query(|node_aabb| frustum.intersects_aabb(node_aabb))
+    }
+
+    /// Returns the closest object whose box is hit by the ray with the hit distance
+    pub fn raycast(&self, ray: &Ray, max_distance: f32) -> Option<(SpatialHandle, f32)> {
+        self.raycast_with(ray, max_distance, |_, box_distance| Some(box_distance))
+    }
+
+    /// Returns the closest object hit by the ray, the hit test is given the objects whose box is hit
+    /// with the distance to the box and returns the exact distance, none when the object is missed
+    pub fn raycast_with<F>(
+        &self,
+        ray: &Ray,
+        max_distance: f32,
+        hit_test: F,
+    ) -> Option<(SpatialHandle, f32)>
+    where
+        F: Fn(&T, f32) -> Option<f32>,
+    {
+        let mut closest: Option<(SpatialHandle, f32)> = None;
+        let mut stack: Vec<usize> = self.root.into_iter().collect();
+        while let Some(index) = stack.pop() {
+            let node = &self.nodes[index];
+            let max_distance = closest.map_or(max_distance, |(_, distance)| distance);
+            let distance = match node.aabb.intersects_ray(ray, max_distance) {
+                Some(distance) => distance,
+                None => continue,
+            };
+            match (node.children, node.data.as_ref()) {
+                (Some([left, right]), _) => {
+                    stack.push(left);
+                    stack.push(right);
+                }
+                (None, Some(data)) => {
+                    if let Some(distance) = hit_test(data, distance) {
+                        if distance <= max_distance {
+                            closest = Some((self.get_handle(index), distance));
+                        }
+                    }
+                }
+                (None, None) => {}
+            }
+        }
+        closest
+    }
+
+    /// Pairs of objects whose boxes overlap, for physics broadphase
+    pub fn query_overlapping_pairs(&self) -> Vec<(SpatialHandle, SpatialHandle)> {
+        let mut pairs = Vec::new();
+        for (index, node) in self.nodes.iter().enumerate() {
+            if node.data.is_none() {
+                continue;
+            }
+            for other in self.query_aabb(&node.aabb) {
+                // Only keep each pair once
+                if other.index > index {
+                    pairs.push((self.get_handle(index), other));
+                }
+            }
+        }
+        pairs
+    }
+}
@@ -0,0 +1,414 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc,
+    },
+};
+
+use crate::{
+    core::{
+        debug::errors::EngineError,
+        globals::{EngineGlobal, EngineGlobalGuard},
+        systems::{
+            events::{event_queue, EventCode},
+            jobs::job_submit,
+        },
+    },
+    error,
+    renderer::renderer_frontend::{
+        renderer_get_render_camera_position, renderer_instantiate_gltf, renderer_unload_gltf,
+    },
+    resources::gltf::{gltf_load, GltfInstance, GltfScene},
+    warn,
+};
+
+/// Position of a chunk on the streaming grid (xz plane)
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct ChunkCoordinates {
+    pub x: i32,
+    pub z: i32,
+}
+
+impl ChunkCoordinates {
+    pub fn from_position(position: glam::Vec3, chunk_size: f32) -> Self {
+        Self {
+            x: (position.x / chunk_size).floor() as i32,
+            z: (position.z / chunk_size).floor() as i32,
+        }
+    }
+
+    /// Number of chunks between two cells (square rings)
+    pub fn distance(&self, other: &ChunkCoordinates) -> u32 {
+        self.x.abs_diff(other.x).max(self.z.abs_diff(other.z))
+    }
+}
+
+/// Loads the content of a chunk, called from a worker thread
+pub trait ChunkLoader<T>: Send + Sync {
+    fn load(&self, coordinates: ChunkCoordinates) -> Result<T, EngineError>;
+
+    /// Called on the main thread once the chunk is loaded, e.g. to create its renderer resources
+    fn on_loaded(&self, coordinates: ChunkCoordinates, chunk: &mut T) -> Result<(), EngineError> {
+        Ok(())
+    }
+
+    /// Called on the main thread when a chunk leaves the streaming radius
+    fn unload(&self, coordinates: ChunkCoordinates, chunk: T) -> Result<(), EngineError> {
+        Ok(())
+    }
+}
+
+pub struct ChunkStreamerParameters {
+    /// Size of a grid cell in world units
+    pub chunk_size: f32,
+    /// Chunks closer than this radius (in cells) around the camera are loaded
+    pub load_radius: u32,
+    /// Chunks further than this radius are unloaded, should be bigger than the load radius
+    /// to avoid reloading chunks when moving back and forth on a border
+    pub unload_radius: u32,
+    /// Chunks loading at the same time, the others wait so the job workers stay available
+    pub max_concurrent_loads: usize,
+}
+
+impl Default for ChunkStreamerParameters {
+    fn default() -> Self {
+        Self {
+            chunk_size: 64.0,
+            load_radius: 2,
+            unload_radius: 3,
+            max_concurrent_loads: 2,
+        }
+    }
+}
+
+impl ChunkStreamerParameters {
+    pub fn chunk_size(mut self, chunk_size: f32) -> Self {
+        self.chunk_size = chunk_size;
+        self
+    }
+
+    pub fn load_radius(mut self, load_radius: u32) -> Self {
+        self.load_radius = load_radius;
+        self
+    }
+
+    pub fn unload_radius(mut self, unload_radius: u32) -> Self {
+        self.unload_radius = unload_radius;
+        self
+    }
+
+    pub fn max_concurrent_loads(mut self, max_concurrent_loads: usize) -> Self {
+        self.max_concurrent_loads = max_concurrent_loads;
+        self
+    }
+}
+
+enum ChunkState<T> {
+    Loading,
+    Loaded(T),
+}
+
+type ChunkLoadingResult<T> = (ChunkCoordinates, Result<T, EngineError>);
+
+/// Streams chunks in and out around a position
+pub struct ChunkStreamer<T: Send + 'static> {
+    parameters: ChunkStreamerParameters,
+    loader: Arc<dyn ChunkLoader<T>>,
+    chunks: HashMap<ChunkCoordinates, ChunkState<T>>,
+    /// Number of chunks in the loading state
+    loading_count: usize,
+    sender: Sender<ChunkLoadingResult<T>>,
+    receiver: Receiver<ChunkLoadingResult<T>>,
+}
+
+impl<T: Send + 'static> ChunkStreamer<T> {
+    pub fn new(
+        parameters: ChunkStreamerParameters,
+        loader: Arc<dyn ChunkLoader<T>>,
+    ) -> Result<Self, EngineError> {
+        if parameters.chunk_size <= 0.0 {
+            error!("The chunk size of a chunk streamer must be positive");
+            return Err(EngineError::InvalidValue);
+        }
+        if parameters.unload_radius < parameters.load_radius {
+            error!("The unload radius of a chunk streamer can't be smaller than its load radius");
+            return Err(EngineError::InvalidValue);
+        }
+        if parameters.max_concurrent_loads == 0 {
+            error!("A chunk streamer must load at least one chunk at a time");
+            return Err(EngineError::InvalidValue);
+        }
+        let (sender, receiver) = channel();
+        Ok(Self {
+            parameters,
+            loader,
+            chunks: HashMap::new(),
+            loading_count: 0,
+            sender,
+            receiver,
+        })
+    }
+
+    pub fn get_chunk(&self, coordinates: &ChunkCoordinates) -> Option<&T> {
+        match self.chunks.get(coordinates) {
+            Some(ChunkState::Loaded(chunk)) => Some(chunk),
+            _ => None,
+        }
+    }
+
+    pub fn is_loading(&self, coordinates: &ChunkCoordinates) -> bool {
+        matches!(self.chunks.get(coordinates), Some(ChunkState::Loading))
+    }
+
+    pub fn get_loaded_chunks(&self) -> impl Iterator<Item = (&ChunkCoordinates, &T)> {
+        self.chunks
+            .iter()
+            .filter_map(|(coordinates, state)| match state {
+                ChunkState::Loaded(chunk) => Some((coordinates, chunk)),
+                ChunkState::Loading => None,
+            })
+    }
+
+    fn receive_loaded_chunks(&mut self) -> Result<(), EngineError> {
+        while let Ok((coordinates, result)) = self.receiver.try_recv() {
+            // The chunk may have left the radius while loading, its result is dropped
+            if !self.is_loading(&coordinates) {
+                continue;
+            }
+            self.loading_count -= 1;
+            let mut chunk = match result {
+                Ok(chunk) => chunk,
+                Err(err) => {
+                    error!("Failed to load the chunk {:?}: {:?}", coordinates, err);
+                    self.chunks.remove(&coordinates);
+                    continue;
+                }
+            };
+            if let Err(err) = self.loader.on_loaded(coordinates, &mut chunk) {
+                error!(
+                    "Failed to finish loading the chunk {:?}: {:?}",
+                    coordinates, err
+                );
+                self.chunks.remove(&coordinates);
+                continue;
+            }
+            self.chunks.insert(coordinates, ChunkState::Loaded(chunk));
+            if let Err(err) = event_queue(EventCode::ChunkLoaded {
+                x: coordinates.x,
+                z: coordinates.z,
+            }) {
+                warn!("Failed to queue the chunk loaded event: {:?}", err);
+            }
+        }
+        Ok(())
+    }
+
+    fn unload_chunk(&mut self, coordinates: ChunkCoordinates) -> Result<(), EngineError> {
+        let chunk = match self.chunks.remove(&coordinates) {
+            Some(ChunkState::Loaded(chunk)) => Some(chunk),
+            // Its result will be ignored
+            Some(ChunkState::Loading) => {
+                self.loading_count -= 1;
+                None
+            }
+            None => None,
+        };
+        if let Some(chunk) = chunk {
+            if let Err(err) = self.loader.unload(coordinates, chunk) {
+                error!("Failed to unload the chunk {:?}: {:?}", coordinates, err);
+                return Err(EngineError::ShutdownFailed);
+            }
+            if let Err(err) = event_queue(EventCode::ChunkUnloaded {
+                x: coordinates.x,
+                z: coordinates.z,
+            }) {
+                warn!("Failed to queue the chunk unloaded event: {:?}", err);
+            }
+        }
+        Ok(())
+    }
+
+    /// Request the chunks around the given position and release the far away ones
+    /// Should be called once per frame
+    pub fn update(&mut self, position: glam::Vec3) -> Result<(), EngineError> {
+        self.receive_loaded_chunks()?;
+
+        let center = ChunkCoordinates::from_position(position, self.parameters.chunk_size);
+
+        // Unload far chunks
+        let far_chunks: Vec<ChunkCoordinates> = self
+            .chunks
+            .keys()
+            .filter(|coordinates| coordinates.distance(&center) > self.parameters.unload_radius)
+            .copied()
+            .collect();
+        for coordinates in far_chunks {
+            self.unload_chunk(coordinates)?;
+        }
+
+        // Request the missing chunks, the closest first
+        let radius = self.parameters.load_radius as i32;
+        let mut missing_chunks: Vec<ChunkCoordinates> = ((center.x - radius)..=(center.x + radius))
+            .flat_map(|x| ((center.z - radius)..=(center.z + radius)).map(move |z| (x, z)))
+            .map(|(x, z)| ChunkCoordinates { x, z })
+            .filter(|coordinates| !self.chunks.contains_key(coordinates))
+            .collect();
+        missing_chunks.sort_by_key(|coordinates| coordinates.distance(&center));
+        let available_loads = self
+            .parameters
+            .max_concurrent_loads
+            .saturating_sub(self.loading_count);
+        for coordinates in missing_chunks.into_iter().take(available_loads) {
+            self.chunks.insert(coordinates, ChunkState::Loading);
+            self.loading_count += 1;
+            let loader = Arc::clone(&self.loader);
+            let sender = self.sender.clone();
+            // The loaded chunks are received by the next update rather than by the completion
+            job_submit(
+                move || {
+                    // The streamer may have been dropped in the meantime
+                    let _ = sender.send((coordinates, loader.load(coordinates)));
+                    Ok(())
+                },
+                |_| Ok(()),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Unload every chunk, chunks still loading are discarded
+    pub fn clear(&mut self) -> Result<(), EngineError> {
+        let loaded_chunks: Vec<ChunkCoordinates> = self.chunks.keys().copied().collect();
+        for coordinates in loaded_chunks {
+            self.unload_chunk(coordinates)?;
+        }
+        self.chunks.clear();
+        self.loading_count = 0;
+        Ok(())
+    }
+}
+
+/// Content of a cell scene file, added to the scene graph once loaded
+/// The cells without a file are empty
+pub struct SceneCell {
+    /// Read on a worker thread, dropped once instantiated
+    scene: Option<GltfScene>,
+    pub instance: Option<GltfInstance>,
+}
+
+/// Loads the cells from the gltf files `cell_<x>_<z>.glb` or `cell_<x>_<z>.gltf` of a directory
+/// The cells are authored in world space
+pub struct SceneCellLoader {
+    pub directory: PathBuf,
+}
+
+impl SceneCellLoader {
+    pub fn get_cell_path(&self, coordinates: ChunkCoordinates) -> Option<PathBuf> {
+        ["glb", "gltf"]
+            .iter()
+            .map(|extension| {
+                self.directory.join(format!(
+                    "cell_{}_{}.{}",
+                    coordinates.x, coordinates.z, extension
+                ))
+            })
+            .find(|path| path.is_file())
+    }
+}
+
+impl ChunkLoader<SceneCell> for SceneCellLoader {
+    fn load(&self, coordinates: ChunkCoordinates) -> Result<SceneCell, EngineError> {
+        let scene = match self.get_cell_path(coordinates) {
+            Some(path) => Some(gltf_load(&path)?),
+            None => None,
+        };
+        Ok(SceneCell {
+            scene,
+            instance: None,
+        })
+    }
+
+    fn on_loaded(
+        &self,
+        _coordinates: ChunkCoordinates,
+        cell: &mut SceneCell,
+    ) -> Result<(), EngineError> {
+        if let Some(scene) = cell.scene.take() {
+            cell.instance = Some(renderer_instantiate_gltf(&scene, None)?);
+        }
+        Ok(())
+    }
+
+    fn unload(&self, _coordinates: ChunkCoordinates, cell: SceneCell) -> Result<(), EngineError> {
+        match cell.instance {
+            Some(instance) => renderer_unload_gltf(instance),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Cells of the scene streamed around the camera the frame is rendered from
+#[derive(Default)]
+pub(crate) struct SceneStreaming {
+    streamer: Option<ChunkStreamer<SceneCell>>,
+}
+
+pub(crate) static GLOBAL_SCENE_STREAMING: EngineGlobal<SceneStreaming> =
+    EngineGlobal::new("scene streaming");
+
+fn fetch_global_scene_streaming(
+    error: EngineError,
+) -> Result<EngineGlobalGuard<SceneStreaming>, EngineError> {
+    GLOBAL_SCENE_STREAMING.fetch(error)
+}
+
+/// Stream the cell scene files of a directory around the camera, replaces the cells streamed before
+pub fn scene_streaming_start(
+    directory: &Path,
+    parameters: ChunkStreamerParameters,
+) -> Result<(), EngineError> {
+    if !directory.is_dir() {
+        error!("The scene cells directory {:?} does not exist", directory);
+        return Err(EngineError::InvalidValue);
+    }
+    scene_streaming_stop()?;
+    let loader = SceneCellLoader {
+        directory: directory.to_path_buf(),
+    };
+    let streamer = ChunkStreamer::new(parameters, Arc::new(loader))?;
+    fetch_global_scene_streaming(EngineError::InitializationFailed)?.streamer = Some(streamer);
+    Ok(())
+}
+
+/// Unload every streamed cell
+pub fn scene_streaming_stop() -> Result<(), EngineError> {
+    let mut streaming = fetch_global_scene_streaming(EngineError::ShutdownFailed)?;
+    match streaming.streamer.take() {
+        Some(mut streamer) => streamer.clear(),
+        None => Ok(()),
+    }
+}
+
+/// Number of cells added to the scene
+pub fn scene_streaming_get_loaded_cell_count() -> Result<usize, EngineError> {
+    let streaming = fetch_global_scene_streaming(EngineError::AccessFailed)?;
+    Ok(streaming
+        .streamer
+        .as_ref()
+        .map_or(0, |streamer| streamer.get_loaded_chunks().count()))
+}
+
+/// Load and unload the cells around the render camera, once per frame
+pub(crate) fn scene_streaming_update() -> Result<(), EngineError> {
+    let mut streaming = fetch_global_scene_streaming(EngineError::UpdateFailed)?;
+    let streamer = match streaming.streamer.as_mut() {
+        Some(streamer) => streamer,
+        None => return Ok(()),
+    };
+    match renderer_get_render_camera_position()? {
+        Some(position) => streamer.update(position),
+        None => Ok(()),
+    }
+}
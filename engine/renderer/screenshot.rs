@@ -0,0 +1,114 @@
+use std::path::{Path, PathBuf};
+
+use crate::{core::debug::errors::EngineError, error};
+
+/// Pixels of a presented frame, already resolved when the world is multisampled
+#[derive(Clone, Debug, Default)]
+pub struct ScreenshotPixels {
+    pub width: u32,
+    pub height: u32,
+    /// Tightly packed rgba8 rows, top to bottom
+    pub pixels: Vec<u8>,
+}
+
+impl ScreenshotPixels {
+    /// The format is deduced from the extension, png by default
+    pub fn save(&self, path: &Path) -> Result<(), EngineError> {
+        if let Err(err) = image::save_buffer(
+            path,
+            &self.pixels,
+            self.width,
+            self.height,
+            image::ColorType::Rgba8,
+        ) {
+            error!("Failed to save the screenshot {:?}: {:?}", path, err);
+            return Err(EngineError::IO);
+        }
+        Ok(())
+    }
+}
+
+/// Linear hdr colors, e.g. the offscreen target before the tonemapping
+#[derive(Clone, Debug, Default)]
+pub struct HdrScreenshotPixels {
+    pub width: u32,
+    pub height: u32,
+    /// Top to bottom rows, the alpha is kept in the raw dump only
+    pub pixels: Vec<glam::Vec4>,
+}
+
+impl HdrScreenshotPixels {
+    /// Exposed then tonemapped to srgb, what the player would see
+    pub fn tonemap(&self, exposure: f32) -> ScreenshotPixels {
+        let pixels = self
+            .pixels
+            .iter()
+            .flat_map(|color| {
+                let color = screenshot_tonemap_aces(color.truncate() * exposure);
+                [
+                    screenshot_linear_to_srgb(color.x),
+                    screenshot_linear_to_srgb(color.y),
+                    screenshot_linear_to_srgb(color.z),
+                    255,
+                ]
+            })
+            .collect();
+        ScreenshotPixels {
+            width: self.width,
+            height: self.height,
+            pixels,
+        }
+    }
+
+    /// Unclamped linear values for the artists, the path should end with `.exr`
+    pub fn save_exr(&self, path: &Path) -> Result<(), EngineError> {
+        let values: Vec<f32> = self
+            .pixels
+            .iter()
+            .flat_map(|color| color.to_array())
+            .collect();
+        let buffer = match image::Rgba32FImage::from_raw(self.width, self.height, values) {
+            Some(buffer) => buffer,
+            None => {
+                error!(
+                    "Failed to save the hdr screenshot {:?}: {} pixels for a {}x{} image",
+                    path,
+                    self.pixels.len(),
+                    self.width,
+                    self.height
+                );
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        if let Err(err) = buffer.save_with_format(path, image::ImageFormat::OpenExr) {
+            error!("Failed to save the hdr screenshot {:?}: {:?}", path, err);
+            return Err(EngineError::IO);
+        }
+        Ok(())
+    }
+}
+
+/// Where the next presented frame is written
+#[derive(Clone, Debug, PartialEq)]
+pub struct ScreenshotRequest {
+    pub path: PathBuf,
+    /// Raw linear copy of the hdr target next to the image, see `HdrScreenshotPixels::save_exr`
+    pub hdr_path: Option<PathBuf>,
+}
+
+/// Filmic curve fitted by Krzysztof Narkowicz, maps [0, inf) to [0, 1)
+pub fn screenshot_tonemap_aces(color: glam::Vec3) -> glam::Vec3 {
+    let (a, b, c, d, e) = (2.51, 0.03, 2.43, 0.59, 0.14);
+    ((color * (a * color + b)) / (color * (c * color + d) + e))
+        .clamp(glam::Vec3::ZERO, glam::Vec3::ONE)
+}
+
+pub fn screenshot_linear_to_srgb(value: f32) -> u8 {
+    let value = value.clamp(0.0, 1.0);
+    let encoded = if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    };
+    (encoded * 255.0).round() as u8
+}
@@ -0,0 +1,195 @@
+use crate::renderer::scene::graph::SceneNodeId;
+
+use super::layout::UiRect;
+
+/// Number of panels of the debug workspace
+pub const DEBUG_WORKSPACE_PANEL_COUNT: usize = 4;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DebugPanelKind {
+    /// Gpu statistics and draw calls of the last frame
+    RendererStats,
+    /// Last logged messages
+    LogConsole,
+    /// Transform and renderable of the selected scene node
+    EntityInspector,
+    /// Loaded textures, materials, geometries and fonts
+    ResourceBrowser,
+}
+
+impl DebugPanelKind {
+    pub const ALL: [Self; DEBUG_WORKSPACE_PANEL_COUNT] = [
+        Self::RendererStats,
+        Self::LogConsole,
+        Self::EntityInspector,
+        Self::ResourceBrowser,
+    ];
+
+    pub fn get_title(&self) -> &'static str {
+        match self {
+            Self::RendererStats => "Renderer stats",
+            Self::LogConsole => "Log console",
+            Self::EntityInspector => "Entity inspector",
+            Self::ResourceBrowser => "Resource browser",
+        }
+    }
+}
+
+/// Where a panel is placed in the window
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DebugDockArea {
+    Left,
+    Right,
+    Bottom,
+    /// Not docked, drawn on top of the docked panels
+    Floating(UiRect),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DebugPanel {
+    pub kind: DebugPanelKind,
+    pub dock_area: DebugDockArea,
+    pub is_open: bool,
+}
+
+/// Size of the docking areas as fractions of the window
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct DebugWorkspaceLayout {
+    pub left_width: f32,
+    pub right_width: f32,
+    pub bottom_height: f32,
+}
+
+impl Default for DebugWorkspaceLayout {
+    fn default() -> Self {
+        Self {
+            left_width: 0.25,
+            right_width: 0.25,
+            bottom_height: 0.3,
+        }
+    }
+}
+
+/// Debug panels docked around the edges of the window, drawn on top of the game ui
+pub(crate) struct DebugWorkspace {
+    pub is_visible: bool,
+    pub layout: DebugWorkspaceLayout,
+    panels: [DebugPanel; DEBUG_WORKSPACE_PANEL_COUNT],
+    /// Node shown by the entity inspector
+    pub selected_node: Option<SceneNodeId>,
+}
+
+impl Default for DebugWorkspace {
+    fn default() -> Self {
+        let panel = |kind, dock_area| DebugPanel {
+            kind,
+            dock_area,
+            is_open: true,
+        };
+        Self {
+            is_visible: false,
+            layout: DebugWorkspaceLayout::default(),
+            panels: [
+                panel(DebugPanelKind::RendererStats, DebugDockArea::Left),
+                panel(DebugPanelKind::LogConsole, DebugDockArea::Bottom),
+                panel(DebugPanelKind::EntityInspector, DebugDockArea::Right),
+                panel(DebugPanelKind::ResourceBrowser, DebugDockArea::Right),
+            ],
+            selected_node: None,
+        }
+    }
+}
+
+impl DebugWorkspace {
+    fn get_panel_mut(&mut self, kind: DebugPanelKind) -> &mut DebugPanel {
+        self.panels
+            .iter_mut()
+            .find(|panel| panel.kind == kind)
+            .unwrap()
+    }
+
+    pub fn get_panel(&self, kind: DebugPanelKind) -> DebugPanel {
+        *self.panels.iter().find(|panel| panel.kind == kind).unwrap()
+    }
+
+    /// The panel is moved after the other panels of the area
+    pub fn dock_panel(&mut self, kind: DebugPanelKind, dock_area: DebugDockArea) {
+        self.get_panel_mut(kind).dock_area = dock_area;
+        let index = self
+            .panels
+            .iter()
+            .position(|panel| panel.kind == kind)
+            .unwrap();
+        self.panels[index..].rotate_left(1);
+    }
+
+    pub fn set_panel_open(&mut self, kind: DebugPanelKind, is_open: bool) {
+        self.get_panel_mut(kind).is_open = is_open;
+    }
+
+    /// Open panels docked in the area, in docking order
+    fn get_docked_panels(&self, dock_area: DebugDockArea) -> Vec<DebugPanelKind> {
+        self.panels
+            .iter()
+            .filter(|panel| panel.is_open && panel.dock_area == dock_area)
+            .map(|panel| panel.kind)
+            .collect()
+    }
+
+    /// Rectangles of the open panels, the panels of an area share it evenly
+    /// The side areas take the whole height when nothing is docked at the bottom
+    pub fn compute_panel_rects(&self, screen_size: glam::Vec2) -> Vec<(DebugPanelKind, UiRect)> {
+        let bottom_panels = self.get_docked_panels(DebugDockArea::Bottom);
+        let bottom_height = if bottom_panels.is_empty() {
+            0.0
+        } else {
+            screen_size.y * self.layout.bottom_height.clamp(0.0, 1.0)
+        };
+        let side_height = screen_size.y - bottom_height;
+
+        let mut rects = Vec::with_capacity(DEBUG_WORKSPACE_PANEL_COUNT);
+        // Side panels are stacked from the top
+        for (dock_area, x, width) in [
+            (
+                DebugDockArea::Left,
+                0.0,
+                screen_size.x * self.layout.left_width.clamp(0.0, 1.0),
+            ),
+            (
+                DebugDockArea::Right,
+                screen_size.x * (1.0 - self.layout.right_width.clamp(0.0, 1.0)),
+                screen_size.x * self.layout.right_width.clamp(0.0, 1.0),
+            ),
+        ] {
+            let panels = self.get_docked_panels(dock_area);
+            let height = side_height / panels.len().max(1) as f32;
+            for (index, kind) in panels.into_iter().enumerate() {
+                rects.push((
+                    kind,
+                    UiRect {
+                        position: glam::Vec2::new(x, index as f32 * height),
+                        size: glam::Vec2::new(width, height),
+                    },
+                ));
+            }
+        }
+        // Bottom panels are placed side by side
+        let width = screen_size.x / bottom_panels.len().max(1) as f32;
+        for (index, kind) in bottom_panels.into_iter().enumerate() {
+            rects.push((
+                kind,
+                UiRect {
+                    position: glam::Vec2::new(index as f32 * width, side_height),
+                    size: glam::Vec2::new(width, bottom_height),
+                },
+            ));
+        }
+        // Floating panels last so they are on top
+        for panel in &self.panels {
+            if let (true, DebugDockArea::Floating(rect)) = (panel.is_open, panel.dock_area) {
+                rects.push((panel.kind, rect));
+            }
+        }
+        rects
+    }
+}
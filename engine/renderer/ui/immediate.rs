@@ -0,0 +1,85 @@
+use std::f32::consts::TAU;
+
+use crate::renderer::renderer_types::ImmediateVertexData;
+
+/// Length in pixels of the segments approximating the circles
+pub const IMMEDIATE_CIRCLE_SEGMENT_LENGTH: f32 = 4.0;
+pub const IMMEDIATE_CIRCLE_MIN_SEGMENTS: usize = 8;
+pub const IMMEDIATE_CIRCLE_MAX_SEGMENTS: usize = 128;
+
+/// 2d shapes submitted during a frame, triangulated on the cpu and drawn at the end of the frame
+/// Positions are in screen pixels, from the top left corner
+#[derive(Default)]
+pub(crate) struct ImmediateBatch {
+    vertices: Vec<ImmediateVertexData>,
+}
+
+impl ImmediateBatch {
+    fn push_triangle(&mut self, points: [glam::Vec2; 3], color: glam::Vec4) {
+        self.vertices.extend(
+            points
+                .into_iter()
+                .map(|position| ImmediateVertexData { position, color }),
+        );
+    }
+
+    fn push_quad(&mut self, corners: [glam::Vec2; 4], color: glam::Vec4) {
+        self.push_triangle([corners[0], corners[1], corners[2]], color);
+        self.push_triangle([corners[0], corners[2], corners[3]], color);
+    }
+
+    /// Filled axis aligned rectangle, the position is its top left corner
+    pub fn draw_rect(&mut self, position: glam::Vec2, size: glam::Vec2, color: glam::Vec4) {
+        self.push_quad(
+            [
+                position,
+                position + glam::Vec2::new(0.0, size.y),
+                position + size,
+                position + glam::Vec2::new(size.x, 0.0),
+            ],
+            color,
+        );
+    }
+
+    /// Filled circle, big circles get more segments
+    pub fn draw_circle(&mut self, center: glam::Vec2, radius: f32, color: glam::Vec4) {
+        let segment_count = ((TAU * radius.abs() / IMMEDIATE_CIRCLE_SEGMENT_LENGTH).ceil()
+            as usize)
+            .clamp(IMMEDIATE_CIRCLE_MIN_SEGMENTS, IMMEDIATE_CIRCLE_MAX_SEGMENTS);
+        let point = |segment: usize| {
+            let angle = TAU * segment as f32 / segment_count as f32;
+            center + radius * glam::Vec2::new(angle.cos(), angle.sin())
+        };
+        for segment in 0..segment_count {
+            self.push_triangle([center, point(segment), point(segment + 1)], color);
+        }
+    }
+
+    /// Segment with the given thickness in pixels, its ends are not rounded
+    pub fn draw_line(
+        &mut self,
+        start: glam::Vec2,
+        end: glam::Vec2,
+        thickness: f32,
+        color: glam::Vec4,
+    ) {
+        let direction = (end - start).normalize_or_zero();
+        if direction == glam::Vec2::ZERO {
+            return;
+        }
+        let offset = direction.perp() * thickness * 0.5;
+        self.push_quad(
+            [start + offset, start - offset, end - offset, end + offset],
+            color,
+        );
+    }
+
+    pub fn get_vertices(&self) -> &[ImmediateVertexData] {
+        &self.vertices
+    }
+
+    /// Forget the shapes of the frame, the allocation is kept for the next one
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+    }
+}
@@ -0,0 +1,354 @@
+use crate::{core::debug::errors::EngineError, error};
+
+/// Handle to an element of the ui layout
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct UiElementId(usize);
+
+/// Screen space rectangle, the origin is the top left corner of the window
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct UiRect {
+    pub position: glam::Vec2,
+    pub size: glam::Vec2,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct UiPadding {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+impl UiPadding {
+    pub fn uniform(padding: f32) -> Self {
+        Self {
+            left: padding,
+            right: padding,
+            top: padding,
+            bottom: padding,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UiStackDirection {
+    Horizontal,
+    Vertical,
+}
+
+/// Placement of the children of a stack on the axis perpendicular to the stack
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum UiAlignment {
+    Start,
+    Center,
+    End,
+    /// Children take the whole cross axis
+    Stretch,
+}
+
+/// How an element places its children
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum UiContainer {
+    /// Children are placed with their own anchors
+    Free,
+    /// Children are placed one after the other, their anchors are ignored
+    Stack {
+        direction: UiStackDirection,
+        padding: UiPadding,
+        spacing: f32,
+        alignment: UiAlignment,
+    },
+}
+
+/// Placement of an element relative to its parent
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct UiElementParameters {
+    /// Point of the parent the element is attached to, (0, 0) is the top left corner
+    pub anchor: glam::Vec2,
+    /// Point of the element placed on the anchor, (0, 0) is the top left corner
+    pub pivot: glam::Vec2,
+    /// Offset from the anchor in pixels
+    pub offset: glam::Vec2,
+    /// Size in pixels
+    pub size: glam::Vec2,
+    /// Size added as a fraction of the parent size, (1, 1) fills the parent
+    pub relative_size: glam::Vec2,
+    pub container: UiContainer,
+}
+
+impl Default for UiElementParameters {
+    fn default() -> Self {
+        Self {
+            anchor: glam::Vec2::ZERO,
+            pivot: glam::Vec2::ZERO,
+            offset: glam::Vec2::ZERO,
+            size: glam::Vec2::ZERO,
+            relative_size: glam::Vec2::ZERO,
+            container: UiContainer::Free,
+        }
+    }
+}
+
+impl UiElementParameters {
+    pub fn anchor(mut self, anchor: glam::Vec2) -> Self {
+        self.anchor = anchor;
+        self
+    }
+
+    pub fn pivot(mut self, pivot: glam::Vec2) -> Self {
+        self.pivot = pivot;
+        self
+    }
+
+    pub fn offset(mut self, offset: glam::Vec2) -> Self {
+        self.offset = offset;
+        self
+    }
+
+    pub fn size(mut self, size: glam::Vec2) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn relative_size(mut self, relative_size: glam::Vec2) -> Self {
+        self.relative_size = relative_size;
+        self
+    }
+
+    pub fn container(mut self, container: UiContainer) -> Self {
+        self.container = container;
+        self
+    }
+
+    /// Same point for the anchor and the pivot, e.g. (1, 1) sticks to the bottom right corner
+    pub fn aligned(mut self, point: glam::Vec2) -> Self {
+        self.anchor = point;
+        self.pivot = point;
+        self
+    }
+
+    fn get_size(&self, parent_size: glam::Vec2, scale: f32) -> glam::Vec2 {
+        (self.size * scale + self.relative_size * parent_size).max(glam::Vec2::ZERO)
+    }
+}
+
+struct UiNode {
+    parent: Option<usize>,
+    children: Vec<usize>,
+    parameters: UiElementParameters,
+    rect: UiRect,
+}
+
+/// Tree of ui elements whose rectangles are recomputed when an element or the window changes
+pub struct UiLayout {
+    nodes: Vec<Option<UiNode>>,
+    free_nodes: Vec<usize>,
+    roots: Vec<usize>,
+    screen_size: glam::Vec2,
+    /// Accessibility scale applied to every size given in pixels
+    scale: f32,
+    is_dirty: bool,
+}
+
+impl Default for UiLayout {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            free_nodes: Vec::new(),
+            roots: Vec::new(),
+            screen_size: glam::Vec2::ZERO,
+            scale: 1.0,
+            is_dirty: false,
+        }
+    }
+}
+
+impl UiLayout {
+    fn get_node(&self, id: UiElementId) -> Result<&UiNode, EngineError> {
+        match self.nodes.get(id.0).and_then(|node| node.as_ref()) {
+            Some(node) => Ok(node),
+            None => {
+                error!("The ui element {:?} does not exist", id);
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    fn get_node_mut(&mut self, id: UiElementId) -> Result<&mut UiNode, EngineError> {
+        match self.nodes.get_mut(id.0).and_then(|node| node.as_mut()) {
+            Some(node) => Ok(node),
+            None => {
+                error!("The ui element {:?} does not exist", id);
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    /// Elements without parent are placed relative to the window
+    pub fn create_element(
+        &mut self,
+        parent: Option<UiElementId>,
+        parameters: UiElementParameters,
+    ) -> Result<UiElementId, EngineError> {
+        if let Some(parent) = parent {
+            self.get_node(parent)?;
+        }
+        let node = UiNode {
+            parent: parent.map(|parent| parent.0),
+            children: Vec::new(),
+            parameters,
+            rect: UiRect::default(),
+        };
+        let index = match self.free_nodes.pop() {
+            Some(index) => {
+                self.nodes[index] = Some(node);
+                index
+            }
+            None => {
+                self.nodes.push(Some(node));
+                self.nodes.len() - 1
+            }
+        };
+        match parent {
+            Some(parent) => self.nodes[parent.0].as_mut().unwrap().children.push(index),
+            None => self.roots.push(index),
+        }
+        self.is_dirty = true;
+        Ok(UiElementId(index))
+    }
+
+    /// Destroy an element and all of its children
+    pub fn destroy_element(&mut self, id: UiElementId) -> Result<(), EngineError> {
+        let siblings = match self.get_node(id)?.parent {
+            Some(parent) => &mut self.nodes[parent].as_mut().unwrap().children,
+            None => &mut self.roots,
+        };
+        siblings.retain(|sibling| *sibling != id.0);
+        let mut to_destroy = vec![id.0];
+        while let Some(index) = to_destroy.pop() {
+            if let Some(node) = self.nodes[index].take() {
+                to_destroy.extend(node.children);
+                self.free_nodes.push(index);
+            }
+        }
+        self.is_dirty = true;
+        Ok(())
+    }
+
+    pub fn set_element_parameters(
+        &mut self,
+        id: UiElementId,
+        parameters: UiElementParameters,
+    ) -> Result<(), EngineError> {
+        self.get_node_mut(id)?.parameters = parameters;
+        self.is_dirty = true;
+        Ok(())
+    }
+
+    pub fn get_element_parameters(
+        &self,
+        id: UiElementId,
+    ) -> Result<UiElementParameters, EngineError> {
+        Ok(self.get_node(id)?.parameters)
+    }
+
+    /// Rectangle computed by the last reflow
+    pub fn get_element_rect(&self, id: UiElementId) -> Result<UiRect, EngineError> {
+        Ok(self.get_node(id)?.rect)
+    }
+
+    /// Called when the window is resized
+    pub fn set_screen_size(&mut self, width: u32, height: u32) {
+        self.screen_size = glam::Vec2::new(width as f32, height as f32);
+        self.is_dirty = true;
+    }
+
+    pub fn get_screen_size(&self) -> glam::Vec2 {
+        self.screen_size
+    }
+
+    pub fn set_scale(&mut self, scale: f32) {
+        self.scale = scale;
+        self.is_dirty = true;
+    }
+
+    pub fn get_scale(&self) -> f32 {
+        self.scale
+    }
+
+    /// Recompute the rectangles if anything changed since the last reflow
+    pub fn update(&mut self) {
+        if !self.is_dirty {
+            return;
+        }
+        let screen = UiRect {
+            position: glam::Vec2::ZERO,
+            size: self.screen_size,
+        };
+        let roots = self.roots.clone();
+        self.place_children(&roots, screen, UiContainer::Free);
+        self.is_dirty = false;
+    }
+
+    fn place_children(&mut self, children: &[usize], parent: UiRect, container: UiContainer) {
+        match container {
+            UiContainer::Free => {
+                for child in children {
+                    let parameters = self.nodes[*child].as_ref().unwrap().parameters;
+                    let size = parameters.get_size(parent.size, self.scale);
+                    let position = parent.position + parameters.anchor * parent.size
+                        - parameters.pivot * size
+                        + parameters.offset * self.scale;
+                    self.place(*child, UiRect { position, size });
+                }
+            }
+            UiContainer::Stack {
+                direction,
+                padding,
+                spacing,
+                alignment,
+            } => {
+                let scale = self.scale;
+                let content = UiRect {
+                    position: parent.position + glam::Vec2::new(padding.left, padding.top) * scale,
+                    size: (parent.size
+                        - glam::Vec2::new(
+                            padding.left + padding.right,
+                            padding.top + padding.bottom,
+                        ) * scale)
+                        .max(glam::Vec2::ZERO),
+                };
+                // Index of the stacking axis and of the cross axis
+                let (main, cross) = match direction {
+                    UiStackDirection::Horizontal => (0, 1),
+                    UiStackDirection::Vertical => (1, 0),
+                };
+                let mut cursor = content.position[main];
+                for child in children {
+                    let parameters = self.nodes[*child].as_ref().unwrap().parameters;
+                    let mut size = parameters.get_size(content.size, scale);
+                    let mut position = content.position;
+                    position[main] = cursor;
+                    match alignment {
+                        UiAlignment::Start => (),
+                        UiAlignment::Center => {
+                            position[cross] += (content.size[cross] - size[cross]) * 0.5
+                        }
+                        UiAlignment::End => position[cross] += content.size[cross] - size[cross],
+                        UiAlignment::Stretch => size[cross] = content.size[cross],
+                    }
+                    cursor += size[main] + spacing * scale;
+                    self.place(*child, UiRect { position, size });
+                }
+            }
+        }
+    }
+
+    fn place(&mut self, index: usize, rect: UiRect) {
+        let node = self.nodes[index].as_mut().unwrap();
+        node.rect = rect;
+        let children = node.children.clone();
+        let container = node.parameters.container;
+        self.place_children(&children, rect, container);
+    }
+}
@@ -0,0 +1,6 @@
+pub(crate) mod debug_workspace;
+pub(crate) mod immediate;
+pub(crate) mod layout;
+pub(crate) mod nine_slice;
+pub(crate) mod overlay;
+pub(crate) mod text;
@@ -0,0 +1,142 @@
+use crate::{core::debug::errors::EngineError, error, renderer::renderer_types::VertexData};
+
+/// Size in pixels of the borders of a texture that must not be stretched
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NineSliceInsets {
+    pub left: f32,
+    pub right: f32,
+    pub top: f32,
+    pub bottom: f32,
+}
+
+impl NineSliceInsets {
+    pub fn uniform(inset: f32) -> Self {
+        Self {
+            left: inset,
+            right: inset,
+            top: inset,
+            bottom: inset,
+        }
+    }
+}
+
+/// Panel whose corners keep their size while the edges and center stretch
+#[derive(Clone, Copy, Debug)]
+pub struct NineSlicePanel {
+    /// Top left corner in screen pixels
+    pub position: glam::Vec2,
+    /// Size in screen pixels
+    pub size: glam::Vec2,
+    /// Size in pixels of the texture
+    pub texture_size: glam::Vec2,
+    pub insets: NineSliceInsets,
+    pub depth: f32,
+}
+
+impl NineSlicePanel {
+    pub fn new(texture_size: glam::Vec2, insets: NineSliceInsets) -> Self {
+        Self {
+            position: glam::Vec2::ZERO,
+            size: texture_size,
+            texture_size,
+            insets,
+            depth: 0.0,
+        }
+    }
+
+    pub fn position(mut self, position: glam::Vec2) -> Self {
+        self.position = position;
+        self
+    }
+
+    pub fn size(mut self, size: glam::Vec2) -> Self {
+        self.size = size;
+        self
+    }
+
+    pub fn depth(mut self, depth: f32) -> Self {
+        self.depth = depth;
+        self
+    }
+
+    /// Returns the 16 vertices and 54 indices of the 9 quads
+    pub(crate) fn build_geometry(&self) -> Result<(Vec<VertexData>, Vec<u32>), EngineError> {
+        let insets = &self.insets;
+        if self.texture_size.x <= 0.0 || self.texture_size.y <= 0.0 {
+            error!("Can't build a 9-slice panel from an empty texture");
+            return Err(EngineError::InvalidValue);
+        }
+        if insets.left + insets.right > self.texture_size.x
+            || insets.top + insets.bottom > self.texture_size.y
+        {
+            error!(
+                "The 9-slice insets {:?} don't fit in a {}x{} texture",
+                insets, self.texture_size.x, self.texture_size.y
+            );
+            return Err(EngineError::InvalidValue);
+        }
+
+        // Panels smaller than their borders shrink the borders instead of overlapping them
+        let horizontal_scale =
+            (self.size.x / (insets.left + insets.right).max(f32::EPSILON)).min(1.0);
+        let vertical_scale =
+            (self.size.y / (insets.top + insets.bottom).max(f32::EPSILON)).min(1.0);
+
+        let xs = [
+            self.position.x,
+            self.position.x + insets.left * horizontal_scale,
+            self.position.x + self.size.x - insets.right * horizontal_scale,
+            self.position.x + self.size.x,
+        ];
+        let ys = [
+            self.position.y,
+            self.position.y + insets.top * vertical_scale,
+            self.position.y + self.size.y - insets.bottom * vertical_scale,
+            self.position.y + self.size.y,
+        ];
+        let us = [
+            0.0,
+            insets.left / self.texture_size.x,
+            1.0 - insets.right / self.texture_size.x,
+            1.0,
+        ];
+        let vs = [
+            0.0,
+            insets.top / self.texture_size.y,
+            1.0 - insets.bottom / self.texture_size.y,
+            1.0,
+        ];
+
+        let mut vertices = Vec::with_capacity(16);
+        for row in 0..4 {
+            for column in 0..4 {
+                vertices.push(VertexData {
+                    position: glam::Vec3::new(xs[column], ys[row], self.depth),
+                    texture: glam::Vec2::new(us[column], vs[row]),
+                    lightmap_texture: glam::Vec2::ZERO,
+                    normal: glam::Vec3::Z,
+                    tangent: glam::Vec4::new(1.0, 0.0, 0.0, 1.0),
+                });
+            }
+        }
+
+        let mut indices = Vec::with_capacity(54);
+        for row in 0..3 {
+            for column in 0..3 {
+                let top_left = (row * 4 + column) as u32;
+                let top_right = top_left + 1;
+                let bottom_left = top_left + 4;
+                let bottom_right = bottom_left + 1;
+                indices.extend_from_slice(&[
+                    top_left,
+                    bottom_left,
+                    bottom_right,
+                    top_left,
+                    bottom_right,
+                    top_right,
+                ]);
+            }
+        }
+        Ok((vertices, indices))
+    }
+}
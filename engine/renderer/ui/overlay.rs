@@ -0,0 +1,151 @@
+use crate::{
+    core::debug::errors::EngineError, renderer::renderer_types::UiVertexData,
+    resources::texture_system::TextureHandle,
+};
+
+use super::nine_slice::NineSlicePanel;
+
+/// Textured rectangle of the ui overlay, in screen pixels from the top left corner
+#[derive(Clone, Copy, Debug)]
+pub struct UiQuad {
+    /// Top left corner
+    pub position: glam::Vec2,
+    pub size: glam::Vec2,
+    /// Texture coordinates of the top left corner
+    pub uv_min: glam::Vec2,
+    /// Texture coordinates of the bottom right corner
+    pub uv_max: glam::Vec2,
+    /// Multiplied with the texture
+    pub color: glam::Vec4,
+    /// Untextured quads are plain colored
+    pub texture: Option<TextureHandle>,
+}
+
+impl UiQuad {
+    pub fn new(position: glam::Vec2, size: glam::Vec2) -> Self {
+        Self {
+            position,
+            size,
+            uv_min: glam::Vec2::ZERO,
+            uv_max: glam::Vec2::ONE,
+            color: glam::Vec4::ONE,
+            texture: None,
+        }
+    }
+
+    pub fn uv(mut self, uv_min: glam::Vec2, uv_max: glam::Vec2) -> Self {
+        self.uv_min = uv_min;
+        self.uv_max = uv_max;
+        self
+    }
+
+    pub fn color(mut self, color: glam::Vec4) -> Self {
+        self.color = color;
+        self
+    }
+
+    pub fn texture(mut self, texture: TextureHandle) -> Self {
+        self.texture = Some(texture);
+        self
+    }
+}
+
+/// Consecutive vertices sampling the same texture, drawn with a single draw call
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct UiOverlayBatch {
+    /// None for the plain colored quads
+    pub texture: Option<TextureHandle>,
+    pub first_vertex: u32,
+    pub vertex_count: u32,
+}
+
+/// Ui quads submitted during a frame, drawn in order on top of the scene at the end of the frame
+#[derive(Default)]
+pub(crate) struct UiOverlay {
+    vertices: Vec<UiVertexData>,
+    batches: Vec<UiOverlayBatch>,
+}
+
+impl UiOverlay {
+    /// Add triangles to the last batch, or start a new one if the texture changes
+    fn push_vertices(
+        &mut self,
+        texture: Option<TextureHandle>,
+        vertices: impl IntoIterator<Item = UiVertexData>,
+    ) {
+        let first_vertex = self.vertices.len() as u32;
+        self.vertices.extend(vertices);
+        let vertex_count = self.vertices.len() as u32 - first_vertex;
+        match self.batches.last_mut() {
+            Some(batch) if batch.texture == texture => batch.vertex_count += vertex_count,
+            _ => self.batches.push(UiOverlayBatch {
+                texture,
+                first_vertex,
+                vertex_count,
+            }),
+        }
+    }
+
+    pub fn draw_quad(&mut self, quad: &UiQuad) {
+        let corner = |factor: glam::Vec2| UiVertexData {
+            position: quad.position + quad.size * factor,
+            texture: quad.uv_min + (quad.uv_max - quad.uv_min) * factor,
+            color: quad.color,
+        };
+        let top_left = corner(glam::Vec2::new(0.0, 0.0));
+        let bottom_left = corner(glam::Vec2::new(0.0, 1.0));
+        let bottom_right = corner(glam::Vec2::new(1.0, 1.0));
+        let top_right = corner(glam::Vec2::new(1.0, 0.0));
+        self.push_vertices(
+            quad.texture,
+            [
+                top_left,
+                bottom_left,
+                bottom_right,
+                top_left,
+                bottom_right,
+                top_right,
+            ],
+        );
+    }
+
+    /// Stretchable panel, its depth is ignored as the overlay is drawn in submission order
+    pub fn draw_nine_slice(
+        &mut self,
+        panel: &NineSlicePanel,
+        texture: Option<TextureHandle>,
+        color: glam::Vec4,
+    ) -> Result<(), EngineError> {
+        let (vertices, indices) = panel.build_geometry()?;
+        self.push_vertices(
+            texture,
+            indices.into_iter().map(|index| {
+                let vertex = &vertices[index as usize];
+                UiVertexData {
+                    position: vertex.position.truncate(),
+                    texture: vertex.texture,
+                    color,
+                }
+            }),
+        );
+        Ok(())
+    }
+
+    pub fn get_vertices(&self) -> &[UiVertexData] {
+        &self.vertices
+    }
+
+    pub fn get_batches(&self) -> &[UiOverlayBatch] {
+        &self.batches
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+
+    /// Forget the quads of the frame, the allocations are kept for the next one
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.batches.clear();
+    }
+}
@@ -0,0 +1,70 @@
+use crate::resources::font::BitmapFont;
+
+use super::overlay::UiQuad;
+
+/// Walk the glyphs of the text, calling `on_glyph` with the pen position of every drawn glyph
+/// Returns the size of the text, in pixels of the font
+fn text_layout(
+    font: &BitmapFont,
+    text: &str,
+    mut on_glyph: impl FnMut(char, glam::Vec2),
+) -> glam::Vec2 {
+    let mut pen = glam::Vec2::ZERO;
+    let mut width: f32 = 0.0;
+    let mut previous = None;
+    for character in text.chars() {
+        if character == '\n' {
+            width = width.max(pen.x);
+            pen = glam::Vec2::new(0.0, pen.y + font.line_height);
+            previous = None;
+            continue;
+        }
+        let glyph = match font.get_glyph(character) {
+            Some(glyph) => glyph,
+            None => continue,
+        };
+        if let Some(previous) = previous {
+            pen.x += font.get_kerning(previous, character);
+        }
+        on_glyph(character, pen);
+        pen.x += glyph.x_advance;
+        previous = Some(character);
+    }
+    glam::Vec2::new(width.max(pen.x), pen.y + font.line_height)
+}
+
+/// Size in screen pixels of the text drawn with the font at the given scale
+pub fn text_measure(font: &BitmapFont, text: &str, scale: f32) -> glam::Vec2 {
+    text_layout(font, text, |_, _| ()) * scale
+}
+
+/// Quads of the glyphs of the text, the position is the top left corner of the first line
+/// The missing characters are drawn as question marks, or skipped if the font has none
+pub fn text_build_quads(
+    font: &BitmapFont,
+    position: glam::Vec2,
+    text: &str,
+    color: glam::Vec4,
+    scale: f32,
+) -> Vec<UiQuad> {
+    let atlas_size = glam::Vec2::new(font.atlas_width as f32, font.atlas_height as f32);
+    let mut quads = Vec::with_capacity(text.len());
+    text_layout(font, text, |character, pen| {
+        let glyph = font.get_glyph(character).unwrap();
+        if glyph.width == 0 || glyph.height == 0 {
+            return;
+        }
+        let glyph_position = glam::Vec2::new(glyph.x as f32, glyph.y as f32);
+        let glyph_size = glam::Vec2::new(glyph.width as f32, glyph.height as f32);
+        let offset = glam::Vec2::new(glyph.x_offset, glyph.y_offset);
+        let mut quad = UiQuad::new(position + (pen + offset) * scale, glyph_size * scale)
+            .uv(
+                glyph_position / atlas_size,
+                (glyph_position + glyph_size) / atlas_size,
+            )
+            .color(color);
+        quad.texture = font.texture;
+        quads.push(quad);
+    });
+    quads
+}
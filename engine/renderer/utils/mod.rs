@@ -1,2 +1,3 @@
-pub mod color;
-pub mod render_area;
+pub(crate) mod color;
+pub(crate) mod parallel;
+pub(crate) mod render_area;
@@ -0,0 +1,33 @@
+use std::{num::NonZeroUsize, thread};
+
+/// Below this number of items per thread, spawning threads costs more than it saves
+pub const PARALLEL_MIN_CHUNK_SIZE: usize = 1024;
+
+/// Map every item on several threads when there are enough of them, the order is kept
+pub fn parallel_map<T, R, F>(items: &[T], function: F) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+    F: Fn(&T) -> R + Sync,
+{
+    let thread_count = thread::available_parallelism()
+        .map(NonZeroUsize::get)
+        .unwrap_or(1)
+        .min(items.len() / PARALLEL_MIN_CHUNK_SIZE);
+    if thread_count <= 1 {
+        return items.iter().map(function).collect();
+    }
+
+    let chunk_size = items.len().div_ceil(thread_count);
+    let function = &function;
+    thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || chunk.iter().map(function).collect::<Vec<R>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect()
+    })
+}
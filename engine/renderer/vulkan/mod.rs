@@ -1,5 +1,5 @@
-pub mod vulkan_backend;
-pub mod vulkan_init;
-pub mod vulkan_shaders;
-pub mod vulkan_types;
-pub mod vulkan_utils;
+pub(crate) mod vulkan_backend;
+pub(crate) mod vulkan_init;
+pub(crate) mod vulkan_shaders;
+pub(crate) mod vulkan_types;
+pub(crate) mod vulkan_utils;
@@ -1,17 +1,50 @@
-use ash::vk::{Extent2D, Fence, PipelineStageFlags, Rect2D, SubmitInfo, Viewport};
+use ash::vk::{Fence, PipelineStageFlags, SubmitInfo};
 
 use crate::{
-    core::debug::errors::EngineError,
+    core::{application::windows::WindowHandle, debug::errors::EngineError},
     error,
     platforms::platform::Platform,
-    renderer::{renderer_backend::RendererBackend, renderer_types::GeometryRenderData},
+    renderer::{
+        lighting::{LightingSystem, LIGHTING_MAX_POINT_LIGHTS},
+        post_process::PostProcessSettings,
+        reflection_probe::EnvironmentMap,
+        renderer_backend::RendererBackend,
+        renderer_types::{
+            AntiAliasing, CameraPassTarget, ComputeBinding, DepthStencilSettings, DisplayOutput,
+            GeometryRenderData, ImmediateVertexData, OutputColorSpace, RendererStatistics,
+            UiRenderData, UiVertexData, VertexData, VsyncMode,
+        },
+        scene::camera::CameraViewport,
+        screenshot::{HdrScreenshotPixels, ScreenshotPixels},
+    },
+    resources::{material::MaterialRenderState, texture_system::TextureSystem},
 };
 
-use super::{vulkan_types::VulkanRendererBackend, vulkan_utils::texture::Texture};
+use super::{
+    vulkan_init::query_pools::{
+        VULKAN_TIMESTAMP_FRAME_BEGIN, VULKAN_TIMESTAMP_FRAME_END, VULKAN_TIMESTAMP_UI_PASS_BEGIN,
+    },
+    vulkan_types::VulkanRendererBackend,
+    vulkan_utils::{
+        camera_pass::{camera_passes_get_windows, VulkanCameraTarget},
+        geometry::Geometry,
+        texture::Texture,
+    },
+};
 
 impl RendererBackend for VulkanRendererBackend<'_> {
-    fn init(&mut self, application_name: &str, platform: &dyn Platform) -> Result<(), EngineError> {
-        self.vulkan_init(application_name, platform)?;
+    fn init(
+        &mut self,
+        application_name: &str,
+        platform: &dyn Platform,
+        anti_aliasing: AntiAliasing,
+        display_output: DisplayOutput,
+        depth_stencil: &DepthStencilSettings,
+    ) -> Result<(), EngineError> {
+        self.display_output = display_output;
+        self.depth_stencil = depth_stencil.clone();
+        self.render_scale = 1.0;
+        self.vulkan_init(application_name, platform, anti_aliasing)?;
         Ok(())
     }
 
@@ -25,10 +58,37 @@ impl RendererBackend for VulkanRendererBackend<'_> {
         Ok(())
     }
 
+    fn set_vsync_mode(&mut self, mode: VsyncMode) -> Result<(), EngineError> {
+        if self.vsync_mode == mode {
+            return Ok(());
+        }
+        self.vsync_mode = mode;
+        if let Err(err) = self.swapchain_recreate() {
+            error!(
+                "Failed to recreate the vulkan swapchain with the vsync {:?}: {:?}",
+                mode, err
+            );
+            return Err(EngineError::UpdateFailed);
+        }
+        Ok(())
+    }
+
+    fn set_render_scale(&mut self, scale: f32) -> Result<(), EngineError> {
+        // Used from the next frame, the graph of the current one is already declared
+        self.render_scale = scale;
+        Ok(())
+    }
+
+    fn get_output_color_space(&self) -> Result<OutputColorSpace, EngineError> {
+        Ok(self.output_color_space)
+    }
+
     fn begin_frame(&mut self, delta_time: f64) -> Result<bool, EngineError> {
         self.frame_delta_time = delta_time;
+        // Dropped with the frame if it doesn't begin
+        self.camera_passes_begin_frame();
 
-        if self.context.has_framebuffer_been_resized {
+        if self.context.frame.has_framebuffer_been_resized {
             if let Err(err) = self.swapchain_recreate() {
                 error!(
                     "Failed to recreate the vulkan swapchain when beginning a new frame: {:?}",
@@ -36,12 +96,16 @@ impl RendererBackend for VulkanRendererBackend<'_> {
                 );
                 return Err(EngineError::Unknown);
             }
-            self.context.has_framebuffer_been_resized = false;
+            self.context.frame.has_framebuffer_been_resized = false;
             return Ok(false);
         }
 
+        // Swap the pipelines whose shaders were edited before recording the frame
+        #[cfg(debug_assertions)]
+        self.builtin_shaders_hot_reload(delta_time)?;
+
         // Wait for the execution of the current frame to complete. The fence being free will allow this one to move on
-        let current_frame_index = self.context.current_frame as usize;
+        let current_frame_index = self.context.frame.current_frame as usize;
         let current_image_fence =
             &self.get_sync_structures()?.in_flight_fences[current_frame_index];
         let device = self.get_device()?;
@@ -51,6 +115,72 @@ impl RendererBackend for VulkanRendererBackend<'_> {
                 "Failed to wait for the current image fence when beginning a new frame: {:?}",
                 err
             );
+            if let EngineError::DeviceLost = err {
+                return Err(self.on_device_lost());
+            }
+            return Err(EngineError::Unknown);
+        }
+
+        // The previous use of this frame is done, its instance buffer can be rewritten
+        if let Err(err) = self.object_shaders_reset_instances() {
+            error!(
+                "Failed to reset the object instances when beginning a new frame: {:?}",
+                err
+            );
+            return Err(EngineError::Unknown);
+        }
+
+        // The previous use of this frame is done, its per object uniform buffer can be rewritten
+        if let Err(err) = self.object_shaders_reset_uniforms() {
+            error!(
+                "Failed to reset the object uniforms when beginning a new frame: {:?}",
+                err
+            );
+            return Err(EngineError::Unknown);
+        }
+
+        // The previous use of this frame is done, the semaphores of the uploads it waited on can be reused
+        if let Err(err) = self.transfer_frame_completed(current_frame_index) {
+            error!(
+                "Failed to complete the uploads when beginning a new frame: {:?}",
+                err
+            );
+            return Err(EngineError::Unknown);
+        }
+
+        // The previous use of this frame is done, the textures replaced before it can be destroyed
+        if let Err(err) = self.retired_textures_frame_completed(current_frame_index) {
+            error!(
+                "Failed to destroy the retired textures when beginning a new frame: {:?}",
+                err
+            );
+            return Err(EngineError::Unknown);
+        }
+
+        // The previous use of this frame is done, its screenshot can be read back
+        if let Err(err) = self.screenshot_frame_completed(current_frame_index) {
+            error!(
+                "Failed to read the screenshot when beginning a new frame: {:?}",
+                err
+            );
+            return Err(EngineError::Unknown);
+        }
+
+        // The previous use of this frame is done, its luminance downsample can be read back
+        if let Err(err) = self.luminance_readback_frame_completed(current_frame_index) {
+            error!(
+                "Failed to read the luminance when beginning a new frame: {:?}",
+                err
+            );
+            return Err(EngineError::Unknown);
+        }
+
+        // The previous use of this frame is done, its queries can be read back
+        if let Err(err) = self.query_pools_collect() {
+            error!(
+                "Failed to collect the query results when beginning a new frame: {:?}",
+                err
+            );
             return Err(EngineError::Unknown);
         }
 
@@ -62,7 +192,7 @@ impl RendererBackend for VulkanRendererBackend<'_> {
         let next_image_index =
             self.get_swapchain_next_image_index(timeout, image_available_semaphore, Fence::null())?;
         if let Some(index) = next_image_index {
-            self.context.image_index = index;
+            self.context.frame.image_index = index;
         } else {
             if let Err(err) = self.swapchain_recreate() {
                 error!("Failed to recreate the vulkan swapchain when acquiring a wrong image at the beginning of a new frame: {:?}", err);
@@ -81,8 +211,18 @@ impl RendererBackend for VulkanRendererBackend<'_> {
             return Err(EngineError::InitializationFailed);
         }
 
+        // The windows drawn by the cameras of the frame are presented along with the main one
+        let windows = camera_passes_get_windows(&self.context.frame.camera_passes);
+        if let Err(err) = self.window_swapchains_acquire(current_frame_index, &windows) {
+            error!(
+                "Failed to acquire the window images when beginning a new frame: {:?}",
+                err
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+
         // Begin recording commands
-        let command_buffer = &self.context.graphics_command_buffers[current_frame_index];
+        let command_buffer = &self.context.frame.graphics_command_buffers[current_frame_index];
         let device = self.get_device()?;
         if let Err(err) = command_buffer.reset(device) {
             error!(
@@ -99,51 +239,149 @@ impl RendererBackend for VulkanRendererBackend<'_> {
             return Err(EngineError::InitializationFailed);
         }
 
-        // Begin the render pass
-        let image_index = self.context.image_index as usize;
-        let framebuffer = &self.get_swapchain()?.framebuffers[image_index];
-        if let Err(err) = self.renderpass_begin(command_buffer, *framebuffer.handler.as_ref()) {
+        // Queries must be reset outside of the render pass
+        if let Err(err) = self.query_pools_reset() {
+            error!(
+                "Failed to reset the queries when beginning a new frame: {:?}",
+                err
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+        if let Err(err) = self.query_pools_write_timestamp(
+            VULKAN_TIMESTAMP_FRAME_BEGIN,
+            PipelineStageFlags::TOP_OF_PIPE,
+        ) {
             error!(
-                "Failed to begin the renderpass when beginning a new frame: {:?}",
+                "Failed to write the frame timestamp when beginning a new frame: {:?}",
                 err
             );
             return Err(EngineError::InitializationFailed);
         }
 
-        // Dynamic viewport
-        let render_area = self.get_renderpass()?.render_area;
-        let viewport = [Viewport::default()
-            .x(0.)
-            .y(render_area.height)
-            .width(render_area.width)
-            .height(-render_area.height)
-            .min_depth(0.)
-            .max_depth(1.)];
-        unsafe { device.cmd_set_viewport(*command_buffer.handler.as_ref(), 0, &viewport) };
-
-        // Dynamic scissor
-        let scissor = [Rect2D::default().extent(Extent2D {
-            width: self.framebuffer_width,
-            height: self.framebuffer_height,
-        })];
-        let device = self.get_device()?;
-        unsafe { device.cmd_set_scissor(*command_buffer.handler.as_ref(), 0, &scissor) };
+        // The render graph copies the hdr attachment into it after the world pass
+        if let Err(err) = self.screenshot_prepare_hdr_copy(current_frame_index) {
+            error!(
+                "Failed to prepare the hdr screenshot when beginning a new frame: {:?}",
+                err
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+
+        self.luminance_readback_begin_frame(current_frame_index);
+
+        // The render graph leaves the first camera pass or the world pass open, the ui pass begins once the world is over
+        self.context.frame.has_ui_pass_begun = false;
+        if let Err(err) = self.render_graph_begin_frame() {
+            error!(
+                "Failed to begin the render graph when beginning a new frame: {:?}",
+                err
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+        if self.render_graph_get_open_camera_pass().is_none() {
+            self.begin_world_pass()?;
+        }
 
         Ok(true)
     }
 
+    fn end_camera_pass(&mut self) -> Result<(), EngineError> {
+        if self.render_graph_get_open_camera_pass().is_none() {
+            error!("No vulkan camera pass is open");
+            return Err(EngineError::InvalidValue);
+        }
+        // Copies the camera into its texture or its window, then opens the next camera pass or the world pass
+        if let Err(err) = self.render_graph_execute() {
+            error!("Failed to end the vulkan camera pass: {:?}", err);
+            return Err(EngineError::UpdateFailed);
+        }
+        if self.render_graph_get_open_camera_pass().is_none() {
+            self.begin_world_pass()?;
+        }
+        Ok(())
+    }
+
+    fn add_camera_pass(
+        &mut self,
+        target: CameraPassTarget<'_>,
+        viewport: Option<CameraViewport>,
+    ) -> Result<(), EngineError> {
+        self.camera_pass_add(target, viewport)
+    }
+
+    fn create_window_surface(
+        &mut self,
+        window: WindowHandle,
+        platform: &dyn Platform,
+    ) -> Result<(), EngineError> {
+        self.window_swapchain_create(window, platform)
+    }
+
+    fn destroy_window_surface(&mut self, window: WindowHandle) -> Result<(), EngineError> {
+        // The passes declared for the next frame can't draw into it anymore
+        self.context
+            .frame
+            .pending_camera_passes
+            .retain(|pass| match pass.target {
+                VulkanCameraTarget::Window { window: other, .. } => other != window,
+                VulkanCameraTarget::Texture { .. } => true,
+            });
+        self.window_swapchain_destroy(window)
+    }
+
+    fn set_world_viewport(&mut self, viewport: Option<CameraViewport>) -> Result<(), EngineError> {
+        if self.render_graph_get_open_camera_pass().is_some()
+            || self.context.frame.has_ui_pass_begun
+        {
+            error!("The vulkan world viewport can only be set while the world pass is open");
+            return Err(EngineError::InvalidValue);
+        }
+        self.set_world_viewport_area(viewport)
+    }
+
     fn end_frame(&mut self, delta_time: f64) -> Result<(), EngineError> {
-        let current_frame_index = self.context.current_frame as usize;
+        let current_frame_index = self.context.frame.current_frame as usize;
 
-        // End renderpass
-        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
-        if let Err(err) = self.renderpass_end(command_buffer) {
+        // The world must be post processed into the swapchain image before ending the frame
+        if !self.context.frame.has_ui_pass_begun {
+            if let Err(err) = self.begin_ui_pass() {
+                error!(
+                    "Failed to begin the ui pass when ending a new frame: {:?}",
+                    err
+                );
+                return Err(EngineError::ShutdownFailed);
+            }
+        }
+
+        // End the ui pass and the passes left in the render graph
+        if let Err(err) = self.render_graph_execute() {
+            error!(
+                "Failed to finish the render graph when ending a new frame: {:?}",
+                err
+            );
+            return Err(EngineError::ShutdownFailed);
+        }
+        if let Err(err) = self.query_pools_write_timestamp(
+            VULKAN_TIMESTAMP_FRAME_END,
+            PipelineStageFlags::BOTTOM_OF_PIPE,
+        ) {
+            error!(
+                "Failed to write the frame timestamp when ending a new frame: {:?}",
+                err
+            );
+            return Err(EngineError::ShutdownFailed);
+        }
+
+        // The presented image is final once the render graph is over
+        if let Err(err) = self.screenshot_record_copy(current_frame_index) {
             error!(
-                "Failed to end the renderpass when ending a new frame: {:?}",
+                "Failed to copy the screenshot when ending a new frame: {:?}",
                 err
             );
             return Err(EngineError::ShutdownFailed);
         }
+
+        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
         let device = self.get_device()?;
         if let Err(err) = command_buffer.end(device) {
             error!(
@@ -154,19 +392,81 @@ impl RendererBackend for VulkanRendererBackend<'_> {
         }
 
         // Submit the queue and wait for the operation to complete
-        let command_buffers = [*command_buffer.handler.as_ref()];
-        let signal_semaphores =
-            [self.get_sync_structures()?.queue_complete_semaphores[current_frame_index]];
-        let wait_semaphores =
-            [self.get_sync_structures()?.image_available_semaphores[current_frame_index]];
-        let wait_dst_stage_mask = [PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT];
+        let mut signal_semaphores =
+            vec![self.get_sync_structures()?.queue_complete_semaphores[current_frame_index]];
+        let mut wait_semaphores =
+            vec![self.get_sync_structures()?.image_available_semaphores[current_frame_index]];
+        // The windows drawn by the frame wait for their images like the main one
+        let (window_wait_semaphores, window_signal_semaphores) =
+            self.window_swapchains_get_semaphores(current_frame_index);
+        wait_semaphores.extend(window_wait_semaphores);
+        signal_semaphores.extend(window_signal_semaphores);
+        let mut wait_dst_stage_mask =
+            vec![PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT; wait_semaphores.len()];
+        // The uploads of the frame can be read by any stage
+        let (transfer_semaphores, transfer_acquires) =
+            match self.transfer_take_uploads(current_frame_index) {
+                Ok(uploads) => uploads,
+                Err(err) => {
+                    error!(
+                        "Failed to submit the uploads when ending a new frame: {:?}",
+                        err
+                    );
+                    if let EngineError::DeviceLost = err {
+                        return Err(self.on_device_lost());
+                    }
+                    return Err(EngineError::VulkanFailed);
+                }
+            };
+        // The dispatches run after the uploads, the frame then only waits for the dispatches
+        let compute_submission = match self.compute_submit(
+            current_frame_index,
+            transfer_semaphores,
+            transfer_acquires,
+        ) {
+            Ok(submission) => submission,
+            Err(err) => {
+                error!(
+                    "Failed to submit the dispatches when ending a new frame: {:?}",
+                    err
+                );
+                if let EngineError::DeviceLost = err {
+                    return Err(self.on_device_lost());
+                }
+                return Err(EngineError::VulkanFailed);
+            }
+        };
+        // The resources released by the other queues are acquired ahead of the draws
+        let acquire_command_buffer = match self.queue_ownership_record_frame_acquires(
+            current_frame_index,
+            &compute_submission.acquires,
+        ) {
+            Ok(command_buffer) => command_buffer,
+            Err(err) => {
+                error!(
+                    "Failed to record the queue ownership acquires when ending a new frame: {:?}",
+                    err
+                );
+                return Err(EngineError::VulkanFailed);
+            }
+        };
+        let command_buffers: Vec<ash::vk::CommandBuffer> = acquire_command_buffer
+            .into_iter()
+            .chain([*command_buffer.handler.as_ref()])
+            .collect();
+        signal_semaphores.extend(compute_submission.graphics_semaphore);
+        wait_dst_stage_mask.resize(
+            wait_semaphores.len() + compute_submission.wait_semaphores.len(),
+            PipelineStageFlags::ALL_COMMANDS,
+        );
+        wait_semaphores.extend(compute_submission.wait_semaphores);
         let submit_info = [SubmitInfo::default()
             .command_buffers(&command_buffers)
             .signal_semaphores(&signal_semaphores)
             .wait_semaphores(&wait_semaphores)
             .wait_dst_stage_mask(&wait_dst_stage_mask)];
         let current_fence = &self.get_sync_structures()?.in_flight_fences[current_frame_index];
-        let graphics_queue = self.get_queues()?.graphics_queue.unwrap();
+        let graphics_queue = self.get_queues()?.get_graphics_queue()?;
         let device = self.get_device()?;
         unsafe {
             if let Err(err) = device.queue_submit(
@@ -178,14 +478,30 @@ impl RendererBackend for VulkanRendererBackend<'_> {
                     "Failed to submit the vulkan graphics queue when ending a new frame: {:?}",
                     err
                 );
+                if err == ash::vk::Result::ERROR_DEVICE_LOST {
+                    return Err(self.on_device_lost());
+                }
                 return Err(EngineError::VulkanFailed);
             }
         }
 
-        // Give the image back to the swapchain.
+        // The textures replaced so far may have been sampled by this frame at the latest
+        self.retired_textures_frame_submitted(current_frame_index);
+
+        // Give the images back to the swapchains, the main one last as it moves to the next frame
+        if let Err(err) = self.window_swapchains_present(current_frame_index) {
+            error!(
+                "Failed to present the windows when ending a new frame: {:?}",
+                err
+            );
+            if let EngineError::DeviceLost = err {
+                return Err(self.on_device_lost());
+            }
+            return Err(err);
+        }
         let render_complete_semaphore =
             self.get_sync_structures()?.queue_complete_semaphores[current_frame_index];
-        match self.swapchain_present(render_complete_semaphore, self.context.image_index) {
+        match self.swapchain_present(render_complete_semaphore, self.context.frame.image_index) {
             Ok(Some(())) => (),
             Ok(None) => self.swapchain_recreate()?,
             Err(err) => {
@@ -193,6 +509,9 @@ impl RendererBackend for VulkanRendererBackend<'_> {
                     "Failed to present the vulkan swapchain when ending a new frame: {:?}",
                     err
                 );
+                if let EngineError::DeviceLost = err {
+                    return Err(self.on_device_lost());
+                }
                 return Err(err);
             }
         }
@@ -214,25 +533,44 @@ impl RendererBackend for VulkanRendererBackend<'_> {
         projection: glam::Mat4,
         view: glam::Mat4,
         view_position: glam::Vec3,
-        ambient_colour: glam::Vec4,
+        lighting: &LightingSystem,
         mode: i32,
     ) -> Result<(), EngineError> {
-        let current_frame_index = self.context.current_frame as usize;
-        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
-        let device = self.get_device()?;
-
-        let object_shaders = &self.get_builtin_shaders()?.object_shaders;
-        object_shaders.r#use(device, command_buffer)?;
-        let object_shaders = &mut self
-            .context
-            .builtin_shaders
-            .as_mut()
-            .unwrap()
-            .object_shaders;
+        let current_frame_index = self.get_current_frame_index();
+        let (core, frame, resources) = self.split_context_mut();
+        let command_buffer = frame.get_graphics_command_buffer(current_frame_index)?;
+        let object_shaders = resources.get_object_shaders_mut()?;
+        object_shaders.r#use(
+            core.get_device()?,
+            command_buffer,
+            &MaterialRenderState::default(),
+        )?;
         object_shaders.global_ubo.projection = projection;
         object_shaders.global_ubo.view = view;
+        object_shaders.global_ubo.view_position = view_position.extend(1.0);
+        object_shaders.global_ubo.ambient_color = lighting.ambient_color;
+        let (direction, color) = match &lighting.directional_light {
+            Some(light) => (
+                light.direction.normalize_or_zero().extend(1.0),
+                (light.color * light.intensity).extend(1.0),
+            ),
+            None => (glam::Vec4::ZERO, glam::Vec4::ZERO),
+        };
+        object_shaders.global_ubo.directional_light_direction = direction;
+        object_shaders.global_ubo.directional_light_color = color;
+        let point_lights = lighting.get_point_lights();
+        let point_light_count = point_lights.len().min(LIGHTING_MAX_POINT_LIGHTS);
+        object_shaders.global_ubo.point_light_count =
+            glam::UVec4::new(point_light_count as u32, 0, 0, 0);
+        for (slot, light) in object_shaders
+            .global_ubo
+            .point_lights
+            .iter_mut()
+            .zip(&point_lights)
+        {
+            *slot = light.into();
+        }
 
-        // TODO: other ubo properties
         if let Err(err) = self.update_object_shaders_global_state() {
             error!(
                 "Failed to update the vulkan object shaders global state: {:?}",
@@ -246,59 +584,197 @@ impl RendererBackend for VulkanRendererBackend<'_> {
 
     fn get_aspect_ratio(&self) -> Result<f32, EngineError> {
         let width = self.get_swapchain()?.extent.width as f32;
-        let height = self.get_swapchain()?.extent.width as f32;
+        let height = self.get_swapchain()?.extent.height as f32;
         Ok(width / height)
     }
 
-    fn update_object(&mut self, data: &GeometryRenderData) -> Result<(), EngineError> {
-        let current_frame_index = self.context.current_frame as usize;
-        if let Err(err) = self.update_object_shaders(data) {
+    fn get_framebuffer_size(&self) -> Result<(u32, u32), EngineError> {
+        Ok((self.framebuffer_width, self.framebuffer_height))
+    }
+
+    fn get_sample_count(&self) -> Result<u32, EngineError> {
+        Ok(self.sample_count.as_raw())
+    }
+
+    fn update_post_process(
+        &mut self,
+        settings: &PostProcessSettings,
+        color_filter: glam::Mat4,
+    ) -> Result<(), EngineError> {
+        self.post_process_settings = *settings;
+        self.color_filter = color_filter;
+        Ok(())
+    }
+
+    fn update_object(
+        &mut self,
+        data: &GeometryRenderData,
+        textures: &TextureSystem,
+    ) -> Result<(), EngineError> {
+        self.draw_object(data, &data.model, 0, 1, textures)
+    }
+
+    fn draw_instances(
+        &mut self,
+        data: &GeometryRenderData,
+        models: &[glam::Mat4],
+        textures: &TextureSystem,
+    ) -> Result<(), EngineError> {
+        match self.object_shaders_push_instances(models) {
+            Ok(Some(first_instance)) => self.draw_object(
+                data,
+                &glam::Mat4::IDENTITY,
+                first_instance,
+                models.len() as u32,
+                textures,
+            ),
+            // The instance buffer of the frame is full, the objects are drawn one by one
+            Ok(None) => {
+                for model in models {
+                    self.draw_object(data, model, 0, 1, textures)?;
+                }
+                Ok(())
+            }
+            Err(err) => {
+                error!(
+                    "Failed to push the instances when drawing the vulkan objects: {:?}",
+                    err
+                );
+                Err(EngineError::UpdateFailed)
+            }
+        }
+    }
+
+    fn begin_ui_pass(&mut self) -> Result<(), EngineError> {
+        if self.context.frame.has_ui_pass_begun {
+            error!("The vulkan ui pass has already begun this frame");
+            return Err(EngineError::InvalidValue);
+        }
+        if self.render_graph_get_open_camera_pass().is_some() {
+            error!("The vulkan camera passes must be ended before the ui pass");
+            return Err(EngineError::InvalidValue);
+        }
+        // The queries can't span several renderpasses, the statistics only cover the world
+        if let Err(err) = self.query_pools_end_frame() {
             error!(
-                "Failed to update the vulkan object shaders when updating the vulkan objects: {:?}",
+                "Failed to end the frame queries when beginning the ui pass: {:?}",
                 err
             );
             return Err(EngineError::UpdateFailed);
         }
+        if let Err(err) = self.render_graph_end_pass() {
+            error!(
+                "Failed to end the world pass when beginning the ui pass: {:?}",
+                err
+            );
+            return Err(EngineError::UpdateFailed);
+        }
+        // The world pass ends once all its commands are done
+        if let Err(err) = self.query_pools_write_timestamp(
+            VULKAN_TIMESTAMP_UI_PASS_BEGIN,
+            PipelineStageFlags::BOTTOM_OF_PIPE,
+        ) {
+            error!(
+                "Failed to write the ui pass timestamp when beginning the ui pass: {:?}",
+                err
+            );
+            return Err(EngineError::UpdateFailed);
+        }
+        // The last post processing pass leaves the present renderpass open for the ui
+        if let Err(err) = self.render_graph_execute() {
+            error!(
+                "Failed to record the post processing passes when beginning the ui pass: {:?}",
+                err
+            );
+            return Err(EngineError::UpdateFailed);
+        }
+        self.context.frame.has_ui_pass_begun = true;
+        self.set_frame_viewport()
+    }
 
-        // TODO: temporary test code
-        {
-            let object_shaders = &self.get_builtin_shaders()?.object_shaders;
-            let image_index = self.context.image_index as usize;
-            let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
-            let device = self.get_device()?;
-            object_shaders.r#use(device, command_buffer)?;
-            // Bind vertex buffer at offset
-            let offsets = [0];
-            let vertex_buffer = [self.get_objects_buffers()?.vertex_buffer.buffer];
-            unsafe {
-                device.cmd_bind_vertex_buffers(
-                    *command_buffer.handler.as_ref(),
-                    0,
-                    &vertex_buffer,
-                    &offsets,
-                );
-            }
-            // Bind index buffer at offset
-            let index_buffer = self.get_objects_buffers()?.index_buffer.buffer;
-            unsafe {
-                device.cmd_bind_index_buffer(
-                    *command_buffer.handler.as_ref(),
-                    index_buffer,
-                    0,
-                    ash::vk::IndexType::UINT32,
+    fn draw_ui(
+        &mut self,
+        projection: glam::Mat4,
+        vertices: &[UiVertexData],
+        batches: &[UiRenderData],
+    ) -> Result<(), EngineError> {
+        if let Err(err) = self.draw_ui_shaders(projection, vertices, batches) {
+            error!("Failed to draw the vulkan ui shaders: {:?}", err);
+            return Err(EngineError::UpdateFailed);
+        }
+        Ok(())
+    }
+
+    fn draw_immediate(
+        &mut self,
+        projection: glam::Mat4,
+        vertices: &[ImmediateVertexData],
+    ) -> Result<(), EngineError> {
+        if let Err(err) = self.draw_immediate_shaders(projection, vertices) {
+            error!("Failed to draw the vulkan immediate shaders: {:?}", err);
+            return Err(EngineError::UpdateFailed);
+        }
+        Ok(())
+    }
+
+    fn acquire_object_resources(&mut self) -> Result<u32, EngineError> {
+        match self.object_shader_acquire_resources() {
+            Ok(object_id) => Ok(object_id),
+            Err(err) => {
+                error!(
+                    "Failed to acquire the vulkan object shader resources: {:?}",
+                    err
                 );
+                Err(EngineError::InitializationFailed)
             }
-            // Issue the draw
-            unsafe {
-                device.cmd_draw_indexed(*command_buffer.handler.as_ref(), 6, 1, 0, 0, 0);
-            }
         }
-        // TODO: end temporary test code
+    }
+
+    fn release_object_resources(&mut self, object_id: u32) -> Result<(), EngineError> {
+        if let Err(err) = self.object_shader_release_resources(object_id) {
+            error!(
+                "Failed to release the vulkan object shader resources {}: {:?}",
+                object_id, err
+            );
+            return Err(EngineError::ShutdownFailed);
+        }
+        Ok(())
+    }
+
+    fn get_statistics(&self) -> Result<RendererStatistics, EngineError> {
+        Ok(self.statistics)
+    }
+
+    fn request_screenshot(&mut self, hdr: bool) -> Result<(), EngineError> {
+        self.screenshot_request(hdr)
+    }
+
+    fn take_screenshot(&mut self) -> Result<Option<ScreenshotPixels>, EngineError> {
+        Ok(self.screenshot_take())
+    }
+
+    fn take_hdr_screenshot(&mut self) -> Result<Option<HdrScreenshotPixels>, EngineError> {
+        Ok(self.screenshot_take_hdr())
+    }
+
+    fn set_luminance_readback_enabled(&mut self, is_enabled: bool) -> Result<(), EngineError> {
+        self.context.resources.luminance_readback_requested = is_enabled;
         Ok(())
     }
 
+    fn take_luminance_samples(&mut self) -> Result<Option<Vec<glam::Vec3>>, EngineError> {
+        Ok(self.context.resources.luminance_samples.take())
+    }
+
+    fn upload_reflection_probes(
+        &mut self,
+        environment_maps: &[&EnvironmentMap],
+    ) -> Result<(), EngineError> {
+        self.reflection_probes_upload(environment_maps)
+    }
+
     fn create_texture(
-        &self,
+        &mut self,
         params: crate::resources::texture::TextureCreatorParameters,
     ) -> Result<Box<dyn crate::resources::texture::Texture>, EngineError> {
         let vulkan_texture = match self.vulkan_create_texture(params) {
@@ -331,4 +807,300 @@ impl RendererBackend for VulkanRendererBackend<'_> {
         }
         Ok(())
     }
+
+    fn retire_texture(
+        &mut self,
+        texture: Box<dyn crate::resources::texture::Texture>,
+    ) -> Result<(), EngineError> {
+        let vulkan_texture = match texture.as_any().downcast_ref::<Texture>() {
+            Some(texture) => *texture,
+            None => {
+                error!("A vulkan renderer can only retire vulkan textures");
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        self.vulkan_retire_texture(vulkan_texture);
+        Ok(())
+    }
+
+    fn update_texture_region(
+        &self,
+        texture: &dyn crate::resources::texture::Texture,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Result<(), EngineError> {
+        let vulkan_texture = match texture.as_any().downcast_ref::<Texture>() {
+            Some(texture) => texture,
+            None => {
+                error!("A vulkan renderer can only update vulkan textures");
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        if let Err(err) =
+            self.vulkan_update_texture_region(vulkan_texture, x, y, width, height, pixels)
+        {
+            error!("Failed to update a region of a vulkan texture: {:?}", err);
+            return Err(EngineError::UpdateFailed);
+        }
+        Ok(())
+    }
+
+    fn read_texture_pixels(
+        &self,
+        texture: &dyn crate::resources::texture::Texture,
+    ) -> Result<Vec<u8>, EngineError> {
+        let vulkan_texture = match texture.as_any().downcast_ref::<Texture>() {
+            Some(texture) => texture,
+            None => {
+                error!("A vulkan renderer can only read vulkan textures");
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        match self.vulkan_read_texture_pixels(vulkan_texture) {
+            Ok(pixels) => Ok(pixels),
+            Err(err) => {
+                error!("Failed to read the pixels of a vulkan texture: {:?}", err);
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
+
+    fn create_geometry(
+        &mut self,
+        params: crate::resources::geometry::GeometryCreatorParameters,
+    ) -> Result<Box<dyn crate::resources::geometry::Geometry>, EngineError> {
+        let vulkan_geometry = match self.vulkan_create_geometry(params) {
+            Ok(geometry) => geometry,
+            Err(err) => {
+                error!(
+                    "Failed to create a vulkan geometry when creating a renderer geometry: {:?}",
+                    err
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        Ok(Box::new(vulkan_geometry))
+    }
+
+    fn destroy_geometry(
+        &mut self,
+        geometry: &dyn crate::resources::geometry::Geometry,
+    ) -> Result<(), EngineError> {
+        let vulkan_geometry = match geometry.as_any().downcast_ref::<Geometry>() {
+            Some(geometry) => geometry,
+            None => {
+                error!("A vulkan renderer can only destroy vulkan geometries");
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        if let Err(err) = self.vulkan_destroy_geometry(vulkan_geometry) {
+            error!("Failed to destroy a vulkan geometry: {:?}", err);
+            return Err(EngineError::ShutdownFailed);
+        }
+        Ok(())
+    }
+
+    fn update_geometry_vertices(
+        &mut self,
+        geometry: &dyn crate::resources::geometry::Geometry,
+        vertices: &[VertexData],
+    ) -> Result<(), EngineError> {
+        let vulkan_geometry = match geometry.as_any().downcast_ref::<Geometry>() {
+            Some(geometry) => geometry,
+            None => {
+                error!("A vulkan renderer can only update vulkan geometries");
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        if let Err(err) = self.vulkan_update_geometry_vertices(vulkan_geometry, vertices) {
+            error!(
+                "Failed to update the vertices of a vulkan geometry: {:?}",
+                err
+            );
+            return Err(EngineError::UpdateFailed);
+        }
+        Ok(())
+    }
+
+    fn create_compute_shader(&mut self, path: &str) -> Result<u32, EngineError> {
+        match self.compute_create_shader(path, None) {
+            Ok(shader_id) => Ok(shader_id),
+            Err(err) => {
+                error!(
+                    "Failed to create the vulkan compute shader {:?}: {:?}",
+                    path, err
+                );
+                Err(EngineError::InitializationFailed)
+            }
+        }
+    }
+
+    fn destroy_compute_shader(&mut self, shader_id: u32) -> Result<(), EngineError> {
+        if let Err(err) = self.compute_destroy_shader(shader_id) {
+            error!("Failed to destroy a vulkan compute shader: {:?}", err);
+            return Err(EngineError::ShutdownFailed);
+        }
+        Ok(())
+    }
+
+    fn create_storage_buffer(&mut self, size: usize) -> Result<u32, EngineError> {
+        match self.compute_create_storage_buffer(size) {
+            Ok(storage_buffer_id) => Ok(storage_buffer_id),
+            Err(err) => {
+                error!("Failed to create a vulkan storage buffer: {:?}", err);
+                Err(EngineError::InitializationFailed)
+            }
+        }
+    }
+
+    fn destroy_storage_buffer(&mut self, storage_buffer_id: u32) -> Result<(), EngineError> {
+        if let Err(err) = self.compute_destroy_storage_buffer(storage_buffer_id) {
+            error!("Failed to destroy a vulkan storage buffer: {:?}", err);
+            return Err(EngineError::ShutdownFailed);
+        }
+        Ok(())
+    }
+
+    fn write_storage_buffer(
+        &mut self,
+        storage_buffer_id: u32,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<(), EngineError> {
+        if let Err(err) = self.compute_write_storage_buffer(storage_buffer_id, offset, data) {
+            error!("Failed to write a vulkan storage buffer: {:?}", err);
+            return Err(EngineError::UpdateFailed);
+        }
+        Ok(())
+    }
+
+    fn read_storage_buffer(&self, storage_buffer_id: u32) -> Result<Vec<u8>, EngineError> {
+        match self.compute_read_storage_buffer(storage_buffer_id) {
+            Ok(data) => Ok(data),
+            Err(err) => {
+                error!("Failed to read a vulkan storage buffer: {:?}", err);
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
+
+    fn dispatch_compute(
+        &mut self,
+        shader_id: u32,
+        bindings: &[ComputeBinding],
+        group_count: glam::UVec3,
+        push_constants: &[u8],
+    ) -> Result<(), EngineError> {
+        if let Err(err) = self.compute_dispatch(shader_id, bindings, group_count, push_constants) {
+            error!("Failed to record a vulkan compute dispatch: {:?}", err);
+            return Err(EngineError::UpdateFailed);
+        }
+        Ok(())
+    }
+}
+
+impl VulkanRendererBackend<'_> {
+    /// The world pass is open, its statistics and viewport are set up for the frontend
+    fn begin_world_pass(&self) -> Result<(), EngineError> {
+        if let Err(err) = self.query_pools_begin_frame() {
+            error!(
+                "Failed to begin the frame queries when beginning the world pass: {:?}",
+                err
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+        self.set_world_viewport_area(None)
+    }
+
+    /// Viewport of an area of the world pass, shrunk by the render scale of the frame
+    fn set_world_viewport_area(&self, area: Option<CameraViewport>) -> Result<(), EngineError> {
+        let render_area = self.get_renderpass()?.render_area;
+        let area = area.unwrap_or(CameraViewport::new(render_area.width, render_area.height));
+        let scale = self.render_graph_get_render_scale();
+        self.set_frame_viewport_area(Some(CameraViewport {
+            x: area.x * scale,
+            y: area.y * scale,
+            width: area.width * scale,
+            height: area.height * scale,
+        }))
+    }
+
+    /// Record the draw of an object, several instances of it when the count is more than one
+    /// The occlusion queries only cover the objects drawn alone in the world pass
+    fn draw_object(
+        &mut self,
+        data: &GeometryRenderData,
+        model: &glam::Mat4,
+        first_instance: u32,
+        instance_count: u32,
+        textures: &TextureSystem,
+    ) -> Result<(), EngineError> {
+        let current_frame_index = self.context.frame.current_frame as usize;
+        let render_state = data
+            .material
+            .as_ref()
+            .map(|material| material.render_state)
+            .unwrap_or_default();
+        if let Err(err) = self.object_shaders_acquire_pipeline_variant(&render_state) {
+            error!(
+                "Failed to acquire the pipeline of the object render state when updating the vulkan objects: {:?}",
+                err
+            );
+            return Err(EngineError::UpdateFailed);
+        }
+        if let Err(err) = self.update_object_shaders(data, model, textures) {
+            error!(
+                "Failed to update the vulkan object shaders when updating the vulkan objects: {:?}",
+                err
+            );
+            return Err(EngineError::UpdateFailed);
+        }
+
+        let object_id = data.object_id.unwrap_or_default();
+        let is_object_queried = instance_count == 1
+            && self.render_graph_get_open_camera_pass().is_none()
+            && match self.query_pools_begin_object(object_id) {
+                Ok(is_queried) => is_queried,
+                Err(err) => {
+                    error!(
+                        "Failed to begin the object queries when updating the vulkan objects: {:?}",
+                        err
+                    );
+                    return Err(EngineError::UpdateFailed);
+                }
+            };
+
+        if let Some(geometry) = &data.geometry {
+            let vulkan_geometry = match geometry.as_any().downcast_ref::<Geometry>() {
+                Some(geometry) => geometry,
+                None => {
+                    error!("A vulkan renderer can only draw vulkan geometries");
+                    return Err(EngineError::InvalidValue);
+                }
+            };
+            let object_shaders = &self.get_builtin_shaders()?.object_shaders;
+            let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
+            object_shaders.r#use(self.get_device()?, command_buffer, &render_state)?;
+            if let Err(err) =
+                self.vulkan_draw_geometry(vulkan_geometry, first_instance, instance_count)
+            {
+                error!("Failed to draw a vulkan geometry: {:?}", err);
+                return Err(EngineError::UpdateFailed);
+            }
+        }
+
+        if is_object_queried {
+            if let Err(err) = self.query_pools_end_object(object_id) {
+                error!(
+                    "Failed to end the object queries when updating the vulkan objects: {:?}",
+                    err
+                );
+                return Err(EngineError::UpdateFailed);
+            }
+        }
+        Ok(())
+    }
 }
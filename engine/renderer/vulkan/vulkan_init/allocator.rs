@@ -1,24 +1,391 @@
+use std::{cell::RefCell, ffi::c_void};
+
+use ash::vk::{DeviceMemory, MemoryAllocateInfo, MemoryMapFlags, MemoryRequirements, WHOLE_SIZE};
+
 use crate::{
-    core::debug::errors::EngineError, renderer::vulkan::vulkan_types::VulkanRendererBackend,
+    core::debug::errors::EngineError,
+    debug, error,
+    renderer::vulkan::vulkan_types::{VulkanCoreContext, VulkanRendererBackend},
 };
 
+/// Size of the device memory blocks the buffers and images are sub allocated from
+pub const ALLOCATOR_BLOCK_SIZE: u64 = 64 * 1024 * 1024;
+
+/// Bigger resources get their own device memory to keep the blocks from being wasted
+pub const ALLOCATOR_DEDICATED_THRESHOLD: u64 = ALLOCATOR_BLOCK_SIZE / 2;
+
+/// Range of device memory bound to a buffer or an image
+#[derive(Default, Clone, Copy, Debug)]
+pub(crate) struct MemoryAllocation {
+    pub memory: DeviceMemory,
+    /// Offset of the range in the device memory
+    pub offset: u64,
+    pub size: u64,
+    /// None when the memory is dedicated to the resource
+    block_index: Option<usize>,
+}
+
+/// Device memory shared by several resources
+struct MemoryBlock {
+    memory: DeviceMemory,
+    memory_type_index: u32,
+    /// Unused ranges as (offset, size), sorted by offset and never adjacent
+    free_ranges: Vec<(u64, u64)>,
+    allocation_count: usize,
+    /// Host visible blocks stay mapped as a memory can't be mapped twice
+    mapped_pointer: Option<*mut c_void>,
+}
+
+// Safety: the mapped memory is not tied to a thread, the block is only used through the allocator
+unsafe impl Send for MemoryBlock {}
+
+impl MemoryBlock {
+    /// First free range fitting the size once aligned
+    fn find_range(&self, size: u64, alignment: u64) -> Option<(usize, u64)> {
+        self.free_ranges
+            .iter()
+            .enumerate()
+            .find_map(|(index, &(offset, range_size))| {
+                let aligned_offset = offset.next_multiple_of(alignment);
+                (aligned_offset + size <= offset + range_size).then_some((index, aligned_offset))
+            })
+    }
+
+    /// Split the free range around the allocated one
+    fn take_range(&mut self, index: usize, offset: u64, size: u64) {
+        let (range_offset, range_size) = self.free_ranges.remove(index);
+        let end = offset + size;
+        let range_end = range_offset + range_size;
+        if end < range_end {
+            self.free_ranges.insert(index, (end, range_end - end));
+        }
+        if range_offset < offset {
+            self.free_ranges
+                .insert(index, (range_offset, offset - range_offset));
+        }
+        self.allocation_count += 1;
+    }
+
+    /// Merge the range with its free neighbours
+    fn release_range(&mut self, offset: u64, size: u64) {
+        let index = self
+            .free_ranges
+            .partition_point(|&(range_offset, _)| range_offset < offset);
+        self.free_ranges.insert(index, (offset, size));
+        if index + 1 < self.free_ranges.len() {
+            let (next_offset, next_size) = self.free_ranges[index + 1];
+            if offset + size == next_offset {
+                self.free_ranges[index].1 += next_size;
+                self.free_ranges.remove(index + 1);
+            }
+        }
+        if index > 0 {
+            let (previous_offset, previous_size) = self.free_ranges[index - 1];
+            if previous_offset + previous_size == offset {
+                self.free_ranges[index - 1].1 += self.free_ranges[index].1;
+                self.free_ranges.remove(index);
+            }
+        }
+        self.allocation_count -= 1;
+    }
+}
+
+/// Sub allocates the buffers and images from big blocks of device memory
+/// A device only allows a few thousands `vkAllocateMemory` at the same time
+#[derive(Default)]
+pub(crate) struct MemoryAllocator {
+    /// Emptied blocks are freed and leave a hole to keep the indices of the others
+    blocks: Vec<Option<MemoryBlock>>,
+    /// Alignment between linear and optimal resources sharing a block
+    buffer_image_granularity: u64,
+}
+
+impl MemoryAllocator {
+    pub fn get_block_count(&self) -> usize {
+        self.blocks.iter().flatten().count()
+    }
+}
+
 impl VulkanRendererBackend<'_> {
     pub fn allocator_init(&mut self) -> Result<(), EngineError> {
         // TODO: build an allocator
-        self.context.allocator = None;
+        self.context.core.allocator = None;
         Ok(())
     }
 
     pub fn allocator_shutdown(&mut self) -> Result<(), EngineError> {
-        self.context.allocator = None;
+        self.context.core.allocator = None;
+        Ok(())
+    }
+
+    pub fn memory_allocator_init(&mut self) -> Result<(), EngineError> {
+        let buffer_image_granularity = self
+            .get_physical_device_info()?
+            .properties
+            .limits
+            .buffer_image_granularity;
+        self.context.core.memory_allocator = Some(RefCell::new(MemoryAllocator {
+            blocks: Vec::new(),
+            buffer_image_granularity,
+        }));
         Ok(())
     }
+
+    pub fn memory_allocator_shutdown(&mut self) -> Result<(), EngineError> {
+        let memory_allocator = match self.context.core.memory_allocator.take() {
+            Some(memory_allocator) => memory_allocator.into_inner(),
+            None => return Ok(()),
+        };
+        let device = self.get_device()?;
+        let allocator = self.get_allocator()?;
+        for block in memory_allocator.blocks.into_iter().flatten() {
+            if block.allocation_count > 0 {
+                error!(
+                    "{} vulkan allocations are still alive when shutting down the memory allocator",
+                    block.allocation_count
+                );
+            }
+            unsafe { device.free_memory(block.memory, allocator) };
+        }
+        Ok(())
+    }
+
+    fn get_memory_allocator(&self) -> Result<&RefCell<MemoryAllocator>, EngineError> {
+        match &self.context.core.memory_allocator {
+            Some(memory_allocator) => Ok(memory_allocator),
+            None => {
+                error!("Can't access the vulkan memory allocator");
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
+
+    fn allocate_device_memory(
+        &self,
+        size: u64,
+        memory_type_index: u32,
+    ) -> Result<DeviceMemory, EngineError> {
+        let memory_allocate_info = MemoryAllocateInfo::default()
+            .allocation_size(size)
+            .memory_type_index(memory_type_index);
+        unsafe {
+            match self
+                .get_device()?
+                .allocate_memory(&memory_allocate_info, self.get_allocator()?)
+            {
+                Ok(memory) => Ok(memory),
+                Err(err) => {
+                    error!("Failed to allocate vulkan device memory: {:?}", err);
+                    Err(EngineError::VulkanFailed)
+                }
+            }
+        }
+    }
+
+    fn is_memory_type_host_visible(&self, memory_type_index: u32) -> Result<bool, EngineError> {
+        let memory_properties = unsafe {
+            self.get_instance()?
+                .get_physical_device_memory_properties(*self.get_physical_device()?)
+        };
+        Ok(memory_properties.memory_types[memory_type_index as usize]
+            .property_flags
+            .contains(ash::vk::MemoryPropertyFlags::HOST_VISIBLE))
+    }
+
+    /// Find room for the resource in the blocks of its memory type, a new block is created if they are full
+    pub(crate) fn memory_allocate(
+        &self,
+        requirements: MemoryRequirements,
+        memory_type_index: u32,
+    ) -> Result<MemoryAllocation, EngineError> {
+        if requirements.size > ALLOCATOR_DEDICATED_THRESHOLD {
+            let memory = self.allocate_device_memory(requirements.size, memory_type_index)?;
+            return Ok(MemoryAllocation {
+                memory,
+                offset: 0,
+                size: requirements.size,
+                block_index: None,
+            });
+        }
+
+        let mut memory_allocator = self.get_memory_allocator()?.borrow_mut();
+        // Linear and optimal resources can't share a page so everything is aligned on it
+        let alignment = requirements
+            .alignment
+            .max(memory_allocator.buffer_image_granularity)
+            .max(1);
+
+        let found = memory_allocator
+            .blocks
+            .iter()
+            .enumerate()
+            .find_map(|(block_index, block)| {
+                let block = block.as_ref()?;
+                if block.memory_type_index != memory_type_index {
+                    return None;
+                }
+                let (range_index, offset) = block.find_range(requirements.size, alignment)?;
+                Some((block_index, range_index, offset))
+            });
+
+        let (block_index, range_index, offset) = match found {
+            Some(found) => found,
+            None => {
+                let memory =
+                    self.allocate_device_memory(ALLOCATOR_BLOCK_SIZE, memory_type_index)?;
+                let mapped_pointer = if self.is_memory_type_host_visible(memory_type_index)? {
+                    let device = self.get_device()?;
+                    match unsafe {
+                        device.map_memory(memory, 0, WHOLE_SIZE, MemoryMapFlags::empty())
+                    } {
+                        Ok(pointer) => Some(pointer),
+                        Err(err) => {
+                            error!("Failed to map a vulkan memory block: {:?}", err);
+                            unsafe { device.free_memory(memory, self.get_allocator()?) };
+                            return Err(EngineError::VulkanFailed);
+                        }
+                    }
+                } else {
+                    None
+                };
+                let block = MemoryBlock {
+                    memory,
+                    memory_type_index,
+                    free_ranges: vec![(0, ALLOCATOR_BLOCK_SIZE)],
+                    allocation_count: 0,
+                    mapped_pointer,
+                };
+                // Reuse the hole of a freed block if any
+                let block_index = match memory_allocator.blocks.iter().position(Option::is_none) {
+                    Some(index) => {
+                        memory_allocator.blocks[index] = Some(block);
+                        index
+                    }
+                    None => {
+                        memory_allocator.blocks.push(Some(block));
+                        memory_allocator.blocks.len() - 1
+                    }
+                };
+                debug!(
+                    "New vulkan memory block of type {}, {} blocks allocated",
+                    memory_type_index,
+                    memory_allocator.get_block_count()
+                );
+                (block_index, 0, 0)
+            }
+        };
+
+        let block = match memory_allocator.blocks[block_index].as_mut() {
+            Some(block) => block,
+            None => {
+                error!(
+                    "The vulkan memory block {} was freed while allocating from it",
+                    block_index
+                );
+                return Err(EngineError::AccessFailed);
+            }
+        };
+        block.take_range(range_index, offset, requirements.size);
+        Ok(MemoryAllocation {
+            memory: block.memory,
+            offset,
+            size: requirements.size,
+            block_index: Some(block_index),
+        })
+    }
+
+    /// Give the range back to its block, the block is freed once empty
+    pub(crate) fn memory_free(&self, allocation: &MemoryAllocation) -> Result<(), EngineError> {
+        let device = self.get_device()?;
+        let block_index = match allocation.block_index {
+            Some(block_index) => block_index,
+            None => {
+                unsafe { device.free_memory(allocation.memory, self.get_allocator()?) };
+                return Ok(());
+            }
+        };
+
+        let mut memory_allocator = self.get_memory_allocator()?.borrow_mut();
+        let block = match memory_allocator.blocks.get_mut(block_index) {
+            Some(Some(block)) => block,
+            _ => {
+                error!("Can't free a vulkan allocation from an unknown memory block");
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        block.release_range(allocation.offset, allocation.size);
+        if block.allocation_count == 0 {
+            let memory = block.memory;
+            memory_allocator.blocks[block_index] = None;
+            // Freeing the memory also unmaps it
+            unsafe { device.free_memory(memory, self.get_allocator()?) };
+        }
+        Ok(())
+    }
+
+    /// Cpu address of the allocation in its persistently mapped block
+    /// Dedicated allocations are mapped on demand and must be unmapped with `memory_unmap`
+    pub(crate) fn memory_map(
+        &self,
+        allocation: &MemoryAllocation,
+        offset: u64,
+        size: u64,
+    ) -> Result<*mut c_void, EngineError> {
+        let block_pointer = match allocation.block_index {
+            Some(block_index) => {
+                let memory_allocator = self.get_memory_allocator()?.borrow();
+                memory_allocator.blocks[block_index]
+                    .as_ref()
+                    .and_then(|block| block.mapped_pointer)
+            }
+            None => {
+                let device = self.get_device()?;
+                return match unsafe {
+                    device.map_memory(
+                        allocation.memory,
+                        allocation.offset + offset,
+                        size,
+                        MemoryMapFlags::empty(),
+                    )
+                } {
+                    Ok(pointer) => Ok(pointer),
+                    Err(err) => {
+                        error!("Failed to map a vulkan dedicated allocation: {:?}", err);
+                        Err(EngineError::VulkanFailed)
+                    }
+                };
+            }
+        };
+        match block_pointer {
+            Some(pointer) => Ok(unsafe { pointer.add((allocation.offset + offset) as usize) }),
+            None => {
+                error!("Can't map a vulkan allocation which is not host visible");
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    /// Only the dedicated allocations are unmapped, the blocks stay mapped
+    pub(crate) fn memory_unmap(&self, allocation: &MemoryAllocation) -> Result<(), EngineError> {
+        if allocation.block_index.is_none() {
+            unsafe { self.get_device()?.unmap_memory(allocation.memory) };
+        }
+        Ok(())
+    }
+}
+
+impl<'a> VulkanCoreContext<'a> {
+    pub fn get_allocator(
+        &self,
+    ) -> Result<Option<&'a ash::vk::AllocationCallbacks<'a>>, EngineError> {
+        Ok(self.allocator)
+    }
 }
 
 impl<'a> VulkanRendererBackend<'a> {
     pub fn get_allocator(
         &self,
     ) -> Result<Option<&'a ash::vk::AllocationCallbacks<'a>>, EngineError> {
-        Ok(self.context.allocator)
+        self.context.core.get_allocator()
     }
 }
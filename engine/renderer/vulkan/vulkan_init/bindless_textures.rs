@@ -0,0 +1,207 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use ash::vk::{
+    DescriptorBindingFlags, DescriptorImageInfo, DescriptorPool, DescriptorPoolCreateFlags,
+    DescriptorPoolCreateInfo, DescriptorPoolSize, DescriptorSet, DescriptorSetAllocateInfo,
+    DescriptorSetLayout, DescriptorSetLayoutBinding, DescriptorSetLayoutBindingFlagsCreateInfo,
+    DescriptorSetLayoutCreateFlags, DescriptorSetLayoutCreateInfo, DescriptorType, ImageLayout,
+    ShaderStageFlags, WriteDescriptorSet,
+};
+
+use crate::{
+    core::debug::errors::EngineError,
+    debug, error,
+    renderer::vulkan::{vulkan_types::VulkanRendererBackend, vulkan_utils::texture::Texture},
+};
+
+/// Textures of the bindless array, lowered to the device limits
+pub const VULKAN_BINDLESS_TEXTURES_MAX_COUNT: u32 = 4096;
+
+/// Index of the written textures, the indices of the destroyed ones are reused
+#[derive(Default)]
+struct BindlessTextureIndices {
+    by_texture_id: HashMap<u32, u32>,
+    next_index: u32,
+    free_indices: Vec<u32>,
+}
+
+/// Single array of every texture drawn by the object shaders
+/// A texture is written once and keeps its index until it is destroyed
+pub(crate) struct BindlessTextures {
+    pub descriptor_pool: DescriptorPool,
+    pub descriptor_set_layout: DescriptorSetLayout,
+    /// Shared by the frames in flight, only the indices they don't use are written while they are pending
+    pub descriptor_set: DescriptorSet,
+    pub capacity: u32,
+    // The textures are destroyed through shared references
+    indices: RefCell<BindlessTextureIndices>,
+}
+
+impl VulkanRendererBackend<'_> {
+    /// None when the device lacks the descriptor indexing features
+    pub fn get_bindless_textures(&self) -> Option<&BindlessTextures> {
+        self.context.resources.bindless_textures.as_ref()
+    }
+
+    pub fn bindless_textures_init(&mut self) -> Result<(), EngineError> {
+        let max_bindless_textures = self.get_physical_device_info()?.max_bindless_textures;
+        if max_bindless_textures == 0 {
+            debug!("The device lacks descriptor indexing, the textures are bound per object");
+            self.context.resources.bindless_textures = None;
+            return Ok(());
+        }
+        let capacity = max_bindless_textures.min(VULKAN_BINDLESS_TEXTURES_MAX_COUNT);
+        let device = self.get_device()?;
+        let allocator = self.get_allocator()?;
+
+        // The indices left unwritten are never sampled
+        let bindings = [DescriptorSetLayoutBinding::default()
+            .binding(0)
+            .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(capacity)
+            .stage_flags(ShaderStageFlags::FRAGMENT)];
+        let binding_flags = [DescriptorBindingFlags::PARTIALLY_BOUND
+            | DescriptorBindingFlags::UPDATE_AFTER_BIND
+            | DescriptorBindingFlags::UPDATE_UNUSED_WHILE_PENDING];
+        let mut binding_flags_create_info =
+            DescriptorSetLayoutBindingFlagsCreateInfo::default().binding_flags(&binding_flags);
+        let layout_create_info = DescriptorSetLayoutCreateInfo::default()
+            .flags(DescriptorSetLayoutCreateFlags::UPDATE_AFTER_BIND_POOL)
+            .bindings(&bindings)
+            .push_next(&mut binding_flags_create_info);
+        let descriptor_set_layout =
+            match unsafe { device.create_descriptor_set_layout(&layout_create_info, allocator) } {
+                Ok(layout) => layout,
+                Err(err) => {
+                    error!(
+                        "Failed to create the layout of the bindless textures: {:?}",
+                        err
+                    );
+                    return Err(EngineError::VulkanFailed);
+                }
+            };
+
+        let pool_sizes = [DescriptorPoolSize::default()
+            .ty(DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(capacity)];
+        let pool_create_info = DescriptorPoolCreateInfo::default()
+            .flags(DescriptorPoolCreateFlags::UPDATE_AFTER_BIND)
+            .pool_sizes(&pool_sizes)
+            .max_sets(1);
+        let descriptor_pool = match unsafe {
+            device.create_descriptor_pool(&pool_create_info, allocator)
+        } {
+            Ok(pool) => pool,
+            Err(err) => {
+                error!(
+                    "Failed to create the descriptor pool of the bindless textures: {:?}",
+                    err
+                );
+                unsafe { device.destroy_descriptor_set_layout(descriptor_set_layout, allocator) };
+                return Err(EngineError::VulkanFailed);
+            }
+        };
+
+        let layouts = [descriptor_set_layout];
+        let allocate_info = DescriptorSetAllocateInfo::default()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(&layouts);
+        let descriptor_set = match unsafe { device.allocate_descriptor_sets(&allocate_info) } {
+            Ok(sets) => sets[0],
+            Err(err) => {
+                error!(
+                    "Failed to allocate the descriptor set of the bindless textures: {:?}",
+                    err
+                );
+                unsafe {
+                    device.destroy_descriptor_pool(descriptor_pool, allocator);
+                    device.destroy_descriptor_set_layout(descriptor_set_layout, allocator);
+                }
+                return Err(EngineError::VulkanFailed);
+            }
+        };
+
+        self.context.resources.bindless_textures = Some(BindlessTextures {
+            descriptor_pool,
+            descriptor_set_layout,
+            descriptor_set,
+            capacity,
+            indices: RefCell::new(BindlessTextureIndices::default()),
+        });
+        Ok(())
+    }
+
+    pub fn bindless_textures_shutdown(&mut self) -> Result<(), EngineError> {
+        if let Some(bindless_textures) = self.context.resources.bindless_textures.take() {
+            let device = self.get_device()?;
+            let allocator = self.get_allocator()?;
+            unsafe {
+                device.destroy_descriptor_pool(bindless_textures.descriptor_pool, allocator);
+                device.destroy_descriptor_set_layout(
+                    bindless_textures.descriptor_set_layout,
+                    allocator,
+                );
+            }
+        }
+        Ok(())
+    }
+
+    /// Index of the texture in the bindless array, written the first time the texture is drawn
+    pub fn bindless_textures_acquire(&self, texture: &Texture) -> Result<u32, EngineError> {
+        let bindless_textures = match self.get_bindless_textures() {
+            Some(bindless_textures) => bindless_textures,
+            None => {
+                error!("Can't access the vulkan bindless textures");
+                return Err(EngineError::AccessFailed);
+            }
+        };
+        let mut indices = bindless_textures.indices.borrow_mut();
+        if let Some(index) = indices.by_texture_id.get(&texture.id) {
+            return Ok(*index);
+        }
+
+        let index = match indices.free_indices.pop() {
+            Some(index) => index,
+            None if indices.next_index < bindless_textures.capacity => {
+                indices.next_index += 1;
+                indices.next_index - 1
+            }
+            None => {
+                error!(
+                    "The {} bindless textures are all in use",
+                    bindless_textures.capacity
+                );
+                return Err(EngineError::UpdateFailed);
+            }
+        };
+
+        // No frame in flight samples a free index
+        let image_info = [DescriptorImageInfo::default()
+            .image_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .image_view(texture.image.get_image_view()?)
+            .sampler(texture.sampler)];
+        let descriptor_writes = [WriteDescriptorSet::default()
+            .dst_set(bindless_textures.descriptor_set)
+            .dst_binding(0)
+            .dst_array_element(index)
+            .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)
+            .image_info(&image_info)];
+        unsafe {
+            self.get_device()?
+                .update_descriptor_sets(&descriptor_writes, &[])
+        };
+        indices.by_texture_id.insert(texture.id, index);
+        Ok(index)
+    }
+
+    /// The texture is destroyed, the frames sampling it must be done
+    pub fn bindless_textures_release(&self, texture_id: u32) {
+        if let Some(bindless_textures) = self.get_bindless_textures() {
+            let mut indices = bindless_textures.indices.borrow_mut();
+            if let Some(index) = indices.by_texture_id.remove(&texture_id) {
+                indices.free_indices.push(index);
+            }
+        }
+    }
+}
@@ -7,7 +7,9 @@ use ash::{
 };
 
 use crate::{
-    core::debug::errors::EngineError, error, renderer::vulkan::vulkan_types::VulkanRendererBackend,
+    core::debug::errors::EngineError,
+    error,
+    renderer::vulkan::vulkan_types::{VulkanFrameContext, VulkanRendererBackend},
 };
 
 #[derive(Clone)]
@@ -161,10 +163,10 @@ impl VulkanRendererBackend<'_> {
         let device = self.get_device()?;
         let command_pool = self.get_graphics_command_pool()?;
 
-        for buffer in &self.context.graphics_command_buffers {
+        for buffer in &self.context.frame.graphics_command_buffers {
             buffer.free(device, command_pool)?;
         }
-        self.context.graphics_command_buffers.clear();
+        self.context.frame.graphics_command_buffers.clear();
         Ok(())
     }
 
@@ -183,12 +185,30 @@ impl VulkanRendererBackend<'_> {
             new_buffers.push(new_buffer);
         }
 
-        self.context.graphics_command_buffers = new_buffers;
+        self.context.frame.graphics_command_buffers = new_buffers;
 
         Ok(())
     }
 
     pub fn get_graphics_command_buffers(&self) -> Result<&Vec<CommandBuffer>, EngineError> {
-        Ok(&self.context.graphics_command_buffers)
+        Ok(&self.context.frame.graphics_command_buffers)
+    }
+}
+
+impl VulkanFrameContext {
+    pub fn get_graphics_command_buffer(
+        &self,
+        frame_index: usize,
+    ) -> Result<&CommandBuffer, EngineError> {
+        match self.graphics_command_buffers.get(frame_index) {
+            Some(command_buffer) => Ok(command_buffer),
+            None => {
+                error!(
+                    "Can't access the vulkan graphics command buffer {}",
+                    frame_index
+                );
+                Err(EngineError::AccessFailed)
+            }
+        }
     }
 }
@@ -7,9 +7,9 @@ use crate::{
 impl VulkanRendererBackend<'_> {
     pub fn graphics_command_pool_init(&mut self) -> Result<(), EngineError> {
         let pool_create_info = CommandPoolCreateInfo::default()
-            .queue_family_index(self.get_queues()?.graphics_family_index.unwrap() as u32)
+            .queue_family_index(self.get_queues()?.get_graphics_family_index()?)
             .flags(CommandPoolCreateFlags::RESET_COMMAND_BUFFER);
-        self.context.graphics_command_pool = unsafe {
+        self.context.frame.graphics_command_pool = unsafe {
             let device = self.get_device()?;
             match device.create_command_pool(&pool_create_info, self.get_allocator()?) {
                 Ok(pool) => Some(pool),
@@ -35,7 +35,7 @@ impl VulkanRendererBackend<'_> {
     }
 
     pub fn get_graphics_command_pool(&self) -> Result<&CommandPool, EngineError> {
-        match &self.context.graphics_command_pool {
+        match &self.context.frame.graphics_command_pool {
             Some(pool) => Ok(pool),
             None => {
                 error!("Can't access the vulkan graphics command pool");
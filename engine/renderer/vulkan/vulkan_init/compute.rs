@@ -0,0 +1,819 @@
+use std::{cell::RefCell, collections::HashMap};
+
+use ash::vk::{
+    AccessFlags, BufferCopy, BufferUsageFlags, CommandPool, CommandPoolCreateFlags,
+    CommandPoolCreateInfo, DependencyFlags, DescriptorBufferInfo, DescriptorImageInfo,
+    DescriptorPool, DescriptorPoolCreateInfo, DescriptorPoolResetFlags, DescriptorPoolSize,
+    DescriptorSetAllocateInfo, DescriptorSetLayout, DescriptorSetLayoutBinding,
+    DescriptorSetLayoutCreateInfo, DescriptorType, ImageLayout, MemoryBarrier, MemoryMapFlags,
+    MemoryPropertyFlags, PipelineBindPoint, PipelineShaderStageCreateInfo, PipelineStageFlags,
+    Queue, Semaphore, ShaderStageFlags, SubmitInfo, WriteDescriptorSet, WHOLE_SIZE,
+};
+
+use crate::{
+    core::debug::errors::EngineError,
+    error,
+    renderer::{
+        renderer_types::{ComputeBinding, RENDERER_MAX_IN_FLIGHT_FRAMES},
+        vulkan::{
+            vulkan_init::{command_buffer::CommandBuffer, queue_ownership::OwnershipTransfer},
+            vulkan_shaders::{
+                reflection::{
+                    reflection_get_descriptor_set_layout_bindings,
+                    reflection_get_push_constant_ranges,
+                },
+                shader::Shader,
+            },
+            vulkan_types::VulkanRendererBackend,
+            vulkan_utils::{
+                buffer::{Buffer, BufferCreatorParameters},
+                per_frame_resource::PerFrameResource,
+                pipeline::{ComputePipelineCreateInfo, Pipeline},
+                texture::Texture,
+            },
+        },
+    },
+};
+
+/// Dispatches recorded in a frame at most, each one needs its own descriptor set
+pub const VULKAN_COMPUTE_MAX_DISPATCHES: u32 = 256;
+/// Storage buffers and images bound by a dispatch at most
+pub const VULKAN_COMPUTE_MAX_BINDINGS: u32 = 8;
+/// Largest write recorded at once with `cmd_update_buffer`
+const VULKAN_COMPUTE_MAX_UPDATE_SIZE: usize = 65536;
+
+/// Compute stage and the pipeline running it, its resources are all in the set 0
+pub(crate) struct ComputeShader {
+    shader: Shader,
+    descriptor_set_layout: DescriptorSetLayout,
+    layout_bindings: Vec<DescriptorSetLayoutBinding<'static>>,
+    push_constant_size: u32,
+    pipeline: Pipeline,
+}
+
+/// Work of the compute queue recorded during a frame
+struct ComputeFrame {
+    command_buffer: CommandBuffer,
+    descriptor_pool: DescriptorPool,
+    /// Signaled by the compute submission, waited by the graphics submission of the same frame
+    compute_semaphore: Semaphore,
+    /// Signaled by the graphics submission, waited by the compute submission of the next frame
+    graphics_semaphore: Semaphore,
+    is_recording: bool,
+    /// Storage images acquired from the graphics queue, given back when the frame is submitted
+    images: Vec<ash::vk::Image>,
+}
+
+/// What the graphics submission of a frame needs from the compute submission
+pub(crate) struct ComputeSubmission {
+    /// Semaphores to wait on, in place of the uploads' ones
+    pub wait_semaphores: Vec<Semaphore>,
+    /// Handovers to acquire before the draws, the uploads' ones when the compute queue didn't take them
+    pub acquires: Vec<OwnershipTransfer>,
+    /// Signaled for the next compute submission when the frame used the compute queue
+    pub graphics_semaphore: Option<Semaphore>,
+}
+
+/// Dispatches done on the compute queue, ahead of the graphics work of their frame
+/// A frame using the compute queue makes the next compute submission wait for its graphics work,
+/// so the dispatches never write the resources still read by the previous frame
+pub(crate) struct ComputeContext {
+    command_pool: CommandPool,
+    queue: Queue,
+    frames: PerFrameResource<ComputeFrame>,
+    /// Signaled by the last graphics submission and not waited yet
+    pending_graphics_semaphore: Option<Semaphore>,
+    shaders: HashMap<u32, ComputeShader>,
+    next_shader_id: u32,
+    storage_buffers: HashMap<u32, Buffer>,
+    next_storage_buffer_id: u32,
+}
+
+impl VulkanRendererBackend<'_> {
+    pub fn compute_init(&mut self) -> Result<(), EngineError> {
+        let queues = self.get_queues()?;
+        let family_index = queues.get_compute_family_index()?;
+        let queue = queues.get_compute_queue()?;
+        let device = self.get_device()?;
+        let allocator = self.get_allocator()?;
+
+        let pool_create_info = CommandPoolCreateInfo::default()
+            .queue_family_index(family_index)
+            .flags(CommandPoolCreateFlags::RESET_COMMAND_BUFFER);
+        let command_pool = unsafe {
+            match device.create_command_pool(&pool_create_info, allocator) {
+                Ok(pool) => pool,
+                Err(err) => {
+                    error!(
+                        "Failed to create the vulkan compute command pool: {:?}",
+                        err
+                    );
+                    return Err(EngineError::InitializationFailed);
+                }
+            }
+        };
+
+        let descriptor_count = VULKAN_COMPUTE_MAX_DISPATCHES * VULKAN_COMPUTE_MAX_BINDINGS;
+        let pool_sizes = [
+            DescriptorPoolSize::default()
+                .ty(DescriptorType::STORAGE_BUFFER)
+                .descriptor_count(descriptor_count),
+            DescriptorPoolSize::default()
+                .ty(DescriptorType::STORAGE_IMAGE)
+                .descriptor_count(descriptor_count),
+        ];
+        let descriptor_pool_create_info = DescriptorPoolCreateInfo::default()
+            .pool_sizes(&pool_sizes)
+            .max_sets(VULKAN_COMPUTE_MAX_DISPATCHES);
+        let mut frames = Vec::new();
+        for _ in 0..RENDERER_MAX_IN_FLIGHT_FRAMES {
+            let command_buffer = CommandBuffer::allocate(&command_pool, true, device)?;
+            let descriptor_pool = unsafe {
+                match device.create_descriptor_pool(&descriptor_pool_create_info, allocator) {
+                    Ok(pool) => pool,
+                    Err(err) => {
+                        error!(
+                            "Failed to create a vulkan compute descriptor pool: {:?}",
+                            err
+                        );
+                        return Err(EngineError::InitializationFailed);
+                    }
+                }
+            };
+            frames.push(ComputeFrame {
+                command_buffer,
+                descriptor_pool,
+                compute_semaphore: self.create_default_semaphore()?,
+                graphics_semaphore: self.create_default_semaphore()?,
+                is_recording: false,
+                images: Vec::new(),
+            });
+        }
+
+        self.context.core.compute = Some(RefCell::new(ComputeContext {
+            command_pool,
+            queue,
+            frames: PerFrameResource::from_vec(frames)?,
+            pending_graphics_semaphore: None,
+            shaders: HashMap::new(),
+            next_shader_id: 0,
+            storage_buffers: HashMap::new(),
+            next_storage_buffer_id: 0,
+        }));
+        Ok(())
+    }
+
+    /// The device is idle when shutting down so every dispatch is done
+    pub fn compute_shutdown(&mut self) -> Result<(), EngineError> {
+        let compute = match self.context.core.compute.take() {
+            Some(compute) => compute.into_inner(),
+            None => return Ok(()),
+        };
+        let device = self.get_device()?;
+        let allocator = self.get_allocator()?;
+
+        for shader in compute.shaders.values() {
+            self.compute_destroy_shader_resources(shader)?;
+        }
+        for buffer in compute.storage_buffers.values() {
+            self.destroy_buffer(buffer)?;
+        }
+        for frame in compute.frames.iter() {
+            self.destroy_semaphore(&frame.compute_semaphore)?;
+            self.destroy_semaphore(&frame.graphics_semaphore)?;
+            unsafe { device.destroy_descriptor_pool(frame.descriptor_pool, allocator) };
+        }
+        // Destroying the pool frees the command buffers of the frames
+        unsafe { device.destroy_command_pool(compute.command_pool, allocator) };
+        Ok(())
+    }
+
+    fn get_compute(&self) -> Result<&RefCell<ComputeContext>, EngineError> {
+        match &self.context.core.compute {
+            Some(compute) => Ok(compute),
+            None => {
+                error!("Can't access the vulkan compute context");
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
+
+    fn compute_destroy_shader_resources(&self, shader: &ComputeShader) -> Result<(), EngineError> {
+        let device = self.get_device()?;
+        let allocator = self.get_allocator()?;
+        shader.pipeline.destroy(device, allocator)?;
+        unsafe { device.destroy_descriptor_set_layout(shader.descriptor_set_layout, allocator) };
+        shader.shader.destroy(device, allocator)
+    }
+
+    /// Load a compute stage from the assets/shaders/ folder, its source is compiled first when present
+    /// The shader can only use storage buffers and storage images, all in the set 0
+    pub(crate) fn compute_create_shader(
+        &self,
+        shader_path_from_shaders_dir: &str,
+        shader_entry_point: Option<&str>,
+    ) -> Result<u32, EngineError> {
+        // The build script only compiles the builtin shaders
+        if Shader::get_source_modification_time(shader_path_from_shaders_dir).is_some() {
+            Shader::compile(shader_path_from_shaders_dir, shader_entry_point)?;
+        }
+        let device = self.get_device()?;
+        let allocator = self.get_allocator()?;
+        let shader = Shader::create(
+            device,
+            allocator,
+            ShaderStageFlags::COMPUTE,
+            shader_path_from_shaders_dir,
+            shader_entry_point,
+        )?;
+
+        let layout_bindings = match Self::compute_get_layout_bindings(&shader) {
+            Ok(bindings) => bindings,
+            Err(err) => {
+                error!(
+                    "Unsupported resources in the vulkan compute shader {:?}",
+                    shader_path_from_shaders_dir
+                );
+                shader.destroy(device, allocator)?;
+                return Err(err);
+            }
+        };
+        let layout_create_info =
+            DescriptorSetLayoutCreateInfo::default().bindings(&layout_bindings);
+        let descriptor_set_layout = unsafe {
+            match device.create_descriptor_set_layout(&layout_create_info, allocator) {
+                Ok(layout) => layout,
+                Err(err) => {
+                    error!(
+                        "Failed to create the descriptor layout of a vulkan compute shader: {:?}",
+                        err
+                    );
+                    shader.destroy(device, allocator)?;
+                    return Err(EngineError::VulkanFailed);
+                }
+            }
+        };
+
+        let pipeline_info = ComputePipelineCreateInfo {
+            descriptor_set_layouts: vec![descriptor_set_layout],
+            push_constant_ranges: reflection_get_push_constant_ranges(&[&shader]),
+            shader_stage_info: PipelineShaderStageCreateInfo::default()
+                .stage(ShaderStageFlags::COMPUTE)
+                .module(shader.shader_module)
+                .name(&shader.entry_point),
+        };
+        let pipeline = match Pipeline::create_compute(device, allocator, pipeline_info) {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                error!(
+                    "Failed to create the pipeline of a vulkan compute shader: {:?}",
+                    err
+                );
+                unsafe { device.destroy_descriptor_set_layout(descriptor_set_layout, allocator) };
+                shader.destroy(device, allocator)?;
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+
+        let mut compute = self.get_compute()?.borrow_mut();
+        let id = compute.next_shader_id;
+        compute.next_shader_id += 1;
+        compute.shaders.insert(
+            id,
+            ComputeShader {
+                push_constant_size: shader.reflection.push_constant_size,
+                shader,
+                descriptor_set_layout,
+                layout_bindings,
+                pipeline,
+            },
+        );
+        Ok(id)
+    }
+
+    fn compute_get_layout_bindings(
+        shader: &Shader,
+    ) -> Result<Vec<DescriptorSetLayoutBinding<'static>>, EngineError> {
+        if let Some(binding) = shader
+            .reflection
+            .descriptor_bindings
+            .iter()
+            .find(|binding| binding.set != 0)
+        {
+            error!(
+                "The compute shaders only have a set 0, found the binding {} of the set {}",
+                binding.binding, binding.set
+            );
+            return Err(EngineError::InvalidValue);
+        }
+        let layout_bindings = reflection_get_descriptor_set_layout_bindings(&[shader], 0)?;
+        for binding in layout_bindings.iter() {
+            if binding.descriptor_count != 1
+                || binding.binding >= VULKAN_COMPUTE_MAX_BINDINGS
+                || (binding.descriptor_type != DescriptorType::STORAGE_BUFFER
+                    && binding.descriptor_type != DescriptorType::STORAGE_IMAGE)
+            {
+                error!(
+                    "The compute shaders only bind single storage buffers and images below the binding {}, found {:?}",
+                    VULKAN_COMPUTE_MAX_BINDINGS, binding
+                );
+                return Err(EngineError::InvalidValue);
+            }
+        }
+        Ok(layout_bindings)
+    }
+
+    /// Waits for the gpu, the shader may be used by the frames in flight
+    pub(crate) fn compute_destroy_shader(&self, shader_id: u32) -> Result<(), EngineError> {
+        self.device_wait_idle()?;
+        let shader = match self.get_compute()?.borrow_mut().shaders.remove(&shader_id) {
+            Some(shader) => shader,
+            None => {
+                error!(
+                    "Can't destroy the unknown vulkan compute shader {}",
+                    shader_id
+                );
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        self.compute_destroy_shader_resources(&shader)
+    }
+
+    /// Device local buffer owned by the compute queue, its content is undefined until written
+    pub(crate) fn compute_create_storage_buffer(&self, size: usize) -> Result<u32, EngineError> {
+        let buffer = self.create_storage_buffer(BufferCreatorParameters::storage(
+            size,
+            BufferUsageFlags::empty(),
+        ))?;
+        let mut compute = self.get_compute()?.borrow_mut();
+        let id = compute.next_storage_buffer_id;
+        compute.next_storage_buffer_id += 1;
+        compute.storage_buffers.insert(id, buffer);
+        Ok(id)
+    }
+
+    /// Waits for the gpu, the buffer may be used by the frames in flight
+    pub(crate) fn compute_destroy_storage_buffer(
+        &self,
+        storage_buffer_id: u32,
+    ) -> Result<(), EngineError> {
+        self.device_wait_idle()?;
+        let buffer = match self
+            .get_compute()?
+            .borrow_mut()
+            .storage_buffers
+            .remove(&storage_buffer_id)
+        {
+            Some(buffer) => buffer,
+            None => {
+                error!(
+                    "Can't destroy the unknown vulkan storage buffer {}",
+                    storage_buffer_id
+                );
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        self.destroy_buffer(&buffer)
+    }
+
+    /// Copy the storage buffer back to the cpu, waits for the frames submitted so far
+    pub(crate) fn compute_read_storage_buffer(
+        &self,
+        storage_buffer_id: u32,
+    ) -> Result<Vec<u8>, EngineError> {
+        self.device_wait_idle()?;
+        let compute = self.get_compute()?.borrow();
+        let buffer = match compute.storage_buffers.get(&storage_buffer_id) {
+            Some(buffer) => buffer,
+            None => {
+                error!(
+                    "Can't read the unknown vulkan storage buffer {}",
+                    storage_buffer_id
+                );
+                return Err(EngineError::InvalidValue);
+            }
+        };
+
+        let staging_buffer_creator_parameters = BufferCreatorParameters::default()
+            .size(buffer.total_size)
+            .should_be_bind(true)
+            .buffer_usage_flags(BufferUsageFlags::TRANSFER_DST)
+            .memory_flags(MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT);
+        let staging = self.create_buffer(staging_buffer_creator_parameters)?;
+
+        // Copied on the compute queue, which keeps the buffer
+        let pool = &compute.command_pool;
+        let device = self.get_device()?;
+        let read_result = CommandBuffer::allocate_and_begin_single_use(device, pool)
+            .and_then(|command_buffer| {
+                let copy_regions = [BufferCopy::default().size(buffer.total_size as u64)];
+                unsafe {
+                    device.cmd_copy_buffer(
+                        *command_buffer.handler.as_ref(),
+                        buffer.buffer,
+                        staging.buffer,
+                        &copy_regions,
+                    );
+                }
+                command_buffer.end_single_use(device, pool, compute.queue)
+            })
+            .and_then(|_| {
+                self.read_data_from_buffer(&staging, 0, buffer.total_size, MemoryMapFlags::empty())
+            });
+        self.destroy_buffer(&staging)?;
+        if let Err(err) = &read_result {
+            error!("Failed to read back a vulkan storage buffer: {:?}", err);
+        }
+        read_result
+    }
+
+    /// Begin the compute command buffer of the frame if nothing was recorded in it yet
+    /// The previous use of the frame is done once the frame began
+    fn compute_begin_recording(
+        &self,
+        compute: &mut ComputeContext,
+    ) -> Result<CommandBuffer, EngineError> {
+        let device = self.get_device()?;
+        let frame = compute.frames.get_mut(self.get_current_frame_index());
+        if !frame.is_recording {
+            frame.command_buffer.reset(device)?;
+            if let Err(err) = unsafe {
+                device
+                    .reset_descriptor_pool(frame.descriptor_pool, DescriptorPoolResetFlags::empty())
+            } {
+                error!(
+                    "Failed to reset a vulkan compute descriptor pool: {:?}",
+                    err
+                );
+                return Err(EngineError::VulkanFailed);
+            }
+            let is_single_use = true;
+            let is_renderpass_continue = false;
+            let is_simultaneous_use = false;
+            frame.command_buffer.begin(
+                device,
+                is_single_use,
+                is_renderpass_continue,
+                is_simultaneous_use,
+            )?;
+            frame.is_recording = true;
+        }
+        Ok(frame.command_buffer.clone())
+    }
+
+    /// The recorded writes and dispatches run one after the other
+    fn compute_record_barrier(&self, command_buffer: &CommandBuffer) -> Result<(), EngineError> {
+        let memory_barriers = [MemoryBarrier::default()
+            .src_access_mask(AccessFlags::SHADER_WRITE | AccessFlags::TRANSFER_WRITE)
+            .dst_access_mask(
+                AccessFlags::SHADER_READ | AccessFlags::SHADER_WRITE | AccessFlags::TRANSFER_WRITE,
+            )];
+        let stages = PipelineStageFlags::COMPUTE_SHADER | PipelineStageFlags::TRANSFER;
+        unsafe {
+            self.get_device()?.cmd_pipeline_barrier(
+                *command_buffer.handler.as_ref(),
+                stages,
+                stages,
+                DependencyFlags::empty(),
+                &memory_barriers,
+                &[],
+                &[],
+            );
+        }
+        Ok(())
+    }
+
+    /// Record the write in the frame's compute work, ordered with its dispatches
+    /// The offset and the size of the data must be multiples of 4
+    pub(crate) fn compute_write_storage_buffer(
+        &self,
+        storage_buffer_id: u32,
+        offset: u64,
+        data: &[u8],
+    ) -> Result<(), EngineError> {
+        let mut compute = self.get_compute()?.borrow_mut();
+        let compute = &mut *compute;
+        let buffer = match compute.storage_buffers.get(&storage_buffer_id) {
+            Some(buffer) => buffer.buffer,
+            None => {
+                error!(
+                    "Can't write the unknown vulkan storage buffer {}",
+                    storage_buffer_id
+                );
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        let size = compute.storage_buffers[&storage_buffer_id].total_size as u64;
+        if !offset.is_multiple_of(4)
+            || !data.len().is_multiple_of(4)
+            || offset + data.len() as u64 > size
+        {
+            error!(
+                "Can't write {} bytes at {} in the vulkan storage buffer {} of {} bytes, the range must be aligned to 4",
+                data.len(),
+                offset,
+                storage_buffer_id,
+                size
+            );
+            return Err(EngineError::InvalidValue);
+        }
+
+        let command_buffer = self.compute_begin_recording(compute)?;
+        self.compute_record_barrier(&command_buffer)?;
+        let device = self.get_device()?;
+        for (index, chunk) in data.chunks(VULKAN_COMPUTE_MAX_UPDATE_SIZE).enumerate() {
+            let chunk_offset = offset + (index * VULKAN_COMPUTE_MAX_UPDATE_SIZE) as u64;
+            unsafe {
+                device.cmd_update_buffer(
+                    *command_buffer.handler.as_ref(),
+                    buffer,
+                    chunk_offset,
+                    chunk,
+                )
+            };
+        }
+        Ok(())
+    }
+
+    /// Record a dispatch of the shader in the frame's compute work
+    /// The bindings are in the order of the shader's bindings, the push constants are copied as is
+    pub(crate) fn compute_dispatch(
+        &self,
+        shader_id: u32,
+        bindings: &[ComputeBinding],
+        group_count: glam::UVec3,
+        push_constants: &[u8],
+    ) -> Result<(), EngineError> {
+        let mut compute = self.get_compute()?.borrow_mut();
+        let compute = &mut *compute;
+        let shader = match compute.shaders.get(&shader_id) {
+            Some(shader) => shader,
+            None => {
+                error!(
+                    "Can't dispatch the unknown vulkan compute shader {}",
+                    shader_id
+                );
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        if push_constants.len() as u32 > shader.push_constant_size {
+            error!(
+                "The vulkan compute shader {} has {} bytes of push constants, got {}",
+                shader_id,
+                shader.push_constant_size,
+                push_constants.len()
+            );
+            return Err(EngineError::InvalidValue);
+        }
+
+        // Resolve the resources before recording anything
+        let mut buffer_infos = Vec::new();
+        let mut image_infos = Vec::new();
+        let mut images = Vec::new();
+        for layout_binding in shader.layout_bindings.iter() {
+            let binding = match bindings.get(layout_binding.binding as usize) {
+                Some(binding) => binding,
+                None => {
+                    error!(
+                        "The vulkan compute shader {} needs a resource at the binding {}",
+                        shader_id, layout_binding.binding
+                    );
+                    return Err(EngineError::InvalidValue);
+                }
+            };
+            match (layout_binding.descriptor_type, binding) {
+                (DescriptorType::STORAGE_BUFFER, ComputeBinding::StorageBuffer(id)) => {
+                    let buffer = match compute.storage_buffers.get(id) {
+                        Some(buffer) => buffer,
+                        None => {
+                            error!("Can't bind the unknown vulkan storage buffer {}", id);
+                            return Err(EngineError::InvalidValue);
+                        }
+                    };
+                    buffer_infos.push((
+                        layout_binding.binding,
+                        [DescriptorBufferInfo::default()
+                            .buffer(buffer.buffer)
+                            .offset(0)
+                            .range(WHOLE_SIZE)],
+                    ));
+                }
+                (DescriptorType::STORAGE_IMAGE, ComputeBinding::StorageImage(texture)) => {
+                    let vulkan_texture = match texture.as_any().downcast_ref::<Texture>() {
+                        Some(texture) => texture,
+                        None => {
+                            error!("A vulkan compute shader can only bind vulkan textures");
+                            return Err(EngineError::InvalidValue);
+                        }
+                    };
+                    image_infos.push((
+                        layout_binding.binding,
+                        [DescriptorImageInfo::default()
+                            .image_layout(ImageLayout::GENERAL)
+                            .image_view(vulkan_texture.image.get_image_view()?)],
+                    ));
+                    // An image bound twice is acquired once
+                    if !images
+                        .iter()
+                        .any(|image: &&Texture| image.image.image == vulkan_texture.image.image)
+                    {
+                        images.push(vulkan_texture);
+                    }
+                }
+                (descriptor_type, _) => {
+                    error!(
+                        "The binding {} of the vulkan compute shader {} expects a {:?}",
+                        layout_binding.binding, shader_id, descriptor_type
+                    );
+                    return Err(EngineError::InvalidValue);
+                }
+            }
+        }
+
+        let device = self.get_device()?;
+        let command_buffer = self.compute_begin_recording(compute)?;
+        let shader = &compute.shaders[&shader_id];
+        let descriptor_pool = compute
+            .frames
+            .get(self.get_current_frame_index())
+            .descriptor_pool;
+
+        let mut descriptor_sets = Vec::new();
+        if !shader.layout_bindings.is_empty() {
+            let layouts = [shader.descriptor_set_layout];
+            let allocate_info = DescriptorSetAllocateInfo::default()
+                .descriptor_pool(descriptor_pool)
+                .set_layouts(&layouts);
+            let descriptor_set = match unsafe { device.allocate_descriptor_sets(&allocate_info) } {
+                Ok(sets) => sets[0],
+                Err(err) => {
+                    error!(
+                        "Failed to allocate a vulkan compute descriptor set, at most {} dispatches per frame: {:?}",
+                        VULKAN_COMPUTE_MAX_DISPATCHES, err
+                    );
+                    return Err(EngineError::VulkanFailed);
+                }
+            };
+            let buffer_writes = buffer_infos.iter().map(|(binding, info)| {
+                WriteDescriptorSet::default()
+                    .dst_set(descriptor_set)
+                    .dst_binding(*binding)
+                    .descriptor_type(DescriptorType::STORAGE_BUFFER)
+                    .descriptor_count(1)
+                    .buffer_info(info)
+            });
+            let image_writes = image_infos.iter().map(|(binding, info)| {
+                WriteDescriptorSet::default()
+                    .dst_set(descriptor_set)
+                    .dst_binding(*binding)
+                    .descriptor_type(DescriptorType::STORAGE_IMAGE)
+                    .descriptor_count(1)
+                    .image_info(info)
+            });
+            let writes: Vec<WriteDescriptorSet> = buffer_writes.chain(image_writes).collect();
+            unsafe { device.update_descriptor_sets(&writes, &[]) };
+            descriptor_sets.push(descriptor_set);
+        }
+
+        // The images stay in the general layout until the frame is submitted
+        self.compute_record_barrier(&command_buffer)?;
+        let frame = compute.frames.get_mut(self.get_current_frame_index());
+        let new_images: Vec<ash::vk::Image> = images
+            .iter()
+            .map(|image| image.image.image)
+            .filter(|image| !frame.images.contains(image))
+            .collect();
+        let acquires: Vec<OwnershipTransfer> = new_images
+            .iter()
+            .map(|image| OwnershipTransfer::storage_image_to_compute(*image))
+            .collect();
+        self.record_ownership_acquire(&command_buffer, &acquires)?;
+        frame.images.extend(new_images);
+        shader
+            .pipeline
+            .bind(device, &command_buffer, PipelineBindPoint::COMPUTE)?;
+        unsafe {
+            if !descriptor_sets.is_empty() {
+                device.cmd_bind_descriptor_sets(
+                    *command_buffer.handler.as_ref(),
+                    PipelineBindPoint::COMPUTE,
+                    shader.pipeline.layout,
+                    0,
+                    &descriptor_sets,
+                    &[],
+                );
+            }
+            if !push_constants.is_empty() {
+                device.cmd_push_constants(
+                    *command_buffer.handler.as_ref(),
+                    shader.pipeline.layout,
+                    ShaderStageFlags::COMPUTE,
+                    0,
+                    push_constants,
+                );
+            }
+            device.cmd_dispatch(
+                *command_buffer.handler.as_ref(),
+                group_count.x,
+                group_count.y,
+                group_count.z,
+            );
+        }
+        Ok(())
+    }
+
+    /// Submit the compute work of the frame after the uploads it may read
+    /// The graphics queue first acquires the uploads and lends the storage images,
+    /// the dispatches give the images back at their end
+    pub(crate) fn compute_submit(
+        &self,
+        frame_index: usize,
+        upload_semaphores: Vec<Semaphore>,
+        upload_acquires: Vec<OwnershipTransfer>,
+    ) -> Result<ComputeSubmission, EngineError> {
+        let mut compute = self.get_compute()?.borrow_mut();
+        let compute = &mut *compute;
+        let frame = compute.frames.get_mut(frame_index);
+        // Nothing to run and nothing to release
+        if !frame.is_recording && compute.pending_graphics_semaphore.is_none() {
+            return Ok(ComputeSubmission {
+                wait_semaphores: upload_semaphores,
+                acquires: upload_acquires,
+                graphics_semaphore: None,
+            });
+        }
+
+        let device = self.get_device()?;
+        let mut wait_semaphores = upload_semaphores;
+        let mut acquires = upload_acquires;
+        if !frame.images.is_empty() {
+            let releases: Vec<OwnershipTransfer> = frame
+                .images
+                .iter()
+                .map(|image| OwnershipTransfer::storage_image_to_compute(*image))
+                .collect();
+            let release_result = self.queue_ownership_submit_releases(
+                frame_index,
+                &acquires,
+                &releases,
+                &wait_semaphores,
+            );
+            let release_semaphore = match release_result {
+                Ok(semaphore) => semaphore,
+                Err(err) => {
+                    // The command buffer is reset by the next use of the frame
+                    frame.is_recording = false;
+                    frame.images.clear();
+                    return Err(err);
+                }
+            };
+            wait_semaphores = vec![release_semaphore];
+            acquires.clear();
+        }
+
+        let mut command_buffers = Vec::new();
+        if frame.is_recording {
+            let releases: Vec<OwnershipTransfer> = frame
+                .images
+                .drain(..)
+                .map(OwnershipTransfer::storage_image_to_graphics)
+                .collect();
+            self.record_ownership_release(&frame.command_buffer, &releases)?;
+            acquires.extend(releases);
+            frame.command_buffer.end(device)?;
+            command_buffers.push(*frame.command_buffer.handler.as_ref());
+        }
+        wait_semaphores.extend(compute.pending_graphics_semaphore.take());
+        let wait_dst_stage_mask = vec![PipelineStageFlags::ALL_COMMANDS; wait_semaphores.len()];
+        let signal_semaphores = [frame.compute_semaphore];
+        let submit_info = [SubmitInfo::default()
+            .command_buffers(&command_buffers)
+            .wait_semaphores(&wait_semaphores)
+            .wait_dst_stage_mask(&wait_dst_stage_mask)
+            .signal_semaphores(&signal_semaphores)];
+        if let Err(err) =
+            unsafe { device.queue_submit(compute.queue, &submit_info, ash::vk::Fence::null()) }
+        {
+            error!("Failed to submit the vulkan compute queue: {:?}", err);
+            frame.is_recording = false;
+            if err == ash::vk::Result::ERROR_DEVICE_LOST {
+                return Err(EngineError::DeviceLost);
+            }
+            return Err(EngineError::VulkanFailed);
+        }
+
+        // A frame without dispatches only waits for the previous one, it doesn't chain the next
+        let graphics_semaphore = if frame.is_recording {
+            compute.pending_graphics_semaphore = Some(frame.graphics_semaphore);
+            Some(frame.graphics_semaphore)
+        } else {
+            None
+        };
+        frame.is_recording = false;
+        Ok(ComputeSubmission {
+            wait_semaphores: vec![frame.compute_semaphore],
+            acquires,
+            graphics_semaphore,
+        })
+    }
+}
@@ -1,12 +1,36 @@
-use std::{borrow::Cow, ffi::CStr};
+use std::{borrow::Cow, collections::VecDeque, ffi::CStr, sync::Mutex};
 
 use ash::{ext::debug_utils, vk};
+use once_cell::sync::Lazy;
 
 use crate::{
     core::debug::errors::EngineError, debug_no_details, error, error_no_details, info_no_details,
     renderer::vulkan::vulkan_types::VulkanRendererBackend, warn_no_details,
 };
 
+/// Number of validation errors and warnings kept for the crash reports
+const VULKAN_RECENT_VALIDATION_MESSAGE_COUNT: usize = 32;
+
+/// The callback can be called from any thread, always lock it
+static VULKAN_RECENT_VALIDATION_MESSAGES: Lazy<Mutex<VecDeque<String>>> = Lazy::new(Mutex::default);
+
+fn record_validation_message(message: String) {
+    if let Ok(mut messages) = VULKAN_RECENT_VALIDATION_MESSAGES.lock() {
+        if messages.len() >= VULKAN_RECENT_VALIDATION_MESSAGE_COUNT {
+            messages.pop_front();
+        }
+        messages.push_back(message);
+    }
+}
+
+/// Last validation errors and warnings, oldest first
+pub(crate) fn get_recent_validation_messages() -> Vec<String> {
+    match VULKAN_RECENT_VALIDATION_MESSAGES.lock() {
+        Ok(messages) => messages.iter().cloned().collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
 /// Callback function for Vulkan debug messages.
 unsafe extern "system" fn vulkan_debug_callback(
     message_severity: vk::DebugUtilsMessageSeverityFlagsEXT,
@@ -29,6 +53,14 @@ unsafe extern "system" fn vulkan_debug_callback(
         CStr::from_ptr(callback_data.p_message).to_string_lossy()
     };
 
+    if message_severity == vk::DebugUtilsMessageSeverityFlagsEXT::ERROR
+        || message_severity == vk::DebugUtilsMessageSeverityFlagsEXT::WARNING
+    {
+        record_validation_message(format!(
+            "{message_severity:?} {message_type:?} [{message_id_name} ({message_id_number})] : {message}"
+        ));
+    }
+
     if message_severity == vk::DebugUtilsMessageSeverityFlagsEXT::ERROR {
         error_no_details!(
             "VULKAN: {message_type:?} [{message_id_name} ({message_id_number})] : {message}\n"
@@ -55,7 +87,7 @@ unsafe extern "system" fn vulkan_debug_callback(
 
 impl VulkanRendererBackend<'_> {
     pub fn get_debug_loader(&self) -> Result<&debug_utils::Instance, EngineError> {
-        match &self.context.debug_utils_loader {
+        match &self.context.core.debug_utils_loader {
             Some(instance) => Ok(instance),
             None => {
                 error!("Can't access the vulkan debug loader");
@@ -65,7 +97,7 @@ impl VulkanRendererBackend<'_> {
     }
 
     pub fn get_debug_callback(&self) -> Result<&vk::DebugUtilsMessengerEXT, EngineError> {
-        match &self.context.debug_callback {
+        match &self.context.core.debug_callback {
             Some(debug_callback) => Ok(debug_callback),
             None => {
                 error!("Can't access the vulkan debug callback");
@@ -105,8 +137,8 @@ impl VulkanRendererBackend<'_> {
             }
         };
 
-        self.context.debug_utils_loader = Some(debug_utils_loader);
-        self.context.debug_callback = Some(debug_callback);
+        self.context.core.debug_utils_loader = Some(debug_utils_loader);
+        self.context.core.debug_callback = Some(debug_callback);
         Ok(())
     }
 
@@ -115,8 +147,8 @@ impl VulkanRendererBackend<'_> {
             self.get_debug_loader()?
                 .destroy_debug_utils_messenger(*self.get_debug_callback()?, self.get_allocator()?);
         }
-        self.context.debug_callback = None;
-        self.context.debug_utils_loader = None;
+        self.context.core.debug_callback = None;
+        self.context.core.debug_utils_loader = None;
         Ok(())
     }
 }
@@ -1,32 +1,47 @@
+use std::ffi::c_char;
+
 use ash::{
-    vk::{DeviceCreateInfo, DeviceQueueCreateInfo},
+    vk::{DeviceCreateInfo, DeviceQueueCreateInfo, PhysicalDeviceDescriptorIndexingFeatures},
     Device,
 };
 
 use crate::{
-    core::debug::errors::EngineError, error, renderer::vulkan::vulkan_types::VulkanRendererBackend,
+    core::debug::errors::EngineError,
+    error,
+    renderer::vulkan::vulkan_types::{VulkanCoreContext, VulkanRendererBackend},
 };
 
+impl VulkanCoreContext<'_> {
+    pub fn get_device(&self) -> Result<&Device, EngineError> {
+        match &self.device {
+            Some(device) => Ok(device),
+            None => {
+                error!("Can't access the vulkan device");
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
+}
+
 impl VulkanRendererBackend<'_> {
     fn get_device_queue_create_infos(&self) -> Result<Vec<DeviceQueueCreateInfo>, EngineError> {
         // NOTE: do not create additional queues for shared indices
-        let present_shares_graphics_queue =
-            self.get_queues()?.graphics_family_index == self.get_queues()?.present_family_index;
-        let transfer_shares_graphics_queue =
-            self.get_queues()?.graphics_family_index == self.get_queues()?.transfer_family_index;
-
-        let mut queue_indices = vec![self.get_queues()?.graphics_family_index.unwrap()];
-        if !present_shares_graphics_queue {
-            queue_indices.push(self.get_queues()?.present_family_index.unwrap());
-        }
-        if !transfer_shares_graphics_queue {
-            queue_indices.push(self.get_queues()?.transfer_family_index.unwrap());
+        let queues = self.get_queues()?;
+        let mut queue_indices = vec![queues.get_graphics_family_index()?];
+        for queue_index in [
+            queues.get_present_family_index()?,
+            queues.get_compute_family_index()?,
+            queues.get_transfer_family_index()?,
+        ] {
+            if !queue_indices.contains(&queue_index) {
+                queue_indices.push(queue_index);
+            }
         }
 
         let mut queue_create_infos: Vec<DeviceQueueCreateInfo> = Vec::new();
         for queue_index in queue_indices {
             let queue_create_info = DeviceQueueCreateInfo::default()
-                .queue_family_index(queue_index as u32)
+                .queue_family_index(queue_index)
                 // TODO: change the queue priorities
                 .queue_priorities(&[1.]);
             queue_create_infos.push(queue_create_info);
@@ -47,11 +62,31 @@ impl VulkanRendererBackend<'_> {
         };
 
         let requirements = self.get_device_requirements()?;
+        let extension_names: Vec<*const c_char> = requirements
+            .extensions
+            .iter()
+            .map(|extension| extension.as_ptr())
+            .collect();
+
+        // Optional features used by the renderer statistics
+        let mut enabled_features = requirements.features;
+        enabled_features.pipeline_statistics_query =
+            physical_device_info.features.pipeline_statistics_query;
 
-        let device_create_info = DeviceCreateInfo::default()
+        // Optional descriptor indexing used by the bindless textures
+        let mut descriptor_indexing_features = PhysicalDeviceDescriptorIndexingFeatures::default()
+            .runtime_descriptor_array(true)
+            .descriptor_binding_partially_bound(true)
+            .descriptor_binding_sampled_image_update_after_bind(true)
+            .descriptor_binding_update_unused_while_pending(true);
+
+        let mut device_create_info = DeviceCreateInfo::default()
             .queue_create_infos(queue_create_infos.as_slice())
-            .enabled_features(&requirements.features)
-            .enabled_extension_names(requirements.extensions.as_slice());
+            .enabled_features(&enabled_features)
+            .enabled_extension_names(extension_names.as_slice());
+        if physical_device_info.has_bindless_textures() {
+            device_create_info = device_create_info.push_next(&mut descriptor_indexing_features);
+        }
 
         unsafe {
             match self.get_instance()?.create_device(
@@ -59,7 +94,7 @@ impl VulkanRendererBackend<'_> {
                 &device_create_info,
                 self.get_allocator()?,
             ) {
-                Ok(device) => self.context.device = Some(device),
+                Ok(device) => self.context.core.device = Some(device),
                 Err(err) => {
                     error!("Failed to initialize the vulkan logical device: {:?}", err);
                     return Err(EngineError::VulkanFailed);
@@ -74,18 +109,12 @@ impl VulkanRendererBackend<'_> {
         unsafe {
             self.get_device()?.destroy_device(self.get_allocator()?);
         }
-        self.context.device = None;
+        self.context.core.device = None;
         Ok(())
     }
 
     pub fn get_device(&self) -> Result<&Device, EngineError> {
-        match &self.context.device {
-            Some(device) => Ok(device),
-            None => {
-                error!("Can't access the vulkan device");
-                Err(EngineError::AccessFailed)
-            }
-        }
+        self.context.core.get_device()
     }
 
     pub fn device_wait_idle(&self) -> Result<(), EngineError> {
@@ -1,9 +1,11 @@
 use std::ffi::CStr;
 
-use ash::vk::PhysicalDeviceFeatures;
+use ash::vk::{Format, PhysicalDeviceFeatures};
 
 use crate::{
-    core::debug::errors::EngineError, error, renderer::vulkan::vulkan_types::VulkanRendererBackend,
+    core::debug::errors::EngineError,
+    error,
+    renderer::{renderer_types::DepthFormat, vulkan::vulkan_types::VulkanRendererBackend},
 };
 
 pub(crate) struct DeviceRequirements {
@@ -13,15 +15,28 @@ pub(crate) struct DeviceRequirements {
     pub does_require_transfer_queue: bool,
     pub is_discrete_gpu: bool,
     pub features: PhysicalDeviceFeatures,
-    pub extensions: Vec<*const i8>,
+    pub extensions: Vec<&'static CStr>,
+    /// Depth formats to pick from, by order of preference
+    pub depth_format_preferences: Vec<Format>,
+    /// Only pick depth formats with a stencil component
+    pub does_require_stencil: bool,
 }
 
 impl Default for DeviceRequirements {
     fn default() -> Self {
-        let required_features = PhysicalDeviceFeatures::default().sampler_anisotropy(true);
+        // The reflection probes are sampled from a cube array
+        let required_features = PhysicalDeviceFeatures::default()
+            .sampler_anisotropy(true)
+            .image_cube_array(true);
 
-        let required_extensions =
-            vec![unsafe { CStr::from_bytes_with_nul_unchecked(b"VK_KHR_swapchain\0").as_ptr() }];
+        #[allow(unused_mut)]
+        let mut required_extensions =
+            vec![unsafe { CStr::from_bytes_with_nul_unchecked(b"VK_KHR_swapchain\0") }];
+
+        // Must be enabled on the portability implementations like MoltenVK
+        #[cfg(target_os = "macos")]
+        required_extensions
+            .push(unsafe { CStr::from_bytes_with_nul_unchecked(b"VK_KHR_portability_subset\0") });
 
         Self {
             does_require_graphics_queue: true,
@@ -31,24 +46,76 @@ impl Default for DeviceRequirements {
             is_discrete_gpu: false,
             features: required_features,
             extensions: required_extensions,
+            depth_format_preferences: vec![
+                Format::D32_SFLOAT,
+                Format::D32_SFLOAT_S8_UINT,
+                Format::D24_UNORM_S8_UINT,
+            ],
+            does_require_stencil: false,
         }
     }
 }
 
+impl DeviceRequirements {
+    pub fn depth_format_preferences(mut self, depth_format_preferences: Vec<Format>) -> Self {
+        self.depth_format_preferences = depth_format_preferences;
+        self
+    }
+
+    pub fn does_require_stencil(mut self, does_require_stencil: bool) -> Self {
+        self.does_require_stencil = does_require_stencil;
+        self
+    }
+}
+
+/// Returns true if the depth format also stores a stencil value
+pub(crate) fn format_has_stencil(format: Format) -> bool {
+    matches!(
+        format,
+        Format::S8_UINT
+            | Format::D16_UNORM_S8_UINT
+            | Format::D24_UNORM_S8_UINT
+            | Format::D32_SFLOAT_S8_UINT
+    )
+}
+
+pub(crate) fn depth_format_to_vulkan(format: DepthFormat) -> Format {
+    match format {
+        DepthFormat::D16 => Format::D16_UNORM,
+        DepthFormat::D32 => Format::D32_SFLOAT,
+        DepthFormat::D24S8 => Format::D24_UNORM_S8_UINT,
+        DepthFormat::D32S8 => Format::D32_SFLOAT_S8_UINT,
+    }
+}
+
 impl VulkanRendererBackend<'_> {
+    /// The depth formats come from the application parameters
     pub fn device_requirements_init(&mut self) -> Result<(), EngineError> {
-        // TODO: make the device requirements configurable
-        self.context.device_requirements = Some(DeviceRequirements::default());
+        if self.depth_stencil.formats.is_empty() {
+            error!("Failed to init the vulkan device requirements: no depth format is given");
+            return Err(EngineError::InvalidValue);
+        }
+        let depth_format_preferences = self
+            .depth_stencil
+            .formats
+            .iter()
+            .map(|format| depth_format_to_vulkan(*format))
+            .collect();
+        self.context.core.device_requirements = Some(
+            DeviceRequirements::default()
+                .depth_format_preferences(depth_format_preferences)
+                .does_require_stencil(self.depth_stencil.require_stencil),
+        );
         Ok(())
     }
 
     pub fn device_requirements_shutdown(&mut self) -> Result<(), EngineError> {
-        self.context.device_requirements = None;
+        self.context.core.device_requirements = None;
         Ok(())
     }
 
     pub fn get_device_requirements(&self) -> Result<&DeviceRequirements, EngineError> {
-        match &self.context.device_requirements {
+        match &self.context.core.device_requirements {
             Some(requirements) => Ok(requirements),
             None => {
                 error!("Can't access the vulkan device requirements");
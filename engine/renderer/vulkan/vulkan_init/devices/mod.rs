@@ -1,4 +1,4 @@
-pub mod device;
-pub mod device_requirements;
-pub mod physical_device;
-pub mod queues;
+pub(crate) mod device;
+pub(crate) mod device_requirements;
+pub(crate) mod physical_device;
+pub(crate) mod queues;
@@ -1,21 +1,41 @@
 use std::ffi::CStr;
 
 use ash::vk::{
-    api_version_major, api_version_minor, api_version_patch, ExtensionProperties, Format,
-    FormatFeatureFlags, MemoryPropertyFlags, PhysicalDevice, PhysicalDeviceFeatures,
-    PhysicalDeviceMemoryProperties, PhysicalDeviceProperties, PhysicalDeviceType,
+    api_version_major, api_version_minor, api_version_patch, make_api_version, ExtensionProperties,
+    Format, FormatFeatureFlags, ImageTiling, MemoryPropertyFlags, PhysicalDevice,
+    PhysicalDeviceDescriptorIndexingFeatures, PhysicalDeviceDescriptorIndexingProperties,
+    PhysicalDeviceFeatures, PhysicalDeviceFeatures2, PhysicalDeviceMemoryProperties,
+    PhysicalDeviceProperties, PhysicalDeviceProperties2, PhysicalDeviceType, SampleCountFlags,
+    API_VERSION_1_2, API_VERSION_1_3, TRUE,
 };
 
 use crate::{
     core::debug::errors::EngineError,
     debug, error,
-    renderer::vulkan::{
-        vulkan_types::VulkanRendererBackend,
-        vulkan_utils::device_features::physical_device_features_to_vector,
+    renderer::{
+        renderer_types::AntiAliasing,
+        vulkan::{
+            vulkan_types::VulkanRendererBackend,
+            vulkan_utils::device_features::physical_device_features_to_vector,
+        },
     },
+    warn,
 };
 
-use super::{device_requirements::DeviceRequirements, queues::Queues};
+use super::{
+    device_requirements::{format_has_stencil, DeviceRequirements},
+    queues::Queues,
+};
+
+/// Features depending on the negotiated api version, or on an extension before it
+/// They still have to be enabled when creating the logical device before being used
+#[derive(Default, Debug, Clone, Copy)]
+pub(crate) struct VersionedFeatures {
+    /// Core since vulkan 1.3, else `VK_KHR_dynamic_rendering'
+    pub has_dynamic_rendering: bool,
+    /// Core since vulkan 1.2, else `VK_KHR_timeline_semaphore'
+    pub has_timeline_semaphores: bool,
+}
 
 #[derive(Default, Debug)]
 pub(crate) struct PhysicalDeviceInfo {
@@ -25,6 +45,42 @@ pub(crate) struct PhysicalDeviceInfo {
     pub extension_properties: Vec<ExtensionProperties>,
     pub memory_properties: PhysicalDeviceMemoryProperties,
     pub depth_format: Option<Format>,
+    /// Lowest of the instance and device versions, without the patch number
+    pub api_version: u32,
+    pub versioned_features: VersionedFeatures,
+    /// Textures of a bindless array, 0 when the device lacks the descriptor indexing features
+    pub max_bindless_textures: u32,
+}
+
+impl PhysicalDeviceInfo {
+    pub fn has_extension(&self, extension: &CStr) -> bool {
+        self.extension_properties.iter().any(|properties| {
+            properties
+                .extension_name_as_c_str()
+                .is_ok_and(|name| name == extension)
+        })
+    }
+
+    /// Timestamps can be written on every graphics and compute queue
+    pub fn has_gpu_timestamps(&self) -> bool {
+        self.properties.limits.timestamp_compute_and_graphics == TRUE
+    }
+
+    /// The textures can be sampled from a single descriptor array
+    pub fn has_bindless_textures(&self) -> bool {
+        self.max_bindless_textures > 0
+    }
+
+    /// Core features of the api version or their extension
+    fn get_versioned_features(&self) -> VersionedFeatures {
+        let has_feature = |core_version: u32, extension: &CStr| {
+            self.api_version >= core_version || self.has_extension(extension)
+        };
+        VersionedFeatures {
+            has_dynamic_rendering: has_feature(API_VERSION_1_3, c"VK_KHR_dynamic_rendering"),
+            has_timeline_semaphores: has_feature(API_VERSION_1_2, c"VK_KHR_timeline_semaphore"),
+        }
+    }
 }
 
 impl VulkanRendererBackend<'_> {
@@ -67,12 +123,11 @@ impl VulkanRendererBackend<'_> {
         requirements: &DeviceRequirements,
         physical_device_info: &PhysicalDeviceInfo,
     ) -> Result<bool, EngineError> {
-        'cur_extension: for required_extension in &requirements.extensions {
-            let required_extension_cstr = unsafe { CStr::from_ptr(*required_extension) };
+        'cur_extension: for required_extension_cstr in &requirements.extensions {
             for found_extension in &physical_device_info.extension_properties {
                 let found_extension_cstr =
                     unsafe { CStr::from_ptr(found_extension.extension_name.as_ptr()) };
-                if found_extension_cstr == required_extension_cstr {
+                if found_extension_cstr == *required_extension_cstr {
                     continue 'cur_extension;
                 }
             }
@@ -106,6 +161,44 @@ impl VulkanRendererBackend<'_> {
         Ok(true)
     }
 
+    /// Textures the device can sample from an array written after being bound, 0 if unsupported
+    /// The descriptor indexing is only used from vulkan 1.2, where it is core
+    fn get_max_bindless_textures(
+        &self,
+        physical_device: &PhysicalDevice,
+        api_version: u32,
+    ) -> Result<u32, EngineError> {
+        if api_version < API_VERSION_1_2 {
+            return Ok(0);
+        }
+        let instance = self.get_instance()?;
+
+        let mut indexing_features = PhysicalDeviceDescriptorIndexingFeatures::default();
+        let mut features = PhysicalDeviceFeatures2::default().push_next(&mut indexing_features);
+        unsafe { instance.get_physical_device_features2(*physical_device, &mut features) };
+        let has_features = [
+            indexing_features.runtime_descriptor_array,
+            indexing_features.descriptor_binding_partially_bound,
+            indexing_features.descriptor_binding_sampled_image_update_after_bind,
+            indexing_features.descriptor_binding_update_unused_while_pending,
+        ]
+        .iter()
+        .all(|feature| *feature == TRUE);
+        if !has_features {
+            return Ok(0);
+        }
+
+        let mut indexing_properties = PhysicalDeviceDescriptorIndexingProperties::default();
+        let mut properties =
+            PhysicalDeviceProperties2::default().push_next(&mut indexing_properties);
+        unsafe { instance.get_physical_device_properties2(*physical_device, &mut properties) };
+        Ok(indexing_properties
+            .max_descriptor_set_update_after_bind_samplers
+            .min(indexing_properties.max_descriptor_set_update_after_bind_sampled_images)
+            .min(indexing_properties.max_per_stage_descriptor_update_after_bind_samplers)
+            .min(indexing_properties.max_per_stage_descriptor_update_after_bind_sampled_images))
+    }
+
     fn physical_device_info_init(
         &self,
         physical_device: &PhysicalDevice,
@@ -136,14 +229,28 @@ impl VulkanRendererBackend<'_> {
             }
         };
 
-        Ok(PhysicalDeviceInfo {
+        // The device can't use a version the instance did not request
+        let api_version = properties.api_version.min(self.context.core.api_version);
+        let mut physical_device_info = PhysicalDeviceInfo {
             properties,
             features,
             extension_properties,
             memory_properties,
             queues: Queues::default(),
             depth_format: None,
-        })
+            api_version: make_api_version(
+                0,
+                api_version_major(api_version),
+                api_version_minor(api_version),
+                0,
+            ),
+            versioned_features: VersionedFeatures::default(),
+            max_bindless_textures: 0,
+        };
+        physical_device_info.versioned_features = physical_device_info.get_versioned_features();
+        physical_device_info.max_bindless_textures =
+            self.get_max_bindless_textures(physical_device, physical_device_info.api_version)?;
+        Ok(physical_device_info)
     }
 
     fn is_device_suitable(
@@ -206,11 +313,25 @@ impl VulkanRendererBackend<'_> {
         );
 
         debug!(
-            "\tVulkan API version: {:?}.{:?}.{:?}\n\n",
+            "\tVulkan API version: {:?}.{:?}.{:?}",
             api_version_major(device_info.properties.api_version),
             api_version_minor(device_info.properties.api_version),
             api_version_patch(device_info.properties.api_version),
         );
+
+        debug!(
+            "\tNegotiated Vulkan API version: {:?}.{:?}, {:?}",
+            api_version_major(device_info.api_version),
+            api_version_minor(device_info.api_version),
+            device_info.versioned_features,
+        );
+
+        debug!("\tGPU timestamps: {:?}", device_info.has_gpu_timestamps());
+
+        debug!(
+            "\tBindless textures: {:?}\n\n",
+            device_info.max_bindless_textures
+        );
     }
 
     pub fn physical_device_init(&mut self) -> Result<(), EngineError> {
@@ -239,8 +360,8 @@ impl VulkanRendererBackend<'_> {
             if is_suitable {
                 debug!("Found physical device");
                 Self::display_physical_device(&physical_device, &device_info);
-                self.context.physical_device = Some(physical_device);
-                self.context.physical_device_info = Some(device_info);
+                self.context.core.physical_device = Some(physical_device);
+                self.context.core.physical_device_info = Some(device_info);
                 return Ok(());
             }
         }
@@ -250,13 +371,13 @@ impl VulkanRendererBackend<'_> {
     }
 
     pub fn physical_device_shutdown(&mut self) -> Result<(), EngineError> {
-        self.context.physical_device_info = None;
-        self.context.physical_device = None;
+        self.context.core.physical_device_info = None;
+        self.context.core.physical_device = None;
         Ok(())
     }
 
     pub fn get_physical_device(&self) -> Result<&PhysicalDevice, EngineError> {
-        match &self.context.physical_device {
+        match &self.context.core.physical_device {
             Some(device) => Ok(device),
             None => {
                 error!("Can't access the vulkan physical device");
@@ -266,7 +387,17 @@ impl VulkanRendererBackend<'_> {
     }
 
     pub fn get_physical_device_info(&self) -> Result<&PhysicalDeviceInfo, EngineError> {
-        match &self.context.physical_device_info {
+        match &self.context.core.physical_device_info {
+            Some(device_info) => Ok(device_info),
+            None => {
+                error!("Can't access the vulkan physical device info");
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
+
+    pub fn get_physical_device_info_mut(&mut self) -> Result<&mut PhysicalDeviceInfo, EngineError> {
+        match &mut self.context.core.physical_device_info {
             Some(device_info) => Ok(device_info),
             None => {
                 error!("Can't access the vulkan physical device info");
@@ -297,34 +428,80 @@ impl VulkanRendererBackend<'_> {
         Err(EngineError::VulkanFailed)
     }
 
+    /// Check if the device supports the given usage of a format
+    pub(crate) fn device_is_format_supported(
+        &self,
+        format: Format,
+        tiling: ImageTiling,
+        features: FormatFeatureFlags,
+    ) -> Result<bool, EngineError> {
+        let physical_device = self.get_physical_device()?;
+        let format_properties = unsafe {
+            self.get_instance()?
+                .get_physical_device_format_properties(*physical_device, format)
+        };
+        let supported_features = match tiling {
+            ImageTiling::LINEAR => format_properties.linear_tiling_features,
+            _ => format_properties.optimal_tiling_features,
+        };
+        Ok(supported_features.contains(features))
+    }
+
     pub(crate) fn device_detect_depth_format(&mut self) -> Result<(), EngineError> {
-        // Format candidates
-        let candidates = [
-            Format::D32_SFLOAT,
-            Format::D32_SFLOAT_S8_UINT,
-            Format::D24_UNORM_S8_UINT,
-        ];
+        let requirements = self.get_device_requirements()?;
+        let does_require_stencil = requirements.does_require_stencil;
+        let candidates = requirements.depth_format_preferences.clone();
+        // The depth images are always created with an optimal tiling
         let flags = FormatFeatureFlags::DEPTH_STENCIL_ATTACHMENT;
-        let physical_device = self.context.physical_device.as_ref().unwrap();
 
         for candidate in candidates {
-            let format_properties = unsafe {
-                self.get_instance()?
-                    .get_physical_device_format_properties(*physical_device, candidate)
-            };
-            if format_properties.linear_tiling_features.intersects(flags) {
-                let device_info = self.context.physical_device_info.as_mut().unwrap();
-                device_info.depth_format = Some(candidate);
-                return Ok(());
+            if does_require_stencil && !format_has_stencil(candidate) {
+                continue;
             }
-            if format_properties.optimal_tiling_features.intersects(flags) {
-                let device_info = self.context.physical_device_info.as_mut().unwrap();
+            if self.device_is_format_supported(candidate, ImageTiling::OPTIMAL, flags)? {
+                debug!("Vulkan depth format: {:?}", candidate);
+                let device_info = self.get_physical_device_info_mut()?;
                 device_info.depth_format = Some(candidate);
                 return Ok(());
             }
         }
 
-        error!("Failed to detect the vulkan physical device depth forma");
+        error!("Failed to detect the vulkan physical device depth format");
         Err(EngineError::VulkanFailed)
     }
+
+    /// Use the highest sample count supported by both the color and depth attachments
+    /// that is not above the requested one
+    pub(crate) fn device_select_sample_count(
+        &mut self,
+        anti_aliasing: AntiAliasing,
+    ) -> Result<(), EngineError> {
+        let limits = self.get_physical_device_info()?.properties.limits;
+        let supported_counts =
+            limits.framebuffer_color_sample_counts & limits.framebuffer_depth_sample_counts;
+        let requested_count = anti_aliasing.get_sample_count();
+        let mut sample_count = requested_count;
+        while sample_count > 1
+            && !supported_counts.contains(SampleCountFlags::from_raw(sample_count))
+        {
+            sample_count /= 2;
+        }
+        if sample_count != requested_count {
+            warn!(
+                "The vulkan physical device does not support {:?}, using {} samples per pixel instead",
+                anti_aliasing, sample_count
+            );
+        }
+        debug!("Vulkan sample count: {}", sample_count);
+        self.sample_count = SampleCountFlags::from_raw(sample_count);
+        Ok(())
+    }
+
+    /// Returns true if the detected depth format has a stencil component
+    pub fn device_has_stencil(&self) -> Result<bool, EngineError> {
+        Ok(self
+            .get_physical_device_info()?
+            .depth_format
+            .is_some_and(format_has_stencil))
+    }
 }
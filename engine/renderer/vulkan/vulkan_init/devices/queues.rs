@@ -23,6 +23,54 @@ pub(crate) struct Queues {
     pub transfer_queue: Option<Queue>,
 }
 
+fn get_selected_family_index(index: Option<usize>, name: &str) -> Result<u32, EngineError> {
+    index.map(|index| index as u32).ok_or_else(|| {
+        error!("The vulkan {} queue family was not selected", name);
+        EngineError::NotInitialized
+    })
+}
+
+fn get_created_queue(queue: Option<Queue>, name: &str) -> Result<Queue, EngineError> {
+    queue.ok_or_else(|| {
+        error!("The vulkan {} queue was not created", name);
+        EngineError::NotInitialized
+    })
+}
+
+impl Queues {
+    pub fn get_graphics_family_index(&self) -> Result<u32, EngineError> {
+        get_selected_family_index(self.graphics_family_index, "graphics")
+    }
+
+    pub fn get_present_family_index(&self) -> Result<u32, EngineError> {
+        get_selected_family_index(self.present_family_index, "present")
+    }
+
+    pub fn get_compute_family_index(&self) -> Result<u32, EngineError> {
+        get_selected_family_index(self.compute_family_index, "compute")
+    }
+
+    pub fn get_transfer_family_index(&self) -> Result<u32, EngineError> {
+        get_selected_family_index(self.transfer_family_index, "transfer")
+    }
+
+    pub fn get_graphics_queue(&self) -> Result<Queue, EngineError> {
+        get_created_queue(self.graphics_queue, "graphics")
+    }
+
+    pub fn get_present_queue(&self) -> Result<Queue, EngineError> {
+        get_created_queue(self.present_queue, "present")
+    }
+
+    pub fn get_compute_queue(&self) -> Result<Queue, EngineError> {
+        get_created_queue(self.compute_queue, "compute")
+    }
+
+    pub fn get_transfer_queue(&self) -> Result<Queue, EngineError> {
+        get_created_queue(self.transfer_queue, "transfer")
+    }
+}
+
 impl VulkanRendererBackend<'_> {
     pub(crate) fn queue_family_properties_create(
         &self,
@@ -92,23 +140,24 @@ impl VulkanRendererBackend<'_> {
 
     pub fn queues_init(&mut self) -> Result<(), EngineError> {
         let device = self.get_device()?;
+        let queues = self.get_queues()?;
+        let graphics_family_index = queues.get_graphics_family_index()?;
+        let present_family_index = queues.get_present_family_index()?;
+        let compute_family_index = queues.get_compute_family_index()?;
+        let transfer_family_index = queues.get_transfer_family_index()?;
+
         let graphics_queue;
         let present_queue;
         let compute_queue;
         let transfer_queue;
-
         unsafe {
-            graphics_queue = device
-                .get_device_queue(self.get_queues()?.graphics_family_index.unwrap() as u32, 0);
-            present_queue =
-                device.get_device_queue(self.get_queues()?.present_family_index.unwrap() as u32, 0);
-            compute_queue =
-                device.get_device_queue(self.get_queues()?.compute_family_index.unwrap() as u32, 0);
-            transfer_queue = device
-                .get_device_queue(self.get_queues()?.transfer_family_index.unwrap() as u32, 0);
+            graphics_queue = device.get_device_queue(graphics_family_index, 0);
+            present_queue = device.get_device_queue(present_family_index, 0);
+            compute_queue = device.get_device_queue(compute_family_index, 0);
+            transfer_queue = device.get_device_queue(transfer_family_index, 0);
         }
 
-        let physical_device_info = self.context.physical_device_info.as_mut().unwrap();
+        let physical_device_info = self.get_physical_device_info_mut()?;
         physical_device_info.queues.graphics_queue = Some(graphics_queue);
         physical_device_info.queues.present_queue = Some(present_queue);
         physical_device_info.queues.compute_queue = Some(compute_queue);
@@ -6,7 +6,7 @@ use crate::{
 
 impl VulkanRendererBackend<'_> {
     pub fn get_entry(&self) -> Result<&Entry, EngineError> {
-        match &self.context.entry {
+        match &self.context.core.entry {
             Some(entry) => Ok(entry),
             None => {
                 error!("Can't access the vulkan entry");
@@ -17,7 +17,7 @@ impl VulkanRendererBackend<'_> {
 
     pub fn entry_init(&mut self) -> Result<(), EngineError> {
         unsafe {
-            self.context.entry = Some(match Entry::load() {
+            self.context.core.entry = Some(match Entry::load() {
                 Ok(entry) => entry,
                 Err(err) => {
                     error!("Failed to load the vulkan library: {:?}", err);
@@ -30,7 +30,7 @@ impl VulkanRendererBackend<'_> {
     }
 
     pub fn entry_shutdown(&mut self) -> Result<(), EngineError> {
-        self.context.entry = None;
+        self.context.core.entry = None;
         Ok(())
     }
 }
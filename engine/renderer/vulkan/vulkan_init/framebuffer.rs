@@ -1,7 +1,10 @@
-use std::cmp::{max, min};
+use std::{
+    cmp::{max, min},
+    collections::HashMap,
+};
 
 use ash::{
-    vk::{self, FramebufferCreateInfo, ImageView},
+    vk::{self, FramebufferCreateInfo, ImageView, RenderPass},
     Device,
 };
 
@@ -13,16 +16,9 @@ use crate::{
 
 use super::renderpass::Renderpass;
 
-#[derive(PartialEq)]
-pub(crate) enum FramebufferState {
-    Running,
-    Idle,
-}
-
 pub(crate) struct Framebuffer {
     pub handler: Box<vk::Framebuffer>,
     pub attachments: Vec<ImageView>,
-    pub state: FramebufferState,
 }
 
 impl Framebuffer {
@@ -57,7 +53,6 @@ impl Framebuffer {
         Ok(Framebuffer {
             handler: Box::new(handler),
             attachments,
-            state: FramebufferState::Running,
         })
     }
 
@@ -66,15 +61,46 @@ impl Framebuffer {
         device: &Device,
         allocator: Option<&vk::AllocationCallbacks<'_>>,
     ) -> Result<(), EngineError> {
-        if self.state == FramebufferState::Running {
-            unsafe {
-                device.destroy_framebuffer(*self.handler.as_ref(), allocator);
-            }
+        unsafe {
+            device.destroy_framebuffer(*self.handler.as_ref(), allocator);
         }
         Ok(())
     }
 }
 
+/// A change of renderpass, attachments or size gives another framebuffer
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct FramebufferKey {
+    renderpass: RenderPass,
+    attachments: Vec<ImageView>,
+    width: u32,
+    height: u32,
+}
+
+/// Framebuffers created when first used and destroyed with their renderpass or one of their attachments
+/// The handles of destroyed objects can be reused by new ones so the entries can't outlive them
+#[derive(Default)]
+pub(crate) struct FramebufferCache {
+    framebuffers: HashMap<FramebufferKey, Framebuffer>,
+}
+
+impl FramebufferCache {
+    fn take_framebuffers<F: Fn(&FramebufferKey) -> bool>(
+        &mut self,
+        is_taken: F,
+    ) -> Vec<Framebuffer> {
+        let keys: Vec<FramebufferKey> = self
+            .framebuffers
+            .keys()
+            .filter(|key| is_taken(key))
+            .cloned()
+            .collect();
+        keys.iter()
+            .filter_map(|key| self.framebuffers.remove(key))
+            .collect()
+    }
+}
+
 impl VulkanRendererBackend<'_> {
     pub fn framebuffer_dimensions_init(&mut self) -> Result<(), EngineError> {
         let (width, height) = application_get_framebuffer_size()?;
@@ -99,45 +125,92 @@ impl VulkanRendererBackend<'_> {
         Ok(())
     }
 
-    pub fn swapchain_framebuffers_shutdown(&mut self) -> Result<(), EngineError> {
-        let framebuffers = &self.context.swapchain.as_ref().unwrap().framebuffers;
-        for buffer in framebuffers {
-            buffer.destroy(self.get_device()?, self.get_allocator()?)?;
-        }
-        let framebuffers = &mut self.context.swapchain.as_mut().unwrap().framebuffers;
-        for buffer in framebuffers.iter_mut() {
-            buffer.state = FramebufferState::Idle;
-        }
+    pub fn framebuffer_cache_init(&mut self) -> Result<(), EngineError> {
+        self.framebuffer_cache_shutdown()?;
+        Ok(())
+    }
+
+    pub fn framebuffer_cache_shutdown(&mut self) -> Result<(), EngineError> {
+        let framebuffers = self
+            .context
+            .frame
+            .framebuffer_cache
+            .get_mut()
+            .take_framebuffers(|_| true);
+        self.destroy_framebuffers(&framebuffers)
+    }
 
+    fn destroy_framebuffers(&self, framebuffers: &[Framebuffer]) -> Result<(), EngineError> {
+        let device = self.get_device()?;
+        let allocator = self.get_allocator()?;
+        for framebuffer in framebuffers {
+            framebuffer.destroy(device, allocator)?;
+        }
         Ok(())
     }
 
-    pub fn swapchain_framebuffers_init(&mut self) -> Result<(), EngineError> {
-        // destroy swapchain framebuffers
-        self.swapchain_framebuffers_shutdown()?;
-
-        let depth_attachment = self.get_swapchain()?.depth_attachment.as_ref().unwrap();
-        let image_views: &Vec<ImageView> = self.get_swapchain()?.image_views.as_ref();
-        let swpachain_extent = self.get_swapchain()?.extent;
-
-        let mut framebuffers = Vec::new();
-
-        for image_view in image_views {
-            // TODO: make this dynamic based on the currently configured attachments
-            let attachments = vec![*image_view, depth_attachment.image_view.unwrap()];
-            let new_framebuffer = Framebuffer::create(
-                self.get_device()?,
-                self.get_allocator()?,
-                swpachain_extent.width,
-                swpachain_extent.height,
-                &attachments,
-                self.get_renderpass()?,
-            )?;
-            framebuffers.push(new_framebuffer);
+    /// Framebuffer of the renderpass drawing into the attachments, created if it does not exist yet
+    pub(crate) fn framebuffer_cache_get(
+        &self,
+        renderpass: &Renderpass,
+        attachments: &[ImageView],
+        width: u32,
+        height: u32,
+    ) -> Result<vk::Framebuffer, EngineError> {
+        let key = FramebufferKey {
+            renderpass: renderpass.handler,
+            attachments: attachments.to_vec(),
+            width,
+            height,
+        };
+        let mut framebuffer_cache = self.context.frame.framebuffer_cache.borrow_mut();
+        if let Some(framebuffer) = framebuffer_cache.framebuffers.get(&key) {
+            return Ok(*framebuffer.handler.as_ref());
         }
+        let framebuffer = match Framebuffer::create(
+            self.get_device()?,
+            self.get_allocator()?,
+            width,
+            height,
+            attachments,
+            renderpass,
+        ) {
+            Ok(framebuffer) => framebuffer,
+            Err(err) => {
+                error!("Failed to create a cached vulkan framebuffer: {:?}", err);
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        let handler = *framebuffer.handler.as_ref();
+        framebuffer_cache.framebuffers.insert(key, framebuffer);
+        Ok(handler)
+    }
 
-        self.context.swapchain.as_mut().unwrap().framebuffers = framebuffers;
+    /// Destroy the framebuffers using the attachment, it must not be used by the frames in flight anymore
+    pub(crate) fn framebuffer_cache_evict_attachment(
+        &self,
+        image_view: ImageView,
+    ) -> Result<(), EngineError> {
+        let framebuffers = self
+            .context
+            .frame
+            .framebuffer_cache
+            .borrow_mut()
+            .take_framebuffers(|key| key.attachments.contains(&image_view));
+        self.destroy_framebuffers(&framebuffers)
+    }
 
-        Ok(())
+    /// Destroy the framebuffers of the renderpass, it must not be used by the frames in flight anymore
+    pub(crate) fn framebuffer_cache_evict_renderpass(
+        &self,
+        renderpass: RenderPass,
+    ) -> Result<(), EngineError> {
+        let framebuffers = self
+            .context
+            .frame
+            .framebuffer_cache
+            .borrow_mut()
+            .take_framebuffers(|key| key.renderpass == renderpass);
+        self.destroy_framebuffers(&framebuffers)
     }
 }
@@ -1,15 +1,21 @@
 use std::ffi::{CStr, CString};
 
-use ash::vk::{make_api_version, ApplicationInfo, InstanceCreateInfo, API_VERSION_1_3};
+use ash::vk::{
+    api_version_major, api_version_minor, make_api_version, ApplicationInfo, InstanceCreateInfo,
+    API_VERSION_1_0, API_VERSION_1_3,
+};
 
 use crate::{
     core::debug::errors::EngineError, debug, error, platforms::platform::Platform,
     renderer::vulkan::vulkan_types::VulkanRendererBackend,
 };
 
+/// Highest vulkan version the engine knows how to use
+pub const VULKAN_MAX_API_VERSION: u32 = API_VERSION_1_3;
+
 impl VulkanRendererBackend<'_> {
     pub fn get_instance(&self) -> Result<&ash::Instance, EngineError> {
-        match &self.context.instance {
+        match &self.context.core.instance {
             Some(instance) => Ok(instance),
             None => {
                 error!("Can't access the vulkan instance");
@@ -71,6 +77,25 @@ impl VulkanRendererBackend<'_> {
         required_extensions
             .push(unsafe { CStr::from_bytes_with_nul_unchecked(b"VK_EXT_debug_utils\0").as_ptr() });
 
+        // Optional, the surfaces only report their hdr color spaces with it
+        let available_extensions = match unsafe {
+            self.get_entry()?
+                .enumerate_instance_extension_properties(None)
+        } {
+            Ok(extensions) => extensions,
+            Err(err) => {
+                error!("Failed to enumerate the available extensions: {:?}", err);
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        let colorspace_extension = ash::ext::swapchain_colorspace::NAME;
+        if available_extensions
+            .iter()
+            .any(|extension| extension.extension_name_as_c_str() == Ok(colorspace_extension))
+        {
+            required_extensions.push(colorspace_extension.as_ptr());
+        }
+
         Ok(required_extensions)
     }
 
@@ -90,19 +115,56 @@ impl VulkanRendererBackend<'_> {
         }
     }
 
+    /// Highest version supported by both the loader and the engine, without the patch number
+    fn negotiate_api_version(&self) -> Result<u32, EngineError> {
+        // Vulkan 1.0 loaders can't report their version
+        let loader_version = match unsafe { self.get_entry()?.try_enumerate_instance_version() } {
+            Ok(Some(version)) => version,
+            Ok(None) => API_VERSION_1_0,
+            Err(err) => {
+                error!("Failed to enumerate the vulkan instance version: {:?}", err);
+                return Err(EngineError::VulkanFailed);
+            }
+        };
+        let version = loader_version.min(VULKAN_MAX_API_VERSION);
+        Ok(make_api_version(
+            0,
+            api_version_major(version),
+            api_version_minor(version),
+            0,
+        ))
+    }
+
     pub fn instance_init(
         &mut self,
         application_name: &str,
         platform: &dyn Platform,
     ) -> Result<(), EngineError> {
-        let engine_name_cstr = CString::new("BigoudiEngine").unwrap();
-        let application_name_cstr = CString::new(application_name).unwrap();
+        let engine_name_cstr = c"BigoudiEngine";
+        let application_name_cstr = match CString::new(application_name) {
+            Ok(name) => name,
+            Err(err) => {
+                error!(
+                    "The application name can't be given to the vulkan instance: {:?}",
+                    err
+                );
+                return Err(EngineError::InvalidValue);
+            }
+        };
+
+        let api_version = self.negotiate_api_version()?;
+        debug!(
+            "Vulkan instance API version: {:?}.{:?}",
+            api_version_major(api_version),
+            api_version_minor(api_version)
+        );
+        self.context.core.api_version = api_version;
 
         let application_info = ApplicationInfo::default()
-            .api_version(API_VERSION_1_3)
+            .api_version(api_version)
             .application_name(&application_name_cstr)
             .application_version(make_api_version(0, 1, 0, 0))
-            .engine_name(&engine_name_cstr)
+            .engine_name(engine_name_cstr)
             .engine_version(make_api_version(0, 1, 0, 0));
 
         // Get the required extensions
@@ -118,6 +180,7 @@ impl VulkanRendererBackend<'_> {
         Self::display_layers(&required_layers);
 
         let instance_create_info = InstanceCreateInfo::default()
+            .flags(platform.get_instance_create_flags())
             .application_info(&application_info)
             .enabled_extension_names(&required_extensions)
             .enabled_layer_names(&required_layers);
@@ -128,7 +191,7 @@ impl VulkanRendererBackend<'_> {
                 .create_instance(&instance_create_info, self.get_allocator()?)
             {
                 Ok(instance) => {
-                    self.context.instance = Some(instance);
+                    self.context.core.instance = Some(instance);
                     Ok(())
                 }
                 Err(err) => {
@@ -143,7 +206,7 @@ impl VulkanRendererBackend<'_> {
         unsafe {
             self.get_instance()?.destroy_instance(self.get_allocator()?);
         }
-        self.context.instance = None;
+        self.context.core.instance = None;
         Ok(())
     }
 }
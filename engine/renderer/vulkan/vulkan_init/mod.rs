@@ -1,385 +1,301 @@
 use crate::{
-    core::debug::errors::EngineError, debug, error, platforms::platform::Platform,
-    renderer::renderer_types::VertexData,
+    core::{debug::errors::EngineError, initializer::InitializerRegistry},
+    debug, error,
+    platforms::platform::Platform,
+    renderer::renderer_types::AntiAliasing,
 };
 
-use super::{vulkan_types::VulkanRendererBackend, vulkan_utils::buffer::BufferCommandParameters};
+use super::vulkan_types::VulkanRendererBackend;
+
+pub(crate) mod allocator;
+pub(crate) mod bindless_textures;
+pub(crate) mod command_buffer;
+pub(crate) mod command_pool;
+pub(crate) mod compute;
+pub(crate) mod debug;
+pub(crate) mod devices;
+pub(crate) mod entry;
+pub(crate) mod framebuffer;
+pub(crate) mod instance;
+pub(crate) mod objects;
+pub(crate) mod query_pools;
+pub(crate) mod queue_ownership;
+pub(crate) mod render_graph;
+pub(crate) mod renderpass;
+pub(crate) mod shaders;
+pub(crate) mod surface;
+pub(crate) mod swapchain;
+pub(crate) mod sync_structures;
+pub(crate) mod transfer;
+pub(crate) mod window_swapchains;
+
+/// What the vulkan backend needs only during its initialization
+pub(crate) struct VulkanInitParameters<'a> {
+    pub application_name: &'a str,
+    pub platform: &'a dyn Platform,
+    pub anti_aliasing: AntiAliasing,
+}
+
+impl<'a> VulkanRendererBackend<'a> {
+    /// Parts of the vulkan backend and the ones they need, the initialization order is computed from the dependencies
+    fn vulkan_initializers<'p>() -> InitializerRegistry<Self, VulkanInitParameters<'p>> {
+        let registry = InitializerRegistry::<Self, VulkanInitParameters<'p>>::default()
+            .register(
+                "vulkan entry",
+                &[],
+                |backend, _| backend.entry_init(),
+                |backend| backend.entry_shutdown(),
+            )
+            .register(
+                "vulkan allocator",
+                &[],
+                |backend, _| backend.allocator_init(),
+                |backend| backend.allocator_shutdown(),
+            )
+            .register(
+                "vulkan instance",
+                &["vulkan entry", "vulkan allocator"],
+                |backend, parameters| {
+                    backend.instance_init(parameters.application_name, parameters.platform)
+                },
+                |backend| backend.instance_shutdown(),
+            );
 
-pub mod allocator;
-pub mod command_buffer;
-pub mod command_pool;
-pub mod debug;
-pub mod devices;
-pub mod entry;
-pub mod framebuffer;
-pub mod instance;
-pub mod objects;
-pub mod renderpass;
-pub mod shaders;
-pub mod surface;
-pub mod swapchain;
-pub mod sync_structures;
+        #[cfg(debug_assertions)]
+        let registry = registry.register(
+            "vulkan debugger",
+            &["vulkan instance"],
+            |backend, _| backend.debugger_init(),
+            |backend| backend.debugger_shutdown(),
+        );
+
+        registry
+            .register(
+                "vulkan surface",
+                &["vulkan instance"],
+                |backend, parameters| backend.surface_init(parameters.platform),
+                |backend| backend.surface_shutdown(),
+            )
+            .register(
+                "vulkan device requirements",
+                &[],
+                |backend, _| backend.device_requirements_init(),
+                |backend| backend.device_requirements_shutdown(),
+            )
+            .register(
+                "vulkan physical device",
+                &[
+                    "vulkan instance",
+                    "vulkan surface",
+                    "vulkan device requirements",
+                ],
+                |backend, _| backend.physical_device_init(),
+                |backend| backend.physical_device_shutdown(),
+            )
+            .register(
+                "vulkan logical device",
+                &["vulkan physical device"],
+                |backend, _| backend.device_init(),
+                |backend| backend.device_shutdown(),
+            )
+            .register(
+                "vulkan logical device queues",
+                &["vulkan logical device"],
+                |backend, _| backend.queues_init(),
+                |backend| backend.queues_shutdown(),
+            )
+            .register(
+                "vulkan memory allocator",
+                &["vulkan logical device"],
+                |backend, _| backend.memory_allocator_init(),
+                |backend| backend.memory_allocator_shutdown(),
+            )
+            .register(
+                "vulkan queue ownership",
+                &["vulkan logical device queues"],
+                |backend, _| backend.queue_ownership_init(),
+                |backend| backend.queue_ownership_shutdown(),
+            )
+            .register(
+                "vulkan transfer",
+                &[
+                    "vulkan logical device queues",
+                    "vulkan memory allocator",
+                    "vulkan queue ownership",
+                ],
+                |backend, _| backend.transfer_init(),
+                |backend| backend.transfer_shutdown(),
+            )
+            .register(
+                "vulkan compute",
+                &[
+                    "vulkan logical device queues",
+                    "vulkan memory allocator",
+                    "vulkan queue ownership",
+                ],
+                |backend, _| backend.compute_init(),
+                |backend| backend.compute_shutdown(),
+            )
+            .register(
+                "vulkan retired textures",
+                &["vulkan memory allocator"],
+                |backend, _| backend.retired_textures_init(),
+                |backend| backend.retired_textures_shutdown(),
+            )
+            .register(
+                "vulkan bindless textures",
+                &["vulkan logical device"],
+                |backend, _| backend.bindless_textures_init(),
+                |backend| backend.bindless_textures_shutdown(),
+            )
+            .register(
+                "vulkan framebuffer dimensions",
+                &["vulkan physical device"],
+                |backend, _| {
+                    backend.framebuffer_dimensions_init()?;
+                    debug!(
+                        "Vulkan framebuffer dimensions: (width={:?}, height={:?})",
+                        backend.framebuffer_width, backend.framebuffer_height
+                    );
+                    Ok(())
+                },
+                // The dimensions are overwritten on the next initialization
+                |_| Ok(()),
+            )
+            .register(
+                "vulkan sample count",
+                &["vulkan physical device"],
+                |backend, parameters| backend.device_select_sample_count(parameters.anti_aliasing),
+                // The sample count is selected again on the next initialization
+                |_| Ok(()),
+            )
+            .register(
+                "vulkan swapchain",
+                &[
+                    "vulkan logical device queues",
+                    "vulkan memory allocator",
+                    "vulkan framebuffer dimensions",
+                    "vulkan sample count",
+                ],
+                |backend, _| backend.swapchain_init(),
+                |backend| backend.swapchain_shutdown(),
+            )
+            .register(
+                "vulkan renderpass",
+                &["vulkan swapchain"],
+                |backend, _| backend.renderpass_init(),
+                |backend| backend.renderpass_shutdown(),
+            )
+            .register(
+                "vulkan render graph",
+                &["vulkan swapchain", "vulkan memory allocator"],
+                |backend, _| backend.render_graph_init(),
+                |backend| backend.render_graph_shutdown(),
+            )
+            .register(
+                "vulkan graphics command pool",
+                &["vulkan logical device queues"],
+                |backend, _| backend.graphics_command_pool_init(),
+                |backend| backend.graphics_command_pool_shutdown(),
+            )
+            .register(
+                "vulkan graphics command buffers",
+                &["vulkan graphics command pool", "vulkan swapchain"],
+                |backend, _| backend.graphics_command_buffers_init(),
+                |backend| backend.graphics_command_buffers_shutdown(),
+            )
+            .register(
+                "vulkan framebuffer cache",
+                &["vulkan renderpass"],
+                |backend, _| backend.framebuffer_cache_init(),
+                |backend| backend.framebuffer_cache_shutdown(),
+            )
+            .register(
+                "vulkan screenshots",
+                &["vulkan swapchain", "vulkan memory allocator"],
+                |backend, _| backend.screenshots_init(),
+                |backend| backend.screenshots_shutdown(),
+            )
+            .register(
+                "vulkan luminance readback",
+                &["vulkan swapchain", "vulkan memory allocator"],
+                |backend, _| backend.luminance_readback_init(),
+                |backend| backend.luminance_readback_shutdown(),
+            )
+            .register(
+                "vulkan reflection probes",
+                &["vulkan graphics command pool", "vulkan memory allocator"],
+                |backend, _| backend.reflection_probes_init(),
+                |backend| backend.reflection_probes_shutdown(),
+            )
+            .register(
+                "vulkan sync structures",
+                &["vulkan swapchain"],
+                |backend, _| backend.sync_structures_init(),
+                |backend| backend.sync_structures_shutdown(),
+            )
+            .register(
+                "vulkan window swapchains",
+                &[
+                    "vulkan swapchain",
+                    "vulkan sync structures",
+                    "vulkan framebuffer cache",
+                ],
+                |backend, _| backend.window_swapchains_init(),
+                |backend| backend.window_swapchains_shutdown(),
+            )
+            .register(
+                "vulkan query pools",
+                &["vulkan swapchain"],
+                |backend, _| backend.query_pools_init(),
+                |backend| backend.query_pools_shutdown(),
+            )
+            .register(
+                "vulkan builtin shaders",
+                &[
+                    "vulkan renderpass",
+                    "vulkan graphics command pool",
+                    "vulkan memory allocator",
+                    "vulkan bindless textures",
+                ],
+                |backend, _| backend.builtin_shaders_init(),
+                |backend| backend.builtin_shaders_shutdown(),
+            )
+            .register(
+                "vulkan objects buffers",
+                &["vulkan transfer", "vulkan memory allocator"],
+                |backend, _| backend.objects_buffers_init(),
+                |backend| backend.objects_buffers_shutdown(),
+            )
+    }
 
-impl VulkanRendererBackend<'_> {
     pub fn vulkan_init(
         &mut self,
         application_name: &str,
         platform: &dyn Platform,
+        anti_aliasing: AntiAliasing,
     ) -> Result<(), EngineError> {
-        if let Err(err) = self.entry_init() {
-            error!("Failed to initialize the vulkan entry: {:?}", err);
-            return Err(EngineError::InitializationFailed);
-        } else {
-            debug!("Vulkan entry initialized successfully !");
-        }
-
-        if let Err(err) = self.allocator_init() {
-            error!("Failed to initialize the vulkan allocator: {:?}", err);
-            return Err(EngineError::InitializationFailed);
-        } else {
-            debug!("Vulkan allocator initialized successfully !");
-        }
-
-        if let Err(err) = self.instance_init(application_name, platform) {
-            error!("Failed to initialize the vulkan instance: {:?}", err);
-            return Err(EngineError::InitializationFailed);
-        } else {
-            debug!("Vulkan instance initialized successfully !");
-        }
-
-        #[cfg(debug_assertions)]
-        {
-            if let Err(err) = self.debugger_init() {
-                error!("Failed to initialize the vulkan debugger: {:?}", err);
-                return Err(EngineError::InitializationFailed);
-            } else {
-                debug!("Vulkan debugger initialized successfully !");
-            }
-        }
-
-        if let Err(err) = self.surface_init(platform) {
-            error!("Failed to initialize the vulkan surface: {:?}", err);
-            return Err(EngineError::InitializationFailed);
-        } else {
-            debug!("Vulkan surface initialized successfully !");
-        }
-
-        if let Err(err) = self.device_requirements_init() {
-            error!(
-                "Failed to initialize the vulkan device requirements: {:?}",
-                err
-            );
-            return Err(EngineError::InitializationFailed);
-        } else {
-            debug!("Vulkan device requirements initialized successfully !");
-        }
-
-        if let Err(err) = self.physical_device_init() {
-            error!("Failed to initialize the vulkan physical device: {:?}", err);
-            return Err(EngineError::InitializationFailed);
-        } else {
-            debug!("Vulkan physical device initialized successfully !");
-        }
-
-        if let Err(err) = self.device_init() {
-            error!("Failed to initialize the vulkan logical device: {:?}", err);
-            return Err(EngineError::InitializationFailed);
-        } else {
-            debug!("Vulkan logical device initialized successfully !");
-        }
-
-        if let Err(err) = self.queues_init() {
-            error!(
-                "Failed to initialize the vulkan logical device queues: {:?}",
-                err
-            );
-            return Err(EngineError::InitializationFailed);
-        } else {
-            debug!("Vulkan logical device queues initialized successfully !");
-        }
-
-        if let Err(err) = self.framebuffer_dimensions_init() {
-            error!(
-                "Failed to initialize the vulkan framebuffer dimensions: {:?}",
-                err
-            );
-            return Err(EngineError::InitializationFailed);
-        } else {
-            debug!("Vulkan framebuffer dimensions initialized successfully: (width={:?}, height={:?})!",
-            self.framebuffer_width, self.framebuffer_height
-            );
-        }
-
-        if let Err(err) = self.swapchain_init() {
-            error!("Failed to initialize the vulkan swapchain: {:?}", err);
-            return Err(EngineError::InitializationFailed);
-        } else {
-            debug!("Vulkan swapchain initialized successfully !");
-        }
-
-        if let Err(err) = self.renderpass_init() {
-            error!("Failed to initialize the vulkan renderpass: {:?}", err);
-            return Err(EngineError::InitializationFailed);
-        } else {
-            debug!("Vulkan renderpass initialized successfully !");
-        }
-
-        if let Err(err) = self.graphics_command_pool_init() {
-            error!(
-                "Failed to initialize the vulkan graphics command pool: {:?}",
-                err
-            );
+        let parameters = VulkanInitParameters {
+            application_name,
+            platform,
+            anti_aliasing,
+        };
+        if let Err(err) = Self::vulkan_initializers().init(self, &parameters) {
+            error!("Failed to initialize the vulkan backend: {:?}", err);
             return Err(EngineError::InitializationFailed);
-        } else {
-            debug!("Vulkan graphics command pool initialized successfully !");
         }
 
-        if let Err(err) = self.graphics_command_buffers_init() {
-            error!(
-                "Failed to initialize the vulkan graphics command buffers: {:?}",
-                err
-            );
-            return Err(EngineError::InitializationFailed);
-        } else {
-            debug!("Vulkan graphics command buffers initialized successfully !");
-        }
-
-        if let Err(err) = self.swapchain_framebuffers_init() {
-            error!(
-                "Failed to initialize the vulkan swapchain framebuffers: {:?}",
-                err
-            );
-            return Err(EngineError::InitializationFailed);
-        } else {
-            debug!("Vulkan swapchain framebuffers initialized successfully !");
-        }
-
-        if let Err(err) = self.sync_structures_init() {
-            error!("Failed to initialize the vulkan sync structures: {:?}", err);
-            return Err(EngineError::InitializationFailed);
-        } else {
-            debug!("Vulkan sync structures initialized successfully !");
-        }
-
-        if let Err(err) = self.builtin_shaders_init() {
-            error!("Failed to initialize the vulkan builtin shaders: {:?}", err);
-            return Err(EngineError::InitializationFailed);
-        } else {
-            debug!("Vulkan builtin shaders initialized successfully !");
-        }
-
-        if let Err(err) = self.objects_buffers_init() {
-            error!("Failed to initialize the vulkan objects buffers: {:?}", err);
-            return Err(EngineError::InitializationFailed);
-        } else {
-            debug!("Vulkan objects buffers initialized successfully !");
-        }
-
-        // TODO: temporary test code
-        {
-            let factor = 10.0;
-            let mut vertices: Vec<VertexData> = vec![
-                VertexData {
-                    position: glam::Vec3::new(-0.5 * factor, -0.5 * factor, 0.0),
-                    texture: glam::Vec2::new(0.0, 0.0),
-                },
-                VertexData {
-                    position: glam::Vec3::new(0.5 * factor, 0.5 * factor, 0.0),
-                    texture: glam::Vec2::new(1.0, 1.0),
-                },
-                VertexData {
-                    position: glam::Vec3::new(-0.5 * factor, 0.5 * factor, 0.0),
-                    texture: glam::Vec2::new(0.0, 1.0),
-                },
-                VertexData {
-                    position: glam::Vec3::new(0.5 * factor, -0.5 * factor, 0.0),
-                    texture: glam::Vec2::new(1.0, 0.0),
-                },
-            ];
-            let mut indices: Vec<u32> = vec![0, 1, 2, 0, 3, 1];
-            let vertices_command_parameters = BufferCommandParameters {
-                command_pool: self.get_graphics_command_pool()?,
-                fence: &ash::vk::Fence::null(),
-                queue: self.get_queues()?.graphics_queue.unwrap(),
-            };
-            let vertices_buffer = &self.get_objects_buffers()?.vertex_buffer;
-            let vertices_size = size_of::<VertexData>() * vertices.len();
-            self.upload_data_range(
-                vertices_command_parameters,
-                vertices_buffer,
-                0,
-                vertices_size,
-                vertices.as_mut_ptr() as *mut std::ffi::c_void,
-            )?;
-
-            let indices_command_parameters = BufferCommandParameters {
-                command_pool: self.get_graphics_command_pool()?,
-                fence: &ash::vk::Fence::null(),
-                queue: self.get_queues()?.graphics_queue.unwrap(),
-            };
-            let indices_buffer = &self.get_objects_buffers()?.index_buffer;
-            let indices_size = size_of::<u32>() * indices.len();
-            self.upload_data_range(
-                indices_command_parameters,
-                indices_buffer,
-                0,
-                indices_size,
-                indices.as_mut_ptr() as *mut std::ffi::c_void,
-            )?;
-
-            let object_id = match self.object_shader_acquire_resources() {
-                Ok(id) => id,
-                Err(err) => {
-                    error!("Failed to acquire the shader resources: {:?}", err);
-                    return Err(EngineError::AccessFailed);
-                }
-            };
-        }
-        // TODO: end temp code
-
         Ok(())
     }
 
+    /// A failing part doesn't prevent the others from shutting down
     pub fn vulkan_shutdown(&mut self) -> Result<(), EngineError> {
         self.device_wait_idle()?;
 
-        if let Err(err) = self.objects_buffers_shutdown() {
-            error!("Failed to shutdown the vulkan objects buffers: {:?}", err);
-            return Err(EngineError::InitializationFailed);
-        } else {
-            debug!("Vulkan objects buffers shutted down successfully !");
-        }
-
-        if let Err(err) = self.builtin_shaders_shutdown() {
-            error!("Failed to shutdown the vulkan builtin shaders: {:?}", err);
-            return Err(EngineError::ShutdownFailed);
-        } else {
-            debug!("Vulkan builtin shaders shutted down successfully !");
-        }
-
-        if let Err(err) = self.sync_structures_shutdown() {
-            error!("Failed to shutdown the vulkan sync structures: {:?}", err);
-            return Err(EngineError::ShutdownFailed);
-        } else {
-            debug!("Vulkan sync structures shutted down successfully !");
-        }
-
-        if let Err(err) = self.swapchain_framebuffers_shutdown() {
-            error!(
-                "Failed to shutdown the vulkan swapchain framebuffers: {:?}",
-                err
-            );
-            return Err(EngineError::ShutdownFailed);
-        } else {
-            debug!("Vulkan swapchain framebuffers shutted down successfully !");
-        }
-
-        if let Err(err) = self.graphics_command_buffers_shutdown() {
-            error!(
-                "Failed to shutdown the vulkan graphics command buffers: {:?}",
-                err
-            );
-            return Err(EngineError::ShutdownFailed);
-        } else {
-            debug!("Vulkan graphics command buffers shutted down successfully !");
-        }
-
-        if let Err(err) = self.graphics_command_pool_shutdown() {
-            error!(
-                "Failed to shutdown the vulkan graphics command pool: {:?}",
-                err
-            );
-            return Err(EngineError::ShutdownFailed);
-        } else {
-            debug!("Vulkan graphics command pool shutted down successfully !");
-        }
-
-        if let Err(err) = self.renderpass_shutdown() {
-            error!("Failed to shutdown the vulkan renderpass: {:?}", err);
+        if let Err(err) = Self::vulkan_initializers().shutdown(self) {
+            error!("Failed to shutdown the vulkan backend: {:?}", err);
             return Err(EngineError::ShutdownFailed);
-        } else {
-            debug!("Vulkan renderpass shutted down successfully !");
         }
-
-        if let Err(err) = self.swapchain_shutdown() {
-            error!("Failed to shutdown the vulkan swapchain: {:?}", err);
-            return Err(EngineError::ShutdownFailed);
-        } else {
-            debug!("Vulkan swapchain shutted down successfully !");
-        }
-
-        if let Err(err) = self.queues_shutdown() {
-            error!(
-                "Failed to shutdown the vulkan logical device queues: {:?}",
-                err
-            );
-            return Err(EngineError::ShutdownFailed);
-        } else {
-            debug!("Vulkan logical device queues shutted down successfully !");
-        }
-
-        if let Err(err) = self.device_shutdown() {
-            error!("Failed to shutdown the vulkan logical device: {:?}", err);
-            return Err(EngineError::ShutdownFailed);
-        } else {
-            debug!("Vulkan logical device shutted down successfully !");
-        }
-
-        if let Err(err) = self.physical_device_shutdown() {
-            error!("Failed to shutdown the vulkan physical device: {:?}", err);
-            return Err(EngineError::ShutdownFailed);
-        } else {
-            debug!("Vulkan physical device shutted down successfully !");
-        }
-
-        if let Err(err) = self.device_requirements_shutdown() {
-            error!(
-                "Failed to shutdown the vulkan device requirements: {:?}",
-                err
-            );
-            return Err(EngineError::ShutdownFailed);
-        } else {
-            debug!("Vulkan device requirements shutted down successfully !");
-        }
-
-        if let Err(err) = self.surface_shutdown() {
-            error!("Failed to shutdown the vulkan surface: {:?}", err);
-            return Err(EngineError::ShutdownFailed);
-        } else {
-            debug!("Vulkan surface shutted down successfully !");
-        }
-
-        #[cfg(debug_assertions)]
-        {
-            if let Err(err) = self.debugger_shutdown() {
-                error!("Failed to shutdown the vulkan debugger: {:?}", err);
-                return Err(EngineError::ShutdownFailed);
-            } else {
-                debug!("Vulkan debugger shutted down successfully !");
-            }
-        }
-
-        if let Err(err) = self.instance_shutdown() {
-            error!("Failed to shutdown the vulkan instance: {:?}", err);
-            return Err(EngineError::ShutdownFailed);
-        } else {
-            debug!("Vulkan instance shutted down successfully !");
-        }
-
-        if let Err(err) = self.allocator_shutdown() {
-            error!("Failed to shutdown the vulkan allocator: {:?}", err);
-            return Err(EngineError::ShutdownFailed);
-        } else {
-            debug!("Vulkan allocator shutted down successfully !");
-        }
-
-        if let Err(err) = self.entry_shutdown() {
-            error!("Failed to shutdown the vulkan entry: {:?}", err);
-            return Err(EngineError::ShutdownFailed);
-        } else {
-            debug!("Vulkan entry shutted down successfully !");
-        }
-
         Ok(())
     }
 }
@@ -9,12 +9,110 @@ use crate::{
     },
 };
 
+/// Part of an objects buffer, in bytes
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct BufferRange {
+    pub offset: u64,
+    pub size: u64,
+}
+
 pub(crate) struct ObjectsBuffers {
     pub vertex_buffer: Buffer,
+    /// End of the used part of the vertex buffer
     pub vertex_offset: u64,
+    /// Ranges released before the end of the used part
+    pub vertex_free_ranges: Vec<BufferRange>,
 
     pub index_buffer: Buffer,
     pub index_offset: u64,
+    pub index_free_ranges: Vec<BufferRange>,
+
+    pub next_geometry_id: u32,
+}
+
+impl ObjectsBuffers {
+    /// First fit in the released ranges, else at the end of the buffer
+    fn allocate_range(
+        free_ranges: &mut Vec<BufferRange>,
+        end_offset: &mut u64,
+        capacity: u64,
+        size: u64,
+    ) -> Option<BufferRange> {
+        if let Some(index) = free_ranges.iter().position(|range| range.size >= size) {
+            let free_range = &mut free_ranges[index];
+            let range = BufferRange {
+                offset: free_range.offset,
+                size,
+            };
+            free_range.offset += size;
+            free_range.size -= size;
+            if free_range.size == 0 {
+                free_ranges.remove(index);
+            }
+            return Some(range);
+        }
+        if *end_offset + size > capacity {
+            return None;
+        }
+        let range = BufferRange {
+            offset: *end_offset,
+            size,
+        };
+        *end_offset += size;
+        Some(range)
+    }
+
+    /// Merge the released range with its neighbours to limit fragmentation
+    fn free_range(free_ranges: &mut Vec<BufferRange>, end_offset: &mut u64, range: BufferRange) {
+        let index = free_ranges.partition_point(|free_range| free_range.offset < range.offset);
+        free_ranges.insert(index, range);
+        if index + 1 < free_ranges.len()
+            && free_ranges[index].offset + free_ranges[index].size == free_ranges[index + 1].offset
+        {
+            free_ranges[index].size += free_ranges[index + 1].size;
+            free_ranges.remove(index + 1);
+        }
+        if index > 0
+            && free_ranges[index - 1].offset + free_ranges[index - 1].size
+                == free_ranges[index].offset
+        {
+            free_ranges[index - 1].size += free_ranges[index].size;
+            free_ranges.remove(index);
+        }
+        // Give the last range back to the end of the used part
+        if let Some(last) = free_ranges.last() {
+            if last.offset + last.size == *end_offset {
+                *end_offset = last.offset;
+                free_ranges.pop();
+            }
+        }
+    }
+
+    pub fn allocate_vertices(&mut self, size: u64) -> Option<BufferRange> {
+        Self::allocate_range(
+            &mut self.vertex_free_ranges,
+            &mut self.vertex_offset,
+            self.vertex_buffer.total_size as u64,
+            size,
+        )
+    }
+
+    pub fn allocate_indices(&mut self, size: u64) -> Option<BufferRange> {
+        Self::allocate_range(
+            &mut self.index_free_ranges,
+            &mut self.index_offset,
+            self.index_buffer.total_size as u64,
+            size,
+        )
+    }
+
+    pub fn free_vertices(&mut self, range: BufferRange) {
+        Self::free_range(&mut self.vertex_free_ranges, &mut self.vertex_offset, range);
+    }
+
+    pub fn free_indices(&mut self, range: BufferRange) {
+        Self::free_range(&mut self.index_free_ranges, &mut self.index_offset, range);
+    }
 }
 
 impl VulkanRendererBackend<'_> {
@@ -58,17 +156,30 @@ impl VulkanRendererBackend<'_> {
         };
         let index_offset = 0;
 
-        self.context.objects = Some(ObjectsBuffers {
+        self.context.resources.objects = Some(ObjectsBuffers {
             vertex_buffer,
             index_buffer,
             vertex_offset,
             index_offset,
+            vertex_free_ranges: Vec::new(),
+            index_free_ranges: Vec::new(),
+            next_geometry_id: 0,
         });
         Ok(())
     }
 
     pub fn get_objects_buffers(&self) -> Result<&ObjectsBuffers, EngineError> {
-        match &self.context.objects {
+        match &self.context.resources.objects {
+            Some(objects) => Ok(objects),
+            None => {
+                error!("Can't access the vulkan objects buffers");
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
+
+    pub fn get_objects_buffers_mut(&mut self) -> Result<&mut ObjectsBuffers, EngineError> {
+        match &mut self.context.resources.objects {
             Some(objects) => Ok(objects),
             None => {
                 error!("Can't access the vulkan objects buffers");
@@ -0,0 +1,290 @@
+use ash::vk::{PipelineStageFlags, QueryPipelineStatisticFlags, QueryType, TRUE};
+
+use crate::{
+    core::debug::errors::EngineError,
+    error,
+    renderer::{
+        renderer_types::{GpuPassTimings, RendererStatistics},
+        vulkan::{
+            vulkan_types::VulkanRendererBackend,
+            vulkan_utils::{
+                crash_report::VULKAN_CRASH_REPORT_FRAME_COUNT,
+                query_pool::{QueryPool, VULKAN_PIPELINE_STATISTICS_FLAGS},
+            },
+        },
+    },
+};
+
+/// Objects with an id past this count are not tested for occlusion
+pub const VULKAN_OCCLUSION_QUERY_COUNT: u32 = 1024;
+
+/// Timestamps written during a frame, the passes are timed between them
+pub const VULKAN_TIMESTAMP_FRAME_BEGIN: u32 = 0;
+pub const VULKAN_TIMESTAMP_UI_PASS_BEGIN: u32 = 1;
+pub const VULKAN_TIMESTAMP_FRAME_END: u32 = 2;
+const VULKAN_TIMESTAMP_COUNT: u32 = 3;
+
+/// Queries recorded during one frame
+pub(crate) struct FrameQueryPools {
+    // One query per object id
+    pub occlusion: QueryPool,
+    // One query for the whole frame, none if the device does not support it
+    pub pipeline_statistics: Option<QueryPool>,
+    // Pass boundaries, none if the device does not support timestamps
+    pub timestamps: Option<QueryPool>,
+    // Object ids whose occlusion query was recorded this frame
+    pub occlusion_queries_used: Vec<u32>,
+    pub has_been_submitted: bool,
+}
+
+/// One set of query pools per frame in flight
+pub(crate) struct QueryPools {
+    pub frames: Vec<FrameQueryPools>,
+}
+
+impl VulkanRendererBackend<'_> {
+    pub fn get_query_pools(&self) -> Result<&QueryPools, EngineError> {
+        match &self.context.frame.query_pools {
+            Some(query_pools) => Ok(query_pools),
+            None => {
+                error!("Can't access the vulkan query pools");
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
+
+    pub fn get_query_pools_mut(&mut self) -> Result<&mut QueryPools, EngineError> {
+        match &mut self.context.frame.query_pools {
+            Some(query_pools) => Ok(query_pools),
+            None => {
+                error!("Can't access the vulkan query pools");
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
+
+    pub fn query_pools_init(&mut self) -> Result<(), EngineError> {
+        let max_frames_in_flight = self.get_swapchain()?.max_frames_in_flight;
+        let has_pipeline_statistics = self
+            .get_physical_device_info()?
+            .features
+            .pipeline_statistics_query
+            == TRUE;
+        let has_timestamps = self.get_physical_device_info()?.has_gpu_timestamps();
+        let device = self.get_device()?;
+        let allocator = self.get_allocator()?;
+
+        let mut frames = Vec::new();
+        for _ in 0..max_frames_in_flight {
+            let occlusion = QueryPool::create(
+                device,
+                allocator,
+                QueryType::OCCLUSION,
+                VULKAN_OCCLUSION_QUERY_COUNT,
+                QueryPipelineStatisticFlags::empty(),
+            )?;
+            let pipeline_statistics = if has_pipeline_statistics {
+                Some(QueryPool::create(
+                    device,
+                    allocator,
+                    QueryType::PIPELINE_STATISTICS,
+                    1,
+                    VULKAN_PIPELINE_STATISTICS_FLAGS,
+                )?)
+            } else {
+                None
+            };
+            let timestamps = if has_timestamps {
+                Some(QueryPool::create(
+                    device,
+                    allocator,
+                    QueryType::TIMESTAMP,
+                    VULKAN_TIMESTAMP_COUNT,
+                    QueryPipelineStatisticFlags::empty(),
+                )?)
+            } else {
+                None
+            };
+            frames.push(FrameQueryPools {
+                occlusion,
+                pipeline_statistics,
+                timestamps,
+                occlusion_queries_used: Vec::new(),
+                has_been_submitted: false,
+            });
+        }
+
+        self.context.frame.query_pools = Some(QueryPools { frames });
+        Ok(())
+    }
+
+    pub fn query_pools_shutdown(&mut self) -> Result<(), EngineError> {
+        let device = self.get_device()?;
+        let allocator = self.get_allocator()?;
+        for frame in &self.get_query_pools()?.frames {
+            frame.occlusion.destroy(device, allocator)?;
+            if let Some(pool) = &frame.pipeline_statistics {
+                pool.destroy(device, allocator)?;
+            }
+            if let Some(pool) = &frame.timestamps {
+                pool.destroy(device, allocator)?;
+            }
+        }
+        self.context.frame.query_pools = None;
+        Ok(())
+    }
+
+    /// Read back the results of the previous use of the current frame
+    /// Must be called once the frame fence has been waited on
+    pub fn query_pools_collect(&mut self) -> Result<(), EngineError> {
+        let current_frame_index = self.context.frame.current_frame as usize;
+        let device = self.get_device()?;
+        let frame = &self.get_query_pools()?.frames[current_frame_index];
+        if !frame.has_been_submitted {
+            return Ok(());
+        }
+
+        let mut statistics = RendererStatistics {
+            // The frame in this slot was submitted max_frames_in_flight frames ago
+            frame_number: self
+                .frame_number
+                .saturating_sub(self.get_query_pools()?.frames.len() as u64),
+            ..Default::default()
+        };
+
+        if let Some(pool) = &frame.pipeline_statistics {
+            if let Some(results) = pool.get_results(device, 0, 1)? {
+                // Results are written in the order of the flag bits
+                statistics.input_assembly_vertices = Some(results[0]);
+                statistics.vertex_shader_invocations = Some(results[1]);
+                statistics.clipping_primitives = Some(results[2]);
+                statistics.fragment_shader_invocations = Some(results[3]);
+            }
+        }
+
+        if let Some(pool) = &frame.timestamps {
+            if let Some(results) = pool.get_results(device, 0, VULKAN_TIMESTAMP_COUNT)? {
+                // The timestamps are in ticks of `timestamp_period` nanoseconds
+                let period = self
+                    .get_physical_device_info()?
+                    .properties
+                    .limits
+                    .timestamp_period as f64
+                    * 1e-9;
+                let elapsed = |start: u32, end: u32| {
+                    results[end as usize].wrapping_sub(results[start as usize]) as f64 * period
+                };
+                statistics.gpu_pass_timings = Some(GpuPassTimings {
+                    world: elapsed(VULKAN_TIMESTAMP_FRAME_BEGIN, VULKAN_TIMESTAMP_UI_PASS_BEGIN),
+                    ui: elapsed(VULKAN_TIMESTAMP_UI_PASS_BEGIN, VULKAN_TIMESTAMP_FRAME_END),
+                });
+            }
+        }
+
+        for query in &frame.occlusion_queries_used {
+            if let Some(results) = frame.occlusion.get_results(device, *query, 1)? {
+                statistics.tested_objects += 1;
+                if results[0] > 0 {
+                    statistics.visible_objects += 1;
+                }
+            }
+        }
+
+        self.statistics = statistics;
+        if self.statistics_history.len() >= VULKAN_CRASH_REPORT_FRAME_COUNT {
+            self.statistics_history.pop_front();
+        }
+        self.statistics_history.push_back(statistics);
+        Ok(())
+    }
+
+    /// Reset the queries of the current frame, must be recorded outside of a renderpass
+    pub fn query_pools_reset(&mut self) -> Result<(), EngineError> {
+        let current_frame_index = self.context.frame.current_frame as usize;
+        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
+        let device = self.get_device()?;
+        let frame = &self.get_query_pools()?.frames[current_frame_index];
+        frame
+            .occlusion
+            .reset(device, command_buffer.handler.as_ref());
+        if let Some(pool) = &frame.pipeline_statistics {
+            pool.reset(device, command_buffer.handler.as_ref());
+        }
+        if let Some(pool) = &frame.timestamps {
+            pool.reset(device, command_buffer.handler.as_ref());
+        }
+        let frame = &mut self.get_query_pools_mut()?.frames[current_frame_index];
+        frame.occlusion_queries_used.clear();
+        frame.has_been_submitted = false;
+        Ok(())
+    }
+
+    pub fn query_pools_begin_frame(&self) -> Result<(), EngineError> {
+        let current_frame_index = self.context.frame.current_frame as usize;
+        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
+        let device = self.get_device()?;
+        if let Some(pool) = &self.get_query_pools()?.frames[current_frame_index].pipeline_statistics
+        {
+            pool.begin(device, command_buffer.handler.as_ref(), 0)?;
+        }
+        Ok(())
+    }
+
+    pub fn query_pools_end_frame(&mut self) -> Result<(), EngineError> {
+        let current_frame_index = self.context.frame.current_frame as usize;
+        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
+        let device = self.get_device()?;
+        if let Some(pool) = &self.get_query_pools()?.frames[current_frame_index].pipeline_statistics
+        {
+            pool.end(device, command_buffer.handler.as_ref(), 0)?;
+        }
+        self.get_query_pools_mut()?.frames[current_frame_index].has_been_submitted = true;
+        Ok(())
+    }
+
+    /// Write a timestamp of the current frame, nothing is done if the device has no timestamps
+    pub fn query_pools_write_timestamp(
+        &self,
+        query: u32,
+        stage: PipelineStageFlags,
+    ) -> Result<(), EngineError> {
+        let current_frame_index = self.context.frame.current_frame as usize;
+        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
+        let device = self.get_device()?;
+        if let Some(pool) = &self.get_query_pools()?.frames[current_frame_index].timestamps {
+            pool.write_timestamp(device, command_buffer.handler.as_ref(), stage, query)?;
+        }
+        Ok(())
+    }
+
+    /// Returns false if the object was already queried this frame or has no query
+    pub fn query_pools_begin_object(&mut self, object_id: u32) -> Result<bool, EngineError> {
+        let current_frame_index = self.context.frame.current_frame as usize;
+        let frame = &self.get_query_pools()?.frames[current_frame_index];
+        // An object can only be queried once per frame
+        if object_id >= VULKAN_OCCLUSION_QUERY_COUNT
+            || frame.occlusion_queries_used.contains(&object_id)
+        {
+            return Ok(false);
+        }
+        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
+        let device = self.get_device()?;
+        frame
+            .occlusion
+            .begin(device, command_buffer.handler.as_ref(), object_id)?;
+        self.get_query_pools_mut()?.frames[current_frame_index]
+            .occlusion_queries_used
+            .push(object_id);
+        Ok(true)
+    }
+
+    pub fn query_pools_end_object(&self, object_id: u32) -> Result<(), EngineError> {
+        let current_frame_index = self.context.frame.current_frame as usize;
+        let frame = &self.get_query_pools()?.frames[current_frame_index];
+        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
+        let device = self.get_device()?;
+        frame
+            .occlusion
+            .end(device, command_buffer.handler.as_ref(), object_id)
+    }
+}
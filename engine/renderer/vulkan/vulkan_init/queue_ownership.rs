@@ -0,0 +1,455 @@
+use ash::vk::{
+    self, AccessFlags, BufferMemoryBarrier, CommandPool, CommandPoolCreateFlags,
+    CommandPoolCreateInfo, DependencyFlags, ImageAspectFlags, ImageLayout, ImageMemoryBarrier,
+    ImageSubresourceRange, PipelineStageFlags, Queue, Semaphore, SubmitInfo, QUEUE_FAMILY_IGNORED,
+};
+
+use crate::{
+    core::debug::errors::EngineError,
+    error,
+    renderer::{
+        renderer_types::RENDERER_MAX_IN_FLIGHT_FRAMES,
+        vulkan::{
+            vulkan_init::command_buffer::CommandBuffer, vulkan_types::VulkanRendererBackend,
+            vulkan_utils::per_frame_resource::PerFrameResource,
+        },
+    },
+};
+
+/// Queues the resources are handed over between, the resources are exclusive to one at a time
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum QueueFamily {
+    Graphics,
+    Compute,
+    Transfer,
+}
+
+/// Resource handed over, the images change of layout at the same time
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum OwnedResource {
+    Image {
+        image: vk::Image,
+        old_layout: ImageLayout,
+        new_layout: ImageLayout,
+    },
+    Buffer {
+        buffer: vk::Buffer,
+        offset: u64,
+        size: u64,
+    },
+}
+
+/// Release recorded on the source queue and acquire recorded on the destination queue
+/// The acquire must be submitted after the release, behind a semaphore or a wait
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct OwnershipTransfer {
+    pub resource: OwnedResource,
+    pub src_family: QueueFamily,
+    pub dst_family: QueueFamily,
+    /// Accesses of the source queue done before the release
+    pub src_stage: PipelineStageFlags,
+    pub src_access: AccessFlags,
+    /// Accesses of the destination queue done after the acquire
+    pub dst_stage: PipelineStageFlags,
+    pub dst_access: AccessFlags,
+}
+
+impl OwnershipTransfer {
+    /// Texture written on the transfer queue, sampled by the frames
+    pub fn uploaded_image(image: vk::Image) -> Self {
+        Self {
+            resource: OwnedResource::Image {
+                image,
+                old_layout: ImageLayout::TRANSFER_DST_OPTIMAL,
+                new_layout: ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            },
+            src_family: QueueFamily::Transfer,
+            dst_family: QueueFamily::Graphics,
+            src_stage: PipelineStageFlags::TRANSFER,
+            src_access: AccessFlags::TRANSFER_WRITE,
+            dst_stage: PipelineStageFlags::FRAGMENT_SHADER,
+            dst_access: AccessFlags::SHADER_READ,
+        }
+    }
+
+    /// Range of a geometry buffer written on the transfer queue, read by the draws
+    pub fn uploaded_buffer_range(buffer: vk::Buffer, offset: u64, size: u64) -> Self {
+        Self {
+            resource: OwnedResource::Buffer {
+                buffer,
+                offset,
+                size,
+            },
+            src_family: QueueFamily::Transfer,
+            dst_family: QueueFamily::Graphics,
+            src_stage: PipelineStageFlags::TRANSFER,
+            src_access: AccessFlags::TRANSFER_WRITE,
+            dst_stage: PipelineStageFlags::VERTEX_INPUT,
+            dst_access: AccessFlags::VERTEX_ATTRIBUTE_READ | AccessFlags::INDEX_READ,
+        }
+    }
+
+    /// Sampled image lent to the dispatches, the frames stop sampling it first
+    pub fn storage_image_to_compute(image: vk::Image) -> Self {
+        Self {
+            resource: OwnedResource::Image {
+                image,
+                old_layout: ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                new_layout: ImageLayout::GENERAL,
+            },
+            src_family: QueueFamily::Graphics,
+            dst_family: QueueFamily::Compute,
+            src_stage: PipelineStageFlags::FRAGMENT_SHADER,
+            src_access: AccessFlags::empty(),
+            dst_stage: PipelineStageFlags::COMPUTE_SHADER,
+            dst_access: AccessFlags::SHADER_READ | AccessFlags::SHADER_WRITE,
+        }
+    }
+
+    /// Storage image given back to the frames once the dispatches wrote it
+    pub fn storage_image_to_graphics(image: vk::Image) -> Self {
+        Self {
+            resource: OwnedResource::Image {
+                image,
+                old_layout: ImageLayout::GENERAL,
+                new_layout: ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            },
+            src_family: QueueFamily::Compute,
+            dst_family: QueueFamily::Graphics,
+            src_stage: PipelineStageFlags::COMPUTE_SHADER,
+            src_access: AccessFlags::SHADER_WRITE,
+            dst_stage: PipelineStageFlags::FRAGMENT_SHADER,
+            dst_access: AccessFlags::SHADER_READ,
+        }
+    }
+}
+
+/// Graphics work of a frame taking or lending the resources of the other queues
+struct OwnershipFrame {
+    /// Submitted ahead of the frame's command buffer, acquires what the other queues released
+    acquire_command_buffer: CommandBuffer,
+    /// Submitted before the dispatches, releases the images they write
+    release_command_buffer: CommandBuffer,
+    /// Signaled by the release submission, waited by the compute submission of the same frame
+    release_semaphore: Semaphore,
+}
+
+/// Handovers of the graphics queue, the other queues record theirs in their own command buffers
+/// When two queues share a family nothing is released, the acquire does the whole barrier
+pub(crate) struct QueueOwnershipContext {
+    command_pool: CommandPool,
+    queue: Queue,
+    frames: PerFrameResource<OwnershipFrame>,
+}
+
+impl VulkanRendererBackend<'_> {
+    pub fn queue_ownership_init(&mut self) -> Result<(), EngineError> {
+        let queues = self.get_queues()?;
+        let family_index = queues.get_graphics_family_index()?;
+        let queue = queues.get_graphics_queue()?;
+        let device = self.get_device()?;
+
+        let pool_create_info = CommandPoolCreateInfo::default()
+            .queue_family_index(family_index)
+            .flags(CommandPoolCreateFlags::RESET_COMMAND_BUFFER);
+        let command_pool = unsafe {
+            match device.create_command_pool(&pool_create_info, self.get_allocator()?) {
+                Ok(pool) => pool,
+                Err(err) => {
+                    error!(
+                        "Failed to create the vulkan queue ownership command pool: {:?}",
+                        err
+                    );
+                    return Err(EngineError::InitializationFailed);
+                }
+            }
+        };
+
+        let mut frames = Vec::new();
+        for _ in 0..RENDERER_MAX_IN_FLIGHT_FRAMES {
+            frames.push(OwnershipFrame {
+                acquire_command_buffer: CommandBuffer::allocate(&command_pool, true, device)?,
+                release_command_buffer: CommandBuffer::allocate(&command_pool, true, device)?,
+                release_semaphore: self.create_default_semaphore()?,
+            });
+        }
+
+        self.context.core.queue_ownership = Some(QueueOwnershipContext {
+            command_pool,
+            queue,
+            frames: PerFrameResource::from_vec(frames)?,
+        });
+        Ok(())
+    }
+
+    /// The device is idle when shutting down so every handover is done
+    pub fn queue_ownership_shutdown(&mut self) -> Result<(), EngineError> {
+        let queue_ownership = match self.context.core.queue_ownership.take() {
+            Some(queue_ownership) => queue_ownership,
+            None => return Ok(()),
+        };
+        for frame in queue_ownership.frames.iter() {
+            self.destroy_semaphore(&frame.release_semaphore)?;
+        }
+        // Destroying the pool frees the command buffers of the frames
+        unsafe {
+            self.get_device()?
+                .destroy_command_pool(queue_ownership.command_pool, self.get_allocator()?)
+        };
+        Ok(())
+    }
+
+    fn get_queue_ownership(&self) -> Result<&QueueOwnershipContext, EngineError> {
+        match &self.context.core.queue_ownership {
+            Some(queue_ownership) => Ok(queue_ownership),
+            None => {
+                error!("Can't access the vulkan queue ownership context");
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
+
+    pub(crate) fn get_queue_family_index(&self, family: QueueFamily) -> Result<u32, EngineError> {
+        let queues = self.get_queues()?;
+        match family {
+            QueueFamily::Graphics => queues.get_graphics_family_index(),
+            QueueFamily::Compute => queues.get_compute_family_index(),
+            QueueFamily::Transfer => queues.get_transfer_family_index(),
+        }
+    }
+
+    /// Record the release half of the handovers, on a command buffer of their source queue
+    pub(crate) fn record_ownership_release(
+        &self,
+        command_buffer: &CommandBuffer,
+        transfers: &[OwnershipTransfer],
+    ) -> Result<(), EngineError> {
+        self.record_ownership_barriers(command_buffer, transfers, true)
+    }
+
+    /// Record the acquire half of the handovers, on a command buffer of their destination queue
+    pub(crate) fn record_ownership_acquire(
+        &self,
+        command_buffer: &CommandBuffer,
+        transfers: &[OwnershipTransfer],
+    ) -> Result<(), EngineError> {
+        self.record_ownership_barriers(command_buffer, transfers, false)
+    }
+
+    fn record_ownership_barriers(
+        &self,
+        command_buffer: &CommandBuffer,
+        transfers: &[OwnershipTransfer],
+        is_release: bool,
+    ) -> Result<(), EngineError> {
+        let mut src_stage = PipelineStageFlags::empty();
+        let mut dst_stage = PipelineStageFlags::empty();
+        let mut buffer_memory_barriers = Vec::new();
+        let mut image_memory_barriers = Vec::new();
+        for transfer in transfers.iter() {
+            let src_family_index = self.get_queue_family_index(transfer.src_family)?;
+            let dst_family_index = self.get_queue_family_index(transfer.dst_family)?;
+            // The source half makes the writes available, the destination half makes them visible
+            let (
+                src_family_index,
+                dst_family_index,
+                barrier_src_stage,
+                src_access,
+                barrier_dst_stage,
+                dst_access,
+            ) = if src_family_index == dst_family_index {
+                if is_release {
+                    continue;
+                }
+                (
+                    QUEUE_FAMILY_IGNORED,
+                    QUEUE_FAMILY_IGNORED,
+                    transfer.src_stage,
+                    transfer.src_access,
+                    transfer.dst_stage,
+                    transfer.dst_access,
+                )
+            } else if is_release {
+                (
+                    src_family_index,
+                    dst_family_index,
+                    transfer.src_stage,
+                    transfer.src_access,
+                    PipelineStageFlags::BOTTOM_OF_PIPE,
+                    AccessFlags::empty(),
+                )
+            } else {
+                (
+                    src_family_index,
+                    dst_family_index,
+                    PipelineStageFlags::TOP_OF_PIPE,
+                    AccessFlags::empty(),
+                    transfer.dst_stage,
+                    transfer.dst_access,
+                )
+            };
+            src_stage |= barrier_src_stage;
+            dst_stage |= barrier_dst_stage;
+
+            match transfer.resource {
+                OwnedResource::Image {
+                    image,
+                    old_layout,
+                    new_layout,
+                } => {
+                    let subresource = ImageSubresourceRange::default()
+                        .aspect_mask(ImageAspectFlags::COLOR)
+                        .base_mip_level(0)
+                        .level_count(1)
+                        .base_array_layer(0)
+                        .layer_count(1);
+                    image_memory_barriers.push(
+                        ImageMemoryBarrier::default()
+                            .old_layout(old_layout)
+                            .new_layout(new_layout)
+                            .src_queue_family_index(src_family_index)
+                            .dst_queue_family_index(dst_family_index)
+                            .src_access_mask(src_access)
+                            .dst_access_mask(dst_access)
+                            .image(image)
+                            .subresource_range(subresource),
+                    );
+                }
+                OwnedResource::Buffer {
+                    buffer,
+                    offset,
+                    size,
+                } => {
+                    buffer_memory_barriers.push(
+                        BufferMemoryBarrier::default()
+                            .src_queue_family_index(src_family_index)
+                            .dst_queue_family_index(dst_family_index)
+                            .src_access_mask(src_access)
+                            .dst_access_mask(dst_access)
+                            .buffer(buffer)
+                            .offset(offset)
+                            .size(size),
+                    );
+                }
+            }
+        }
+        if buffer_memory_barriers.is_empty() && image_memory_barriers.is_empty() {
+            return Ok(());
+        }
+
+        unsafe {
+            self.get_device()?.cmd_pipeline_barrier(
+                *command_buffer.handler.as_ref(),
+                src_stage,
+                dst_stage,
+                DependencyFlags::empty(),
+                &[],
+                &buffer_memory_barriers,
+                &image_memory_barriers,
+            );
+        }
+        Ok(())
+    }
+
+    /// Record the acquires in the frame's command buffer submitted ahead of its draws
+    /// None when there is nothing to acquire, the frame then only submits its draws
+    pub(crate) fn queue_ownership_record_frame_acquires(
+        &self,
+        frame_index: usize,
+        transfers: &[OwnershipTransfer],
+    ) -> Result<Option<vk::CommandBuffer>, EngineError> {
+        if transfers.is_empty() {
+            return Ok(None);
+        }
+        let queue_ownership = self.get_queue_ownership()?;
+        let command_buffer = &queue_ownership
+            .frames
+            .get(frame_index)
+            .acquire_command_buffer;
+        self.queue_ownership_record(command_buffer, |command_buffer| {
+            self.record_ownership_acquire(command_buffer, transfers)
+        })?;
+        Ok(Some(*command_buffer.handler.as_ref()))
+    }
+
+    /// Submit the graphics side of the frame's dispatches after the uploads they may read
+    /// The uploads are acquired first since the released images can be among them
+    /// Returns the semaphore the compute submission waits on
+    pub(crate) fn queue_ownership_submit_releases(
+        &self,
+        frame_index: usize,
+        acquires: &[OwnershipTransfer],
+        releases: &[OwnershipTransfer],
+        wait_semaphores: &[Semaphore],
+    ) -> Result<Semaphore, EngineError> {
+        let queue_ownership = self.get_queue_ownership()?;
+        let frame = queue_ownership.frames.get(frame_index);
+        self.queue_ownership_record(&frame.release_command_buffer, |command_buffer| {
+            self.record_ownership_acquire(command_buffer, acquires)?;
+            self.record_ownership_release(command_buffer, releases)
+        })?;
+
+        let command_buffers = [*frame.release_command_buffer.handler.as_ref()];
+        let wait_dst_stage_mask = vec![PipelineStageFlags::ALL_COMMANDS; wait_semaphores.len()];
+        let signal_semaphores = [frame.release_semaphore];
+        let submit_info = [SubmitInfo::default()
+            .command_buffers(&command_buffers)
+            .wait_semaphores(wait_semaphores)
+            .wait_dst_stage_mask(&wait_dst_stage_mask)
+            .signal_semaphores(&signal_semaphores)];
+        if let Err(err) = unsafe {
+            self.get_device()?
+                .queue_submit(queue_ownership.queue, &submit_info, vk::Fence::null())
+        } {
+            error!(
+                "Failed to submit the vulkan queue ownership releases: {:?}",
+                err
+            );
+            if err == vk::Result::ERROR_DEVICE_LOST {
+                return Err(EngineError::DeviceLost);
+            }
+            return Err(EngineError::VulkanFailed);
+        }
+        Ok(frame.release_semaphore)
+    }
+
+    /// Acquire on the graphics queue and wait for it, for the handovers whose release is done
+    pub(crate) fn queue_ownership_acquire_now(
+        &self,
+        transfers: &[OwnershipTransfer],
+    ) -> Result<(), EngineError> {
+        if transfers.is_empty() {
+            return Ok(());
+        }
+        let queue_ownership = self.get_queue_ownership()?;
+        let device = self.get_device()?;
+        let command_buffer =
+            CommandBuffer::allocate_and_begin_single_use(device, &queue_ownership.command_pool)?;
+        self.record_ownership_acquire(&command_buffer, transfers)?;
+        command_buffer.end_single_use(device, &queue_ownership.command_pool, queue_ownership.queue)
+    }
+
+    /// The previous use of the frame's command buffers is done once the frame began
+    fn queue_ownership_record<F>(
+        &self,
+        command_buffer: &CommandBuffer,
+        record: F,
+    ) -> Result<(), EngineError>
+    where
+        F: FnOnce(&CommandBuffer) -> Result<(), EngineError>,
+    {
+        let device = self.get_device()?;
+        command_buffer.reset(device)?;
+        let is_single_use = true;
+        let is_renderpass_continue = false;
+        let is_simultaneous_use = false;
+        command_buffer.begin(
+            device,
+            is_single_use,
+            is_renderpass_continue,
+            is_simultaneous_use,
+        )?;
+        record(command_buffer)?;
+        command_buffer.end(device)
+    }
+}
@@ -0,0 +1,1023 @@
+use ash::vk::{
+    self, AccessFlags, DependencyFlags, Extent2D, Filter, Format, ImageAspectFlags, ImageBlit,
+    ImageLayout, ImageMemoryBarrier, ImageSubresourceLayers, ImageSubresourceRange, ImageTiling,
+    ImageUsageFlags, ImageView, MemoryPropertyFlags, Offset3D, PipelineStageFlags, Rect2D,
+    SampleCountFlags, QUEUE_FAMILY_IGNORED,
+};
+
+use crate::{
+    core::debug::errors::EngineError,
+    error,
+    renderer::vulkan::{
+        vulkan_shaders::builtin_shaders::post_process_shaders::{
+            post_process_get_passes, PostProcessConstants, PostProcessEffect,
+        },
+        vulkan_types::VulkanRendererBackend,
+        vulkan_utils::{
+            camera_pass::VulkanCameraTarget,
+            image::{Image, ImageCreatorParameters},
+        },
+    },
+};
+
+use super::{renderpass::Renderpass, swapchain::VULKAN_HDR_ATTACHMENT_FORMAT};
+
+/// Handle of an attachment declared in the render graph of the frame
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct RenderGraphAttachment(usize);
+
+/// Attachment allocated by the graph at the size of the swapchain
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct RenderGraphAttachmentInfo {
+    pub format: Format,
+    pub sample_count: SampleCountFlags,
+    pub usage: ImageUsageFlags,
+    pub aspect: ImageAspectFlags,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum RenderGraphAttachmentSource {
+    /// Only lives during the frame, its image can be shared with the attachments used before or after it
+    Transient(RenderGraphAttachmentInfo),
+    /// Image acquired from the swapchain of a window, made ready to be presented after the last pass
+    Swapchain {
+        image: vk::Image,
+        view: ImageView,
+        extent: Extent2D,
+        state: RenderGraphImageState,
+    },
+}
+
+/// How a pass uses one of its attachments
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum RenderGraphAccess {
+    /// Color or resolve attachment, the renderpass clears or overwrites it
+    ColorWrite,
+    /// Depth stencil attachment, cleared by the renderpass
+    DepthWrite,
+    /// Sampled by the fragment stage
+    Sampled,
+    /// Copied out of the image, e.g. for a screenshot
+    TransferRead,
+    /// Copied into the image, e.g. by the upscale of the world
+    TransferWrite,
+}
+
+/// Layout of an image and its last access, the next access waits for it
+#[derive(Clone, Copy, Debug)]
+struct RenderGraphImageState {
+    layout: ImageLayout,
+    stage: PipelineStageFlags,
+    access: AccessFlags,
+}
+
+impl Default for RenderGraphImageState {
+    fn default() -> Self {
+        Self {
+            layout: ImageLayout::UNDEFINED,
+            stage: PipelineStageFlags::TOP_OF_PIPE,
+            access: AccessFlags::empty(),
+        }
+    }
+}
+
+impl RenderGraphImageState {
+    /// The submission waits for the acquired image at the color output stage
+    const SWAPCHAIN_ACQUIRED: Self = Self {
+        layout: ImageLayout::UNDEFINED,
+        stage: PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+        access: AccessFlags::empty(),
+    };
+
+    const PRESENTED: Self = Self {
+        layout: ImageLayout::PRESENT_SRC_KHR,
+        stage: PipelineStageFlags::BOTTOM_OF_PIPE,
+        access: AccessFlags::empty(),
+    };
+
+    fn is_written(&self) -> bool {
+        self.access.intersects(
+            AccessFlags::COLOR_ATTACHMENT_WRITE
+                | AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE
+                | AccessFlags::TRANSFER_WRITE,
+        )
+    }
+}
+
+impl RenderGraphAccess {
+    fn get_state(&self) -> RenderGraphImageState {
+        match self {
+            Self::ColorWrite => RenderGraphImageState {
+                layout: ImageLayout::COLOR_ATTACHMENT_OPTIMAL,
+                stage: PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                access: AccessFlags::COLOR_ATTACHMENT_READ | AccessFlags::COLOR_ATTACHMENT_WRITE,
+            },
+            Self::DepthWrite => RenderGraphImageState {
+                layout: ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL,
+                stage: PipelineStageFlags::EARLY_FRAGMENT_TESTS
+                    | PipelineStageFlags::LATE_FRAGMENT_TESTS,
+                access: AccessFlags::DEPTH_STENCIL_ATTACHMENT_READ
+                    | AccessFlags::DEPTH_STENCIL_ATTACHMENT_WRITE,
+            },
+            Self::Sampled => RenderGraphImageState {
+                layout: ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                stage: PipelineStageFlags::FRAGMENT_SHADER,
+                access: AccessFlags::SHADER_READ,
+            },
+            Self::TransferRead => RenderGraphImageState {
+                layout: ImageLayout::TRANSFER_SRC_OPTIMAL,
+                stage: PipelineStageFlags::TRANSFER,
+                access: AccessFlags::TRANSFER_READ,
+            },
+            Self::TransferWrite => RenderGraphImageState {
+                layout: ImageLayout::TRANSFER_DST_OPTIMAL,
+                stage: PipelineStageFlags::TRANSFER,
+                access: AccessFlags::TRANSFER_WRITE,
+            },
+        }
+    }
+
+    fn is_write(&self) -> bool {
+        !matches!(self, Self::Sampled | Self::TransferRead)
+    }
+}
+
+/// Renderpass begun by a pass and what records its draws
+#[derive(Clone, Copy, Debug)]
+pub(crate) enum RenderGraphPassKind {
+    /// Drawn by the frontend until the ui pass begins
+    World,
+    /// Fullscreen pass drawing into an offscreen attachment
+    PostProcess(PostProcessEffect, PostProcessConstants),
+    /// Fullscreen pass drawing into the swapchain image, then left open for the ui
+    Present(PostProcessEffect, PostProcessConstants),
+    /// Copy of its input to the hdr screenshot buffer of the frame, outside of a renderpass
+    HdrReadback,
+    /// Downsample of its input read back for the auto exposure, outside of a renderpass
+    LuminanceReadback,
+    /// Blit of the area the world is drawn in to the whole attachment, outside of a renderpass
+    Upscale,
+    /// Drawn by the frontend with one of the cameras of the frame, in the world renderpass
+    Camera(usize),
+    /// Copy of its input into the texture of one of the cameras, outside of a renderpass
+    CameraCopy(usize),
+    /// Fullscreen pass drawing the input of one of the cameras into the image of its window
+    WindowPresent(usize),
+}
+
+impl RenderGraphPassKind {
+    /// The frontend records its draws in the pass, it is ended by the next step of the frame
+    fn is_left_open(&self) -> bool {
+        matches!(self, Self::World | Self::Camera(..) | Self::Present(..))
+    }
+}
+
+pub(crate) struct RenderGraphPass {
+    pub name: &'static str,
+    pub kind: RenderGraphPassKind,
+    /// Written attachments, in the order of the renderpass attachments
+    pub attachments: Vec<(RenderGraphAttachment, RenderGraphAccess)>,
+    /// Attachments sampled or copied by the pass
+    pub inputs: Vec<(RenderGraphAttachment, RenderGraphAccess)>,
+}
+
+impl RenderGraphPass {
+    pub fn new(name: &'static str, kind: RenderGraphPassKind) -> Self {
+        Self {
+            name,
+            kind,
+            attachments: Vec::new(),
+            inputs: Vec::new(),
+        }
+    }
+
+    pub fn write(mut self, attachment: RenderGraphAttachment, access: RenderGraphAccess) -> Self {
+        self.attachments.push((attachment, access));
+        self
+    }
+
+    pub fn read(mut self, attachment: RenderGraphAttachment) -> Self {
+        self.inputs.push((attachment, RenderGraphAccess::Sampled));
+        self
+    }
+
+    pub fn copy(mut self, attachment: RenderGraphAttachment) -> Self {
+        self.inputs
+            .push((attachment, RenderGraphAccess::TransferRead));
+        self
+    }
+
+    fn writes(&self, attachment: RenderGraphAttachment) -> bool {
+        self.attachments
+            .iter()
+            .any(|(written, _)| *written == attachment)
+    }
+
+    /// The inputs first, they are read before the attachments are written
+    fn get_accesses(&self) -> Vec<(RenderGraphAttachment, RenderGraphAccess)> {
+        self.inputs
+            .iter()
+            .chain(self.attachments.iter())
+            .copied()
+            .collect()
+    }
+}
+
+/// Image of the transient attachments, kept between the frames and destroyed with the swapchain
+struct RenderGraphImage {
+    info: RenderGraphAttachmentInfo,
+    image: Image,
+    state: RenderGraphImageState,
+}
+
+/// Passes of the frame and the attachments they read and write, declared again every frame
+/// The passes are ordered from their dependencies and the barriers between them are derived from their accesses
+/// The transient attachments whose lifetimes do not overlap share the same image
+#[derive(Default)]
+pub(crate) struct RenderGraph {
+    attachments: Vec<RenderGraphAttachmentSource>,
+    passes: Vec<RenderGraphPass>,
+    /// Indices of the passes in execution order
+    order: Vec<usize>,
+    /// Image of each transient attachment for the frame, none for the swapchain images
+    bindings: Vec<Option<usize>>,
+    /// Position in the order of the next pass to record
+    next_pass: usize,
+    /// The last recorded pass is left open for the frontend
+    is_pass_open: bool,
+    /// Camera drawn by the open pass, none when it is not a camera pass
+    open_camera_pass: Option<usize>,
+    /// Fraction of the framebuffer size the world is drawn at this frame
+    render_scale: f32,
+    images: Vec<RenderGraphImage>,
+}
+
+impl RenderGraph {
+    fn reset(&mut self) {
+        self.attachments.clear();
+        self.passes.clear();
+        self.order.clear();
+        self.bindings.clear();
+        self.next_pass = 0;
+        self.is_pass_open = false;
+        self.open_camera_pass = None;
+        self.render_scale = 1.0;
+    }
+
+    pub fn create_attachment(&mut self, info: RenderGraphAttachmentInfo) -> RenderGraphAttachment {
+        self.attachments
+            .push(RenderGraphAttachmentSource::Transient(info));
+        RenderGraphAttachment(self.attachments.len() - 1)
+    }
+
+    pub fn import_swapchain(
+        &mut self,
+        image: vk::Image,
+        view: ImageView,
+        extent: Extent2D,
+    ) -> RenderGraphAttachment {
+        self.attachments
+            .push(RenderGraphAttachmentSource::Swapchain {
+                image,
+                view,
+                extent,
+                state: RenderGraphImageState::SWAPCHAIN_ACQUIRED,
+            });
+        RenderGraphAttachment(self.attachments.len() - 1)
+    }
+
+    pub fn add_pass(&mut self, pass: RenderGraphPass) {
+        self.passes.push(pass);
+    }
+
+    /// Every pass runs after the ones writing what it reads, and after the previously declared ones writing the same attachments
+    /// The declaration order is kept between independent passes
+    fn sort_passes(&mut self) -> Result<(), EngineError> {
+        let count = self.passes.len();
+        let mut dependencies = vec![Vec::new(); count];
+        for (index, pass) in self.passes.iter().enumerate() {
+            for (input, _) in &pass.inputs {
+                let writers: Vec<usize> = (0..count)
+                    .filter(|&writer| writer != index && self.passes[writer].writes(*input))
+                    .collect();
+                if writers.is_empty() {
+                    error!(
+                        "The {} pass of the render graph reads an attachment no pass writes",
+                        pass.name
+                    );
+                    return Err(EngineError::InvalidValue);
+                }
+                dependencies[index].extend(writers);
+            }
+            for (attachment, _) in &pass.attachments {
+                dependencies[index]
+                    .extend((0..index).filter(|&writer| self.passes[writer].writes(*attachment)));
+            }
+        }
+
+        let mut is_scheduled = vec![false; count];
+        self.order.clear();
+        while self.order.len() < count {
+            let next = (0..count).find(|&index| {
+                !is_scheduled[index]
+                    && dependencies[index]
+                        .iter()
+                        .all(|&dependency| is_scheduled[dependency])
+            });
+            match next {
+                Some(index) => {
+                    is_scheduled[index] = true;
+                    self.order.push(index);
+                }
+                None => {
+                    error!("The passes of the render graph depend on each other");
+                    return Err(EngineError::InvalidValue);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// First and last positions in the order where each attachment is used, none if it is never used
+    fn get_lifetimes(&self) -> Vec<Option<(usize, usize)>> {
+        let mut lifetimes = vec![None; self.attachments.len()];
+        for (position, pass_index) in self.order.iter().enumerate() {
+            for (attachment, _) in self.passes[*pass_index].get_accesses() {
+                let lifetime: &mut Option<(usize, usize)> = &mut lifetimes[attachment.0];
+                *lifetime =
+                    Some(lifetime.map_or((position, position), |(first, _)| (first, position)));
+            }
+        }
+        lifetimes
+    }
+
+    fn get_image(
+        &self,
+        attachment: RenderGraphAttachment,
+    ) -> Result<(vk::Image, ImageView, ImageAspectFlags), EngineError> {
+        match (self.attachments[attachment.0], self.bindings[attachment.0]) {
+            (RenderGraphAttachmentSource::Swapchain { image, view, .. }, _) => {
+                Ok((image, view, ImageAspectFlags::COLOR))
+            }
+            (RenderGraphAttachmentSource::Transient(info), Some(binding)) => {
+                let image = &self.images[binding].image;
+                Ok((image.image, image.get_image_view()?, info.aspect))
+            }
+            (RenderGraphAttachmentSource::Transient(_), None) => {
+                error!(
+                    "The attachment {} of the render graph has no image",
+                    attachment.0
+                );
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
+
+    /// Size of the imported swapchain images, none for the transient attachments sized like the main swapchain
+    fn get_extent(&self, attachment: RenderGraphAttachment) -> Option<Extent2D> {
+        match self.attachments[attachment.0] {
+            RenderGraphAttachmentSource::Swapchain { extent, .. } => Some(extent),
+            RenderGraphAttachmentSource::Transient(_) => None,
+        }
+    }
+
+    fn get_state_mut(
+        &mut self,
+        attachment: RenderGraphAttachment,
+    ) -> Result<&mut RenderGraphImageState, EngineError> {
+        match (
+            &mut self.attachments[attachment.0],
+            self.bindings[attachment.0],
+        ) {
+            (RenderGraphAttachmentSource::Swapchain { state, .. }, _) => Ok(state),
+            (RenderGraphAttachmentSource::Transient(_), Some(binding)) => {
+                Ok(&mut self.images[binding].state)
+            }
+            (RenderGraphAttachmentSource::Transient(_), None) => {
+                error!(
+                    "The attachment {} of the render graph has no image",
+                    attachment.0
+                );
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
+
+    /// Barriers bringing the images in the state of their next access, with the stages they wait on and block
+    /// The passes overwrite what they write, the previous content is discarded
+    fn transition(
+        &mut self,
+        accesses: &[(RenderGraphAttachment, RenderGraphAccess)],
+    ) -> Result<
+        (
+            Vec<ImageMemoryBarrier<'static>>,
+            PipelineStageFlags,
+            PipelineStageFlags,
+        ),
+        EngineError,
+    > {
+        let mut barriers = Vec::new();
+        let mut src_stage = PipelineStageFlags::empty();
+        let mut dst_stage = PipelineStageFlags::empty();
+        for (attachment, access) in accesses {
+            let (image, _, aspect) = self.get_image(*attachment)?;
+            let next = access.get_state();
+            let state = self.get_state_mut(*attachment)?;
+            // Reading again in the same layout does not need to wait
+            if !access.is_write() && !state.is_written() && state.layout == next.layout {
+                continue;
+            }
+            let old_layout = if access.is_write() {
+                ImageLayout::UNDEFINED
+            } else {
+                state.layout
+            };
+            barriers.push(
+                ImageMemoryBarrier::default()
+                    .src_access_mask(state.access)
+                    .dst_access_mask(next.access)
+                    .old_layout(old_layout)
+                    .new_layout(next.layout)
+                    .src_queue_family_index(QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(QUEUE_FAMILY_IGNORED)
+                    .image(image)
+                    .subresource_range(get_subresource_range(aspect)),
+            );
+            src_stage |= state.stage;
+            dst_stage |= next.stage;
+            *state = next;
+        }
+        Ok((barriers, src_stage, dst_stage))
+    }
+
+    /// Barriers of the swapchain images once the last pass is done, without the ones no pass drew into
+    fn transition_to_present(&mut self) -> Vec<ImageMemoryBarrier<'static>> {
+        let mut barriers = Vec::new();
+        for source in self.attachments.iter_mut() {
+            let RenderGraphAttachmentSource::Swapchain { image, state, .. } = source else {
+                continue;
+            };
+            if state.layout == ImageLayout::UNDEFINED {
+                continue;
+            }
+            let next = RenderGraphImageState::PRESENTED;
+            barriers.push(
+                ImageMemoryBarrier::default()
+                    .src_access_mask(state.access)
+                    .dst_access_mask(next.access)
+                    .old_layout(state.layout)
+                    .new_layout(next.layout)
+                    .src_queue_family_index(QUEUE_FAMILY_IGNORED)
+                    .dst_queue_family_index(QUEUE_FAMILY_IGNORED)
+                    .image(*image)
+                    .subresource_range(get_subresource_range(ImageAspectFlags::COLOR)),
+            );
+            *state = next;
+        }
+        barriers
+    }
+
+    fn is_done(&self) -> bool {
+        self.next_pass >= self.order.len()
+    }
+}
+
+/// The attachments only use their first mip level
+fn get_subresource_range(aspect: ImageAspectFlags) -> ImageSubresourceRange {
+    ImageSubresourceRange::default()
+        .aspect_mask(aspect)
+        .base_mip_level(0)
+        .level_count(1)
+        .base_array_layer(0)
+        .layer_count(1)
+}
+
+impl VulkanRendererBackend<'_> {
+    pub fn render_graph_init(&mut self) -> Result<(), EngineError> {
+        *self.context.frame.render_graph.get_mut() = RenderGraph::default();
+        Ok(())
+    }
+
+    pub fn render_graph_shutdown(&mut self) -> Result<(), EngineError> {
+        self.render_graph_destroy_images()
+    }
+
+    /// Destroy the images of the transient attachments, they must not be used by the frames in flight anymore
+    pub(crate) fn render_graph_destroy_images(&self) -> Result<(), EngineError> {
+        let images = std::mem::take(&mut self.context.frame.render_graph.borrow_mut().images);
+        for image in images {
+            if let Err(err) = self.destroy_image(&image.image) {
+                error!(
+                    "Failed to destroy an image of the vulkan render graph: {:?}",
+                    err
+                );
+                return Err(EngineError::ShutdownFailed);
+            }
+        }
+        Ok(())
+    }
+
+    /// Attachments of a pass drawn with the world renderpass, in the order of its attachments
+    fn render_graph_declare_world_attachments(
+        &self,
+        graph: &mut RenderGraph,
+        hdr: RenderGraphAttachment,
+        (depth_format, depth_aspect): (Format, ImageAspectFlags),
+    ) -> Vec<(RenderGraphAttachment, RenderGraphAccess)> {
+        let depth = graph.create_attachment(RenderGraphAttachmentInfo {
+            format: depth_format,
+            sample_count: self.sample_count,
+            usage: ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT,
+            aspect: depth_aspect,
+        });
+        let mut attachments = vec![
+            (hdr, RenderGraphAccess::ColorWrite),
+            (depth, RenderGraphAccess::DepthWrite),
+        ];
+        if self.sample_count != SampleCountFlags::TYPE_1 {
+            // Only read by the resolve, the samples never need to leave the gpu tile memory
+            let multisampled_color = graph.create_attachment(RenderGraphAttachmentInfo {
+                format: VULKAN_HDR_ATTACHMENT_FORMAT,
+                sample_count: self.sample_count,
+                usage: ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::TRANSIENT_ATTACHMENT,
+                aspect: ImageAspectFlags::COLOR,
+            });
+            attachments.push((multisampled_color, RenderGraphAccess::ColorWrite));
+        }
+        attachments
+    }
+
+    /// The cameras drawn into textures or windows, the world, then the enabled post processing passes, the last one drawing into the swapchain image
+    fn render_graph_declare_frame(&self) -> Result<(), EngineError> {
+        let depth_format = match self.get_physical_device_info()?.depth_format {
+            Some(format) => format,
+            None => {
+                error!(
+                    "The render graph can't declare the depth attachment without a depth format"
+                );
+                return Err(EngineError::AccessFailed);
+            }
+        };
+        // The view of a depth stencil attachment must cover both aspects
+        let depth_aspect = if self.device_has_stencil()? {
+            ImageAspectFlags::DEPTH | ImageAspectFlags::STENCIL
+        } else {
+            ImageAspectFlags::DEPTH
+        };
+        let swapchain = self.get_swapchain()?;
+        let image_index = self.context.frame.image_index as usize;
+        let texel_size = glam::Vec2::new(
+            1.0 / swapchain.extent.width.max(1) as f32,
+            1.0 / swapchain.extent.height.max(1) as f32,
+        );
+        let post_process_passes = post_process_get_passes(
+            &self.post_process_settings,
+            self.color_filter,
+            self.output_color_space,
+            texel_size,
+        );
+
+        let mut graph = self.context.frame.render_graph.borrow_mut();
+        graph.reset();
+        // Drawn in by a pass then sampled by the next one, the hdr screenshots copy it
+        let offscreen_info = RenderGraphAttachmentInfo {
+            format: VULKAN_HDR_ATTACHMENT_FORMAT,
+            sample_count: SampleCountFlags::TYPE_1,
+            usage: ImageUsageFlags::COLOR_ATTACHMENT
+                | ImageUsageFlags::SAMPLED
+                | ImageUsageFlags::TRANSFER_SRC,
+            aspect: ImageAspectFlags::COLOR,
+        };
+        // Declared first, the world samples the textures they draw into
+        // Their attachments are not used anymore once copied, the world shares their images
+        for index in 0..self.context.frame.camera_passes.len() {
+            let camera_hdr = graph.create_attachment(offscreen_info);
+            let mut camera = RenderGraphPass::new("camera", RenderGraphPassKind::Camera(index));
+            for (attachment, access) in self.render_graph_declare_world_attachments(
+                &mut graph,
+                camera_hdr,
+                (depth_format, depth_aspect),
+            ) {
+                camera = camera.write(attachment, access);
+            }
+            graph.add_pass(camera);
+            match self.camera_pass_get(index)?.target {
+                VulkanCameraTarget::Texture { .. } => graph.add_pass(
+                    RenderGraphPass::new("camera copy", RenderGraphPassKind::CameraCopy(index))
+                        .copy(camera_hdr),
+                ),
+                // Nothing is presented when the window has no image this frame
+                VulkanCameraTarget::Window { .. } => {
+                    if let Some((image, view, extent)) = self.camera_pass_get_window_image(index)? {
+                        let window_image = graph.import_swapchain(image, view, extent);
+                        graph.add_pass(
+                            RenderGraphPass::new(
+                                "window present",
+                                RenderGraphPassKind::WindowPresent(index),
+                            )
+                            .read(camera_hdr)
+                            .write(window_image, RenderGraphAccess::ColorWrite),
+                        );
+                    }
+                }
+            }
+        }
+        let hdr = graph.create_attachment(offscreen_info);
+        let mut world = RenderGraphPass::new("world", RenderGraphPassKind::World);
+        for (attachment, access) in self.render_graph_declare_world_attachments(
+            &mut graph,
+            hdr,
+            (depth_format, depth_aspect),
+        ) {
+            world = world.write(attachment, access);
+        }
+        graph.add_pass(world);
+        // The world is drawn in the top left corner, stretched over the whole image before anything reads it
+        let hdr = if self.render_scale < 1.0 {
+            graph.render_scale = self.render_scale;
+            let upscaled = graph.create_attachment(RenderGraphAttachmentInfo {
+                usage: offscreen_info.usage | ImageUsageFlags::TRANSFER_DST,
+                ..offscreen_info
+            });
+            graph.add_pass(
+                RenderGraphPass::new("upscale", RenderGraphPassKind::Upscale)
+                    .copy(hdr)
+                    .write(upscaled, RenderGraphAccess::TransferWrite),
+            );
+            upscaled
+        } else {
+            hdr
+        };
+        // Declared right after the world, they run before the post processing passes
+        let current_frame_index = self.get_current_frame_index();
+        if self.screenshot_has_hdr_copy(current_frame_index) {
+            graph.add_pass(
+                RenderGraphPass::new("hdr readback", RenderGraphPassKind::HdrReadback).copy(hdr),
+            );
+        }
+        if self.luminance_readback_has_copy(current_frame_index) {
+            graph.add_pass(
+                RenderGraphPass::new("luminance readback", RenderGraphPassKind::LuminanceReadback)
+                    .copy(hdr),
+            );
+        }
+
+        let swapchain_image = graph.import_swapchain(
+            swapchain.images[image_index],
+            swapchain.image_views[image_index],
+            swapchain.extent,
+        );
+        let mut input = hdr;
+        let pass_count = post_process_passes.len();
+        for (index, (effect, constants)) in post_process_passes.into_iter().enumerate() {
+            if index + 1 == pass_count {
+                graph.add_pass(
+                    RenderGraphPass::new(
+                        "present",
+                        RenderGraphPassKind::Present(effect, constants),
+                    )
+                    .read(input)
+                    .write(swapchain_image, RenderGraphAccess::ColorWrite),
+                );
+            } else {
+                let output = graph.create_attachment(offscreen_info);
+                graph.add_pass(
+                    RenderGraphPass::new(
+                        "post processing",
+                        RenderGraphPassKind::PostProcess(effect, constants),
+                    )
+                    .read(input)
+                    .write(output, RenderGraphAccess::ColorWrite),
+                );
+                input = output;
+            }
+        }
+        Ok(())
+    }
+
+    /// Bind an image to every used transient attachment, an image is shared by the attachments whose lifetimes do not overlap
+    /// The missing images are created at the size of the swapchain
+    fn render_graph_allocate_images(&self) -> Result<(), EngineError> {
+        let extent = self.get_swapchain()?.extent;
+        let mut graph = self.context.frame.render_graph.borrow_mut();
+        let lifetimes = graph.get_lifetimes();
+        // Last position where the images are used by the attachments bound so far
+        let mut busy_until: Vec<Option<usize>> = vec![None; graph.images.len()];
+        let mut attachments: Vec<(usize, (usize, usize))> = lifetimes
+            .iter()
+            .enumerate()
+            .filter_map(|(attachment, lifetime)| lifetime.map(|lifetime| (attachment, lifetime)))
+            .collect();
+        attachments.sort_by_key(|(_, (first, _))| *first);
+
+        graph.bindings = vec![None; graph.attachments.len()];
+        for (attachment, (first, last)) in attachments {
+            let RenderGraphAttachmentSource::Transient(info) = graph.attachments[attachment] else {
+                continue;
+            };
+            let free_image = graph.images.iter().enumerate().position(|(index, image)| {
+                image.info == info && busy_until[index].is_none_or(|until| until < first)
+            });
+            let image_index = match free_image {
+                Some(image_index) => image_index,
+                None => {
+                    let image_creation_parameters = ImageCreatorParameters::default()
+                        .height(extent.height)
+                        .width(extent.width)
+                        .image_format(info.format)
+                        .image_tiling(ImageTiling::OPTIMAL)
+                        .image_usage_flags(info.usage)
+                        .memory_flags(MemoryPropertyFlags::DEVICE_LOCAL)
+                        .should_create_view(true)
+                        .image_view_aspect_flags(info.aspect)
+                        .sample_count(info.sample_count);
+                    let image = match self.create_image(image_creation_parameters) {
+                        Ok(image) => image,
+                        Err(err) => {
+                            error!(
+                                "Failed to create an image of the vulkan render graph: {:?}",
+                                err
+                            );
+                            return Err(EngineError::InitializationFailed);
+                        }
+                    };
+                    graph.images.push(RenderGraphImage {
+                        info,
+                        image,
+                        state: RenderGraphImageState::default(),
+                    });
+                    busy_until.push(None);
+                    graph.images.len() - 1
+                }
+            };
+            busy_until[image_index] = Some(last);
+            graph.bindings[attachment] = Some(image_index);
+        }
+        Ok(())
+    }
+
+    /// Declare and compile the passes of the frame, then record them until the world pass is open
+    pub(crate) fn render_graph_begin_frame(&self) -> Result<(), EngineError> {
+        self.post_process_shaders_begin_frame()?;
+        self.render_graph_declare_frame()?;
+        if let Err(err) = self.context.frame.render_graph.borrow_mut().sort_passes() {
+            error!(
+                "Failed to order the passes of the vulkan render graph: {:?}",
+                err
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+        self.render_graph_allocate_images()?;
+        self.render_graph_execute()
+    }
+
+    /// Fraction of the framebuffer size the world of the current frame is drawn at
+    pub(crate) fn render_graph_get_render_scale(&self) -> f32 {
+        self.context.frame.render_graph.borrow().render_scale
+    }
+
+    /// Camera drawn by the pass left open for the frontend, none when it is the world or the ui
+    pub(crate) fn render_graph_get_open_camera_pass(&self) -> Option<usize> {
+        self.context.frame.render_graph.borrow().open_camera_pass
+    }
+
+    /// End the pass left open for the frontend, if any
+    pub(crate) fn render_graph_end_pass(&self) -> Result<(), EngineError> {
+        let mut graph = self.context.frame.render_graph.borrow_mut();
+        if !graph.is_pass_open {
+            return Ok(());
+        }
+        graph.is_pass_open = false;
+        graph.open_camera_pass = None;
+        let current_frame_index = self.get_current_frame_index();
+        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
+        self.renderpass_end(command_buffer)
+    }
+
+    fn render_graph_record_barriers(
+        &self,
+        barriers: &[ImageMemoryBarrier<'_>],
+        src_stage: PipelineStageFlags,
+        dst_stage: PipelineStageFlags,
+    ) -> Result<(), EngineError> {
+        if barriers.is_empty() {
+            return Ok(());
+        }
+        let current_frame_index = self.get_current_frame_index();
+        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
+        unsafe {
+            self.get_device()?.cmd_pipeline_barrier(
+                *command_buffer.handler.as_ref(),
+                src_stage,
+                dst_stage,
+                DependencyFlags::empty(),
+                &[],
+                &[],
+                barriers,
+            );
+        }
+        Ok(())
+    }
+
+    /// Stretch the area of the source the world is drawn in over the whole destination
+    fn render_graph_record_upscale(
+        &self,
+        (source, destination): (vk::Image, vk::Image),
+        extent: Extent2D,
+    ) -> Result<(), EngineError> {
+        let scale = self.render_graph_get_render_scale();
+        let scaled_width = ((extent.width as f32 * scale).ceil() as u32).clamp(1, extent.width);
+        let scaled_height = ((extent.height as f32 * scale).ceil() as u32).clamp(1, extent.height);
+        let subresource = ImageSubresourceLayers::default()
+            .aspect_mask(ImageAspectFlags::COLOR)
+            .mip_level(0)
+            .base_array_layer(0)
+            .layer_count(1);
+        let blits = [ImageBlit::default()
+            .src_subresource(subresource)
+            .src_offsets([
+                Offset3D::default(),
+                Offset3D::default()
+                    .x(scaled_width as i32)
+                    .y(scaled_height as i32)
+                    .z(1),
+            ])
+            .dst_subresource(subresource)
+            .dst_offsets([
+                Offset3D::default(),
+                Offset3D::default()
+                    .x(extent.width as i32)
+                    .y(extent.height as i32)
+                    .z(1),
+            ])];
+        let current_frame_index = self.get_current_frame_index();
+        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
+        unsafe {
+            self.get_device()?.cmd_blit_image(
+                *command_buffer.handler.as_ref(),
+                source,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                destination,
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                &blits,
+                Filter::LINEAR,
+            );
+        }
+        Ok(())
+    }
+
+    fn render_graph_get_renderpass(
+        &self,
+        kind: &RenderGraphPassKind,
+    ) -> Result<&Renderpass, EngineError> {
+        match kind {
+            RenderGraphPassKind::World | RenderGraphPassKind::Camera(..) => self.get_renderpass(),
+            RenderGraphPassKind::PostProcess(..) => self.get_post_process_renderpass(),
+            RenderGraphPassKind::Present(..) | RenderGraphPassKind::WindowPresent(..) => {
+                self.get_present_renderpass()
+            }
+            RenderGraphPassKind::HdrReadback
+            | RenderGraphPassKind::LuminanceReadback
+            | RenderGraphPassKind::Upscale
+            | RenderGraphPassKind::CameraCopy(..) => {
+                error!("The copy passes of the render graph have no renderpass");
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    /// Record the next passes until one is left open for the frontend, the open pass is ended first
+    /// The swapchain images are made ready to be presented once every pass is recorded
+    pub(crate) fn render_graph_execute(&self) -> Result<(), EngineError> {
+        self.render_graph_end_pass()?;
+        let current_frame_index = self.get_current_frame_index();
+        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
+        let extent = self.get_swapchain()?.extent;
+        loop {
+            let (name, kind, images, views, inputs, framebuffer_extent) = {
+                let mut graph = self.context.frame.render_graph.borrow_mut();
+                if graph.is_done() {
+                    break;
+                }
+                let pass_index = graph.order[graph.next_pass];
+                graph.next_pass += 1;
+                let pass = &graph.passes[pass_index];
+                let (name, kind) = (pass.name, pass.kind);
+                let accesses = pass.get_accesses();
+                let attachments = pass.attachments.clone();
+                let inputs = pass.inputs.clone();
+                let (barriers, src_stage, dst_stage) = graph.transition(&accesses)?;
+                self.render_graph_record_barriers(&barriers, src_stage, dst_stage)?;
+                let (images, views): (Vec<vk::Image>, Vec<ImageView>) = attachments
+                    .iter()
+                    .map(|(attachment, _)| {
+                        graph
+                            .get_image(*attachment)
+                            .map(|(image, view, _)| (image, view))
+                    })
+                    .collect::<Result<Vec<(vk::Image, ImageView)>, EngineError>>()?
+                    .into_iter()
+                    .unzip();
+                let inputs = inputs
+                    .iter()
+                    .map(|(attachment, _)| {
+                        graph
+                            .get_image(*attachment)
+                            .map(|(image, view, _)| (image, view))
+                    })
+                    .collect::<Result<Vec<(vk::Image, ImageView)>, EngineError>>()?;
+                // The windows are not sized like the main swapchain
+                let framebuffer_extent = attachments
+                    .iter()
+                    .find_map(|(attachment, _)| graph.get_extent(*attachment))
+                    .unwrap_or(extent);
+                (name, kind, images, views, inputs, framebuffer_extent)
+            };
+
+            match kind {
+                RenderGraphPassKind::HdrReadback => {
+                    self.screenshot_record_hdr_copy(current_frame_index, inputs[0].0)?;
+                    continue;
+                }
+                RenderGraphPassKind::LuminanceReadback => {
+                    self.luminance_readback_record_copy(current_frame_index, inputs[0].0)?;
+                    continue;
+                }
+                RenderGraphPassKind::Upscale => {
+                    self.render_graph_record_upscale((inputs[0].0, images[0]), framebuffer_extent)?;
+                    continue;
+                }
+                RenderGraphPassKind::CameraCopy(index) => {
+                    self.camera_pass_record_copy(index, inputs[0].0)?;
+                    continue;
+                }
+                _ => (),
+            }
+
+            let renderpass = self.render_graph_get_renderpass(&kind)?;
+            let framebuffer = self.framebuffer_cache_get(
+                renderpass,
+                &views,
+                framebuffer_extent.width,
+                framebuffer_extent.height,
+            )?;
+            let begin_result = match kind {
+                RenderGraphPassKind::WindowPresent(..) => self.renderpass_begin_area(
+                    renderpass,
+                    command_buffer,
+                    framebuffer,
+                    Rect2D::default().extent(framebuffer_extent),
+                ),
+                _ => self.renderpass_begin(renderpass, command_buffer, framebuffer),
+            };
+            if let Err(err) = begin_result {
+                error!(
+                    "Failed to begin the renderpass of the {} pass of the render graph: {:?}",
+                    name, err
+                );
+                return Err(EngineError::UpdateFailed);
+            }
+            match kind {
+                RenderGraphPassKind::World => (),
+                RenderGraphPassKind::Camera(index) => {
+                    self.camera_pass_set_viewport(index)?;
+                    self.context
+                        .frame
+                        .render_graph
+                        .borrow_mut()
+                        .open_camera_pass = Some(index);
+                }
+                RenderGraphPassKind::PostProcess(effect, constants) => {
+                    self.draw_post_process_pass(effect, &constants, inputs[0].1, false)?
+                }
+                RenderGraphPassKind::Present(effect, constants) => {
+                    self.draw_post_process_pass(effect, &constants, inputs[0].1, true)?
+                }
+                RenderGraphPassKind::WindowPresent(index) => {
+                    self.camera_pass_record_window_present(index, inputs[0].1)?
+                }
+                RenderGraphPassKind::HdrReadback
+                | RenderGraphPassKind::LuminanceReadback
+                | RenderGraphPassKind::Upscale
+                | RenderGraphPassKind::CameraCopy(..) => (),
+            }
+            if kind.is_left_open() {
+                self.context.frame.render_graph.borrow_mut().is_pass_open = true;
+                return Ok(());
+            }
+            self.renderpass_end(command_buffer)?;
+        }
+
+        let barriers = self
+            .context
+            .frame
+            .render_graph
+            .borrow_mut()
+            .transition_to_present();
+        self.render_graph_record_barriers(
+            &barriers,
+            PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+            PipelineStageFlags::BOTTOM_OF_PIPE,
+        )
+    }
+}
@@ -1,21 +1,37 @@
 use ash::vk::{
-    self, AccessFlags, AttachmentDescription, AttachmentLoadOp, AttachmentReference,
-    AttachmentStoreOp, ClearColorValue, ClearDepthStencilValue, ClearValue, Extent2D, Framebuffer,
-    ImageLayout, Offset2D, PipelineBindPoint, PipelineStageFlags, Rect2D, RenderPassBeginInfo,
-    RenderPassCreateInfo, SampleCountFlags, SubpassContents, SubpassDependency, SubpassDescription,
-    SUBPASS_EXTERNAL,
+    self, AttachmentDescription, AttachmentLoadOp, AttachmentReference, AttachmentStoreOp,
+    ClearColorValue, ClearDepthStencilValue, ClearValue, Extent2D, Format, Framebuffer,
+    ImageLayout, Offset2D, PipelineBindPoint, Rect2D, RenderPassBeginInfo, RenderPassCreateInfo,
+    SampleCountFlags, SubpassContents, SubpassDescription, Viewport,
 };
 
 use crate::{
     core::debug::errors::EngineError,
     error,
     renderer::{
+        scene::camera::CameraViewport,
         utils::{color::Color, render_area::RenderArea},
-        vulkan::vulkan_types::VulkanRendererBackend,
+        vulkan::{
+            vulkan_types::VulkanRendererBackend,
+            vulkan_utils::camera_pass::camera_pass_clip_viewport,
+        },
     },
 };
 
-use super::command_buffer::CommandBuffer;
+use super::{command_buffer::CommandBuffer, swapchain::VULKAN_HDR_ATTACHMENT_FORMAT};
+
+/// Subpass of the world renderpass drawing the scene with the depth attachment
+pub const VULKAN_RENDERPASS_WORLD_SUBPASS: u32 = 0;
+/// Subpass of the post processing renderpass drawing a fullscreen pass
+pub const VULKAN_RENDERPASS_POST_PROCESS_SUBPASS: u32 = 0;
+/// Subpass of the present renderpass drawing the last post processing pass then the 2d overlay on top of it, without depth
+pub const VULKAN_RENDERPASS_UI_SUBPASS: u32 = 0;
+
+/// Index of the hdr image in the world renderpass attachments
+const VULKAN_RENDERPASS_COLOR_ATTACHMENT: u32 = 0;
+const VULKAN_RENDERPASS_DEPTH_ATTACHMENT: u32 = 1;
+/// Multisampled color of the world subpass, resolved into the hdr image
+const VULKAN_RENDERPASS_MULTISAMPLED_COLOR_ATTACHMENT: u32 = 2;
 
 pub(crate) enum RenderpassState {
     Ready,
@@ -33,38 +49,51 @@ pub(crate) struct Renderpass {
     pub depth: f32,
     pub stencil: u32,
     pub state: RenderpassState,
+    /// Samples per pixel of the color and depth attachments
+    pub sample_count: SampleCountFlags,
 }
 
 impl VulkanRendererBackend<'_> {
     fn init_color_attachment(&self) -> Result<AttachmentDescription, EngineError> {
         // TODO: make the renderpass attachments configurable
-        let format = self.get_swapchain()?.surface_format.format;
-        Ok(
-            AttachmentDescription::default()
-                .format(format)
-                .samples(SampleCountFlags::TYPE_1)
-                .load_op(AttachmentLoadOp::CLEAR)
-                .store_op(AttachmentStoreOp::STORE)
-                .stencil_load_op(AttachmentLoadOp::DONT_CARE)
-                .stencil_store_op(AttachmentStoreOp::DONT_CARE)
-                .initial_layout(ImageLayout::UNDEFINED) // Do not expect any particular layout before render pass starts
-                .final_layout(ImageLayout::PRESENT_SRC_KHR), // Transitioned to after the render pass
-        )
+        let format = VULKAN_HDR_ATTACHMENT_FORMAT;
+        // The resolve overwrites the whole image when the world is multisampled
+        let load_op = if self.sample_count == SampleCountFlags::TYPE_1 {
+            AttachmentLoadOp::CLEAR
+        } else {
+            AttachmentLoadOp::DONT_CARE
+        };
+        Ok(AttachmentDescription::default()
+            .format(format)
+            .samples(SampleCountFlags::TYPE_1)
+            .load_op(load_op)
+            .store_op(AttachmentStoreOp::STORE)
+            .stencil_load_op(AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(AttachmentStoreOp::DONT_CARE)
+            // The render graph transitions the attachments between the passes
+            .initial_layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .final_layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL))
     }
 
     fn init_depth_attachment(&self) -> Result<Option<AttachmentDescription>, EngineError> {
         // TODO: make the renderpass attachments configurable
         let format = self.get_physical_device_info()?.depth_format;
+        // The stencil is cleared with the depth when the format has one
+        let stencil_load_op = if self.device_has_stencil()? {
+            AttachmentLoadOp::CLEAR
+        } else {
+            AttachmentLoadOp::DONT_CARE
+        };
         if let Some(format) = format {
             Ok(Some(
                 AttachmentDescription::default()
                     .format(format)
-                    .samples(SampleCountFlags::TYPE_1)
+                    .samples(self.sample_count)
                     .load_op(AttachmentLoadOp::CLEAR)
                     .store_op(AttachmentStoreOp::DONT_CARE)
-                    .stencil_load_op(AttachmentLoadOp::DONT_CARE)
+                    .stencil_load_op(stencil_load_op)
                     .stencil_store_op(AttachmentStoreOp::DONT_CARE)
-                    .initial_layout(ImageLayout::UNDEFINED)
+                    .initial_layout(ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL)
                     .final_layout(ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL),
             ))
         } else {
@@ -72,24 +101,78 @@ impl VulkanRendererBackend<'_> {
         }
     }
 
-    fn init_dependencies(&self) -> Result<SubpassDependency, EngineError> {
-        // TODO: make the renderpass dependencies configurable
-        Ok(SubpassDependency::default()
-            .src_subpass(SUBPASS_EXTERNAL)
-            .src_stage_mask(PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
-            .dst_stage_mask(PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT)
-            .dst_access_mask(
-                AccessFlags::COLOR_ATTACHMENT_READ | AccessFlags::COLOR_ATTACHMENT_WRITE,
-            ))
+    /// None without anti aliasing, the world is then drawn directly in the hdr image
+    fn init_multisampled_color_attachment(
+        &self,
+    ) -> Result<Option<AttachmentDescription>, EngineError> {
+        if self.sample_count == SampleCountFlags::TYPE_1 {
+            return Ok(None);
+        }
+        let format = VULKAN_HDR_ATTACHMENT_FORMAT;
+        Ok(Some(
+            AttachmentDescription::default()
+                .format(format)
+                .samples(self.sample_count)
+                .load_op(AttachmentLoadOp::CLEAR)
+                // Only the resolved color is kept
+                .store_op(AttachmentStoreOp::DONT_CARE)
+                .stencil_load_op(AttachmentLoadOp::DONT_CARE)
+                .stencil_store_op(AttachmentStoreOp::DONT_CARE)
+                .initial_layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+                .final_layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL),
+        ))
+    }
+
+    /// Renderpass of a fullscreen pass drawing in a single color attachment
+    /// Every pixel is drawn so the previous content is not loaded
+    fn renderpass_create_fullscreen(&self, format: Format) -> Result<vk::RenderPass, EngineError> {
+        let attachments = [AttachmentDescription::default()
+            .format(format)
+            .samples(SampleCountFlags::TYPE_1)
+            .load_op(AttachmentLoadOp::DONT_CARE)
+            .store_op(AttachmentStoreOp::STORE)
+            .stencil_load_op(AttachmentLoadOp::DONT_CARE)
+            .stencil_store_op(AttachmentStoreOp::DONT_CARE)
+            .initial_layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)
+            .final_layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)];
+        let color_attachment_reference = [AttachmentReference::default()
+            .attachment(0)
+            .layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)];
+        let subpass = [SubpassDescription::default()
+            .pipeline_bind_point(PipelineBindPoint::GRAPHICS)
+            .color_attachments(&color_attachment_reference)];
+        let renderpass_info = RenderPassCreateInfo::default()
+            .attachments(&attachments)
+            .subpasses(&subpass);
+
+        let device = self.get_device()?;
+        unsafe {
+            match device.create_render_pass(&renderpass_info, self.get_allocator()?) {
+                Ok(renderpass) => Ok(renderpass),
+                Err(err) => {
+                    error!("Failed to create a fullscreen vulkan renderpass: {:?}", err);
+                    Err(EngineError::InitializationFailed)
+                }
+            }
+        }
     }
 
     pub fn renderpass_render_area_clamp(&mut self) -> Result<(), EngineError> {
         self.framebuffer_dimensions_init()?;
         let width = self.framebuffer_width as f32;
         let height = self.framebuffer_height as f32;
-        let render_area = &mut self.context.renderpass.as_mut().unwrap().render_area;
-        render_area.width = width;
-        render_area.height = height;
+        let frame = &mut self.context.frame;
+        for renderpass in [
+            &mut frame.renderpass,
+            &mut frame.post_process_renderpass,
+            &mut frame.present_renderpass,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            renderpass.render_area.width = width;
+            renderpass.render_area.height = height;
+        }
         Ok(())
     }
 
@@ -114,42 +197,53 @@ impl VulkanRendererBackend<'_> {
         // Color attachment
         let color_attachment = self.init_color_attachment()?;
         let color_attachment_reference = [AttachmentReference::default()
-            .attachment(0) // Attachment description array index
+            .attachment(VULKAN_RENDERPASS_COLOR_ATTACHMENT) // Attachment description array index
             .layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)];
-        let subpass = subpass.color_attachments(&color_attachment_reference);
         // Depth attachment, if there is one
         let depth_attachment = self.init_depth_attachment()?;
-        let has_depth = depth_attachment.is_some();
         let depth_attachment_reference = AttachmentReference::default()
-            .attachment(1) // Attachment description array index
+            .attachment(VULKAN_RENDERPASS_DEPTH_ATTACHMENT) // Attachment description array index
             .layout(ImageLayout::DEPTH_STENCIL_ATTACHMENT_OPTIMAL);
+        // Multisampled color attachment, resolved into the color attachment at the end of the world subpass
+        let multisampled_color_attachment = self.init_multisampled_color_attachment()?;
+        let multisampled_color_attachment_reference = [AttachmentReference::default()
+            .attachment(VULKAN_RENDERPASS_MULTISAMPLED_COLOR_ATTACHMENT)
+            .layout(ImageLayout::COLOR_ATTACHMENT_OPTIMAL)];
 
-        let subpass = if let Some(depth_attachment) = depth_attachment {
+        let subpass = if multisampled_color_attachment.is_some() {
+            subpass
+                .color_attachments(&multisampled_color_attachment_reference)
+                .resolve_attachments(&color_attachment_reference)
+        } else {
+            subpass.color_attachments(&color_attachment_reference)
+        };
+        let subpass = if depth_attachment.is_some() {
             subpass.depth_stencil_attachment(&depth_attachment_reference)
         } else {
             subpass
         };
-        // TODO: other attachment types (input, resolve, preserve)
+        // TODO: other attachment types (input, preserve)
 
-        // Dependencies
-        let dependencies = [self.init_dependencies()?];
+        // The barriers between the passes are recorded by the render graph
         let subpass = [subpass];
+
+        // The multisampled attachment needs the depth one before it to keep the indices
+        let mut attachments = vec![color_attachment];
+        if let Some(depth_attachment) = depth_attachment {
+            attachments.push(depth_attachment);
+        }
+        if let Some(multisampled_color_attachment) = multisampled_color_attachment {
+            if depth_attachment.is_none() {
+                error!("The vulkan renderpass can't be multisampled without a depth attachment");
+                return Err(EngineError::InitializationFailed);
+            }
+            attachments.push(multisampled_color_attachment);
+        }
+
         // Render pass create
         let renderpass_info = RenderPassCreateInfo::default()
-            .subpasses(&subpass)
-            .dependencies(&dependencies);
-
-        let attachments = [color_attachment];
-        let attachments_with_depth = if has_depth {
-            Some([color_attachment, depth_attachment.unwrap()])
-        } else {
-            None
-        };
-        let renderpass_info = if has_depth {
-            renderpass_info.attachments(attachments_with_depth.as_ref().unwrap())
-        } else {
-            renderpass_info.attachments(&attachments)
-        };
+            .attachments(&attachments)
+            .subpasses(&subpass);
 
         let device = self.get_device()?;
         let renderpass = unsafe {
@@ -162,33 +256,61 @@ impl VulkanRendererBackend<'_> {
             }
         };
 
-        self.context.renderpass = Some(Renderpass {
+        self.context.frame.renderpass = Some(Renderpass {
             handler: renderpass,
             render_area,
             clear_color,
             depth,
             stencil,
             state: RenderpassState::Ready,
+            sample_count: self.sample_count,
         });
 
-        Ok(())
+        // The fullscreen passes after the world are never multisampled
+        let fullscreen_renderpass = |handler| Renderpass {
+            handler,
+            render_area,
+            clear_color,
+            depth,
+            stencil,
+            state: RenderpassState::Ready,
+            sample_count: SampleCountFlags::TYPE_1,
+        };
+        let post_process_renderpass =
+            self.renderpass_create_fullscreen(VULKAN_HDR_ATTACHMENT_FORMAT)?;
+        self.context.frame.post_process_renderpass =
+            Some(fullscreen_renderpass(post_process_renderpass));
+        let present_renderpass =
+            self.renderpass_create_fullscreen(self.get_swapchain()?.surface_format.format)?;
+        self.context.frame.present_renderpass = Some(fullscreen_renderpass(present_renderpass));
+
+        // Nothing is in flight, the state of a previous renderpass can be dropped
+        self.resource_epoch_advance()
     }
 
     pub fn renderpass_shutdown(&mut self) -> Result<(), EngineError> {
-        let device = self.get_device()?;
-        unsafe {
-            device.destroy_render_pass(self.get_renderpass()?.handler, self.get_allocator()?);
-        };
+        let handlers = [
+            self.get_renderpass()?.handler,
+            self.get_post_process_renderpass()?.handler,
+            self.get_present_renderpass()?.handler,
+        ];
+        for handler in handlers {
+            self.framebuffer_cache_evict_renderpass(handler)?;
+            let device = self.get_device()?;
+            unsafe {
+                device.destroy_render_pass(handler, self.get_allocator()?);
+            };
+        }
         Ok(())
     }
 
     /// None if there swapchain needs to be recreated
     pub fn renderpass_begin(
         &self,
+        renderpass: &Renderpass,
         command_buffer: &CommandBuffer,
         frame_buffer: Framebuffer,
     ) -> Result<(), EngineError> {
-        let renderpass = self.get_renderpass()?;
         let render_area_offset = Offset2D {
             x: renderpass.render_area.x as i32,
             y: renderpass.render_area.y as i32,
@@ -206,7 +328,25 @@ impl VulkanRendererBackend<'_> {
         render_area_extent.width, render_area_extent.height, self.framebuffer_width, self.framebuffer_height);
             return Err(EngineError::InvalidValue);
         }
+        self.renderpass_begin_area(
+            renderpass,
+            command_buffer,
+            frame_buffer,
+            Rect2D {
+                offset: render_area_offset,
+                extent: render_area_extent,
+            },
+        )
+    }
 
+    /// Begin the renderpass over an area of a framebuffer not sized like the main one, e.g. an additional window
+    pub(crate) fn renderpass_begin_area(
+        &self,
+        renderpass: &Renderpass,
+        command_buffer: &CommandBuffer,
+        frame_buffer: Framebuffer,
+        render_area: Rect2D,
+    ) -> Result<(), EngineError> {
         let clear_values_color: ClearValue = ClearValue {
             color: ClearColorValue {
                 float32: [
@@ -223,15 +363,13 @@ impl VulkanRendererBackend<'_> {
                 stencil: renderpass.stencil,
             },
         };
-        let clear_values = [clear_values_color, clear_values_depth];
+        // Indexed like the attachments, the multisampled color is cleared like the color
+        let clear_values = [clear_values_color, clear_values_depth, clear_values_color];
 
         let renderpass_begin_info = RenderPassBeginInfo::default()
             .render_pass(renderpass.handler)
             .framebuffer(frame_buffer)
-            .render_area(Rect2D {
-                offset: render_area_offset,
-                extent: render_area_extent,
-            })
+            .render_area(render_area)
             .clear_values(&clear_values);
 
         let device = self.get_device()?;
@@ -254,8 +392,42 @@ impl VulkanRendererBackend<'_> {
         Ok(())
     }
 
+    /// Flipped viewport so the y axis goes up, and scissor of the whole framebuffer
+    /// The dynamic state is set again once the post processing passes changed it
+    pub fn set_frame_viewport(&self) -> Result<(), EngineError> {
+        self.set_frame_viewport_area(None)
+    }
+
+    /// Flipped viewport and scissor of an area of the framebuffer, in pixels from its top left corner
+    /// The whole framebuffer when none, the area is clipped to it
+    pub fn set_frame_viewport_area(&self, area: Option<CameraViewport>) -> Result<(), EngineError> {
+        let current_frame_index = self.get_current_frame_index();
+        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
+        let device = self.get_device()?;
+
+        let render_area = self.get_renderpass()?.render_area;
+        let area = area.unwrap_or(CameraViewport::new(render_area.width, render_area.height));
+        let viewport = [Viewport::default()
+            .x(area.x)
+            .y(area.y + area.height)
+            .width(area.width)
+            .height(-area.height)
+            .min_depth(0.)
+            .max_depth(1.)];
+        unsafe { device.cmd_set_viewport(*command_buffer.handler.as_ref(), 0, &viewport) };
+
+        let scissor = [camera_pass_clip_viewport(
+            Some(area),
+            self.framebuffer_width,
+            self.framebuffer_height,
+        )
+        .unwrap_or_default()];
+        unsafe { device.cmd_set_scissor(*command_buffer.handler.as_ref(), 0, &scissor) };
+        Ok(())
+    }
+
     pub fn get_renderpass(&self) -> Result<&Renderpass, EngineError> {
-        match &self.context.renderpass {
+        match &self.context.frame.renderpass {
             Some(renderpass) => Ok(renderpass),
             None => {
                 error!("Can't access the vulkan renderpass");
@@ -263,4 +435,34 @@ impl VulkanRendererBackend<'_> {
             }
         }
     }
+
+    pub fn get_renderpass_mut(&mut self) -> Result<&mut Renderpass, EngineError> {
+        match &mut self.context.frame.renderpass {
+            Some(renderpass) => Ok(renderpass),
+            None => {
+                error!("Can't access the vulkan renderpass");
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
+
+    pub fn get_post_process_renderpass(&self) -> Result<&Renderpass, EngineError> {
+        match &self.context.frame.post_process_renderpass {
+            Some(renderpass) => Ok(renderpass),
+            None => {
+                error!("Can't access the vulkan post processing renderpass");
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
+
+    pub fn get_present_renderpass(&self) -> Result<&Renderpass, EngineError> {
+        match &self.context.frame.present_renderpass {
+            Some(renderpass) => Ok(renderpass),
+            None => {
+                error!("Can't access the vulkan present renderpass");
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
 }
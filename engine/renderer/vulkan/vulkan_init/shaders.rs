@@ -2,13 +2,24 @@ use crate::{
     core::debug::errors::EngineError,
     error,
     renderer::vulkan::{
-        vulkan_shaders::builtin_shaders::BuiltinShaders, vulkan_types::VulkanRendererBackend,
+        vulkan_shaders::builtin_shaders::{object_shaders::ObjectShaders, BuiltinShaders},
+        vulkan_types::{VulkanRendererBackend, VulkanResourceContext},
     },
 };
 
-impl VulkanRendererBackend<'_> {
+impl VulkanResourceContext {
     pub fn get_builtin_shaders(&self) -> Result<&BuiltinShaders, EngineError> {
-        match &self.context.builtin_shaders {
+        match &self.builtin_shaders {
+            Some(shaders) => Ok(shaders),
+            None => {
+                error!("Can't access the vulkan builtin shaders");
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
+
+    pub fn get_builtin_shaders_mut(&mut self) -> Result<&mut BuiltinShaders, EngineError> {
+        match &mut self.builtin_shaders {
             Some(shaders) => Ok(shaders),
             None => {
                 error!("Can't access the vulkan builtin shaders");
@@ -17,10 +28,36 @@ impl VulkanRendererBackend<'_> {
         }
     }
 
+    pub fn get_object_shaders(&self) -> Result<&ObjectShaders, EngineError> {
+        Ok(&self.get_builtin_shaders()?.object_shaders)
+    }
+
+    pub fn get_object_shaders_mut(&mut self) -> Result<&mut ObjectShaders, EngineError> {
+        Ok(&mut self.get_builtin_shaders_mut()?.object_shaders)
+    }
+}
+
+impl VulkanRendererBackend<'_> {
+    pub fn get_builtin_shaders(&self) -> Result<&BuiltinShaders, EngineError> {
+        self.context.resources.get_builtin_shaders()
+    }
+
+    pub fn get_builtin_shaders_mut(&mut self) -> Result<&mut BuiltinShaders, EngineError> {
+        self.context.resources.get_builtin_shaders_mut()
+    }
+
+    pub fn get_object_shaders(&self) -> Result<&ObjectShaders, EngineError> {
+        self.context.resources.get_object_shaders()
+    }
+
+    pub fn get_object_shaders_mut(&mut self) -> Result<&mut ObjectShaders, EngineError> {
+        self.context.resources.get_object_shaders_mut()
+    }
+
     pub fn builtin_shaders_init(&mut self) -> Result<(), EngineError> {
         let device = self.get_device()?;
         let allocator = self.get_allocator()?;
-        self.context.builtin_shaders = Some(match BuiltinShaders::create(self) {
+        self.context.resources.builtin_shaders = Some(match BuiltinShaders::create(self) {
             Ok(shaders) => shaders,
             Err(err) => {
                 error!("Failed to create vulkan builtin shaders: {:?}", err);
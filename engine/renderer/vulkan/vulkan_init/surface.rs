@@ -7,7 +7,7 @@ use crate::{
 
 impl VulkanRendererBackend<'_> {
     pub fn get_surface_loader(&self) -> Result<&surface::Instance, EngineError> {
-        match &self.context.surface_loader {
+        match &self.context.core.surface_loader {
             Some(surface) => Ok(surface),
             None => {
                 error!("Can't access the vulkan surface loader");
@@ -17,7 +17,7 @@ impl VulkanRendererBackend<'_> {
     }
 
     pub fn get_surface(&self) -> Result<&SurfaceKHR, EngineError> {
-        match &self.context.surface {
+        match &self.context.core.surface {
             Some(surface) => Ok(surface),
             None => {
                 error!("Can't access the vulkan surface");
@@ -31,7 +31,7 @@ impl VulkanRendererBackend<'_> {
         let surface_loader = surface::Instance::new(self.get_entry()?, self.get_instance()?);
 
         // init the platform specific surface
-        let surface = match platform.get_vulkan_surface(&self.context) {
+        let surface = match platform.get_vulkan_surface(&self.context.core) {
             Ok(surface) => surface,
             Err(err) => {
                 error!("Failed to create the vulkan surface: {:?}", err);
@@ -39,8 +39,8 @@ impl VulkanRendererBackend<'_> {
             }
         };
 
-        self.context.surface_loader = Some(surface_loader);
-        self.context.surface = Some(surface);
+        self.context.core.surface_loader = Some(surface_loader);
+        self.context.core.surface = Some(surface);
 
         Ok(())
     }
@@ -50,8 +50,8 @@ impl VulkanRendererBackend<'_> {
             self.get_surface_loader()?
                 .destroy_surface(*self.get_surface()?, self.get_allocator()?);
         }
-        self.context.surface = None;
-        self.context.surface_loader = None;
+        self.context.core.surface = None;
+        self.context.core.surface_loader = None;
         Ok(())
     }
 }
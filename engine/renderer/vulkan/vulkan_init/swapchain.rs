@@ -4,24 +4,52 @@ use ash::{
     khr::swapchain,
     vk::{
         ColorSpaceKHR, CompositeAlphaFlagsKHR, Extent2D, Fence, Format, Image, ImageAspectFlags,
-        ImageSubresourceRange, ImageTiling, ImageUsageFlags, ImageView, ImageViewCreateInfo,
-        ImageViewType, MemoryPropertyFlags, PhysicalDevice, PresentInfoKHR, PresentModeKHR,
-        Semaphore, SharingMode, SurfaceCapabilitiesKHR, SurfaceFormatKHR, SwapchainCreateInfoKHR,
-        SwapchainKHR,
+        ImageSubresourceRange, ImageUsageFlags, ImageView, ImageViewCreateInfo, ImageViewType,
+        PhysicalDevice, PresentInfoKHR, PresentModeKHR, Semaphore, SharingMode,
+        SurfaceCapabilitiesKHR, SurfaceFormatKHR, SwapchainCreateInfoKHR, SwapchainKHR,
     },
 };
 
 use crate::{
     core::debug::errors::EngineError,
-    error,
-    renderer::vulkan::{
-        vulkan_types::VulkanRendererBackend,
-        vulkan_utils::{self, image::ImageCreatorParameters},
+    debug, error,
+    renderer::{
+        renderer_types::{DisplayOutput, OutputColorSpace, VsyncMode},
+        vulkan::vulkan_types::VulkanRendererBackend,
     },
     warn,
 };
 
-use super::framebuffer::Framebuffer;
+/// Format of the offscreen color targets, the world is drawn in linear hdr before the post processing
+pub const VULKAN_HDR_ATTACHMENT_FORMAT: Format = Format::R16G16B16A16_SFLOAT;
+
+/// Surface formats of the sdr output by preference
+const VULKAN_SDR_SURFACE_FORMATS: &[SurfaceFormatKHR] = &[
+    SurfaceFormatKHR {
+        format: Format::B8G8R8A8_UNORM,
+        color_space: ColorSpaceKHR::SRGB_NONLINEAR,
+    },
+    SurfaceFormatKHR {
+        format: Format::R8G8B8A8_UNORM,
+        color_space: ColorSpaceKHR::SRGB_NONLINEAR,
+    },
+];
+
+/// Surface formats of the hdr output by preference, hdr10 before scrgb
+const VULKAN_HDR_SURFACE_FORMATS: &[SurfaceFormatKHR] = &[
+    SurfaceFormatKHR {
+        format: Format::A2B10G10R10_UNORM_PACK32,
+        color_space: ColorSpaceKHR::HDR10_ST2084_EXT,
+    },
+    SurfaceFormatKHR {
+        format: Format::A2R10G10B10_UNORM_PACK32,
+        color_space: ColorSpaceKHR::HDR10_ST2084_EXT,
+    },
+    SurfaceFormatKHR {
+        format: Format::R16G16B16A16_SFLOAT,
+        color_space: ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT,
+    },
+];
 
 #[derive(Default, Debug)]
 pub(crate) struct SwapchainSupportDetails {
@@ -43,9 +71,9 @@ pub(crate) struct Swapchain {
     pub max_frames_in_flight: u16,
     pub images: Vec<Image>,
     pub image_views: Vec<ImageView>,
-    pub depth_attachment: Option<vulkan_utils::image::Image>,
-    pub framebuffers: Vec<Framebuffer>,
     pub extent: Extent2D,
+    /// The images can be copied back for the screenshots, not every surface allows it
+    pub supports_readback: bool,
 }
 
 impl VulkanRendererBackend<'_> {
@@ -53,22 +81,39 @@ impl VulkanRendererBackend<'_> {
         &self,
         physical_device: &PhysicalDevice,
     ) -> Result<SwapchainSupportDetails, EngineError> {
-        let surface_capabilities = unsafe {
-            self.get_surface_loader()?
-                .get_physical_device_surface_capabilities(*physical_device, *(self.get_surface()?))
-                .unwrap()
+        let surface_loader = self.get_surface_loader()?;
+        let surface = *self.get_surface()?;
+        let surface_capabilities = match unsafe {
+            surface_loader.get_physical_device_surface_capabilities(*physical_device, surface)
+        } {
+            Ok(capabilities) => capabilities,
+            Err(err) => {
+                error!("Failed to query the vulkan surface capabilities: {:?}", err);
+                return Err(EngineError::VulkanFailed);
+            }
         };
 
-        let surface_format = unsafe {
-            self.get_surface_loader()?
-                .get_physical_device_surface_formats(*physical_device, *(self.get_surface()?))
-                .unwrap()
+        let surface_format = match unsafe {
+            surface_loader.get_physical_device_surface_formats(*physical_device, surface)
+        } {
+            Ok(formats) => formats,
+            Err(err) => {
+                error!("Failed to query the vulkan surface formats: {:?}", err);
+                return Err(EngineError::VulkanFailed);
+            }
         };
 
-        let surface_present_modes = unsafe {
-            self.get_surface_loader()?
-                .get_physical_device_surface_present_modes(*physical_device, *(self.get_surface()?))
-                .unwrap()
+        let surface_present_modes = match unsafe {
+            surface_loader.get_physical_device_surface_present_modes(*physical_device, surface)
+        } {
+            Ok(present_modes) => present_modes,
+            Err(err) => {
+                error!(
+                    "Failed to query the vulkan surface present modes: {:?}",
+                    err
+                );
+                return Err(EngineError::VulkanFailed);
+            }
         };
 
         Ok(SwapchainSupportDetails {
@@ -79,7 +124,7 @@ impl VulkanRendererBackend<'_> {
     }
 
     fn swapchain_create_max_frames_in_flight(&mut self, nb_frames: u16) -> Result<(), EngineError> {
-        let swapchain = self.context.swapchain.as_mut().unwrap();
+        let swapchain = self.get_swapchain_mut()?;
         swapchain.max_frames_in_flight = nb_frames;
         Ok(())
     }
@@ -90,39 +135,73 @@ impl VulkanRendererBackend<'_> {
         self.query_swapchain_support(self.get_physical_device()?)
     }
 
-    fn swapchain_select_format(
-        &mut self,
-        prefered_format: Format,
-        prefered_color_space: ColorSpaceKHR,
-    ) -> Result<(), EngineError> {
+    /// First supported format of the display output, the hdr outputs fall back to the sdr formats
+    /// The format of a recreated swapchain is kept while supported, the renderpasses are built for it
+    fn swapchain_select_format(&mut self) -> Result<(), EngineError> {
         let supported_formats = self.get_swapchain_support_details()?.formats.clone();
-        let mut selected_format: Option<SurfaceFormatKHR> = None;
-        'get_prefered_format_loop: for format in &supported_formats {
-            if format.format == prefered_format && format.color_space == prefered_color_space {
-                selected_format = Some(*format);
-                break 'get_prefered_format_loop;
-            }
+        let current_format = self.get_swapchain()?.surface_format;
+        if current_format.format != Format::UNDEFINED && supported_formats.contains(&current_format)
+        {
+            return Ok(());
+        }
+
+        let prefered_formats: Vec<SurfaceFormatKHR> = match self.display_output {
+            DisplayOutput::Sdr => VULKAN_SDR_SURFACE_FORMATS.to_vec(),
+            DisplayOutput::Hdr => [VULKAN_HDR_SURFACE_FORMATS, VULKAN_SDR_SURFACE_FORMATS].concat(),
+        };
+        let selected_format = prefered_formats
+            .iter()
+            .find(|format| supported_formats.contains(format))
+            .copied()
+            .unwrap_or(supported_formats[0]);
+
+        let output_color_space = match selected_format.color_space {
+            ColorSpaceKHR::HDR10_ST2084_EXT => OutputColorSpace::Hdr10,
+            ColorSpaceKHR::EXTENDED_SRGB_LINEAR_EXT => OutputColorSpace::ScRgb,
+            _ => OutputColorSpace::Srgb,
+        };
+        if self.display_output == DisplayOutput::Hdr && output_color_space == OutputColorSpace::Srgb
+        {
+            warn!("The surface doesn't support hdr output, presenting in srgb");
         }
-        let swapchain = self.context.swapchain.as_mut().unwrap();
-        match selected_format {
-            Some(format) => swapchain.surface_format = format,
-            None => swapchain.surface_format = supported_formats[0],
+        if current_format.format != Format::UNDEFINED {
+            warn!(
+                "The surface format {:?} is no longer supported, presenting with {:?}",
+                current_format, selected_format
+            );
         }
+        debug!(
+            "Vulkan swapchain format {:?} with the {:?} output",
+            selected_format, output_color_space
+        );
+        self.output_color_space = output_color_space;
+        self.get_swapchain_mut()?.surface_format = selected_format;
         Ok(())
     }
 
-    fn swapchain_select_present_mode(
-        &self,
-        default_mode: PresentModeKHR,
-        prefered_mode: PresentModeKHR,
-    ) -> Result<PresentModeKHR, EngineError> {
+    /// First supported mode of the vsync setting, fifo is always supported
+    fn swapchain_select_present_mode(&self) -> Result<PresentModeKHR, EngineError> {
+        let prefered_modes: &[PresentModeKHR] = match self.vsync_mode {
+            VsyncMode::On => &[],
+            VsyncMode::Mailbox => &[PresentModeKHR::MAILBOX],
+            VsyncMode::Off => &[PresentModeKHR::IMMEDIATE, PresentModeKHR::MAILBOX],
+        };
         let supported_present_modes = &self.get_swapchain_support_details()?.present_modes;
-        for present_mode in supported_present_modes {
-            if *present_mode == prefered_mode {
-                return Ok(prefered_mode);
-            }
+        let present_mode = prefered_modes
+            .iter()
+            .find(|mode| supported_present_modes.contains(mode))
+            .copied()
+            .unwrap_or(PresentModeKHR::FIFO);
+        if prefered_modes
+            .first()
+            .is_some_and(|mode| *mode != present_mode)
+        {
+            warn!(
+                "The vsync {:?} is not supported, presenting with {:?}",
+                self.vsync_mode, present_mode
+            );
         }
-        Ok(default_mode)
+        Ok(present_mode)
     }
 
     fn swpachain_create_extent(&self, width: u32, height: u32) -> Result<Extent2D, EngineError> {
@@ -148,7 +227,7 @@ impl VulkanRendererBackend<'_> {
     }
 
     fn swapchain_images_init(&mut self) -> Result<(), EngineError> {
-        let swapchain = self.context.swapchain.as_mut().unwrap();
+        let swapchain = self.get_swapchain_mut()?;
         swapchain.images = unsafe {
             let swapchain_device = &swapchain.device;
             match swapchain_device.get_swapchain_images(swapchain.handler) {
@@ -164,7 +243,7 @@ impl VulkanRendererBackend<'_> {
     }
 
     fn swapchain_image_views_init(&mut self) -> Result<(), EngineError> {
-        let swapchain = self.context.swapchain.as_ref().unwrap();
+        let swapchain = self.get_swapchain()?;
         let mut new_image_views = Vec::new();
         for image in &swapchain.images {
             let subresource_range = ImageSubresourceRange::default()
@@ -197,65 +276,49 @@ impl VulkanRendererBackend<'_> {
         }
 
         {
-            let swapchain = self.context.swapchain.as_mut().unwrap();
+            let swapchain = self.get_swapchain_mut()?;
             swapchain.image_views = new_image_views;
         }
 
         Ok(())
     }
 
-    fn swapchain_create_depth_images(&mut self, extent: Extent2D) -> Result<(), EngineError> {
-        // Create depth image and its view.
-        let depth_image_creation_parameters = ImageCreatorParameters::default()
-            .height(extent.height)
-            .width(extent.width)
-            .image_format(self.get_physical_device_info()?.depth_format.unwrap())
-            .image_tiling(ImageTiling::OPTIMAL)
-            .image_usage_flags(ImageUsageFlags::DEPTH_STENCIL_ATTACHMENT)
-            .memory_flags(MemoryPropertyFlags::DEVICE_LOCAL)
-            .should_create_view(true)
-            .image_view_aspect_flags(ImageAspectFlags::DEPTH);
-        let depth_image = match self.create_image(depth_image_creation_parameters) {
-            Ok(depth_image) => depth_image,
-            Err(err) => {
-                error!("Failed to create the vulkan depth image: {:?}", err);
-                return Err(EngineError::VulkanFailed);
-            }
-        };
-        let swapchain = self.context.swapchain.as_mut().unwrap();
-        swapchain.depth_attachment = Some(depth_image);
-
-        Ok(())
-    }
-
     fn swapchain_create_base(&mut self, width: u32, height: u32) -> Result<(), EngineError> {
         // for triple buffering, so at most writting to 2 frames at a time
         self.swapchain_create_max_frames_in_flight(2)?;
         // Choose a swap surface format.
-        self.swapchain_select_format(Format::B8G8R8A8_UNORM, ColorSpaceKHR::SRGB_NONLINEAR)?;
+        self.swapchain_select_format()?;
         let image_format = self.get_swapchain()?.surface_format;
         // Choose a present mode
-        let present_mode =
-            self.swapchain_select_present_mode(PresentModeKHR::FIFO, PresentModeKHR::MAILBOX)?;
+        let present_mode = self.swapchain_select_present_mode()?;
         // Requery swapchain support
         {
             let physical_device = *self.get_physical_device()?;
             let new_swapchain_support = self.query_swapchain_support(&physical_device)?;
-            let physical_device_info = self.context.physical_device_info.as_mut().unwrap();
+            let physical_device_info = self.get_physical_device_info_mut()?;
         }
         // Create extent
         let extent = self.swpachain_create_extent(width, height)?;
-        self.context.swapchain.as_mut().unwrap().extent = extent;
+        self.get_swapchain_mut()?.extent = extent;
         // Create image count
         let image_count = self.swapchain_create_image_count()?;
 
+        // get the transform
+        let capabilities = self.get_swapchain_support_details()?.capabilities;
+        let pre_transform = capabilities.current_transform;
+        // Needed to copy the presented images for the screenshots
+        let supports_readback = capabilities
+            .supported_usage_flags
+            .contains(ImageUsageFlags::TRANSFER_SRC);
+        let image_usage = if supports_readback {
+            ImageUsageFlags::COLOR_ATTACHMENT | ImageUsageFlags::TRANSFER_SRC
+        } else {
+            ImageUsageFlags::COLOR_ATTACHMENT
+        };
+        self.get_swapchain_mut()?.supports_readback = supports_readback;
+
         // get the surface
         let surface = self.get_surface()?;
-        // get the transform
-        let pre_transform = self
-            .get_swapchain_support_details()?
-            .capabilities
-            .current_transform;
 
         let swapchain_create_info = SwapchainCreateInfoKHR::default()
             .surface(*surface)
@@ -264,7 +327,7 @@ impl VulkanRendererBackend<'_> {
             .image_format(image_format.format)
             .image_color_space(image_format.color_space)
             .image_array_layers(1)
-            .image_usage(ImageUsageFlags::COLOR_ATTACHMENT)
+            .image_usage(image_usage)
             .pre_transform(pre_transform)
             .composite_alpha(CompositeAlphaFlagsKHR::OPAQUE)
             .present_mode(present_mode)
@@ -272,8 +335,8 @@ impl VulkanRendererBackend<'_> {
 
         // Setup the queue family indices
         let queues = self.get_queues()?;
-        let graphics_queue_index = self.get_queues()?.graphics_family_index.unwrap() as u32;
-        let present_queue_index = self.get_queues()?.present_family_index.unwrap() as u32;
+        let graphics_queue_index = queues.get_graphics_family_index()?;
+        let present_queue_index = queues.get_present_family_index()?;
         let queue_family_indices = [graphics_queue_index, present_queue_index];
         let swapchain_create_info = if graphics_queue_index != present_queue_index {
             swapchain_create_info
@@ -294,26 +357,23 @@ impl VulkanRendererBackend<'_> {
             }
         };
 
-        self.context.swapchain.as_mut().unwrap().handler = swapchain;
+        self.get_swapchain_mut()?.handler = swapchain;
         // Create images
-        self.context.image_index = 0;
+        self.context.frame.image_index = 0;
         self.swapchain_images_init()?;
         self.swapchain_image_views_init()?;
-        // Depth resources
+        // The attachments of the passes are created by the render graph
         self.device_detect_depth_format()?;
-        self.swapchain_create_depth_images(extent)?;
         Ok(())
     }
 
     fn swapchain_destroy_base(&mut self) -> Result<(), EngineError> {
-        // Destoy depth attachment
-        let depth_image = &self.get_swapchain()?.depth_attachment;
-        if let Some(depth_image) = depth_image {
-            self.destroy_image(depth_image)?;
-        }
+        // The attachments of the render graph have the size of the swapchain
+        self.render_graph_destroy_images()?;
 
         // Only destroy the views, not the images, since those are owned by the swapchain
         for image_view in &self.get_swapchain()?.image_views {
+            self.framebuffer_cache_evict_attachment(*image_view)?;
             let device = self.get_device()?;
             unsafe {
                 device.destroy_image_view(*image_view, self.get_allocator()?);
@@ -365,25 +425,33 @@ impl VulkanRendererBackend<'_> {
         self.sync_structures_shutdown()?;
         self.sync_structures_init()?;
 
-        // cleanup framebuffers
-        self.swapchain_framebuffers_shutdown()?;
-        self.swapchain_framebuffers_init()?;
+        // The framebuffers of the old images were destroyed with them, the new ones are created when first used
+        self.resource_epoch_advance()
+    }
 
+    /// Invalidate the state depending on the swapchain or the renderpass, the gpu must be idle
+    /// The framebuffers are all destroyed and the descriptors are written again when next used
+    pub(crate) fn resource_epoch_advance(&mut self) -> Result<(), EngineError> {
+        self.framebuffer_cache_shutdown()?;
+        self.context.frame.resource_epoch += 1;
+        debug!(
+            "Vulkan resource epoch advanced to {}",
+            self.context.frame.resource_epoch
+        );
         Ok(())
     }
 
     pub fn swapchain_init(&mut self) -> Result<(), EngineError> {
         let swapchain_device = swapchain::Device::new(self.get_instance()?, self.get_device()?);
-        self.context.swapchain = Some(Swapchain {
+        self.context.frame.swapchain = Some(Swapchain {
             device: swapchain_device,
             handler: SwapchainKHR::default(),
             surface_format: SurfaceFormatKHR::default(),
             max_frames_in_flight: 0,
             images: Vec::new(),
             image_views: Vec::new(),
-            depth_attachment: None,
-            framebuffers: Vec::new(),
             extent: Extent2D::default(),
+            supports_readback: false,
         });
 
         self.swapchain_create(self.framebuffer_width, self.framebuffer_height)?;
@@ -393,7 +461,7 @@ impl VulkanRendererBackend<'_> {
     pub fn swapchain_shutdown(&mut self) -> Result<(), EngineError> {
         self.device_wait_idle()?;
         self.swapchain_destroy_base()?;
-        self.context.swapchain = None;
+        self.context.frame.swapchain = None;
         Ok(())
     }
 
@@ -450,12 +518,9 @@ impl VulkanRendererBackend<'_> {
             .swapchains(&swapchains)
             .image_indices(&image_indices);
 
-        let queues = self.get_queues()?;
+        let present_queue = self.get_queues()?.get_present_queue()?;
         unsafe {
-            match swapchain
-                .device
-                .queue_present(queues.present_queue.unwrap(), &present_info)
-            {
+            match swapchain.device.queue_present(present_queue, &present_info) {
                 Ok(is_suboptimal) => {
                     if is_suboptimal {
                         warn!("Found suboptimal swapchain when presenting swapchain: swapchain recreation...");
@@ -466,6 +531,9 @@ impl VulkanRendererBackend<'_> {
                     if err == ash::vk::Result::ERROR_OUT_OF_DATE_KHR {
                         warn!("Found out of date swapchain when presenting swapchain: swapchain recreation...");
                         return Ok(None);
+                    } else if err == ash::vk::Result::ERROR_DEVICE_LOST {
+                        error!("Lost the vulkan device when presenting the swapchain image");
+                        return Err(EngineError::DeviceLost);
                     } else {
                         error!("Failed to present the vulkan swapchain image: {:?}", err);
                         return Err(EngineError::VulkanFailed);
@@ -474,13 +542,23 @@ impl VulkanRendererBackend<'_> {
             }
         }
         // Increment (and loop) the index
-        self.context.current_frame =
-            (self.context.current_frame + 1) % self.get_swapchain()?.max_frames_in_flight;
+        self.context.frame.current_frame =
+            (self.context.frame.current_frame + 1) % self.get_swapchain()?.max_frames_in_flight;
         Ok(Some(()))
     }
 
     pub fn get_swapchain(&self) -> Result<&Swapchain, EngineError> {
-        match &self.context.swapchain {
+        match &self.context.frame.swapchain {
+            Some(swapchain) => Ok(swapchain),
+            None => {
+                error!("Can't access the vulkan swapchain");
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
+
+    pub fn get_swapchain_mut(&mut self) -> Result<&mut Swapchain, EngineError> {
+        match &mut self.context.frame.swapchain {
             Some(swapchain) => Ok(swapchain),
             None => {
                 error!("Can't access the vulkan swapchain");
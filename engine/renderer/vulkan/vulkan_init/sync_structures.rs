@@ -14,7 +14,17 @@ pub(crate) struct SyncStructure {
 
 impl VulkanRendererBackend<'_> {
     pub fn get_sync_structures(&self) -> Result<&SyncStructure, EngineError> {
-        match &self.context.sync_structures {
+        match &self.context.frame.sync_structures {
+            Some(sync_structures) => Ok(sync_structures),
+            None => {
+                error!("Can't access the vulkan sync structures");
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
+
+    pub fn get_sync_structures_mut(&mut self) -> Result<&mut SyncStructure, EngineError> {
+        match &mut self.context.frame.sync_structures {
             Some(sync_structures) => Ok(sync_structures),
             None => {
                 error!("Can't access the vulkan sync structures");
@@ -39,7 +49,7 @@ impl VulkanRendererBackend<'_> {
             in_flight_fences.push(Fence::create(device, allocator, true)?);
         }
 
-        self.context.sync_structures = Some(SyncStructure {
+        self.context.frame.sync_structures = Some(SyncStructure {
             image_available_semaphores,
             queue_complete_semaphores,
             in_flight_fences,
@@ -58,18 +68,13 @@ impl VulkanRendererBackend<'_> {
         }
 
         // destroy fences
-        let in_flight_fences = &self
-            .context
-            .sync_structures
-            .as_ref()
-            .unwrap()
-            .in_flight_fences;
+        let in_flight_fences = &self.get_sync_structures()?.in_flight_fences;
         for fence in in_flight_fences {
             fence.destroy(self.get_device()?, self.get_allocator()?)?;
         }
 
         // empty vectors
-        let sync_structures = self.context.sync_structures.as_mut().unwrap();
+        let sync_structures = self.get_sync_structures_mut()?;
         sync_structures.image_available_semaphores.clear();
         sync_structures.queue_complete_semaphores.clear();
         sync_structures.in_flight_fences.clear();
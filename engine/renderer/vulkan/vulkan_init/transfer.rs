@@ -0,0 +1,436 @@
+use std::{cell::RefCell, collections::VecDeque, ffi::c_void};
+
+use ash::vk::{
+    BufferUsageFlags, CommandPool, CommandPoolCreateFlags, CommandPoolCreateInfo,
+    MemoryPropertyFlags, Queue, Semaphore, SubmitInfo,
+};
+
+use crate::{
+    core::debug::errors::EngineError,
+    error,
+    renderer::vulkan::{
+        vulkan_init::{command_buffer::CommandBuffer, queue_ownership::OwnershipTransfer},
+        vulkan_types::VulkanRendererBackend,
+        vulkan_utils::{
+            buffer::{Buffer, BufferCreatorParameters},
+            fence::Fence,
+            per_frame_resource::PerFrameResource,
+        },
+    },
+};
+
+/// Size of the host visible buffer the uploads are staged in
+pub const TRANSFER_STAGING_RING_SIZE: usize = 16 * 1024 * 1024;
+
+/// Uploads recorded together and submitted at once on the transfer queue
+struct TransferBatch {
+    command_buffer: CommandBuffer,
+    /// Signaled once the copies are done, their staged data can then be overwritten
+    fence: Fence,
+    /// End of the batch data in the staging ring
+    ring_end: u64,
+    /// Staging buffers of the uploads too big for the ring
+    temporary_buffers: Vec<Buffer>,
+}
+
+/// Host visible buffer reused as a ring by the uploads
+/// The positions only grow, the staged data lives in [tail, head) modulo the size of the buffer
+struct StagingRing {
+    buffer: Buffer,
+    mapped_pointer: *mut c_void,
+    head: u64,
+    tail: u64,
+}
+
+// Safety: the mapped memory is not tied to a thread, the ring is only used through the backend
+unsafe impl Send for StagingRing {}
+
+impl StagingRing {
+    fn get_size(&self) -> u64 {
+        self.buffer.total_size as u64
+    }
+
+    /// Offset of a free range in the buffer, None until older batches are done
+    fn reserve(&mut self, size: u64, alignment: u64) -> Option<u64> {
+        let ring_size = self.get_size();
+        let position = self.head % ring_size;
+        let lap_start = self.head - position;
+        let aligned_position = position.next_multiple_of(alignment);
+        // A range never wraps around the end of the buffer
+        let start = if aligned_position + size <= ring_size {
+            lap_start + aligned_position
+        } else {
+            lap_start + ring_size
+        };
+        let end = start + size;
+        if end - self.tail > ring_size {
+            return None;
+        }
+        self.head = end;
+        Some(start % ring_size)
+    }
+}
+
+/// Uploads done on the dedicated transfer queue without waiting for the device
+/// The next frame submission waits on the semaphores of the submitted batches and acquires the written resources
+pub(crate) struct TransferContext {
+    command_pool: CommandPool,
+    queue: Queue,
+    staging_ring: StagingRing,
+    /// Batch receiving the uploads until the next submission
+    recording_batch: Option<TransferBatch>,
+    /// Oldest first, they are done in order
+    submitted_batches: VecDeque<TransferBatch>,
+    free_batches: Vec<TransferBatch>,
+    /// Signaled by the submitted batches and not waited yet
+    pending_semaphores: Vec<Semaphore>,
+    /// Released by the recorded or submitted batches and not acquired by the graphics queue yet
+    pending_acquires: Vec<OwnershipTransfer>,
+    /// A semaphore can only be signaled again once the frame waiting on it is done
+    frame_semaphores: PerFrameResource<Vec<Semaphore>>,
+    free_semaphores: Vec<Semaphore>,
+}
+
+impl VulkanRendererBackend<'_> {
+    pub fn transfer_init(&mut self) -> Result<(), EngineError> {
+        let queues = self.get_queues()?;
+        let family_index = queues.get_transfer_family_index()?;
+        let queue = queues.get_transfer_queue()?;
+
+        let pool_create_info = CommandPoolCreateInfo::default()
+            .queue_family_index(family_index)
+            .flags(
+                CommandPoolCreateFlags::RESET_COMMAND_BUFFER | CommandPoolCreateFlags::TRANSIENT,
+            );
+        let command_pool = unsafe {
+            match self
+                .get_device()?
+                .create_command_pool(&pool_create_info, self.get_allocator()?)
+            {
+                Ok(pool) => pool,
+                Err(err) => {
+                    error!(
+                        "Failed to create the vulkan transfer command pool: {:?}",
+                        err
+                    );
+                    return Err(EngineError::InitializationFailed);
+                }
+            }
+        };
+
+        let staging_buffer_creator_parameters = BufferCreatorParameters::default()
+            .size(TRANSFER_STAGING_RING_SIZE)
+            .should_be_bind(true)
+            .buffer_usage_flags(BufferUsageFlags::TRANSFER_SRC)
+            .memory_flags(MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT);
+        let staging_buffer = match self.create_buffer(staging_buffer_creator_parameters) {
+            Ok(buffer) => buffer,
+            Err(err) => {
+                error!("Failed to create the vulkan staging ring buffer: {:?}", err);
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        // The ring stays mapped for the lifetime of the backend
+        let mapped_pointer =
+            match self.memory_map(&staging_buffer.memory, 0, TRANSFER_STAGING_RING_SIZE as u64) {
+                Ok(pointer) => pointer,
+                Err(err) => {
+                    error!("Failed to map the vulkan staging ring buffer: {:?}", err);
+                    return Err(EngineError::InitializationFailed);
+                }
+            };
+
+        self.context.core.transfer = Some(RefCell::new(TransferContext {
+            command_pool,
+            queue,
+            staging_ring: StagingRing {
+                buffer: staging_buffer,
+                mapped_pointer,
+                head: 0,
+                tail: 0,
+            },
+            recording_batch: None,
+            submitted_batches: VecDeque::new(),
+            free_batches: Vec::new(),
+            pending_semaphores: Vec::new(),
+            pending_acquires: Vec::new(),
+            frame_semaphores: PerFrameResource::from_fn(|_| Vec::new()),
+            free_semaphores: Vec::new(),
+        }));
+        Ok(())
+    }
+
+    /// The device is idle when shutting down so every batch is done
+    pub fn transfer_shutdown(&mut self) -> Result<(), EngineError> {
+        let transfer = match self.context.core.transfer.take() {
+            Some(transfer) => transfer.into_inner(),
+            None => return Ok(()),
+        };
+        let device = self.get_device()?;
+        let allocator = self.get_allocator()?;
+
+        let batches = transfer
+            .recording_batch
+            .into_iter()
+            .chain(transfer.submitted_batches)
+            .chain(transfer.free_batches);
+        for batch in batches {
+            batch.fence.destroy(device, allocator)?;
+            for buffer in batch.temporary_buffers.iter() {
+                self.destroy_buffer(buffer)?;
+            }
+        }
+
+        let semaphores = transfer
+            .pending_semaphores
+            .iter()
+            .chain(transfer.frame_semaphores.iter().flatten())
+            .chain(transfer.free_semaphores.iter());
+        for semaphore in semaphores {
+            self.destroy_semaphore(semaphore)?;
+        }
+
+        self.memory_unmap(&transfer.staging_ring.buffer.memory)?;
+        self.destroy_buffer(&transfer.staging_ring.buffer)?;
+        // Destroying the pool frees the command buffers of the batches
+        unsafe { device.destroy_command_pool(transfer.command_pool, allocator) };
+        Ok(())
+    }
+
+    fn get_transfer(&self) -> Result<&RefCell<TransferContext>, EngineError> {
+        match &self.context.core.transfer {
+            Some(transfer) => Ok(transfer),
+            None => {
+                error!("Can't access the vulkan transfer context");
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
+
+    /// Give back the staging space and the command buffers of the batches done on the gpu
+    fn transfer_retire_batches(
+        &self,
+        transfer: &mut TransferContext,
+        should_wait: bool,
+    ) -> Result<(), EngineError> {
+        let device = self.get_device()?;
+        while let Some(batch) = transfer.submitted_batches.front() {
+            if should_wait {
+                batch.fence.wait(device, u64::MAX)?;
+            } else {
+                match unsafe { device.get_fence_status(*batch.fence.handler.as_ref()) } {
+                    Ok(true) => (),
+                    Ok(false) => break,
+                    Err(ash::vk::Result::ERROR_DEVICE_LOST) => {
+                        error!("Lost the vulkan device while checking a transfer batch");
+                        return Err(EngineError::DeviceLost);
+                    }
+                    Err(err) => {
+                        error!("Failed to check a vulkan transfer batch: {:?}", err);
+                        return Err(EngineError::VulkanFailed);
+                    }
+                }
+            }
+            let mut batch = match transfer.submitted_batches.pop_front() {
+                Some(batch) => batch,
+                None => break,
+            };
+            transfer.staging_ring.tail = batch.ring_end;
+            for buffer in batch.temporary_buffers.drain(..) {
+                self.destroy_buffer(&buffer)?;
+            }
+            transfer.free_batches.push(batch);
+        }
+        Ok(())
+    }
+
+    /// Reuse a retired batch if any
+    fn transfer_begin_batch(
+        &self,
+        transfer: &mut TransferContext,
+    ) -> Result<TransferBatch, EngineError> {
+        let device = self.get_device()?;
+        let batch = match transfer.free_batches.pop() {
+            Some(batch) => {
+                batch.fence.reset(device)?;
+                batch.command_buffer.reset(device)?;
+                batch
+            }
+            None => TransferBatch {
+                command_buffer: CommandBuffer::allocate(&transfer.command_pool, true, device)?,
+                fence: Fence::create(device, self.get_allocator()?, false)?,
+                ring_end: 0,
+                temporary_buffers: Vec::new(),
+            },
+        };
+        let is_single_use = true;
+        let is_renderpass_continue = false;
+        let is_simultaneous_use = false;
+        batch.command_buffer.begin(
+            device,
+            is_single_use,
+            is_renderpass_continue,
+            is_simultaneous_use,
+        )?;
+        Ok(batch)
+    }
+
+    fn transfer_submit_recording_batch(
+        &self,
+        transfer: &mut TransferContext,
+    ) -> Result<(), EngineError> {
+        let batch = match transfer.recording_batch.take() {
+            Some(batch) => batch,
+            None => return Ok(()),
+        };
+        let device = self.get_device()?;
+        batch.command_buffer.end(device)?;
+
+        let semaphore = match transfer.free_semaphores.pop() {
+            Some(semaphore) => semaphore,
+            None => self.create_default_semaphore()?,
+        };
+        let command_buffers = [*batch.command_buffer.handler.as_ref()];
+        let signal_semaphores = [semaphore];
+        let submit_info = [SubmitInfo::default()
+            .command_buffers(&command_buffers)
+            .signal_semaphores(&signal_semaphores)];
+        if let Err(err) = unsafe {
+            device.queue_submit(transfer.queue, &submit_info, *batch.fence.handler.as_ref())
+        } {
+            error!("Failed to submit a vulkan transfer batch: {:?}", err);
+            transfer.free_semaphores.push(semaphore);
+            transfer.free_batches.push(batch);
+            if err == ash::vk::Result::ERROR_DEVICE_LOST {
+                return Err(EngineError::DeviceLost);
+            }
+            return Err(EngineError::VulkanFailed);
+        }
+        transfer.pending_semaphores.push(semaphore);
+        transfer.submitted_batches.push_back(batch);
+        Ok(())
+    }
+
+    /// Stage the data and let the recorder read it from the transfer command buffer
+    /// The recorder gets the command buffer, the staging buffer and the offset of the data in it,
+    /// it returns the handover of the written resource to the graphics queue
+    pub(crate) fn transfer_upload<F>(
+        &self,
+        data: *mut c_void,
+        size: usize,
+        alignment: u64,
+        record: F,
+    ) -> Result<(), EngineError>
+    where
+        F: FnOnce(&CommandBuffer, &Buffer, u64) -> Result<OwnershipTransfer, EngineError>,
+    {
+        let mut transfer = self.get_transfer()?.borrow_mut();
+        let transfer = &mut *transfer;
+        self.transfer_retire_batches(transfer, false)?;
+
+        let mut temporary_buffer = None;
+        let offset = if size as u64 > transfer.staging_ring.get_size() {
+            // Too big for the ring, it gets its own buffer until the batch is done
+            let buffer_creator_parameters = BufferCreatorParameters::default()
+                .size(size)
+                .should_be_bind(true)
+                .buffer_usage_flags(BufferUsageFlags::TRANSFER_SRC)
+                .memory_flags(
+                    MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+                );
+            let buffer = self.create_buffer(buffer_creator_parameters)?;
+            let load_result = self.load_data_into_buffer(
+                &buffer,
+                0,
+                size,
+                ash::vk::MemoryMapFlags::empty(),
+                data,
+            );
+            if let Err(err) = load_result {
+                self.destroy_buffer(&buffer)?;
+                return Err(err);
+            }
+            temporary_buffer = Some(buffer);
+            0
+        } else {
+            let offset = match transfer.staging_ring.reserve(size as u64, alignment) {
+                Some(offset) => offset,
+                None => {
+                    // The ring is full, only this upload waits for the older ones
+                    self.transfer_submit_recording_batch(transfer)?;
+                    self.transfer_retire_batches(transfer, true)?;
+                    match transfer.staging_ring.reserve(size as u64, alignment) {
+                        Some(offset) => offset,
+                        None => {
+                            error!(
+                                "Can't find {} bytes in the vulkan staging ring buffer",
+                                size
+                            );
+                            return Err(EngineError::InvalidValue);
+                        }
+                    }
+                }
+            };
+            unsafe {
+                (transfer.staging_ring.mapped_pointer.add(offset as usize) as *mut u8)
+                    .copy_from_nonoverlapping(data as *const u8, size);
+            }
+            offset
+        };
+
+        let batch = match transfer.recording_batch.take() {
+            Some(batch) => batch,
+            None => self.transfer_begin_batch(transfer)?,
+        };
+        let batch = transfer.recording_batch.insert(batch);
+        batch.ring_end = transfer.staging_ring.head;
+        let staging_buffer = temporary_buffer
+            .as_ref()
+            .unwrap_or(&transfer.staging_ring.buffer);
+        let result = record(&batch.command_buffer, staging_buffer, offset).and_then(|transfer| {
+            self.record_ownership_release(&batch.command_buffer, &[transfer])?;
+            Ok(transfer)
+        });
+        if let Some(buffer) = temporary_buffer {
+            batch.temporary_buffers.push(buffer);
+        }
+        transfer.pending_acquires.push(result?);
+        Ok(())
+    }
+
+    /// Submit the recorded uploads, the frame submission has to wait on the returned semaphores
+    /// and acquire the returned handovers before reading the uploaded resources
+    pub(crate) fn transfer_take_uploads(
+        &self,
+        frame_index: usize,
+    ) -> Result<(Vec<Semaphore>, Vec<OwnershipTransfer>), EngineError> {
+        let mut transfer = self.get_transfer()?.borrow_mut();
+        self.transfer_submit_recording_batch(&mut transfer)?;
+        let semaphores = std::mem::take(&mut transfer.pending_semaphores);
+        transfer
+            .frame_semaphores
+            .get_mut(frame_index)
+            .extend(semaphores.iter().copied());
+        Ok((semaphores, std::mem::take(&mut transfer.pending_acquires)))
+    }
+
+    /// The frame is done on the gpu, the semaphores it waited on can be signaled again
+    pub(crate) fn transfer_frame_completed(&self, frame_index: usize) -> Result<(), EngineError> {
+        let mut transfer = self.get_transfer()?.borrow_mut();
+        let transfer = &mut *transfer;
+        let semaphores = std::mem::take(transfer.frame_semaphores.get_mut(frame_index));
+        transfer.free_semaphores.extend(semaphores);
+        self.transfer_retire_batches(transfer, false)
+    }
+
+    /// Block until every upload is done and owned by the graphics queue,
+    /// for the work on the other queues not waiting on the semaphores
+    pub(crate) fn transfer_wait_idle(&self) -> Result<(), EngineError> {
+        let mut transfer = self.get_transfer()?.borrow_mut();
+        self.transfer_submit_recording_batch(&mut transfer)?;
+        self.transfer_retire_batches(&mut transfer, true)?;
+        // The next frame still waits on the semaphores but has nothing left to acquire
+        let acquires = std::mem::take(&mut transfer.pending_acquires);
+        self.queue_ownership_acquire_now(&acquires)
+    }
+}
@@ -0,0 +1,527 @@
+use std::cmp::{max, min};
+
+use ash::vk::{
+    self, CompositeAlphaFlagsKHR, Extent2D, ImageAspectFlags, ImageSubresourceRange,
+    ImageUsageFlags, ImageView, ImageViewCreateInfo, ImageViewType, PresentInfoKHR, PresentModeKHR,
+    Semaphore, SharingMode, SurfaceFormatKHR, SurfaceKHR, SwapchainCreateInfoKHR, SwapchainKHR,
+};
+
+use crate::{
+    core::{application::windows::WindowHandle, debug::errors::EngineError},
+    error,
+    platforms::platform::Platform,
+    renderer::vulkan::vulkan_types::VulkanRendererBackend,
+    warn,
+};
+
+/// Surface and swapchain of an additional window, presented along with the main one
+pub(crate) struct WindowSwapchain {
+    pub surface: SurfaceKHR,
+    /// Null while the window has no area to present to, e.g. when it is minimized
+    pub handler: SwapchainKHR,
+    /// Format of the main swapchain when it was created, the present renderpass draws into both
+    pub surface_format: SurfaceFormatKHR,
+    pub images: Vec<vk::Image>,
+    pub image_views: Vec<ImageView>,
+    pub extent: Extent2D,
+    /// Signaled once the image of the frame is acquired, one per frame in flight
+    pub image_available_semaphores: Vec<Semaphore>,
+    /// Signaled once the frame drew into the window, waited on by its presentation
+    pub render_complete_semaphores: Vec<Semaphore>,
+    /// Image acquired by the frame being recorded, none if the frame doesn't draw into the window
+    pub image_index: Option<u32>,
+}
+
+impl VulkanRendererBackend<'_> {
+    pub(crate) fn window_swapchains_init(&mut self) -> Result<(), EngineError> {
+        self.context.frame.window_swapchains.clear();
+        Ok(())
+    }
+
+    /// Destroy the swapchains of the windows the application left open
+    pub(crate) fn window_swapchains_shutdown(&mut self) -> Result<(), EngineError> {
+        let windows: Vec<WindowHandle> = self
+            .context
+            .frame
+            .window_swapchains
+            .keys()
+            .copied()
+            .collect();
+        for window in windows {
+            self.window_swapchain_destroy(window)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn get_window_swapchain(
+        &self,
+        window: WindowHandle,
+    ) -> Result<&WindowSwapchain, EngineError> {
+        match self.context.frame.window_swapchains.get(&window) {
+            Some(window_swapchain) => Ok(window_swapchain),
+            None => {
+                error!("The window {:?} has no vulkan swapchain", window);
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    fn get_window_swapchain_mut(
+        &mut self,
+        window: WindowHandle,
+    ) -> Result<&mut WindowSwapchain, EngineError> {
+        match self.context.frame.window_swapchains.get_mut(&window) {
+            Some(window_swapchain) => Ok(window_swapchain),
+            None => {
+                error!("The window {:?} has no vulkan swapchain", window);
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    /// Create the surface and the swapchain of an additional window
+    pub(crate) fn window_swapchain_create(
+        &mut self,
+        window: WindowHandle,
+        platform: &dyn Platform,
+    ) -> Result<(), EngineError> {
+        if window.is_main() || self.context.frame.window_swapchains.contains_key(&window) {
+            error!("The window {:?} already has a vulkan swapchain", window);
+            return Err(EngineError::Duplicate);
+        }
+        let surface = match platform.get_window_vulkan_surface(window, &self.context.core) {
+            Ok(surface) => surface,
+            Err(err) => {
+                error!(
+                    "Failed to create the vulkan surface of the window {:?}: {:?}",
+                    window, err
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        let present_family_index = self.get_queues()?.get_present_family_index()?;
+        let is_supported = unsafe {
+            self.get_surface_loader()?
+                .get_physical_device_surface_support(
+                    *self.get_physical_device()?,
+                    present_family_index,
+                    surface,
+                )
+        };
+        if !matches!(is_supported, Ok(true)) {
+            error!(
+                "The present queue can't present to the window {:?}: {:?}",
+                window, is_supported
+            );
+            unsafe {
+                self.get_surface_loader()?
+                    .destroy_surface(surface, self.get_allocator()?)
+            };
+            return Err(EngineError::InitializationFailed);
+        }
+
+        let max_frames_in_flight = self.get_swapchain()?.max_frames_in_flight;
+        let mut image_available_semaphores = Vec::new();
+        let mut render_complete_semaphores = Vec::new();
+        for _ in 0..max_frames_in_flight {
+            image_available_semaphores.push(self.create_default_semaphore()?);
+            render_complete_semaphores.push(self.create_default_semaphore()?);
+        }
+        self.context.frame.window_swapchains.insert(
+            window,
+            WindowSwapchain {
+                surface,
+                handler: SwapchainKHR::null(),
+                surface_format: SurfaceFormatKHR::default(),
+                images: Vec::new(),
+                image_views: Vec::new(),
+                extent: Extent2D::default(),
+                image_available_semaphores,
+                render_complete_semaphores,
+                image_index: None,
+            },
+        );
+        if let Err(err) = self.window_swapchain_build(window) {
+            error!(
+                "Failed to create the vulkan swapchain of the window {:?}: {:?}",
+                window, err
+            );
+            self.window_swapchain_destroy(window)?;
+            return Err(EngineError::InitializationFailed);
+        }
+        Ok(())
+    }
+
+    /// Destroy the views of the swapchain images and their framebuffers
+    fn window_swapchain_destroy_images(&mut self, window: WindowHandle) -> Result<(), EngineError> {
+        let image_views = std::mem::take(&mut self.get_window_swapchain_mut(window)?.image_views);
+        for image_view in image_views {
+            self.framebuffer_cache_evict_attachment(image_view)?;
+            unsafe {
+                self.get_device()?
+                    .destroy_image_view(image_view, self.get_allocator()?)
+            };
+        }
+        self.get_window_swapchain_mut(window)?.images.clear();
+        Ok(())
+    }
+
+    /// (Re)create the swapchain at the size of the window, in the format of the main swapchain
+    /// The gpu must not use the previous images anymore
+    fn window_swapchain_build(&mut self, window: WindowHandle) -> Result<(), EngineError> {
+        self.window_swapchain_destroy_images(window)?;
+        let (surface, old_swapchain) = {
+            let window_swapchain = self.get_window_swapchain(window)?;
+            (window_swapchain.surface, window_swapchain.handler)
+        };
+        let surface_format = self.get_swapchain()?.surface_format;
+        let physical_device = *self.get_physical_device()?;
+        let surface_loader = self.get_surface_loader()?;
+        let (capabilities, formats, present_modes) = match unsafe {
+            surface_loader
+                .get_physical_device_surface_capabilities(physical_device, surface)
+                .and_then(|capabilities| {
+                    Ok((
+                        capabilities,
+                        surface_loader
+                            .get_physical_device_surface_formats(physical_device, surface)?,
+                        surface_loader
+                            .get_physical_device_surface_present_modes(physical_device, surface)?,
+                    ))
+                })
+        } {
+            Ok(support) => support,
+            Err(err) => {
+                error!(
+                    "Failed to query the vulkan surface of the window {:?}: {:?}",
+                    window, err
+                );
+                return Err(EngineError::VulkanFailed);
+            }
+        };
+        if !formats.contains(&surface_format) {
+            error!(
+                "The window {:?} doesn't support the format {:?} of the main window",
+                window, surface_format
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+
+        // The surface has the size of the window, else the framebuffer's is clamped to it
+        let extent = if capabilities.current_extent.width != u32::MAX {
+            capabilities.current_extent
+        } else {
+            Extent2D {
+                width: min(
+                    capabilities.max_image_extent.width,
+                    max(capabilities.min_image_extent.width, self.framebuffer_width),
+                ),
+                height: min(
+                    capabilities.max_image_extent.height,
+                    max(
+                        capabilities.min_image_extent.height,
+                        self.framebuffer_height,
+                    ),
+                ),
+            }
+        };
+        let swapchain_device = &self.get_swapchain()?.device;
+        if extent.width == 0 || extent.height == 0 {
+            // Nothing to present to until the window gets an area again
+            unsafe { swapchain_device.destroy_swapchain(old_swapchain, self.get_allocator()?) };
+            let window_swapchain = self.get_window_swapchain_mut(window)?;
+            window_swapchain.handler = SwapchainKHR::null();
+            window_swapchain.extent = extent;
+            return Ok(());
+        }
+
+        // The additional windows don't wait for the vertical blank when they can avoid it
+        let present_mode = if present_modes.contains(&PresentModeKHR::MAILBOX) {
+            PresentModeKHR::MAILBOX
+        } else {
+            PresentModeKHR::FIFO
+        };
+        let image_count = if capabilities.max_image_count > 0 {
+            min(
+                capabilities.min_image_count + 1,
+                capabilities.max_image_count,
+            )
+        } else {
+            capabilities.min_image_count + 1
+        };
+        let swapchain_create_info = SwapchainCreateInfoKHR::default()
+            .surface(surface)
+            .image_extent(extent)
+            .min_image_count(image_count)
+            .image_format(surface_format.format)
+            .image_color_space(surface_format.color_space)
+            .image_array_layers(1)
+            .image_usage(ImageUsageFlags::COLOR_ATTACHMENT)
+            .pre_transform(capabilities.current_transform)
+            .composite_alpha(CompositeAlphaFlagsKHR::OPAQUE)
+            .present_mode(present_mode)
+            .clipped(true)
+            .old_swapchain(old_swapchain);
+        let queues = self.get_queues()?;
+        let graphics_queue_index = queues.get_graphics_family_index()?;
+        let present_queue_index = queues.get_present_family_index()?;
+        let queue_family_indices = [graphics_queue_index, present_queue_index];
+        let swapchain_create_info = if graphics_queue_index != present_queue_index {
+            swapchain_create_info
+                .image_sharing_mode(SharingMode::CONCURRENT)
+                .queue_family_indices(&queue_family_indices)
+        } else {
+            swapchain_create_info.image_sharing_mode(SharingMode::EXCLUSIVE)
+        };
+
+        let allocator = self.get_allocator()?;
+        let handler = unsafe {
+            let handler = match swapchain_device.create_swapchain(&swapchain_create_info, allocator)
+            {
+                Ok(handler) => handler,
+                Err(err) => {
+                    error!(
+                        "Failed to create the vulkan swapchain of the window {:?}: {:?}",
+                        window, err
+                    );
+                    return Err(EngineError::VulkanFailed);
+                }
+            };
+            swapchain_device.destroy_swapchain(old_swapchain, allocator);
+            handler
+        };
+        let images = match unsafe { swapchain_device.get_swapchain_images(handler) } {
+            Ok(images) => images,
+            Err(err) => {
+                error!(
+                    "Failed to get the swapchain images of the window {:?}: {:?}",
+                    window, err
+                );
+                unsafe { swapchain_device.destroy_swapchain(handler, allocator) };
+                return Err(EngineError::VulkanFailed);
+            }
+        };
+        {
+            let window_swapchain = self.get_window_swapchain_mut(window)?;
+            window_swapchain.handler = handler;
+            window_swapchain.surface_format = surface_format;
+            window_swapchain.extent = extent;
+            window_swapchain.images = images.clone();
+        }
+
+        let mut image_views = Vec::new();
+        for image in images {
+            let subresource_range = ImageSubresourceRange::default()
+                .aspect_mask(ImageAspectFlags::COLOR)
+                .base_mip_level(0)
+                .level_count(1)
+                .base_array_layer(0)
+                .layer_count(1);
+            let image_view_info = ImageViewCreateInfo::default()
+                .image(image)
+                .format(surface_format.format)
+                .view_type(ImageViewType::TYPE_2D)
+                .subresource_range(subresource_range);
+            match unsafe {
+                self.get_device()?
+                    .create_image_view(&image_view_info, self.get_allocator()?)
+            } {
+                Ok(image_view) => image_views.push(image_view),
+                Err(err) => {
+                    error!(
+                        "Failed to create a swapchain image view of the window {:?}: {:?}",
+                        window, err
+                    );
+                    self.get_window_swapchain_mut(window)?.image_views = image_views;
+                    return Err(EngineError::VulkanFailed);
+                }
+            }
+        }
+        self.get_window_swapchain_mut(window)?.image_views = image_views;
+        Ok(())
+    }
+
+    /// Destroy the swapchain and the surface of the window, before the platform closes it
+    pub(crate) fn window_swapchain_destroy(
+        &mut self,
+        window: WindowHandle,
+    ) -> Result<(), EngineError> {
+        if let Err(err) = self.device_wait_idle() {
+            error!(
+                "Failed to wait idle when destroying the swapchain of the window {:?}: {:?}",
+                window, err
+            );
+            return Err(EngineError::ShutdownFailed);
+        }
+        self.window_swapchain_destroy_images(window)?;
+        let window_swapchain = match self.context.frame.window_swapchains.remove(&window) {
+            Some(window_swapchain) => window_swapchain,
+            None => return Ok(()),
+        };
+        for semaphore in window_swapchain
+            .image_available_semaphores
+            .iter()
+            .chain(&window_swapchain.render_complete_semaphores)
+        {
+            self.destroy_semaphore(semaphore)?;
+        }
+        let allocator = self.get_allocator()?;
+        unsafe {
+            self.get_swapchain()?
+                .device
+                .destroy_swapchain(window_swapchain.handler, allocator);
+            self.get_surface_loader()?
+                .destroy_surface(window_swapchain.surface, allocator);
+        }
+        Ok(())
+    }
+
+    /// Acquire the images of the windows drawn by the frame
+    /// A window whose swapchain is out of date is recreated and skipped for this frame
+    pub(crate) fn window_swapchains_acquire(
+        &mut self,
+        frame_index: usize,
+        windows: &[WindowHandle],
+    ) -> Result<(), EngineError> {
+        for window_swapchain in self.context.frame.window_swapchains.values_mut() {
+            window_swapchain.image_index = None;
+        }
+        let main_format = self.get_swapchain()?.surface_format;
+        for window in windows {
+            let window_swapchain = self.get_window_swapchain(*window)?;
+            // The present renderpass follows the format of the main swapchain
+            if window_swapchain.handler == SwapchainKHR::null()
+                || window_swapchain.surface_format != main_format
+            {
+                self.device_wait_idle()?;
+                self.window_swapchain_build(*window)?;
+            }
+            let window_swapchain = self.get_window_swapchain(*window)?;
+            if window_swapchain.handler == SwapchainKHR::null() {
+                continue;
+            }
+            let semaphore = window_swapchain.image_available_semaphores[frame_index];
+            let result = unsafe {
+                self.get_swapchain()?.device.acquire_next_image(
+                    window_swapchain.handler,
+                    u64::MAX,
+                    semaphore,
+                    vk::Fence::null(),
+                )
+            };
+            match result {
+                // A suboptimal image is still acquired, the swapchain is recreated once presented
+                Ok((image_index, _)) => {
+                    self.get_window_swapchain_mut(*window)?.image_index = Some(image_index)
+                }
+                Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                    warn!(
+                        "Found out of date swapchain of the window {:?}: swapchain recreation...",
+                        window
+                    );
+                    self.device_wait_idle()?;
+                    self.window_swapchain_build(*window)?;
+                }
+                Err(err) => {
+                    error!(
+                        "Failed to acquire the next swapchain image of the window {:?}: {:?}",
+                        window, err
+                    );
+                    return Err(EngineError::VulkanFailed);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Image acquired for the window by the frame being recorded, with its view and size
+    pub(crate) fn window_swapchain_get_acquired_image(
+        &self,
+        window: WindowHandle,
+    ) -> Option<(vk::Image, ImageView, Extent2D)> {
+        let window_swapchain = self.context.frame.window_swapchains.get(&window)?;
+        let image_index = window_swapchain.image_index? as usize;
+        Some((
+            window_swapchain.images[image_index],
+            window_swapchain.image_views[image_index],
+            window_swapchain.extent,
+        ))
+    }
+
+    /// Semaphores the submission of the frame waits on and signals for the acquired windows
+    pub(crate) fn window_swapchains_get_semaphores(
+        &self,
+        frame_index: usize,
+    ) -> (Vec<Semaphore>, Vec<Semaphore>) {
+        self.context
+            .frame
+            .window_swapchains
+            .values()
+            .filter(|window_swapchain| window_swapchain.image_index.is_some())
+            .map(|window_swapchain| {
+                (
+                    window_swapchain.image_available_semaphores[frame_index],
+                    window_swapchain.render_complete_semaphores[frame_index],
+                )
+            })
+            .unzip()
+    }
+
+    /// Present the acquired windows once the frame is submitted, before the main window
+    pub(crate) fn window_swapchains_present(
+        &mut self,
+        frame_index: usize,
+    ) -> Result<(), EngineError> {
+        let windows: Vec<WindowHandle> = self
+            .context
+            .frame
+            .window_swapchains
+            .iter()
+            .filter(|(_, window_swapchain)| window_swapchain.image_index.is_some())
+            .map(|(window, _)| *window)
+            .collect();
+        for window in windows {
+            let window_swapchain = self.get_window_swapchain_mut(window)?;
+            let image_index = window_swapchain.image_index.take().unwrap_or_default();
+            let wait_semaphores = [window_swapchain.render_complete_semaphores[frame_index]];
+            let swapchains = [window_swapchain.handler];
+            let image_indices = [image_index];
+            let present_info = PresentInfoKHR::default()
+                .wait_semaphores(&wait_semaphores)
+                .swapchains(&swapchains)
+                .image_indices(&image_indices);
+            let present_queue = self.get_queues()?.get_present_queue()?;
+            let result = unsafe {
+                self.get_swapchain()?
+                    .device
+                    .queue_present(present_queue, &present_info)
+            };
+            match result {
+                Ok(false) => (),
+                Ok(true) | Err(vk::Result::ERROR_OUT_OF_DATE_KHR) => {
+                    warn!(
+                        "Found out of date swapchain of the window {:?} when presenting: swapchain recreation...",
+                        window
+                    );
+                    self.device_wait_idle()?;
+                    self.window_swapchain_build(window)?;
+                }
+                Err(vk::Result::ERROR_DEVICE_LOST) => {
+                    error!(
+                        "Lost the vulkan device when presenting the window {:?}",
+                        window
+                    );
+                    return Err(EngineError::DeviceLost);
+                }
+                Err(err) => {
+                    error!(
+                        "Failed to present the swapchain image of the window {:?}: {:?}",
+                        window, err
+                    );
+                    return Err(EngineError::VulkanFailed);
+                }
+            }
+        }
+        Ok(())
+    }
+}
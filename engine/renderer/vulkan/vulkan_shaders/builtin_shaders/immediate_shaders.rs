@@ -0,0 +1,416 @@
+use ash::{
+    vk::{
+        BufferUsageFlags, CullModeFlags, Extent2D, MemoryMapFlags, MemoryPropertyFlags, Offset2D,
+        PipelineBindPoint, PipelineShaderStageCreateInfo, Rect2D, ShaderStageFlags,
+        VertexInputBindingDescription, VertexInputRate, Viewport,
+    },
+    Device,
+};
+
+use crate::{
+    core::debug::errors::EngineError,
+    error,
+    renderer::{
+        renderer_types::{ImmediateVertexData, RENDERER_MAX_IN_FLIGHT_FRAMES},
+        vulkan::{
+            vulkan_init::{
+                command_buffer::CommandBuffer, renderpass::VULKAN_RENDERPASS_UI_SUBPASS,
+            },
+            vulkan_shaders::{
+                builtin_shaders::OverlayConstants,
+                hot_reload::ShaderSource,
+                reflection::{
+                    reflection_get_push_constant_ranges, reflection_get_vertex_attributes,
+                },
+                shader::Shader,
+            },
+            vulkan_types::VulkanRendererBackend,
+            vulkan_utils::{
+                buffer::{Buffer, BufferCreatorParameters},
+                per_frame_resource::PerFrameResource,
+                pipeline::{Pipeline, PipelineCreateInfo},
+            },
+        },
+    },
+    resources::material::BlendMode,
+    warn,
+};
+
+/// Vertices of the immediate 2d shapes that can be drawn in a single frame
+pub const VULKAN_IMMEDIATE_SHADERS_MAX_VERTICES: usize = 65536;
+
+/// Colored 2d triangles drawn on top of the scene, without depth test nor culling
+pub(crate) struct ImmediateShaders {
+    pub vertex_stage: Shader,
+    pub fragment_stage: Shader,
+    pub pipeline: Pipeline,
+    /// Sources of the stages, used to hot reload them
+    pub vertex_source: ShaderSource,
+    pub fragment_source: ShaderSource,
+    /// Rewritten every frame from the cpu
+    pub vertex_buffers: PerFrameResource<Buffer>,
+    /// Vertices already drawn this frame, the next draws are appended after them
+    pub frame_vertex_count: usize,
+    /// Frame the vertex count belongs to
+    pub frame_number: u64,
+}
+
+impl ImmediateShaders {
+    fn create_pipeline_info<'a>(
+        backend: &'a VulkanRendererBackend<'a>,
+        vertex_shader: &'a Shader,
+        fragment_shader: &'a Shader,
+    ) -> Result<PipelineCreateInfo<'a>, EngineError> {
+        let viewports = vec![Viewport::default()
+            .x(0.)
+            .y(backend.framebuffer_height as f32)
+            .width(backend.framebuffer_width as f32)
+            .height(-(backend.framebuffer_height as f32))
+            .min_depth(0.0)
+            .max_depth(1.0)];
+        let scissors = vec![Rect2D::default()
+            .offset(Offset2D { x: 0, y: 0 })
+            .extent(Extent2D {
+                width: backend.framebuffer_width,
+                height: backend.framebuffer_height,
+            })];
+
+        // Input attributes, the locations of the vertex shader follow the fields of ImmediateVertexData
+        let vertex_input_binding_description = VertexInputBindingDescription::default()
+            .binding(0)
+            .stride(size_of::<ImmediateVertexData>() as u32)
+            .input_rate(VertexInputRate::VERTEX);
+
+        let shader_stages_info = vec![
+            PipelineShaderStageCreateInfo::default()
+                .stage(vertex_shader.stage_flag)
+                .module(vertex_shader.shader_module)
+                .name(vertex_shader.entry_point.as_c_str()),
+            PipelineShaderStageCreateInfo::default()
+                .stage(fragment_shader.stage_flag)
+                .module(fragment_shader.shader_module)
+                .name(fragment_shader.entry_point.as_c_str()),
+        ];
+
+        Ok(PipelineCreateInfo {
+            renderpass: backend.get_present_renderpass()?,
+            viewports,
+            scissors,
+            is_wireframe: false,
+            vertex_input_attributes_description: reflection_get_vertex_attributes(
+                vertex_shader,
+                vertex_input_binding_description.binding,
+            ),
+            vertex_input_bindings_description: vec![vertex_input_binding_description],
+            descriptor_set_layouts: Vec::new(),
+            push_constant_ranges: reflection_get_push_constant_ranges(&[
+                vertex_shader,
+                fragment_shader,
+            ]),
+            shader_stages_info,
+            stencil_state: None,
+            // The winding of the shapes depends on the direction they are drawn in
+            cull_mode: CullModeFlags::NONE,
+            // Drawn in the overlay, always on top of the scene
+            is_depth_test_enabled: false,
+            is_depth_write_enabled: false,
+            blend_mode: BlendMode::AlphaBlend,
+            subpass: VULKAN_RENDERPASS_UI_SUBPASS,
+        })
+    }
+
+    pub fn create(backend: &VulkanRendererBackend<'_>) -> Result<Self, EngineError> {
+        let device = backend.get_device()?;
+        let allocator = backend.get_allocator()?;
+
+        let vertex_source = ShaderSource::new(
+            "builtin/immediate.vert.slang",
+            None,
+            ShaderStageFlags::VERTEX,
+        );
+        let fragment_source = ShaderSource::new(
+            "builtin/immediate.frag.slang",
+            None,
+            ShaderStageFlags::FRAGMENT,
+        );
+        let vertex_stage = match Shader::create(
+            device,
+            allocator,
+            vertex_source.stage_flag,
+            vertex_source.path,
+            vertex_source.entry_point,
+        ) {
+            Ok(shader) => shader,
+            Err(err) => {
+                error!("Failed to create the immediate vertex shader: {:?}", err);
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        let fragment_stage = match Shader::create(
+            device,
+            allocator,
+            fragment_source.stage_flag,
+            fragment_source.path,
+            fragment_source.entry_point,
+        ) {
+            Ok(shader) => shader,
+            Err(err) => {
+                error!("Failed to create the immediate fragment shader: {:?}", err);
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+
+        let pipeline = match Self::create_pipeline_info(backend, &vertex_stage, &fragment_stage)
+            .and_then(|pipeline_info| Pipeline::create_graphics(device, allocator, pipeline_info))
+        {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                error!(
+                    "Failed to create the pipeline of the vulkan immediate shaders: {:?}",
+                    err
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+
+        let mut vertex_buffers = Vec::new();
+        for _ in 0..RENDERER_MAX_IN_FLIGHT_FRAMES {
+            let vertex_buffer_creator_params = BufferCreatorParameters::default()
+                .buffer_usage_flags(BufferUsageFlags::VERTEX_BUFFER)
+                .memory_flags(
+                    MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+                )
+                .should_be_bind(true)
+                .size(size_of::<ImmediateVertexData>() * VULKAN_IMMEDIATE_SHADERS_MAX_VERTICES);
+            match backend.create_buffer(vertex_buffer_creator_params) {
+                Ok(buffer) => vertex_buffers.push(buffer),
+                Err(err) => {
+                    error!("Failed to create the vertex buffer when creating vulkan immediate shaders: {:?}", err);
+                    return Err(EngineError::InitializationFailed);
+                }
+            }
+        }
+
+        Ok(ImmediateShaders {
+            vertex_stage,
+            fragment_stage,
+            pipeline,
+            vertex_source,
+            fragment_source,
+            vertex_buffers: PerFrameResource::from_vec(vertex_buffers)?,
+            frame_vertex_count: 0,
+            frame_number: 0,
+        })
+    }
+
+    /// Recompile the modified stages and swap the pipeline, the previous one is kept on failure
+    pub fn hot_reload(&mut self, backend: &VulkanRendererBackend<'_>) -> Result<(), EngineError> {
+        let has_vertex_changed = self.vertex_source.has_changed();
+        let has_fragment_changed = self.fragment_source.has_changed();
+        if !has_vertex_changed && !has_fragment_changed {
+            return Ok(());
+        }
+        let device = backend.get_device()?;
+        let allocator = backend.get_allocator()?;
+
+        let vertex_stage = if has_vertex_changed {
+            Some(self.vertex_source.rebuild(backend)?)
+        } else {
+            None
+        };
+        let fragment_stage = if has_fragment_changed {
+            match self.fragment_source.rebuild(backend) {
+                Ok(shader) => Some(shader),
+                Err(err) => {
+                    if let Some(shader) = vertex_stage {
+                        shader.destroy(device, allocator)?;
+                    }
+                    return Err(err);
+                }
+            }
+        } else {
+            None
+        };
+
+        let pipeline = Self::create_pipeline_info(
+            backend,
+            vertex_stage.as_ref().unwrap_or(&self.vertex_stage),
+            fragment_stage.as_ref().unwrap_or(&self.fragment_stage),
+        )
+        .and_then(|pipeline_info| Pipeline::create_graphics(device, allocator, pipeline_info));
+        let pipeline = match pipeline {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                error!(
+                    "Failed to rebuild the pipeline when reloading the vulkan immediate shaders: {:?}",
+                    err
+                );
+                for shader in vertex_stage.iter().chain(fragment_stage.iter()) {
+                    shader.destroy(device, allocator)?;
+                }
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+
+        // The previous pipeline may still be used by the frames in flight
+        if let Err(err) = backend.device_wait_idle() {
+            error!(
+                "Failed to wait idle when reloading the vulkan immediate shaders: {:?}",
+                err
+            );
+            return Err(EngineError::Unknown);
+        }
+        std::mem::replace(&mut self.pipeline, pipeline).destroy(device, allocator)?;
+        if let Some(shader) = vertex_stage {
+            std::mem::replace(&mut self.vertex_stage, shader).destroy(device, allocator)?;
+        }
+        if let Some(shader) = fragment_stage {
+            std::mem::replace(&mut self.fragment_stage, shader).destroy(device, allocator)?;
+        }
+        Ok(())
+    }
+
+    pub fn destroy(&self, backend: &VulkanRendererBackend<'_>) -> Result<(), EngineError> {
+        let device = backend.get_device()?;
+        let allocator = backend.get_allocator()?;
+
+        for buffer in self.vertex_buffers.iter() {
+            if let Err(err) = backend.destroy_buffer(buffer) {
+                error!(
+                    "Failed to destroy a vertex buffer of the vulkan immediate shaders: {:?}",
+                    err
+                );
+                return Err(EngineError::ShutdownFailed);
+            }
+        }
+        if let Err(err) = self.pipeline.destroy(device, allocator) {
+            error!(
+                "Failed to destroy the pipeline of the vulkan immediate shaders: {:?}",
+                err
+            );
+            return Err(EngineError::ShutdownFailed);
+        }
+        if let Err(err) = self.vertex_stage.destroy(device, allocator) {
+            error!(
+                "Failed to destroy the vertex stage of the vulkan immediate shaders: {:?}",
+                err
+            );
+            return Err(EngineError::ShutdownFailed);
+        }
+        if let Err(err) = self.fragment_stage.destroy(device, allocator) {
+            error!(
+                "Failed to destroy the fragment stage of the vulkan immediate shaders: {:?}",
+                err
+            );
+            return Err(EngineError::ShutdownFailed);
+        }
+        Ok(())
+    }
+
+    pub fn r#use(
+        &self,
+        device: &Device,
+        command_buffer: &CommandBuffer,
+    ) -> Result<(), EngineError> {
+        if let Err(err) = self
+            .pipeline
+            .bind(device, command_buffer, PipelineBindPoint::GRAPHICS)
+        {
+            error!(
+                "Failed to bind the pipeline of the vulkan immediate shaders: {:?}",
+                err
+            );
+            return Err(EngineError::UpdateFailed);
+        }
+        Ok(())
+    }
+}
+
+impl VulkanRendererBackend<'_> {
+    /// Record the draw of the triangles batched by the frontend during this frame
+    pub fn draw_immediate_shaders(
+        &mut self,
+        projection: glam::Mat4,
+        vertices: &[ImmediateVertexData],
+    ) -> Result<(), EngineError> {
+        if vertices.is_empty() {
+            return Ok(());
+        }
+        // Several draws can be recorded in a frame, e.g. by the render hooks
+        let frame_number = self.frame_number;
+        let immediate_shaders = &mut self.get_builtin_shaders_mut()?.immediate_shaders;
+        if immediate_shaders.frame_number != frame_number {
+            immediate_shaders.frame_number = frame_number;
+            immediate_shaders.frame_vertex_count = 0;
+        }
+        let first_vertex = immediate_shaders.frame_vertex_count;
+        let available_vertices = VULKAN_IMMEDIATE_SHADERS_MAX_VERTICES - first_vertex;
+        let vertices = if vertices.len() > available_vertices {
+            warn!(
+                "Too many immediate vertices this frame, only the first {} are drawn",
+                VULKAN_IMMEDIATE_SHADERS_MAX_VERTICES
+            );
+            // Keep whole triangles
+            &vertices[..available_vertices - available_vertices % 3]
+        } else {
+            vertices
+        };
+        if vertices.is_empty() {
+            return Ok(());
+        }
+        immediate_shaders.frame_vertex_count += vertices.len();
+
+        let current_frame_index = self.get_current_frame_index();
+        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
+        let device = self.get_device()?;
+        let immediate_shaders = &self.get_builtin_shaders()?.immediate_shaders;
+        let vertex_buffer = immediate_shaders.vertex_buffers.get(current_frame_index);
+
+        if let Err(err) = self.load_data_into_buffer(
+            vertex_buffer,
+            (first_vertex * size_of::<ImmediateVertexData>()) as u64,
+            std::mem::size_of_val(vertices),
+            MemoryMapFlags::empty(),
+            vertices.as_ptr() as *mut std::ffi::c_void,
+        ) {
+            error!(
+                "Failed to upload the vertices of the vulkan immediate shaders: {:?}",
+                err
+            );
+            return Err(EngineError::UpdateFailed);
+        }
+
+        immediate_shaders.r#use(device, command_buffer)?;
+        // The fragment stage encodes the colors for the swapchain
+        let overlay_constants = self.get_overlay_constants(projection);
+        let constants = unsafe {
+            std::slice::from_raw_parts(
+                &overlay_constants as *const OverlayConstants as *const u8,
+                size_of::<OverlayConstants>(),
+            )
+        };
+        unsafe {
+            device.cmd_push_constants(
+                *command_buffer.handler.as_ref(),
+                immediate_shaders.pipeline.layout,
+                ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT,
+                0,
+                constants,
+            );
+            device.cmd_bind_vertex_buffers(
+                *command_buffer.handler.as_ref(),
+                0,
+                &[vertex_buffer.buffer],
+                &[0],
+            );
+            device.cmd_draw(
+                *command_buffer.handler.as_ref(),
+                vertices.len() as u32,
+                1,
+                first_vertex as u32,
+                0,
+            );
+        }
+        Ok(())
+    }
+}
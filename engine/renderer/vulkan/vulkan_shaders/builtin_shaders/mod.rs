@@ -1,13 +1,33 @@
-pub mod object_shaders;
+pub(crate) mod immediate_shaders;
+pub(crate) mod object_shaders;
+pub(crate) mod post_process_shaders;
+pub(crate) mod ui_shaders;
 
+use immediate_shaders::ImmediateShaders;
 use object_shaders::ObjectShaders;
+use post_process_shaders::{output_color_space_get_index, PostProcessShaders};
+use ui_shaders::UiShaders;
 
 use crate::{
     core::debug::errors::EngineError, error, renderer::vulkan::vulkan_types::VulkanRendererBackend,
 };
 
+/// Push constants of the overlays drawn in the swapchain images, shared by their vertex and fragment stages
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct OverlayConstants {
+    pub projection: glam::Mat4,
+    /// x is the color space of the swapchain and y the sdr white in nits
+    pub output_parameters: glam::Vec4,
+}
+
 pub(crate) struct BuiltinShaders {
     pub object_shaders: ObjectShaders,
+    pub immediate_shaders: ImmediateShaders,
+    pub ui_shaders: UiShaders,
+    pub post_process_shaders: PostProcessShaders,
+    /// Seconds since the shader sources were last checked
+    pub hot_reload_elapsed_time: f64,
 }
 
 impl BuiltinShaders {
@@ -22,7 +42,43 @@ impl BuiltinShaders {
                 return Err(EngineError::InitializationFailed);
             }
         };
-        Ok(BuiltinShaders { object_shaders })
+        let immediate_shaders = match ImmediateShaders::create(backend) {
+            Ok(shader) => shader,
+            Err(err) => {
+                error!(
+                    "Failed to create the immediate shaders of the builtin vulkan shaders: {:?}",
+                    err
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        let ui_shaders = match UiShaders::create(backend) {
+            Ok(shader) => shader,
+            Err(err) => {
+                error!(
+                    "Failed to create the ui shaders of the builtin vulkan shaders: {:?}",
+                    err
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        let post_process_shaders = match PostProcessShaders::create(backend) {
+            Ok(shader) => shader,
+            Err(err) => {
+                error!(
+                    "Failed to create the post processing shaders of the builtin vulkan shaders: {:?}",
+                    err
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        Ok(BuiltinShaders {
+            object_shaders,
+            immediate_shaders,
+            ui_shaders,
+            post_process_shaders,
+            hot_reload_elapsed_time: 0.0,
+        })
     }
 
     pub fn destroy(&self, backend: &VulkanRendererBackend<'_>) -> Result<(), EngineError> {
@@ -33,6 +89,41 @@ impl BuiltinShaders {
             );
             return Err(EngineError::InitializationFailed);
         }
+        if let Err(err) = self.immediate_shaders.destroy(backend) {
+            error!(
+                "Failed to destroy the immediate shaders of the builtin vulkan shaders: {:?}",
+                err
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+        if let Err(err) = self.ui_shaders.destroy(backend) {
+            error!(
+                "Failed to destroy the ui shaders of the builtin vulkan shaders: {:?}",
+                err
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+        if let Err(err) = self.post_process_shaders.destroy(backend) {
+            error!(
+                "Failed to destroy the post processing shaders of the builtin vulkan shaders: {:?}",
+                err
+            );
+            return Err(EngineError::InitializationFailed);
+        }
         Ok(())
     }
 }
+
+impl VulkanRendererBackend<'_> {
+    pub(crate) fn get_overlay_constants(&self, projection: glam::Mat4) -> OverlayConstants {
+        OverlayConstants {
+            projection,
+            output_parameters: glam::Vec4::new(
+                output_color_space_get_index(self.output_color_space) as f32,
+                self.post_process_settings.tonemap.hdr_paper_white,
+                0.0,
+                0.0,
+            ),
+        }
+    }
+}
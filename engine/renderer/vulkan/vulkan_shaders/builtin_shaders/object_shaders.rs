@@ -1,12 +1,14 @@
+use std::collections::HashMap;
+
 use ash::{
     vk::{
-        BufferUsageFlags, DescriptorBufferInfo, DescriptorImageInfo, DescriptorPool,
-        DescriptorPoolCreateInfo, DescriptorPoolSize, DescriptorSet, DescriptorSetAllocateInfo,
-        DescriptorSetLayout, DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo,
-        DescriptorType, Extent2D, Format, ImageLayout, MemoryMapFlags, MemoryPropertyFlags,
-        Offset2D, PipelineBindPoint, PipelineShaderStageCreateInfo, Rect2D, ShaderStageFlags,
-        VertexInputAttributeDescription, VertexInputBindingDescription, VertexInputRate, Viewport,
-        WriteDescriptorSet,
+        AllocationCallbacks, BufferUsageFlags, CullModeFlags, DescriptorBufferInfo,
+        DescriptorImageInfo, DescriptorPool, DescriptorPoolCreateFlags, DescriptorPoolCreateInfo,
+        DescriptorPoolSize, DescriptorSet, DescriptorSetAllocateInfo, DescriptorSetLayout,
+        DescriptorSetLayoutBinding, DescriptorSetLayoutCreateInfo, DescriptorType, Extent2D,
+        ImageLayout, MemoryMapFlags, MemoryPropertyFlags, Offset2D, PipelineBindPoint,
+        PipelineShaderStageCreateInfo, Rect2D, ShaderStageFlags, VertexInputBindingDescription,
+        VertexInputRate, Viewport, WriteDescriptorSet,
     },
     Device,
 };
@@ -15,69 +17,310 @@ use crate::{
     core::debug::errors::EngineError,
     error,
     renderer::{
-        renderer_frontend::renderer_get_default_texture,
         renderer_types::{
             GeometryRenderData, RendererGlobalUniformObject, RendererPerObjectUniformObject,
-            RENDERER_MAX_IN_FLIGHT_FRAMES,
+            VertexData, RENDERER_MAX_IN_FLIGHT_FRAMES,
         },
         vulkan::{
-            vulkan_init::command_buffer::CommandBuffer,
-            vulkan_shaders::shader::Shader,
+            vulkan_init::{
+                command_buffer::CommandBuffer, renderpass::VULKAN_RENDERPASS_WORLD_SUBPASS,
+            },
+            vulkan_shaders::{
+                hot_reload::ShaderSource,
+                reflection::{
+                    reflection_get_descriptor_set_layout_bindings,
+                    reflection_get_push_constant_ranges, reflection_get_vertex_attributes_in_range,
+                },
+                shader::Shader,
+            },
             vulkan_types::VulkanRendererBackend,
             vulkan_utils::{
                 buffer::{Buffer, BufferCreatorParameters},
+                per_frame_resource::PerFrameResource,
                 pipeline::{Pipeline, PipelineCreateInfo},
                 texture::Texture,
             },
         },
     },
+    resources::{
+        material::{
+            MaterialRenderState, MaterialTextureSlot, MATERIAL_DEFAULT_SHININESS,
+            MATERIAL_DEFAULT_SPECULAR_STRENGTH, MATERIAL_TEXTURE_SLOT_COUNT,
+        },
+        texture_system::TextureSystem,
+    },
 };
 
-pub const VULKAN_MAX_OBJECT_COUNT: usize = 1024;
-pub const VULKAN_OBJECT_SHADERS_PER_OBJECT_DESCRIPTOR_COUNT: usize = 2;
+/// Objects added to the per object storage every time it is full
+pub const VULKAN_OBJECT_SHADERS_OBJECT_COUNT_STEP: usize = 1024;
+/// Draws whose uniforms fit in the first per object uniform buffer of a frame, it doubles when full
+pub const VULKAN_OBJECT_SHADERS_INITIAL_DRAW_COUNT: usize = 1024;
+/// Per object uniform sets of a frame alive at once, the replaced ones live until the frame is done
+pub const VULKAN_OBJECT_SHADERS_UNIFORM_SETS_PER_FRAME: usize = 4;
+/// Instances of the objects drawn together that can be drawn in a single frame
+pub const VULKAN_OBJECT_SHADERS_MAX_INSTANCES: usize = 16384;
+/// First location of the per instance attributes in the vertex shader
+pub const VULKAN_OBJECT_SHADERS_INSTANCE_LOCATION: u32 = 5;
+/// Only the samplers remain per object, one per material texture slot, the uniform data lives in a shared dynamic buffer
+pub const VULKAN_OBJECT_SHADERS_PER_OBJECT_DESCRIPTOR_COUNT: usize = MATERIAL_TEXTURE_SLOT_COUNT;
+/// Binding of the reflection probes cube array in the global set, next to the global uniform buffer
+pub const VULKAN_OBJECT_SHADERS_REFLECTION_PROBES_BINDING: u32 = 1;
+/// Views drawn per frame, each with its global uniforms: the cameras drawing into textures and the world
+pub const VULKAN_OBJECT_SHADERS_MAX_VIEWS: usize = 8;
+
+/// Layout bindings of the descriptor sets, reflected from the shader binaries
+struct ObjectShadersLayoutBindings {
+    global: Vec<DescriptorSetLayoutBinding<'static>>,
+    per_object_ubo: Vec<DescriptorSetLayoutBinding<'static>>,
+    per_object: Vec<DescriptorSetLayoutBinding<'static>>,
+}
 
 #[derive(Default, Clone, Copy)]
 pub(crate) struct DescriptorState {
-    // One per frame
-    pub generations: [Option<u32>; RENDERER_MAX_IN_FLIGHT_FRAMES],
+    pub generations: PerFrameResource<Option<u32>>,
+    /// Id of the bound texture, a new texture can have the same generation as the old one
+    pub ids: PerFrameResource<Option<u32>>,
 }
 
 #[derive(Default, Clone, Copy)]
 pub(crate) struct ObjectShadersPerObjectState {
-    // Per frame
-    pub descriptor_sets: [DescriptorSet; RENDERER_MAX_IN_FLIGHT_FRAMES],
+    pub descriptor_sets: PerFrameResource<DescriptorSet>,
+    // Index of the pool the descriptor sets were allocated from
+    pub descriptor_pool_index: usize,
     // Per descriptor
     pub descriptor_states: [DescriptorState; VULKAN_OBJECT_SHADERS_PER_OBJECT_DESCRIPTOR_COUNT],
+    // Resource epoch of the backend when the descriptors were written
+    pub resource_epoch: u64,
+    // Frame number when the sets were last written, a set bound earlier in the frame can't change
+    pub written_frame_numbers: PerFrameResource<Option<u64>>,
+}
+
+impl ObjectShadersPerObjectState {
+    /// Every descriptor is written again when the object is next drawn
+    fn reset_descriptor_states(&mut self, resource_epoch: u64) {
+        for descriptor_state in self.descriptor_states.iter_mut() {
+            descriptor_state.generations = PerFrameResource::default();
+            descriptor_state.ids = PerFrameResource::default();
+        }
+        self.resource_epoch = resource_epoch;
+    }
 }
 
 /// Default shader to display objects
 pub(crate) struct ObjectShaders {
     pub vertex_stage: Shader,
     pub fragment_stage: Shader,
+    // Draws the objects with the default render state
     pub pipeline: Pipeline,
+    // Pipelines of the other material render states, keyed by state hash, created when first drawn
+    pub pipeline_variants: HashMap<u64, Pipeline>,
+    // Watched to rebuild the pipeline when they change
+    pub vertex_source: ShaderSource,
+    pub fragment_source: ShaderSource,
 
-    // One descriptor set per frame
-    pub global_descriptor_sets: [DescriptorSet; RENDERER_MAX_IN_FLIGHT_FRAMES],
+    pub global_descriptor_sets: PerFrameResource<DescriptorSet>,
     pub global_descriptor_pool: DescriptorPool,
     pub global_descriptor_set_layout: DescriptorSetLayout,
     pub global_ubo: RendererGlobalUniformObject,
+    // One slot per view of every frame in flight, selected with a dynamic offset
     pub global_uniform_buffer: Buffer,
-
-    pub per_object_descriptor_pool: DescriptorPool,
+    // Size of one view slot in the global uniform buffer, respecting the device alignment
+    pub global_ubo_stride: u64,
+    // Views whose global uniforms were written during the current frame
+    pub global_ubo_count: usize,
+
+    // One buffer per frame, every draw writes its uniforms in the next slot selected with a dynamic offset
+    pub per_object_ubo_descriptor_sets: PerFrameResource<DescriptorSet>,
+    pub per_object_ubo_descriptor_pool: DescriptorPool,
+    pub per_object_ubo_descriptor_set_layout: DescriptorSetLayout,
+    pub per_object_uniform_buffers: PerFrameResource<Buffer>,
+    // Size of one draw slot in the per object uniform buffers, respecting the device alignment
+    pub per_object_ubo_stride: u64,
+    // Slots written in the buffer of the current frame
+    pub per_object_ubo_count: usize,
+    // Buffers and sets replaced while their frame was recorded, freed once it is done
+    pub retired_per_object_uniforms: PerFrameResource<Vec<(Buffer, DescriptorSet)>>,
+
+    // Per object samplers, a new pool is added when the others are full
+    // Without pools when bindless, the layout is then the one of the bindless textures
+    pub per_object_descriptor_pools: Vec<DescriptorPool>,
     pub per_object_descriptor_set_layout: DescriptorSetLayout,
-    pub per_object_uniform_buffer: Buffer,
-    // TODO: manage a free list of some kind here instead
-    pub object_uniform_buffer_index: u32,
-    // TODO: make dynamic
-    pub object_states: [ObjectShadersPerObjectState; VULKAN_MAX_OBJECT_COUNT],
+    // The material textures are indexed in the bindless array instead of written per object
+    pub is_bindless: bool,
+    // First id never acquired, the released ids are reused before it
+    pub next_object_id: u32,
+    // Released ids available for the next objects
+    pub free_object_ids: Vec<u32>,
+    // Grows with the number of objects acquired at once
+    pub object_states: Vec<ObjectShadersPerObjectState>,
+
+    // Model matrices of the instanced draws, rewritten every frame from the cpu
+    // The first instance is the identity, used by the objects drawn alone
+    pub instance_buffers: PerFrameResource<Buffer>,
+    // Instances written in the buffer of the current frame
+    pub instance_count: usize,
 }
 
 impl ObjectShaders {
+    /// Pool holding the samplers of VULKAN_OBJECT_SHADERS_OBJECT_COUNT_STEP objects for every frame
+    fn create_per_object_descriptor_pool(
+        device: &Device,
+        allocator: Option<&AllocationCallbacks<'_>>,
+    ) -> Result<DescriptorPool, EngineError> {
+        let set_count =
+            (VULKAN_OBJECT_SHADERS_OBJECT_COUNT_STEP * RENDERER_MAX_IN_FLIGHT_FRAMES) as u32;
+        let pool_sizes: [DescriptorPoolSize; 1] = [
+            // Image samplers
+            DescriptorPoolSize::default()
+                .ty(DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(
+                    VULKAN_OBJECT_SHADERS_PER_OBJECT_DESCRIPTOR_COUNT as u32 * set_count,
+                ),
+        ];
+        let create_info = DescriptorPoolCreateInfo::default()
+            // The sets of the released objects are given back to the pool
+            .flags(DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
+            .pool_sizes(&pool_sizes)
+            .max_sets(set_count);
+        unsafe {
+            match device.create_descriptor_pool(&create_info, allocator) {
+                Ok(pool) => Ok(pool),
+                Err(err) => {
+                    error!("Failed to create a local descriptor pool of the vulkan object shaders: {:?}", err);
+                    Err(EngineError::VulkanFailed)
+                }
+            }
+        }
+    }
+
+    /// Per object uniform buffer holding the given number of draws and the set pointing to it
+    fn create_per_object_uniforms(
+        backend: &VulkanRendererBackend<'_>,
+        descriptor_pool: DescriptorPool,
+        descriptor_set_layout: DescriptorSetLayout,
+        stride: u64,
+        draw_count: usize,
+    ) -> Result<(Buffer, DescriptorSet), EngineError> {
+        let device = backend.get_device()?;
+        let buffer_creator_params = BufferCreatorParameters::default()
+            .buffer_usage_flags(BufferUsageFlags::UNIFORM_BUFFER)
+            .memory_flags(MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT)
+            .should_be_bind(true)
+            .size(stride as usize * draw_count);
+        let buffer = match backend.create_buffer(buffer_creator_params) {
+            Ok(buffer) => buffer,
+            Err(err) => {
+                error!("Failed to create a per object uniform buffer of the vulkan object shaders: {:?}", err);
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+
+        let layouts = [descriptor_set_layout];
+        let allocate_info = DescriptorSetAllocateInfo::default()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(&layouts);
+        let descriptor_set = match unsafe { device.allocate_descriptor_sets(&allocate_info) } {
+            Ok(sets) => sets[0],
+            Err(err) => {
+                error!("Failed to allocate a per object uniform descriptor set of the vulkan object shaders: {:?}", err);
+                backend.destroy_buffer(&buffer)?;
+                return Err(EngineError::VulkanFailed);
+            }
+        };
+
+        // The set always points to the start of the buffer, the draw is selected at bind time
+        let buffer_info = [DescriptorBufferInfo::default()
+            .buffer(buffer.buffer)
+            .offset(0)
+            .range(size_of::<RendererPerObjectUniformObject>() as u64)];
+        let descriptor_writes = [WriteDescriptorSet::default()
+            .dst_set(descriptor_set)
+            .dst_binding(0)
+            .dst_array_element(0)
+            .descriptor_type(DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+            .descriptor_count(1)
+            .buffer_info(&buffer_info)];
+        unsafe { device.update_descriptor_sets(&descriptor_writes, &[]) };
+        Ok((buffer, descriptor_set))
+    }
+
+    /// Bindings of the global, per object uniform and per object sampler sets
+    fn get_descriptor_set_layout_bindings(
+        stages: &[&Shader],
+        is_bindless: bool,
+    ) -> Result<ObjectShadersLayoutBindings, EngineError> {
+        let reflected_global_bindings = reflection_get_descriptor_set_layout_bindings(stages, 0)?;
+        // The uniform data of every object lives in a single buffer selected with a dynamic offset
+        let per_object_ubo_bindings = reflection_get_descriptor_set_layout_bindings(stages, 1)?
+            .into_iter()
+            .map(|binding| match binding.descriptor_type {
+                DescriptorType::UNIFORM_BUFFER => {
+                    binding.descriptor_type(DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+                }
+                _ => binding,
+            })
+            .collect::<Vec<_>>();
+        let per_object_bindings = reflection_get_descriptor_set_layout_bindings(stages, 2)?;
+
+        // The descriptor writes of the objects expect this interface
+        let global_types = reflected_global_bindings
+            .iter()
+            .map(|binding| (binding.binding, binding.descriptor_type))
+            .collect::<Vec<_>>();
+        let expected_global_types = [
+            (0, DescriptorType::UNIFORM_BUFFER),
+            (
+                VULKAN_OBJECT_SHADERS_REFLECTION_PROBES_BINDING,
+                DescriptorType::COMBINED_IMAGE_SAMPLER,
+            ),
+        ];
+        if global_types != expected_global_types || per_object_ubo_bindings.len() != 1 {
+            error!(
+                "The object shaders expect a global uniform buffer with the reflection probes and a per object uniform buffer, got {:?} and {:?}",
+                reflected_global_bindings, per_object_ubo_bindings
+            );
+            return Err(EngineError::InvalidValue);
+        }
+        // Every view of the frame has its global uniforms in the same buffer
+        let global_bindings = reflected_global_bindings
+            .into_iter()
+            .map(|binding| match binding.descriptor_type {
+                DescriptorType::UNIFORM_BUFFER => {
+                    binding.descriptor_type(DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+                }
+                _ => binding,
+            })
+            .collect::<Vec<_>>();
+        // The bindless variant samples every texture from a single array
+        let per_object_binding_count = if is_bindless {
+            1
+        } else {
+            VULKAN_OBJECT_SHADERS_PER_OBJECT_DESCRIPTOR_COUNT
+        };
+        if per_object_bindings.len() != per_object_binding_count
+            || per_object_bindings
+                .iter()
+                .any(|binding| binding.descriptor_type != DescriptorType::COMBINED_IMAGE_SAMPLER)
+        {
+            error!(
+                "The object shaders expect {} per object samplers, got {:?}",
+                per_object_binding_count, per_object_bindings
+            );
+            return Err(EngineError::InvalidValue);
+        }
+        Ok(ObjectShadersLayoutBindings {
+            global: global_bindings,
+            per_object_ubo: per_object_ubo_bindings,
+            per_object: per_object_bindings,
+        })
+    }
+
     fn create_pipeline_info<'a>(
         backend: &'a VulkanRendererBackend<'a>,
         vertex_shader: &'a Shader,
         fragment_shader: &'a Shader,
         layouts: Vec<DescriptorSetLayout>,
+        render_state: &MaterialRenderState,
     ) -> Result<PipelineCreateInfo<'a>, EngineError> {
         // Pipeline creation
         let viewports = vec![Viewport::default()
@@ -96,31 +339,30 @@ impl ObjectShaders {
                 height: backend.framebuffer_height,
             })];
 
-        // Input attributes
-        let offset = 0;
+        // Input attributes, the locations of the vertex shader follow the fields of VertexData
         let vertex_input_binding_description = VertexInputBindingDescription::default()
             .binding(0)
-            .stride((size_of::<glam::Vec3>() + size_of::<glam::Vec2>()) as u32)
+            .stride(size_of::<VertexData>() as u32)
             .input_rate(VertexInputRate::VERTEX);
-        let position_attribute_description = VertexInputAttributeDescription::default()
-            //  position
-            .binding(vertex_input_binding_description.binding)// should match binding description
-            .location(0)
-            .format(Format::R32G32B32_SFLOAT)
-            .offset(0) // because first, else offset += size_of::<attribute type>
-        ;
-        let texture_attribute_description = VertexInputAttributeDescription::default()
-            //  texture coordinates
-            .binding(vertex_input_binding_description.binding)// should match binding description
-            .location(1)
-            .format(Format::R32G32_SFLOAT)
-            .offset(size_of::<glam::Vec3>() as u32) // offset += size_of::<previous attribute type>
-        ;
-        let vertex_input_attributes_description = vec![
-            position_attribute_description,
-            texture_attribute_description,
+        // Then come the columns of the model matrix of each instance
+        let instance_input_binding_description = VertexInputBindingDescription::default()
+            .binding(1)
+            .stride(size_of::<glam::Mat4>() as u32)
+            .input_rate(VertexInputRate::INSTANCE);
+        let mut vertex_input_attributes_description = reflection_get_vertex_attributes_in_range(
+            vertex_shader,
+            vertex_input_binding_description.binding,
+            0..VULKAN_OBJECT_SHADERS_INSTANCE_LOCATION,
+        );
+        vertex_input_attributes_description.extend(reflection_get_vertex_attributes_in_range(
+            vertex_shader,
+            instance_input_binding_description.binding,
+            VULKAN_OBJECT_SHADERS_INSTANCE_LOCATION..u32::MAX,
+        ));
+        let vertex_input_bindings_description = vec![
+            vertex_input_binding_description,
+            instance_input_binding_description,
         ];
-        let vertex_input_bindings_description = vec![vertex_input_binding_description];
 
         // descriptor set layouts
         let descriptor_set_layouts = layouts;
@@ -147,7 +389,21 @@ impl ObjectShaders {
             vertex_input_attributes_description,
             vertex_input_bindings_description,
             descriptor_set_layouts,
+            push_constant_ranges: reflection_get_push_constant_ranges(&[
+                vertex_shader,
+                fragment_shader,
+            ]),
             shader_stages_info,
+            stencil_state: None,
+            cull_mode: if render_state.is_two_sided {
+                CullModeFlags::NONE
+            } else {
+                CullModeFlags::BACK
+            },
+            is_depth_test_enabled: render_state.is_depth_test_enabled,
+            is_depth_write_enabled: render_state.is_depth_write_enabled,
+            blend_mode: render_state.blend_mode,
+            subpass: VULKAN_RENDERPASS_WORLD_SUBPASS,
         })
     }
 
@@ -156,12 +412,22 @@ impl ObjectShaders {
         let allocator = backend.get_allocator()?;
 
         // Shader module init per stage
+        let bindless_textures = backend.get_bindless_textures();
+        let is_bindless = bindless_textures.is_some();
+        let vertex_source =
+            ShaderSource::new("builtin/object.vert.slang", None, ShaderStageFlags::VERTEX);
+        let fragment_path = if is_bindless {
+            "builtin/object_bindless.frag.slang"
+        } else {
+            "builtin/object.frag.slang"
+        };
+        let fragment_source = ShaderSource::new(fragment_path, None, ShaderStageFlags::FRAGMENT);
         let vertex_stage = match Shader::create(
             device,
             allocator,
-            ShaderStageFlags::VERTEX,
-            "builtin/object.vert.slang",
-            None,
+            vertex_source.stage_flag,
+            vertex_source.path,
+            vertex_source.entry_point,
         ) {
             Ok(shader) => shader,
             Err(err) => {
@@ -173,9 +439,9 @@ impl ObjectShaders {
         let fragment_stage = match Shader::create(
             device,
             allocator,
-            ShaderStageFlags::FRAGMENT,
-            "builtin/object.frag.slang",
-            None,
+            fragment_source.stage_flag,
+            fragment_source.path,
+            fragment_source.entry_point,
         ) {
             Ok(shader) => shader,
             Err(err) => {
@@ -184,15 +450,22 @@ impl ObjectShaders {
             }
         };
 
-        // Descriptors
+        // Descriptors, the layouts are read from the shader binaries
+        let stages = [&vertex_stage, &fragment_stage];
+        let layout_bindings = match Self::get_descriptor_set_layout_bindings(&stages, is_bindless) {
+            Ok(bindings) => bindings,
+            Err(err) => {
+                error!(
+                    "Failed to reflect the descriptor layouts of the vulkan object shaders: {:?}",
+                    err
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+
         // Global Descriptors
-        let global_ubo_layout_bindings = [DescriptorSetLayoutBinding::default()
-            .binding(0)
-            .descriptor_count(1)
-            .descriptor_type(DescriptorType::UNIFORM_BUFFER)
-            .stage_flags(ShaderStageFlags::VERTEX)];
         let global_ubo_layout_create_info =
-            DescriptorSetLayoutCreateInfo::default().bindings(&global_ubo_layout_bindings);
+            DescriptorSetLayoutCreateInfo::default().bindings(&layout_bindings.global);
         let device = backend.get_device()?;
         let allocator = backend.get_allocator()?;
         let global_ubo_layout = unsafe {
@@ -206,9 +479,14 @@ impl ObjectShaders {
         };
         // Global descriptor pool: Used for global items such as view/projection matrix
         let image_count = backend.get_swapchain()?.images.len() as u32;
-        let global_descriptor_pool_sizes = [DescriptorPoolSize::default()
-            .ty(DescriptorType::UNIFORM_BUFFER)
-            .descriptor_count(image_count)];
+        let global_descriptor_pool_sizes = [
+            DescriptorPoolSize::default()
+                .ty(DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+                .descriptor_count(image_count),
+            DescriptorPoolSize::default()
+                .ty(DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(image_count),
+        ];
         let global_descriptor_pool_create_info = DescriptorPoolCreateInfo::default()
             .pool_sizes(&global_descriptor_pool_sizes)
             .max_sets(image_count);
@@ -225,80 +503,91 @@ impl ObjectShaders {
             }
         };
 
-        // Local/Object Descriptors
-        let local_sampler_count = 1;
-        let local_descriptor_types: [DescriptorType;
-            VULKAN_OBJECT_SHADERS_PER_OBJECT_DESCRIPTOR_COUNT] = [
-            DescriptorType::UNIFORM_BUFFER,         // Binding 0 - uniform buffer
-            DescriptorType::COMBINED_IMAGE_SAMPLER, // Binding 1 - Diffuse sampler layout
-        ];
-        let mut local_descriptor_set_layout_bindings: [DescriptorSetLayoutBinding;
-            VULKAN_OBJECT_SHADERS_PER_OBJECT_DESCRIPTOR_COUNT] =
-            [DescriptorSetLayoutBinding::default()
-                .descriptor_count(1)
-                .stage_flags(ShaderStageFlags::FRAGMENT);
-                VULKAN_OBJECT_SHADERS_PER_OBJECT_DESCRIPTOR_COUNT];
-        for (i, val) in local_descriptor_set_layout_bindings.iter_mut().enumerate() {
-            val.binding = i as u32;
-            val.descriptor_type = local_descriptor_types[i];
-        }
-
-        let local_descriptor_set_layout_create_info = DescriptorSetLayoutCreateInfo::default()
-            .bindings(&local_descriptor_set_layout_bindings);
-        let local_descriptor_set_layouts = unsafe {
-            match device
-                .create_descriptor_set_layout(&local_descriptor_set_layout_create_info, allocator)
+        // Per object uniform descriptors
+        let per_object_ubo_layout_create_info =
+            DescriptorSetLayoutCreateInfo::default().bindings(&layout_bindings.per_object_ubo);
+        let per_object_ubo_layout = unsafe {
+            match device.create_descriptor_set_layout(&per_object_ubo_layout_create_info, allocator)
             {
-                Ok(layouts) => layouts,
+                Ok(layout) => layout,
                 Err(err) => {
-                    error!("Failed to create the local descriptor layouts of the vulkan object shaders: {:?}", err);
+                    error!("Failed to create the per object uniform buffer layout of the vulkan object shaders: {:?}", err);
                     return Err(EngineError::VulkanFailed);
                 }
             }
         };
-
-        // Local/Object descriptor pool: Used for object-specific items like diffuse colour
-        let local_descriptor_pool_sizes: [DescriptorPoolSize;
-            VULKAN_OBJECT_SHADERS_PER_OBJECT_DESCRIPTOR_COUNT] = [
-            // The first section will be used for uniform buffers
-            DescriptorPoolSize::default()
-                .ty(DescriptorType::UNIFORM_BUFFER)
-                .descriptor_count(VULKAN_MAX_OBJECT_COUNT as u32),
-            // The second section will be used for image samplers
-            DescriptorPoolSize::default()
-                .ty(DescriptorType::COMBINED_IMAGE_SAMPLER)
-                .descriptor_count(local_sampler_count * VULKAN_MAX_OBJECT_COUNT as u32),
-        ];
-        let local_descriptor_pool_create_info = DescriptorPoolCreateInfo::default()
-            .pool_sizes(&local_descriptor_pool_sizes)
-            .max_sets(VULKAN_MAX_OBJECT_COUNT as u32);
-
-        // Create object descriptor pool
-        let local_descriptor_pool = unsafe {
-            match device.create_descriptor_pool(&local_descriptor_pool_create_info, allocator) {
+        // Per object uniform descriptor pool: one set per frame for all the objects, replaced when its buffer grows
+        let per_object_ubo_set_count =
+            (RENDERER_MAX_IN_FLIGHT_FRAMES * VULKAN_OBJECT_SHADERS_UNIFORM_SETS_PER_FRAME) as u32;
+        let per_object_ubo_descriptor_pool_sizes = [DescriptorPoolSize::default()
+            .ty(DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+            .descriptor_count(per_object_ubo_set_count)];
+        let per_object_ubo_descriptor_pool_create_info = DescriptorPoolCreateInfo::default()
+            .flags(DescriptorPoolCreateFlags::FREE_DESCRIPTOR_SET)
+            .pool_sizes(&per_object_ubo_descriptor_pool_sizes)
+            .max_sets(per_object_ubo_set_count);
+        let per_object_ubo_descriptor_pool = unsafe {
+            match device
+                .create_descriptor_pool(&per_object_ubo_descriptor_pool_create_info, allocator)
+            {
                 Ok(pool) => pool,
                 Err(err) => {
-                    error!("failed to create the local descriptor pool of the vulkan object shaders: {:?}", err);
+                    error!("Failed to create the per object uniform descriptor pool of the vulkan object shaders: {:?}", err);
                     return Err(EngineError::VulkanFailed);
                 }
             }
         };
 
+        // Local/Object Descriptors, the bindless array is shared by every object
+        let (local_descriptor_set_layouts, local_descriptor_pools) = match bindless_textures {
+            Some(bindless_textures) => (bindless_textures.descriptor_set_layout, Vec::new()),
+            None => {
+                let local_descriptor_set_layout_create_info =
+                    DescriptorSetLayoutCreateInfo::default().bindings(&layout_bindings.per_object);
+                let local_descriptor_set_layouts = unsafe {
+                    match device.create_descriptor_set_layout(
+                        &local_descriptor_set_layout_create_info,
+                        allocator,
+                    ) {
+                        Ok(layouts) => layouts,
+                        Err(err) => {
+                            error!("Failed to create the local descriptor layouts of the vulkan object shaders: {:?}", err);
+                            return Err(EngineError::VulkanFailed);
+                        }
+                    }
+                };
+
+                // Local/Object descriptor pool: Used for object-specific items like the diffuse texture
+                let local_descriptor_pool =
+                    Self::create_per_object_descriptor_pool(device, allocator)?;
+                (local_descriptor_set_layouts, vec![local_descriptor_pool])
+            }
+        };
+
         // Descriptor layouts
-        let layouts = vec![global_ubo_layout, local_descriptor_set_layouts];
+        let layouts = vec![
+            global_ubo_layout,
+            per_object_ubo_layout,
+            local_descriptor_set_layouts,
+        ];
 
         // Pipelines
-        let pipeline_info =
-            match Self::create_pipeline_info(backend, &vertex_stage, &fragment_stage, layouts) {
-                Ok(info) => info,
-                Err(err) => {
-                    error!(
+        let pipeline_info = match Self::create_pipeline_info(
+            backend,
+            &vertex_stage,
+            &fragment_stage,
+            layouts,
+            &MaterialRenderState::default(),
+        ) {
+            Ok(info) => info,
+            Err(err) => {
+                error!(
                     "Failed to create the pipeline info when creating vulkan object shaders: {:?}",
                     err
                 );
-                    return Err(EngineError::InitializationFailed);
-                }
-            };
+                return Err(EngineError::InitializationFailed);
+            }
+        };
         let pipeline = match Pipeline::create_graphics(device, allocator, pipeline_info) {
             Ok(pipeline) => pipeline,
             Err(err) => {
@@ -310,12 +599,25 @@ impl ObjectShaders {
             }
         };
 
-        // Create uniform buffer
+        // Create uniform buffer, one aligned slot per view of each frame
+        let min_alignment = backend
+            .get_physical_device_info()?
+            .properties
+            .limits
+            .min_uniform_buffer_offset_alignment
+            .max(1);
+        let global_ubo_stride = (size_of::<RendererGlobalUniformObject>() as u64)
+            .div_ceil(min_alignment)
+            * min_alignment;
         let global_uniform_buffer_creator_params = BufferCreatorParameters::default()
             .buffer_usage_flags(BufferUsageFlags::TRANSFER_DST | BufferUsageFlags::UNIFORM_BUFFER)
             .memory_flags(MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT)
             .should_be_bind(true)
-            .size(size_of::<RendererGlobalUniformObject>());
+            .size(
+                global_ubo_stride as usize
+                    * VULKAN_OBJECT_SHADERS_MAX_VIEWS
+                    * RENDERER_MAX_IN_FLIGHT_FRAMES,
+            );
         let global_uniform_buffer = match backend
             .create_buffer(global_uniform_buffer_creator_params)
         {
@@ -327,11 +629,10 @@ impl ObjectShaders {
         };
 
         // Allocate global descriptor sets
-        let global_descriptor_sets_layouts =
-            [global_ubo_layout, global_ubo_layout, global_ubo_layout];
+        let global_descriptor_sets_layouts = PerFrameResource::splat(global_ubo_layout);
         let global_descriptor_sets_allocate_info = DescriptorSetAllocateInfo::default()
             .descriptor_pool(global_descriptor_pool)
-            .set_layouts(&global_descriptor_sets_layouts);
+            .set_layouts(global_descriptor_sets_layouts.as_slice());
         let global_descriptor_sets = unsafe {
             match device.allocate_descriptor_sets(&global_descriptor_sets_allocate_info) {
                 Ok(sets) => sets,
@@ -341,44 +642,156 @@ impl ObjectShaders {
                 }
             }
         };
-        let global_descriptor_sets = [
-            global_descriptor_sets[0],
-            global_descriptor_sets[1],
-            global_descriptor_sets[2],
-        ];
+        let global_descriptor_sets = PerFrameResource::from_vec(global_descriptor_sets)?;
+
+        // Create the local uniform buffer, one aligned slot per object
+        let per_object_ubo_stride = (size_of::<RendererPerObjectUniformObject>() as u64)
+            .div_ceil(min_alignment)
+            * min_alignment;
+        let mut per_object_uniform_buffers = Vec::new();
+        let mut per_object_ubo_descriptor_sets = Vec::new();
+        for _ in 0..RENDERER_MAX_IN_FLIGHT_FRAMES {
+            let (buffer, descriptor_set) = Self::create_per_object_uniforms(
+                backend,
+                per_object_ubo_descriptor_pool,
+                per_object_ubo_layout,
+                per_object_ubo_stride,
+                VULKAN_OBJECT_SHADERS_INITIAL_DRAW_COUNT,
+            )?;
+            per_object_uniform_buffers.push(buffer);
+            per_object_ubo_descriptor_sets.push(descriptor_set);
+        }
 
-        // Create the local uniform buffer
-        let local_uniform_buffer_creator_params = BufferCreatorParameters::default()
-            .buffer_usage_flags(BufferUsageFlags::TRANSFER_DST | BufferUsageFlags::UNIFORM_BUFFER)
-            .memory_flags(MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT)
-            .should_be_bind(true)
-            .size(size_of::<RendererPerObjectUniformObject>());
-        let local_uniform_buffer = match backend.create_buffer(local_uniform_buffer_creator_params)
-        {
-            Ok(buffer) => buffer,
-            Err(err) => {
-                error!("Failed to create the local uniform buffer when creating vulkan object shaders: {:?}", err);
-                return Err(EngineError::InitializationFailed);
+        let mut instance_buffers = Vec::new();
+        for _ in 0..RENDERER_MAX_IN_FLIGHT_FRAMES {
+            let instance_buffer_creator_params = BufferCreatorParameters::default()
+                .buffer_usage_flags(BufferUsageFlags::VERTEX_BUFFER)
+                .memory_flags(
+                    MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+                )
+                .should_be_bind(true)
+                .size(size_of::<glam::Mat4>() * VULKAN_OBJECT_SHADERS_MAX_INSTANCES);
+            match backend.create_buffer(instance_buffer_creator_params) {
+                Ok(buffer) => instance_buffers.push(buffer),
+                Err(err) => {
+                    error!("Failed to create the instance buffer when creating vulkan object shaders: {:?}", err);
+                    return Err(EngineError::InitializationFailed);
+                }
             }
-        };
+        }
 
         Ok(ObjectShaders {
             vertex_stage,
             fragment_stage,
             pipeline,
+            pipeline_variants: HashMap::new(),
+            vertex_source,
+            fragment_source,
             global_descriptor_pool,
             global_descriptor_set_layout: global_ubo_layout,
             global_descriptor_sets,
             global_ubo: RendererGlobalUniformObject::default(),
             global_uniform_buffer,
-            per_object_descriptor_pool: local_descriptor_pool,
+            global_ubo_stride,
+            global_ubo_count: 0,
+            per_object_ubo_descriptor_sets: PerFrameResource::from_vec(
+                per_object_ubo_descriptor_sets,
+            )?,
+            per_object_ubo_descriptor_pool,
+            per_object_ubo_descriptor_set_layout: per_object_ubo_layout,
+            per_object_uniform_buffers: PerFrameResource::from_vec(per_object_uniform_buffers)?,
+            per_object_ubo_stride,
+            per_object_ubo_count: 0,
+            retired_per_object_uniforms: PerFrameResource::default(),
+            per_object_descriptor_pools: local_descriptor_pools,
             per_object_descriptor_set_layout: local_descriptor_set_layouts,
-            per_object_uniform_buffer: local_uniform_buffer,
-            object_uniform_buffer_index: 0,
-            object_states: [ObjectShadersPerObjectState::default(); VULKAN_MAX_OBJECT_COUNT],
+            is_bindless,
+            next_object_id: 0,
+            free_object_ids: Vec::new(),
+            object_states: Vec::new(),
+            instance_buffers: PerFrameResource::from_vec(instance_buffers)?,
+            instance_count: 0,
         })
     }
 
+    /// Recompile the modified stages and swap the pipeline, the previous one is kept on failure
+    pub fn hot_reload(&mut self, backend: &VulkanRendererBackend<'_>) -> Result<(), EngineError> {
+        let has_vertex_changed = self.vertex_source.has_changed();
+        let has_fragment_changed = self.fragment_source.has_changed();
+        if !has_vertex_changed && !has_fragment_changed {
+            return Ok(());
+        }
+        let device = backend.get_device()?;
+        let allocator = backend.get_allocator()?;
+
+        let vertex_stage = if has_vertex_changed {
+            Some(self.vertex_source.rebuild(backend)?)
+        } else {
+            None
+        };
+        let fragment_stage = if has_fragment_changed {
+            match self.fragment_source.rebuild(backend) {
+                Ok(shader) => Some(shader),
+                Err(err) => {
+                    if let Some(shader) = vertex_stage {
+                        shader.destroy(device, allocator)?;
+                    }
+                    return Err(err);
+                }
+            }
+        } else {
+            None
+        };
+
+        let layouts = vec![
+            self.global_descriptor_set_layout,
+            self.per_object_ubo_descriptor_set_layout,
+            self.per_object_descriptor_set_layout,
+        ];
+        let pipeline = Self::create_pipeline_info(
+            backend,
+            vertex_stage.as_ref().unwrap_or(&self.vertex_stage),
+            fragment_stage.as_ref().unwrap_or(&self.fragment_stage),
+            layouts,
+            &MaterialRenderState::default(),
+        )
+        .and_then(|pipeline_info| Pipeline::create_graphics(device, allocator, pipeline_info));
+        let pipeline = match pipeline {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                error!(
+                    "Failed to rebuild the pipeline when reloading the vulkan object shaders: {:?}",
+                    err
+                );
+                for shader in vertex_stage.iter().chain(fragment_stage.iter()) {
+                    shader.destroy(device, allocator)?;
+                }
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+
+        // The previous pipeline may still be used by the frames in flight
+        if let Err(err) = backend.device_wait_idle() {
+            error!(
+                "Failed to wait idle when reloading the vulkan object shaders: {:?}",
+                err
+            );
+            return Err(EngineError::Unknown);
+        }
+        std::mem::replace(&mut self.pipeline, pipeline).destroy(device, allocator)?;
+        // The variants are rebuilt from the new stages the next time they are drawn
+        for (_, variant) in self.pipeline_variants.drain() {
+            variant.destroy(device, allocator)?;
+        }
+        if let Some(shader) = vertex_stage {
+            std::mem::replace(&mut self.vertex_stage, shader).destroy(device, allocator)?;
+        }
+        if let Some(shader) = fragment_stage {
+            std::mem::replace(&mut self.fragment_stage, shader).destroy(device, allocator)?;
+        }
+        Ok(())
+    }
+
     pub fn destroy(&self, backend: &VulkanRendererBackend<'_>) -> Result<(), EngineError> {
         let device = backend.get_device()?;
         let allocator = backend.get_allocator()?;
@@ -391,12 +804,32 @@ impl ObjectShaders {
             );
             return Err(EngineError::ShutdownFailed);
         }
-        if let Err(err) = backend.destroy_buffer(&self.per_object_uniform_buffer) {
-            error!(
-                "Failed to destroy the per object uniform buffer of the vulkan object shaders: {:?}",
-                err
-            );
-            return Err(EngineError::ShutdownFailed);
+        let retired_buffers = self
+            .retired_per_object_uniforms
+            .iter()
+            .flatten()
+            .map(|(buffer, _)| buffer);
+        for buffer in self
+            .per_object_uniform_buffers
+            .iter()
+            .chain(retired_buffers)
+        {
+            if let Err(err) = backend.destroy_buffer(buffer) {
+                error!(
+                    "Failed to destroy a per object uniform buffer of the vulkan object shaders: {:?}",
+                    err
+                );
+                return Err(EngineError::ShutdownFailed);
+            }
+        }
+        for buffer in self.instance_buffers.iter() {
+            if let Err(err) = backend.destroy_buffer(buffer) {
+                error!(
+                    "Failed to destroy an instance buffer of the vulkan object shaders: {:?}",
+                    err
+                );
+                return Err(EngineError::ShutdownFailed);
+            }
         }
 
         if let Err(err) = self.pipeline.destroy(device, allocator) {
@@ -406,6 +839,15 @@ impl ObjectShaders {
             );
             return Err(EngineError::ShutdownFailed);
         }
+        for variant in self.pipeline_variants.values() {
+            if let Err(err) = variant.destroy(device, allocator) {
+                error!(
+                    "Failed to destroy a pipeline variant of the vulkan object shaders: {:?}",
+                    err
+                );
+                return Err(EngineError::ShutdownFailed);
+            }
+        }
         if let Err(err) = self.vertex_stage.destroy(device, allocator) {
             error!(
                 "Failed to destroy the vertex stage of the vulkan object shaders: {:?}",
@@ -423,18 +865,91 @@ impl ObjectShaders {
         unsafe {
             device.destroy_descriptor_pool(self.global_descriptor_pool, allocator);
             device.destroy_descriptor_set_layout(self.global_descriptor_set_layout, allocator);
-            device.destroy_descriptor_pool(self.per_object_descriptor_pool, allocator);
-            device.destroy_descriptor_set_layout(self.per_object_descriptor_set_layout, allocator);
+            device.destroy_descriptor_pool(self.per_object_ubo_descriptor_pool, allocator);
+            device.destroy_descriptor_set_layout(
+                self.per_object_ubo_descriptor_set_layout,
+                allocator,
+            );
+            for pool in &self.per_object_descriptor_pools {
+                device.destroy_descriptor_pool(*pool, allocator);
+            }
+            // The bindless layout belongs to the bindless textures
+            if !self.is_bindless {
+                device.destroy_descriptor_set_layout(
+                    self.per_object_descriptor_set_layout,
+                    allocator,
+                );
+            }
         }
         Ok(())
     }
 
+    /// Pipeline of the given render state, the default one if its variant was not created yet
+    pub fn get_object_state(
+        &self,
+        object_id: usize,
+    ) -> Result<&ObjectShadersPerObjectState, EngineError> {
+        match self.object_states.get(object_id) {
+            Some(state) => Ok(state),
+            None => {
+                error!("The state of the object {} does not exist", object_id);
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    pub fn get_object_state_mut(
+        &mut self,
+        object_id: usize,
+    ) -> Result<&mut ObjectShadersPerObjectState, EngineError> {
+        match self.object_states.get_mut(object_id) {
+            Some(state) => Ok(state),
+            None => {
+                error!("The state of the object {} does not exist", object_id);
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    pub fn get_pipeline(&self, render_state: &MaterialRenderState) -> &Pipeline {
+        if *render_state == MaterialRenderState::default() {
+            return &self.pipeline;
+        }
+        self.pipeline_variants
+            .get(&render_state.get_hash())
+            .unwrap_or(&self.pipeline)
+    }
+
+    /// Pipeline drawing the given render state, created from the current stages
+    fn create_pipeline_variant(
+        &self,
+        backend: &VulkanRendererBackend<'_>,
+        render_state: &MaterialRenderState,
+    ) -> Result<Pipeline, EngineError> {
+        let layouts = vec![
+            self.global_descriptor_set_layout,
+            self.per_object_ubo_descriptor_set_layout,
+            self.per_object_descriptor_set_layout,
+        ];
+        let device = backend.get_device()?;
+        let allocator = backend.get_allocator()?;
+        Self::create_pipeline_info(
+            backend,
+            &self.vertex_stage,
+            &self.fragment_stage,
+            layouts,
+            render_state,
+        )
+        .and_then(|pipeline_info| Pipeline::create_graphics(device, allocator, pipeline_info))
+    }
+
     pub fn r#use(
         &self,
         device: &Device,
         command_buffer: &CommandBuffer,
+        render_state: &MaterialRenderState,
     ) -> Result<(), EngineError> {
-        let pipeline = &self.pipeline;
+        let pipeline = self.get_pipeline(render_state);
         if let Err(err) = pipeline.bind(device, command_buffer, PipelineBindPoint::GRAPHICS) {
             error!(
                 "Failed to bind the pipeline of the vulkan object shaders: {:?}",
@@ -447,45 +962,56 @@ impl ObjectShaders {
 }
 
 impl VulkanRendererBackend<'_> {
-    pub fn update_object_shaders_global_state(&mut self) -> Result<(), EngineError> {
-        let delta_time = self.frame_delta_time;
-
-        let current_frame_index = self.context.current_frame as usize;
-        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
-        let device = self.get_device()?;
+    /// Create the pipeline variant of a render state the first time it is drawn
+    /// The variants share the layout of the default pipeline, the bound sets stay valid when switching
+    pub fn object_shaders_acquire_pipeline_variant(
+        &mut self,
+        render_state: &MaterialRenderState,
+    ) -> Result<(), EngineError> {
+        let key = render_state.get_hash();
+        let object_shaders = self.get_object_shaders()?;
+        if *render_state == MaterialRenderState::default()
+            || object_shaders.pipeline_variants.contains_key(&key)
+        {
+            return Ok(());
+        }
+        let pipeline = match object_shaders.create_pipeline_variant(self, render_state) {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                error!(
+                    "Failed to create the pipeline variant of the render state {:?}: {:?}",
+                    render_state, err
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        self.get_object_shaders_mut()?
+            .pipeline_variants
+            .insert(key, pipeline);
+        Ok(())
+    }
 
-        // Bind the global descriptor set to be updated
-        let object_shaders = &self.get_builtin_shaders()?.object_shaders;
-        let global_descriptor_set = [object_shaders.global_descriptor_sets[current_frame_index]];
-        unsafe {
-            let offsets = [];
-            device.cmd_bind_descriptor_sets(
-                *command_buffer.handler.as_ref(),
-                PipelineBindPoint::GRAPHICS,
-                object_shaders.pipeline.layout,
-                0,
-                &global_descriptor_set,
-                &offsets,
+    /// Write the global uniforms of the next view of the frame and bind them
+    pub fn update_object_shaders_global_state(&mut self) -> Result<(), EngineError> {
+        let current_frame_index = self.get_current_frame_index();
+        let object_shaders = self.get_object_shaders()?;
+        let view_index = object_shaders.global_ubo_count;
+        if view_index >= VULKAN_OBJECT_SHADERS_MAX_VIEWS {
+            error!(
+                "The vulkan object shaders can't draw more than {} views per frame",
+                VULKAN_OBJECT_SHADERS_MAX_VIEWS
             );
+            return Err(EngineError::InvalidValue);
         }
 
         // Configure the descriptors for the given index
         let range = size_of::<RendererGlobalUniformObject>();
-        let offset = 0;
-
-        // Copy data to buffer
-        let data = {
-            let object_shaders = &mut self
-                .context
-                .builtin_shaders
-                .as_mut()
-                .unwrap()
-                .object_shaders;
-            &mut object_shaders.global_ubo as *mut RendererGlobalUniformObject
-                as *mut std::ffi::c_void
-        };
+        let offset = (current_frame_index * VULKAN_OBJECT_SHADERS_MAX_VIEWS + view_index) as u64
+            * object_shaders.global_ubo_stride;
 
-        let object_shaders = &self.get_builtin_shaders()?.object_shaders;
+        // Copy data to buffer, the uniform object is only read
+        let data = &object_shaders.global_ubo as *const RendererGlobalUniformObject
+            as *mut std::ffi::c_void;
         if let Err(err) = self.load_data_into_buffer(
             &object_shaders.global_uniform_buffer,
             offset,
@@ -497,35 +1023,96 @@ impl VulkanRendererBackend<'_> {
             return Err(EngineError::ShutdownFailed);
         }
 
-        let descriptor_buffer_info = [DescriptorBufferInfo::default()
-            .buffer(object_shaders.global_uniform_buffer.buffer)
-            .offset(offset)
-            .range(range as u64)];
-
-        // Update descriptor sets
-        let descriptor_writes = [WriteDescriptorSet::default()
-            .dst_set(object_shaders.global_descriptor_sets[current_frame_index])
-            .dst_binding(0)
-            .dst_array_element(0)
-            .descriptor_type(DescriptorType::UNIFORM_BUFFER)
-            .descriptor_count(1)
-            .buffer_info(&descriptor_buffer_info)];
-        let descriptor_copies = [];
+        let global_descriptor_set = *object_shaders
+            .global_descriptor_sets
+            .get(current_frame_index);
+        // The set can't change once the first view of the frame bound it, the views only move the offset
+        if view_index == 0 {
+            // The probes may have been replaced since the set of this frame was last written
+            let reflection_probes = match self.context.resources.reflection_probes.as_ref() {
+                Some(reflection_probes) => reflection_probes,
+                None => {
+                    error!("The vulkan object shaders are updated without the reflection probes");
+                    return Err(EngineError::NotInitialized);
+                }
+            };
+            let descriptor_image_info = [DescriptorImageInfo::default()
+                .image_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .image_view(reflection_probes.image.get_image_view()?)
+                .sampler(reflection_probes.sampler)];
+            let descriptor_buffer_info = [DescriptorBufferInfo::default()
+                .buffer(object_shaders.global_uniform_buffer.buffer)
+                .offset(0)
+                .range(range as u64)];
+            let descriptor_writes = [
+                WriteDescriptorSet::default()
+                    .dst_set(global_descriptor_set)
+                    .dst_binding(0)
+                    .dst_array_element(0)
+                    .descriptor_type(DescriptorType::UNIFORM_BUFFER_DYNAMIC)
+                    .descriptor_count(1)
+                    .buffer_info(&descriptor_buffer_info),
+                WriteDescriptorSet::default()
+                    .dst_set(global_descriptor_set)
+                    .dst_binding(VULKAN_OBJECT_SHADERS_REFLECTION_PROBES_BINDING)
+                    .dst_array_element(0)
+                    .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .descriptor_count(1)
+                    .image_info(&descriptor_image_info),
+            ];
+            let descriptor_copies = [];
+            let device = self.get_device()?;
+            unsafe { device.update_descriptor_sets(&descriptor_writes, &descriptor_copies) };
+        }
 
         let device = self.get_device()?;
-        unsafe { device.update_descriptor_sets(&descriptor_writes, &descriptor_copies) };
+        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
+        unsafe {
+            let offsets = [offset as u32];
+            device.cmd_bind_descriptor_sets(
+                *command_buffer.handler.as_ref(),
+                PipelineBindPoint::GRAPHICS,
+                object_shaders.pipeline.layout,
+                0,
+                &[global_descriptor_set],
+                &offsets,
+            );
+        }
+        self.get_object_shaders_mut()?.global_ubo_count = view_index + 1;
+
+        Ok(())
+    }
 
+    /// Forget the descriptors written before the swapchain or the renderpass was recreated
+    fn object_shader_check_resource_epoch(&mut self, object_id: usize) -> Result<(), EngineError> {
+        let resource_epoch = self.context.frame.resource_epoch;
+        let state = self
+            .get_object_shaders_mut()?
+            .get_object_state_mut(object_id)?;
+        if state.resource_epoch != resource_epoch {
+            state.reset_descriptor_states(resource_epoch);
+        }
         Ok(())
     }
 
-    pub fn update_object_shaders(&mut self, data: &GeometryRenderData) -> Result<(), EngineError> {
-        let current_frame_index = self.context.current_frame as usize;
+    /// The model is pushed for the vertex shader, it is the identity for the instanced draws
+    pub fn update_object_shaders(
+        &mut self,
+        data: &GeometryRenderData,
+        model: &glam::Mat4,
+        textures: &TextureSystem,
+    ) -> Result<(), EngineError> {
+        if let Some(object_id) = data.object_id {
+            self.object_shader_check_resource_epoch(object_id as usize)?;
+        }
+        let offset = self.object_shaders_acquire_uniform_slot()?;
+        let current_frame_index = self.get_current_frame_index();
         let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
         let device = self.get_device()?;
-        let object_shaders = &self.get_builtin_shaders()?.object_shaders;
+        let object_shaders = self.get_object_shaders()?;
 
         // Convert glam::Mat4 into &[u8]
-        let ptr: *const glam::Mat4 = &data.model;
+        let ptr: *const glam::Mat4 = model;
         // Convert the raw pointer to a raw pointer to u8
         let byte_ptr: *const u8 = ptr as *const u8;
         // Calculate the length of the byte slice (Mat4 is 16 floats, each 4 bytes)
@@ -552,36 +1139,80 @@ impl VulkanRendererBackend<'_> {
             }
         };
 
-        let state: &ObjectShadersPerObjectState = match object_shaders.object_states.get(object_id)
-        {
-            Some(_) => &object_shaders.object_states[object_id],
-            None => {
-                error!("The state does not exist");
-                return Err(EngineError::InvalidValue);
+        // Material textures, the default one replaces the missing ones
+        let mut slot_textures = Vec::with_capacity(MATERIAL_TEXTURE_SLOT_COUNT);
+        for slot in MaterialTextureSlot::ALL {
+            slot_textures.push(Self::object_shaders_get_slot_texture(data, slot, textures)?);
+        }
+        // Index of the textures in the bindless array, in the order of the slots
+        let mut texture_indices = glam::UVec4::ZERO;
+        if object_shaders.is_bindless {
+            for (slot, texture) in MaterialTextureSlot::ALL.iter().zip(&slot_textures) {
+                texture_indices[slot.get_binding() as usize] =
+                    self.bindless_textures_acquire(texture)?;
             }
-        };
-
-        let object_descriptor_set = state.descriptor_sets[current_frame_index];
-
-        // TODO: if needs update
-        let mut write_descriptors: Vec<WriteDescriptorSet> = Vec::new();
-
-        // Descriptor 0 - Uniform buffer
-        let range = size_of::<RendererPerObjectUniformObject>();
-        let offset = (size_of::<RendererPerObjectUniformObject>() * object_id) as u64; // also the index into the array.
+        }
 
-        // TODO: get diffuse colour from a material
-        let diffuse = glam::Vec4::new(1.0, 1.0, 1.0, 1.0);
+        let diffuse = data
+            .material
+            .as_ref()
+            .map_or(glam::Vec4::ONE, |material| material.diffuse_color)
+            * glam::Vec4::new(1.0, 1.0, 1.0, data.fade);
+        // The shaders ignore the samplers of the missing maps
+        let has_map = |slot: MaterialTextureSlot| {
+            let has_texture = data
+                .material
+                .as_ref()
+                .is_some_and(|material| material.get_texture(slot).is_some());
+            if has_texture {
+                1.0
+            } else {
+                0.0
+            }
+        };
+        let lightmap = glam::Vec4::new(has_map(MaterialTextureSlot::Lightmap), 0.0, 0.0, 0.0);
+        let normal_map = glam::Vec4::new(has_map(MaterialTextureSlot::Normal), 0.0, 0.0, 0.0);
+        let (specular_strength, shininess) = data.material.as_ref().map_or(
+            (
+                MATERIAL_DEFAULT_SPECULAR_STRENGTH,
+                MATERIAL_DEFAULT_SHININESS,
+            ),
+            |material| (material.specular_strength, material.shininess),
+        );
+        let specular = glam::Vec4::new(
+            specular_strength,
+            shininess,
+            has_map(MaterialTextureSlot::Specular),
+            0.0,
+        );
+        let reflection = match (
+            data.reflection_probe,
+            &self.context.resources.reflection_probes,
+        ) {
+            (Some(probe), Some(reflection_probes)) => {
+                glam::Vec4::new(probe as f32, 1.0, reflection_probes.level_count as f32, 0.0)
+            }
+            _ => glam::Vec4::ZERO,
+        };
 
         // buffer
-        let mut object_uniform_buffer = RendererPerObjectUniformObject::default().diffuse(diffuse);
+        let mut object_uniform_buffer = RendererPerObjectUniformObject::default()
+            .diffuse(diffuse)
+            .lightmap(lightmap)
+            .specular(specular)
+            .normal_map(normal_map)
+            .texture_indices(texture_indices)
+            .reflection(reflection);
         let object_uniform_buffer = &mut object_uniform_buffer
             as *mut RendererPerObjectUniformObject
             as *mut std::ffi::c_void;
 
-        // Load the data into the buffer
+        // Uniform buffer of the frame, selected through a dynamic offset when binding
+        let range = size_of::<RendererPerObjectUniformObject>();
         if let Err(err) = self.load_data_into_buffer(
-            &object_shaders.per_object_uniform_buffer,
+            object_shaders
+                .per_object_uniform_buffers
+                .get(current_frame_index),
             offset,
             range,
             MemoryMapFlags::empty(),
@@ -594,167 +1225,26 @@ impl VulkanRendererBackend<'_> {
             return Err(EngineError::Unknown);
         }
 
-        // Only do this if the descriptor has not yet been updated
-        let mut descriptor_index = 0;
-        let mut should_update_descriptor_sets = false;
-
-        let descriptor_buffer_info_tmp = [DescriptorBufferInfo::default()
-            .buffer(object_shaders.per_object_uniform_buffer.buffer)
-            .offset(offset)
-            .range(range as u64)];
-        if state.descriptor_states[descriptor_index].generations[current_frame_index].is_none() {
-            let descriptor = WriteDescriptorSet::default()
-                .dst_set(object_descriptor_set)
-                .dst_binding(descriptor_index as u32)
-                .descriptor_type(DescriptorType::UNIFORM_BUFFER)
-                .descriptor_count(1)
-                .buffer_info(&descriptor_buffer_info_tmp);
-            write_descriptors.push(descriptor);
-            should_update_descriptor_sets = true;
-
-            // Update the frame generation. In this case it is only needed once since this is a buffer
-            let object_shaders = &mut self
-                .context
-                .builtin_shaders
-                .as_mut()
-                .unwrap()
-                .object_shaders;
-            let state: &mut ObjectShadersPerObjectState =
-                match object_shaders.object_states.get(object_id) {
-                    Some(_) => &mut object_shaders.object_states[object_id],
-                    None => {
-                        error!("The state does not exist");
-                        return Err(EngineError::InvalidValue);
-                    }
-                };
-            state.descriptor_states[descriptor_index].generations[current_frame_index] = Some(1);
-        }
-        descriptor_index += 1;
-
-        // TODO: other samplers
-        let sampler_count = 1; // only one texture for now
-        let mut descriptor_image_info_tmp: Vec<(
-                [DescriptorImageInfo; 1], // descriptor_image_info
-                u32,                      // descriptor_index,
-            )> = Vec::new()
-        ;
-        for sampler_index in 0..sampler_count {
-            // for sampler_index in 0..sampler_count {
-            let object_shaders = &self.get_builtin_shaders()?.object_shaders;
-            let state: &ObjectShadersPerObjectState =
-                match object_shaders.object_states.get(object_id) {
-                    Some(_) => &object_shaders.object_states[object_id],
-                    None => {
-                        error!("The state does not exist");
-                        return Err(EngineError::InvalidValue);
-                    }
-                };
-            let texture = &data.textures[sampler_index];
-            let generation =
-                state.descriptor_states[descriptor_index].generations[current_frame_index];
-
-            if let Some(texture) = texture {
-                // If the texture hasn't been loaded yet, use the default
-                // TODO: Determine which use the texture has and pull appropriate default based on that
-                let (texture, is_default_texture) = if texture.get_generation().is_none() {
-                    // Reset the descriptor generation if using the default texture
-                    let object_shaders = &mut self
-                        .context
-                        .builtin_shaders
-                        .as_mut()
-                        .unwrap()
-                        .object_shaders;
-                    let state: &mut ObjectShadersPerObjectState =
-                        match object_shaders.object_states.get(object_id) {
-                            Some(_) => &mut object_shaders.object_states[object_id],
-                            None => {
-                                error!("The state does not exist");
-                                return Err(EngineError::InvalidValue);
-                            }
-                        };
-                    state.descriptor_states[descriptor_index].generations[current_frame_index] =
-                        None;
-                    (
-                        match renderer_get_default_texture() {
-                            Ok(texture) => texture,
-                            Err(err) => {
-                                error!("Failed to fetch the default texture when updating the object shaders: {:?}", err);
-                                return Err(EngineError::AccessFailed);
-                            }
-                        },
-                        true,
-                    )
-                } else {
-                    (texture.as_ref(), false)
-                };
-                // Check if the descriptor needs updating first
-                if texture.get_generation() != generation || is_default_texture {
-                    let vulkan_texture = match texture.as_any().downcast_ref::<Texture>() {
-                        Some(texture) => texture,
-                        None => {
-                            error!("Failed to downcast a texture to a vulkan texture");
-                            return Err(EngineError::InvalidValue);
-                        }
-                    };
-
-                    // assign view and sampler
-                    let descriptor_image_info = DescriptorImageInfo::default()
-                        .image_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL)
-                        .image_view(vulkan_texture.image.image_view.unwrap())
-                        .sampler(vulkan_texture.sampler);
-
-                    descriptor_image_info_tmp.push(
-                        (
-                            [descriptor_image_info], 
-                            descriptor_index as u32
-                        )
-                    );
-
-                    should_update_descriptor_sets = true;
-                    
-                    // Sync frame generation if not using a default texture
-                    if texture.get_generation().is_some() {
-                        let object_shaders = &mut self
-                            .context
-                            .builtin_shaders
-                            .as_mut()
-                            .unwrap()
-                            .object_shaders;
-                        let state: &mut ObjectShadersPerObjectState =
-                            match object_shaders.object_states.get(object_id) {
-                                Some(_) => &mut object_shaders.object_states[object_id],
-                                None => {
-                                    error!("The state does not exist");
-                                    return Err(EngineError::InvalidValue);
-                                }
-                            };
-                        state.descriptor_states[descriptor_index].generations
-                            [current_frame_index] = texture.get_generation();
-                    }
-                    descriptor_index += 1;
-                }
-            }
-        }
-        for (descriptor_image_info, descriptor_index) in &descriptor_image_info_tmp {
-            let descriptor = WriteDescriptorSet::default()
-                .dst_set(object_descriptor_set)
-                .dst_binding(*descriptor_index)
-                .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
-                .descriptor_count(1)
-                .image_info(descriptor_image_info);
-            write_descriptors.push(descriptor);
-        }
+        // The bindless array replaces the sampler set of the object
+        let bindless_descriptor_set = self
+            .get_bindless_textures()
+            .filter(|_| object_shaders.is_bindless)
+            .map(|bindless_textures| bindless_textures.descriptor_set);
+        let object_descriptor_set = match bindless_descriptor_set {
+            Some(descriptor_set) => descriptor_set,
+            None => self.object_shaders_update_samplers(object_id, &slot_textures)?,
+        };
 
+        // Bind the shared uniform set at the object's offset and the object's sampler set
         let device = self.get_device()?;
-        if should_update_descriptor_sets {
-            unsafe {
-                device.update_descriptor_sets(&write_descriptors, &[]);
-            }
-        }
-
-        // Bind the descriptor set to be updated, or in case the shader changed
-        let sets = [object_descriptor_set];
-        let object_shaders = &self.get_builtin_shaders()?.object_shaders;
+        let object_shaders = self.get_object_shaders()?;
+        let sets = [
+            *object_shaders
+                .per_object_ubo_descriptor_sets
+                .get(current_frame_index),
+            object_descriptor_set,
+        ];
+        let dynamic_offsets = [offset as u32];
         let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
         unsafe {
             device.cmd_bind_descriptor_sets(
@@ -763,47 +1253,303 @@ impl VulkanRendererBackend<'_> {
                 object_shaders.pipeline.layout,
                 1,
                 &sets,
-                &[],
+                &dynamic_offsets,
             );
         }
 
         Ok(())
     }
 
-    /// Returns the object id of the new resource
-    pub fn object_shader_acquire_resources(&mut self) -> Result<u32, EngineError> {
-        // TODO: free list
-        let object_shaders = &self.get_builtin_shaders()?.object_shaders;
-        let object_id = object_shaders.object_uniform_buffer_index;
-        let object_shaders = &mut self
-            .context
-            .builtin_shaders
-            .as_mut()
-            .unwrap()
-            .object_shaders;
-        object_shaders.object_uniform_buffer_index += 1;
-
-        let state: &mut ObjectShadersPerObjectState =
-            match object_shaders.object_states.get(object_id as usize) {
-                Some(_) => &mut object_shaders.object_states[object_id as usize],
+    /// Texture drawn in a material slot
+    /// If there is no texture or if it hasn't been loaded yet, the default is used
+    fn object_shaders_get_slot_texture<'t>(
+        data: &GeometryRenderData,
+        slot: MaterialTextureSlot,
+        textures: &'t TextureSystem,
+    ) -> Result<&'t Texture, EngineError> {
+        let texture = data
+            .material
+            .as_ref()
+            .and_then(|material| material.get_texture(slot))
+            .and_then(|handle| textures.get(handle));
+        // TODO: Determine which use the texture has and pull appropriate default based on that
+        let texture = match texture {
+            Some(texture) if texture.get_generation().is_some() => texture,
+            _ => match textures.get_default_texture() {
+                Some(texture) => texture,
                 None => {
-                    error!("The state does not exist");
-                    return Err(EngineError::InvalidValue);
+                    error!("Failed to fetch the default texture when updating the object shaders");
+                    return Err(EngineError::AccessFailed);
+                }
+            },
+        };
+        match texture.as_any().downcast_ref::<Texture>() {
+            Some(texture) => Ok(texture),
+            None => {
+                error!("Failed to downcast a texture to a vulkan texture");
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    /// Write the textures that changed in the sampler set of the object for the current frame
+    /// The set is written at most once per frame, the object can be drawn by several views
+    fn object_shaders_update_samplers(
+        &mut self,
+        object_id: usize,
+        textures: &[&Texture],
+    ) -> Result<DescriptorSet, EngineError> {
+        let current_frame_index = self.get_current_frame_index();
+        let frame_number = self.frame_number;
+        let state = self.get_object_shaders()?.get_object_state(object_id)?;
+        let object_descriptor_set = *state.descriptor_sets.get(current_frame_index);
+        if *state.written_frame_numbers.get(current_frame_index) == Some(frame_number) {
+            return Ok(object_descriptor_set);
+        }
+
+        // One sampler per material texture slot
+        let mut descriptor_image_info_tmp: Vec<(
+            [DescriptorImageInfo; 1], // descriptor_image_info
+            u32,                      // descriptor_index,
+        )> = Vec::new();
+        // New (generation, id) of the changed descriptors, applied once the textures are read
+        let mut descriptor_state_updates: Vec<(usize, Option<u32>, Option<u32>)> = Vec::new();
+        for (slot, texture) in MaterialTextureSlot::ALL.iter().zip(textures) {
+            let descriptor_index = slot.get_binding() as usize;
+            let generation = *state.descriptor_states[descriptor_index]
+                .generations
+                .get(current_frame_index);
+            let id = *state.descriptor_states[descriptor_index]
+                .ids
+                .get(current_frame_index);
+
+            // Check if the descriptor needs updating first, the default texture has no generation
+            if texture.generation != generation || Some(texture.id) != id {
+                // assign view and sampler
+                let descriptor_image_info = DescriptorImageInfo::default()
+                    .image_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                    .image_view(texture.image.get_image_view()?)
+                    .sampler(texture.sampler);
+
+                descriptor_image_info_tmp.push(([descriptor_image_info], descriptor_index as u32));
+                descriptor_state_updates.push((
+                    descriptor_index,
+                    texture.generation,
+                    Some(texture.id),
+                ));
+            }
+        }
+        let write_descriptors: Vec<WriteDescriptorSet> = descriptor_image_info_tmp
+            .iter()
+            .map(|(descriptor_image_info, descriptor_index)| {
+                WriteDescriptorSet::default()
+                    .dst_set(object_descriptor_set)
+                    .dst_binding(*descriptor_index)
+                    .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+                    .descriptor_count(1)
+                    .image_info(descriptor_image_info)
+            })
+            .collect();
+
+        // Only do this if the descriptor has not yet been updated
+        if !write_descriptors.is_empty() {
+            unsafe {
+                self.get_device()?
+                    .update_descriptor_sets(&write_descriptors, &[]);
+            }
+        }
+
+        // Remember which textures the sets point to
+        let state = self
+            .get_object_shaders_mut()?
+            .get_object_state_mut(object_id)?;
+        state
+            .written_frame_numbers
+            .set(current_frame_index, Some(frame_number));
+        if !descriptor_state_updates.is_empty() {
+            for (descriptor_index, generation, id) in descriptor_state_updates {
+                let descriptor_state = &mut state.descriptor_states[descriptor_index];
+                descriptor_state
+                    .generations
+                    .set(current_frame_index, generation);
+                descriptor_state.ids.set(current_frame_index, id);
+            }
+        }
+
+        Ok(object_descriptor_set)
+    }
+
+    /// Start the instances of the current frame with the identity of the objects drawn alone
+    /// The previous use of the frame must be done
+    pub fn object_shaders_reset_instances(&mut self) -> Result<(), EngineError> {
+        let current_frame_index = self.get_current_frame_index();
+        let object_shaders = self.get_object_shaders()?;
+        let instance_buffer = object_shaders.instance_buffers.get(current_frame_index);
+        let mut identity = glam::Mat4::IDENTITY;
+        if let Err(err) = self.load_data_into_buffer(
+            instance_buffer,
+            0,
+            size_of::<glam::Mat4>(),
+            MemoryMapFlags::empty(),
+            &mut identity as *mut glam::Mat4 as *mut std::ffi::c_void,
+        ) {
+            error!(
+                "Failed to reset the instances of the vulkan object shaders: {:?}",
+                err
+            );
+            return Err(EngineError::UpdateFailed);
+        }
+        self.get_object_shaders_mut()?.instance_count = 1;
+        Ok(())
+    }
+
+    /// Returns the index of the first instance written, none when the buffer of the frame is full
+    pub fn object_shaders_push_instances(
+        &mut self,
+        models: &[glam::Mat4],
+    ) -> Result<Option<u32>, EngineError> {
+        let current_frame_index = self.get_current_frame_index();
+        let object_shaders = self.get_object_shaders()?;
+        let first_instance = object_shaders.instance_count;
+        if first_instance + models.len() > VULKAN_OBJECT_SHADERS_MAX_INSTANCES {
+            return Ok(None);
+        }
+        let instance_buffer = object_shaders.instance_buffers.get(current_frame_index);
+        if let Err(err) = self.load_data_into_buffer(
+            instance_buffer,
+            (first_instance * size_of::<glam::Mat4>()) as u64,
+            std::mem::size_of_val(models),
+            MemoryMapFlags::empty(),
+            models.as_ptr() as *mut std::ffi::c_void,
+        ) {
+            error!(
+                "Failed to upload the instances of the vulkan object shaders: {:?}",
+                err
+            );
+            return Err(EngineError::UpdateFailed);
+        }
+        self.get_object_shaders_mut()?.instance_count += models.len();
+        Ok(Some(first_instance as u32))
+    }
+
+    /// Offset of the next uniform slot of the frame, the buffer of the frame is replaced by a bigger one when full
+    /// The draws recorded so far still use the previous buffer and set, they are freed once the frame is done
+    fn object_shaders_acquire_uniform_slot(&mut self) -> Result<u64, EngineError> {
+        let current_frame_index = self.get_current_frame_index();
+        let object_shaders = self.get_object_shaders()?;
+        let stride = object_shaders.per_object_ubo_stride;
+        let capacity = object_shaders
+            .per_object_uniform_buffers
+            .get(current_frame_index)
+            .total_size
+            / stride as usize;
+        let mut slot = object_shaders.per_object_ubo_count;
+        if slot >= capacity {
+            let (buffer, descriptor_set) = match ObjectShaders::create_per_object_uniforms(
+                self,
+                object_shaders.per_object_ubo_descriptor_pool,
+                object_shaders.per_object_ubo_descriptor_set_layout,
+                stride,
+                capacity * 2,
+            ) {
+                Ok(uniforms) => uniforms,
+                Err(err) => {
+                    error!("Failed to grow the per object uniform buffer of the vulkan object shaders: {:?}", err);
+                    return Err(EngineError::UpdateFailed);
                 }
             };
-        for i in 0..VULKAN_OBJECT_SHADERS_PER_OBJECT_DESCRIPTOR_COUNT {
-            for j in 0..RENDERER_MAX_IN_FLIGHT_FRAMES {
-                state.descriptor_states[i].generations[j] = None;
+            let object_shaders = self.get_object_shaders_mut()?;
+            let old_buffer = std::mem::replace(
+                object_shaders
+                    .per_object_uniform_buffers
+                    .get_mut(current_frame_index),
+                buffer,
+            );
+            let old_descriptor_set = std::mem::replace(
+                object_shaders
+                    .per_object_ubo_descriptor_sets
+                    .get_mut(current_frame_index),
+                descriptor_set,
+            );
+            object_shaders
+                .retired_per_object_uniforms
+                .get_mut(current_frame_index)
+                .push((old_buffer, old_descriptor_set));
+            slot = 0;
+        }
+        self.get_object_shaders_mut()?.per_object_ubo_count = slot + 1;
+        Ok(slot as u64 * stride)
+    }
+
+    /// Start writing the uniforms of the current frame from the first slot and free the buffers it replaced
+    /// The previous use of the frame must be done
+    pub fn object_shaders_reset_uniforms(&mut self) -> Result<(), EngineError> {
+        let current_frame_index = self.get_current_frame_index();
+        let object_shaders = self.get_object_shaders_mut()?;
+        object_shaders.per_object_ubo_count = 0;
+        object_shaders.global_ubo_count = 0;
+        let retired = std::mem::take(
+            object_shaders
+                .retired_per_object_uniforms
+                .get_mut(current_frame_index),
+        );
+        let descriptor_pool = object_shaders.per_object_ubo_descriptor_pool;
+        let device = self.get_device()?;
+        for (buffer, descriptor_set) in retired {
+            if let Err(err) =
+                unsafe { device.free_descriptor_sets(descriptor_pool, &[descriptor_set]) }
+            {
+                error!("Failed to free a replaced per object uniform set of the vulkan object shaders: {:?}", err);
+                return Err(EngineError::ShutdownFailed);
             }
+            self.destroy_buffer(&buffer)?;
         }
+        Ok(())
+    }
 
-        // Allocate descriptor sets
-        let layouts =
-            [object_shaders.per_object_descriptor_set_layout; RENDERER_MAX_IN_FLIGHT_FRAMES];
-        let allocate_info = DescriptorSetAllocateInfo::default()
-            .descriptor_pool(object_shaders.per_object_descriptor_pool)
-            .set_layouts(&layouts);
+    /// Allocate the sampler sets of an object from the first pool with room left,
+    /// a new pool is created when they are all full
+    fn object_shader_allocate_descriptor_sets(
+        &mut self,
+    ) -> Result<(PerFrameResource<DescriptorSet>, usize), EngineError> {
         let device = self.get_device()?;
+        let object_shaders = self.get_object_shaders()?;
+        let layouts = PerFrameResource::splat(object_shaders.per_object_descriptor_set_layout);
+        for (pool_index, pool) in object_shaders
+            .per_object_descriptor_pools
+            .iter()
+            .enumerate()
+        {
+            let allocate_info = DescriptorSetAllocateInfo::default()
+                .descriptor_pool(*pool)
+                .set_layouts(layouts.as_slice());
+            match unsafe { device.allocate_descriptor_sets(&allocate_info) } {
+                Ok(descriptor_sets) => {
+                    let descriptor_sets = match PerFrameResource::from_vec(descriptor_sets) {
+                        Ok(descriptor_sets) => descriptor_sets,
+                        Err(err) => {
+                            error!(
+                                "The descriptor doesn't have the required number of elements: {:?}",
+                                err
+                            );
+                            return Err(EngineError::InvalidValue);
+                        }
+                    };
+                    return Ok((descriptor_sets, pool_index));
+                }
+                Err(ash::vk::Result::ERROR_OUT_OF_POOL_MEMORY)
+                | Err(ash::vk::Result::ERROR_FRAGMENTED_POOL) => continue,
+                Err(err) => {
+                    error!("Failed to allocate descriptor set: {:?}", err);
+                    return Err(EngineError::VulkanFailed);
+                }
+            }
+        }
+
+        let pool = ObjectShaders::create_per_object_descriptor_pool(device, self.get_allocator()?)?;
+        let allocate_info = DescriptorSetAllocateInfo::default()
+            .descriptor_pool(pool)
+            .set_layouts(layouts.as_slice());
         let descriptor_sets = unsafe {
             match device.allocate_descriptor_sets(&allocate_info) {
                 Ok(set) => set,
@@ -813,82 +1559,86 @@ impl VulkanRendererBackend<'_> {
                 }
             }
         };
+        let descriptor_sets = PerFrameResource::from_vec(descriptor_sets)?;
+        let pools = &mut self.get_object_shaders_mut()?.per_object_descriptor_pools;
+        pools.push(pool);
+        Ok((descriptor_sets, pools.len() - 1))
+    }
 
-        if descriptor_sets.len() != RENDERER_MAX_IN_FLIGHT_FRAMES {
-            error!("The descriptor doesn't have the required number of elements");
-            return Err(EngineError::InvalidValue);
+    /// Returns the object id of the new resource
+    pub fn object_shader_acquire_resources(&mut self) -> Result<u32, EngineError> {
+        let object_shaders = self.get_object_shaders_mut()?;
+        let object_id = match object_shaders.free_object_ids.pop() {
+            Some(object_id) => object_id,
+            None => {
+                let object_id = object_shaders.next_object_id;
+                object_shaders.next_object_id += 1;
+                object_shaders
+                    .object_states
+                    .push(ObjectShadersPerObjectState::default());
+                object_id
+            }
+        };
+
+        // The bindless objects only need an id for their uniforms and queries
+        if self.get_object_shaders()?.is_bindless {
+            return Ok(object_id);
         }
-        let object_shaders = &mut self
-            .context
-            .builtin_shaders
-            .as_mut()
-            .unwrap()
-            .object_shaders;
-        let state: &mut ObjectShadersPerObjectState =
-            match object_shaders.object_states.get(object_id as usize) {
-                Some(_) => &mut object_shaders.object_states[object_id as usize],
-                None => {
-                    error!("The state does not exist");
-                    return Err(EngineError::InvalidValue);
+
+        let (descriptor_sets, descriptor_pool_index) =
+            match self.object_shader_allocate_descriptor_sets() {
+                Ok(resources) => resources,
+                Err(err) => {
+                    // The slot can still be used by the next object
+                    self.get_object_shaders_mut()?
+                        .free_object_ids
+                        .push(object_id);
+                    return Err(err);
                 }
             };
-        state.descriptor_sets[..RENDERER_MAX_IN_FLIGHT_FRAMES]
-            .copy_from_slice(&descriptor_sets[..RENDERER_MAX_IN_FLIGHT_FRAMES]);
+
+        let resource_epoch = self.context.frame.resource_epoch;
+        let state = self
+            .get_object_shaders_mut()?
+            .get_object_state_mut(object_id as usize)?;
+        state.reset_descriptor_states(resource_epoch);
+        state.descriptor_sets = descriptor_sets;
+        state.descriptor_pool_index = descriptor_pool_index;
 
         Ok(object_id)
     }
 
     pub fn object_shader_release_resources(&mut self, object_id: u32) -> Result<(), EngineError> {
-        let object_shaders = &self
-            .context
-            .builtin_shaders
-            .as_ref()
-            .unwrap()
-            .object_shaders;
-        let state = match object_shaders.object_states.get(object_id as usize) {
-            Some(_) => &object_shaders.object_states[object_id as usize],
-            None => {
-                error!("The state does not exist");
-                return Err(EngineError::InvalidValue);
-            }
-        };
+        let object_shaders = self.get_object_shaders()?;
+        if object_id >= object_shaders.next_object_id
+            || object_shaders.free_object_ids.contains(&object_id)
+        {
+            error!("Can't release the object {}, it is not acquired", object_id);
+            return Err(EngineError::InvalidValue);
+        }
+        let state = object_shaders.get_object_state(object_id as usize)?;
 
         // Release object descriptor sets
         let device = self.get_device()?;
-        unsafe {
-            if let Err(err) = device.free_descriptor_sets(
-                object_shaders.per_object_descriptor_pool,
-                &state.descriptor_sets,
-            ) {
-                error!(
-                    "Failed to destroy descriptor sets of the current object: {:?}",
-                    err
-                );
-                return Err(EngineError::ShutdownFailed);
-            }
-        }
-
-        let object_shaders = &mut self
-            .context
-            .builtin_shaders
-            .as_mut()
-            .unwrap()
-            .object_shaders;
-        let state: &mut ObjectShadersPerObjectState =
-            match object_shaders.object_states.get(object_id as usize) {
-                Some(_) => &mut object_shaders.object_states[object_id as usize],
-                None => {
-                    error!("The state does not exist");
-                    return Err(EngineError::InvalidValue);
+        if !object_shaders.is_bindless {
+            unsafe {
+                if let Err(err) = device.free_descriptor_sets(
+                    object_shaders.per_object_descriptor_pools[state.descriptor_pool_index],
+                    state.descriptor_sets.as_slice(),
+                ) {
+                    error!(
+                        "Failed to destroy descriptor sets of the current object: {:?}",
+                        err
+                    );
+                    return Err(EngineError::ShutdownFailed);
                 }
-            };
-        for i in 0..VULKAN_OBJECT_SHADERS_PER_OBJECT_DESCRIPTOR_COUNT {
-            for j in 0..RENDERER_MAX_IN_FLIGHT_FRAMES {
-                state.descriptor_states[i].generations[j] = None;
             }
         }
-        Ok(())
 
-        // TODO: add the object_id to the free list
+        let object_shaders = self.get_object_shaders_mut()?;
+        *object_shaders.get_object_state_mut(object_id as usize)? =
+            ObjectShadersPerObjectState::default();
+        object_shaders.free_object_ids.push(object_id);
+        Ok(())
     }
 }
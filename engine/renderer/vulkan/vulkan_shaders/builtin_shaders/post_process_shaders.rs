@@ -0,0 +1,762 @@
+use ash::{
+    vk::{
+        BorderColor, CompareOp, CullModeFlags, DescriptorImageInfo, DescriptorPool,
+        DescriptorPoolCreateInfo, DescriptorPoolResetFlags, DescriptorPoolSize,
+        DescriptorSetAllocateInfo, DescriptorSetLayout, DescriptorSetLayoutCreateInfo,
+        DescriptorType, Extent2D, Filter, ImageLayout, ImageView, Offset2D, PipelineBindPoint,
+        PipelineShaderStageCreateInfo, Rect2D, Sampler, SamplerAddressMode, SamplerCreateInfo,
+        SamplerMipmapMode, ShaderStageFlags, Viewport, WriteDescriptorSet,
+    },
+    Device,
+};
+
+use crate::{
+    core::debug::errors::EngineError,
+    error,
+    renderer::{
+        post_process::{PostProcessSettings, TonemapOperator},
+        renderer_types::{OutputColorSpace, RENDERER_MAX_IN_FLIGHT_FRAMES},
+        vulkan::{
+            vulkan_init::renderpass::{
+                Renderpass, VULKAN_RENDERPASS_POST_PROCESS_SUBPASS, VULKAN_RENDERPASS_UI_SUBPASS,
+            },
+            vulkan_shaders::{
+                builtin_shaders::object_shaders::VULKAN_OBJECT_SHADERS_MAX_VIEWS,
+                hot_reload::ShaderSource,
+                reflection::{
+                    reflection_get_descriptor_set_layout_bindings,
+                    reflection_get_push_constant_ranges,
+                },
+                shader::Shader,
+            },
+            vulkan_types::VulkanRendererBackend,
+            vulkan_utils::{
+                per_frame_resource::PerFrameResource,
+                pipeline::{Pipeline, PipelineCreateInfo},
+            },
+        },
+    },
+    resources::material::BlendMode,
+};
+
+/// Fullscreen passes of the post processing chain, in the order they are applied
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum PostProcessEffect {
+    /// Accessibility filter of the color vision deficiencies
+    ColorFilter,
+    Tonemap,
+    Fxaa,
+    Vignette,
+    /// Only used when every other pass is disabled, the world still has to reach the swapchain image
+    Copy,
+}
+
+impl PostProcessEffect {
+    const ALL: [Self; 5] = [
+        Self::ColorFilter,
+        Self::Tonemap,
+        Self::Fxaa,
+        Self::Vignette,
+        Self::Copy,
+    ];
+
+    fn get_source_path(&self) -> &'static str {
+        match self {
+            Self::ColorFilter => "builtin/color_filter.frag.slang",
+            Self::Tonemap => "builtin/tonemap.frag.slang",
+            Self::Fxaa => "builtin/fxaa.frag.slang",
+            Self::Vignette => "builtin/vignette.frag.slang",
+            Self::Copy => "builtin/copy.frag.slang",
+        }
+    }
+}
+
+/// Push constants shared by the fragment stages, each effect gives its own meaning to the parameters
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct PostProcessConstants {
+    pub parameters: glam::Vec4,
+    /// Size of a pixel in texture coordinates
+    pub texel_size: glam::Vec2,
+    pub mode: u32,
+    /// Encoding of the swapchain images, only read by the tonemap pass
+    pub color_space: u32,
+    /// Only read by the color filter pass
+    pub color_filter: glam::Mat4,
+}
+
+/// Index of the color space in the shaders writing in the swapchain images
+pub(crate) fn output_color_space_get_index(color_space: OutputColorSpace) -> u32 {
+    match color_space {
+        OutputColorSpace::Srgb => 0,
+        OutputColorSpace::Hdr10 => 1,
+        OutputColorSpace::ScRgb => 2,
+    }
+}
+
+/// Enabled passes of the settings with their push constants
+/// The hdr outputs are encoded by the tonemap pass, it runs without its curve when disabled
+/// The color filter runs on the linear colors, before the tonemapping
+pub(crate) fn post_process_get_passes(
+    settings: &PostProcessSettings,
+    color_filter: glam::Mat4,
+    output_color_space: OutputColorSpace,
+    texel_size: glam::Vec2,
+) -> Vec<(PostProcessEffect, PostProcessConstants)> {
+    let mut passes = Vec::new();
+    if color_filter != glam::Mat4::IDENTITY {
+        passes.push((
+            PostProcessEffect::ColorFilter,
+            PostProcessConstants {
+                texel_size,
+                color_filter,
+                ..Default::default()
+            },
+        ));
+    }
+    if settings.tonemap.is_enabled || output_color_space != OutputColorSpace::Srgb {
+        let (mode, exposure) = match (settings.tonemap.is_enabled, settings.tonemap.operator) {
+            (false, _) => (2, 1.0),
+            (true, TonemapOperator::Reinhard) => (0, settings.tonemap.exposure),
+            (true, TonemapOperator::Aces) => (1, settings.tonemap.exposure),
+        };
+        passes.push((
+            PostProcessEffect::Tonemap,
+            PostProcessConstants {
+                parameters: glam::Vec4::new(
+                    exposure,
+                    settings.tonemap.hdr_paper_white,
+                    settings.tonemap.hdr_peak_brightness,
+                    0.0,
+                ),
+                texel_size,
+                mode,
+                color_space: output_color_space_get_index(output_color_space),
+                ..Default::default()
+            },
+        ));
+    }
+    if settings.fxaa.is_enabled {
+        passes.push((
+            PostProcessEffect::Fxaa,
+            PostProcessConstants {
+                parameters: glam::Vec4::new(
+                    settings.fxaa.edge_threshold,
+                    settings.fxaa.edge_threshold_min,
+                    settings.fxaa.subpixel_quality,
+                    0.0,
+                ),
+                texel_size,
+                ..Default::default()
+            },
+        ));
+    }
+    if settings.vignette.is_enabled {
+        passes.push((
+            PostProcessEffect::Vignette,
+            PostProcessConstants {
+                parameters: glam::Vec4::new(
+                    settings.vignette.intensity,
+                    settings.vignette.radius,
+                    settings.vignette.smoothness,
+                    0.0,
+                ),
+                texel_size,
+                ..Default::default()
+            },
+        ));
+    }
+    if passes.is_empty() {
+        passes.push((
+            PostProcessEffect::Copy,
+            PostProcessConstants {
+                texel_size,
+                ..Default::default()
+            },
+        ));
+    }
+    passes
+}
+
+/// Fragment stage of a pass and its pipelines
+pub(crate) struct PostProcessEffectShader {
+    pub effect: PostProcessEffect,
+    pub fragment_stage: Shader,
+    /// Source of the stage, used to hot reload it
+    pub fragment_source: ShaderSource,
+    /// Draws into an offscreen attachment, read by the next pass
+    pub pipeline: Pipeline,
+    /// Draws into the swapchain image, used by the last pass of the chain
+    pub present_pipeline: Pipeline,
+}
+
+impl PostProcessEffectShader {
+    fn destroy(
+        &self,
+        device: &Device,
+        backend: &VulkanRendererBackend<'_>,
+    ) -> Result<(), EngineError> {
+        let allocator = backend.get_allocator()?;
+        self.pipeline.destroy(device, allocator)?;
+        self.present_pipeline.destroy(device, allocator)?;
+        self.fragment_stage.destroy(device, allocator)
+    }
+}
+
+/// Fullscreen passes between the world and the ui, each one samples the output of the previous one
+pub(crate) struct PostProcessShaders {
+    /// Fullscreen triangle shared by every pass
+    pub vertex_stage: Shader,
+    pub vertex_source: ShaderSource,
+    pub effects: Vec<PostProcessEffectShader>,
+    /// The output of the previous pass, set 0
+    pub input_descriptor_set_layout: DescriptorSetLayout,
+    /// Reset at the start of every frame, the sets only live for one frame
+    pub input_descriptor_pools: PerFrameResource<DescriptorPool>,
+    /// Linear and clamped to the edges so the neighbours of the border pixels stay inside the image
+    pub sampler: Sampler,
+}
+
+impl PostProcessShaders {
+    fn create_pipeline_info<'a>(
+        backend: &'a VulkanRendererBackend<'a>,
+        renderpass: &'a Renderpass,
+        subpass: u32,
+        vertex_shader: &'a Shader,
+        fragment_shader: &'a Shader,
+        input_descriptor_set_layout: DescriptorSetLayout,
+    ) -> Result<PipelineCreateInfo<'a>, EngineError> {
+        let viewports = vec![Viewport::default()
+            .x(0.)
+            .y(0.)
+            .width(backend.framebuffer_width as f32)
+            .height(backend.framebuffer_height as f32)
+            .min_depth(0.0)
+            .max_depth(1.0)];
+        let scissors = vec![Rect2D::default()
+            .offset(Offset2D { x: 0, y: 0 })
+            .extent(Extent2D {
+                width: backend.framebuffer_width,
+                height: backend.framebuffer_height,
+            })];
+
+        let shader_stages_info = vec![
+            PipelineShaderStageCreateInfo::default()
+                .stage(vertex_shader.stage_flag)
+                .module(vertex_shader.shader_module)
+                .name(vertex_shader.entry_point.as_c_str()),
+            PipelineShaderStageCreateInfo::default()
+                .stage(fragment_shader.stage_flag)
+                .module(fragment_shader.shader_module)
+                .name(fragment_shader.entry_point.as_c_str()),
+        ];
+
+        Ok(PipelineCreateInfo {
+            renderpass,
+            viewports,
+            scissors,
+            is_wireframe: false,
+            // The triangle is generated from the vertex index
+            vertex_input_attributes_description: Vec::new(),
+            vertex_input_bindings_description: Vec::new(),
+            descriptor_set_layouts: vec![input_descriptor_set_layout],
+            push_constant_ranges: reflection_get_push_constant_ranges(&[
+                vertex_shader,
+                fragment_shader,
+            ]),
+            shader_stages_info,
+            stencil_state: None,
+            cull_mode: CullModeFlags::NONE,
+            is_depth_test_enabled: false,
+            is_depth_write_enabled: false,
+            // Every pixel is overwritten
+            blend_mode: BlendMode::Opaque,
+            subpass,
+        })
+    }
+
+    /// The pipeline drawing offscreen and the one drawing into the swapchain image
+    fn create_pipelines(
+        backend: &VulkanRendererBackend<'_>,
+        vertex_shader: &Shader,
+        fragment_shader: &Shader,
+        input_descriptor_set_layout: DescriptorSetLayout,
+    ) -> Result<(Pipeline, Pipeline), EngineError> {
+        let device = backend.get_device()?;
+        let allocator = backend.get_allocator()?;
+        let pipeline = Self::create_pipeline_info(
+            backend,
+            backend.get_post_process_renderpass()?,
+            VULKAN_RENDERPASS_POST_PROCESS_SUBPASS,
+            vertex_shader,
+            fragment_shader,
+            input_descriptor_set_layout,
+        )
+        .and_then(|pipeline_info| Pipeline::create_graphics(device, allocator, pipeline_info))?;
+        let present_pipeline = match Self::create_pipeline_info(
+            backend,
+            backend.get_present_renderpass()?,
+            VULKAN_RENDERPASS_UI_SUBPASS,
+            vertex_shader,
+            fragment_shader,
+            input_descriptor_set_layout,
+        )
+        .and_then(|pipeline_info| Pipeline::create_graphics(device, allocator, pipeline_info))
+        {
+            Ok(present_pipeline) => present_pipeline,
+            Err(err) => {
+                pipeline.destroy(device, allocator)?;
+                return Err(err);
+            }
+        };
+        Ok((pipeline, present_pipeline))
+    }
+
+    fn create_effect(
+        backend: &VulkanRendererBackend<'_>,
+        effect: PostProcessEffect,
+        vertex_stage: &Shader,
+        input_descriptor_set_layout: DescriptorSetLayout,
+    ) -> Result<PostProcessEffectShader, EngineError> {
+        let device = backend.get_device()?;
+        let allocator = backend.get_allocator()?;
+        let fragment_source =
+            ShaderSource::new(effect.get_source_path(), None, ShaderStageFlags::FRAGMENT);
+        let fragment_stage = match Shader::create(
+            device,
+            allocator,
+            fragment_source.stage_flag,
+            fragment_source.path,
+            fragment_source.entry_point,
+        ) {
+            Ok(shader) => shader,
+            Err(err) => {
+                error!(
+                    "Failed to create the {:?} post processing fragment shader: {:?}",
+                    effect, err
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        let (pipeline, present_pipeline) = match Self::create_pipelines(
+            backend,
+            vertex_stage,
+            &fragment_stage,
+            input_descriptor_set_layout,
+        ) {
+            Ok(pipelines) => pipelines,
+            Err(err) => {
+                error!(
+                    "Failed to create the pipelines of the {:?} post processing pass: {:?}",
+                    effect, err
+                );
+                fragment_stage.destroy(device, allocator)?;
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        Ok(PostProcessEffectShader {
+            effect,
+            fragment_stage,
+            fragment_source,
+            pipeline,
+            present_pipeline,
+        })
+    }
+
+    pub fn create(backend: &VulkanRendererBackend<'_>) -> Result<Self, EngineError> {
+        let device = backend.get_device()?;
+        let allocator = backend.get_allocator()?;
+
+        let vertex_source = ShaderSource::new(
+            "builtin/fullscreen.vert.slang",
+            None,
+            ShaderStageFlags::VERTEX,
+        );
+        let vertex_stage = match Shader::create(
+            device,
+            allocator,
+            vertex_source.stage_flag,
+            vertex_source.path,
+            vertex_source.entry_point,
+        ) {
+            Ok(shader) => shader,
+            Err(err) => {
+                error!("Failed to create the fullscreen vertex shader: {:?}", err);
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+
+        // Input descriptors, the copy pass samples the input like every other pass
+        let copy_stage = match Shader::create(
+            device,
+            allocator,
+            ShaderStageFlags::FRAGMENT,
+            PostProcessEffect::Copy.get_source_path(),
+            None,
+        ) {
+            Ok(shader) => shader,
+            Err(err) => {
+                error!("Failed to create the copy fragment shader: {:?}", err);
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        let input_bindings =
+            reflection_get_descriptor_set_layout_bindings(&[&vertex_stage, &copy_stage], 0);
+        copy_stage.destroy(device, allocator)?;
+        let input_bindings = match input_bindings {
+            Ok(bindings) => bindings,
+            Err(err) => {
+                error!(
+                    "Failed to reflect the input bindings of the vulkan post processing shaders: {:?}",
+                    err
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        if input_bindings.len() != 1
+            || input_bindings[0].descriptor_type != DescriptorType::COMBINED_IMAGE_SAMPLER
+        {
+            error!(
+                "The post processing shaders expect a single sampler in the set 0, got {:?}",
+                input_bindings
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+        let input_layout_create_info =
+            DescriptorSetLayoutCreateInfo::default().bindings(&input_bindings);
+        let input_descriptor_set_layout = unsafe {
+            match device.create_descriptor_set_layout(&input_layout_create_info, allocator) {
+                Ok(layout) => layout,
+                Err(err) => {
+                    error!(
+                        "Failed to create the input descriptor layout of the vulkan post processing shaders: {:?}",
+                        err
+                    );
+                    return Err(EngineError::VulkanFailed);
+                }
+            }
+        };
+
+        // Every pass of the chain samples a single input, and so does the pass presenting each camera drawn into a window
+        let max_passes = (PostProcessEffect::ALL.len() + VULKAN_OBJECT_SHADERS_MAX_VIEWS) as u32;
+        let pool_sizes = [DescriptorPoolSize::default()
+            .ty(DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(max_passes)];
+        let pool_create_info = DescriptorPoolCreateInfo::default()
+            .pool_sizes(&pool_sizes)
+            .max_sets(max_passes);
+        let mut input_descriptor_pools = Vec::new();
+        for _ in 0..RENDERER_MAX_IN_FLIGHT_FRAMES {
+            match unsafe { device.create_descriptor_pool(&pool_create_info, allocator) } {
+                Ok(pool) => input_descriptor_pools.push(pool),
+                Err(err) => {
+                    error!(
+                        "Failed to create an input descriptor pool of the vulkan post processing shaders: {:?}",
+                        err
+                    );
+                    return Err(EngineError::VulkanFailed);
+                }
+            }
+        }
+
+        let sampler_create_info = SamplerCreateInfo::default()
+            .mag_filter(Filter::LINEAR)
+            .min_filter(Filter::LINEAR)
+            .address_mode_u(SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(SamplerAddressMode::CLAMP_TO_EDGE)
+            .anisotropy_enable(false)
+            .border_color(BorderColor::INT_OPAQUE_BLACK)
+            .unnormalized_coordinates(false)
+            .compare_enable(false)
+            .compare_op(CompareOp::ALWAYS)
+            .mipmap_mode(SamplerMipmapMode::NEAREST)
+            .mip_lod_bias(0.0)
+            .min_lod(0.0)
+            .max_lod(0.0);
+        let sampler = unsafe {
+            match device.create_sampler(&sampler_create_info, allocator) {
+                Ok(sampler) => sampler,
+                Err(err) => {
+                    error!(
+                        "Failed to create the sampler of the vulkan post processing shaders: {:?}",
+                        err
+                    );
+                    return Err(EngineError::VulkanFailed);
+                }
+            }
+        };
+
+        let mut effects = Vec::new();
+        for effect in PostProcessEffect::ALL {
+            match Self::create_effect(backend, effect, &vertex_stage, input_descriptor_set_layout) {
+                Ok(effect_shader) => effects.push(effect_shader),
+                Err(err) => {
+                    error!(
+                        "Failed to create the {:?} pass of the vulkan post processing shaders: {:?}",
+                        effect, err
+                    );
+                    return Err(EngineError::InitializationFailed);
+                }
+            }
+        }
+
+        Ok(PostProcessShaders {
+            vertex_stage,
+            vertex_source,
+            effects,
+            input_descriptor_set_layout,
+            input_descriptor_pools: PerFrameResource::from_vec(input_descriptor_pools)?,
+            sampler,
+        })
+    }
+
+    /// Recompile the modified stages and swap the pipelines using them, the previous ones are kept on failure
+    /// A modified vertex stage rebuilds the pipelines of every pass
+    pub fn hot_reload(&mut self, backend: &VulkanRendererBackend<'_>) -> Result<(), EngineError> {
+        let has_vertex_changed = self.vertex_source.has_changed();
+        let changed_effects: Vec<usize> = self
+            .effects
+            .iter_mut()
+            .enumerate()
+            .filter_map(|(index, effect)| {
+                (effect.fragment_source.has_changed() || has_vertex_changed).then_some(index)
+            })
+            .collect();
+        if changed_effects.is_empty() {
+            return Ok(());
+        }
+        let device = backend.get_device()?;
+        let allocator = backend.get_allocator()?;
+
+        let vertex_stage = if has_vertex_changed {
+            Some(self.vertex_source.rebuild(backend)?)
+        } else {
+            None
+        };
+        let mut rebuilt_effects = Vec::new();
+        for index in changed_effects {
+            let effect = &self.effects[index];
+            let fragment_stage = match effect.fragment_source.rebuild(backend) {
+                Ok(shader) => shader,
+                Err(err) => {
+                    error!(
+                        "Failed to rebuild the {:?} pass when reloading the vulkan post processing shaders: {:?}",
+                        effect.effect, err
+                    );
+                    continue;
+                }
+            };
+            match Self::create_pipelines(
+                backend,
+                vertex_stage.as_ref().unwrap_or(&self.vertex_stage),
+                &fragment_stage,
+                self.input_descriptor_set_layout,
+            ) {
+                Ok((pipeline, present_pipeline)) => {
+                    rebuilt_effects.push((index, fragment_stage, pipeline, present_pipeline))
+                }
+                Err(err) => {
+                    error!(
+                        "Failed to rebuild the pipelines of the {:?} pass when reloading the vulkan post processing shaders: {:?}",
+                        effect.effect, err
+                    );
+                    fragment_stage.destroy(device, allocator)?;
+                }
+            }
+        }
+
+        // The previous pipelines may still be used by the frames in flight
+        if let Err(err) = backend.device_wait_idle() {
+            error!(
+                "Failed to wait idle when reloading the vulkan post processing shaders: {:?}",
+                err
+            );
+            return Err(EngineError::Unknown);
+        }
+        for (index, fragment_stage, pipeline, present_pipeline) in rebuilt_effects {
+            let effect = &mut self.effects[index];
+            std::mem::replace(&mut effect.pipeline, pipeline).destroy(device, allocator)?;
+            std::mem::replace(&mut effect.present_pipeline, present_pipeline)
+                .destroy(device, allocator)?;
+            std::mem::replace(&mut effect.fragment_stage, fragment_stage)
+                .destroy(device, allocator)?;
+        }
+        if let Some(shader) = vertex_stage {
+            std::mem::replace(&mut self.vertex_stage, shader).destroy(device, allocator)?;
+        }
+        Ok(())
+    }
+
+    pub fn destroy(&self, backend: &VulkanRendererBackend<'_>) -> Result<(), EngineError> {
+        let device = backend.get_device()?;
+        let allocator = backend.get_allocator()?;
+
+        for effect in &self.effects {
+            if let Err(err) = effect.destroy(device, backend) {
+                error!(
+                    "Failed to destroy the {:?} pass of the vulkan post processing shaders: {:?}",
+                    effect.effect, err
+                );
+                return Err(EngineError::ShutdownFailed);
+            }
+        }
+        if let Err(err) = self.vertex_stage.destroy(device, allocator) {
+            error!(
+                "Failed to destroy the vertex stage of the vulkan post processing shaders: {:?}",
+                err
+            );
+            return Err(EngineError::ShutdownFailed);
+        }
+        unsafe {
+            device.destroy_sampler(self.sampler, allocator);
+            for pool in self.input_descriptor_pools.iter() {
+                device.destroy_descriptor_pool(*pool, allocator);
+            }
+            device.destroy_descriptor_set_layout(self.input_descriptor_set_layout, allocator);
+        }
+        Ok(())
+    }
+
+    fn get_effect(
+        &self,
+        effect: PostProcessEffect,
+    ) -> Result<&PostProcessEffectShader, EngineError> {
+        match self.effects.iter().find(|shader| shader.effect == effect) {
+            Some(shader) => Ok(shader),
+            None => {
+                error!("The {:?} post processing pass doesn't exist", effect);
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
+}
+
+impl VulkanRendererBackend<'_> {
+    /// The sets of this frame's pool were used the last time this frame was in flight
+    pub(crate) fn post_process_shaders_begin_frame(&self) -> Result<(), EngineError> {
+        let device = self.get_device()?;
+        let post_process_shaders = &self.get_builtin_shaders()?.post_process_shaders;
+        let descriptor_pool = *post_process_shaders
+            .input_descriptor_pools
+            .get(self.get_current_frame_index());
+        if let Err(err) = unsafe {
+            device.reset_descriptor_pool(descriptor_pool, DescriptorPoolResetFlags::empty())
+        } {
+            error!(
+                "Failed to reset the input descriptor pool of the vulkan post processing shaders: {:?}",
+                err
+            );
+            return Err(EngineError::VulkanFailed);
+        }
+        Ok(())
+    }
+
+    /// Record a post processing pass sampling the input, its renderpass must be begun
+    /// The last pass of the chain draws into the swapchain image with the present pipeline
+    pub(crate) fn draw_post_process_pass(
+        &self,
+        effect: PostProcessEffect,
+        constants: &PostProcessConstants,
+        input: ImageView,
+        is_last: bool,
+    ) -> Result<(), EngineError> {
+        let extent = self.get_swapchain()?.extent;
+        // The texture coordinates follow the rows of the images, the viewport is not flipped
+        let viewport = Viewport::default()
+            .x(0.)
+            .y(0.)
+            .width(extent.width as f32)
+            .height(extent.height as f32)
+            .min_depth(0.)
+            .max_depth(1.);
+        let scissor = Rect2D::default().extent(extent);
+        self.draw_post_process_pass_area(effect, constants, input, is_last, (viewport, scissor))
+    }
+
+    /// Record a post processing pass stretching the input over the viewport, only the scissor is drawn
+    pub(crate) fn draw_post_process_pass_area(
+        &self,
+        effect: PostProcessEffect,
+        constants: &PostProcessConstants,
+        input: ImageView,
+        is_last: bool,
+        (viewport, scissor): (Viewport, Rect2D),
+    ) -> Result<(), EngineError> {
+        let current_frame_index = self.get_current_frame_index();
+        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
+        let device = self.get_device()?;
+        let post_process_shaders = &self.get_builtin_shaders()?.post_process_shaders;
+        let descriptor_pool = *post_process_shaders
+            .input_descriptor_pools
+            .get(current_frame_index);
+        let effect_shader = post_process_shaders.get_effect(effect)?;
+        let pipeline = if is_last {
+            &effect_shader.present_pipeline
+        } else {
+            &effect_shader.pipeline
+        };
+
+        unsafe {
+            device.cmd_set_viewport(*command_buffer.handler.as_ref(), 0, &[viewport]);
+            device.cmd_set_scissor(*command_buffer.handler.as_ref(), 0, &[scissor]);
+        }
+        pipeline.bind(device, command_buffer, PipelineBindPoint::GRAPHICS)?;
+
+        let layouts = [post_process_shaders.input_descriptor_set_layout];
+        let allocate_info = DescriptorSetAllocateInfo::default()
+            .descriptor_pool(descriptor_pool)
+            .set_layouts(&layouts);
+        let descriptor_set = match unsafe { device.allocate_descriptor_sets(&allocate_info) } {
+            Ok(sets) => sets[0],
+            Err(err) => {
+                error!(
+                    "Failed to allocate an input descriptor set of the vulkan post processing shaders: {:?}",
+                    err
+                );
+                return Err(EngineError::VulkanFailed);
+            }
+        };
+        let image_info = [DescriptorImageInfo::default()
+            .image_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+            .image_view(input)
+            .sampler(post_process_shaders.sampler)];
+        let write = WriteDescriptorSet::default()
+            .dst_set(descriptor_set)
+            .dst_binding(0)
+            .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(1)
+            .image_info(&image_info);
+        // The copy pass has no constants, the others may not use all of them
+        let push_constant_size =
+            effect_shader.fragment_stage.reflection.push_constant_size as usize;
+        let constants = unsafe {
+            std::slice::from_raw_parts(
+                constants as *const PostProcessConstants as *const u8,
+                size_of::<PostProcessConstants>().min(push_constant_size),
+            )
+        };
+        unsafe {
+            device.update_descriptor_sets(&[write], &[]);
+            device.cmd_bind_descriptor_sets(
+                *command_buffer.handler.as_ref(),
+                PipelineBindPoint::GRAPHICS,
+                pipeline.layout,
+                0,
+                &[descriptor_set],
+                &[],
+            );
+            if !constants.is_empty() {
+                device.cmd_push_constants(
+                    *command_buffer.handler.as_ref(),
+                    pipeline.layout,
+                    ShaderStageFlags::FRAGMENT,
+                    0,
+                    constants,
+                );
+            }
+            device.cmd_draw(*command_buffer.handler.as_ref(), 3, 1, 0, 0);
+        }
+        Ok(())
+    }
+}
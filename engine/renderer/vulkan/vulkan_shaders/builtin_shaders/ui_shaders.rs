@@ -0,0 +1,550 @@
+use ash::{
+    vk::{
+        BufferUsageFlags, CullModeFlags, DescriptorImageInfo, DescriptorPool,
+        DescriptorPoolCreateInfo, DescriptorPoolResetFlags, DescriptorPoolSize,
+        DescriptorSetAllocateInfo, DescriptorSetLayout, DescriptorSetLayoutCreateInfo,
+        DescriptorType, Extent2D, ImageLayout, MemoryMapFlags, MemoryPropertyFlags, Offset2D,
+        PipelineBindPoint, PipelineShaderStageCreateInfo, Rect2D, ShaderStageFlags,
+        VertexInputBindingDescription, VertexInputRate, Viewport, WriteDescriptorSet,
+    },
+    Device,
+};
+
+use crate::{
+    core::debug::errors::EngineError,
+    error,
+    renderer::{
+        renderer_types::{UiRenderData, UiVertexData, RENDERER_MAX_IN_FLIGHT_FRAMES},
+        vulkan::{
+            vulkan_init::{
+                command_buffer::CommandBuffer, renderpass::VULKAN_RENDERPASS_UI_SUBPASS,
+            },
+            vulkan_shaders::{
+                builtin_shaders::OverlayConstants,
+                hot_reload::ShaderSource,
+                reflection::{
+                    reflection_get_descriptor_set_layout_bindings,
+                    reflection_get_push_constant_ranges, reflection_get_vertex_attributes,
+                },
+                shader::Shader,
+            },
+            vulkan_types::VulkanRendererBackend,
+            vulkan_utils::{
+                buffer::{Buffer, BufferCreatorParameters},
+                per_frame_resource::PerFrameResource,
+                pipeline::{Pipeline, PipelineCreateInfo},
+                texture::Texture,
+            },
+        },
+    },
+    resources::material::BlendMode,
+    warn,
+};
+
+/// Vertices of the ui overlay that can be drawn in a single frame
+pub const VULKAN_UI_SHADERS_MAX_VERTICES: usize = 65536;
+/// Texture changes of the ui overlay in a single frame, each one needs its own descriptor set
+pub const VULKAN_UI_SHADERS_MAX_BATCHES: usize = 1024;
+
+/// Textured 2d quads drawn in the overlay subpass, alpha blended without depth test
+pub(crate) struct UiShaders {
+    pub vertex_stage: Shader,
+    pub fragment_stage: Shader,
+    pub pipeline: Pipeline,
+    /// Sources of the stages, used to hot reload them
+    pub vertex_source: ShaderSource,
+    pub fragment_source: ShaderSource,
+    /// The texture sampled by a batch, set 0
+    pub texture_descriptor_set_layout: DescriptorSetLayout,
+    /// Reset at the start of every frame, the sets only live for one frame
+    pub texture_descriptor_pools: PerFrameResource<DescriptorPool>,
+    /// Rewritten every frame from the cpu
+    pub vertex_buffers: PerFrameResource<Buffer>,
+}
+
+impl UiShaders {
+    fn create_pipeline_info<'a>(
+        backend: &'a VulkanRendererBackend<'a>,
+        vertex_shader: &'a Shader,
+        fragment_shader: &'a Shader,
+        texture_descriptor_set_layout: DescriptorSetLayout,
+    ) -> Result<PipelineCreateInfo<'a>, EngineError> {
+        let viewports = vec![Viewport::default()
+            .x(0.)
+            .y(backend.framebuffer_height as f32)
+            .width(backend.framebuffer_width as f32)
+            .height(-(backend.framebuffer_height as f32))
+            .min_depth(0.0)
+            .max_depth(1.0)];
+        let scissors = vec![Rect2D::default()
+            .offset(Offset2D { x: 0, y: 0 })
+            .extent(Extent2D {
+                width: backend.framebuffer_width,
+                height: backend.framebuffer_height,
+            })];
+
+        // Input attributes, the locations of the vertex shader follow the fields of UiVertexData
+        let vertex_input_binding_description = VertexInputBindingDescription::default()
+            .binding(0)
+            .stride(size_of::<UiVertexData>() as u32)
+            .input_rate(VertexInputRate::VERTEX);
+
+        let shader_stages_info = vec![
+            PipelineShaderStageCreateInfo::default()
+                .stage(vertex_shader.stage_flag)
+                .module(vertex_shader.shader_module)
+                .name(vertex_shader.entry_point.as_c_str()),
+            PipelineShaderStageCreateInfo::default()
+                .stage(fragment_shader.stage_flag)
+                .module(fragment_shader.shader_module)
+                .name(fragment_shader.entry_point.as_c_str()),
+        ];
+
+        Ok(PipelineCreateInfo {
+            renderpass: backend.get_present_renderpass()?,
+            viewports,
+            scissors,
+            is_wireframe: false,
+            vertex_input_attributes_description: reflection_get_vertex_attributes(
+                vertex_shader,
+                vertex_input_binding_description.binding,
+            ),
+            vertex_input_bindings_description: vec![vertex_input_binding_description],
+            descriptor_set_layouts: vec![texture_descriptor_set_layout],
+            push_constant_ranges: reflection_get_push_constant_ranges(&[
+                vertex_shader,
+                fragment_shader,
+            ]),
+            shader_stages_info,
+            stencil_state: None,
+            // Mirrored quads are still drawn
+            cull_mode: CullModeFlags::NONE,
+            // Drawn in the overlay, in submission order on top of the scene
+            is_depth_test_enabled: false,
+            is_depth_write_enabled: false,
+            blend_mode: BlendMode::AlphaBlend,
+            subpass: VULKAN_RENDERPASS_UI_SUBPASS,
+        })
+    }
+
+    pub fn create(backend: &VulkanRendererBackend<'_>) -> Result<Self, EngineError> {
+        let device = backend.get_device()?;
+        let allocator = backend.get_allocator()?;
+
+        let vertex_source =
+            ShaderSource::new("builtin/ui.vert.slang", None, ShaderStageFlags::VERTEX);
+        let fragment_source =
+            ShaderSource::new("builtin/ui.frag.slang", None, ShaderStageFlags::FRAGMENT);
+        let vertex_stage = match Shader::create(
+            device,
+            allocator,
+            vertex_source.stage_flag,
+            vertex_source.path,
+            vertex_source.entry_point,
+        ) {
+            Ok(shader) => shader,
+            Err(err) => {
+                error!("Failed to create the ui vertex shader: {:?}", err);
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        let fragment_stage = match Shader::create(
+            device,
+            allocator,
+            fragment_source.stage_flag,
+            fragment_source.path,
+            fragment_source.entry_point,
+        ) {
+            Ok(shader) => shader,
+            Err(err) => {
+                error!("Failed to create the ui fragment shader: {:?}", err);
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+
+        // Texture descriptors
+        let texture_bindings = match reflection_get_descriptor_set_layout_bindings(
+            &[&vertex_stage, &fragment_stage],
+            0,
+        ) {
+            Ok(bindings) => bindings,
+            Err(err) => {
+                error!(
+                    "Failed to reflect the texture bindings of the vulkan ui shaders: {:?}",
+                    err
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        if texture_bindings.len() != 1
+            || texture_bindings[0].descriptor_type != DescriptorType::COMBINED_IMAGE_SAMPLER
+        {
+            error!(
+                "The ui shaders expect a single sampler in the set 0, got {:?}",
+                texture_bindings
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+        let texture_layout_create_info =
+            DescriptorSetLayoutCreateInfo::default().bindings(&texture_bindings);
+        let texture_descriptor_set_layout = unsafe {
+            match device.create_descriptor_set_layout(&texture_layout_create_info, allocator) {
+                Ok(layout) => layout,
+                Err(err) => {
+                    error!(
+                        "Failed to create the texture descriptor layout of the vulkan ui shaders: {:?}",
+                        err
+                    );
+                    return Err(EngineError::VulkanFailed);
+                }
+            }
+        };
+
+        let pool_sizes = [DescriptorPoolSize::default()
+            .ty(DescriptorType::COMBINED_IMAGE_SAMPLER)
+            .descriptor_count(VULKAN_UI_SHADERS_MAX_BATCHES as u32)];
+        let pool_create_info = DescriptorPoolCreateInfo::default()
+            .pool_sizes(&pool_sizes)
+            .max_sets(VULKAN_UI_SHADERS_MAX_BATCHES as u32);
+        let mut texture_descriptor_pools = Vec::new();
+        for _ in 0..RENDERER_MAX_IN_FLIGHT_FRAMES {
+            match unsafe { device.create_descriptor_pool(&pool_create_info, allocator) } {
+                Ok(pool) => texture_descriptor_pools.push(pool),
+                Err(err) => {
+                    error!(
+                        "Failed to create a texture descriptor pool of the vulkan ui shaders: {:?}",
+                        err
+                    );
+                    return Err(EngineError::VulkanFailed);
+                }
+            }
+        }
+
+        let pipeline = match Self::create_pipeline_info(
+            backend,
+            &vertex_stage,
+            &fragment_stage,
+            texture_descriptor_set_layout,
+        )
+        .and_then(|pipeline_info| Pipeline::create_graphics(device, allocator, pipeline_info))
+        {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                error!(
+                    "Failed to create the pipeline of the vulkan ui shaders: {:?}",
+                    err
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+
+        let mut vertex_buffers = Vec::new();
+        for _ in 0..RENDERER_MAX_IN_FLIGHT_FRAMES {
+            let vertex_buffer_creator_params = BufferCreatorParameters::default()
+                .buffer_usage_flags(BufferUsageFlags::VERTEX_BUFFER)
+                .memory_flags(
+                    MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+                )
+                .should_be_bind(true)
+                .size(size_of::<UiVertexData>() * VULKAN_UI_SHADERS_MAX_VERTICES);
+            match backend.create_buffer(vertex_buffer_creator_params) {
+                Ok(buffer) => vertex_buffers.push(buffer),
+                Err(err) => {
+                    error!(
+                        "Failed to create the vertex buffer when creating vulkan ui shaders: {:?}",
+                        err
+                    );
+                    return Err(EngineError::InitializationFailed);
+                }
+            }
+        }
+
+        Ok(UiShaders {
+            vertex_stage,
+            fragment_stage,
+            pipeline,
+            vertex_source,
+            fragment_source,
+            texture_descriptor_set_layout,
+            texture_descriptor_pools: PerFrameResource::from_vec(texture_descriptor_pools)?,
+            vertex_buffers: PerFrameResource::from_vec(vertex_buffers)?,
+        })
+    }
+
+    /// Recompile the modified stages and swap the pipeline, the previous one is kept on failure
+    /// The texture layout is kept, the reloaded stages must sample a single texture in the set 0
+    pub fn hot_reload(&mut self, backend: &VulkanRendererBackend<'_>) -> Result<(), EngineError> {
+        let has_vertex_changed = self.vertex_source.has_changed();
+        let has_fragment_changed = self.fragment_source.has_changed();
+        if !has_vertex_changed && !has_fragment_changed {
+            return Ok(());
+        }
+        let device = backend.get_device()?;
+        let allocator = backend.get_allocator()?;
+
+        let vertex_stage = if has_vertex_changed {
+            Some(self.vertex_source.rebuild(backend)?)
+        } else {
+            None
+        };
+        let fragment_stage = if has_fragment_changed {
+            match self.fragment_source.rebuild(backend) {
+                Ok(shader) => Some(shader),
+                Err(err) => {
+                    if let Some(shader) = vertex_stage {
+                        shader.destroy(device, allocator)?;
+                    }
+                    return Err(err);
+                }
+            }
+        } else {
+            None
+        };
+
+        let pipeline = Self::create_pipeline_info(
+            backend,
+            vertex_stage.as_ref().unwrap_or(&self.vertex_stage),
+            fragment_stage.as_ref().unwrap_or(&self.fragment_stage),
+            self.texture_descriptor_set_layout,
+        )
+        .and_then(|pipeline_info| Pipeline::create_graphics(device, allocator, pipeline_info));
+        let pipeline = match pipeline {
+            Ok(pipeline) => pipeline,
+            Err(err) => {
+                error!(
+                    "Failed to rebuild the pipeline when reloading the vulkan ui shaders: {:?}",
+                    err
+                );
+                for shader in vertex_stage.iter().chain(fragment_stage.iter()) {
+                    shader.destroy(device, allocator)?;
+                }
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+
+        // The previous pipeline may still be used by the frames in flight
+        if let Err(err) = backend.device_wait_idle() {
+            error!(
+                "Failed to wait idle when reloading the vulkan ui shaders: {:?}",
+                err
+            );
+            return Err(EngineError::Unknown);
+        }
+        std::mem::replace(&mut self.pipeline, pipeline).destroy(device, allocator)?;
+        if let Some(shader) = vertex_stage {
+            std::mem::replace(&mut self.vertex_stage, shader).destroy(device, allocator)?;
+        }
+        if let Some(shader) = fragment_stage {
+            std::mem::replace(&mut self.fragment_stage, shader).destroy(device, allocator)?;
+        }
+        Ok(())
+    }
+
+    pub fn destroy(&self, backend: &VulkanRendererBackend<'_>) -> Result<(), EngineError> {
+        let device = backend.get_device()?;
+        let allocator = backend.get_allocator()?;
+
+        for buffer in self.vertex_buffers.iter() {
+            if let Err(err) = backend.destroy_buffer(buffer) {
+                error!(
+                    "Failed to destroy a vertex buffer of the vulkan ui shaders: {:?}",
+                    err
+                );
+                return Err(EngineError::ShutdownFailed);
+            }
+        }
+        if let Err(err) = self.pipeline.destroy(device, allocator) {
+            error!(
+                "Failed to destroy the pipeline of the vulkan ui shaders: {:?}",
+                err
+            );
+            return Err(EngineError::ShutdownFailed);
+        }
+        if let Err(err) = self.vertex_stage.destroy(device, allocator) {
+            error!(
+                "Failed to destroy the vertex stage of the vulkan ui shaders: {:?}",
+                err
+            );
+            return Err(EngineError::ShutdownFailed);
+        }
+        if let Err(err) = self.fragment_stage.destroy(device, allocator) {
+            error!(
+                "Failed to destroy the fragment stage of the vulkan ui shaders: {:?}",
+                err
+            );
+            return Err(EngineError::ShutdownFailed);
+        }
+        unsafe {
+            for pool in self.texture_descriptor_pools.iter() {
+                device.destroy_descriptor_pool(*pool, allocator);
+            }
+            device.destroy_descriptor_set_layout(self.texture_descriptor_set_layout, allocator);
+        }
+        Ok(())
+    }
+
+    pub fn r#use(
+        &self,
+        device: &Device,
+        command_buffer: &CommandBuffer,
+    ) -> Result<(), EngineError> {
+        if let Err(err) = self
+            .pipeline
+            .bind(device, command_buffer, PipelineBindPoint::GRAPHICS)
+        {
+            error!(
+                "Failed to bind the pipeline of the vulkan ui shaders: {:?}",
+                err
+            );
+            return Err(EngineError::UpdateFailed);
+        }
+        Ok(())
+    }
+}
+
+impl VulkanRendererBackend<'_> {
+    /// Record the draw of the ui quads batched by the frontend during this frame
+    /// Each batch binds its texture and draws its range of the vertices
+    /// The projection comes from the 2d camera, the viewport is already flipped
+    pub fn draw_ui_shaders(
+        &mut self,
+        projection: glam::Mat4,
+        vertices: &[UiVertexData],
+        batches: &[UiRenderData],
+    ) -> Result<(), EngineError> {
+        if vertices.is_empty() || batches.is_empty() {
+            return Ok(());
+        }
+        if vertices.len() > VULKAN_UI_SHADERS_MAX_VERTICES {
+            warn!(
+                "Too many ui vertices this frame, only the first {} are drawn",
+                VULKAN_UI_SHADERS_MAX_VERTICES
+            );
+        }
+        if batches.len() > VULKAN_UI_SHADERS_MAX_BATCHES {
+            warn!(
+                "Too many ui batches this frame, only the first {} are drawn",
+                VULKAN_UI_SHADERS_MAX_BATCHES
+            );
+        }
+        let vertices = &vertices[..vertices.len().min(VULKAN_UI_SHADERS_MAX_VERTICES)];
+        let batches = &batches[..batches.len().min(VULKAN_UI_SHADERS_MAX_BATCHES)];
+
+        let current_frame_index = self.get_current_frame_index();
+        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
+        let device = self.get_device()?;
+        let ui_shaders = &self.get_builtin_shaders()?.ui_shaders;
+        let vertex_buffer = ui_shaders.vertex_buffers.get(current_frame_index);
+        let descriptor_pool = *ui_shaders.texture_descriptor_pools.get(current_frame_index);
+
+        // The sets of this frame's pool were used the last time this frame was in flight
+        if let Err(err) = unsafe {
+            device.reset_descriptor_pool(descriptor_pool, DescriptorPoolResetFlags::empty())
+        } {
+            error!(
+                "Failed to reset the texture descriptor pool of the vulkan ui shaders: {:?}",
+                err
+            );
+            return Err(EngineError::VulkanFailed);
+        }
+
+        if let Err(err) = self.load_data_into_buffer(
+            vertex_buffer,
+            0,
+            std::mem::size_of_val(vertices),
+            MemoryMapFlags::empty(),
+            vertices.as_ptr() as *mut std::ffi::c_void,
+        ) {
+            error!(
+                "Failed to upload the vertices of the vulkan ui shaders: {:?}",
+                err
+            );
+            return Err(EngineError::UpdateFailed);
+        }
+
+        ui_shaders.r#use(device, command_buffer)?;
+        // The fragment stage encodes the colors for the swapchain
+        let overlay_constants = self.get_overlay_constants(projection);
+        let constants = unsafe {
+            std::slice::from_raw_parts(
+                &overlay_constants as *const OverlayConstants as *const u8,
+                size_of::<OverlayConstants>(),
+            )
+        };
+        unsafe {
+            device.cmd_push_constants(
+                *command_buffer.handler.as_ref(),
+                ui_shaders.pipeline.layout,
+                ShaderStageFlags::VERTEX | ShaderStageFlags::FRAGMENT,
+                0,
+                constants,
+            );
+            device.cmd_bind_vertex_buffers(
+                *command_buffer.handler.as_ref(),
+                0,
+                &[vertex_buffer.buffer],
+                &[0],
+            );
+        }
+
+        let layouts = [ui_shaders.texture_descriptor_set_layout];
+        for batch in batches {
+            // Skip the batches cut by the vertex limit
+            let first_vertex = batch.first_vertex as usize;
+            if first_vertex >= vertices.len() {
+                break;
+            }
+            let vertex_count = (batch.vertex_count as usize).min(vertices.len() - first_vertex);
+
+            let vulkan_texture = match batch.texture.as_any().downcast_ref::<Texture>() {
+                Some(texture) => texture,
+                None => {
+                    error!("Failed to downcast a ui texture to a vulkan texture");
+                    return Err(EngineError::InvalidValue);
+                }
+            };
+            let allocate_info = DescriptorSetAllocateInfo::default()
+                .descriptor_pool(descriptor_pool)
+                .set_layouts(&layouts);
+            let descriptor_set = match unsafe { device.allocate_descriptor_sets(&allocate_info) } {
+                Ok(sets) => sets[0],
+                Err(err) => {
+                    error!(
+                        "Failed to allocate a texture descriptor set of the vulkan ui shaders: {:?}",
+                        err
+                    );
+                    return Err(EngineError::VulkanFailed);
+                }
+            };
+            let image_info = [DescriptorImageInfo::default()
+                .image_layout(ImageLayout::SHADER_READ_ONLY_OPTIMAL)
+                .image_view(vulkan_texture.image.get_image_view()?)
+                .sampler(vulkan_texture.sampler)];
+            let write = WriteDescriptorSet::default()
+                .dst_set(descriptor_set)
+                .dst_binding(0)
+                .descriptor_type(DescriptorType::COMBINED_IMAGE_SAMPLER)
+                .descriptor_count(1)
+                .image_info(&image_info);
+            unsafe {
+                device.update_descriptor_sets(&[write], &[]);
+                device.cmd_bind_descriptor_sets(
+                    *command_buffer.handler.as_ref(),
+                    PipelineBindPoint::GRAPHICS,
+                    ui_shaders.pipeline.layout,
+                    0,
+                    &[descriptor_set],
+                    &[],
+                );
+                device.cmd_draw(
+                    *command_buffer.handler.as_ref(),
+                    vertex_count as u32,
+                    1,
+                    first_vertex as u32,
+                    0,
+                );
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,110 @@
+use std::time::SystemTime;
+
+use ash::vk::ShaderStageFlags;
+
+use crate::{
+    core::debug::errors::EngineError, error, info,
+    renderer::vulkan::vulkan_types::VulkanRendererBackend,
+};
+
+use super::shader::Shader;
+
+/// Seconds between two checks of the shader sources
+pub const VULKAN_SHADER_HOT_RELOAD_INTERVAL: f64 = 0.5;
+
+/// Source of a shader stage, recompiled when it changes on disk
+pub(crate) struct ShaderSource {
+    /// The shader path within the assets/shaders/ folder
+    pub path: &'static str,
+    pub entry_point: Option<&'static str>,
+    pub stage_flag: ShaderStageFlags,
+    modification_time: Option<SystemTime>,
+}
+
+impl ShaderSource {
+    pub fn new(
+        path: &'static str,
+        entry_point: Option<&'static str>,
+        stage_flag: ShaderStageFlags,
+    ) -> Self {
+        Self {
+            path,
+            entry_point,
+            stage_flag,
+            modification_time: Shader::get_source_modification_time(path),
+        }
+    }
+
+    /// Returns true once every time the source is modified
+    pub fn has_changed(&mut self) -> bool {
+        let modification_time = Shader::get_source_modification_time(self.path);
+        if modification_time.is_none() || modification_time == self.modification_time {
+            return false;
+        }
+        self.modification_time = modification_time;
+        true
+    }
+
+    /// Compile the source and create the new shader module
+    pub fn rebuild(&self, backend: &VulkanRendererBackend<'_>) -> Result<Shader, EngineError> {
+        if let Err(err) = Shader::compile(self.path, self.entry_point) {
+            error!("Failed to recompile the shader {}: {:?}", self.path, err);
+            return Err(EngineError::InitializationFailed);
+        }
+        match Shader::create(
+            backend.get_device()?,
+            backend.get_allocator()?,
+            self.stage_flag,
+            self.path,
+            self.entry_point,
+        ) {
+            Ok(shader) => {
+                info!("Reloaded the shader {}", self.path);
+                Ok(shader)
+            }
+            Err(err) => {
+                error!("Failed to reload the shader {}: {:?}", self.path, err);
+                Err(EngineError::InitializationFailed)
+            }
+        }
+    }
+}
+
+impl VulkanRendererBackend<'_> {
+    /// Rebuild the builtin pipelines whose shader sources changed, called between frames
+    /// A shader failing to compile keeps the previous pipeline
+    pub(crate) fn builtin_shaders_hot_reload(
+        &mut self,
+        delta_time: f64,
+    ) -> Result<(), EngineError> {
+        let mut builtin_shaders = match self.context.resources.builtin_shaders.take() {
+            Some(builtin_shaders) => builtin_shaders,
+            None => return Ok(()),
+        };
+        builtin_shaders.hot_reload_elapsed_time += delta_time;
+        let result = if builtin_shaders.hot_reload_elapsed_time < VULKAN_SHADER_HOT_RELOAD_INTERVAL
+        {
+            Ok(())
+        } else {
+            builtin_shaders.hot_reload_elapsed_time = 0.0;
+            if let Err(err) = builtin_shaders.object_shaders.hot_reload(self) {
+                error!("Failed to hot reload the object shaders: {:?}", err);
+            }
+            if let Err(err) = builtin_shaders.immediate_shaders.hot_reload(self) {
+                error!("Failed to hot reload the immediate shaders: {:?}", err);
+            }
+            if let Err(err) = builtin_shaders.post_process_shaders.hot_reload(self) {
+                error!(
+                    "Failed to hot reload the post processing shaders: {:?}",
+                    err
+                );
+            }
+            builtin_shaders.ui_shaders.hot_reload(self)
+        };
+        self.context.resources.builtin_shaders = Some(builtin_shaders);
+        if let Err(err) = result {
+            error!("Failed to hot reload the ui shaders: {:?}", err);
+        }
+        Ok(())
+    }
+}
@@ -1,2 +1,4 @@
-pub mod builtin_shaders;
-pub mod shader;
+pub(crate) mod builtin_shaders;
+pub(crate) mod hot_reload;
+pub(crate) mod reflection;
+pub(crate) mod shader;
@@ -0,0 +1,631 @@
+use std::{collections::HashMap, ops::Range};
+
+use ash::vk::{
+    DescriptorSetLayoutBinding, DescriptorType, Format, PushConstantRange, ShaderStageFlags,
+    VertexInputAttributeDescription,
+};
+
+use crate::{core::debug::errors::EngineError, error};
+
+use super::shader::Shader;
+
+const SPIRV_MAGIC_NUMBER: u32 = 0x0723_0203;
+const SPIRV_HEADER_WORD_COUNT: usize = 5;
+
+// Opcodes
+const OP_ENTRY_POINT: u32 = 15;
+const OP_TYPE_INT: u32 = 21;
+const OP_TYPE_FLOAT: u32 = 22;
+const OP_TYPE_VECTOR: u32 = 23;
+const OP_TYPE_MATRIX: u32 = 24;
+const OP_TYPE_IMAGE: u32 = 25;
+const OP_TYPE_SAMPLER: u32 = 26;
+const OP_TYPE_SAMPLED_IMAGE: u32 = 27;
+const OP_TYPE_ARRAY: u32 = 28;
+const OP_TYPE_RUNTIME_ARRAY: u32 = 29;
+const OP_TYPE_STRUCT: u32 = 30;
+const OP_TYPE_POINTER: u32 = 32;
+const OP_CONSTANT: u32 = 43;
+const OP_VARIABLE: u32 = 59;
+const OP_DECORATE: u32 = 71;
+const OP_MEMBER_DECORATE: u32 = 72;
+const OP_TYPE_ACCELERATION_STRUCTURE: u32 = 5341;
+
+// Decorations
+const DECORATION_BLOCK: u32 = 2;
+const DECORATION_BUFFER_BLOCK: u32 = 3;
+const DECORATION_ARRAY_STRIDE: u32 = 6;
+const DECORATION_MATRIX_STRIDE: u32 = 7;
+const DECORATION_BUILT_IN: u32 = 11;
+const DECORATION_LOCATION: u32 = 30;
+const DECORATION_BINDING: u32 = 33;
+const DECORATION_DESCRIPTOR_SET: u32 = 34;
+const DECORATION_OFFSET: u32 = 35;
+
+// Storage classes
+const STORAGE_CLASS_UNIFORM_CONSTANT: u32 = 0;
+const STORAGE_CLASS_INPUT: u32 = 1;
+const STORAGE_CLASS_UNIFORM: u32 = 2;
+const STORAGE_CLASS_PUSH_CONSTANT: u32 = 9;
+const STORAGE_CLASS_STORAGE_BUFFER: u32 = 12;
+
+/// Resource bound to a descriptor set of the shader
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct ReflectedDescriptorBinding {
+    pub set: u32,
+    pub binding: u32,
+    pub descriptor_type: DescriptorType,
+    /// Size of the array, 1 for single resources and runtime arrays
+    pub count: u32,
+}
+
+/// Input attribute of a vertex shader
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct ReflectedVertexInput {
+    pub location: u32,
+    pub format: Format,
+    /// In bytes
+    pub size: u32,
+}
+
+/// Interface of a shader stage read from its spir-v binary
+#[derive(Clone, Debug, Default)]
+pub(crate) struct ShaderReflection {
+    pub stage_flag: ShaderStageFlags,
+    pub descriptor_bindings: Vec<ReflectedDescriptorBinding>,
+    /// Size in bytes of the push constant block, 0 without push constants
+    pub push_constant_size: u32,
+    /// Sorted by location, the built-in inputs are not included
+    pub vertex_inputs: Vec<ReflectedVertexInput>,
+}
+
+/// Types declared in the module, only the ones needed by the reflection
+#[derive(Clone, Debug)]
+enum SpirvType {
+    Scalar {
+        is_float: bool,
+        is_signed: bool,
+        width: u32,
+    },
+    Vector {
+        component: u32,
+        count: u32,
+    },
+    Matrix {
+        column: u32,
+        count: u32,
+    },
+    Image {
+        is_buffer: bool,
+        sampled: u32,
+    },
+    Sampler,
+    SampledImage,
+    Array {
+        element: u32,
+        length_id: u32,
+    },
+    RuntimeArray {
+        element: u32,
+    },
+    Struct {
+        members: Vec<u32>,
+    },
+    Pointer {
+        storage_class: u32,
+        pointee: u32,
+    },
+    AccelerationStructure,
+}
+
+#[derive(Default)]
+struct SpirvModule {
+    execution_model: Option<u32>,
+    types: HashMap<u32, SpirvType>,
+    constants: HashMap<u32, u32>,
+    /// (id, storage class, pointer type)
+    variables: Vec<(u32, u32, u32)>,
+    decorations: HashMap<(u32, u32), u32>,
+    member_decorations: HashMap<(u32, u32, u32), u32>,
+}
+
+impl SpirvModule {
+    fn parse(code: &[u32]) -> Result<Self, EngineError> {
+        if code.len() < SPIRV_HEADER_WORD_COUNT || code[0] != SPIRV_MAGIC_NUMBER {
+            error!("Can't reflect a shader that is not a spir-v binary");
+            return Err(EngineError::InvalidValue);
+        }
+        let mut module = SpirvModule::default();
+        let mut index = SPIRV_HEADER_WORD_COUNT;
+        while index < code.len() {
+            let word_count = (code[index] >> 16) as usize;
+            let opcode = code[index] & 0xffff;
+            if word_count == 0 || index + word_count > code.len() {
+                error!("Can't reflect a truncated spir-v binary");
+                return Err(EngineError::InvalidValue);
+            }
+            let operands = &code[index + 1..index + word_count];
+            let operand = |i: usize| operands.get(i).copied().unwrap_or_default();
+            match opcode {
+                // Every stage is compiled to its own binary, the first entry point is the stage
+                OP_ENTRY_POINT if module.execution_model.is_none() => {
+                    module.execution_model = Some(operand(0));
+                }
+                OP_TYPE_INT => {
+                    module.types.insert(
+                        operand(0),
+                        SpirvType::Scalar {
+                            is_float: false,
+                            is_signed: operand(2) == 1,
+                            width: operand(1),
+                        },
+                    );
+                }
+                OP_TYPE_FLOAT => {
+                    module.types.insert(
+                        operand(0),
+                        SpirvType::Scalar {
+                            is_float: true,
+                            is_signed: true,
+                            width: operand(1),
+                        },
+                    );
+                }
+                OP_TYPE_VECTOR => {
+                    module.types.insert(
+                        operand(0),
+                        SpirvType::Vector {
+                            component: operand(1),
+                            count: operand(2),
+                        },
+                    );
+                }
+                OP_TYPE_MATRIX => {
+                    module.types.insert(
+                        operand(0),
+                        SpirvType::Matrix {
+                            column: operand(1),
+                            count: operand(2),
+                        },
+                    );
+                }
+                OP_TYPE_IMAGE => {
+                    module.types.insert(
+                        operand(0),
+                        SpirvType::Image {
+                            // Dim::Buffer
+                            is_buffer: operand(2) == 5,
+                            sampled: operand(6),
+                        },
+                    );
+                }
+                OP_TYPE_SAMPLER => {
+                    module.types.insert(operand(0), SpirvType::Sampler);
+                }
+                OP_TYPE_SAMPLED_IMAGE => {
+                    module.types.insert(operand(0), SpirvType::SampledImage);
+                }
+                OP_TYPE_ARRAY => {
+                    module.types.insert(
+                        operand(0),
+                        SpirvType::Array {
+                            element: operand(1),
+                            length_id: operand(2),
+                        },
+                    );
+                }
+                OP_TYPE_RUNTIME_ARRAY => {
+                    module.types.insert(
+                        operand(0),
+                        SpirvType::RuntimeArray {
+                            element: operand(1),
+                        },
+                    );
+                }
+                OP_TYPE_STRUCT => {
+                    module.types.insert(
+                        operand(0),
+                        SpirvType::Struct {
+                            members: operands[1..].to_vec(),
+                        },
+                    );
+                }
+                OP_TYPE_POINTER => {
+                    module.types.insert(
+                        operand(0),
+                        SpirvType::Pointer {
+                            storage_class: operand(1),
+                            pointee: operand(2),
+                        },
+                    );
+                }
+                OP_TYPE_ACCELERATION_STRUCTURE => {
+                    module
+                        .types
+                        .insert(operand(0), SpirvType::AccelerationStructure);
+                }
+                OP_CONSTANT => {
+                    // Only the lower word is needed for the array lengths
+                    module.constants.insert(operand(1), operand(2));
+                }
+                OP_VARIABLE => {
+                    module.variables.push((operand(1), operand(2), operand(0)));
+                }
+                OP_DECORATE => {
+                    module
+                        .decorations
+                        .insert((operand(0), operand(1)), operand(2));
+                }
+                OP_MEMBER_DECORATE => {
+                    module
+                        .member_decorations
+                        .insert((operand(0), operand(1), operand(2)), operand(3));
+                }
+                _ => (),
+            }
+            index += word_count;
+        }
+        Ok(module)
+    }
+
+    fn get_type(&self, id: u32) -> Result<&SpirvType, EngineError> {
+        match self.types.get(&id) {
+            Some(spirv_type) => Ok(spirv_type),
+            None => {
+                error!("The spir-v type {} is not supported by the reflection", id);
+                Err(EngineError::NotImplemented)
+            }
+        }
+    }
+
+    fn get_stage_flag(&self) -> Result<ShaderStageFlags, EngineError> {
+        match self.execution_model {
+            Some(0) => Ok(ShaderStageFlags::VERTEX),
+            Some(1) => Ok(ShaderStageFlags::TESSELLATION_CONTROL),
+            Some(2) => Ok(ShaderStageFlags::TESSELLATION_EVALUATION),
+            Some(3) => Ok(ShaderStageFlags::GEOMETRY),
+            Some(4) => Ok(ShaderStageFlags::FRAGMENT),
+            Some(5) => Ok(ShaderStageFlags::COMPUTE),
+            execution_model => {
+                error!(
+                    "Can't reflect a spir-v binary with the execution model {:?}",
+                    execution_model
+                );
+                Err(EngineError::NotImplemented)
+            }
+        }
+    }
+
+    /// Size in bytes of a type laid out in a buffer
+    fn get_size(&self, id: u32) -> Result<u32, EngineError> {
+        Ok(match self.get_type(id)? {
+            SpirvType::Scalar { width, .. } => width / 8,
+            SpirvType::Vector { component, count } => self.get_size(*component)? * count,
+            SpirvType::Matrix { column, count } => self.get_size(*column)? * count,
+            SpirvType::Array { element, length_id } => {
+                let length = self.constants.get(length_id).copied().unwrap_or(1);
+                let stride = match self.decorations.get(&(id, DECORATION_ARRAY_STRIDE)) {
+                    Some(stride) => *stride,
+                    None => self.get_size(*element)?,
+                };
+                stride * length
+            }
+            SpirvType::Struct { members } => {
+                let mut size = 0;
+                for (member, member_type) in members.iter().enumerate() {
+                    let member = member as u32;
+                    let offset = self
+                        .member_decorations
+                        .get(&(id, member, DECORATION_OFFSET))
+                        .copied()
+                        .unwrap_or(size);
+                    let member_size = match (
+                        self.get_type(*member_type)?,
+                        self.member_decorations
+                            .get(&(id, member, DECORATION_MATRIX_STRIDE)),
+                    ) {
+                        (SpirvType::Matrix { count, .. }, Some(stride)) => count * stride,
+                        _ => self.get_size(*member_type)?,
+                    };
+                    size = size.max(offset + member_size);
+                }
+                size
+            }
+            _ => 0,
+        })
+    }
+
+    fn get_descriptor_type(
+        &self,
+        storage_class: u32,
+        pointee: u32,
+    ) -> Result<(DescriptorType, u32), EngineError> {
+        let (resource, count) = match self.get_type(pointee)? {
+            SpirvType::Array { element, length_id } => (
+                *element,
+                self.constants.get(length_id).copied().unwrap_or(1),
+            ),
+            SpirvType::RuntimeArray { element } => (*element, 1),
+            _ => (pointee, 1),
+        };
+        let descriptor_type = match (storage_class, self.get_type(resource)?) {
+            (STORAGE_CLASS_STORAGE_BUFFER, _) => DescriptorType::STORAGE_BUFFER,
+            (STORAGE_CLASS_UNIFORM, _)
+                if self
+                    .decorations
+                    .contains_key(&(resource, DECORATION_BUFFER_BLOCK)) =>
+            {
+                DescriptorType::STORAGE_BUFFER
+            }
+            (STORAGE_CLASS_UNIFORM, _)
+                if self.decorations.contains_key(&(resource, DECORATION_BLOCK)) =>
+            {
+                DescriptorType::UNIFORM_BUFFER
+            }
+            (_, SpirvType::SampledImage) => DescriptorType::COMBINED_IMAGE_SAMPLER,
+            (_, SpirvType::Sampler) => DescriptorType::SAMPLER,
+            (_, SpirvType::Image { is_buffer, sampled }) => match (is_buffer, sampled) {
+                (true, 2) => DescriptorType::STORAGE_TEXEL_BUFFER,
+                (true, _) => DescriptorType::UNIFORM_TEXEL_BUFFER,
+                (false, 2) => DescriptorType::STORAGE_IMAGE,
+                (false, _) => DescriptorType::SAMPLED_IMAGE,
+            },
+            (_, SpirvType::AccelerationStructure) => DescriptorType::ACCELERATION_STRUCTURE_KHR,
+            (storage_class, resource_type) => {
+                error!(
+                    "Can't reflect the descriptor type of a {:?} in the storage class {}",
+                    resource_type, storage_class
+                );
+                return Err(EngineError::NotImplemented);
+            }
+        };
+        Ok((descriptor_type, count))
+    }
+
+    fn get_vertex_input_format(&self, id: u32) -> Result<(Format, u32), EngineError> {
+        let (component, count) = match self.get_type(id)? {
+            SpirvType::Vector { component, count } => (*component, *count),
+            _ => (id, 1),
+        };
+        let format = match (self.get_type(component)?, count) {
+            (
+                SpirvType::Scalar {
+                    width: 32,
+                    is_float: true,
+                    ..
+                },
+                1,
+            ) => Format::R32_SFLOAT,
+            (
+                SpirvType::Scalar {
+                    width: 32,
+                    is_float: true,
+                    ..
+                },
+                2,
+            ) => Format::R32G32_SFLOAT,
+            (
+                SpirvType::Scalar {
+                    width: 32,
+                    is_float: true,
+                    ..
+                },
+                3,
+            ) => Format::R32G32B32_SFLOAT,
+            (
+                SpirvType::Scalar {
+                    width: 32,
+                    is_float: true,
+                    ..
+                },
+                4,
+            ) => Format::R32G32B32A32_SFLOAT,
+            (
+                SpirvType::Scalar {
+                    width: 32,
+                    is_signed: true,
+                    ..
+                },
+                1,
+            ) => Format::R32_SINT,
+            (
+                SpirvType::Scalar {
+                    width: 32,
+                    is_signed: true,
+                    ..
+                },
+                2,
+            ) => Format::R32G32_SINT,
+            (
+                SpirvType::Scalar {
+                    width: 32,
+                    is_signed: true,
+                    ..
+                },
+                3,
+            ) => Format::R32G32B32_SINT,
+            (
+                SpirvType::Scalar {
+                    width: 32,
+                    is_signed: true,
+                    ..
+                },
+                4,
+            ) => Format::R32G32B32A32_SINT,
+            (SpirvType::Scalar { width: 32, .. }, 1) => Format::R32_UINT,
+            (SpirvType::Scalar { width: 32, .. }, 2) => Format::R32G32_UINT,
+            (SpirvType::Scalar { width: 32, .. }, 3) => Format::R32G32B32_UINT,
+            (SpirvType::Scalar { width: 32, .. }, 4) => Format::R32G32B32A32_UINT,
+            (component_type, count) => {
+                error!(
+                    "Can't reflect a vertex input of {} {:?}",
+                    count, component_type
+                );
+                return Err(EngineError::NotImplemented);
+            }
+        };
+        Ok((format, count * 4))
+    }
+}
+
+impl ShaderReflection {
+    /// Read the descriptors, push constants and vertex inputs used by a spir-v binary
+    pub fn parse(code: &[u32]) -> Result<Self, EngineError> {
+        let module = SpirvModule::parse(code)?;
+        let mut reflection = ShaderReflection {
+            stage_flag: module.get_stage_flag()?,
+            ..Default::default()
+        };
+
+        for (id, storage_class, pointer_type) in module.variables.iter().copied() {
+            let pointee = match module.get_type(pointer_type)? {
+                SpirvType::Pointer { pointee, .. } => *pointee,
+                _ => continue,
+            };
+            match storage_class {
+                STORAGE_CLASS_UNIFORM_CONSTANT
+                | STORAGE_CLASS_UNIFORM
+                | STORAGE_CLASS_STORAGE_BUFFER => {
+                    let (set, binding) = match (
+                        module.decorations.get(&(id, DECORATION_DESCRIPTOR_SET)),
+                        module.decorations.get(&(id, DECORATION_BINDING)),
+                    ) {
+                        (Some(set), Some(binding)) => (*set, *binding),
+                        // Not bound through a descriptor set
+                        _ => continue,
+                    };
+                    let (descriptor_type, count) =
+                        module.get_descriptor_type(storage_class, pointee)?;
+                    reflection
+                        .descriptor_bindings
+                        .push(ReflectedDescriptorBinding {
+                            set,
+                            binding,
+                            descriptor_type,
+                            count,
+                        });
+                }
+                STORAGE_CLASS_PUSH_CONSTANT => {
+                    reflection.push_constant_size = module.get_size(pointee)?;
+                }
+                STORAGE_CLASS_INPUT if reflection.stage_flag == ShaderStageFlags::VERTEX => {
+                    if module.decorations.contains_key(&(id, DECORATION_BUILT_IN)) {
+                        continue;
+                    }
+                    let location = match module.decorations.get(&(id, DECORATION_LOCATION)) {
+                        Some(location) => *location,
+                        None => continue,
+                    };
+                    let (format, size) = module.get_vertex_input_format(pointee)?;
+                    reflection.vertex_inputs.push(ReflectedVertexInput {
+                        location,
+                        format,
+                        size,
+                    });
+                }
+                _ => (),
+            }
+        }
+        reflection
+            .descriptor_bindings
+            .sort_by_key(|binding| (binding.set, binding.binding));
+        reflection.vertex_inputs.sort_by_key(|input| input.location);
+        Ok(reflection)
+    }
+}
+
+/// Bindings of a descriptor set used by any of the stages
+pub(crate) fn reflection_get_descriptor_set_layout_bindings(
+    stages: &[&Shader],
+    set: u32,
+) -> Result<Vec<DescriptorSetLayoutBinding<'static>>, EngineError> {
+    let mut layout_bindings: Vec<DescriptorSetLayoutBinding<'static>> = Vec::new();
+    for stage in stages {
+        for reflected in stage
+            .reflection
+            .descriptor_bindings
+            .iter()
+            .filter(|binding| binding.set == set)
+        {
+            match layout_bindings
+                .iter_mut()
+                .find(|binding| binding.binding == reflected.binding)
+            {
+                Some(binding) => {
+                    if binding.descriptor_type != reflected.descriptor_type
+                        || binding.descriptor_count != reflected.count
+                    {
+                        error!(
+                            "The stages don't agree on the binding {} of the set {}: {:?} and {:?}",
+                            reflected.binding, set, binding, reflected
+                        );
+                        return Err(EngineError::InvalidValue);
+                    }
+                    binding.stage_flags |= stage.stage_flag;
+                }
+                None => layout_bindings.push(
+                    DescriptorSetLayoutBinding::default()
+                        .binding(reflected.binding)
+                        .descriptor_type(reflected.descriptor_type)
+                        .descriptor_count(reflected.count)
+                        .stage_flags(stage.stage_flag),
+                ),
+            }
+        }
+    }
+    layout_bindings.sort_by_key(|binding| binding.binding);
+    Ok(layout_bindings)
+}
+
+/// A single range starting at 0 shared by every stage using push constants
+pub(crate) fn reflection_get_push_constant_ranges(stages: &[&Shader]) -> Vec<PushConstantRange> {
+    let mut stage_flags = ShaderStageFlags::empty();
+    let mut size = 0;
+    for stage in stages {
+        if stage.reflection.push_constant_size > 0 {
+            stage_flags |= stage.stage_flag;
+            size = size.max(stage.reflection.push_constant_size);
+        }
+    }
+    if size == 0 {
+        return Vec::new();
+    }
+    vec![PushConstantRange::default()
+        .stage_flags(stage_flags)
+        .offset(0)
+        .size(size)]
+}
+
+/// Attributes read from a single interleaved binding, in the order of their locations
+/// The vertex struct must start with the attributes in the same order, the other fields are skipped by the stride
+pub(crate) fn reflection_get_vertex_attributes(
+    vertex_stage: &Shader,
+    binding: u32,
+) -> Vec<VertexInputAttributeDescription> {
+    reflection_get_vertex_attributes_in_range(vertex_stage, binding, 0..u32::MAX)
+}
+
+/// Attributes of the given locations read from a single interleaved binding, e.g. the per
+/// instance ones next to the per vertex ones
+pub(crate) fn reflection_get_vertex_attributes_in_range(
+    vertex_stage: &Shader,
+    binding: u32,
+    locations: Range<u32>,
+) -> Vec<VertexInputAttributeDescription> {
+    let mut offset = 0;
+    vertex_stage
+        .reflection
+        .vertex_inputs
+        .iter()
+        .filter(|input| locations.contains(&input.location))
+        .map(|input| {
+            let attribute = VertexInputAttributeDescription::default()
+                .binding(binding)
+                .location(input.location)
+                .format(input.format)
+                .offset(offset);
+            offset += input.size;
+            attribute
+        })
+        .collect()
+}
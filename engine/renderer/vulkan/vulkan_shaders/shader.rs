@@ -1,4 +1,9 @@
-use std::{ffi::CString, path::Path};
+use std::{
+    ffi::CString,
+    path::{Path, PathBuf},
+    process::Command,
+    time::SystemTime,
+};
 
 use ash::{
     util::read_spv,
@@ -8,10 +13,14 @@ use ash::{
 
 use crate::{core::debug::errors::EngineError, error};
 
+use super::reflection::ShaderReflection;
+
 pub(crate) struct Shader {
     pub shader_module: ShaderModule,
     pub stage_flag: ShaderStageFlags,
     pub entry_point: CString,
+    /// Interface of the stage, used to build the pipeline layouts
+    pub reflection: ShaderReflection,
 }
 
 impl Shader {
@@ -25,6 +34,62 @@ impl Shader {
             .into_owned()
     }
 
+    fn get_source_shader_path(shader: &str) -> PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join("assets/shaders")
+            .join(shader)
+    }
+
+    /// Last modification of the source of the shader, none if it can't be read
+    pub fn get_source_modification_time(shader_path_from_shaders_dir: &str) -> Option<SystemTime> {
+        std::fs::metadata(Self::get_source_shader_path(shader_path_from_shaders_dir))
+            .and_then(|metadata| metadata.modified())
+            .ok()
+    }
+
+    /// Compile the source of the shader to spir-v at runtime, with the same options as the build script
+    /// shader_path_from_shaders_dir The shader path within the assets/shaders/ folder (expect .slang file)
+    /// shader_entry_point The name of the entry point function for the shader stage, if None default to "main"
+    pub fn compile(
+        shader_path_from_shaders_dir: &str,
+        shader_entry_point: Option<&str>,
+    ) -> Result<(), EngineError> {
+        let source_path = Self::get_source_shader_path(shader_path_from_shaders_dir);
+        let spv_path = env!("CARGO_MANIFEST_DIR").to_owned()
+            + &Self::get_compiled_shader_path(shader_path_from_shaders_dir);
+        let output = Command::new("slangc")
+            .arg(&source_path)
+            .arg("-emit-spirv-directly")
+            .arg("-g2")
+            .arg("-profile")
+            .arg("glsl_460")
+            .arg("-target")
+            .arg("spirv")
+            .arg("-o")
+            .arg(&spv_path)
+            .arg("-entry")
+            .arg(shader_entry_point.unwrap_or("main"))
+            .output();
+        match output {
+            Ok(output) if output.status.success() => Ok(()),
+            Ok(output) => {
+                error!(
+                    "Failed to compile the shader {:?}:\n{}",
+                    source_path,
+                    String::from_utf8_lossy(&output.stderr)
+                );
+                Err(EngineError::InvalidValue)
+            }
+            Err(err) => {
+                error!(
+                    "Failed to run the shader compiler for {:?}: {:?}",
+                    source_path, err
+                );
+                Err(EngineError::IO)
+            }
+        }
+    }
+
     /// Create a shader stage
     /// device The logical device to build the shader module
     /// stage_flag Indicates the type of shader (Vertex, Fragment, ...)
@@ -57,6 +122,24 @@ impl Shader {
             }
         };
 
+        let reflection = match ShaderReflection::parse(&spv_code) {
+            Ok(reflection) => reflection,
+            Err(err) => {
+                error!(
+                    "Failed to reflect the vulkan shader {:?}: {:?}",
+                    spv_path, err
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        if reflection.stage_flag != stage_flag {
+            error!(
+                "The vulkan shader {:?} is a {:?} stage, expected a {:?} stage",
+                spv_path, reflection.stage_flag, stage_flag
+            );
+            return Err(EngineError::InvalidValue);
+        }
+
         let create_info = ShaderModuleCreateInfo::default().code(&spv_code);
 
         let shader_module = unsafe {
@@ -99,6 +182,7 @@ impl Shader {
             shader_module,
             stage_flag,
             entry_point,
+            reflection,
         })
     }
 
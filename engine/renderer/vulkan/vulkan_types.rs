@@ -1,26 +1,62 @@
+use std::{
+    cell::RefCell,
+    collections::{HashMap, VecDeque},
+};
+
 use ash::{
     ext::debug_utils,
     khr::surface,
-    vk::{AllocationCallbacks, CommandPool, DebugUtilsMessengerEXT, PhysicalDevice, SurfaceKHR},
+    vk::{
+        AllocationCallbacks, CommandPool, DebugUtilsMessengerEXT, PhysicalDevice, SampleCountFlags,
+        SurfaceKHR,
+    },
     Device, Entry, Instance,
 };
 
+use crate::{
+    core::application::windows::WindowHandle,
+    renderer::{
+        post_process::PostProcessSettings,
+        renderer_types::{
+            DepthStencilSettings, DisplayOutput, OutputColorSpace, RendererStatistics, VsyncMode,
+        },
+        screenshot::{HdrScreenshotPixels, ScreenshotPixels},
+    },
+};
+
 use super::{
     vulkan_init::{
+        allocator::MemoryAllocator,
+        bindless_textures::BindlessTextures,
         command_buffer::CommandBuffer,
+        compute::ComputeContext,
         devices::{device_requirements::DeviceRequirements, physical_device::PhysicalDeviceInfo},
+        framebuffer::FramebufferCache,
         objects::ObjectsBuffers,
+        query_pools::QueryPools,
+        queue_ownership::QueueOwnershipContext,
+        render_graph::RenderGraph,
         renderpass::Renderpass,
         swapchain::Swapchain,
         sync_structures::SyncStructure,
+        transfer::TransferContext,
+        window_swapchains::WindowSwapchain,
     },
     vulkan_shaders::builtin_shaders::BuiltinShaders,
+    vulkan_utils::{
+        camera_pass::CameraPass, luminance::LuminanceReadback,
+        per_frame_resource::PerFrameResource, reflection_probes::ReflectionProbeCubemaps,
+        screenshot::ScreenshotReadback, texture::Texture,
+    },
 };
 
+/// Instance and device level objects, they live as long as the backend
 #[derive(Default)]
-pub(crate) struct VulkanContext<'a> {
+pub(crate) struct VulkanCoreContext<'a> {
     pub entry: Option<Entry>,
     pub instance: Option<Instance>,
+    /// Version requested by the instance, the device may support less
+    pub api_version: u32,
     pub allocator: Option<&'a AllocationCallbacks<'a>>,
 
     pub debug_utils_loader: Option<debug_utils::Instance>,
@@ -33,23 +69,99 @@ pub(crate) struct VulkanContext<'a> {
     pub physical_device_info: Option<PhysicalDeviceInfo>,
     pub physical_device: Option<PhysicalDevice>,
     pub device: Option<Device>,
+    /// Device memory of the buffers and images, borrowed while creating or destroying them
+    pub memory_allocator: Option<RefCell<MemoryAllocator>>,
+    /// Staging ring and batches of the transfer queue, borrowed while uploading
+    pub transfer: Option<RefCell<TransferContext>>,
+    /// Compute shaders, storage buffers and the dispatches of the frames, borrowed while recording
+    pub compute: Option<RefCell<ComputeContext>>,
+    /// Command buffers of the graphics queue handing the resources over to the other queues
+    pub queue_ownership: Option<QueueOwnershipContext>,
+}
 
+/// Objects used to record and present the frames, recreated with the swapchain
+#[derive(Default)]
+pub(crate) struct VulkanFrameContext {
     pub swapchain: Option<Swapchain>,
     pub image_index: u32,
     pub current_frame: u16,
 
     pub has_framebuffer_been_resized: bool,
+    /// Bumped every time the swapchain or the renderpass is recreated
+    /// The state built against the previous ones, e.g. the written descriptors, is stale
+    pub resource_epoch: u64,
+    /// Borrowed while drawing or destroying the attachments
+    pub framebuffer_cache: RefCell<FramebufferCache>,
 
+    /// Draws the world in the hdr attachment
     pub renderpass: Option<Renderpass>,
+    /// Draws a post processing pass in one of the offscreen attachments
+    pub post_process_renderpass: Option<Renderpass>,
+    /// Draws the last post processing pass and the ui in the swapchain image
+    pub present_renderpass: Option<Renderpass>,
+    /// The world renderpass is over for the frame being recorded
+    pub has_ui_pass_begun: bool,
+    /// Passes of the frame being recorded, borrowed while declaring or recording them
+    pub render_graph: RefCell<RenderGraph>,
+    /// Cameras drawn into textures or additional windows by the next frame
+    pub pending_camera_passes: Vec<CameraPass>,
+    /// Cameras drawn into textures or additional windows by the frame being recorded, before its world
+    pub camera_passes: Vec<CameraPass>,
+    /// Surface and swapchain of every additional window, presented with the main one
+    pub window_swapchains: HashMap<WindowHandle, WindowSwapchain>,
 
     pub graphics_command_pool: Option<CommandPool>,
     pub graphics_command_buffers: Vec<CommandBuffer>,
 
     pub sync_structures: Option<SyncStructure>,
 
+    pub query_pools: Option<QueryPools>,
+}
+
+/// Shaders and buffers shared by the drawn objects
+#[derive(Default)]
+pub(crate) struct VulkanResourceContext {
     pub builtin_shaders: Option<BuiltinShaders>,
 
     pub objects: Option<ObjectsBuffers>,
+
+    /// None when the textures are bound per object
+    pub bindless_textures: Option<BindlessTextures>,
+
+    /// Textures replaced since the last submitted frame
+    pub retired_textures: Vec<Texture>,
+    /// Textures destroyed once the frame is done on the gpu
+    pub retired_frame_textures: PerFrameResource<Vec<Texture>>,
+
+    /// Copy the image presented by the next frame
+    pub screenshot_requested: bool,
+    /// Copies read once their frame is done on the gpu
+    pub screenshot_readbacks: PerFrameResource<Option<ScreenshotReadback>>,
+    /// Last read copy, until the frontend takes it
+    pub screenshot: Option<ScreenshotPixels>,
+    /// Also copy the hdr attachment of the next frame, before the post processing
+    pub screenshot_hdr_requested: bool,
+    /// Copies of the hdr attachment, recorded by the render graph
+    pub screenshot_hdr_readbacks: PerFrameResource<Option<ScreenshotReadback>>,
+    /// Last read hdr copy, until the frontend takes it
+    pub screenshot_hdr: Option<HdrScreenshotPixels>,
+
+    /// Downsample the hdr attachment of every frame for the auto exposure
+    pub luminance_readback_requested: bool,
+    pub luminance_readback: Option<LuminanceReadback>,
+    /// Linear colors of the last read downsample, until the frontend takes them
+    pub luminance_samples: Option<Vec<glam::Vec3>>,
+
+    /// Bound as a black cube until the frontend uploads its probes
+    pub reflection_probes: Option<ReflectionProbeCubemaps>,
+}
+
+/// The parts can be borrowed separately, see `VulkanRendererBackend::split_context_mut`
+#[derive(Default)]
+pub(crate) struct VulkanContext<'a> {
+    pub core: VulkanCoreContext<'a>,
+    pub frame: VulkanFrameContext,
+    pub resources: VulkanResourceContext,
 }
 
 #[derive(Default)]
@@ -60,4 +172,45 @@ pub(crate) struct VulkanRendererBackend<'a> {
     pub framebuffer_width: u32,
     pub framebuffer_height: u32,
     pub frame_delta_time: f64,
+    /// Samples per pixel of the world subpass, selected from the anti aliasing setting
+    pub sample_count: SampleCountFlags,
+    /// Present mode requested for the swapchain
+    pub vsync_mode: VsyncMode,
+    /// Color range requested for the swapchain
+    pub display_output: DisplayOutput,
+    /// Depth formats requested for the depth attachments
+    pub depth_stencil: DepthStencilSettings,
+    /// Encoding of the swapchain images, selected with the surface format
+    pub output_color_space: OutputColorSpace,
+    /// Fullscreen passes between the world and the ui, given by the frontend every frame
+    pub post_process_settings: PostProcessSettings,
+    /// Accessibility matrix applied by the first post processing pass, skipped when it is the identity
+    pub color_filter: glam::Mat4,
+    /// Fraction of the framebuffer size the world is drawn at, validated by the frontend
+    pub render_scale: f32,
+
+    /// Id given to the next created texture
+    pub next_texture_id: u32,
+
+    pub statistics: RendererStatistics,
+    /// Last collected statistics, oldest first, for the crash reports
+    pub statistics_history: VecDeque<RendererStatistics>,
+}
+
+impl<'a> VulkanRendererBackend<'a> {
+    /// Borrow the parts of the context at the same time, e.g. to modify the shaders while recording commands
+    pub fn split_context_mut(
+        &mut self,
+    ) -> (
+        &VulkanCoreContext<'a>,
+        &VulkanFrameContext,
+        &mut VulkanResourceContext,
+    ) {
+        let VulkanContext {
+            core,
+            frame,
+            resources,
+        } = &mut self.context;
+        (core, frame, resources)
+    }
 }
@@ -1,27 +1,51 @@
 use std::ffi::c_void;
 
 use ash::vk::{
-    self, BufferCopy, BufferCreateInfo, BufferUsageFlags, CommandPool, DeviceMemory, Fence,
-    MemoryAllocateInfo, MemoryMapFlags, MemoryPropertyFlags, Queue, SharingMode,
+    self, BufferCopy, BufferCreateInfo, BufferUsageFlags, CommandPool, DescriptorBufferInfo,
+    DescriptorSet, DescriptorSetLayoutBinding, DescriptorType, Fence, MemoryMapFlags,
+    MemoryPropertyFlags, Queue, ShaderStageFlags, SharingMode, WriteDescriptorSet, WHOLE_SIZE,
 };
 
 use crate::{
     core::debug::errors::EngineError,
     error,
     renderer::vulkan::{
-        vulkan_init::command_buffer::CommandBuffer, vulkan_types::VulkanRendererBackend,
+        vulkan_init::{
+            allocator::MemoryAllocation, command_buffer::CommandBuffer,
+            queue_ownership::OwnershipTransfer,
+        },
+        vulkan_types::VulkanRendererBackend,
     },
 };
 
 #[derive(Default)]
 pub(crate) struct Buffer {
-    pub memory: DeviceMemory,
+    pub memory: MemoryAllocation,
     pub buffer: vk::Buffer,
     pub total_size: usize,
     pub buffer_usage_flags: BufferUsageFlags,
     pub memory_flags: MemoryPropertyFlags,
 }
 
+impl Buffer {
+    pub fn is_storage_buffer(&self) -> bool {
+        self.buffer_usage_flags
+            .contains(BufferUsageFlags::STORAGE_BUFFER)
+    }
+
+    /// Layout binding to access a storage buffer from the given stages
+    pub fn storage_layout_binding(
+        binding: u32,
+        stage_flags: ShaderStageFlags,
+    ) -> DescriptorSetLayoutBinding<'static> {
+        DescriptorSetLayoutBinding::default()
+            .binding(binding)
+            .descriptor_count(1)
+            .descriptor_type(DescriptorType::STORAGE_BUFFER)
+            .stage_flags(stage_flags)
+    }
+}
+
 pub(crate) struct BufferCopyParameters<'a> {
     pub src_buffer: &'a Buffer,
     pub src_offset: u64,
@@ -60,6 +84,30 @@ impl BufferCreatorParameters {
         self.size = size;
         self
     }
+
+    /// Device local storage buffer filled through transfers
+    /// Extra usages (VERTEX_BUFFER, INDIRECT_BUFFER, ...) can be added for compute or vertex access
+    pub fn storage(size: usize, extra_usage_flags: BufferUsageFlags) -> Self {
+        BufferCreatorParameters::default()
+            .size(size)
+            .should_be_bind(true)
+            .buffer_usage_flags(
+                BufferUsageFlags::STORAGE_BUFFER
+                    | BufferUsageFlags::TRANSFER_DST
+                    | BufferUsageFlags::TRANSFER_SRC
+                    | extra_usage_flags,
+            )
+            .memory_flags(MemoryPropertyFlags::DEVICE_LOCAL)
+    }
+
+    /// Host visible storage buffer written directly from the cpu every frame
+    pub fn host_visible_storage(size: usize, extra_usage_flags: BufferUsageFlags) -> Self {
+        BufferCreatorParameters::default()
+            .size(size)
+            .should_be_bind(true)
+            .buffer_usage_flags(BufferUsageFlags::STORAGE_BUFFER | extra_usage_flags)
+            .memory_flags(MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT)
+    }
 }
 
 impl VulkanRendererBackend<'_> {
@@ -67,12 +115,11 @@ impl VulkanRendererBackend<'_> {
         &self,
         buffer_creation_parameters: BufferCreatorParameters,
     ) -> Result<Buffer, EngineError> {
-        // Creation info
+        // Creation info, the queues hand the buffer over to each other
         let buffer_create_info = BufferCreateInfo::default()
             .size(buffer_creation_parameters.size as u64)
             .usage(buffer_creation_parameters.buffer_usage_flags)
-            .sharing_mode(SharingMode::EXCLUSIVE) // only used in one queue
-        ;
+            .sharing_mode(SharingMode::EXCLUSIVE);
 
         let device = self.get_device()?;
         let allocator = self.get_allocator()?;
@@ -101,19 +148,12 @@ impl VulkanRendererBackend<'_> {
                 return Err(EngineError::VulkanFailed);
             }
         };
-        // Allocate memory info
-        let memory_allocate_info = MemoryAllocateInfo::default()
-            .allocation_size(memory_requirements.size)
-            .memory_type_index(memory_index);
-
         // Allocate the memory
-        let memory = unsafe {
-            match device.allocate_memory(&memory_allocate_info, allocator) {
-                Ok(memory) => memory,
-                Err(err) => {
-                    error!("Failed to allocate a vulkan buffer memory: {:?}", err);
-                    return Err(EngineError::VulkanFailed);
-                }
+        let memory = match self.memory_allocate(memory_requirements, memory_index) {
+            Ok(memory) => memory,
+            Err(err) => {
+                error!("Failed to allocate a vulkan buffer memory: {:?}", err);
+                return Err(EngineError::VulkanFailed);
             }
         };
 
@@ -135,11 +175,77 @@ impl VulkanRendererBackend<'_> {
         Ok(new_buffer)
     }
 
+    pub(crate) fn create_storage_buffer(
+        &self,
+        buffer_creation_parameters: BufferCreatorParameters,
+    ) -> Result<Buffer, EngineError> {
+        if !buffer_creation_parameters
+            .buffer_usage_flags
+            .contains(BufferUsageFlags::STORAGE_BUFFER)
+        {
+            error!("Can't create a vulkan storage buffer without the storage buffer usage");
+            return Err(EngineError::InvalidValue);
+        }
+        let max_range = self
+            .get_physical_device_info()?
+            .properties
+            .limits
+            .max_storage_buffer_range as usize;
+        if buffer_creation_parameters.size > max_range {
+            error!(
+                "The vulkan storage buffer size {} exceeds the device limit of {}",
+                buffer_creation_parameters.size, max_range
+            );
+            return Err(EngineError::InvalidValue);
+        }
+        match self.create_buffer(buffer_creation_parameters) {
+            Ok(buffer) => Ok(buffer),
+            Err(err) => {
+                error!("Failed to create a vulkan storage buffer: {:?}", err);
+                Err(EngineError::InitializationFailed)
+            }
+        }
+    }
+
+    /// Point the given binding of a descriptor set to a storage buffer range
+    /// Use a range of None to bind the whole buffer from the offset
+    pub(crate) fn update_storage_buffer_descriptor(
+        &self,
+        descriptor_set: DescriptorSet,
+        binding: u32,
+        buffer: &Buffer,
+        offset: u64,
+        range: Option<u64>,
+    ) -> Result<(), EngineError> {
+        if !buffer.is_storage_buffer() {
+            error!("Can't bind a vulkan buffer without the storage usage as a storage buffer");
+            return Err(EngineError::InvalidValue);
+        }
+        let descriptor_buffer_info = [DescriptorBufferInfo::default()
+            .buffer(buffer.buffer)
+            .offset(offset)
+            .range(range.unwrap_or(WHOLE_SIZE))];
+        let descriptor_writes = [WriteDescriptorSet::default()
+            .dst_set(descriptor_set)
+            .dst_binding(binding)
+            .dst_array_element(0)
+            .descriptor_type(DescriptorType::STORAGE_BUFFER)
+            .descriptor_count(1)
+            .buffer_info(&descriptor_buffer_info)];
+        let device = self.get_device()?;
+        unsafe { device.update_descriptor_sets(&descriptor_writes, &[]) };
+        Ok(())
+    }
+
+    /// The offset is relative to the memory allocated for the buffer
     pub(crate) fn bind_buffer(&self, buffer: &Buffer, offset: u64) -> Result<(), EngineError> {
         let device = self.get_device()?;
-        let allocator = self.get_allocator()?;
         unsafe {
-            if let Err(err) = device.bind_buffer_memory(buffer.buffer, buffer.memory, offset) {
+            if let Err(err) = device.bind_buffer_memory(
+                buffer.buffer,
+                buffer.memory.memory,
+                buffer.memory.offset + offset,
+            ) {
                 error!("Failed to bind a vulkan buffer: {:?}", err);
                 return Err(EngineError::VulkanFailed);
             }
@@ -151,10 +257,9 @@ impl VulkanRendererBackend<'_> {
         let device = self.get_device()?;
         let allocator = self.get_allocator()?;
         unsafe {
-            device.free_memory(buffer.memory, allocator);
             device.destroy_buffer(buffer.buffer, allocator);
         }
-        Ok(())
+        self.memory_free(&buffer.memory)
     }
 
     fn map_memory_buffer(
@@ -164,25 +269,18 @@ impl VulkanRendererBackend<'_> {
         size: usize,
         flags: MemoryMapFlags,
     ) -> Result<*mut c_void, EngineError> {
-        let device = self.get_device()?;
-        unsafe {
-            // crate::debug!("memory_flags: {:?}", buffer.memory_flags);
-            match device.map_memory(buffer.memory, offset, size as u64, flags) {
-                Ok(data) => Ok(data),
-                Err(err) => {
-                    error!("Failed to lock the memory of a vulkan buffer: {:?}", err);
-                    Err(EngineError::VulkanFailed)
-                }
+        // crate::debug!("memory_flags: {:?}", buffer.memory_flags);
+        match self.memory_map(&buffer.memory, offset, size as u64) {
+            Ok(data) => Ok(data),
+            Err(err) => {
+                error!("Failed to lock the memory of a vulkan buffer: {:?}", err);
+                Err(EngineError::VulkanFailed)
             }
         }
     }
 
     fn unmap_memory_buffer(&self, buffer: &Buffer) -> Result<(), EngineError> {
-        let device = self.get_device()?;
-        unsafe {
-            device.unmap_memory(buffer.memory);
-        }
-        Ok(())
+        self.memory_unmap(&buffer.memory)
     }
 
     pub(crate) fn load_data_into_buffer(
@@ -218,26 +316,69 @@ impl VulkanRendererBackend<'_> {
         Ok(())
     }
 
+    /// Copy the content of a host visible buffer to the cpu
+    pub(crate) fn read_data_from_buffer(
+        &self,
+        buffer: &Buffer,
+        offset: u64,
+        size: usize,
+        flags: MemoryMapFlags,
+    ) -> Result<Vec<u8>, EngineError> {
+        let space_in_memory = match self.map_memory_buffer(buffer, offset, size, flags) {
+            Ok(space) => space,
+            Err(err) => {
+                error!(
+                    "Failed to lock memory when reading data from a vulkan buffer: {:?}",
+                    err
+                );
+                return Err(EngineError::AccessFailed);
+            }
+        };
+        let mut data = vec![0u8; size];
+        unsafe {
+            data.as_mut_ptr()
+                .copy_from_nonoverlapping(space_in_memory as *const u8, size);
+        }
+        if let Err(err) = self.unmap_memory_buffer(buffer) {
+            error!(
+                "Failed to unlock memory when reading data from a vulkan buffer: {:?}",
+                err
+            );
+            return Err(EngineError::AccessFailed);
+        }
+        Ok(data)
+    }
+
     pub(crate) fn copy_buffer_to(
         &self,
         command_parameters: BufferCommandParameters<'_>,
         copy_parameters: BufferCopyParameters<'_>,
         size: usize,
     ) -> Result<(), EngineError> {
-        if let Err(err) = self.device_wait_idle() {
+        // The buffers may still be written by the uploads or by the queue
+        if let Err(err) = self.transfer_wait_idle() {
             error!(
-                "Failed to wait for the device when copying a vulkan buffer: {:?}",
+                "Failed to wait for the uploads when copying a vulkan buffer: {:?}",
                 err
             );
             return Err(EngineError::VulkanFailed);
         }
+        let device = self.get_device()?;
+        unsafe {
+            if let Err(err) = device.queue_wait_idle(command_parameters.queue) {
+                error!(
+                    "Failed to wait for the queue when copying a vulkan buffer: {:?}",
+                    err
+                );
+                return Err(EngineError::VulkanFailed);
+            }
+        }
         let src_offset = copy_parameters.src_offset;
         let dst_offset = copy_parameters.dst_offset;
         let src_buffer = copy_parameters.src_buffer;
         let dst_buffer = copy_parameters.dst_buffer;
 
         // Create a one-time-use command buffer
-        let device = self.get_device()?;
         let command_buffer = match CommandBuffer::allocate_and_begin_single_use(
             device,
             command_parameters.command_pool,
@@ -307,7 +448,7 @@ impl VulkanRendererBackend<'_> {
         };
 
         // Gather memory requirements
-        let memory_requirements = unsafe { device.get_buffer_memory_requirements(buffer.buffer) };
+        let memory_requirements = unsafe { device.get_buffer_memory_requirements(new_buffer) };
         let memory_index = match self
             .device_find_memory_index(memory_requirements.memory_type_bits, buffer.memory_flags)
         {
@@ -320,24 +461,23 @@ impl VulkanRendererBackend<'_> {
                 return Err(EngineError::InvalidValue);
             }
         };
-        // Allocate memory info
-        let memory_allocate_info = MemoryAllocateInfo::default()
-            .allocation_size(memory_requirements.size)
-            .memory_type_index(memory_index);
         // Allocate the memory
-        let new_memory = unsafe {
-            match device.allocate_memory(&memory_allocate_info, allocator) {
-                Ok(memory) => memory,
-                Err(err) => {
-                    error!("Failed to allocate a vulkan buffer memory for vulkan buffer resizing: {:?}", err);
-                    return Err(EngineError::VulkanFailed);
-                }
+        let new_memory = match self.memory_allocate(memory_requirements, memory_index) {
+            Ok(memory) => memory,
+            Err(err) => {
+                error!(
+                    "Failed to allocate a vulkan buffer memory for vulkan buffer resizing: {:?}",
+                    err
+                );
+                return Err(EngineError::VulkanFailed);
             }
         };
 
         // Bind the new buffer's memory
         unsafe {
-            if let Err(err) = device.bind_buffer_memory(new_buffer, new_memory, 0) {
+            if let Err(err) =
+                device.bind_buffer_memory(new_buffer, new_memory.memory, new_memory.offset)
+            {
                 error!(
                     "Failed to bind a vulkan buffer memory for vulkan buffer resizing: {:?}",
                     err
@@ -391,63 +531,41 @@ impl VulkanRendererBackend<'_> {
         Ok(new_buffer)
     }
 
+    /// Stage the data and copy it on the transfer queue without waiting for the copy
+    /// The range must not be read by the frames in flight, the next frame waits for the copy
     pub(crate) fn upload_data_range(
         &self,
-        command_parameters: BufferCommandParameters<'_>,
         buffer: &Buffer,
         offset: u64,
         size: usize,
         data: *mut c_void,
     ) -> Result<(), EngineError> {
-        // Create a host-visible staging buffer to upload to
-        // Mark it as the source of the transfer
-        let staging_buffer_memory_flags =
-            MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT;
-        let staging_buffer_create_params = BufferCreatorParameters::default()
-            .memory_flags(staging_buffer_memory_flags)
-            .size(size)
-            .buffer_usage_flags(BufferUsageFlags::TRANSFER_SRC)
-            .should_be_bind(true);
-        let staging_buffer = match self.create_buffer(staging_buffer_create_params) {
-            Ok(buffer) => buffer,
-            Err(err) => {
-                error!("Failed to create a vulkan staging buffer: {:?}", err);
-                return Err(err);
-            }
-        };
-
-        // Load the data into the staging buffer
-        if let Err(err) =
-            self.load_data_into_buffer(&staging_buffer, offset, size, MemoryMapFlags::empty(), data)
-        {
-            error!(
-                "Failed to load data into a vulkan staging buffer: {:?}",
-                err
-            );
-            return Err(err);
-        }
-
-        // Perform the copy from staging to the device local buffer
-        let copy_parameters = BufferCopyParameters {
-            src_buffer: &staging_buffer,
-            src_offset: 0,
-            dst_buffer: buffer,
-            dst_offset: offset,
-        };
-        if let Err(err) = self.copy_buffer_to(command_parameters, copy_parameters, size) {
-            error!(
-                "Failed to copy data from a vulkan staging buffer: {:?}",
-                err
-            );
-            return Err(err);
-        }
-
-        // Clean up the staging buffer
-        if let Err(err) = self.destroy_buffer(&staging_buffer) {
-            error!("Failed to destroy a vulkan staging buffer: {:?}", err);
+        let record_copy =
+            |command_buffer: &CommandBuffer, staging_buffer: &Buffer, staging_offset| {
+                let copy_regions = [BufferCopy::default()
+                    .src_offset(staging_offset)
+                    .dst_offset(offset)
+                    .size(size as u64)];
+                let device = self.get_device()?;
+                unsafe {
+                    device.cmd_copy_buffer(
+                        *command_buffer.handler.as_ref(),
+                        staging_buffer.buffer,
+                        buffer.buffer,
+                        &copy_regions,
+                    );
+                }
+                Ok(OwnershipTransfer::uploaded_buffer_range(
+                    buffer.buffer,
+                    offset,
+                    size as u64,
+                ))
+            };
+        // Copies between buffers only need the offsets to be aligned on 4 bytes
+        if let Err(err) = self.transfer_upload(data, size, 4, record_copy) {
+            error!("Failed to upload data into a vulkan buffer: {:?}", err);
             return Err(err);
         }
-
         Ok(())
     }
 }
@@ -0,0 +1,412 @@
+use ash::vk::{
+    AccessFlags, ClearAttachment, ClearColorValue, ClearRect, ClearValue, DependencyFlags,
+    Extent2D, Filter, ImageAspectFlags, ImageBlit, ImageLayout, ImageMemoryBarrier,
+    ImageSubresourceLayers, ImageSubresourceRange, ImageView, Offset2D, Offset3D,
+    PipelineStageFlags, Rect2D, Viewport, QUEUE_FAMILY_IGNORED,
+};
+
+use crate::{
+    core::{application::windows::WindowHandle, debug::errors::EngineError},
+    error,
+    renderer::{
+        renderer_types::CameraPassTarget,
+        scene::camera::CameraViewport,
+        vulkan::{
+            vulkan_shaders::builtin_shaders::{
+                object_shaders::VULKAN_OBJECT_SHADERS_MAX_VIEWS,
+                post_process_shaders::{
+                    post_process_get_passes, PostProcessConstants, PostProcessEffect,
+                },
+            },
+            vulkan_types::VulkanRendererBackend,
+        },
+    },
+};
+
+use super::texture::Texture;
+
+/// What a camera pass draws into
+#[derive(Clone, Copy)]
+pub(crate) enum VulkanCameraTarget {
+    /// The pass is copied into an area of the texture
+    Texture { texture: Texture, area: Rect2D },
+    /// The pass is presented in the viewport of the window, its size is only known once its image is acquired
+    Window {
+        window: WindowHandle,
+        viewport: Option<CameraViewport>,
+    },
+}
+
+/// Camera drawn into a texture or an additional window before the world, in its own pass of the render graph
+#[derive(Clone, Copy)]
+pub(crate) struct CameraPass {
+    pub target: VulkanCameraTarget,
+}
+
+/// Windows drawn by the camera passes, in their order
+pub(crate) fn camera_passes_get_windows(passes: &[CameraPass]) -> Vec<WindowHandle> {
+    passes
+        .iter()
+        .filter_map(|pass| match pass.target {
+            VulkanCameraTarget::Window { window, .. } => Some(window),
+            VulkanCameraTarget::Texture { .. } => None,
+        })
+        .collect()
+}
+
+/// Area of the target covered by the viewport, none if they don't overlap
+pub(crate) fn camera_pass_clip_viewport(
+    viewport: Option<CameraViewport>,
+    width: u32,
+    height: u32,
+) -> Option<Rect2D> {
+    let viewport = viewport.unwrap_or(CameraViewport::new(width as f32, height as f32));
+    let x_min = viewport.x.clamp(0.0, width as f32) as u32;
+    let y_min = viewport.y.clamp(0.0, height as f32) as u32;
+    let x_max = (viewport.x + viewport.width).clamp(0.0, width as f32) as u32;
+    let y_max = (viewport.y + viewport.height).clamp(0.0, height as f32) as u32;
+    if x_max <= x_min || y_max <= y_min {
+        return None;
+    }
+    Some(Rect2D {
+        offset: Offset2D {
+            x: x_min as i32,
+            y: y_min as i32,
+        },
+        extent: Extent2D {
+            width: x_max - x_min,
+            height: y_max - y_min,
+        },
+    })
+}
+
+impl VulkanRendererBackend<'_> {
+    /// Declare a camera drawn into the texture or the window by the next frame
+    pub(crate) fn camera_pass_add(
+        &mut self,
+        target: CameraPassTarget<'_>,
+        viewport: Option<CameraViewport>,
+    ) -> Result<(), EngineError> {
+        // The main view uses the last uniform slot of the frame
+        if self.context.frame.pending_camera_passes.len() + 1 >= VULKAN_OBJECT_SHADERS_MAX_VIEWS {
+            error!(
+                "Can't draw more than {} cameras into textures or windows per frame",
+                VULKAN_OBJECT_SHADERS_MAX_VIEWS - 1
+            );
+            return Err(EngineError::InvalidValue);
+        }
+        let target = match target {
+            CameraPassTarget::Texture(texture) => {
+                let texture = match texture.as_any().downcast_ref::<Texture>() {
+                    Some(texture) => *texture,
+                    None => {
+                        error!("Can't draw a camera into a texture that isn't a vulkan texture");
+                        return Err(EngineError::InvalidValue);
+                    }
+                };
+                let area = match camera_pass_clip_viewport(viewport, texture.width, texture.height)
+                {
+                    Some(area) => area,
+                    None => {
+                        error!(
+                            "The viewport {:?} is outside of the {}x{} texture the camera draws into",
+                            viewport, texture.width, texture.height
+                        );
+                        return Err(EngineError::InvalidValue);
+                    }
+                };
+                VulkanCameraTarget::Texture { texture, area }
+            }
+            CameraPassTarget::Window(window) => {
+                self.get_window_swapchain(window)?;
+                // A window presents a single image per frame
+                if camera_passes_get_windows(&self.context.frame.pending_camera_passes)
+                    .contains(&window)
+                {
+                    error!(
+                        "Can't draw more than one camera into the window {:?}",
+                        window
+                    );
+                    return Err(EngineError::Duplicate);
+                }
+                VulkanCameraTarget::Window { window, viewport }
+            }
+        };
+        self.context
+            .frame
+            .pending_camera_passes
+            .push(CameraPass { target });
+        Ok(())
+    }
+
+    /// The cameras declared since the last frame are drawn by the frame beginning
+    pub(crate) fn camera_passes_begin_frame(&mut self) {
+        self.context.frame.camera_passes =
+            std::mem::take(&mut self.context.frame.pending_camera_passes);
+    }
+
+    pub(crate) fn camera_pass_get(&self, index: usize) -> Result<&CameraPass, EngineError> {
+        match self.context.frame.camera_passes.get(index) {
+            Some(pass) => Ok(pass),
+            None => {
+                error!("The frame has no camera pass {}", index);
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
+
+    /// Area of the target the camera is drawn into, none if the window has no image this frame or the viewport is outside of it
+    pub(crate) fn camera_pass_get_target_area(
+        &self,
+        index: usize,
+    ) -> Result<Option<Rect2D>, EngineError> {
+        Ok(match self.camera_pass_get(index)?.target {
+            VulkanCameraTarget::Texture { area, .. } => Some(area),
+            VulkanCameraTarget::Window { window, viewport } => self
+                .window_swapchain_get_acquired_image(window)
+                .and_then(|(_, _, extent)| {
+                    camera_pass_clip_viewport(viewport, extent.width, extent.height)
+                }),
+        })
+    }
+
+    /// Image of the window drawn by the camera pass, none if it draws into a texture or the window has no image this frame
+    pub(crate) fn camera_pass_get_window_image(
+        &self,
+        index: usize,
+    ) -> Result<Option<(ash::vk::Image, ImageView, Extent2D)>, EngineError> {
+        Ok(match self.camera_pass_get(index)?.target {
+            VulkanCameraTarget::Window { window, .. } => {
+                self.window_swapchain_get_acquired_image(window)
+            }
+            VulkanCameraTarget::Texture { .. } => None,
+        })
+    }
+
+    /// Area drawn in the attachments of the pass, they have the size of the swapchain
+    /// The copy or the presentation stretches it over the target area when it is larger
+    pub(crate) fn camera_pass_get_draw_extent(
+        &self,
+        index: usize,
+    ) -> Result<Extent2D, EngineError> {
+        let swapchain_extent = self.get_swapchain()?.extent;
+        Ok(match self.camera_pass_get_target_area(index)? {
+            Some(area) => Extent2D {
+                width: area.extent.width.min(swapchain_extent.width),
+                height: area.extent.height.min(swapchain_extent.height),
+            },
+            None => swapchain_extent,
+        })
+    }
+
+    /// Flipped viewport and scissor of the area drawn by the open camera pass
+    pub(crate) fn camera_pass_set_viewport(&self, index: usize) -> Result<(), EngineError> {
+        let extent = self.camera_pass_get_draw_extent(index)?;
+        let current_frame_index = self.get_current_frame_index();
+        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
+        let device = self.get_device()?;
+        let viewport = [Viewport::default()
+            .x(0.)
+            .y(extent.height as f32)
+            .width(extent.width as f32)
+            .height(-(extent.height as f32))
+            .min_depth(0.)
+            .max_depth(1.)];
+        let scissor = [Rect2D::default().extent(extent)];
+        unsafe {
+            device.cmd_set_viewport(*command_buffer.handler.as_ref(), 0, &viewport);
+            device.cmd_set_scissor(*command_buffer.handler.as_ref(), 0, &scissor);
+        }
+        Ok(())
+    }
+
+    fn camera_pass_barrier(
+        &self,
+        image: ash::vk::Image,
+        (old_layout, new_layout): (ImageLayout, ImageLayout),
+        (src_access_mask, dst_access_mask): (AccessFlags, AccessFlags),
+        (src_stage, dst_stage): (PipelineStageFlags, PipelineStageFlags),
+    ) -> Result<(), EngineError> {
+        let subresource = ImageSubresourceRange::default()
+            .aspect_mask(ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(1)
+            .base_array_layer(0)
+            .layer_count(1);
+        let barriers = [ImageMemoryBarrier::default()
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_queue_family_index(QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(QUEUE_FAMILY_IGNORED)
+            .src_access_mask(src_access_mask)
+            .dst_access_mask(dst_access_mask)
+            .image(image)
+            .subresource_range(subresource)];
+        let current_frame_index = self.get_current_frame_index();
+        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
+        unsafe {
+            self.get_device()?.cmd_pipeline_barrier(
+                *command_buffer.handler.as_ref(),
+                src_stage,
+                dst_stage,
+                DependencyFlags::empty(),
+                &[],
+                &[],
+                &barriers,
+            );
+        }
+        Ok(())
+    }
+
+    /// Blit the drawn area of the hdr attachment, already in the transfer source layout, into the target area of the texture
+    /// The texture is sampled again by the following passes, its colors are clamped by its format
+    pub(crate) fn camera_pass_record_copy(
+        &self,
+        index: usize,
+        hdr_image: ash::vk::Image,
+    ) -> Result<(), EngineError> {
+        let VulkanCameraTarget::Texture { texture, area } = self.camera_pass_get(index)?.target
+        else {
+            error!("The camera pass {} doesn't draw into a texture", index);
+            return Err(EngineError::InvalidValue);
+        };
+        let extent = self.camera_pass_get_draw_extent(index)?;
+        let subresource = ImageSubresourceLayers::default()
+            .aspect_mask(ImageAspectFlags::COLOR)
+            .mip_level(0)
+            .base_array_layer(0)
+            .layer_count(1);
+
+        self.camera_pass_barrier(
+            texture.image.image,
+            (
+                ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+            ),
+            (AccessFlags::SHADER_READ, AccessFlags::TRANSFER_WRITE),
+            (
+                PipelineStageFlags::FRAGMENT_SHADER,
+                PipelineStageFlags::TRANSFER,
+            ),
+        )?;
+        let blits = [ImageBlit::default()
+            .src_subresource(subresource)
+            .src_offsets([
+                Offset3D::default(),
+                Offset3D::default()
+                    .x(extent.width as i32)
+                    .y(extent.height as i32)
+                    .z(1),
+            ])
+            .dst_subresource(subresource)
+            .dst_offsets([
+                Offset3D::default().x(area.offset.x).y(area.offset.y),
+                Offset3D::default()
+                    .x(area.offset.x + area.extent.width as i32)
+                    .y(area.offset.y + area.extent.height as i32)
+                    .z(1),
+            ])];
+        let current_frame_index = self.get_current_frame_index();
+        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
+        unsafe {
+            self.get_device()?.cmd_blit_image(
+                *command_buffer.handler.as_ref(),
+                hdr_image,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                texture.image.image,
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                &blits,
+                Filter::LINEAR,
+            );
+        }
+        self.camera_pass_barrier(
+            texture.image.image,
+            (
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            ),
+            (AccessFlags::TRANSFER_WRITE, AccessFlags::SHADER_READ),
+            (
+                PipelineStageFlags::TRANSFER,
+                PipelineStageFlags::FRAGMENT_SHADER,
+            ),
+        )
+    }
+
+    /// Stretch the drawn area of the hdr attachment over the viewport of the window, its renderpass must be begun over the whole window
+    /// The rest of the window is cleared, only the pass converting the colors to the output is applied
+    pub(crate) fn camera_pass_record_window_present(
+        &self,
+        index: usize,
+        hdr_view: ImageView,
+    ) -> Result<(), EngineError> {
+        let Some((_, _, window_extent)) = self.camera_pass_get_window_image(index)? else {
+            error!("The camera pass {} has no window image to present", index);
+            return Err(EngineError::InvalidValue);
+        };
+        let area = self.camera_pass_get_target_area(index)?;
+        if area.map(|area| area.extent) != Some(window_extent) {
+            let clear_attachments = [ClearAttachment::default()
+                .aspect_mask(ImageAspectFlags::COLOR)
+                .color_attachment(0)
+                .clear_value(ClearValue {
+                    color: ClearColorValue {
+                        float32: [0.0, 0.0, 0.0, 1.0],
+                    },
+                })];
+            let clear_rects = [ClearRect::default()
+                .rect(Rect2D::default().extent(window_extent))
+                .base_array_layer(0)
+                .layer_count(1)];
+            let current_frame_index = self.get_current_frame_index();
+            let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
+            unsafe {
+                self.get_device()?.cmd_clear_attachments(
+                    *command_buffer.handler.as_ref(),
+                    &clear_attachments,
+                    &clear_rects,
+                )
+            };
+        }
+        // The viewport is outside of the window
+        let Some(area) = area else {
+            return Ok(());
+        };
+
+        // The whole attachment is stretched so that its drawn area covers the viewport
+        let draw_extent = self.camera_pass_get_draw_extent(index)?;
+        let swapchain_extent = self.get_swapchain()?.extent;
+        let viewport = Viewport::default()
+            .x(area.offset.x as f32)
+            .y(area.offset.y as f32)
+            .width(
+                area.extent.width as f32 * swapchain_extent.width as f32 / draw_extent.width as f32,
+            )
+            .height(
+                area.extent.height as f32 * swapchain_extent.height as f32
+                    / draw_extent.height as f32,
+            )
+            .min_depth(0.)
+            .max_depth(1.);
+        let texel_size = glam::Vec2::new(
+            1.0 / swapchain_extent.width.max(1) as f32,
+            1.0 / swapchain_extent.height.max(1) as f32,
+        );
+        let (effect, constants) = post_process_get_passes(
+            &self.post_process_settings,
+            self.color_filter,
+            self.output_color_space,
+            texel_size,
+        )
+        .into_iter()
+        .find(|(effect, _)| *effect == PostProcessEffect::Tonemap)
+        .unwrap_or((
+            PostProcessEffect::Copy,
+            PostProcessConstants {
+                texel_size,
+                ..Default::default()
+            },
+        ));
+        self.draw_post_process_pass_area(effect, &constants, hdr_view, true, (viewport, area))
+    }
+}
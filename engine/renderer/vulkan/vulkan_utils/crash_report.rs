@@ -0,0 +1,126 @@
+use std::{fmt::Write as _, fs::File, io::Write as _, path::PathBuf};
+
+use crate::{
+    core::{debug::errors::EngineError, systems::logger::logger_flush},
+    error,
+    renderer::vulkan::{
+        vulkan_init::debug::get_recent_validation_messages, vulkan_types::VulkanRendererBackend,
+    },
+};
+
+/// Number of frames of statistics kept for the crash reports
+pub(crate) const VULKAN_CRASH_REPORT_FRAME_COUNT: usize = 16;
+
+impl VulkanRendererBackend<'_> {
+    fn crash_report_build(&self) -> String {
+        // Writing to a string can't fail
+        let mut report = String::new();
+        let _ = writeln!(report, "=== Renderer state ===");
+        let _ = writeln!(report, "frame number: {}", self.frame_number);
+        let _ = writeln!(
+            report,
+            "current frame: {}",
+            self.context.frame.current_frame
+        );
+        let _ = writeln!(report, "image index: {}", self.context.frame.image_index);
+        let _ = writeln!(
+            report,
+            "framebuffer: {}x{}",
+            self.framebuffer_width, self.framebuffer_height
+        );
+
+        let _ = writeln!(report, "\n=== Device ===");
+        if let Some(info) = &self.context.core.physical_device_info {
+            let properties = &info.properties;
+            let device_name = properties
+                .device_name_as_c_str()
+                .map_or(String::from("unknown"), |name| {
+                    name.to_string_lossy().into_owned()
+                });
+            let _ = writeln!(report, "name: {}", device_name);
+            let _ = writeln!(
+                report,
+                "api version: {}.{}.{}",
+                ash::vk::api_version_major(properties.api_version),
+                ash::vk::api_version_minor(properties.api_version),
+                ash::vk::api_version_patch(properties.api_version)
+            );
+            let _ = writeln!(report, "driver version: {}", properties.driver_version);
+        }
+        let _ = writeln!(report, "enabled extensions:");
+        if let Some(requirements) = &self.context.core.device_requirements {
+            for extension in &requirements.extensions {
+                let _ = writeln!(report, "\t{}", extension.to_string_lossy());
+            }
+        }
+
+        let _ = writeln!(report, "\n=== In flight submissions ===");
+        if let (Ok(device), Ok(sync_structures)) = (self.get_device(), self.get_sync_structures()) {
+            for (frame, fence) in sync_structures.in_flight_fences.iter().enumerate() {
+                let status = unsafe { device.get_fence_status(*fence.handler.as_ref()) };
+                let status = match status {
+                    Ok(true) => String::from("completed"),
+                    Ok(false) => String::from("pending"),
+                    Err(err) => format!("{:?}", err),
+                };
+                let _ = writeln!(report, "frame {}: {}", frame, status);
+            }
+        }
+
+        let _ = writeln!(report, "\n=== Last frames statistics ===");
+        for statistics in &self.statistics_history {
+            let _ = writeln!(report, "{:?}", statistics);
+        }
+
+        let _ = writeln!(report, "\n=== Last validation messages ===");
+        for message in get_recent_validation_messages() {
+            let _ = writeln!(report, "{}", message);
+        }
+        report
+    }
+
+    /// Write what is known about the renderer next to the log file and flush the logs
+    pub fn crash_report_write(&self) -> Result<PathBuf, EngineError> {
+        let crate_path = env!("CARGO_MANIFEST_DIR");
+        let report_path: PathBuf = [crate_path, "crash_report.log"].iter().collect();
+        let report = self.crash_report_build();
+
+        let mut file = match File::create(&report_path) {
+            Ok(file) => file,
+            Err(err) => {
+                error!(
+                    "Failed to create the crash report {:?}: {:?}",
+                    report_path, err
+                );
+                return Err(EngineError::IO);
+            }
+        };
+        if let Err(err) = file
+            .write_all(report.as_bytes())
+            .and_then(|()| file.sync_all())
+        {
+            error!(
+                "Failed to write the crash report {:?}: {:?}",
+                report_path, err
+            );
+            return Err(EngineError::IO);
+        }
+        if let Err(err) = logger_flush() {
+            error!("Failed to flush the logs for the crash report: {:?}", err);
+            return Err(EngineError::IO);
+        }
+        Ok(report_path)
+    }
+
+    /// The device can't be recovered, dump the diagnostics before the engine aborts
+    pub fn on_device_lost(&self) -> EngineError {
+        match self.crash_report_write() {
+            Ok(path) => error!("Lost the vulkan device, crash report written to {:?}", path),
+            Err(err) => error!(
+                "Lost the vulkan device and failed to write the crash report: {:?}",
+                err
+            ),
+        }
+        EngineError::DeviceLost
+    }
+}
@@ -59,6 +59,10 @@ impl Fence {
                     );
                     Ok(())
                 }
+                Err(ash::vk::Result::ERROR_DEVICE_LOST) => {
+                    error!("Lost the vulkan device while waiting for a fence");
+                    Err(EngineError::DeviceLost)
+                }
                 Err(err) => {
                     error!("Failed to wait for a vulkan fence: {:?}", err);
                     Err(EngineError::VulkanFailed)
@@ -0,0 +1,232 @@
+use crate::{
+    core::debug::errors::EngineError,
+    error,
+    renderer::{
+        renderer_types::VertexData,
+        vulkan::{vulkan_init::objects::BufferRange, vulkan_types::VulkanRendererBackend},
+    },
+    resources::geometry::GeometryCreatorParameters,
+};
+
+/// Vertices and indices stored in the shared objects buffers
+#[derive(Clone, Copy)]
+pub(crate) struct Geometry {
+    pub id: u32,
+    pub generation: Option<u32>,
+    pub vertex_count: u32,
+    pub vertex_range: BufferRange,
+    pub index_count: u32,
+    pub index_range: BufferRange,
+}
+
+impl crate::resources::geometry::Geometry for Geometry {
+    fn get_id(&self) -> u32 {
+        self.id
+    }
+
+    fn get_vertex_count(&self) -> u32 {
+        self.vertex_count
+    }
+
+    fn get_index_count(&self) -> u32 {
+        self.index_count
+    }
+
+    fn get_generation(&self) -> Option<u32> {
+        self.generation
+    }
+
+    fn as_any(&self) -> &dyn std::any::Any {
+        self
+    }
+
+    fn clone_box(&self) -> Box<dyn crate::resources::geometry::Geometry> {
+        Box::new(*self)
+    }
+}
+
+impl VulkanRendererBackend<'_> {
+    fn upload_geometry_range<T>(
+        &self,
+        data: &[T],
+        range: BufferRange,
+        is_index: bool,
+    ) -> Result<(), EngineError> {
+        let objects_buffers = self.get_objects_buffers()?;
+        let buffer = if is_index {
+            &objects_buffers.index_buffer
+        } else {
+            &objects_buffers.vertex_buffer
+        };
+        self.upload_data_range(
+            buffer,
+            range.offset,
+            range.size as usize,
+            data.as_ptr() as *mut std::ffi::c_void,
+        )
+    }
+
+    pub(crate) fn vulkan_create_geometry(
+        &mut self,
+        params: GeometryCreatorParameters,
+    ) -> Result<Geometry, EngineError> {
+        if params.vertices.is_empty() {
+            error!(
+                "Can't create the geometry `{}' without vertices",
+                params.name
+            );
+            return Err(EngineError::InvalidValue);
+        }
+        let vertex_size = std::mem::size_of_val(params.vertices) as u64;
+        let index_size = std::mem::size_of_val(params.indices) as u64;
+
+        let objects_buffers = self.get_objects_buffers_mut()?;
+        let vertex_range = match objects_buffers.allocate_vertices(vertex_size) {
+            Some(range) => range,
+            None => {
+                error!(
+                    "Not enough space left in the vertex buffer for the geometry `{}'",
+                    params.name
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        let index_range = match objects_buffers.allocate_indices(index_size) {
+            Some(range) => range,
+            None => {
+                objects_buffers.free_vertices(vertex_range);
+                error!(
+                    "Not enough space left in the index buffer for the geometry `{}'",
+                    params.name
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        let id = objects_buffers.next_geometry_id;
+        objects_buffers.next_geometry_id += 1;
+
+        let geometry = Geometry {
+            id,
+            generation: Some(0),
+            vertex_count: params.vertices.len() as u32,
+            vertex_range,
+            index_count: params.indices.len() as u32,
+            index_range,
+        };
+
+        if let Err(err) = self.upload_geometry_range(params.vertices, vertex_range, false) {
+            error!(
+                "Failed to upload the vertices of the geometry `{}': {:?}",
+                params.name, err
+            );
+            self.vulkan_destroy_geometry(&geometry)?;
+            return Err(EngineError::InitializationFailed);
+        }
+        if index_size > 0 {
+            if let Err(err) = self.upload_geometry_range(params.indices, index_range, true) {
+                error!(
+                    "Failed to upload the indices of the geometry `{}': {:?}",
+                    params.name, err
+                );
+                self.vulkan_destroy_geometry(&geometry)?;
+                return Err(EngineError::InitializationFailed);
+            }
+        }
+        Ok(geometry)
+    }
+
+    /// The frames in flight may still draw the previous vertices
+    pub(crate) fn vulkan_update_geometry_vertices(
+        &self,
+        geometry: &Geometry,
+        vertices: &[VertexData],
+    ) -> Result<(), EngineError> {
+        if vertices.len() != geometry.vertex_count as usize {
+            error!(
+                "Can't replace the {} vertices of the geometry {} by {} vertices",
+                geometry.vertex_count,
+                geometry.id,
+                vertices.len()
+            );
+            return Err(EngineError::InvalidValue);
+        }
+        self.upload_geometry_range(vertices, geometry.vertex_range, false)
+    }
+
+    pub(crate) fn vulkan_destroy_geometry(
+        &mut self,
+        geometry: &Geometry,
+    ) -> Result<(), EngineError> {
+        // The ranges may still be read by frames in flight
+        if let Err(err) = self.device_wait_idle() {
+            error!(
+                "Failed to wait idle when destroying a vulkan geometry: {:?}",
+                err
+            );
+            return Err(EngineError::ShutdownFailed);
+        }
+        let objects_buffers = self.get_objects_buffers_mut()?;
+        objects_buffers.free_vertices(geometry.vertex_range);
+        if geometry.index_range.size > 0 {
+            objects_buffers.free_indices(geometry.index_range);
+        }
+        Ok(())
+    }
+
+    /// Bind the geometry buffers and record its draw
+    /// The instances are read from the instance buffer of the frame, the first one is the identity
+    pub(crate) fn vulkan_draw_geometry(
+        &self,
+        geometry: &Geometry,
+        first_instance: u32,
+        instance_count: u32,
+    ) -> Result<(), EngineError> {
+        let current_frame_index = self.get_current_frame_index();
+        let command_buffer = &self.get_graphics_command_buffers()?[current_frame_index];
+        let device = self.get_device()?;
+        let objects_buffers = self.get_objects_buffers()?;
+        let instance_buffer = self
+            .get_object_shaders()?
+            .instance_buffers
+            .get(current_frame_index);
+        let vertex_buffers = [objects_buffers.vertex_buffer.buffer, instance_buffer.buffer];
+        let offsets = [geometry.vertex_range.offset, 0];
+        unsafe {
+            device.cmd_bind_vertex_buffers(
+                *command_buffer.handler.as_ref(),
+                0,
+                &vertex_buffers,
+                &offsets,
+            );
+        }
+        if geometry.index_count > 0 {
+            unsafe {
+                device.cmd_bind_index_buffer(
+                    *command_buffer.handler.as_ref(),
+                    objects_buffers.index_buffer.buffer,
+                    geometry.index_range.offset,
+                    ash::vk::IndexType::UINT32,
+                );
+                device.cmd_draw_indexed(
+                    *command_buffer.handler.as_ref(),
+                    geometry.index_count,
+                    instance_count,
+                    0,
+                    0,
+                    first_instance,
+                );
+            }
+        } else {
+            unsafe {
+                device.cmd_draw(
+                    *command_buffer.handler.as_ref(),
+                    geometry.vertex_count,
+                    instance_count,
+                    0,
+                    first_instance,
+                );
+            }
+        }
+        Ok(())
+    }
+}
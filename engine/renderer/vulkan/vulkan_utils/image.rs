@@ -1,16 +1,17 @@
 use ash::vk::{
-    self, AccessFlags, BufferImageCopy, DependencyFlags, DeviceMemory, Extent3D, Format,
-    ImageAspectFlags, ImageCreateInfo, ImageLayout, ImageMemoryBarrier, ImageSubresourceLayers,
+    self, AccessFlags, BufferImageCopy, DependencyFlags, Extent3D, Format, ImageAspectFlags,
+    ImageCreateFlags, ImageCreateInfo, ImageLayout, ImageMemoryBarrier, ImageSubresourceLayers,
     ImageSubresourceRange, ImageTiling, ImageType, ImageUsageFlags, ImageView, ImageViewCreateInfo,
-    ImageViewType, MemoryAllocateInfo, MemoryPropertyFlags, PipelineStageFlags, SampleCountFlags,
-    SharingMode,
+    ImageViewType, MemoryPropertyFlags, Offset3D, PipelineStageFlags, SampleCountFlags,
+    SharingMode, QUEUE_FAMILY_IGNORED,
 };
 
 use crate::{
     core::debug::errors::EngineError,
     error,
     renderer::vulkan::{
-        vulkan_init::command_buffer::CommandBuffer, vulkan_types::VulkanRendererBackend,
+        vulkan_init::{allocator::MemoryAllocation, command_buffer::CommandBuffer},
+        vulkan_types::VulkanRendererBackend,
     },
 };
 
@@ -18,13 +19,23 @@ use super::buffer::Buffer;
 
 #[derive(Default, Clone, Copy)]
 pub(crate) struct Image {
-    pub memory: DeviceMemory,
+    pub memory: MemoryAllocation,
     pub image: vk::Image,
     pub image_view: Option<ImageView>,
     pub width: u32,
     pub height: u32,
 }
 
+impl Image {
+    /// Only the images created with `should_create_view` have one
+    pub fn get_image_view(&self) -> Result<ImageView, EngineError> {
+        self.image_view.ok_or_else(|| {
+            error!("The vulkan image {:?} has no view", self.image);
+            EngineError::AccessFailed
+        })
+    }
+}
+
 pub(crate) struct ImageCreatorParameters {
     pub image_type: ImageType,
     pub width: u32,
@@ -35,6 +46,14 @@ pub(crate) struct ImageCreatorParameters {
     pub memory_flags: MemoryPropertyFlags,
     pub should_create_view: bool,
     pub image_view_aspect_flags: ImageAspectFlags,
+    /// Multisampled images only have one mip level
+    pub sample_count: SampleCountFlags,
+    /// Levels of the image and of its view, by default the image has 4 and the view sees the first
+    pub mip_levels: Option<u32>,
+    /// The six faces of every cube of a cube array are consecutive layers
+    pub array_layers: u32,
+    pub image_view_type: ImageViewType,
+    pub image_create_flags: ImageCreateFlags,
 }
 
 impl ImageCreatorParameters {
@@ -74,6 +93,26 @@ impl ImageCreatorParameters {
         self.image_view_aspect_flags = image_view_aspect_flags;
         self
     }
+    pub fn sample_count(mut self, sample_count: SampleCountFlags) -> Self {
+        self.sample_count = sample_count;
+        self
+    }
+    pub fn mip_levels(mut self, mip_levels: u32) -> Self {
+        self.mip_levels = Some(mip_levels);
+        self
+    }
+    pub fn array_layers(mut self, array_layers: u32) -> Self {
+        self.array_layers = array_layers;
+        self
+    }
+    pub fn image_view_type(mut self, image_view_type: ImageViewType) -> Self {
+        self.image_view_type = image_view_type;
+        self
+    }
+    pub fn image_create_flags(mut self, image_create_flags: ImageCreateFlags) -> Self {
+        self.image_create_flags = image_create_flags;
+        self
+    }
 }
 
 impl Default for ImageCreatorParameters {
@@ -88,6 +127,11 @@ impl Default for ImageCreatorParameters {
             memory_flags: Default::default(),
             should_create_view: Default::default(),
             image_view_aspect_flags: Default::default(),
+            sample_count: SampleCountFlags::TYPE_1,
+            mip_levels: None,
+            array_layers: 1,
+            image_view_type: ImageViewType::TYPE_2D,
+            image_create_flags: ImageCreateFlags::empty(),
         }
     }
 }
@@ -103,19 +147,28 @@ impl VulkanRendererBackend<'_> {
             ..Default::default()
         };
 
-        // Creation info
+        let mip_levels = match image_creation_parameters.mip_levels {
+            Some(mip_levels) => mip_levels,
+            None if image_creation_parameters.sample_count == SampleCountFlags::TYPE_1 => 4,
+            None => 1,
+        };
+        // Creation info, the queues hand the image over to each other
         let image_create_info = ImageCreateInfo::default()
+            .flags(image_creation_parameters.image_create_flags)
             .image_type(image_creation_parameters.image_type)
-            .extent(Extent3D{ width: new_image.width, height: new_image.height, depth: 1 }) // TODO: Support configurable depth
-            .mip_levels(4) // TODO: Support mip mapping
-            .array_layers(1) // TODO: Support number of layer in the image
+            .extent(Extent3D {
+                width: new_image.width,
+                height: new_image.height,
+                depth: 1,
+            }) // TODO: Support configurable depth
+            .mip_levels(mip_levels)
+            .array_layers(image_creation_parameters.array_layers)
             .format(image_creation_parameters.image_format)
             .tiling(image_creation_parameters.image_tiling)
             .initial_layout(ImageLayout::UNDEFINED)
             .usage(image_creation_parameters.image_usage_flags)
-            .samples(SampleCountFlags::TYPE_1) // TODO: Configurable sample count
-            .sharing_mode(SharingMode::EXCLUSIVE) // TODO: Configurable sharing mode
-        ;
+            .samples(image_creation_parameters.sample_count)
+            .sharing_mode(SharingMode::EXCLUSIVE);
 
         let device = &self.get_device()?;
         new_image.image = unsafe {
@@ -137,27 +190,24 @@ impl VulkanRendererBackend<'_> {
         )?;
 
         // Allocate memory
-        let memory_allocate_info = MemoryAllocateInfo::default()
-            .allocation_size(memory_requirements.size)
-            .memory_type_index(memory_type);
-
-        new_image.memory = unsafe {
-            match device.allocate_memory(&memory_allocate_info, self.get_allocator()?) {
-                Ok(memory) => memory,
-                Err(err) => {
-                    error!(
-                        "Failed to allocate memory for vulkan image creation: {:?}",
-                        err
-                    );
-                    return Err(EngineError::VulkanFailed);
-                }
+        new_image.memory = match self.memory_allocate(memory_requirements, memory_type) {
+            Ok(memory) => memory,
+            Err(err) => {
+                error!(
+                    "Failed to allocate memory for vulkan image creation: {:?}",
+                    err
+                );
+                return Err(EngineError::VulkanFailed);
             }
         };
 
         // Bind the memory
         unsafe {
-            match device.bind_image_memory(new_image.image, new_image.memory, 0) {
-                // TODO: configurable memory offset
+            match device.bind_image_memory(
+                new_image.image,
+                new_image.memory.memory,
+                new_image.memory.offset,
+            ) {
                 Ok(()) => (),
                 Err(err) => {
                     error!(
@@ -191,20 +241,17 @@ impl VulkanRendererBackend<'_> {
         let device = self.get_device()?;
 
         if let Some(image_view) = image.image_view {
+            self.framebuffer_cache_evict_attachment(image_view)?;
             unsafe {
                 device.destroy_image_view(image_view, self.get_allocator()?);
             }
         }
 
-        unsafe {
-            device.free_memory(image.memory, self.get_allocator()?);
-        }
-
         unsafe {
             device.destroy_image(image.image, self.get_allocator()?);
         }
 
-        Ok(())
+        self.memory_free(&image.memory)
     }
 
     fn create_image_view(
@@ -212,17 +259,17 @@ impl VulkanRendererBackend<'_> {
         image: vk::Image,
         image_creation_parameters: ImageCreatorParameters,
     ) -> Result<ImageView, EngineError> {
-        // TODO: make the subresource configurable
+        // The view sees every layer and the explicitly requested levels
         let image_subresource_range = ImageSubresourceRange::default()
             .aspect_mask(image_creation_parameters.image_view_aspect_flags)
             .base_mip_level(0)
-            .level_count(1)
+            .level_count(image_creation_parameters.mip_levels.unwrap_or(1))
             .base_array_layer(0)
-            .layer_count(1);
+            .layer_count(image_creation_parameters.array_layers);
 
         let image_view_create_info = ImageViewCreateInfo::default()
             .image(image)
-            .view_type(ImageViewType::TYPE_2D) // TODO: Make the view type configurable
+            .view_type(image_creation_parameters.image_view_type)
             .format(image_creation_parameters.image_format)
             .subresource_range(image_subresource_range);
 
@@ -242,9 +289,67 @@ impl VulkanRendererBackend<'_> {
         &self,
         command_buffer: &CommandBuffer,
         buffer: &Buffer,
+        buffer_offset: u64,
+        image: &Image,
+    ) -> Result<(), EngineError> {
+        self.copy_image_region_from_buffer(
+            command_buffer,
+            buffer,
+            buffer_offset,
+            image,
+            Offset3D::default(),
+            Extent3D::default()
+                .width(image.width)
+                .height(image.height)
+                .depth(1),
+        )
+    }
+
+    /// Copy tightly packed pixels from the buffer into a sub-rectangle of the image
+    pub(crate) fn copy_image_region_from_buffer(
+        &self,
+        command_buffer: &CommandBuffer,
+        buffer: &Buffer,
+        buffer_offset: u64,
         image: &Image,
+        offset: Offset3D,
+        extent: Extent3D,
     ) -> Result<(), EngineError> {
         // Region to copy
+        let subresource = ImageSubresourceLayers::default()
+            .aspect_mask(ImageAspectFlags::COLOR)
+            .mip_level(0)
+            .base_array_layer(0)
+            .layer_count(1);
+        let regions = [BufferImageCopy::default()
+            .buffer_offset(buffer_offset)
+            .buffer_row_length(0)
+            .buffer_image_height(0)
+            .image_subresource(subresource)
+            .image_offset(offset)
+            .image_extent(extent)];
+
+        let device = self.get_device()?;
+        unsafe {
+            device.cmd_copy_buffer_to_image(
+                *command_buffer.handler.as_ref(),
+                buffer.buffer,
+                image.image,
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                &regions,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Copy the whole image into the buffer as tightly packed rows
+    pub(crate) fn copy_image_to_buffer(
+        &self,
+        command_buffer: &CommandBuffer,
+        image: &Image,
+        buffer: &Buffer,
+    ) -> Result<(), EngineError> {
         let subresource = ImageSubresourceLayers::default()
             .aspect_mask(ImageAspectFlags::COLOR)
             .mip_level(0)
@@ -263,11 +368,11 @@ impl VulkanRendererBackend<'_> {
 
         let device = self.get_device()?;
         unsafe {
-            device.cmd_copy_buffer_to_image(
+            device.cmd_copy_image_to_buffer(
                 *command_buffer.handler.as_ref(),
-                buffer.buffer,
                 image.image,
-                ImageLayout::TRANSFER_DST_OPTIMAL,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                buffer.buffer,
                 &regions,
             );
         }
@@ -289,12 +394,12 @@ impl VulkanRendererBackend<'_> {
             .level_count(1)
             .base_array_layer(0)
             .layer_count(1);
-        let graphics_family_index = self.get_queues()?.graphics_family_index.unwrap() as u32;
+        // Stays on the queue recording it, the handovers between queues are ownership transfers
         let mut image_memory_barrier = ImageMemoryBarrier::default()
             .old_layout(old_layout)
             .new_layout(new_layout)
-            .src_queue_family_index(graphics_family_index)
-            .dst_queue_family_index(graphics_family_index)
+            .src_queue_family_index(QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(QUEUE_FAMILY_IGNORED)
             .image(image.image)
             .subresource_range(subresource);
 
@@ -321,6 +426,35 @@ impl VulkanRendererBackend<'_> {
                 PipelineStageFlags::TRANSFER,
                 PipelineStageFlags::FRAGMENT_SHADER,
             )
+        } else if old_layout == ImageLayout::SHADER_READ_ONLY_OPTIMAL
+            && new_layout == ImageLayout::TRANSFER_DST_OPTIMAL
+        {
+            // Wait for the previous frames to be done sampling it before overwriting it
+            image_memory_barrier.src_access_mask = AccessFlags::SHADER_READ;
+            image_memory_barrier.dst_access_mask = AccessFlags::TRANSFER_WRITE;
+            (
+                PipelineStageFlags::FRAGMENT_SHADER,
+                PipelineStageFlags::TRANSFER,
+            )
+        } else if old_layout == ImageLayout::SHADER_READ_ONLY_OPTIMAL
+            && new_layout == ImageLayout::TRANSFER_SRC_OPTIMAL
+        {
+            // Wait for the previous frames to be done sampling it before reading it back
+            image_memory_barrier.src_access_mask = AccessFlags::SHADER_READ;
+            image_memory_barrier.dst_access_mask = AccessFlags::TRANSFER_READ;
+            (
+                PipelineStageFlags::FRAGMENT_SHADER,
+                PipelineStageFlags::TRANSFER,
+            )
+        } else if old_layout == ImageLayout::TRANSFER_SRC_OPTIMAL
+            && new_layout == ImageLayout::SHADER_READ_ONLY_OPTIMAL
+        {
+            image_memory_barrier.src_access_mask = AccessFlags::TRANSFER_READ;
+            image_memory_barrier.dst_access_mask = AccessFlags::SHADER_READ;
+            (
+                PipelineStageFlags::TRANSFER,
+                PipelineStageFlags::FRAGMENT_SHADER,
+            )
         } else {
             error!("Unsupported vulkan layout transition");
             return Err(EngineError::VulkanFailed);
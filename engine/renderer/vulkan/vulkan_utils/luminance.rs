@@ -0,0 +1,307 @@
+use ash::vk::{
+    AccessFlags, BufferImageCopy, BufferUsageFlags, DependencyFlags, Extent3D, Filter,
+    ImageAspectFlags, ImageBlit, ImageLayout, ImageMemoryBarrier, ImageSubresourceLayers,
+    ImageSubresourceRange, ImageTiling, ImageUsageFlags, MemoryMapFlags, MemoryPropertyFlags,
+    Offset3D, PipelineStageFlags, QUEUE_FAMILY_IGNORED,
+};
+
+use crate::{
+    core::debug::errors::EngineError,
+    error,
+    renderer::{
+        renderer_types::RENDERER_MAX_IN_FLIGHT_FRAMES,
+        vulkan::{
+            vulkan_init::swapchain::VULKAN_HDR_ATTACHMENT_FORMAT,
+            vulkan_types::VulkanRendererBackend,
+        },
+    },
+};
+
+use super::{
+    buffer::{Buffer, BufferCreatorParameters},
+    image::{Image, ImageCreatorParameters},
+    per_frame_resource::PerFrameResource,
+    screenshot::screenshot_half_to_f32,
+};
+
+/// Side of the downsampled copy of the hdr attachment the exposure is measured on
+const LUMINANCE_READBACK_SIZE: u32 = 64;
+
+/// Bytes per pixel of the downsampled copy, four half floats
+const LUMINANCE_READBACK_PIXEL_SIZE: u32 = 8;
+
+/// Small copy of the hdr attachment read back every frame for the auto exposure
+pub(crate) struct LuminanceReadback {
+    /// Blitted from the hdr attachment, the gpu does the downsampling
+    pub image: Image,
+    pub buffers: PerFrameResource<Buffer>,
+    /// The frame recorded a copy, it is read once the frame is done on the gpu
+    pub is_pending: PerFrameResource<bool>,
+}
+
+impl VulkanRendererBackend<'_> {
+    pub(crate) fn luminance_readback_init(&mut self) -> Result<(), EngineError> {
+        let image_creation_parameters = ImageCreatorParameters::default()
+            .width(LUMINANCE_READBACK_SIZE)
+            .height(LUMINANCE_READBACK_SIZE)
+            .image_format(VULKAN_HDR_ATTACHMENT_FORMAT)
+            .image_tiling(ImageTiling::OPTIMAL)
+            .image_usage_flags(ImageUsageFlags::TRANSFER_DST | ImageUsageFlags::TRANSFER_SRC)
+            .memory_flags(MemoryPropertyFlags::DEVICE_LOCAL);
+        let image = match self.create_image(image_creation_parameters) {
+            Ok(image) => image,
+            Err(err) => {
+                error!(
+                    "Failed to create the image of the luminance readback: {:?}",
+                    err
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        let mut buffers = Vec::with_capacity(RENDERER_MAX_IN_FLIGHT_FRAMES);
+        for _ in 0..RENDERER_MAX_IN_FLIGHT_FRAMES {
+            let buffer_create_info = BufferCreatorParameters::default()
+                .buffer_usage_flags(BufferUsageFlags::TRANSFER_DST)
+                .memory_flags(
+                    MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT,
+                )
+                .size(
+                    (LUMINANCE_READBACK_SIZE
+                        * LUMINANCE_READBACK_SIZE
+                        * LUMINANCE_READBACK_PIXEL_SIZE) as usize,
+                )
+                .should_be_bind(true);
+            match self.create_buffer(buffer_create_info) {
+                Ok(buffer) => buffers.push(buffer),
+                Err(err) => {
+                    error!(
+                        "Failed to create a buffer of the luminance readback: {:?}",
+                        err
+                    );
+                    return Err(EngineError::InitializationFailed);
+                }
+            }
+        }
+        self.context.resources.luminance_readback = Some(LuminanceReadback {
+            image,
+            buffers: PerFrameResource::from_vec(buffers)?,
+            is_pending: PerFrameResource::default(),
+        });
+        self.context.resources.luminance_samples = None;
+        Ok(())
+    }
+
+    /// Waits for the gpu, the copies in flight are dropped
+    pub(crate) fn luminance_readback_shutdown(&mut self) -> Result<(), EngineError> {
+        if let Err(err) = self.device_wait_idle() {
+            error!(
+                "Failed to wait idle when destroying the luminance readback: {:?}",
+                err
+            );
+            return Err(EngineError::ShutdownFailed);
+        }
+        let readback = match self.context.resources.luminance_readback.take() {
+            Some(readback) => readback,
+            None => return Ok(()),
+        };
+        for buffer in readback.buffers.iter() {
+            if let Err(err) = self.destroy_buffer(buffer) {
+                error!(
+                    "Failed to destroy a buffer of the luminance readback: {:?}",
+                    err
+                );
+                return Err(EngineError::ShutdownFailed);
+            }
+        }
+        if let Err(err) = self.destroy_image(&readback.image) {
+            error!(
+                "Failed to destroy the image of the luminance readback: {:?}",
+                err
+            );
+            return Err(EngineError::ShutdownFailed);
+        }
+        self.context.resources.luminance_samples = None;
+        Ok(())
+    }
+
+    /// Decide if the frame copies the hdr attachment, before the render graph is declared
+    pub(crate) fn luminance_readback_begin_frame(&mut self, frame_index: usize) {
+        let is_requested = self.context.resources.luminance_readback_requested;
+        if let Some(readback) = self.context.resources.luminance_readback.as_mut() {
+            readback.is_pending.set(frame_index, is_requested);
+        }
+    }
+
+    /// The render graph downsamples the hdr attachment of the frame
+    pub(crate) fn luminance_readback_has_copy(&self, frame_index: usize) -> bool {
+        self.context
+            .resources
+            .luminance_readback
+            .as_ref()
+            .is_some_and(|readback| *readback.is_pending.get(frame_index))
+    }
+
+    fn luminance_readback_barrier(
+        &self,
+        frame_index: usize,
+        image: ash::vk::Image,
+        (old_layout, new_layout): (ImageLayout, ImageLayout),
+        (src_access_mask, dst_access_mask): (AccessFlags, AccessFlags),
+    ) -> Result<(), EngineError> {
+        let subresource = ImageSubresourceRange::default()
+            .aspect_mask(ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(1)
+            .base_array_layer(0)
+            .layer_count(1);
+        let barriers = [ImageMemoryBarrier::default()
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_queue_family_index(QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(QUEUE_FAMILY_IGNORED)
+            .src_access_mask(src_access_mask)
+            .dst_access_mask(dst_access_mask)
+            .image(image)
+            .subresource_range(subresource)];
+        let command_buffer = &self.get_graphics_command_buffers()?[frame_index];
+        unsafe {
+            self.get_device()?.cmd_pipeline_barrier(
+                *command_buffer.handler.as_ref(),
+                PipelineStageFlags::TRANSFER,
+                PipelineStageFlags::TRANSFER,
+                DependencyFlags::empty(),
+                &[],
+                &[],
+                &barriers,
+            );
+        }
+        Ok(())
+    }
+
+    /// Blit the hdr attachment, already in the transfer source layout, and copy the result to the buffer of the frame
+    pub(crate) fn luminance_readback_record_copy(
+        &self,
+        frame_index: usize,
+        hdr_image: ash::vk::Image,
+    ) -> Result<(), EngineError> {
+        let readback = match self.context.resources.luminance_readback.as_ref() {
+            Some(readback) => readback,
+            None => {
+                error!("The hdr attachment is downsampled without a luminance readback");
+                return Err(EngineError::AccessFailed);
+            }
+        };
+        let extent = self.get_swapchain()?.extent;
+        let subresource = ImageSubresourceLayers::default()
+            .aspect_mask(ImageAspectFlags::COLOR)
+            .mip_level(0)
+            .base_array_layer(0)
+            .layer_count(1);
+        let size = LUMINANCE_READBACK_SIZE as i32;
+
+        self.luminance_readback_barrier(
+            frame_index,
+            readback.image.image,
+            (ImageLayout::UNDEFINED, ImageLayout::TRANSFER_DST_OPTIMAL),
+            (AccessFlags::TRANSFER_READ, AccessFlags::TRANSFER_WRITE),
+        )?;
+        let blits = [ImageBlit::default()
+            .src_subresource(subresource)
+            .src_offsets([
+                Offset3D::default(),
+                Offset3D::default()
+                    .x(extent.width as i32)
+                    .y(extent.height as i32)
+                    .z(1),
+            ])
+            .dst_subresource(subresource)
+            .dst_offsets([
+                Offset3D::default(),
+                Offset3D::default().x(size).y(size).z(1),
+            ])];
+        let command_buffer = &self.get_graphics_command_buffers()?[frame_index];
+        let device = self.get_device()?;
+        unsafe {
+            device.cmd_blit_image(
+                *command_buffer.handler.as_ref(),
+                hdr_image,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                readback.image.image,
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                &blits,
+                Filter::LINEAR,
+            );
+        }
+        self.luminance_readback_barrier(
+            frame_index,
+            readback.image.image,
+            (
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+            ),
+            (AccessFlags::TRANSFER_WRITE, AccessFlags::TRANSFER_READ),
+        )?;
+        let regions = [BufferImageCopy::default()
+            .image_subresource(subresource)
+            .image_extent(
+                Extent3D::default()
+                    .width(LUMINANCE_READBACK_SIZE)
+                    .height(LUMINANCE_READBACK_SIZE)
+                    .depth(1),
+            )];
+        unsafe {
+            device.cmd_copy_image_to_buffer(
+                *command_buffer.handler.as_ref(),
+                readback.image.image,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                readback.buffers.get(frame_index).buffer,
+                &regions,
+            );
+        }
+        Ok(())
+    }
+
+    /// The frame is done on the gpu, its downsampled colors can be read
+    pub(crate) fn luminance_readback_frame_completed(
+        &mut self,
+        frame_index: usize,
+    ) -> Result<(), EngineError> {
+        let is_pending = match self.context.resources.luminance_readback.as_mut() {
+            Some(readback) => std::mem::take(readback.is_pending.get_mut(frame_index)),
+            None => false,
+        };
+        let readback = match self.context.resources.luminance_readback.as_ref() {
+            Some(readback) if is_pending => readback,
+            _ => return Ok(()),
+        };
+        let size = (LUMINANCE_READBACK_SIZE
+            * LUMINANCE_READBACK_SIZE
+            * LUMINANCE_READBACK_PIXEL_SIZE) as usize;
+        let bytes = match self.read_data_from_buffer(
+            readback.buffers.get(frame_index),
+            0,
+            size,
+            MemoryMapFlags::empty(),
+        ) {
+            Ok(bytes) => bytes,
+            Err(err) => {
+                error!("Failed to read the luminance readback: {:?}", err);
+                return Err(EngineError::AccessFailed);
+            }
+        };
+        let samples = bytes
+            .chunks_exact(LUMINANCE_READBACK_PIXEL_SIZE as usize)
+            .map(|pixel| {
+                let channel = |index: usize| {
+                    screenshot_half_to_f32(u16::from_ne_bytes([
+                        pixel[2 * index],
+                        pixel[2 * index + 1],
+                    ]))
+                };
+                glam::Vec3::new(channel(0), channel(1), channel(2))
+            })
+            .collect();
+        self.context.resources.luminance_samples = Some(samples);
+        Ok(())
+    }
+}
@@ -1,7 +1,15 @@
-pub mod buffer;
-pub mod device_features;
-pub mod fence;
-pub mod image;
-pub mod pipeline;
-pub mod semaphore;
-pub mod texture;
+pub(crate) mod buffer;
+pub(crate) mod camera_pass;
+pub(crate) mod crash_report;
+pub(crate) mod device_features;
+pub(crate) mod fence;
+pub(crate) mod geometry;
+pub(crate) mod image;
+pub(crate) mod luminance;
+pub(crate) mod per_frame_resource;
+pub(crate) mod pipeline;
+pub(crate) mod query_pool;
+pub(crate) mod reflection_probes;
+pub(crate) mod screenshot;
+pub(crate) mod semaphore;
+pub(crate) mod texture;
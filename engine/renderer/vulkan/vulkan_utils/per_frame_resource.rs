@@ -0,0 +1,87 @@
+use crate::{
+    core::debug::errors::EngineError,
+    error,
+    renderer::{
+        renderer_types::RENDERER_MAX_IN_FLIGHT_FRAMES, vulkan::vulkan_types::VulkanRendererBackend,
+    },
+};
+
+/// One copy of a resource per frame in flight
+/// The frame being recorded only touches its own copy so it never races with the gpu
+#[derive(Default, Clone, Copy, Debug)]
+pub(crate) struct PerFrameResource<T> {
+    resources: [T; RENDERER_MAX_IN_FLIGHT_FRAMES],
+}
+
+impl<T> PerFrameResource<T> {
+    pub fn new(resources: [T; RENDERER_MAX_IN_FLIGHT_FRAMES]) -> Self {
+        Self { resources }
+    }
+
+    /// Create each copy from its frame index
+    pub fn from_fn<F: FnMut(usize) -> T>(create: F) -> Self {
+        Self {
+            resources: std::array::from_fn(create),
+        }
+    }
+
+    /// Wrap the results of a vulkan allocation made for every frame
+    pub fn from_vec(resources: Vec<T>) -> Result<Self, EngineError> {
+        let resources_count = resources.len();
+        match resources.try_into() {
+            Ok(resources) => Ok(Self { resources }),
+            Err(_) => {
+                error!(
+                    "Can't create a per frame resource from {} elements instead of {}",
+                    resources_count, RENDERER_MAX_IN_FLIGHT_FRAMES
+                );
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    pub fn get(&self, frame_index: usize) -> &T {
+        &self.resources[frame_index]
+    }
+
+    pub fn get_mut(&mut self, frame_index: usize) -> &mut T {
+        &mut self.resources[frame_index]
+    }
+
+    pub fn set(&mut self, frame_index: usize, resource: T) {
+        self.resources[frame_index] = resource;
+    }
+
+    /// Every copy, to create or destroy them all at once
+    pub fn as_slice(&self) -> &[T] {
+        &self.resources
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, T> {
+        self.resources.iter()
+    }
+
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, T> {
+        self.resources.iter_mut()
+    }
+
+    pub fn map<U, F: FnMut(T) -> U>(self, function: F) -> PerFrameResource<U> {
+        PerFrameResource {
+            resources: self.resources.map(function),
+        }
+    }
+}
+
+impl<T: Clone> PerFrameResource<T> {
+    /// Same value for every frame
+    pub fn splat(resource: T) -> Self {
+        Self::from_fn(|_| resource.clone())
+    }
+}
+
+impl VulkanRendererBackend<'_> {
+    /// Index of the frame being recorded, to select the instance of a per frame resource
+    pub fn get_current_frame_index(&self) -> usize {
+        self.context.frame.current_frame as usize
+    }
+}
@@ -8,7 +8,7 @@ use ash::{
         PipelineLayoutCreateInfo, PipelineMultisampleStateCreateInfo,
         PipelineRasterizationStateCreateInfo, PipelineShaderStageCreateInfo,
         PipelineVertexInputStateCreateInfo, PipelineViewportStateCreateInfo, PolygonMode,
-        PrimitiveTopology, PushConstantRange, Rect2D, SampleCountFlags, ShaderStageFlags,
+        PrimitiveTopology, PushConstantRange, Rect2D, StencilOpState,
         VertexInputAttributeDescription, VertexInputBindingDescription, Viewport,
     },
     Device,
@@ -18,6 +18,7 @@ use crate::{
     core::debug::errors::EngineError,
     error,
     renderer::vulkan::vulkan_init::{command_buffer::CommandBuffer, renderpass::Renderpass},
+    resources::material::BlendMode,
 };
 
 #[derive(Default)]
@@ -34,7 +35,24 @@ pub(crate) struct PipelineCreateInfo<'a> {
     pub vertex_input_bindings_description: Vec<VertexInputBindingDescription>,
     pub vertex_input_attributes_description: Vec<VertexInputAttributeDescription>,
     pub descriptor_set_layouts: Vec<DescriptorSetLayout>,
+    pub push_constant_ranges: Vec<PushConstantRange>,
     pub shader_stages_info: Vec<PipelineShaderStageCreateInfo<'a>>,
+    /// Stencil test applied to both faces, None to disable the stencil test
+    /// The reference value is dynamic and set with `cmd_set_stencil_reference`
+    pub stencil_state: Option<StencilOpState>,
+    pub cull_mode: CullModeFlags,
+    /// Disabling the depth test also disables the depth writes
+    pub is_depth_test_enabled: bool,
+    pub is_depth_write_enabled: bool,
+    pub blend_mode: BlendMode,
+    /// Subpass of the renderpass the pipeline is used in
+    pub subpass: u32,
+}
+
+pub(crate) struct ComputePipelineCreateInfo<'a> {
+    pub descriptor_set_layouts: Vec<DescriptorSetLayout>,
+    pub push_constant_ranges: Vec<PushConstantRange>,
+    pub shader_stage_info: PipelineShaderStageCreateInfo<'a>,
 }
 
 impl Pipeline {
@@ -56,28 +74,41 @@ impl Pipeline {
                 PolygonMode::FILL
             })
             .line_width(1.0)
-            .cull_mode(CullModeFlags::BACK)
+            .cull_mode(pipeline_info.cull_mode)
             .front_face(FrontFace::COUNTER_CLOCKWISE);
 
-        // Multisampling
+        // Multisampling, only the world renderpass is multisampled
         let multisampling_create_info = PipelineMultisampleStateCreateInfo::default()
-            .rasterization_samples(SampleCountFlags::TYPE_1)
+            .rasterization_samples(pipeline_info.renderpass.sample_count)
             .min_sample_shading(1.0);
 
         // Depth and stencil
         let depth_stencil_create_info = PipelineDepthStencilStateCreateInfo::default()
-            .depth_test_enable(true)
-            .depth_write_enable(true)
+            .depth_test_enable(pipeline_info.is_depth_test_enabled)
+            .depth_write_enable(
+                pipeline_info.is_depth_test_enabled && pipeline_info.is_depth_write_enabled,
+            )
             .depth_compare_op(CompareOp::LESS);
+        let depth_stencil_create_info = match pipeline_info.stencil_state {
+            Some(stencil_state) => depth_stencil_create_info
+                .stencil_test_enable(true)
+                .front(stencil_state)
+                .back(stencil_state),
+            None => depth_stencil_create_info,
+        };
 
         // Color blending
+        let dst_blend_factor = match pipeline_info.blend_mode {
+            BlendMode::Additive => BlendFactor::ONE,
+            BlendMode::Opaque | BlendMode::AlphaBlend => BlendFactor::ONE_MINUS_SRC_ALPHA,
+        };
         let color_blend_attachment_states = [PipelineColorBlendAttachmentState::default()
-            .blend_enable(true)
+            .blend_enable(pipeline_info.blend_mode != BlendMode::Opaque)
             .src_color_blend_factor(BlendFactor::SRC_ALPHA)
-            .dst_color_blend_factor(BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .dst_color_blend_factor(dst_blend_factor)
             .color_blend_op(BlendOp::ADD)
             .src_alpha_blend_factor(BlendFactor::SRC_ALPHA)
-            .dst_alpha_blend_factor(BlendFactor::ONE_MINUS_SRC_ALPHA)
+            .dst_alpha_blend_factor(dst_blend_factor)
             .alpha_blend_op(BlendOp::ADD)
             .color_write_mask(ColorComponentFlags::RGBA)];
         let color_blend_create_info = PipelineColorBlendStateCreateInfo::default()
@@ -85,11 +116,14 @@ impl Pipeline {
             .attachments(&color_blend_attachment_states);
 
         // Dynamic state
-        let dynamic_states = vec![
+        let mut dynamic_states = vec![
             DynamicState::VIEWPORT,
             DynamicState::SCISSOR,
             DynamicState::LINE_WIDTH,
         ];
+        if pipeline_info.stencil_state.is_some() {
+            dynamic_states.push(DynamicState::STENCIL_REFERENCE);
+        }
         let dynamic_state_create_info =
             PipelineDynamicStateCreateInfo::default().dynamic_states(&dynamic_states);
 
@@ -102,18 +136,10 @@ impl Pipeline {
         let input_assembly_create_info = PipelineInputAssemblyStateCreateInfo::default()
             .topology(PrimitiveTopology::TRIANGLE_LIST);
 
-        // Push constants
-        let push_constant_ranges = [
-            PushConstantRange::default()
-                .stage_flags(ShaderStageFlags::VERTEX) // only push constants to vertex shader
-                .offset(0)
-                .size((size_of::<glam::Mat4>()) as u32), // max size of 128 bytes
-        ];
-
         // Pipeline layout
         let pipeline_layout_create_info = PipelineLayoutCreateInfo::default()
             .set_layouts(&pipeline_info.descriptor_set_layouts)
-            .push_constant_ranges(&push_constant_ranges);
+            .push_constant_ranges(&pipeline_info.push_constant_ranges);
         let pipeline_layout = unsafe {
             match device.create_pipeline_layout(&pipeline_layout_create_info, allocator) {
                 Ok(layout) => layout,
@@ -140,7 +166,7 @@ impl Pipeline {
             .dynamic_state(&dynamic_state_create_info)
             .layout(pipeline_layout)
             .render_pass(pipeline_info.renderpass.handler)
-            .subpass(0)
+            .subpass(pipeline_info.subpass)
             .base_pipeline_handle(vk::Pipeline::null())
             .base_pipeline_index(-1)];
 
@@ -167,6 +193,59 @@ impl Pipeline {
         })
     }
 
+    pub fn create_compute(
+        device: &Device,
+        allocator: Option<&vk::AllocationCallbacks<'_>>,
+        pipeline_info: ComputePipelineCreateInfo,
+    ) -> Result<Self, EngineError> {
+        // Pipeline layout
+        let pipeline_layout_create_info = PipelineLayoutCreateInfo::default()
+            .set_layouts(&pipeline_info.descriptor_set_layouts)
+            .push_constant_ranges(&pipeline_info.push_constant_ranges);
+        let pipeline_layout = unsafe {
+            match device.create_pipeline_layout(&pipeline_layout_create_info, allocator) {
+                Ok(layout) => layout,
+                Err(err) => {
+                    error!(
+                        "Failed to create a vulkan pipeline layout in a compute pipeline: {:?}",
+                        err
+                    );
+                    return Err(EngineError::VulkanFailed);
+                }
+            }
+        };
+
+        // Pipeline
+        let compute_pipeline_create_info = [vk::ComputePipelineCreateInfo::default()
+            .stage(pipeline_info.shader_stage_info)
+            .layout(pipeline_layout)
+            .base_pipeline_handle(vk::Pipeline::null())
+            .base_pipeline_index(-1)];
+
+        let pipeline = unsafe {
+            match device.create_compute_pipelines(
+                PipelineCache::null(),
+                &compute_pipeline_create_info,
+                allocator,
+            ) {
+                Ok(pipelines) => pipelines[0],
+                Err((_, err)) => {
+                    error!(
+                        "Failed to create vulkan pipelines in a compute pipeline: {:?}",
+                        err
+                    );
+                    device.destroy_pipeline_layout(pipeline_layout, allocator);
+                    return Err(EngineError::VulkanFailed);
+                }
+            }
+        };
+
+        Ok(Self {
+            handler: pipeline,
+            layout: pipeline_layout,
+        })
+    }
+
     pub fn destroy(
         &self,
         device: &Device,
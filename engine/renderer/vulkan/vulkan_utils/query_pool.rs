@@ -0,0 +1,198 @@
+use ash::{
+    vk::{
+        self, CommandBuffer, PipelineStageFlags, QueryControlFlags, QueryPipelineStatisticFlags,
+        QueryPoolCreateInfo, QueryResultFlags, QueryType,
+    },
+    Device,
+};
+
+use crate::{core::debug::errors::EngineError, error};
+
+/// Pipeline statistics gathered by the renderer statistics queries
+pub const VULKAN_PIPELINE_STATISTICS_FLAGS: QueryPipelineStatisticFlags =
+    QueryPipelineStatisticFlags::from_raw(
+        QueryPipelineStatisticFlags::INPUT_ASSEMBLY_VERTICES.as_raw()
+            | QueryPipelineStatisticFlags::VERTEX_SHADER_INVOCATIONS.as_raw()
+            | QueryPipelineStatisticFlags::CLIPPING_PRIMITIVES.as_raw()
+            | QueryPipelineStatisticFlags::FRAGMENT_SHADER_INVOCATIONS.as_raw(),
+    );
+
+pub(crate) struct QueryPool {
+    pub handler: vk::QueryPool,
+    pub query_type: QueryType,
+    pub query_count: u32,
+    pub pipeline_statistics: QueryPipelineStatisticFlags,
+}
+
+impl QueryPool {
+    pub fn create(
+        device: &Device,
+        allocator: Option<&vk::AllocationCallbacks<'_>>,
+        query_type: QueryType,
+        query_count: u32,
+        pipeline_statistics: QueryPipelineStatisticFlags,
+    ) -> Result<Self, EngineError> {
+        let mut query_pool_create_info = QueryPoolCreateInfo::default()
+            .query_type(query_type)
+            .query_count(query_count);
+        if query_type == QueryType::PIPELINE_STATISTICS {
+            query_pool_create_info =
+                query_pool_create_info.pipeline_statistics(pipeline_statistics);
+        }
+
+        let handler = unsafe {
+            match device.create_query_pool(&query_pool_create_info, allocator) {
+                Ok(pool) => pool,
+                Err(err) => {
+                    error!("Failed to create a vulkan query pool: {:?}", err);
+                    return Err(EngineError::VulkanFailed);
+                }
+            }
+        };
+
+        Ok(QueryPool {
+            handler,
+            query_type,
+            query_count,
+            pipeline_statistics,
+        })
+    }
+
+    pub fn destroy(
+        &self,
+        device: &Device,
+        allocator: Option<&vk::AllocationCallbacks<'_>>,
+    ) -> Result<(), EngineError> {
+        unsafe {
+            device.destroy_query_pool(self.handler, allocator);
+        }
+        Ok(())
+    }
+
+    /// Number of values written for each query
+    pub fn values_per_query(&self) -> usize {
+        match self.query_type {
+            QueryType::PIPELINE_STATISTICS => {
+                self.pipeline_statistics.as_raw().count_ones() as usize
+            }
+            _ => 1,
+        }
+    }
+
+    /// Must be recorded outside of a renderpass
+    pub fn reset(&self, device: &Device, command_buffer: &CommandBuffer) {
+        unsafe {
+            device.cmd_reset_query_pool(*command_buffer, self.handler, 0, self.query_count);
+        }
+    }
+
+    pub fn begin(
+        &self,
+        device: &Device,
+        command_buffer: &CommandBuffer,
+        query: u32,
+    ) -> Result<(), EngineError> {
+        if query >= self.query_count {
+            error!(
+                "The query {} is out of the vulkan query pool range ({})",
+                query, self.query_count
+            );
+            return Err(EngineError::InvalidValue);
+        }
+        unsafe {
+            device.cmd_begin_query(
+                *command_buffer,
+                self.handler,
+                query,
+                QueryControlFlags::empty(),
+            );
+        }
+        Ok(())
+    }
+
+    pub fn end(
+        &self,
+        device: &Device,
+        command_buffer: &CommandBuffer,
+        query: u32,
+    ) -> Result<(), EngineError> {
+        if query >= self.query_count {
+            error!(
+                "The query {} is out of the vulkan query pool range ({})",
+                query, self.query_count
+            );
+            return Err(EngineError::InvalidValue);
+        }
+        unsafe {
+            device.cmd_end_query(*command_buffer, self.handler, query);
+        }
+        Ok(())
+    }
+
+    /// Write the gpu time once the previous commands reach the stage, for the timestamp pools
+    pub fn write_timestamp(
+        &self,
+        device: &Device,
+        command_buffer: &CommandBuffer,
+        stage: PipelineStageFlags,
+        query: u32,
+    ) -> Result<(), EngineError> {
+        if query >= self.query_count {
+            error!(
+                "The query {} is out of the vulkan query pool range ({})",
+                query, self.query_count
+            );
+            return Err(EngineError::InvalidValue);
+        }
+        unsafe {
+            device.cmd_write_timestamp(*command_buffer, stage, self.handler, query);
+        }
+        Ok(())
+    }
+
+    /// Returns None if the results are not available yet
+    pub fn get_results(
+        &self,
+        device: &Device,
+        first_query: u32,
+        query_count: u32,
+    ) -> Result<Option<Vec<u64>>, EngineError> {
+        if first_query + query_count > self.query_count {
+            error!(
+                "The queries {}..{} are out of the vulkan query pool range ({})",
+                first_query,
+                first_query + query_count,
+                self.query_count
+            );
+            return Err(EngineError::InvalidValue);
+        }
+        let values_per_query = self.values_per_query();
+        let mut results = vec![0u64; query_count as usize * values_per_query];
+        // The raw call is needed since a query can write several values
+        let stride = (values_per_query * size_of::<u64>()) as u64;
+        unsafe {
+            match (device.fp_v1_0().get_query_pool_results)(
+                device.handle(),
+                self.handler,
+                first_query,
+                query_count,
+                size_of_val(results.as_slice()),
+                results.as_mut_ptr().cast(),
+                stride,
+                QueryResultFlags::TYPE_64,
+            )
+            .result()
+            {
+                Ok(()) => Ok(Some(results)),
+                Err(vk::Result::NOT_READY) => Ok(None),
+                Err(err) => {
+                    error!(
+                        "Failed to get the results of a vulkan query pool: {:?}",
+                        err
+                    );
+                    Err(EngineError::VulkanFailed)
+                }
+            }
+        }
+    }
+}
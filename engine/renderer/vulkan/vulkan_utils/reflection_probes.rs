@@ -0,0 +1,414 @@
+use ash::vk::{
+    AccessFlags, BorderColor, BufferImageCopy, BufferUsageFlags, CompareOp, DependencyFlags,
+    Extent3D, Filter, Format, ImageAspectFlags, ImageCreateFlags, ImageLayout, ImageMemoryBarrier,
+    ImageSubresourceLayers, ImageSubresourceRange, ImageTiling, ImageUsageFlags, ImageViewType,
+    MemoryMapFlags, MemoryPropertyFlags, PipelineStageFlags, Sampler, SamplerAddressMode,
+    SamplerCreateInfo, SamplerMipmapMode, LOD_CLAMP_NONE, QUEUE_FAMILY_IGNORED,
+};
+
+use crate::{
+    core::debug::errors::EngineError,
+    error,
+    renderer::{
+        reflection_probe::{EnvironmentMap, REFLECTION_PROBE_FACE_COUNT},
+        vulkan::{vulkan_init::command_buffer::CommandBuffer, vulkan_types::VulkanRendererBackend},
+    },
+};
+
+use super::{
+    buffer::BufferCreatorParameters,
+    image::{Image, ImageCreatorParameters},
+};
+
+/// Linear colors of the probes, four half floats per texel
+const REFLECTION_PROBES_FORMAT: Format = Format::R16G16B16A16_SFLOAT;
+
+/// Bytes per texel of the cube array
+const REFLECTION_PROBES_TEXEL_SIZE: usize = 8;
+
+/// Environment maps of every probe in a single cube array, sampled by the object shaders
+pub(crate) struct ReflectionProbeCubemaps {
+    pub image: Image,
+    pub sampler: Sampler,
+    /// Prefiltered levels of the environment maps, the shaders pick one from the shininess
+    pub level_count: u32,
+}
+
+/// Encode an ieee 754 half float, the mantissa is truncated
+fn reflection_probes_f32_to_half(value: f32) -> u16 {
+    let bits = value.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    if value.is_nan() {
+        return sign | 0x7e00;
+    }
+    let exponent = ((bits >> 23) & 0xff) as i32 - 127 + 15;
+    let mantissa = bits & 0x7fffff;
+    if exponent >= 0x1f {
+        sign | 0x7c00
+    } else if exponent <= 0 {
+        // Subnormal, too small values are flushed to zero
+        if exponent < -10 {
+            return sign;
+        }
+        sign | ((mantissa | 0x800000) >> (14 - exponent)) as u16
+    } else {
+        sign | ((exponent as u16) << 10) | (mantissa >> 13) as u16
+    }
+}
+
+/// Size of the faces and number of mip levels shared by every map
+/// The levels past the 1x1 one can't be mips of the image and are dropped
+fn reflection_probes_get_layout(
+    environment_maps: &[&EnvironmentMap],
+) -> Result<(u32, u32), EngineError> {
+    let first = match environment_maps.first().and_then(|map| map.levels.first()) {
+        Some(level) => level,
+        None => {
+            error!("Can't upload reflection probes without levels");
+            return Err(EngineError::InvalidValue);
+        }
+    };
+    let face_size = first.face_size;
+    let level_count = environment_maps[0].levels.len();
+    let mip_count = (level_count as u32).min(u32::BITS - face_size.leading_zeros());
+    for environment_map in environment_maps {
+        if environment_map.levels.len() != level_count {
+            error!(
+                "The reflection probes must share their level count, got {} and {}",
+                level_count,
+                environment_map.levels.len()
+            );
+            return Err(EngineError::InvalidValue);
+        }
+        for (index, level) in environment_map
+            .levels
+            .iter()
+            .take(mip_count as usize)
+            .enumerate()
+        {
+            let expected_size = (face_size >> index).max(1);
+            if level.face_size != expected_size {
+                error!(
+                    "The level {} of a reflection probe has {}x{} faces instead of {}x{}",
+                    index, level.face_size, level.face_size, expected_size, expected_size
+                );
+                return Err(EngineError::InvalidValue);
+            }
+        }
+    }
+    Ok((face_size, mip_count))
+}
+
+impl VulkanRendererBackend<'_> {
+    fn reflection_probes_barrier(
+        &self,
+        command_buffer: &CommandBuffer,
+        image: &Image,
+        (mip_count, layer_count): (u32, u32),
+        (old_layout, new_layout): (ImageLayout, ImageLayout),
+        (src_access_mask, dst_access_mask): (AccessFlags, AccessFlags),
+        (src_stage, dst_stage): (PipelineStageFlags, PipelineStageFlags),
+    ) -> Result<(), EngineError> {
+        let subresource = ImageSubresourceRange::default()
+            .aspect_mask(ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(mip_count)
+            .base_array_layer(0)
+            .layer_count(layer_count);
+        let barriers = [ImageMemoryBarrier::default()
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_queue_family_index(QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(QUEUE_FAMILY_IGNORED)
+            .src_access_mask(src_access_mask)
+            .dst_access_mask(dst_access_mask)
+            .image(image.image)
+            .subresource_range(subresource)];
+        unsafe {
+            self.get_device()?.cmd_pipeline_barrier(
+                *command_buffer.handler.as_ref(),
+                src_stage,
+                dst_stage,
+                DependencyFlags::empty(),
+                &[],
+                &[],
+                &barriers,
+            );
+        }
+        Ok(())
+    }
+
+    /// Create the cube array and copy the texels level by level, waits for the gpu
+    fn reflection_probes_create_image(
+        &self,
+        face_size: u32,
+        mip_count: u32,
+        cube_count: u32,
+        texels: &[u8],
+    ) -> Result<Image, EngineError> {
+        let layer_count = cube_count * REFLECTION_PROBE_FACE_COUNT as u32;
+        let image_creation_parameters = ImageCreatorParameters::default()
+            .width(face_size)
+            .height(face_size)
+            .image_format(REFLECTION_PROBES_FORMAT)
+            .image_tiling(ImageTiling::OPTIMAL)
+            .image_usage_flags(ImageUsageFlags::TRANSFER_DST | ImageUsageFlags::SAMPLED)
+            .memory_flags(MemoryPropertyFlags::DEVICE_LOCAL)
+            .should_create_view(true)
+            .image_view_aspect_flags(ImageAspectFlags::COLOR)
+            .mip_levels(mip_count)
+            .array_layers(layer_count)
+            .image_view_type(ImageViewType::CUBE_ARRAY)
+            .image_create_flags(ImageCreateFlags::CUBE_COMPATIBLE);
+        let image = match self.create_image(image_creation_parameters) {
+            Ok(image) => image,
+            Err(err) => {
+                error!("Failed to create the reflection probes image: {:?}", err);
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+
+        let buffer_create_info = BufferCreatorParameters::default()
+            .buffer_usage_flags(BufferUsageFlags::TRANSFER_SRC)
+            .memory_flags(MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT)
+            .size(texels.len())
+            .should_be_bind(true);
+        let staging = match self.create_buffer(buffer_create_info) {
+            Ok(staging) => staging,
+            Err(err) => {
+                error!(
+                    "Failed to create the staging buffer of the reflection probes: {:?}",
+                    err
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        let data = texels.as_ptr() as *mut std::ffi::c_void;
+        if let Err(err) =
+            self.load_data_into_buffer(&staging, 0, texels.len(), MemoryMapFlags::empty(), data)
+        {
+            error!(
+                "Failed to load the reflection probes into a staging buffer: {:?}",
+                err
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+
+        // Every face of every probe of a level is copied at once, the levels follow each other
+        let mut regions = Vec::with_capacity(mip_count as usize);
+        let mut buffer_offset = 0;
+        for mip_level in 0..mip_count {
+            let size = (face_size >> mip_level).max(1);
+            let subresource = ImageSubresourceLayers::default()
+                .aspect_mask(ImageAspectFlags::COLOR)
+                .mip_level(mip_level)
+                .base_array_layer(0)
+                .layer_count(layer_count);
+            regions.push(
+                BufferImageCopy::default()
+                    .buffer_offset(buffer_offset)
+                    .image_subresource(subresource)
+                    .image_extent(Extent3D::default().width(size).height(size).depth(1)),
+            );
+            buffer_offset +=
+                (size * size * layer_count) as u64 * REFLECTION_PROBES_TEXEL_SIZE as u64;
+        }
+
+        let pool = self.get_graphics_command_pool()?;
+        let device = self.get_device()?;
+        let command_buffer = match CommandBuffer::allocate_and_begin_single_use(device, pool) {
+            Ok(command_buffer) => command_buffer,
+            Err(err) => {
+                error!(
+                    "Failed to allocate a command buffer when uploading the reflection probes: {:?}",
+                    err
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        self.reflection_probes_barrier(
+            &command_buffer,
+            &image,
+            (mip_count, layer_count),
+            (ImageLayout::UNDEFINED, ImageLayout::TRANSFER_DST_OPTIMAL),
+            (AccessFlags::empty(), AccessFlags::TRANSFER_WRITE),
+            (
+                PipelineStageFlags::TOP_OF_PIPE,
+                PipelineStageFlags::TRANSFER,
+            ),
+        )?;
+        unsafe {
+            device.cmd_copy_buffer_to_image(
+                *command_buffer.handler.as_ref(),
+                staging.buffer,
+                image.image,
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                &regions,
+            );
+        }
+        self.reflection_probes_barrier(
+            &command_buffer,
+            &image,
+            (mip_count, layer_count),
+            (
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+                ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            ),
+            (AccessFlags::TRANSFER_WRITE, AccessFlags::SHADER_READ),
+            (
+                PipelineStageFlags::TRANSFER,
+                PipelineStageFlags::FRAGMENT_SHADER,
+            ),
+        )?;
+        let queue = self.get_queues()?.get_graphics_queue()?;
+        if let Err(err) = command_buffer.end_single_use(device, pool, queue) {
+            error!(
+                "Failed to submit the upload of the reflection probes: {:?}",
+                err
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+
+        if let Err(err) = self.destroy_buffer(&staging) {
+            error!(
+                "Failed to destroy the staging buffer of the reflection probes: {:?}",
+                err
+            );
+            return Err(EngineError::InitializationFailed);
+        }
+        Ok(image)
+    }
+
+    /// Replace the cube array by the environment maps, in the order of the probe indices
+    /// Without maps a single black cube is bound so the shaders always have one
+    pub(crate) fn reflection_probes_upload(
+        &mut self,
+        environment_maps: &[&EnvironmentMap],
+    ) -> Result<(), EngineError> {
+        let (face_size, mip_count, level_count) = if environment_maps.is_empty() {
+            (1, 1, 1)
+        } else {
+            let (face_size, mip_count) = reflection_probes_get_layout(environment_maps)?;
+            (
+                face_size,
+                mip_count,
+                environment_maps[0].levels.len() as u32,
+            )
+        };
+        let cube_count = environment_maps.len().max(1) as u32;
+
+        // Level by level, then probe by probe, then face by face
+        let mut texels = Vec::new();
+        if environment_maps.is_empty() {
+            texels.resize(
+                REFLECTION_PROBE_FACE_COUNT * REFLECTION_PROBES_TEXEL_SIZE,
+                0,
+            );
+        }
+        for mip_level in 0..mip_count {
+            for environment_map in environment_maps {
+                let level = &environment_map.levels[mip_level as usize];
+                for face in &level.faces {
+                    for texel in face {
+                        for channel in [texel.x, texel.y, texel.z, 1.0] {
+                            texels.extend_from_slice(
+                                &reflection_probes_f32_to_half(channel).to_ne_bytes(),
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        let image =
+            self.reflection_probes_create_image(face_size, mip_count, cube_count, &texels)?;
+
+        // The previous frames may still sample the old probes
+        if let Err(err) = self.device_wait_idle() {
+            error!(
+                "Failed to wait idle when replacing the reflection probes: {:?}",
+                err
+            );
+            return Err(EngineError::UpdateFailed);
+        }
+        let reflection_probes = match self.context.resources.reflection_probes.as_mut() {
+            Some(reflection_probes) => reflection_probes,
+            None => {
+                error!("The reflection probes are uploaded before their initialization");
+                return Err(EngineError::NotInitialized);
+            }
+        };
+        let old_image = std::mem::replace(&mut reflection_probes.image, image);
+        reflection_probes.level_count = level_count;
+        if let Err(err) = self.destroy_image(&old_image) {
+            error!(
+                "Failed to destroy the previous reflection probes image: {:?}",
+                err
+            );
+            return Err(EngineError::UpdateFailed);
+        }
+        Ok(())
+    }
+
+    pub(crate) fn reflection_probes_init(&mut self) -> Result<(), EngineError> {
+        // The levels are picked in the shaders, no clamping on the sampler side
+        let sampler_create_info = SamplerCreateInfo::default()
+            .mag_filter(Filter::LINEAR)
+            .min_filter(Filter::LINEAR)
+            .address_mode_u(SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_v(SamplerAddressMode::CLAMP_TO_EDGE)
+            .address_mode_w(SamplerAddressMode::CLAMP_TO_EDGE)
+            .anisotropy_enable(false)
+            .border_color(BorderColor::FLOAT_OPAQUE_BLACK)
+            .unnormalized_coordinates(false)
+            .compare_enable(false)
+            .compare_op(CompareOp::ALWAYS)
+            .mipmap_mode(SamplerMipmapMode::LINEAR)
+            .mip_lod_bias(0.0)
+            .min_lod(0.0)
+            .max_lod(LOD_CLAMP_NONE);
+        let sampler = unsafe {
+            match self
+                .get_device()?
+                .create_sampler(&sampler_create_info, self.get_allocator()?)
+            {
+                Ok(sampler) => sampler,
+                Err(err) => {
+                    error!("Failed to create the reflection probes sampler: {:?}", err);
+                    return Err(EngineError::InitializationFailed);
+                }
+            }
+        };
+        let texels = vec![0; REFLECTION_PROBE_FACE_COUNT * REFLECTION_PROBES_TEXEL_SIZE];
+        let image = self.reflection_probes_create_image(1, 1, 1, &texels)?;
+        self.context.resources.reflection_probes = Some(ReflectionProbeCubemaps {
+            image,
+            sampler,
+            level_count: 1,
+        });
+        Ok(())
+    }
+
+    /// Waits for the gpu, the probes may still be sampled
+    pub(crate) fn reflection_probes_shutdown(&mut self) -> Result<(), EngineError> {
+        if let Err(err) = self.device_wait_idle() {
+            error!(
+                "Failed to wait idle when destroying the reflection probes: {:?}",
+                err
+            );
+            return Err(EngineError::ShutdownFailed);
+        }
+        let reflection_probes = match self.context.resources.reflection_probes.take() {
+            Some(reflection_probes) => reflection_probes,
+            None => return Ok(()),
+        };
+        if let Err(err) = self.destroy_image(&reflection_probes.image) {
+            error!("Failed to destroy the reflection probes image: {:?}", err);
+            return Err(EngineError::ShutdownFailed);
+        }
+        unsafe {
+            self.get_device()?
+                .destroy_sampler(reflection_probes.sampler, self.get_allocator()?);
+        }
+        Ok(())
+    }
+}
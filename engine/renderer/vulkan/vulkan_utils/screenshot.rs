@@ -0,0 +1,408 @@
+use ash::vk::{
+    AccessFlags, BufferImageCopy, BufferUsageFlags, DependencyFlags, Extent3D, Format,
+    ImageAspectFlags, ImageLayout, ImageMemoryBarrier, ImageSubresourceLayers,
+    ImageSubresourceRange, MemoryMapFlags, MemoryPropertyFlags, PipelineStageFlags,
+    QUEUE_FAMILY_IGNORED,
+};
+
+use crate::{
+    core::debug::errors::EngineError,
+    error,
+    renderer::{
+        renderer_types::{OutputColorSpace, RENDERER_MAX_IN_FLIGHT_FRAMES},
+        screenshot::{HdrScreenshotPixels, ScreenshotPixels},
+        vulkan::{
+            vulkan_init::swapchain::VULKAN_HDR_ATTACHMENT_FORMAT,
+            vulkan_types::VulkanRendererBackend,
+        },
+    },
+};
+
+use super::{
+    buffer::{Buffer, BufferCreatorParameters},
+    per_frame_resource::PerFrameResource,
+};
+
+/// Copy of a presented image waiting for its frame to be done on the gpu
+pub(crate) struct ScreenshotReadback {
+    pub buffer: Buffer,
+    pub width: u32,
+    pub height: u32,
+    pub format: Format,
+}
+
+/// Bytes per pixel of the hdr attachment, four half floats
+const SCREENSHOT_HDR_PIXEL_SIZE: u32 = 8;
+
+/// Decode an ieee 754 half float, the hdr attachment is read back as is
+pub(crate) fn screenshot_half_to_f32(bits: u16) -> f32 {
+    let sign = if bits & 0x8000 != 0 { -1.0 } else { 1.0 };
+    let exponent = ((bits >> 10) & 0x1f) as i32;
+    let mantissa = (bits & 0x3ff) as f32;
+    match exponent {
+        0 => sign * mantissa * 2f32.powi(-24),
+        0x1f if mantissa == 0.0 => sign * f32::INFINITY,
+        0x1f => f32::NAN,
+        _ => sign * (1.0 + mantissa / 1024.0) * 2f32.powi(exponent - 15),
+    }
+}
+
+impl VulkanRendererBackend<'_> {
+    /// The image presented by the next frame is copied back
+    /// With `hdr', the hdr attachment is also copied before the post processing
+    pub(crate) fn screenshot_request(&mut self, hdr: bool) -> Result<(), EngineError> {
+        if !self.get_swapchain()?.supports_readback {
+            error!("Failed to request a screenshot: the vulkan swapchain images can't be copied");
+            return Err(EngineError::NotImplemented);
+        }
+        // The readback only converts the 8 bits srgb images
+        if self.output_color_space != OutputColorSpace::Srgb {
+            error!(
+                "Failed to request a screenshot: the {:?} output can't be read back",
+                self.output_color_space
+            );
+            return Err(EngineError::NotImplemented);
+        }
+        self.context.resources.screenshot_requested = true;
+        self.context.resources.screenshot_hdr_requested = hdr;
+        Ok(())
+    }
+
+    /// Create the buffer the render graph copies the hdr attachment into, before the frame is declared
+    pub(crate) fn screenshot_prepare_hdr_copy(
+        &mut self,
+        frame_index: usize,
+    ) -> Result<(), EngineError> {
+        if !self.context.resources.screenshot_hdr_requested {
+            return Ok(());
+        }
+        self.context.resources.screenshot_hdr_requested = false;
+
+        let extent = self.get_swapchain()?.extent;
+        let buffer_create_info = BufferCreatorParameters::default()
+            .buffer_usage_flags(BufferUsageFlags::TRANSFER_DST)
+            .memory_flags(MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT)
+            .size((extent.width * extent.height * SCREENSHOT_HDR_PIXEL_SIZE) as usize)
+            .should_be_bind(true);
+        let buffer = match self.create_buffer(buffer_create_info) {
+            Ok(buffer) => buffer,
+            Err(err) => {
+                error!(
+                    "Failed to create the readback buffer of an hdr screenshot: {:?}",
+                    err
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        self.context.resources.screenshot_hdr_readbacks.set(
+            frame_index,
+            Some(ScreenshotReadback {
+                buffer,
+                width: extent.width,
+                height: extent.height,
+                format: VULKAN_HDR_ATTACHMENT_FORMAT,
+            }),
+        );
+        Ok(())
+    }
+
+    /// The render graph copies the hdr attachment of the frame when a buffer waits for it
+    pub(crate) fn screenshot_has_hdr_copy(&self, frame_index: usize) -> bool {
+        self.context
+            .resources
+            .screenshot_hdr_readbacks
+            .get(frame_index)
+            .is_some()
+    }
+
+    /// Record the copy of the hdr attachment, already in the transfer source layout
+    pub(crate) fn screenshot_record_hdr_copy(
+        &self,
+        frame_index: usize,
+        image: ash::vk::Image,
+    ) -> Result<(), EngineError> {
+        let readback = match self
+            .context
+            .resources
+            .screenshot_hdr_readbacks
+            .get(frame_index)
+        {
+            Some(readback) => readback,
+            None => {
+                error!("The hdr attachment is copied without a readback buffer");
+                return Err(EngineError::AccessFailed);
+            }
+        };
+        let subresource = ImageSubresourceLayers::default()
+            .aspect_mask(ImageAspectFlags::COLOR)
+            .mip_level(0)
+            .base_array_layer(0)
+            .layer_count(1);
+        let regions = [BufferImageCopy::default()
+            .image_subresource(subresource)
+            .image_extent(
+                Extent3D::default()
+                    .width(readback.width)
+                    .height(readback.height)
+                    .depth(1),
+            )];
+        let command_buffer = &self.get_graphics_command_buffers()?[frame_index];
+        unsafe {
+            self.get_device()?.cmd_copy_image_to_buffer(
+                *command_buffer.handler.as_ref(),
+                image,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                readback.buffer.buffer,
+                &regions,
+            );
+        }
+        Ok(())
+    }
+
+    fn screenshot_image_barrier(
+        &self,
+        frame_index: usize,
+        image: ash::vk::Image,
+        old_layout: ImageLayout,
+        new_layout: ImageLayout,
+        (src_access_mask, dst_access_mask): (AccessFlags, AccessFlags),
+        (src_stage, dst_stage): (PipelineStageFlags, PipelineStageFlags),
+    ) -> Result<(), EngineError> {
+        let subresource = ImageSubresourceRange::default()
+            .aspect_mask(ImageAspectFlags::COLOR)
+            .base_mip_level(0)
+            .level_count(1)
+            .base_array_layer(0)
+            .layer_count(1);
+        let barriers = [ImageMemoryBarrier::default()
+            .old_layout(old_layout)
+            .new_layout(new_layout)
+            .src_queue_family_index(QUEUE_FAMILY_IGNORED)
+            .dst_queue_family_index(QUEUE_FAMILY_IGNORED)
+            .src_access_mask(src_access_mask)
+            .dst_access_mask(dst_access_mask)
+            .image(image)
+            .subresource_range(subresource)];
+        let command_buffer = &self.get_graphics_command_buffers()?[frame_index];
+        unsafe {
+            self.get_device()?.cmd_pipeline_barrier(
+                *command_buffer.handler.as_ref(),
+                src_stage,
+                dst_stage,
+                DependencyFlags::empty(),
+                &[],
+                &[],
+                &barriers,
+            );
+        }
+        Ok(())
+    }
+
+    /// Record the copy of the presented image after the render graph, the msaa resolve is already done
+    pub(crate) fn screenshot_record_copy(&mut self, frame_index: usize) -> Result<(), EngineError> {
+        if !self.context.resources.screenshot_requested {
+            return Ok(());
+        }
+        self.context.resources.screenshot_requested = false;
+
+        let swapchain = self.get_swapchain()?;
+        let image = swapchain.images[self.context.frame.image_index as usize];
+        let (width, height) = (swapchain.extent.width, swapchain.extent.height);
+        let format = swapchain.surface_format.format;
+        let buffer_create_info = BufferCreatorParameters::default()
+            .buffer_usage_flags(BufferUsageFlags::TRANSFER_DST)
+            .memory_flags(MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT)
+            .size((width * height * 4) as usize)
+            .should_be_bind(true);
+        let buffer = match self.create_buffer(buffer_create_info) {
+            Ok(buffer) => buffer,
+            Err(err) => {
+                error!(
+                    "Failed to create the readback buffer of a screenshot: {:?}",
+                    err
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+
+        // The render graph left the image ready to be presented
+        self.screenshot_image_barrier(
+            frame_index,
+            image,
+            ImageLayout::PRESENT_SRC_KHR,
+            ImageLayout::TRANSFER_SRC_OPTIMAL,
+            (
+                AccessFlags::COLOR_ATTACHMENT_WRITE,
+                AccessFlags::TRANSFER_READ,
+            ),
+            (
+                PipelineStageFlags::COLOR_ATTACHMENT_OUTPUT,
+                PipelineStageFlags::TRANSFER,
+            ),
+        )?;
+        let subresource = ImageSubresourceLayers::default()
+            .aspect_mask(ImageAspectFlags::COLOR)
+            .mip_level(0)
+            .base_array_layer(0)
+            .layer_count(1);
+        let regions = [BufferImageCopy::default()
+            .image_subresource(subresource)
+            .image_extent(Extent3D::default().width(width).height(height).depth(1))];
+        let command_buffer = &self.get_graphics_command_buffers()?[frame_index];
+        unsafe {
+            self.get_device()?.cmd_copy_image_to_buffer(
+                *command_buffer.handler.as_ref(),
+                image,
+                ImageLayout::TRANSFER_SRC_OPTIMAL,
+                buffer.buffer,
+                &regions,
+            );
+        }
+        self.screenshot_image_barrier(
+            frame_index,
+            image,
+            ImageLayout::TRANSFER_SRC_OPTIMAL,
+            ImageLayout::PRESENT_SRC_KHR,
+            (AccessFlags::TRANSFER_READ, AccessFlags::empty()),
+            (
+                PipelineStageFlags::TRANSFER,
+                PipelineStageFlags::BOTTOM_OF_PIPE,
+            ),
+        )?;
+
+        self.context.resources.screenshot_readbacks.set(
+            frame_index,
+            Some(ScreenshotReadback {
+                buffer,
+                width,
+                height,
+                format,
+            }),
+        );
+        Ok(())
+    }
+
+    /// Read the bytes of a copy and destroy its buffer
+    fn screenshot_read_readback(
+        &mut self,
+        readback: &ScreenshotReadback,
+        pixel_size: u32,
+    ) -> Result<Vec<u8>, EngineError> {
+        let size = (readback.width * readback.height * pixel_size) as usize;
+        let result = self.read_data_from_buffer(&readback.buffer, 0, size, MemoryMapFlags::empty());
+        if let Err(err) = self.destroy_buffer(&readback.buffer) {
+            error!(
+                "Failed to destroy the readback buffer of a screenshot: {:?}",
+                err
+            );
+            return Err(EngineError::ShutdownFailed);
+        }
+        match result {
+            Ok(pixels) => Ok(pixels),
+            Err(err) => {
+                error!("Failed to read the pixels of a screenshot: {:?}", err);
+                Err(EngineError::AccessFailed)
+            }
+        }
+    }
+
+    /// The frame is done on the gpu, its copies can be read
+    pub(crate) fn screenshot_frame_completed(
+        &mut self,
+        frame_index: usize,
+    ) -> Result<(), EngineError> {
+        if let Some(readback) = self
+            .context
+            .resources
+            .screenshot_hdr_readbacks
+            .get_mut(frame_index)
+            .take()
+        {
+            let bytes = self.screenshot_read_readback(&readback, SCREENSHOT_HDR_PIXEL_SIZE)?;
+            let pixels = bytes
+                .chunks_exact(SCREENSHOT_HDR_PIXEL_SIZE as usize)
+                .map(|pixel| {
+                    let channel = |index: usize| {
+                        screenshot_half_to_f32(u16::from_ne_bytes([
+                            pixel[2 * index],
+                            pixel[2 * index + 1],
+                        ]))
+                    };
+                    glam::Vec4::new(channel(0), channel(1), channel(2), channel(3))
+                })
+                .collect();
+            self.context.resources.screenshot_hdr = Some(HdrScreenshotPixels {
+                width: readback.width,
+                height: readback.height,
+                pixels,
+            });
+        }
+
+        let readback = match self
+            .context
+            .resources
+            .screenshot_readbacks
+            .get_mut(frame_index)
+            .take()
+        {
+            Some(readback) => readback,
+            None => return Ok(()),
+        };
+        let mut pixels = self.screenshot_read_readback(&readback, 4)?;
+
+        match readback.format {
+            Format::B8G8R8A8_UNORM | Format::B8G8R8A8_SRGB => pixels
+                .chunks_exact_mut(4)
+                .for_each(|pixel| pixel.swap(0, 2)),
+            Format::R8G8B8A8_UNORM | Format::R8G8B8A8_SRGB => (),
+            format => {
+                error!("Can't read a screenshot of the {:?} format", format);
+                return Err(EngineError::NotImplemented);
+            }
+        }
+        // The window is opaque whatever the alpha written by the shaders
+        pixels.chunks_exact_mut(4).for_each(|pixel| pixel[3] = 255);
+
+        self.context.resources.screenshot = Some(ScreenshotPixels {
+            width: readback.width,
+            height: readback.height,
+            pixels,
+        });
+        Ok(())
+    }
+
+    pub(crate) fn screenshot_take(&mut self) -> Option<ScreenshotPixels> {
+        self.context.resources.screenshot.take()
+    }
+
+    pub(crate) fn screenshot_take_hdr(&mut self) -> Option<HdrScreenshotPixels> {
+        self.context.resources.screenshot_hdr.take()
+    }
+
+    pub(crate) fn screenshots_init(&mut self) -> Result<(), EngineError> {
+        self.context.resources.screenshot_requested = false;
+        self.context.resources.screenshot_readbacks = PerFrameResource::default();
+        self.context.resources.screenshot = None;
+        self.context.resources.screenshot_hdr_requested = false;
+        self.context.resources.screenshot_hdr_readbacks = PerFrameResource::default();
+        self.context.resources.screenshot_hdr = None;
+        Ok(())
+    }
+
+    /// Destroy the copies still in flight, waits for the gpu
+    pub(crate) fn screenshots_shutdown(&mut self) -> Result<(), EngineError> {
+        if let Err(err) = self.device_wait_idle() {
+            error!(
+                "Failed to wait idle when destroying the screenshot readbacks: {:?}",
+                err
+            );
+            return Err(EngineError::ShutdownFailed);
+        }
+        for frame_index in 0..RENDERER_MAX_IN_FLIGHT_FRAMES {
+            self.screenshot_frame_completed(frame_index)?;
+        }
+        self.context.resources.screenshot = None;
+        self.context.resources.screenshot_hdr = None;
+        Ok(())
+    }
+}
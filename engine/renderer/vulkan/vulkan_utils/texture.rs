@@ -1,21 +1,26 @@
 use ash::vk::{
-    BorderColor, BufferUsageFlags, CompareOp, Filter, Format, ImageAspectFlags, ImageLayout,
-    ImageTiling, ImageType, ImageUsageFlags, MemoryMapFlags, MemoryPropertyFlags, Sampler,
-    SamplerAddressMode, SamplerCreateInfo, SamplerMipmapMode,
+    BorderColor, BufferUsageFlags, CompareOp, Extent3D, Filter, Format, ImageAspectFlags,
+    ImageLayout, ImageTiling, ImageType, ImageUsageFlags, MemoryMapFlags, MemoryPropertyFlags,
+    Offset3D, Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerMipmapMode,
 };
 
 use crate::{
     core::debug::errors::EngineError,
     error,
-    renderer::vulkan::{
-        vulkan_init::command_buffer::CommandBuffer, vulkan_types::VulkanRendererBackend,
+    renderer::{
+        renderer_types::RENDERER_MAX_IN_FLIGHT_FRAMES,
+        vulkan::{
+            vulkan_init::{command_buffer::CommandBuffer, queue_ownership::OwnershipTransfer},
+            vulkan_types::VulkanRendererBackend,
+        },
     },
     resources::texture::TextureCreatorParameters,
 };
 
 use super::{
-    buffer::BufferCreatorParameters,
+    buffer::{Buffer, BufferCreatorParameters},
     image::{Image, ImageCreatorParameters},
+    per_frame_resource::PerFrameResource,
 };
 
 #[derive(Clone, Copy)]
@@ -55,6 +60,10 @@ impl crate::resources::texture::Texture for Texture {
         self.generation
     }
 
+    fn set_generation(&mut self, generation: Option<u32>) {
+        self.generation = generation;
+    }
+
     fn as_any(&self) -> &dyn std::any::Any {
         self
     }
@@ -66,13 +75,22 @@ impl crate::resources::texture::Texture for Texture {
 
 impl VulkanRendererBackend<'_> {
     pub(crate) fn vulkan_destroy_texture(&self, texture: &Texture) -> Result<(), EngineError> {
-        if let Err(err) = self.device_wait_idle() {
+        // The uploads not submitted yet may still write or hand over the image
+        if let Err(err) = self
+            .transfer_wait_idle()
+            .and_then(|_| self.device_wait_idle())
+        {
             error!(
                 "Failed to wait idle when destroying a vulkan texture: {:?}",
                 err
             );
             return Err(EngineError::ShutdownFailed);
         }
+        self.vulkan_destroy_texture_resources(texture)
+    }
+
+    /// The texture must not be used by the frames in flight anymore
+    fn vulkan_destroy_texture_resources(&self, texture: &Texture) -> Result<(), EngineError> {
         if let Err(err) = self.destroy_image(&texture.image) {
             error!(
                 "Failed to destroy the image when destroying a vulkan texture: {:?}",
@@ -86,48 +104,76 @@ impl VulkanRendererBackend<'_> {
         unsafe {
             device.destroy_sampler(texture.sampler, allocator);
         }
+        self.bindless_textures_release(texture.id);
         Ok(())
     }
 
-    pub(crate) fn vulkan_create_texture(
-        &self,
-        params: TextureCreatorParameters,
-    ) -> Result<Texture, EngineError> {
-        // Internal data creation
-        // Create a staging buffer and load data into it
-        let image_size = (params.width * params.height * (params.nb_channels as u32)) as usize;
-        let memory_prop_flags =
-            MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT;
-        let buffer_create_info = BufferCreatorParameters::default()
-            .buffer_usage_flags(BufferUsageFlags::TRANSFER_SRC)
-            .memory_flags(memory_prop_flags)
-            .size(image_size)
-            .should_be_bind(true);
-        let staging = match self.create_buffer(buffer_create_info) {
-            Ok(staging) => staging,
-            Err(err) => {
-                error!(
-                    "Failed to create a stagging buffer when creating a vulkan texture: {:?}",
-                    err
-                );
-                return Err(EngineError::InitializationFailed);
+    /// Destroy the texture once the frames that may still sample it are done, without waiting for the gpu
+    pub(crate) fn vulkan_retire_texture(&mut self, texture: Texture) {
+        self.context.resources.retired_textures.push(texture);
+    }
+
+    /// The textures retired so far are destroyed once the frame being submitted is done
+    pub(crate) fn retired_textures_frame_submitted(&mut self, frame_index: usize) {
+        let retired_textures = std::mem::take(&mut self.context.resources.retired_textures);
+        self.context
+            .resources
+            .retired_frame_textures
+            .get_mut(frame_index)
+            .extend(retired_textures);
+    }
+
+    /// The frame is done on the gpu, the textures retired before its submission can be destroyed
+    pub(crate) fn retired_textures_frame_completed(
+        &mut self,
+        frame_index: usize,
+    ) -> Result<(), EngineError> {
+        let retired_textures = std::mem::take(
+            self.context
+                .resources
+                .retired_frame_textures
+                .get_mut(frame_index),
+        );
+        for texture in &retired_textures {
+            if let Err(err) = self.vulkan_destroy_texture_resources(texture) {
+                error!("Failed to destroy a retired vulkan texture: {:?}", err);
+                return Err(EngineError::ShutdownFailed);
             }
-        };
+        }
+        Ok(())
+    }
 
-        let data = params.pixels;
-        let data = data.as_ptr() as *mut std::ffi::c_void;
-        if let Err(err) =
-            self.load_data_into_buffer(&staging, 0, image_size, MemoryMapFlags::empty(), data)
-        {
+    pub(crate) fn retired_textures_init(&mut self) -> Result<(), EngineError> {
+        self.context.resources.retired_textures = Vec::new();
+        self.context.resources.retired_frame_textures = PerFrameResource::default();
+        Ok(())
+    }
+
+    /// Destroy every retired texture, waits for the gpu
+    pub(crate) fn retired_textures_shutdown(&mut self) -> Result<(), EngineError> {
+        if let Err(err) = self.device_wait_idle() {
             error!(
-                "Failed to load data into a stagging buffer when creating a vulkan texture: {:?}",
+                "Failed to wait idle when destroying the retired vulkan textures: {:?}",
                 err
             );
-            return Err(EngineError::InitializationFailed);
-        };
+            return Err(EngineError::ShutdownFailed);
+        }
+        self.retired_textures_frame_submitted(0);
+        for frame_index in 0..RENDERER_MAX_IN_FLIGHT_FRAMES {
+            self.retired_textures_frame_completed(frame_index)?;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn vulkan_create_texture(
+        &mut self,
+        params: TextureCreatorParameters,
+    ) -> Result<Texture, EngineError> {
+        let image_size = (params.width * params.height * (params.nb_channels as u32)) as usize;
 
         // NOTE: Lots of assumptions here, different texture types will require different options here
         // NOTE: Assumes 8 bits per channel
+        // The storage usage lets the compute shaders write the texture
         let image_format = Format::R8G8B8A8_UNORM;
         let image_create_info = ImageCreatorParameters::default()
             .width(params.width)
@@ -140,7 +186,8 @@ impl VulkanRendererBackend<'_> {
                 ImageUsageFlags::TRANSFER_SRC
                     | ImageUsageFlags::TRANSFER_DST
                     | ImageUsageFlags::SAMPLED
-                    | ImageUsageFlags::COLOR_ATTACHMENT,
+                    | ImageUsageFlags::COLOR_ATTACHMENT
+                    | ImageUsageFlags::STORAGE,
             )
             .should_create_view(true)
             .image_view_aspect_flags(ImageAspectFlags::COLOR);
@@ -155,59 +202,34 @@ impl VulkanRendererBackend<'_> {
             }
         };
 
-        let pool = self.get_graphics_command_pool()?;
-        let device = self.get_device()?;
-        let temporary_buffer = match CommandBuffer::allocate_and_begin_single_use(device, pool) {
-            Ok(buffer) => buffer,
-            Err(err) => {
-                error!(
-                    "Failed to allocate a staging buffer when creating a vulkan texture: {:?}",
-                    err
-                );
-                return Err(EngineError::InitializationFailed);
-            }
+        // Upload the pixels on the transfer queue, the next frame waits for them
+        let record_upload = |command_buffer: &CommandBuffer, staging_buffer: &Buffer, offset| {
+            // Transition the layout from whatever it is currently to optimal for recieving data
+            self.transition_image_layout(
+                command_buffer,
+                &image,
+                image_format,
+                ImageLayout::UNDEFINED,
+                ImageLayout::TRANSFER_DST_OPTIMAL,
+            )?;
+            // Copy the data from the buffer
+            self.copy_image_from_buffer(command_buffer, staging_buffer, offset, &image)?;
+            // The graphics queue takes the image and its final layout before sampling it
+            Ok(OwnershipTransfer::uploaded_image(image.image))
         };
-
-        // Transition the layout from whatever it is currently to optimal for recieving data
-        if let Err(err) = self.transition_image_layout(
-            &temporary_buffer,
-            &image,
-            image_format,
-            ImageLayout::UNDEFINED,
-            ImageLayout::TRANSFER_DST_OPTIMAL,
-        ) {
-            error!(
-                "Failed to transition the image layout when creating a vulkan texture: {:?}",
-                err
-            );
-            return Err(EngineError::InitializationFailed);
-        }
-
-        // Copy the data from the buffer
-        if let Err(err) = self.copy_image_from_buffer(&temporary_buffer, &staging, &image) {
-            error!("Failed to copy the image from the staging buffer when creating a vulkan texture: {:?}", err);
-            return Err(EngineError::InitializationFailed);
-        }
-
-        // Transition from optimal for data reciept to shader-read-only optimal layout
-        if let Err(err) = self.transition_image_layout(
-            &temporary_buffer,
-            &image,
-            image_format,
-            ImageLayout::TRANSFER_DST_OPTIMAL,
-            ImageLayout::SHADER_READ_ONLY_OPTIMAL,
-        ) {
+        let data = params.pixels.as_ptr() as *mut std::ffi::c_void;
+        let alignment = self
+            .get_physical_device_info()?
+            .properties
+            .limits
+            .optimal_buffer_copy_offset_alignment
+            .max(4);
+        if let Err(err) = self.transfer_upload(data, image_size, alignment, record_upload) {
             error!(
-                "Failed to transition the image layout when creating a vulkan texture: {:?}",
+                "Failed to upload the pixels when creating a vulkan texture: {:?}",
                 err
             );
-            return Err(EngineError::InitializationFailed);
-        }
-
-        let device = self.get_device()?;
-        let queue = self.get_queues()?.graphics_queue.unwrap();
-        if let Err(err) = temporary_buffer.end_single_use(device, pool, queue) {
-            error!("Failed to end the single use of the staging buffer when creating a vulkan texture: {:?}", err);
+            self.destroy_image(&image)?;
             return Err(EngineError::InitializationFailed);
         }
 
@@ -230,6 +252,7 @@ impl VulkanRendererBackend<'_> {
             .min_lod(0.0)
             .max_lod(0.0);
 
+        let device = self.get_device()?;
         let allocator = self.get_allocator()?;
         let sampler = unsafe {
             match device.create_sampler(&sampler_create_info, allocator) {
@@ -244,21 +267,14 @@ impl VulkanRendererBackend<'_> {
             }
         };
 
-        // Destroy the staging buffer
-        if let Err(err) = self.destroy_buffer(&staging) {
-            error!(
-                "Failed to destroy the staging buffer when creating a vulkan texture: {:?}",
-                err
-            );
-            return Err(EngineError::ShutdownFailed);
-        }
-
         let generation = if params.is_default { None } else { Some(0) };
+        let id = self.next_texture_id;
+        self.next_texture_id += 1;
 
         Ok(Texture {
             width: params.width,
             height: params.height,
-            id: 0, // TODO: change id
+            id,
             nb_channels: params.nb_channels,
             generation,
             has_transparency: params.has_transparency,
@@ -266,4 +282,258 @@ impl VulkanRendererBackend<'_> {
             sampler,
         })
     }
+
+    /// Re-upload a sub-rectangle of the texture, the pixels are tightly packed rows
+    pub(crate) fn vulkan_update_texture_region(
+        &self,
+        texture: &Texture,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+        pixels: &[u8],
+    ) -> Result<(), EngineError> {
+        if width == 0 || height == 0 {
+            return Ok(());
+        }
+        if x + width > texture.width || y + height > texture.height {
+            error!(
+                "The region ({}, {}, {}, {}) is outside of the {}x{} texture",
+                x, y, width, height, texture.width, texture.height
+            );
+            return Err(EngineError::InvalidValue);
+        }
+        let region_size = (width * height * (texture.nb_channels as u32)) as usize;
+        if pixels.len() != region_size {
+            error!(
+                "The region of a texture needs {} bytes of pixels instead of {}",
+                region_size,
+                pixels.len()
+            );
+            return Err(EngineError::InvalidValue);
+        }
+
+        // The texture may still be uploading on the transfer queue
+        if let Err(err) = self.transfer_wait_idle() {
+            error!(
+                "Failed to wait for the uploads when updating a vulkan texture: {:?}",
+                err
+            );
+            return Err(EngineError::UpdateFailed);
+        }
+
+        // Create a staging buffer and load the region into it
+        let memory_prop_flags =
+            MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT;
+        let buffer_create_info = BufferCreatorParameters::default()
+            .buffer_usage_flags(BufferUsageFlags::TRANSFER_SRC)
+            .memory_flags(memory_prop_flags)
+            .size(region_size)
+            .should_be_bind(true);
+        let staging = match self.create_buffer(buffer_create_info) {
+            Ok(staging) => staging,
+            Err(err) => {
+                error!(
+                    "Failed to create a stagging buffer when updating a vulkan texture: {:?}",
+                    err
+                );
+                return Err(EngineError::UpdateFailed);
+            }
+        };
+        let data = pixels.as_ptr() as *mut std::ffi::c_void;
+        if let Err(err) =
+            self.load_data_into_buffer(&staging, 0, region_size, MemoryMapFlags::empty(), data)
+        {
+            error!(
+                "Failed to load data into a stagging buffer when updating a vulkan texture: {:?}",
+                err
+            );
+            return Err(EngineError::UpdateFailed);
+        };
+
+        let pool = self.get_graphics_command_pool()?;
+        let device = self.get_device()?;
+        let temporary_buffer = match CommandBuffer::allocate_and_begin_single_use(device, pool) {
+            Ok(buffer) => buffer,
+            Err(err) => {
+                error!(
+                    "Failed to allocate a command buffer when updating a vulkan texture: {:?}",
+                    err
+                );
+                return Err(EngineError::UpdateFailed);
+            }
+        };
+
+        // NOTE: Assumes 8 bits per channel like the creation
+        let image_format = Format::R8G8B8A8_UNORM;
+        if let Err(err) = self.transition_image_layout(
+            &temporary_buffer,
+            &texture.image,
+            image_format,
+            ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            ImageLayout::TRANSFER_DST_OPTIMAL,
+        ) {
+            error!(
+                "Failed to transition the image layout when updating a vulkan texture: {:?}",
+                err
+            );
+            return Err(EngineError::UpdateFailed);
+        }
+
+        let offset = Offset3D::default().x(x as i32).y(y as i32).z(0);
+        let extent = Extent3D::default().width(width).height(height).depth(1);
+        if let Err(err) = self.copy_image_region_from_buffer(
+            &temporary_buffer,
+            &staging,
+            0,
+            &texture.image,
+            offset,
+            extent,
+        ) {
+            error!("Failed to copy the region from the staging buffer when updating a vulkan texture: {:?}", err);
+            return Err(EngineError::UpdateFailed);
+        }
+
+        if let Err(err) = self.transition_image_layout(
+            &temporary_buffer,
+            &texture.image,
+            image_format,
+            ImageLayout::TRANSFER_DST_OPTIMAL,
+            ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        ) {
+            error!(
+                "Failed to transition the image layout when updating a vulkan texture: {:?}",
+                err
+            );
+            return Err(EngineError::UpdateFailed);
+        }
+
+        let queue = self.get_queues()?.get_graphics_queue()?;
+        if let Err(err) = temporary_buffer.end_single_use(device, pool, queue) {
+            error!("Failed to end the single use of the command buffer when updating a vulkan texture: {:?}", err);
+            return Err(EngineError::UpdateFailed);
+        }
+
+        if let Err(err) = self.destroy_buffer(&staging) {
+            error!(
+                "Failed to destroy the staging buffer when updating a vulkan texture: {:?}",
+                err
+            );
+            return Err(EngineError::UpdateFailed);
+        }
+        Ok(())
+    }
+
+    /// Copy the texture back to the cpu as tightly packed rgba8 rows, waits for the gpu
+    pub(crate) fn vulkan_read_texture_pixels(
+        &self,
+        texture: &Texture,
+    ) -> Result<Vec<u8>, EngineError> {
+        // The texture may still be uploading on the transfer queue
+        if let Err(err) = self.transfer_wait_idle() {
+            error!(
+                "Failed to wait for the uploads when reading a vulkan texture: {:?}",
+                err
+            );
+            return Err(EngineError::AccessFailed);
+        }
+
+        // NOTE: Assumes 8 bits per channel like the creation
+        let image_format = Format::R8G8B8A8_UNORM;
+        let image_size = (texture.width * texture.height * 4) as usize;
+        let memory_prop_flags =
+            MemoryPropertyFlags::HOST_VISIBLE | MemoryPropertyFlags::HOST_COHERENT;
+        let buffer_create_info = BufferCreatorParameters::default()
+            .buffer_usage_flags(BufferUsageFlags::TRANSFER_DST)
+            .memory_flags(memory_prop_flags)
+            .size(image_size)
+            .should_be_bind(true);
+        let staging = match self.create_buffer(buffer_create_info) {
+            Ok(staging) => staging,
+            Err(err) => {
+                error!(
+                    "Failed to create a stagging buffer when reading a vulkan texture: {:?}",
+                    err
+                );
+                return Err(EngineError::AccessFailed);
+            }
+        };
+
+        let pool = self.get_graphics_command_pool()?;
+        let device = self.get_device()?;
+        let temporary_buffer = match CommandBuffer::allocate_and_begin_single_use(device, pool) {
+            Ok(buffer) => buffer,
+            Err(err) => {
+                error!(
+                    "Failed to allocate a command buffer when reading a vulkan texture: {:?}",
+                    err
+                );
+                return Err(EngineError::AccessFailed);
+            }
+        };
+
+        if let Err(err) = self.transition_image_layout(
+            &temporary_buffer,
+            &texture.image,
+            image_format,
+            ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+            ImageLayout::TRANSFER_SRC_OPTIMAL,
+        ) {
+            error!(
+                "Failed to transition the image layout when reading a vulkan texture: {:?}",
+                err
+            );
+            return Err(EngineError::AccessFailed);
+        }
+
+        if let Err(err) = self.copy_image_to_buffer(&temporary_buffer, &texture.image, &staging) {
+            error!(
+                "Failed to copy the image to the staging buffer when reading a vulkan texture: {:?}",
+                err
+            );
+            return Err(EngineError::AccessFailed);
+        }
+
+        if let Err(err) = self.transition_image_layout(
+            &temporary_buffer,
+            &texture.image,
+            image_format,
+            ImageLayout::TRANSFER_SRC_OPTIMAL,
+            ImageLayout::SHADER_READ_ONLY_OPTIMAL,
+        ) {
+            error!(
+                "Failed to transition the image layout when reading a vulkan texture: {:?}",
+                err
+            );
+            return Err(EngineError::AccessFailed);
+        }
+
+        // Waits for the copy to be done before reading the staging buffer
+        let queue = self.get_queues()?.get_graphics_queue()?;
+        if let Err(err) = temporary_buffer.end_single_use(device, pool, queue) {
+            error!("Failed to end the single use of the command buffer when reading a vulkan texture: {:?}", err);
+            return Err(EngineError::AccessFailed);
+        }
+
+        let pixels =
+            match self.read_data_from_buffer(&staging, 0, image_size, MemoryMapFlags::empty()) {
+                Ok(pixels) => pixels,
+                Err(err) => {
+                    error!(
+                        "Failed to read the staging buffer when reading a vulkan texture: {:?}",
+                        err
+                    );
+                    return Err(EngineError::AccessFailed);
+                }
+            };
+
+        if let Err(err) = self.destroy_buffer(&staging) {
+            error!(
+                "Failed to destroy the staging buffer when reading a vulkan texture: {:?}",
+                err
+            );
+            return Err(EngineError::AccessFailed);
+        }
+        Ok(pixels)
+    }
 }
@@ -0,0 +1,224 @@
+use crate::{
+    core::{
+        debug::errors::EngineError,
+        systems::events::{event_queue, EventCode},
+    },
+    error,
+};
+
+/// Position of a joint at a time of the clip
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AnimationKeyframe {
+    /// Time in seconds from the start of the clip
+    pub time: f32,
+    pub position: glam::Vec3,
+}
+
+/// Joint positions over time, one track of keyframes sorted by time per joint
+#[derive(Clone, Debug, Default)]
+pub struct AnimationClip {
+    joint_tracks: Vec<Vec<AnimationKeyframe>>,
+}
+
+impl AnimationClip {
+    /// The keyframes of each joint are sorted by time, a joint needs at least one
+    pub fn new(mut joint_tracks: Vec<Vec<AnimationKeyframe>>) -> Result<Self, EngineError> {
+        if let Some(joint) = joint_tracks.iter().position(|track| track.is_empty()) {
+            error!("The joint {} of an animation clip has no keyframe", joint);
+            return Err(EngineError::InvalidValue);
+        }
+        for track in &mut joint_tracks {
+            track.sort_by(|first, second| first.time.total_cmp(&second.time));
+        }
+        Ok(Self { joint_tracks })
+    }
+
+    pub fn get_joint_count(&self) -> usize {
+        self.joint_tracks.len()
+    }
+
+    /// Time of the last keyframe
+    pub fn get_duration(&self) -> f32 {
+        self.joint_tracks
+            .iter()
+            .filter_map(|track| track.last())
+            .map(|keyframe| keyframe.time)
+            .fold(0.0, f32::max)
+    }
+
+    /// Joint positions at the given time, interpolated linearly between the keyframes
+    pub fn sample(&self, time: f32, pose: &mut Vec<glam::Vec3>) {
+        pose.clear();
+        pose.extend(self.joint_tracks.iter().map(|track| {
+            let next = track.partition_point(|keyframe| keyframe.time <= time);
+            match (
+                next.checked_sub(1).map(|index| track[index]),
+                track.get(next).copied(),
+            ) {
+                (Some(previous), Some(next)) => {
+                    let span = next.time - previous.time;
+                    let factor = if span > 0.0 {
+                        (time - previous.time) / span
+                    } else {
+                        0.0
+                    };
+                    previous.position.lerp(next.position, factor)
+                }
+                (Some(keyframe), None) | (None, Some(keyframe)) => keyframe.position,
+                // The tracks are never empty
+                (None, None) => glam::Vec3::ZERO,
+            }
+        }));
+    }
+}
+
+/// Named event placed on an animation timeline
+#[derive(Clone, Debug)]
+pub struct AnimationEvent {
+    pub id: u32,
+    pub name: String,
+    /// Time in seconds from the start of the clip
+    pub time: f32,
+}
+
+/// Events of a clip sorted by time
+#[derive(Clone, Debug, Default)]
+pub struct AnimationEventTrack {
+    events: Vec<AnimationEvent>,
+    next_id: u32,
+}
+
+impl AnimationEventTrack {
+    /// Returns the id sent with the notify event
+    pub fn add_event(&mut self, name: &str, time: f32) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        let index = self.events.partition_point(|event| event.time <= time);
+        self.events.insert(
+            index,
+            AnimationEvent {
+                id,
+                name: String::from(name),
+                time,
+            },
+        );
+        id
+    }
+
+    pub fn remove_event(&mut self, id: u32) {
+        self.events.retain(|event| event.id != id);
+    }
+
+    pub fn get_event(&self, id: u32) -> Option<&AnimationEvent> {
+        self.events.iter().find(|event| event.id == id)
+    }
+
+    /// Events in (previous_time, current_time], in playback order
+    /// If the playback wrapped around, the end of the clip is visited before its start
+    pub fn get_crossed_events(
+        &self,
+        previous_time: f32,
+        current_time: f32,
+        has_wrapped: bool,
+    ) -> Vec<&AnimationEvent> {
+        if !has_wrapped {
+            return self
+                .events
+                .iter()
+                .filter(|event| event.time > previous_time && event.time <= current_time)
+                .collect();
+        }
+        self.events
+            .iter()
+            .filter(|event| event.time > previous_time)
+            .chain(
+                self.events
+                    .iter()
+                    .filter(|event| event.time <= current_time),
+            )
+            .collect()
+    }
+}
+
+/// Playback state of a clip, fires the notify events crossed while advancing
+#[derive(Clone, Debug)]
+pub struct AnimationPlayback {
+    pub clip_id: u32,
+    pub duration: f32,
+    pub time: f32,
+    pub speed: f32,
+    pub is_looping: bool,
+    pub is_playing: bool,
+    pub events: AnimationEventTrack,
+}
+
+impl AnimationPlayback {
+    pub fn new(clip_id: u32, duration: f32) -> Self {
+        Self {
+            clip_id,
+            duration,
+            time: 0.0,
+            speed: 1.0,
+            is_looping: true,
+            is_playing: true,
+            events: AnimationEventTrack::default(),
+        }
+    }
+
+    pub fn speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    pub fn is_looping(mut self, is_looping: bool) -> Self {
+        self.is_looping = is_looping;
+        self
+    }
+
+    pub fn events(mut self, events: AnimationEventTrack) -> Self {
+        self.events = events;
+        self
+    }
+
+    /// Move the playback forward and fire the crossed events
+    pub fn advance(&mut self, delta_time: f64) -> Result<(), EngineError> {
+        if !self.is_playing || self.duration <= 0.0 {
+            return Ok(());
+        }
+        let previous_time = self.time;
+        let mut current_time = previous_time + delta_time as f32 * self.speed.max(0.0);
+        let mut has_wrapped = false;
+        if current_time >= self.duration {
+            if self.is_looping {
+                current_time %= self.duration;
+                has_wrapped = true;
+            } else {
+                current_time = self.duration;
+                self.is_playing = false;
+            }
+        }
+        self.time = current_time;
+
+        for event in self
+            .events
+            .get_crossed_events(previous_time, current_time, has_wrapped)
+        {
+            if let Err(err) = event_queue(EventCode::AnimationNotify {
+                clip_id: self.clip_id,
+                event_id: event.id,
+            }) {
+                error!(
+                    "Failed to queue the animation event `{}': {:?}",
+                    event.name, err
+                );
+                return Err(EngineError::UpdateFailed);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn restart(&mut self) {
+        self.time = 0.0;
+        self.is_playing = true;
+    }
+}
@@ -0,0 +1,216 @@
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+};
+
+use crate::{core::debug::errors::EngineError, error};
+
+use super::texture_system::TextureHandle;
+
+/// Glyph of a bitmap font, in pixels of the atlas
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct FontGlyph {
+    /// Top left corner in the atlas
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+    /// Offset of the top left corner from the pen position, y goes down
+    pub x_offset: f32,
+    pub y_offset: f32,
+    /// Distance the pen moves after the glyph
+    pub x_advance: f32,
+}
+
+/// Font whose glyphs are prerendered in a single atlas texture, read from an AngelCode .fnt file
+#[derive(Clone, Debug, Default)]
+pub struct BitmapFont {
+    pub name: String,
+    /// Distance in pixels between two lines
+    pub line_height: f32,
+    /// Distance in pixels from the top of a line to the baseline
+    pub base: f32,
+    pub atlas_width: u32,
+    pub atlas_height: u32,
+    /// Path of the atlas image, relative to the .fnt file
+    pub atlas_path: PathBuf,
+    pub glyphs: HashMap<char, FontGlyph>,
+    /// Advance adjustment of a pair of characters
+    pub kernings: HashMap<(char, char), f32>,
+    /// Atlas texture, none until the font is loaded by the renderer
+    pub texture: Option<TextureHandle>,
+}
+
+/// Key value pairs of a line of a .fnt file, the values can be quoted
+fn font_parse_fnt_attributes(line: &str) -> HashMap<&str, &str> {
+    let mut attributes = HashMap::new();
+    let mut rest = line;
+    while let Some(equal) = rest.find('=') {
+        let key = rest[..equal].split_whitespace().last().unwrap_or_default();
+        let value_start = &rest[equal + 1..];
+        let (value, next) = match value_start.strip_prefix('"') {
+            Some(quoted) => {
+                let end = quoted.find('"').unwrap_or(quoted.len());
+                (&quoted[..end], &quoted[(end + 1).min(quoted.len())..])
+            }
+            None => {
+                let end = value_start
+                    .find(char::is_whitespace)
+                    .unwrap_or(value_start.len());
+                (&value_start[..end], &value_start[end..])
+            }
+        };
+        attributes.insert(key, value);
+        rest = next;
+    }
+    attributes
+}
+
+fn font_get_fnt_attribute<T: std::str::FromStr>(
+    attributes: &HashMap<&str, &str>,
+    key: &str,
+    line_number: usize,
+) -> Result<T, EngineError> {
+    match attributes.get(key).map(|value| value.parse::<T>()) {
+        Some(Ok(value)) => Ok(value),
+        _ => {
+            error!(
+                "Missing or invalid `{}' attribute at the line {} of a .fnt file",
+                key, line_number
+            );
+            Err(EngineError::InvalidValue)
+        }
+    }
+}
+
+fn font_get_fnt_char(
+    attributes: &HashMap<&str, &str>,
+    key: &str,
+    line_number: usize,
+) -> Result<char, EngineError> {
+    let code = font_get_fnt_attribute::<u32>(attributes, key, line_number)?;
+    match char::from_u32(code) {
+        Some(character) => Ok(character),
+        None => {
+            error!(
+                "Invalid character {} at the line {} of a .fnt file",
+                code, line_number
+            );
+            Err(EngineError::InvalidValue)
+        }
+    }
+}
+
+/// Parse the text format of a .fnt file, only single page fonts are supported
+pub fn font_parse_fnt(content: &str) -> Result<BitmapFont, EngineError> {
+    let mut font = BitmapFont::default();
+    let mut has_common = false;
+    for (index, line) in content.lines().enumerate() {
+        let line_number = index + 1;
+        let tag = line.split_whitespace().next().unwrap_or_default();
+        let attributes = font_parse_fnt_attributes(line);
+        match tag {
+            "info" => {
+                font.name = attributes
+                    .get("face")
+                    .copied()
+                    .unwrap_or_default()
+                    .to_string();
+            }
+            "common" => {
+                font.line_height = font_get_fnt_attribute(&attributes, "lineHeight", line_number)?;
+                font.base = font_get_fnt_attribute(&attributes, "base", line_number)?;
+                font.atlas_width = font_get_fnt_attribute(&attributes, "scaleW", line_number)?;
+                font.atlas_height = font_get_fnt_attribute(&attributes, "scaleH", line_number)?;
+                let pages: u32 = font_get_fnt_attribute(&attributes, "pages", line_number)?;
+                if pages != 1 {
+                    error!("Fonts with {} atlas pages are not supported", pages);
+                    return Err(EngineError::NotImplemented);
+                }
+                has_common = true;
+            }
+            "page" => {
+                let file: String = font_get_fnt_attribute(&attributes, "file", line_number)?;
+                font.atlas_path = PathBuf::from(file);
+            }
+            "char" => {
+                let character = font_get_fnt_char(&attributes, "id", line_number)?;
+                let glyph = FontGlyph {
+                    x: font_get_fnt_attribute(&attributes, "x", line_number)?,
+                    y: font_get_fnt_attribute(&attributes, "y", line_number)?,
+                    width: font_get_fnt_attribute(&attributes, "width", line_number)?,
+                    height: font_get_fnt_attribute(&attributes, "height", line_number)?,
+                    x_offset: font_get_fnt_attribute(&attributes, "xoffset", line_number)?,
+                    y_offset: font_get_fnt_attribute(&attributes, "yoffset", line_number)?,
+                    x_advance: font_get_fnt_attribute(&attributes, "xadvance", line_number)?,
+                };
+                font.glyphs.insert(character, glyph);
+            }
+            "kerning" => {
+                let first = font_get_fnt_char(&attributes, "first", line_number)?;
+                let second = font_get_fnt_char(&attributes, "second", line_number)?;
+                let amount = font_get_fnt_attribute(&attributes, "amount", line_number)?;
+                font.kernings.insert((first, second), amount);
+            }
+            // Counts and unknown tags
+            _ => (),
+        }
+    }
+
+    if !has_common || font.atlas_width == 0 || font.atlas_height == 0 {
+        error!("A .fnt file needs a `common' line with the size of the atlas");
+        return Err(EngineError::InvalidValue);
+    }
+    if font.atlas_path.as_os_str().is_empty() {
+        error!("A .fnt file needs a `page' line with the atlas file");
+        return Err(EngineError::InvalidValue);
+    }
+    for (character, glyph) in &font.glyphs {
+        if glyph.x + glyph.width > font.atlas_width || glyph.y + glyph.height > font.atlas_height {
+            error!(
+                "The glyph of {:?} is outside of the {}x{} font atlas",
+                character, font.atlas_width, font.atlas_height
+            );
+            return Err(EngineError::InvalidValue);
+        }
+    }
+    Ok(font)
+}
+
+/// Read a .fnt file, the atlas path is made relative to the working directory
+pub fn font_load_fnt(path: &Path) -> Result<BitmapFont, EngineError> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(err) => {
+            error!("Failed to read the font file {:?}: {:?}", path, err);
+            return Err(EngineError::IO);
+        }
+    };
+    let mut font = match font_parse_fnt(&content) {
+        Ok(font) => font,
+        Err(err) => {
+            error!("Failed to parse the font file {:?}: {:?}", path, err);
+            return Err(err);
+        }
+    };
+    if let Some(directory) = path.parent() {
+        font.atlas_path = directory.join(&font.atlas_path);
+    }
+    Ok(font)
+}
+
+impl BitmapFont {
+    /// Glyph drawn for the character, the question mark replaces the missing ones
+    pub fn get_glyph(&self, character: char) -> Option<&FontGlyph> {
+        self.glyphs
+            .get(&character)
+            .or_else(|| self.glyphs.get(&'?'))
+    }
+
+    pub fn get_kerning(&self, first: char, second: char) -> f32 {
+        self.kernings
+            .get(&(first, second))
+            .copied()
+            .unwrap_or_default()
+    }
+}
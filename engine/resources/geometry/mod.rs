@@ -0,0 +1,24 @@
+pub(crate) mod primitives;
+pub(crate) mod processing;
+
+use std::any::Any;
+
+use crate::renderer::renderer_types::VertexData;
+
+pub trait Geometry: Send {
+    fn get_id(&self) -> u32;
+
+    fn get_vertex_count(&self) -> u32;
+    fn get_index_count(&self) -> u32;
+
+    fn get_generation(&self) -> Option<u32>;
+    fn as_any(&self) -> &dyn Any;
+
+    fn clone_box(&self) -> Box<dyn Geometry>;
+}
+
+pub struct GeometryCreatorParameters<'a> {
+    pub name: &'a str,
+    pub vertices: &'a [VertexData],
+    pub indices: &'a [u32],
+}
@@ -0,0 +1,410 @@
+use std::{collections::HashMap, f32::consts::PI};
+
+use crate::renderer::{renderer_types::VertexData, scene::culling::BoundingSphere};
+
+use super::GeometryCreatorParameters;
+
+/// Generated mesh, triangles are counter clockwise seen from outside
+#[derive(Clone, Debug, Default)]
+pub struct PrimitiveMesh {
+    pub vertices: Vec<VertexData>,
+    pub indices: Vec<u32>,
+}
+
+impl PrimitiveMesh {
+    /// Parameters to create the renderer geometry of the mesh
+    pub fn get_geometry_parameters<'a>(&'a self, name: &'a str) -> GeometryCreatorParameters<'a> {
+        GeometryCreatorParameters {
+            name,
+            vertices: &self.vertices,
+            indices: &self.indices,
+        }
+    }
+
+    /// Sphere centered on the origin containing every vertex
+    pub fn get_bounding_sphere(&self) -> BoundingSphere {
+        let radius = self
+            .vertices
+            .iter()
+            .map(|vertex| vertex.position.length())
+            .fold(0.0, f32::max);
+        BoundingSphere::new(glam::Vec3::ZERO, radius)
+    }
+
+    /// Add a grid of vertices, the surface gives the position, normal and tangent of every vertex
+    /// The tangent goes right and the rows go up seen from outside, so the textures are upright
+    fn add_grid(
+        &mut self,
+        columns: u32,
+        rows: u32,
+        lightmap_region: (glam::Vec2, glam::Vec2),
+        surface: impl Fn(u32, u32) -> (glam::Vec3, glam::Vec3, glam::Vec3),
+    ) {
+        let first = self.vertices.len() as u32;
+        let (lightmap_offset, lightmap_scale) = lightmap_region;
+        for row in 0..=rows {
+            for column in 0..=columns {
+                let (position, normal, tangent) = surface(column, row);
+                // Images start at the top row
+                let texture = glam::Vec2::new(
+                    column as f32 / columns as f32,
+                    1.0 - row as f32 / rows as f32,
+                );
+                self.vertices.push(VertexData {
+                    position,
+                    texture,
+                    lightmap_texture: lightmap_offset + texture * lightmap_scale,
+                    normal,
+                    // The texture v goes down while the rows go up
+                    tangent: tangent.extend(-1.0),
+                });
+            }
+        }
+        for row in 0..rows {
+            for column in 0..columns {
+                let bottom_left = first + row * (columns + 1) + column;
+                let bottom_right = bottom_left + 1;
+                let top_left = bottom_left + columns + 1;
+                let top_right = top_left + 1;
+                self.indices.extend_from_slice(&[
+                    bottom_left,
+                    bottom_right,
+                    top_right,
+                    bottom_left,
+                    top_right,
+                    top_left,
+                ]);
+            }
+        }
+    }
+
+    /// Add a disc facing up or down, made of a fan around its center
+    fn add_disc(&mut self, center: glam::Vec3, radius: f32, segments: u32, is_facing_up: bool) {
+        let first = self.vertices.len() as u32;
+        let (normal, side) = if is_facing_up {
+            (glam::Vec3::Y, 1.0)
+        } else {
+            (glam::Vec3::NEG_Y, -1.0)
+        };
+        for index in 0..=segments {
+            // The last vertex is the center
+            let (sine, cosine) = if index < segments {
+                (2.0 * PI * index as f32 / segments as f32).sin_cos()
+            } else {
+                (0.0, 0.0)
+            };
+            let texture = glam::Vec2::new(0.5 + 0.5 * sine, 0.5 + 0.5 * side * cosine);
+            self.vertices.push(VertexData {
+                position: center + radius * glam::Vec3::new(sine, 0.0, cosine),
+                texture,
+                lightmap_texture: texture,
+                normal,
+                tangent: glam::Vec4::new(1.0, 0.0, 0.0, -1.0),
+            });
+        }
+        let center_index = first + segments;
+        for index in 0..segments {
+            let current = first + index;
+            let next = first + (index + 1) % segments;
+            if is_facing_up {
+                self.indices
+                    .extend_from_slice(&[center_index, current, next]);
+            } else {
+                self.indices
+                    .extend_from_slice(&[center_index, next, current]);
+            }
+        }
+    }
+}
+
+/// Point of the unit sphere, the longitude starts at +z and turns toward +x
+fn primitive_sphere_point(longitude: f32, latitude: f32) -> (glam::Vec3, glam::Vec3) {
+    let (longitude_sine, longitude_cosine) = longitude.sin_cos();
+    let (latitude_sine, latitude_cosine) = latitude.sin_cos();
+    let normal = glam::Vec3::new(
+        latitude_cosine * longitude_sine,
+        latitude_sine,
+        latitude_cosine * longitude_cosine,
+    );
+    let tangent = glam::Vec3::new(longitude_cosine, 0.0, -longitude_sine);
+    (normal, tangent)
+}
+
+/// Whole lightmap
+const PRIMITIVE_FULL_LIGHTMAP: (glam::Vec2, glam::Vec2) = (glam::Vec2::ZERO, glam::Vec2::ONE);
+
+/// Plane on xz facing +y, centered on the origin
+pub fn primitive_plane(width: f32, depth: f32, columns: u32, rows: u32) -> PrimitiveMesh {
+    let (columns, rows) = (columns.max(1), rows.max(1));
+    let mut mesh = PrimitiveMesh::default();
+    mesh.add_grid(columns, rows, PRIMITIVE_FULL_LIGHTMAP, |column, row| {
+        let position = glam::Vec3::new(
+            (column as f32 / columns as f32 - 0.5) * width,
+            0.0,
+            (0.5 - row as f32 / rows as f32) * depth,
+        );
+        (position, glam::Vec3::Y, glam::Vec3::X)
+    });
+    mesh
+}
+
+/// Terrain on xz centered on the origin, the heights are given row by row starting at -z
+pub fn primitive_heightfield(
+    width: f32,
+    depth: f32,
+    heights: &[f32],
+    sample_columns: u32,
+    sample_rows: u32,
+) -> PrimitiveMesh {
+    let mut mesh = PrimitiveMesh::default();
+    if sample_columns < 2 || sample_rows < 2 {
+        return mesh;
+    }
+    let (columns, rows) = (sample_columns - 1, sample_rows - 1);
+    let (cell_width, cell_depth) = (width / columns as f32, depth / rows as f32);
+    // Missing samples are flat
+    let get_height = |column: i64, row: i64| {
+        let column = column.clamp(0, columns as i64) as u32;
+        let row = row.clamp(0, rows as i64) as u32;
+        heights
+            .get((row * sample_columns + column) as usize)
+            .copied()
+            .unwrap_or(0.0)
+    };
+    mesh.add_grid(columns, rows, PRIMITIVE_FULL_LIGHTMAP, |column, row| {
+        // The grid rows go toward -z
+        let (column, sample_row) = (column as i64, (rows - row) as i64);
+        let position = glam::Vec3::new(
+            (column as f32 / columns as f32 - 0.5) * width,
+            get_height(column, sample_row),
+            (sample_row as f32 / rows as f32 - 0.5) * depth,
+        );
+        // Central differences
+        let slope_x = (get_height(column + 1, sample_row) - get_height(column - 1, sample_row))
+            / (2.0 * cell_width);
+        let slope_z = (get_height(column, sample_row + 1) - get_height(column, sample_row - 1))
+            / (2.0 * cell_depth);
+        let normal = glam::Vec3::new(-slope_x, 1.0, -slope_z).normalize();
+        let tangent = glam::Vec3::new(1.0, slope_x, 0.0);
+        let tangent = (tangent - normal * normal.dot(tangent)).normalize();
+        (position, normal, tangent)
+    });
+    mesh
+}
+
+/// Box centered on the origin, every face is subdivided in a grid
+/// The faces get their own cell of a 3x2 lightmap
+pub fn primitive_cube(size: glam::Vec3, subdivisions: u32) -> PrimitiveMesh {
+    let subdivisions = subdivisions.max(1);
+    // Normal, right and up of every face seen from outside
+    let faces = [
+        (glam::Vec3::X, glam::Vec3::NEG_Z, glam::Vec3::Y),
+        (glam::Vec3::NEG_X, glam::Vec3::Z, glam::Vec3::Y),
+        (glam::Vec3::Y, glam::Vec3::X, glam::Vec3::NEG_Z),
+        (glam::Vec3::NEG_Y, glam::Vec3::X, glam::Vec3::Z),
+        (glam::Vec3::Z, glam::Vec3::X, glam::Vec3::Y),
+        (glam::Vec3::NEG_Z, glam::Vec3::NEG_X, glam::Vec3::Y),
+    ];
+    let half_size = size * 0.5;
+    let lightmap_scale = glam::Vec2::new(1.0 / 3.0, 0.5);
+    let mut mesh = PrimitiveMesh::default();
+    for (index, (normal, right, up)) in faces.into_iter().enumerate() {
+        let cell = glam::Vec2::new((index % 3) as f32, (index / 3) as f32);
+        mesh.add_grid(
+            subdivisions,
+            subdivisions,
+            (cell * lightmap_scale, lightmap_scale),
+            |column, row| {
+                let x = 2.0 * column as f32 / subdivisions as f32 - 1.0;
+                let y = 2.0 * row as f32 / subdivisions as f32 - 1.0;
+                let position = (normal + right * x + up * y) * half_size;
+                (position, normal, right)
+            },
+        );
+    }
+    mesh
+}
+
+/// Sphere made of rings of latitude, centered on the origin
+pub fn primitive_uv_sphere(radius: f32, segments: u32, rings: u32) -> PrimitiveMesh {
+    let (segments, rings) = (segments.max(3), rings.max(2));
+    let mut mesh = PrimitiveMesh::default();
+    mesh.add_grid(segments, rings, PRIMITIVE_FULL_LIGHTMAP, |column, row| {
+        let longitude = 2.0 * PI * column as f32 / segments as f32;
+        let latitude = PI * row as f32 / rings as f32 - 0.5 * PI;
+        let (normal, tangent) = primitive_sphere_point(longitude, latitude);
+        (normal * radius, normal, tangent)
+    });
+    mesh
+}
+
+/// Sphere made of a subdivided icosahedron, with evenly sized triangles
+pub fn primitive_icosphere(radius: f32, subdivisions: u32) -> PrimitiveMesh {
+    let t = (1.0 + 5.0f32.sqrt()) * 0.5;
+    let mut points: Vec<glam::Vec3> = [
+        (-1.0, t, 0.0),
+        (1.0, t, 0.0),
+        (-1.0, -t, 0.0),
+        (1.0, -t, 0.0),
+        (0.0, -1.0, t),
+        (0.0, 1.0, t),
+        (0.0, -1.0, -t),
+        (0.0, 1.0, -t),
+        (t, 0.0, -1.0),
+        (t, 0.0, 1.0),
+        (-t, 0.0, -1.0),
+        (-t, 0.0, 1.0),
+    ]
+    .into_iter()
+    .map(|(x, y, z)| glam::Vec3::new(x, y, z).normalize())
+    .collect();
+    let mut triangles: Vec<[u32; 3]> = vec![
+        [0, 11, 5],
+        [0, 5, 1],
+        [0, 1, 7],
+        [0, 7, 10],
+        [0, 10, 11],
+        [1, 5, 9],
+        [5, 11, 4],
+        [11, 10, 2],
+        [10, 7, 6],
+        [7, 1, 8],
+        [3, 9, 4],
+        [3, 4, 2],
+        [3, 2, 6],
+        [3, 6, 8],
+        [3, 8, 9],
+        [4, 9, 5],
+        [2, 4, 11],
+        [6, 2, 10],
+        [8, 6, 7],
+        [9, 8, 1],
+    ];
+
+    // Split every triangle in four, the middles are shared by the neighbouring triangles
+    for _ in 0..subdivisions {
+        let mut middles: HashMap<(u32, u32), u32> = HashMap::new();
+        let mut get_middle = |a: u32, b: u32, points: &mut Vec<glam::Vec3>| {
+            *middles.entry((a.min(b), a.max(b))).or_insert_with(|| {
+                points.push((points[a as usize] + points[b as usize]).normalize());
+                points.len() as u32 - 1
+            })
+        };
+        let mut subdivided = Vec::with_capacity(triangles.len() * 4);
+        for [a, b, c] in triangles {
+            let ab = get_middle(a, b, &mut points);
+            let bc = get_middle(b, c, &mut points);
+            let ca = get_middle(c, a, &mut points);
+            subdivided.extend_from_slice(&[[a, ab, ca], [b, bc, ab], [c, ca, bc], [ab, bc, ca]]);
+        }
+        triangles = subdivided;
+    }
+
+    let mut mesh = PrimitiveMesh::default();
+    for normal in &points {
+        let longitude = normal.x.atan2(normal.z);
+        let texture = glam::Vec2::new(
+            0.5 + longitude / (2.0 * PI),
+            0.5 - normal.y.clamp(-1.0, 1.0).asin() / PI,
+        );
+        let (_, tangent) = primitive_sphere_point(longitude, 0.0);
+        mesh.vertices.push(VertexData {
+            position: *normal * radius,
+            texture,
+            lightmap_texture: texture,
+            normal: *normal,
+            tangent: tangent.extend(-1.0),
+        });
+    }
+    // The triangles crossing the seam would wrap around the whole texture,
+    // their vertices on the left side are duplicated on the right side
+    let mut seam_duplicates: HashMap<u32, u32> = HashMap::new();
+    for triangle in triangles.iter_mut() {
+        let us = triangle.map(|index| mesh.vertices[index as usize].texture.x);
+        let max_u = us.iter().copied().fold(f32::MIN, f32::max);
+        let min_u = us.iter().copied().fold(f32::MAX, f32::min);
+        if max_u - min_u <= 0.5 {
+            continue;
+        }
+        for index in triangle.iter_mut() {
+            if mesh.vertices[*index as usize].texture.x >= 0.5 {
+                continue;
+            }
+            *index = *seam_duplicates.entry(*index).or_insert_with(|| {
+                let mut duplicate = mesh.vertices[*index as usize];
+                duplicate.texture.x += 1.0;
+                duplicate.lightmap_texture = duplicate.texture;
+                mesh.vertices.push(duplicate);
+                mesh.vertices.len() as u32 - 1
+            });
+        }
+    }
+    mesh.indices = triangles.into_iter().flatten().collect();
+    mesh
+}
+
+/// Cylinder along y with closed ends, centered on the origin
+pub fn primitive_cylinder(radius: f32, height: f32, segments: u32, rings: u32) -> PrimitiveMesh {
+    let (segments, rings) = (segments.max(3), rings.max(1));
+    let mut mesh = PrimitiveMesh::default();
+    mesh.add_grid(segments, rings, PRIMITIVE_FULL_LIGHTMAP, |column, row| {
+        let longitude = 2.0 * PI * column as f32 / segments as f32;
+        let (normal, tangent) = primitive_sphere_point(longitude, 0.0);
+        let y = (row as f32 / rings as f32 - 0.5) * height;
+        (normal * radius + glam::Vec3::Y * y, normal, tangent)
+    });
+    mesh.add_disc(glam::Vec3::Y * height * 0.5, radius, segments, true);
+    mesh.add_disc(glam::Vec3::NEG_Y * height * 0.5, radius, segments, false);
+    mesh
+}
+
+/// Cylinder along y ended by two half spheres, the height does not include the half spheres
+pub fn primitive_capsule(radius: f32, height: f32, segments: u32, rings: u32) -> PrimitiveMesh {
+    let (segments, rings) = (segments.max(3), rings.max(1));
+    // Each half sphere has rings rows, plus the row between them for the cylinder
+    let rows = 2 * rings + 1;
+    let mut mesh = PrimitiveMesh::default();
+    mesh.add_grid(segments, rows, PRIMITIVE_FULL_LIGHTMAP, |column, row| {
+        let longitude = 2.0 * PI * column as f32 / segments as f32;
+        let (latitude, y) = if row <= rings {
+            (0.5 * PI * (row as f32 / rings as f32 - 1.0), -0.5 * height)
+        } else {
+            (
+                0.5 * PI * (row - rings - 1) as f32 / rings as f32,
+                0.5 * height,
+            )
+        };
+        let (normal, tangent) = primitive_sphere_point(longitude, latitude);
+        (normal * radius + glam::Vec3::Y * y, normal, tangent)
+    });
+    mesh
+}
+
+/// Ring around y, the tube of the minor radius is centered at the major radius
+pub fn primitive_torus(
+    major_radius: f32,
+    minor_radius: f32,
+    major_segments: u32,
+    minor_segments: u32,
+) -> PrimitiveMesh {
+    let (major_segments, minor_segments) = (major_segments.max(3), minor_segments.max(3));
+    let mut mesh = PrimitiveMesh::default();
+    mesh.add_grid(
+        major_segments,
+        minor_segments,
+        PRIMITIVE_FULL_LIGHTMAP,
+        |column, row| {
+            let longitude = 2.0 * PI * column as f32 / major_segments as f32;
+            // Starts outside of the ring and goes up
+            let tube_angle = 2.0 * PI * row as f32 / minor_segments as f32;
+            let (normal, tangent) = primitive_sphere_point(longitude, tube_angle);
+            let (center, _) = primitive_sphere_point(longitude, 0.0);
+            (
+                center * major_radius + normal * minor_radius,
+                normal,
+                tangent,
+            )
+        },
+    );
+    mesh
+}
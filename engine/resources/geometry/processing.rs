@@ -0,0 +1,359 @@
+use std::{
+    cmp::Ordering,
+    collections::{BinaryHeap, HashMap},
+};
+
+use crate::renderer::renderer_types::VertexData;
+
+/// Merge the vertices whose attributes are equal up to the tolerance, the indices are remapped
+pub fn mesh_weld_vertices(vertices: &mut Vec<VertexData>, indices: &mut [u32], tolerance: f32) {
+    let tolerance = tolerance.max(f32::EPSILON);
+    let quantize = |values: &[f32]| -> Vec<i64> {
+        values
+            .iter()
+            .map(|value| (value / tolerance).round() as i64)
+            .collect()
+    };
+
+    let mut welded: Vec<VertexData> = Vec::with_capacity(vertices.len());
+    let mut remap = Vec::with_capacity(vertices.len());
+    let mut existing: HashMap<Vec<i64>, u32> = HashMap::with_capacity(vertices.len());
+    for vertex in vertices.iter() {
+        let mut key = quantize(&vertex.position.to_array());
+        key.extend(quantize(&vertex.texture.to_array()));
+        key.extend(quantize(&vertex.lightmap_texture.to_array()));
+        key.extend(quantize(&vertex.normal.to_array()));
+        let index = *existing.entry(key).or_insert_with(|| {
+            welded.push(*vertex);
+            welded.len() as u32 - 1
+        });
+        remap.push(index);
+    }
+    for index in indices.iter_mut() {
+        *index = remap[*index as usize];
+    }
+    *vertices = welded;
+}
+
+/// Smooth normals, the normals of the triangles around a vertex are weighted by their area
+pub fn mesh_compute_normals(vertices: &mut [VertexData], indices: &[u32]) {
+    let mut normals = vec![glam::Vec3::ZERO; vertices.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|corner| triangle[corner] as usize);
+        // Not normalized so bigger triangles weight more
+        let normal = (vertices[b].position - vertices[a].position)
+            .cross(vertices[c].position - vertices[a].position);
+        for index in [a, b, c] {
+            normals[index] += normal;
+        }
+    }
+    for (vertex, normal) in vertices.iter_mut().zip(normals) {
+        let normal = normal.normalize_or_zero();
+        // Vertices without triangles keep their normal
+        if normal != glam::Vec3::ZERO {
+            vertex.normal = normal;
+        }
+    }
+}
+
+/// Tangents following the texture u, the normals must be computed first
+pub fn mesh_compute_tangents(vertices: &mut [VertexData], indices: &[u32]) {
+    let mut tangents = vec![glam::Vec3::ZERO; vertices.len()];
+    let mut bitangents = vec![glam::Vec3::ZERO; vertices.len()];
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|corner| triangle[corner] as usize);
+        let edge_1 = vertices[b].position - vertices[a].position;
+        let edge_2 = vertices[c].position - vertices[a].position;
+        let delta_1 = vertices[b].texture - vertices[a].texture;
+        let delta_2 = vertices[c].texture - vertices[a].texture;
+        let determinant = delta_1.x * delta_2.y - delta_2.x * delta_1.y;
+        // Skip the triangles without texture space
+        if determinant.abs() <= f32::EPSILON {
+            continue;
+        }
+        let tangent = (edge_1 * delta_2.y - edge_2 * delta_1.y) / determinant;
+        let bitangent = (edge_2 * delta_1.x - edge_1 * delta_2.x) / determinant;
+        for index in [a, b, c] {
+            tangents[index] += tangent;
+            bitangents[index] += bitangent;
+        }
+    }
+    for ((vertex, tangent), bitangent) in vertices.iter_mut().zip(tangents).zip(bitangents) {
+        // Gram-Schmidt orthogonalization against the normal
+        let tangent = (tangent - vertex.normal * vertex.normal.dot(tangent)).normalize_or_zero();
+        if tangent == glam::Vec3::ZERO {
+            continue;
+        }
+        let handedness = if vertex.normal.cross(tangent).dot(bitangent) < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+        vertex.tangent = tangent.extend(handedness);
+    }
+}
+
+/// Symmetric 4x4 matrix summing the squared distances to planes
+#[derive(Clone, Copy, Default)]
+struct Quadric([f64; 10]);
+
+impl Quadric {
+    fn from_plane(normal: glam::DVec3, distance: f64, weight: f64) -> Self {
+        let (a, b, c, d) = (normal.x, normal.y, normal.z, distance);
+        Self(
+            [
+                a * a,
+                a * b,
+                a * c,
+                a * d,
+                b * b,
+                b * c,
+                b * d,
+                c * c,
+                c * d,
+                d * d,
+            ]
+            .map(|value| value * weight),
+        )
+    }
+
+    fn add(&mut self, other: &Self) {
+        for (value, other) in self.0.iter_mut().zip(other.0) {
+            *value += other;
+        }
+    }
+
+    fn get_error(&self, point: glam::DVec3) -> f64 {
+        let [aa, ab, ac, ad, bb, bc, bd, cc, cd, dd] = self.0;
+        let (x, y, z) = (point.x, point.y, point.z);
+        (aa * x * x
+            + 2.0 * ab * x * y
+            + 2.0 * ac * x * z
+            + 2.0 * ad * x
+            + bb * y * y
+            + 2.0 * bc * y * z
+            + 2.0 * bd * y
+            + cc * z * z
+            + 2.0 * cd * z
+            + dd)
+            .max(0.0)
+    }
+}
+
+/// Candidate collapse of a vertex onto a neighbour
+struct MeshCollapse {
+    error: f64,
+    from: u32,
+    to: u32,
+    /// Versions of the vertices when the collapse was computed, outdated collapses are skipped
+    versions: (u32, u32),
+}
+
+impl PartialEq for MeshCollapse {
+    fn eq(&self, other: &Self) -> bool {
+        self.error == other.error
+    }
+}
+
+impl Eq for MeshCollapse {}
+
+impl PartialOrd for MeshCollapse {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for MeshCollapse {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Reversed so the heap pops the smallest error first
+        other.error.total_cmp(&self.error)
+    }
+}
+
+/// Weight of the planes keeping the open borders and the attribute seams in place
+const MESH_SIMPLIFY_BORDER_WEIGHT: f64 = 10.0;
+
+/// Collapse edges until there are at most target_index_count indices or the next collapse
+/// would move the surface by more than max_error, returns the indices of the simplified mesh
+/// The vertices are not modified so every level of detail can share them
+pub fn mesh_simplify(
+    vertices: &[VertexData],
+    indices: &[u32],
+    target_index_count: usize,
+    max_error: f32,
+) -> Vec<u32> {
+    let mut triangles: Vec<[u32; 3]> = indices
+        .chunks_exact(3)
+        .map(|triangle| [triangle[0], triangle[1], triangle[2]])
+        .collect();
+    let position = |index: u32| vertices[index as usize].position.as_dvec3();
+
+    // Quadrics of the triangle planes and of the borders
+    let mut quadrics = vec![Quadric::default(); vertices.len()];
+    let mut edge_counts: HashMap<(u32, u32), u32> = HashMap::new();
+    for triangle in &triangles {
+        let normal = (position(triangle[1]) - position(triangle[0]))
+            .cross(position(triangle[2]) - position(triangle[0]));
+        let area = normal.length() * 0.5;
+        let normal = normal.normalize_or_zero();
+        let plane = Quadric::from_plane(normal, -normal.dot(position(triangle[0])), area);
+        for corner in 0..3 {
+            quadrics[triangle[corner] as usize].add(&plane);
+            let (a, b) = (triangle[corner], triangle[(corner + 1) % 3]);
+            *edge_counts.entry((a.min(b), a.max(b))).or_default() += 1;
+        }
+    }
+    for triangle in &triangles {
+        let face_normal = (position(triangle[1]) - position(triangle[0]))
+            .cross(position(triangle[2]) - position(triangle[0]))
+            .normalize_or_zero();
+        for corner in 0..3 {
+            let (a, b) = (triangle[corner], triangle[(corner + 1) % 3]);
+            if edge_counts[&(a.min(b), a.max(b))] != 1 {
+                continue;
+            }
+            // Plane containing the border, perpendicular to the triangle
+            let edge = position(b) - position(a);
+            let normal = edge.cross(face_normal).normalize_or_zero();
+            let plane = Quadric::from_plane(
+                normal,
+                -normal.dot(position(a)),
+                MESH_SIMPLIFY_BORDER_WEIGHT * edge.length_squared(),
+            );
+            quadrics[a as usize].add(&plane);
+            quadrics[b as usize].add(&plane);
+        }
+    }
+
+    // Triangles around every vertex
+    let mut vertex_triangles: Vec<Vec<usize>> = vec![Vec::new(); vertices.len()];
+    for (triangle_index, triangle) in triangles.iter().enumerate() {
+        for vertex in triangle {
+            vertex_triangles[*vertex as usize].push(triangle_index);
+        }
+    }
+    let mut is_triangle_alive = vec![true; triangles.len()];
+    let mut versions = vec![0u32; vertices.len()];
+
+    let get_collapse = |from: u32, to: u32, quadrics: &[Quadric], versions: &[u32]| {
+        let mut quadric = quadrics[from as usize];
+        quadric.add(&quadrics[to as usize]);
+        MeshCollapse {
+            error: quadric.get_error(position(to)),
+            from,
+            to,
+            versions: (versions[from as usize], versions[to as usize]),
+        }
+    };
+    let mut collapses = BinaryHeap::new();
+    for (a, b) in edge_counts.keys() {
+        collapses.push(get_collapse(*a, *b, &quadrics, &versions));
+        collapses.push(get_collapse(*b, *a, &quadrics, &versions));
+    }
+
+    let max_error = (max_error as f64).powi(2);
+    let mut index_count = triangles.len() * 3;
+    while index_count > target_index_count {
+        let collapse = match collapses.pop() {
+            Some(collapse) => collapse,
+            None => break,
+        };
+        if collapse.error > max_error {
+            break;
+        }
+        let (from, to) = (collapse.from, collapse.to);
+        if collapse.versions != (versions[from as usize], versions[to as usize]) {
+            continue;
+        }
+
+        // Refuse the collapses flipping a triangle
+        let is_flipping = vertex_triangles[from as usize]
+            .iter()
+            .any(|triangle_index| {
+                let triangle = triangles[*triangle_index];
+                if !is_triangle_alive[*triangle_index] || triangle.contains(&to) {
+                    return false;
+                }
+                let moved = triangle.map(|vertex| if vertex == from { to } else { vertex });
+                let normal_before = (position(triangle[1]) - position(triangle[0]))
+                    .cross(position(triangle[2]) - position(triangle[0]));
+                let normal_after = (position(moved[1]) - position(moved[0]))
+                    .cross(position(moved[2]) - position(moved[0]));
+                normal_before.dot(normal_after) <= 0.0
+            });
+        if is_flipping {
+            continue;
+        }
+
+        for triangle_index in std::mem::take(&mut vertex_triangles[from as usize]) {
+            if !is_triangle_alive[triangle_index] {
+                continue;
+            }
+            let triangle = &mut triangles[triangle_index];
+            if triangle.contains(&to) {
+                // The collapsed edge belonged to this triangle
+                is_triangle_alive[triangle_index] = false;
+                index_count -= 3;
+                continue;
+            }
+            for vertex in triangle.iter_mut() {
+                if *vertex == from {
+                    *vertex = to;
+                }
+            }
+            vertex_triangles[to as usize].push(triangle_index);
+        }
+        let from_quadric = quadrics[from as usize];
+        quadrics[to as usize].add(&from_quadric);
+        versions[from as usize] += 1;
+        versions[to as usize] += 1;
+
+        // Update the collapses around the kept vertex
+        let mut neighbours: Vec<u32> = vertex_triangles[to as usize]
+            .iter()
+            .filter(|triangle_index| is_triangle_alive[**triangle_index])
+            .flat_map(|triangle_index| triangles[*triangle_index])
+            .filter(|vertex| *vertex != to)
+            .collect();
+        neighbours.sort_unstable();
+        neighbours.dedup();
+        for neighbour in neighbours {
+            versions[neighbour as usize] += 1;
+        }
+        for triangle_index in &vertex_triangles[to as usize] {
+            if !is_triangle_alive[*triangle_index] {
+                continue;
+            }
+            let triangle = triangles[*triangle_index];
+            for corner in 0..3 {
+                let (a, b) = (triangle[corner], triangle[(corner + 1) % 3]);
+                collapses.push(get_collapse(a, b, &quadrics, &versions));
+                collapses.push(get_collapse(b, a, &quadrics, &versions));
+            }
+        }
+    }
+
+    triangles
+        .into_iter()
+        .zip(is_triangle_alive)
+        .filter(|(_, is_alive)| *is_alive)
+        .flat_map(|(triangle, _)| triangle)
+        .collect()
+}
+
+/// Indices of every level of detail, each one keeping the given ratio of the triangles
+pub fn mesh_generate_lods(
+    vertices: &[VertexData],
+    indices: &[u32],
+    ratios: &[f32],
+    max_error: f32,
+) -> Vec<Vec<u32>> {
+    ratios
+        .iter()
+        .map(|ratio| {
+            let target_index_count =
+                ((indices.len() / 3) as f32 * ratio.clamp(0.0, 1.0)) as usize * 3;
+            mesh_simplify(vertices, indices, target_index_count, max_error)
+        })
+        .collect()
+}
@@ -0,0 +1,327 @@
+use std::path::Path;
+
+use crate::{
+    core::debug::errors::EngineError,
+    error,
+    renderer::{
+        renderer_types::VertexData,
+        scene::{
+            culling::BoundingSphere,
+            graph::{SceneNodeId, SceneTransform},
+        },
+    },
+    resources::{
+        geometry::processing::{mesh_compute_normals, mesh_compute_tangents},
+        morph_targets::{MorphTarget, MorphTargetSet},
+        texture_system::TextureHandle,
+    },
+    warn,
+};
+
+/// Part of a mesh drawn with a single material
+pub struct GltfPrimitive {
+    pub vertices: Vec<VertexData>,
+    pub indices: Vec<u32>,
+    /// Index in the materials of the file
+    pub material: Option<usize>,
+    pub bounding_sphere: BoundingSphere,
+    /// None if the mesh has no morph targets, the weights start at the mesh's default ones
+    pub morph_targets: Option<MorphTargetSet>,
+}
+
+pub struct GltfMesh {
+    pub name: String,
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+pub struct GltfMaterial {
+    pub name: String,
+    pub base_color: glam::Vec4,
+    /// Index in the images of the file
+    pub base_color_image: Option<usize>,
+    /// Tangent space normals, index in the images of the file
+    pub normal_image: Option<usize>,
+    pub is_double_sided: bool,
+    /// Blended with the color behind instead of opaque or alpha tested
+    pub is_blended: bool,
+}
+
+/// Image decoded to rgba8
+pub struct GltfImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+    pub has_transparency: bool,
+}
+
+pub struct GltfNode {
+    pub name: String,
+    pub transform: SceneTransform,
+    /// Index in the meshes of the file
+    pub mesh: Option<usize>,
+    /// Indices in the nodes of the file
+    pub children: Vec<usize>,
+}
+
+/// Content of a .gltf/.glb file, ready to be instantiated in the scene
+pub struct GltfScene {
+    pub meshes: Vec<GltfMesh>,
+    pub materials: Vec<GltfMaterial>,
+    pub images: Vec<GltfImage>,
+    pub nodes: Vec<GltfNode>,
+    /// Nodes of the default scene
+    pub roots: Vec<usize>,
+}
+
+/// Renderer resources created when a gltf scene is instantiated, to be given back when unloading it
+pub struct GltfInstance {
+    /// Node holding the roots of the gltf scene
+    pub root: SceneNodeId,
+    pub geometry_ids: Vec<u32>,
+    pub material_ids: Vec<u32>,
+    pub object_ids: Vec<u32>,
+    pub textures: Vec<TextureHandle>,
+}
+
+fn gltf_convert_image(image: &gltf::image::Data) -> Result<GltfImage, EngineError> {
+    use gltf::image::Format;
+    let pixel_count = (image.width * image.height) as usize;
+    let pixels = match image.format {
+        Format::R8G8B8A8 => image.pixels.clone(),
+        Format::R8G8B8 => image
+            .pixels
+            .chunks_exact(3)
+            .flat_map(|pixel| [pixel[0], pixel[1], pixel[2], 255])
+            .collect(),
+        Format::R8G8 => image
+            .pixels
+            .chunks_exact(2)
+            .flat_map(|pixel| [pixel[0], pixel[1], 0, 255])
+            .collect(),
+        Format::R8 => image
+            .pixels
+            .iter()
+            .flat_map(|value| [*value, *value, *value, 255])
+            .collect(),
+        format => {
+            error!("The gltf image format {:?} is not supported", format);
+            return Err(EngineError::NotImplemented);
+        }
+    };
+    if pixels.len() != pixel_count * 4 {
+        error!("The gltf image doesn't have the expected number of pixels");
+        return Err(EngineError::InvalidValue);
+    }
+    let has_transparency = pixels.chunks_exact(4).any(|pixel| pixel[3] < 255);
+    Ok(GltfImage {
+        width: image.width,
+        height: image.height,
+        pixels,
+        has_transparency,
+    })
+}
+
+/// Position and normal displacements of each morph target of a primitive
+type GltfMorphTargetDeltas = (Option<Vec<[f32; 3]>>, Option<Vec<[f32; 3]>>);
+
+/// Targets named after their index, the names of the files are stored in extras not read here
+fn gltf_convert_morph_targets(
+    read_targets: Vec<GltfMorphTargetDeltas>,
+    vertices: &[VertexData],
+    default_weights: Option<&[f32]>,
+) -> Result<Option<MorphTargetSet>, EngineError> {
+    if read_targets.is_empty() {
+        return Ok(None);
+    }
+    let mut targets = MorphTargetSet::new(
+        vertices.iter().map(|vertex| vertex.position).collect(),
+        vertices.iter().map(|vertex| vertex.normal).collect(),
+    );
+    for (index, (positions, normals)) in read_targets.into_iter().enumerate() {
+        let position_deltas = match positions {
+            Some(positions) => positions.into_iter().map(glam::Vec3::from_array).collect(),
+            None => vec![glam::Vec3::ZERO; vertices.len()],
+        };
+        let normal_deltas =
+            normals.map(|normals| normals.into_iter().map(glam::Vec3::from_array).collect());
+        targets.add_target(MorphTarget {
+            name: index.to_string(),
+            position_deltas,
+            normal_deltas,
+        })?;
+    }
+    if let Some(default_weights) = default_weights {
+        for (index, weight) in default_weights.iter().enumerate() {
+            targets.set_weight(index, *weight)?;
+        }
+    }
+    Ok(Some(targets))
+}
+
+fn gltf_convert_primitive(
+    primitive: &gltf::Primitive,
+    buffers: &[gltf::buffer::Data],
+    default_morph_weights: Option<&[f32]>,
+) -> Result<Option<GltfPrimitive>, EngineError> {
+    if primitive.mode() != gltf::mesh::Mode::Triangles {
+        warn!(
+            "Skipping a gltf primitive drawn as {:?}, only triangles are supported",
+            primitive.mode()
+        );
+        return Ok(None);
+    }
+    let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+    let positions: Vec<[f32; 3]> = match reader.read_positions() {
+        Some(positions) => positions.collect(),
+        None => {
+            warn!("Skipping a gltf primitive without positions");
+            return Ok(None);
+        }
+    };
+    let texture_coordinates: Vec<[f32; 2]> = match reader.read_tex_coords(0) {
+        Some(texture_coordinates) => texture_coordinates.into_f32().collect(),
+        None => vec![[0.0, 0.0]; positions.len()],
+    };
+    // Lightmaps use the second uv channel if there is one
+    let lightmap_coordinates: Vec<[f32; 2]> = match reader.read_tex_coords(1) {
+        Some(lightmap_coordinates) => lightmap_coordinates.into_f32().collect(),
+        None => texture_coordinates.clone(),
+    };
+    // The missing normals and tangents are computed from the triangles
+    let read_normals: Option<Vec<[f32; 3]>> = reader.read_normals().map(Iterator::collect);
+    let read_tangents: Option<Vec<[f32; 4]>> = reader.read_tangents().map(Iterator::collect);
+    let (has_normals, has_tangents) = (read_normals.is_some(), read_tangents.is_some());
+    let normals = read_normals.unwrap_or_else(|| vec![[0.0, 0.0, 1.0]; positions.len()]);
+    let tangents = read_tangents.unwrap_or_else(|| vec![[1.0, 0.0, 0.0, 1.0]; positions.len()]);
+    let mut vertices: Vec<VertexData> = positions
+        .iter()
+        .zip(texture_coordinates.iter())
+        .zip(lightmap_coordinates.iter())
+        .zip(normals.iter().zip(tangents.iter()))
+        .map(
+            |(((position, texture), lightmap_texture), (normal, tangent))| VertexData {
+                position: glam::Vec3::from_array(*position),
+                texture: glam::Vec2::from_array(*texture),
+                lightmap_texture: glam::Vec2::from_array(*lightmap_texture),
+                normal: glam::Vec3::from_array(*normal),
+                tangent: glam::Vec4::from_array(*tangent),
+            },
+        )
+        .collect();
+    let indices: Vec<u32> = match reader.read_indices() {
+        Some(indices) => indices.into_u32().collect(),
+        None => (0..positions.len() as u32).collect(),
+    };
+    if !has_normals {
+        mesh_compute_normals(&mut vertices, &indices);
+    }
+    if !has_tangents {
+        mesh_compute_tangents(&mut vertices, &indices);
+    }
+    let read_morph_targets: Vec<GltfMorphTargetDeltas> = reader
+        .read_morph_targets()
+        .map(|(positions, normals, _tangents)| {
+            (
+                positions.map(Iterator::collect),
+                normals.map(Iterator::collect),
+            )
+        })
+        .collect();
+    let morph_targets =
+        gltf_convert_morph_targets(read_morph_targets, &vertices, default_morph_weights)?;
+
+    let bounds = primitive.bounding_box();
+    let min = glam::Vec3::from_array(bounds.min);
+    let max = glam::Vec3::from_array(bounds.max);
+    Ok(Some(GltfPrimitive {
+        vertices,
+        indices,
+        material: primitive.material().index(),
+        bounding_sphere: BoundingSphere::new((min + max) * 0.5, (max - min).length() * 0.5),
+        morph_targets,
+    }))
+}
+
+/// Read the meshes, materials, images and nodes of a .gltf or .glb file
+pub fn gltf_load(path: &Path) -> Result<GltfScene, EngineError> {
+    let (document, buffers, images) = match gltf::import(path) {
+        Ok(imported) => imported,
+        Err(err) => {
+            error!("Failed to import the gltf file {:?}: {:?}", path, err);
+            return Err(EngineError::IO);
+        }
+    };
+
+    let mut meshes = Vec::new();
+    for mesh in document.meshes() {
+        let mut primitives = Vec::new();
+        for primitive in mesh.primitives() {
+            if let Some(primitive) = gltf_convert_primitive(&primitive, &buffers, mesh.weights())? {
+                primitives.push(primitive);
+            }
+        }
+        meshes.push(GltfMesh {
+            name: mesh.name().unwrap_or_default().to_string(),
+            primitives,
+        });
+    }
+
+    let materials = document
+        .materials()
+        .map(|material| {
+            let pbr = material.pbr_metallic_roughness();
+            GltfMaterial {
+                name: material.name().unwrap_or_default().to_string(),
+                base_color: glam::Vec4::from_array(pbr.base_color_factor()),
+                base_color_image: pbr
+                    .base_color_texture()
+                    .map(|info| info.texture().source().index()),
+                normal_image: material
+                    .normal_texture()
+                    .map(|normal| normal.texture().source().index()),
+                is_double_sided: material.double_sided(),
+                is_blended: material.alpha_mode() == gltf::material::AlphaMode::Blend,
+            }
+        })
+        .collect();
+
+    let mut converted_images = Vec::with_capacity(images.len());
+    for image in &images {
+        converted_images.push(gltf_convert_image(image)?);
+    }
+
+    let nodes = document
+        .nodes()
+        .map(|node| {
+            let (translation, rotation, scale) = node.transform().decomposed();
+            GltfNode {
+                name: node.name().unwrap_or_default().to_string(),
+                transform: SceneTransform::default()
+                    .position(glam::Vec3::from_array(translation))
+                    .rotation(glam::Quat::from_array(rotation))
+                    .scale(glam::Vec3::from_array(scale)),
+                mesh: node.mesh().map(|mesh| mesh.index()),
+                children: node.children().map(|child| child.index()).collect(),
+            }
+        })
+        .collect();
+
+    let roots = match document
+        .default_scene()
+        .or_else(|| document.scenes().next())
+    {
+        Some(scene) => scene.nodes().map(|node| node.index()).collect(),
+        None => {
+            warn!("The gltf file {:?} doesn't have any scene", path);
+            Vec::new()
+        }
+    };
+
+    Ok(GltfScene {
+        meshes,
+        materials,
+        images: converted_images,
+        nodes,
+        roots,
+    })
+}
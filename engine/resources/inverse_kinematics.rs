@@ -0,0 +1,131 @@
+use crate::{core::debug::errors::EngineError, error};
+
+/// Chain of three joints solved analytically (hip/knee/ankle, shoulder/elbow/wrist)
+#[derive(Clone, Copy, Debug)]
+pub struct TwoBoneIkChain {
+    pub root_joint: usize,
+    pub mid_joint: usize,
+    pub end_joint: usize,
+    /// World space position the end joint tries to reach
+    pub target: glam::Vec3,
+    /// Point the mid joint bends towards
+    pub pole_vector: glam::Vec3,
+    /// Blend between the sampled pose (0) and the solved pose (1)
+    pub weight: f32,
+    pub is_enabled: bool,
+}
+
+impl TwoBoneIkChain {
+    pub fn new(root_joint: usize, mid_joint: usize, end_joint: usize) -> Self {
+        Self {
+            root_joint,
+            mid_joint,
+            end_joint,
+            target: glam::Vec3::ZERO,
+            pole_vector: glam::Vec3::Z,
+            weight: 1.0,
+            is_enabled: true,
+        }
+    }
+
+    pub fn target(mut self, target: glam::Vec3) -> Self {
+        self.target = target;
+        self
+    }
+
+    pub fn pole_vector(mut self, pole_vector: glam::Vec3) -> Self {
+        self.pole_vector = pole_vector;
+        self
+    }
+
+    pub fn weight(mut self, weight: f32) -> Self {
+        self.weight = weight.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn is_enabled(mut self, is_enabled: bool) -> Self {
+        self.is_enabled = is_enabled;
+        self
+    }
+
+    /// Move the mid and end joints of a sampled pose, should be called after the clip sampling
+    pub fn apply(&self, joint_positions: &mut [glam::Vec3]) -> Result<(), EngineError> {
+        if !self.is_enabled || self.weight <= 0.0 {
+            return Ok(());
+        }
+        let joint_count = joint_positions.len();
+        if [self.root_joint, self.mid_joint, self.end_joint]
+            .iter()
+            .any(|joint| *joint >= joint_count)
+        {
+            error!(
+                "The ik chain ({}, {}, {}) does not fit in a pose of {} joints",
+                self.root_joint, self.mid_joint, self.end_joint, joint_count
+            );
+            return Err(EngineError::InvalidValue);
+        }
+
+        let root = joint_positions[self.root_joint];
+        let mid = joint_positions[self.mid_joint];
+        let end = joint_positions[self.end_joint];
+        let (solved_mid, solved_end) =
+            solve_two_bone_ik(root, mid, end, self.target, self.pole_vector);
+
+        joint_positions[self.mid_joint] = mid.lerp(solved_mid, self.weight);
+        joint_positions[self.end_joint] = end.lerp(solved_end, self.weight);
+        Ok(())
+    }
+}
+
+/// Returns the new mid and end positions keeping the bone lengths
+/// The target is clamped to the reach of the chain
+/// A chain with a bone of nearly no length, e.g. coincident joints, is returned unchanged
+pub fn solve_two_bone_ik(
+    root: glam::Vec3,
+    mid: glam::Vec3,
+    end: glam::Vec3,
+    target: glam::Vec3,
+    pole_vector: glam::Vec3,
+) -> (glam::Vec3, glam::Vec3) {
+    // Keep a tiny epsilon so the chain never fully straightens and flips
+    let epsilon = 1e-4;
+    let upper_length = (mid - root).length();
+    let lower_length = (end - mid).length();
+    if upper_length < epsilon || lower_length < epsilon {
+        return (mid, end);
+    }
+    let to_target = target - root;
+    let direction = match to_target.try_normalize() {
+        Some(direction) => direction,
+        // The target is on the root, nothing sensible to do
+        None => return (mid, end),
+    };
+
+    // The bounds cross when the bones are short, `max` then `min` never panics unlike `clamp`
+    let distance = to_target
+        .length()
+        .max((upper_length - lower_length).abs() + epsilon)
+        .min(upper_length + lower_length - epsilon)
+        .max(epsilon);
+
+    // Law of cosines for the angle at the root
+    let cos_root = ((upper_length * upper_length + distance * distance
+        - lower_length * lower_length)
+        / (2.0 * upper_length * distance))
+        .clamp(-1.0, 1.0);
+    let sin_root = (1.0 - cos_root * cos_root).sqrt();
+
+    // Bend direction from the pole vector, falls back on the current bend
+    let to_pole = pole_vector - root;
+    let bend_direction = (to_pole - direction * to_pole.dot(direction))
+        .try_normalize()
+        .or_else(|| {
+            let to_mid = mid - root;
+            (to_mid - direction * to_mid.dot(direction)).try_normalize()
+        })
+        .unwrap_or_else(|| direction.any_orthonormal_vector());
+
+    let solved_mid = root + (direction * cos_root + bend_direction * sin_root) * upper_length;
+    let solved_end = root + direction * distance;
+    (solved_mid, solved_end)
+}
@@ -0,0 +1,158 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+
+use crate::resources::texture_system::TextureHandle;
+
+/// How the color of a surface is combined with the one already in the frame
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum BlendMode {
+    /// Replaces the color behind
+    #[default]
+    Opaque,
+    /// Mixed with the color behind by its alpha, drawn back to front after the opaque objects
+    AlphaBlend,
+    /// Added to the color behind, weighted by its alpha
+    Additive,
+}
+
+/// Rasterizer and depth state of the objects drawn with a material
+/// Each different state is drawn with its own pipeline
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct MaterialRenderState {
+    /// Draw the back faces too
+    pub is_two_sided: bool,
+    pub is_depth_test_enabled: bool,
+    /// Ignored when the depth test is disabled
+    pub is_depth_write_enabled: bool,
+    pub blend_mode: BlendMode,
+}
+
+impl Default for MaterialRenderState {
+    fn default() -> Self {
+        Self {
+            is_two_sided: false,
+            is_depth_test_enabled: true,
+            is_depth_write_enabled: true,
+            blend_mode: BlendMode::default(),
+        }
+    }
+}
+
+impl MaterialRenderState {
+    pub fn two_sided(mut self, is_two_sided: bool) -> Self {
+        self.is_two_sided = is_two_sided;
+        self
+    }
+
+    pub fn depth(mut self, is_depth_test_enabled: bool, is_depth_write_enabled: bool) -> Self {
+        self.is_depth_test_enabled = is_depth_test_enabled;
+        self.is_depth_write_enabled = is_depth_write_enabled;
+        self
+    }
+
+    pub fn blend_mode(mut self, blend_mode: BlendMode) -> Self {
+        self.blend_mode = blend_mode;
+        self
+    }
+
+    /// Key of the pipeline variant drawing this state
+    pub fn get_hash(&self) -> u64 {
+        let mut hasher = DefaultHasher::new();
+        self.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+/// Specular strength of the materials created without one
+pub const MATERIAL_DEFAULT_SPECULAR_STRENGTH: f32 = 0.5;
+/// Shininess of the materials created without one
+pub const MATERIAL_DEFAULT_SHININESS: f32 = 32.0;
+
+/// Number of textures a material can reference
+pub const MATERIAL_TEXTURE_SLOT_COUNT: usize = 4;
+
+/// Textures of a material, in the order of the sampler bindings of the object shaders
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum MaterialTextureSlot {
+    Diffuse,
+    Lightmap,
+    /// Tangent space normals
+    Normal,
+    /// Specular strength in the red channel
+    Specular,
+}
+
+impl MaterialTextureSlot {
+    pub const ALL: [Self; MATERIAL_TEXTURE_SLOT_COUNT] =
+        [Self::Diffuse, Self::Lightmap, Self::Normal, Self::Specular];
+
+    /// Binding of the slot sampler in the per object descriptor set
+    pub fn get_binding(&self) -> u32 {
+        *self as u32
+    }
+}
+
+/// Surface properties shared by every object drawn with it
+#[derive(Clone)]
+pub struct Material {
+    pub id: u32,
+    pub name: String,
+    /// Increased every time the properties change
+    pub generation: u32,
+    pub diffuse_color: glam::Vec4,
+    /// The renderer default texture is used when missing
+    pub diffuse_texture: Option<TextureHandle>,
+    /// Baked static lighting, read with the second uv channel, unlit when missing
+    pub lightmap_texture: Option<TextureHandle>,
+    /// The vertex normals are used when missing
+    pub normal_texture: Option<TextureHandle>,
+    /// Scales the specular strength, the strength is used as is when missing
+    pub specular_texture: Option<TextureHandle>,
+    pub specular_strength: f32,
+    /// Sharpness of the specular highlights
+    pub shininess: f32,
+    pub render_state: MaterialRenderState,
+}
+
+impl Material {
+    pub fn get_texture(&self, slot: MaterialTextureSlot) -> Option<TextureHandle> {
+        match slot {
+            MaterialTextureSlot::Diffuse => self.diffuse_texture,
+            MaterialTextureSlot::Lightmap => self.lightmap_texture,
+            MaterialTextureSlot::Normal => self.normal_texture,
+            MaterialTextureSlot::Specular => self.specular_texture,
+        }
+    }
+
+    pub fn set_texture(&mut self, slot: MaterialTextureSlot, texture: Option<TextureHandle>) {
+        match slot {
+            MaterialTextureSlot::Diffuse => self.diffuse_texture = texture,
+            MaterialTextureSlot::Lightmap => self.lightmap_texture = texture,
+            MaterialTextureSlot::Normal => self.normal_texture = texture,
+            MaterialTextureSlot::Specular => self.specular_texture = texture,
+        }
+    }
+
+    /// Every texture referenced by the material
+    pub fn get_textures(&self) -> Vec<TextureHandle> {
+        MaterialTextureSlot::ALL
+            .iter()
+            .filter_map(|slot| self.get_texture(*slot))
+            .collect()
+    }
+}
+
+pub struct MaterialCreatorParameters<'a> {
+    /// Acquiring a material with the name of an existing one returns the existing one
+    pub name: &'a str,
+    /// Destroy the material when it is not referenced anymore
+    pub auto_release: bool,
+    pub diffuse_color: glam::Vec4,
+    /// The material holds a reference to its textures until it is destroyed
+    pub diffuse_texture: Option<TextureHandle>,
+    pub lightmap_texture: Option<TextureHandle>,
+    pub normal_texture: Option<TextureHandle>,
+    pub specular_texture: Option<TextureHandle>,
+    pub specular_strength: f32,
+    pub shininess: f32,
+    pub render_state: MaterialRenderState,
+}
@@ -1 +1,10 @@
-pub mod texture;
+pub(crate) mod animation;
+pub(crate) mod font;
+pub(crate) mod geometry;
+pub(crate) mod gltf;
+pub(crate) mod inverse_kinematics;
+pub(crate) mod material;
+pub(crate) mod morph_targets;
+pub(crate) mod sprite_animation;
+pub(crate) mod texture;
+pub(crate) mod texture_system;
@@ -0,0 +1,158 @@
+use crate::{core::debug::errors::EngineError, error, renderer::renderer_types::VertexData};
+
+/// Offsets applied to the base mesh when the target is fully weighted
+#[derive(Clone, Debug, Default)]
+pub struct MorphTarget {
+    pub name: String,
+    pub position_deltas: Vec<glam::Vec3>,
+    /// None if the target does not change the normals
+    pub normal_deltas: Option<Vec<glam::Vec3>>,
+}
+
+/// Blend shapes of a mesh, blended on the cpu
+/// Imported with the gltf meshes, see `renderer_set_morph_weights` to animate them
+#[derive(Clone, Debug, Default)]
+pub struct MorphTargetSet {
+    pub base_positions: Vec<glam::Vec3>,
+    pub base_normals: Vec<glam::Vec3>,
+    pub targets: Vec<MorphTarget>,
+    pub weights: Vec<f32>,
+}
+
+impl MorphTargetSet {
+    pub fn new(base_positions: Vec<glam::Vec3>, base_normals: Vec<glam::Vec3>) -> Self {
+        Self {
+            base_positions,
+            base_normals,
+            targets: Vec::new(),
+            weights: Vec::new(),
+        }
+    }
+
+    /// Returns the index of the new target
+    pub fn add_target(&mut self, target: MorphTarget) -> Result<usize, EngineError> {
+        if target.position_deltas.len() != self.base_positions.len() {
+            error!(
+                "The morph target `{}' has {} position deltas instead of {}",
+                target.name,
+                target.position_deltas.len(),
+                self.base_positions.len()
+            );
+            return Err(EngineError::InvalidValue);
+        }
+        if let Some(normal_deltas) = &target.normal_deltas {
+            if normal_deltas.len() != self.base_normals.len() {
+                error!(
+                    "The morph target `{}' has {} normal deltas instead of {}",
+                    target.name,
+                    normal_deltas.len(),
+                    self.base_normals.len()
+                );
+                return Err(EngineError::InvalidValue);
+            }
+        }
+        self.targets.push(target);
+        self.weights.push(0.0);
+        Ok(self.targets.len() - 1)
+    }
+
+    pub fn get_target_index(&self, name: &str) -> Option<usize> {
+        self.targets.iter().position(|target| target.name == name)
+    }
+
+    pub fn set_weight(&mut self, index: usize, weight: f32) -> Result<(), EngineError> {
+        match self.weights.get_mut(index) {
+            Some(current_weight) => {
+                *current_weight = weight;
+                Ok(())
+            }
+            None => {
+                error!("The morph target {} does not exist", index);
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    pub fn set_weight_by_name(&mut self, name: &str, weight: f32) -> Result<(), EngineError> {
+        match self.get_target_index(name) {
+            Some(index) => self.set_weight(index, weight),
+            None => {
+                error!("The morph target `{}' does not exist", name);
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    /// Blended positions and normals for the current weights
+    pub fn blend(&self) -> (Vec<glam::Vec3>, Vec<glam::Vec3>) {
+        let mut positions = self.base_positions.clone();
+        let mut normals = self.base_normals.clone();
+        for (target, weight) in self.targets.iter().zip(&self.weights) {
+            if *weight == 0.0 {
+                continue;
+            }
+            for (position, delta) in positions.iter_mut().zip(&target.position_deltas) {
+                *position += *delta * *weight;
+            }
+            if let Some(normal_deltas) = &target.normal_deltas {
+                for (normal, delta) in normals.iter_mut().zip(normal_deltas) {
+                    *normal += *delta * *weight;
+                }
+            }
+        }
+        for normal in normals.iter_mut() {
+            *normal = normal.normalize_or_zero();
+        }
+        (positions, normals)
+    }
+
+    /// Write the blended positions and normals into vertices ready to be uploaded
+    pub(crate) fn blend_into(&self, vertices: &mut [VertexData]) -> Result<(), EngineError> {
+        if vertices.len() != self.base_positions.len() {
+            error!(
+                "Can't blend {} morph target positions into {} vertices",
+                self.base_positions.len(),
+                vertices.len()
+            );
+            return Err(EngineError::InvalidValue);
+        }
+        let (positions, normals) = self.blend();
+        for ((vertex, position), normal) in vertices.iter_mut().zip(positions).zip(normals) {
+            vertex.position = position;
+            vertex.normal = normal;
+        }
+        Ok(())
+    }
+}
+
+/// Vertices of a geometry blended again from its morph targets when the weights change
+pub(crate) struct MorphGeometry {
+    pub vertices: Vec<VertexData>,
+    pub targets: MorphTargetSet,
+}
+
+impl MorphGeometry {
+    pub fn new(vertices: Vec<VertexData>, targets: MorphTargetSet) -> Result<Self, EngineError> {
+        let mut geometry = Self { vertices, targets };
+        geometry.targets.blend_into(&mut geometry.vertices)?;
+        Ok(geometry)
+    }
+
+    /// Returns false if the weights did not change
+    pub fn set_weights(&mut self, weights: &[f32]) -> Result<bool, EngineError> {
+        if weights.len() != self.targets.weights.len() {
+            error!(
+                "Can't set {} weights to {} morph targets",
+                weights.len(),
+                self.targets.weights.len()
+            );
+            return Err(EngineError::InvalidValue);
+        }
+        if weights == self.targets.weights.as_slice() {
+            return Ok(false);
+        }
+        self.targets.weights.copy_from_slice(weights);
+        self.targets.blend_into(&mut self.vertices)?;
+        Ok(true)
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{
+    core::{
+        debug::errors::EngineError,
+        systems::events::{event_queue, EventCode},
+    },
+    error,
+};
+
+/// Area of the atlas covered by a frame, in normalized texture coordinates
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SpriteFrameRect {
+    pub min: glam::Vec2,
+    pub max: glam::Vec2,
+}
+
+/// Frames of an atlas texture
+#[derive(Clone, Debug, Default)]
+pub struct SpriteSheet {
+    pub frames: Vec<SpriteFrameRect>,
+}
+
+impl SpriteSheet {
+    /// Cut the atlas in a regular grid, frames are read row by row from the top left
+    pub fn from_grid(columns: u32, rows: u32) -> Result<Self, EngineError> {
+        if columns == 0 || rows == 0 {
+            error!("A sprite sheet grid needs at least one row and one column");
+            return Err(EngineError::InvalidValue);
+        }
+        let frame_size = glam::Vec2::new(1.0 / columns as f32, 1.0 / rows as f32);
+        let frames = (0..rows)
+            .flat_map(|row| (0..columns).map(move |column| (column, row)))
+            .map(|(column, row)| {
+                let min = glam::Vec2::new(column as f32, row as f32) * frame_size;
+                SpriteFrameRect {
+                    min,
+                    max: min + frame_size,
+                }
+            })
+            .collect();
+        Ok(Self { frames })
+    }
+
+    /// Frames given in pixels on an atlas of the given size
+    pub fn from_pixel_rects(
+        atlas_width: u32,
+        atlas_height: u32,
+        rects: &[(u32, u32, u32, u32)],
+    ) -> Result<Self, EngineError> {
+        if atlas_width == 0 || atlas_height == 0 {
+            error!("A sprite sheet atlas can't be empty");
+            return Err(EngineError::InvalidValue);
+        }
+        let atlas_size = glam::Vec2::new(atlas_width as f32, atlas_height as f32);
+        let mut frames = Vec::with_capacity(rects.len());
+        for (x, y, width, height) in rects {
+            if x + width > atlas_width || y + height > atlas_height {
+                error!(
+                    "The sprite frame ({}, {}, {}, {}) is outside of the {}x{} atlas",
+                    x, y, width, height, atlas_width, atlas_height
+                );
+                return Err(EngineError::InvalidValue);
+            }
+            let min = glam::Vec2::new(*x as f32, *y as f32) / atlas_size;
+            let max = glam::Vec2::new((x + width) as f32, (y + height) as f32) / atlas_size;
+            frames.push(SpriteFrameRect { min, max });
+        }
+        Ok(Self { frames })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SpriteAnimationLoopMode {
+    /// Stop on the last frame
+    Once,
+    Loop,
+    /// Play forward then backward
+    PingPong,
+}
+
+/// Flipbook playback of a sprite sheet
+#[derive(Clone, Debug)]
+pub struct SpriteAnimation {
+    pub sprite_id: u32,
+    pub sheet: SpriteSheet,
+    /// Indices in the sheet of the frames to play, in order
+    pub sequence: Vec<usize>,
+    pub frames_per_second: f32,
+    pub loop_mode: SpriteAnimationLoopMode,
+    pub is_playing: bool,
+    /// Fire an event every time the displayed frame changes
+    pub should_fire_frame_events: bool,
+    time: f32,
+    current_frame: usize,
+}
+
+impl SpriteAnimation {
+    /// Plays every frame of the sheet in order by default
+    pub fn new(sprite_id: u32, sheet: SpriteSheet) -> Self {
+        let sequence = (0..sheet.frames.len()).collect();
+        Self {
+            sprite_id,
+            sheet,
+            sequence,
+            frames_per_second: 12.0,
+            loop_mode: SpriteAnimationLoopMode::Loop,
+            is_playing: true,
+            should_fire_frame_events: false,
+            time: 0.0,
+            current_frame: 0,
+        }
+    }
+
+    pub fn sequence(mut self, sequence: Vec<usize>) -> Self {
+        self.sequence = sequence;
+        self
+    }
+
+    pub fn frames_per_second(mut self, frames_per_second: f32) -> Self {
+        self.frames_per_second = frames_per_second;
+        self
+    }
+
+    pub fn loop_mode(mut self, loop_mode: SpriteAnimationLoopMode) -> Self {
+        self.loop_mode = loop_mode;
+        self
+    }
+
+    pub fn should_fire_frame_events(mut self, should_fire_frame_events: bool) -> Self {
+        self.should_fire_frame_events = should_fire_frame_events;
+        self
+    }
+
+    /// Index in the sequence of the frame at the given number of elapsed frames
+    fn get_sequence_index(&self, elapsed_frames: usize) -> usize {
+        let length = self.sequence.len();
+        match self.loop_mode {
+            SpriteAnimationLoopMode::Once => elapsed_frames.min(length - 1),
+            SpriteAnimationLoopMode::Loop => elapsed_frames % length,
+            SpriteAnimationLoopMode::PingPong => {
+                if length == 1 {
+                    return 0;
+                }
+                // The end frames are not repeated when turning around
+                let period = 2 * (length - 1);
+                let position = elapsed_frames % period;
+                if position < length {
+                    position
+                } else {
+                    period - position
+                }
+            }
+        }
+    }
+
+    /// Move the animation forward, should be called once per frame
+    pub fn update(&mut self, delta_time: f64) -> Result<(), EngineError> {
+        if !self.is_playing || self.sequence.is_empty() || self.frames_per_second <= 0.0 {
+            return Ok(());
+        }
+        self.time += delta_time as f32;
+        let elapsed_frames = (self.time * self.frames_per_second) as usize;
+        if self.loop_mode == SpriteAnimationLoopMode::Once && elapsed_frames >= self.sequence.len()
+        {
+            self.is_playing = false;
+        }
+
+        let new_frame = self.get_sequence_index(elapsed_frames);
+        if new_frame != self.current_frame {
+            self.current_frame = new_frame;
+            if self.should_fire_frame_events {
+                if let Err(err) = event_queue(EventCode::SpriteFrameChanged {
+                    sprite_id: self.sprite_id,
+                    frame: self.sequence[new_frame] as u32,
+                }) {
+                    error!("Failed to queue the sprite frame changed event: {:?}", err);
+                    return Err(EngineError::UpdateFailed);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    pub fn restart(&mut self) {
+        self.time = 0.0;
+        self.current_frame = 0;
+        self.is_playing = true;
+    }
+
+    /// Index in the sheet of the displayed frame
+    pub fn get_current_frame(&self) -> Option<usize> {
+        self.sequence.get(self.current_frame).copied()
+    }
+
+    /// Texture coordinates of the displayed frame, drawn by the `SpriteRenderer` component
+    pub fn get_current_frame_rect(&self) -> Option<SpriteFrameRect> {
+        self.get_current_frame()
+            .and_then(|frame| self.sheet.frames.get(frame).copied())
+    }
+}
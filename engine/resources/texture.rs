@@ -1,6 +1,6 @@
 use std::any::Any;
 
-pub trait Texture {
+pub trait Texture: Send {
     fn get_id(&self) -> u32;
 
     fn get_width(&self) -> u32;
@@ -11,6 +11,7 @@ pub trait Texture {
     fn has_transparency(&self) -> bool;
 
     fn get_generation(&self) -> Option<u32>;
+    fn set_generation(&mut self, generation: Option<u32>);
     fn as_any(&self) -> &dyn Any;
 
     fn clone_box(&self) -> Box<dyn Texture>;
@@ -0,0 +1,282 @@
+use std::{collections::HashMap, path::Path};
+
+use image::{ImageReader, RgbaImage};
+
+use crate::{
+    core::debug::errors::EngineError,
+    error,
+    renderer::renderer_backend::RendererBackend,
+    resources::texture::{Texture, TextureCreatorParameters},
+    warn,
+};
+
+/// Stable handle to a texture of the texture system, stays valid until the texture is destroyed
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct TextureHandle(u32);
+
+struct TextureReference {
+    texture: Box<dyn Texture>,
+    name: String,
+    reference_count: u32,
+    auto_release: bool,
+}
+
+/// Decode an image file as rgba8, returns the image and whether it has transparent pixels
+pub fn texture_load_image(path: &Path) -> Result<(RgbaImage, bool), EngineError> {
+    // TODO: Better path handling
+    let image = match ImageReader::open(path) {
+        Ok(image) => image,
+        Err(err) => {
+            error!(
+                "Failed to open the file: {:?}, when trying to load a texture: {:?}",
+                path, err
+            );
+            return Err(EngineError::IO);
+        }
+    };
+    let image = match image.decode() {
+        Ok(image) => image,
+        Err(err) => {
+            error!(
+                "Failed to decode the file: {:?}, when trying to load a texture: {:?}",
+                path, err
+            );
+            return Err(EngineError::IO);
+        }
+    };
+    // TODO: handle different formats
+    let image = image.to_rgba8();
+    let has_transparency = image.pixels().any(|pixel| pixel[3] < 255);
+    Ok((image, has_transparency))
+}
+
+/// Textures shared by name and reference counted
+#[derive(Default)]
+pub(crate) struct TextureSystem {
+    textures: HashMap<TextureHandle, TextureReference>,
+    next_handle: u32,
+    /// Used when a texture is missing, not reference counted
+    default_texture: Option<Box<dyn Texture>>,
+}
+
+impl TextureSystem {
+    /// Returns the handle of the texture with the given name without acquiring it
+    pub fn get_handle(&self, name: &str) -> Option<TextureHandle> {
+        self.textures
+            .iter()
+            .find(|(_, reference)| reference.name == name)
+            .map(|(handle, _)| *handle)
+    }
+
+    pub fn get(&self, handle: TextureHandle) -> Option<&dyn Texture> {
+        self.textures
+            .get(&handle)
+            .map(|reference| reference.texture.as_ref())
+    }
+
+    /// Acquire the texture with the given name if it exists
+    pub fn acquire_by_name(&mut self, name: &str) -> Option<TextureHandle> {
+        let handle = self.get_handle(name)?;
+        self.add_reference(handle).ok()?;
+        Some(handle)
+    }
+
+    /// Returns the handle of the texture with the given name, creating it if it does not exist yet
+    pub fn acquire(
+        &mut self,
+        backend: &mut dyn RendererBackend,
+        params: TextureCreatorParameters,
+    ) -> Result<TextureHandle, EngineError> {
+        if let Some(handle) = self.acquire_by_name(params.name) {
+            return Ok(handle);
+        }
+        let name = String::from(params.name);
+        let auto_release = params.auto_release;
+        let texture = match backend.create_texture(params) {
+            Ok(texture) => texture,
+            Err(err) => {
+                error!("Failed to create the texture `{}': {:?}", name, err);
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        let handle = TextureHandle(self.next_handle);
+        self.next_handle += 1;
+        self.textures.insert(
+            handle,
+            TextureReference {
+                texture,
+                name,
+                reference_count: 1,
+                auto_release,
+            },
+        );
+        Ok(handle)
+    }
+
+    /// Acquire the texture with the given name, loading it from the file if it does not exist yet
+    pub fn load(
+        &mut self,
+        backend: &mut dyn RendererBackend,
+        path: &Path,
+        name: &str,
+        auto_release: bool,
+    ) -> Result<TextureHandle, EngineError> {
+        if let Some(handle) = self.acquire_by_name(name) {
+            return Ok(handle);
+        }
+        let (image, has_transparency) = texture_load_image(path)?;
+        self.acquire(
+            backend,
+            TextureCreatorParameters {
+                name,
+                auto_release,
+                width: image.width(),
+                height: image.height(),
+                nb_channels: 4, // for now
+                pixels: image.as_raw(),
+                has_transparency,
+                is_default: false,
+            },
+        )
+    }
+
+    /// Give the texture new pixels, the handle and the references stay the same
+    /// The old texture is retired so the frames in flight can keep sampling it
+    pub fn replace(
+        &mut self,
+        backend: &mut dyn RendererBackend,
+        handle: TextureHandle,
+        params: TextureCreatorParameters,
+    ) -> Result<(), EngineError> {
+        let reference = match self.textures.get_mut(&handle) {
+            Some(reference) => reference,
+            None => {
+                error!("Can't replace the unknown texture {:?}", handle);
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        let mut texture = match backend.create_texture(params) {
+            Ok(texture) => texture,
+            Err(err) => {
+                error!(
+                    "Failed to create the new texture when replacing `{}': {:?}",
+                    reference.name, err
+                );
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        // The next generation makes the users of the handle pick the new texture up
+        let generation = reference
+            .texture
+            .get_generation()
+            .map(|generation| generation.wrapping_add(1));
+        texture.set_generation(generation);
+        let old_texture = std::mem::replace(&mut reference.texture, texture);
+        if let Err(err) = backend.retire_texture(old_texture) {
+            error!(
+                "Failed to retire the old texture when replacing `{}': {:?}",
+                reference.name, err
+            );
+            return Err(EngineError::ShutdownFailed);
+        }
+        Ok(())
+    }
+
+    /// Used by the owners sharing an already acquired texture, like the materials
+    pub fn add_reference(&mut self, handle: TextureHandle) -> Result<(), EngineError> {
+        match self.textures.get_mut(&handle) {
+            Some(reference) => {
+                reference.reference_count += 1;
+                Ok(())
+            }
+            None => {
+                error!("Can't acquire the unknown texture {:?}", handle);
+                Err(EngineError::InvalidValue)
+            }
+        }
+    }
+
+    /// Destroy the texture once it is not referenced anymore, if it was created with auto release
+    pub fn release(
+        &mut self,
+        backend: &mut dyn RendererBackend,
+        handle: TextureHandle,
+    ) -> Result<(), EngineError> {
+        let reference = match self.textures.get_mut(&handle) {
+            Some(reference) => reference,
+            None => {
+                error!("Can't release the unknown texture {:?}", handle);
+                return Err(EngineError::InvalidValue);
+            }
+        };
+        if reference.reference_count == 0 {
+            warn!(
+                "The texture `{}' is released more times than it was acquired",
+                reference.name
+            );
+            return Ok(());
+        }
+        reference.reference_count -= 1;
+        if reference.reference_count == 0 && reference.auto_release {
+            let reference = self.textures.remove(&handle).unwrap();
+            if let Err(err) = backend.destroy_texture(reference.texture.as_ref()) {
+                error!(
+                    "Failed to destroy the texture `{}': {:?}",
+                    reference.name, err
+                );
+                return Err(EngineError::ShutdownFailed);
+            }
+        }
+        Ok(())
+    }
+
+    /// Name and reference count of every texture, sorted by name
+    pub fn get_summaries(&self) -> Vec<(String, u32)> {
+        let mut summaries: Vec<(String, u32)> = self
+            .textures
+            .values()
+            .map(|reference| (reference.name.clone(), reference.reference_count))
+            .collect();
+        summaries.sort();
+        summaries
+    }
+
+    pub fn get_default_texture(&self) -> Option<&dyn Texture> {
+        self.default_texture.as_deref()
+    }
+
+    /// Replace the default texture, the previous one is retired
+    pub fn set_default_texture(
+        &mut self,
+        backend: &mut dyn RendererBackend,
+        texture: Box<dyn Texture>,
+    ) -> Result<(), EngineError> {
+        if let Some(old_texture) = self.default_texture.replace(texture) {
+            if let Err(err) = backend.retire_texture(old_texture) {
+                error!("Failed to retire the old default texture: {:?}", err);
+                return Err(EngineError::ShutdownFailed);
+            }
+        }
+        Ok(())
+    }
+
+    /// Destroy every texture, referenced or not
+    pub fn shutdown(&mut self, backend: &mut dyn RendererBackend) -> Result<(), EngineError> {
+        for (_, reference) in self.textures.drain() {
+            if let Err(err) = backend.destroy_texture(reference.texture.as_ref()) {
+                error!(
+                    "Failed to destroy the texture `{}': {:?}",
+                    reference.name, err
+                );
+                return Err(EngineError::ShutdownFailed);
+            }
+        }
+        if let Some(texture) = self.default_texture.take() {
+            if let Err(err) = backend.destroy_texture(texture.as_ref()) {
+                error!("Failed to destroy the default texture: {:?}", err);
+                return Err(EngineError::ShutdownFailed);
+            }
+        }
+        Ok(())
+    }
+}
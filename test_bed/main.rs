@@ -1,4 +1,4 @@
-use engine::{core::application::ApplicationParameters, entry::engine_start, error};
+use engine::prelude::*;
 use src::game::TestBedGame;
 
 pub mod src;
@@ -1,7 +1,4 @@
-use engine::{
-    core::debug::errors::EngineError,
-    renderer::{renderer_frontend::renderer_get_main_camera, scene::camera::Camera},
-};
+use engine::prelude::{renderer_get_main_camera, Camera, EngineError};
 
 pub enum MovementDirection {
     Forward,
@@ -1,18 +1,47 @@
-use engine::{
-    core::{
-        debug::errors::EngineError,
-        systems::input::{input_is_key_down, input_is_key_up, input_was_key_down, keyboard::Key},
-    },
-    error,
-    game::Game,
-    renderer::renderer_frontend::{renderer_set_main_camera, renderer_swap_default_texture},
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
 };
 
-use super::camera::{CameraMovement, MovementDirection};
+use engine::prelude::{
+    console_register_command, console_unregister_command, error, info, input_is_key_down,
+    input_is_key_up, input_was_key_down, renderer_set_main_camera, EngineError, Game, Key,
+};
+
+use super::{
+    camera::{CameraMovement, MovementDirection},
+    sample::{Sample, SampleDescription},
+    test_quad::TestQuad,
+};
+
+/// Folder of the sample files, loaded in the order of their names
+const SAMPLES_FOLDER: &str = "assets/samples";
+
+/// Keys selecting the samples, the first file is on the numpad 1
+/// The function keys are engine hotkeys, see the `sample' console command for more samples
+const SAMPLE_KEYS: [Key; 9] = [
+    Key::NUMPAD1,
+    Key::NUMPAD2,
+    Key::NUMPAD3,
+    Key::NUMPAD4,
+    Key::NUMPAD5,
+    Key::NUMPAD6,
+    Key::NUMPAD7,
+    Key::NUMPAD8,
+    Key::NUMPAD9,
+];
+
+/// Console command selecting a sample by its number
+const SAMPLE_COMMAND: &str = "sample";
 
 #[derive(Default)]
 pub struct TestBedGame {
     pub camera: CameraMovement,
+    pub samples: Vec<SampleDescription>,
+    pub current_sample: Option<Sample>,
+    pub test_quad: Option<TestQuad>,
+    /// Sample picked from the console, switched to on the next update
+    pub requested_sample: Arc<Mutex<Option<usize>>>,
 }
 
 impl TestBedGame {
@@ -52,20 +81,58 @@ impl TestBedGame {
         Ok(())
     }
 
+    fn switch_sample(&mut self, index: usize) -> Result<(), EngineError> {
+        if let Some(sample) = self.current_sample.take() {
+            if let Err(err) = sample.unload() {
+                error!("Failed to unload the current sample: {:?}", err);
+                return Err(EngineError::ShutdownFailed);
+            }
+        }
+        let description = &self.samples[index];
+        info!("Loading the sample `{}'", description.name);
+        // A broken sample file should not stop the test bed
+        self.current_sample = Sample::load(description).ok();
+        Ok(())
+    }
+
+    fn handle_input_samples(&mut self) -> Result<(), EngineError> {
+        let requested_sample = self.requested_sample.lock().unwrap().take();
+        if let Some(index) = requested_sample {
+            if index < self.samples.len() {
+                return self.switch_sample(index);
+            }
+            error!(
+                "There is no sample {}, only {} are loaded",
+                index + 1,
+                self.samples.len()
+            );
+        }
+        for (index, key) in SAMPLE_KEYS.iter().enumerate().take(self.samples.len()) {
+            if input_is_key_up(*key)? && input_was_key_down(*key)? {
+                return self.switch_sample(index);
+            }
+        }
+        Ok(())
+    }
+
     fn handle_input(&mut self, delta_time: f64) -> Result<(), EngineError> {
         if let Err(err) = self.handle_input_camera(delta_time) {
             error!("Failed to handle input in the testbed game: {:?}", err);
             return Err(EngineError::Unknown);
         }
+        if let Err(err) = self.handle_input_samples() {
+            error!("Failed to switch the testbed sample: {:?}", err);
+            return Err(EngineError::Unknown);
+        }
 
-        // TODO: temporary code
-        if input_is_key_up(Key::T).unwrap() && input_was_key_down(Key::T).unwrap() {
-            if let Err(err) = renderer_swap_default_texture() {
-                error!("Failed to swap the default texture: {:?}", err);
-                return Err(EngineError::UpdateFailed);
+        if input_is_key_up(Key::T)? && input_was_key_down(Key::T)? {
+            if let Some(test_quad) = &mut self.test_quad {
+                if let Err(err) = test_quad.swap_texture() {
+                    error!("Failed to swap the test quad texture: {:?}", err);
+                    return Err(EngineError::UpdateFailed);
+                }
             }
         }
-        // TODO: end of temporary code
 
         Ok(())
     }
@@ -74,6 +141,52 @@ impl TestBedGame {
 impl Game for TestBedGame {
     fn on_start(&mut self) -> Result<(), EngineError> {
         self.camera = CameraMovement::new()?;
+        self.test_quad = Some(TestQuad::create()?);
+        self.samples = SampleDescription::load_folder(Path::new(SAMPLES_FOLDER))?;
+        for (index, sample) in self.samples.iter().enumerate() {
+            match SAMPLE_KEYS.get(index) {
+                Some(key) => info!(
+                    "Press {:?} or type `{} {}' for the sample `{}'",
+                    key,
+                    SAMPLE_COMMAND,
+                    index + 1,
+                    sample.name
+                ),
+                None => info!(
+                    "Type `{} {}' for the sample `{}'",
+                    SAMPLE_COMMAND,
+                    index + 1,
+                    sample.name
+                ),
+            }
+        }
+        let requested_sample = Arc::clone(&self.requested_sample);
+        console_register_command(
+            SAMPLE_COMMAND,
+            "sample <number>, switch to the sample of the test bed",
+            move |arguments| match arguments {
+                [number] => match number.parse::<usize>() {
+                    Ok(number) if number > 0 => {
+                        *requested_sample.lock().unwrap() = Some(number - 1);
+                        Ok(())
+                    }
+                    _ => {
+                        error!("Invalid sample number `{}'", number);
+                        Err(EngineError::InvalidValue)
+                    }
+                },
+                _ => {
+                    error!(
+                        "Expected `sample <number>', got {} arguments",
+                        arguments.len()
+                    );
+                    Err(EngineError::InvalidValue)
+                }
+            },
+        )?;
+        if !self.samples.is_empty() {
+            self.switch_sample(0)?;
+        }
         Ok(())
     }
 
@@ -83,7 +196,21 @@ impl Game for TestBedGame {
             return Err(EngineError::Unknown);
         }
         renderer_set_main_camera(&self.camera.camera)?;
+        if let Some(sample) = &mut self.current_sample {
+            sample.update(delta_time)?;
+        }
 
         Ok(())
     }
+
+    fn on_shutdown(&mut self) -> Result<(), EngineError> {
+        console_unregister_command(SAMPLE_COMMAND)?;
+        if let Some(sample) = self.current_sample.take() {
+            sample.unload()?;
+        }
+        if let Some(test_quad) = self.test_quad.take() {
+            test_quad.destroy()?;
+        }
+        Ok(())
+    }
 }
@@ -1,2 +1,4 @@
 pub mod camera;
 pub mod game;
+pub mod sample;
+pub mod test_quad;
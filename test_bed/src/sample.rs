@@ -0,0 +1,563 @@
+//! Samples of the test bed, described by the files of `assets/samples`
+//! They cover the textured meshes, the gltf scenes, the lighting, the sprites, the ui and text,
+//! and the audio. There is no shadow sample, the engine has no shadow mapping yet
+
+use std::{fs, path::Path, sync::Arc};
+
+use engine::prelude::{
+    audio_play, audio_stop, error, gltf_load, renderer_acquire_material, renderer_acquire_object,
+    renderer_add_point_light, renderer_create_geometry, renderer_destroy_geometry,
+    renderer_draw_rect, renderer_draw_text, renderer_instantiate_gltf, renderer_load_font,
+    renderer_load_texture, renderer_release_material, renderer_release_object,
+    renderer_release_texture, renderer_remove_point_light, renderer_scene_create_node,
+    renderer_scene_destroy_node, renderer_scene_set_renderable, renderer_scene_set_transform,
+    renderer_set_ambient_light, renderer_set_directional_light, renderer_ui_draw_quad,
+    renderer_unload_font, renderer_unload_gltf, warn, AudioBus, BoundingSphere, DirectionalLight,
+    EngineError, GeometryCreatorParameters, GltfInstance, MaterialCreatorParameters,
+    MaterialRenderState, PointLight, SceneNodeId, SceneRenderable, SceneTransform, SpriteAnimation,
+    SpriteSheet, TextureHandle, UiQuad, VertexData, AUDIO_CHANNEL_COUNT, AUDIO_SAMPLE_RATE,
+    MATERIAL_DEFAULT_SHININESS, MATERIAL_DEFAULT_SPECULAR_STRENGTH,
+};
+
+/// Extension of the sample descriptions in the samples folder
+pub const SAMPLE_FILE_EXTENSION: &str = "sample";
+
+/// Line of a sample description, see `assets/samples` for the syntax
+#[derive(Clone, Debug, PartialEq)]
+enum SampleEntry {
+    Material {
+        name: String,
+        texture: String,
+    },
+    Cube {
+        material: String,
+        position: glam::Vec3,
+        size: f32,
+    },
+    /// Gltf file instantiated through the scene graph
+    Scene {
+        path: String,
+        position: glam::Vec3,
+        size: f32,
+    },
+    /// Rotation speed of the cubes and scenes around the up axis, in radians per second
+    Spin(f32),
+    Ambient(glam::Vec3),
+    Sun(DirectionalLight),
+    PointLight(PointLight),
+    Rect {
+        position: glam::Vec2,
+        size: glam::Vec2,
+        color: glam::Vec4,
+    },
+    Sprite {
+        texture: String,
+        columns: u32,
+        rows: u32,
+        frames_per_second: f32,
+        position: glam::Vec2,
+        size: f32,
+    },
+    /// Bitmap font used by the texts, a .fnt file next to its atlas
+    Font(String),
+    Text {
+        position: glam::Vec2,
+        color: glam::Vec4,
+        text: String,
+    },
+    Tone {
+        frequency: f32,
+        duration: f32,
+        volume: f32,
+    },
+}
+
+impl SampleEntry {
+    fn parse_floats<const N: usize>(values: &[&str]) -> Option<[f32; N]> {
+        if values.len() != N {
+            return None;
+        }
+        let mut floats = [0.0; N];
+        for (float, value) in floats.iter_mut().zip(values) {
+            *float = value.parse().ok()?;
+        }
+        Some(floats)
+    }
+
+    fn parse(kind: &str, values: &[&str]) -> Option<Self> {
+        let entry = match (kind, values) {
+            ("material", [name, texture]) => Self::Material {
+                name: name.to_string(),
+                texture: texture.to_string(),
+            },
+            ("cube", [material, rest @ ..]) => {
+                let [x, y, z, size] = Self::parse_floats(rest)?;
+                Self::Cube {
+                    material: material.to_string(),
+                    position: glam::Vec3::new(x, y, z),
+                    size,
+                }
+            }
+            ("scene", [path, rest @ ..]) => {
+                let [x, y, z, size] = Self::parse_floats(rest)?;
+                Self::Scene {
+                    path: path.to_string(),
+                    position: glam::Vec3::new(x, y, z),
+                    size,
+                }
+            }
+            ("spin", _) => {
+                let [speed] = Self::parse_floats(values)?;
+                Self::Spin(speed)
+            }
+            ("ambient", _) => {
+                let [r, g, b] = Self::parse_floats(values)?;
+                Self::Ambient(glam::Vec3::new(r, g, b))
+            }
+            ("sun", _) => {
+                let [x, y, z, r, g, b, intensity] = Self::parse_floats(values)?;
+                Self::Sun(DirectionalLight {
+                    direction: glam::Vec3::new(x, y, z).normalize_or_zero(),
+                    color: glam::Vec3::new(r, g, b),
+                    intensity,
+                })
+            }
+            ("point_light", _) => {
+                let [x, y, z, r, g, b, intensity, range] = Self::parse_floats(values)?;
+                Self::PointLight(PointLight {
+                    position: glam::Vec3::new(x, y, z),
+                    color: glam::Vec3::new(r, g, b),
+                    intensity,
+                    range,
+                })
+            }
+            ("rect", _) => {
+                let [x, y, width, height, r, g, b, a] = Self::parse_floats(values)?;
+                Self::Rect {
+                    position: glam::Vec2::new(x, y),
+                    size: glam::Vec2::new(width, height),
+                    color: glam::Vec4::new(r, g, b, a),
+                }
+            }
+            ("sprite", [texture, columns, rows, rest @ ..]) => {
+                let [frames_per_second, x, y, size] = Self::parse_floats(rest)?;
+                Self::Sprite {
+                    texture: texture.to_string(),
+                    columns: columns.parse().ok()?,
+                    rows: rows.parse().ok()?,
+                    frames_per_second,
+                    position: glam::Vec2::new(x, y),
+                    size,
+                }
+            }
+            ("font", [path]) => Self::Font(path.to_string()),
+            ("text", _) if values.len() > 6 => {
+                let [x, y, r, g, b, a] = Self::parse_floats(&values[..6])?;
+                Self::Text {
+                    position: glam::Vec2::new(x, y),
+                    color: glam::Vec4::new(r, g, b, a),
+                    text: values[6..].join(" "),
+                }
+            }
+            ("tone", _) => {
+                let [frequency, duration, volume] = Self::parse_floats(values)?;
+                Self::Tone {
+                    frequency,
+                    duration,
+                    volume,
+                }
+            }
+            _ => return None,
+        };
+        Some(entry)
+    }
+}
+
+/// Content of a sample file, one `kind = values` entry per line
+#[derive(Clone, Debug, Default)]
+pub struct SampleDescription {
+    pub name: String,
+    entries: Vec<SampleEntry>,
+}
+
+impl SampleDescription {
+    pub fn load(path: &Path) -> Result<Self, EngineError> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(err) => {
+                error!("Failed to read the sample {:?}: {:?}", path, err);
+                return Err(EngineError::IO);
+            }
+        };
+        let mut description = Self {
+            name: path
+                .file_stem()
+                .map(|name| name.to_string_lossy().to_string())
+                .unwrap_or_default(),
+            entries: Vec::new(),
+        };
+        for (line_index, line) in content.lines().enumerate() {
+            let line = match line.split_once('#') {
+                Some((line, _comment)) => line,
+                None => line,
+            }
+            .trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (kind, values) = match line.split_once('=') {
+                Some((kind, values)) => (kind.trim(), values.trim()),
+                None => {
+                    warn!(
+                        "Ignoring the line {} of the sample {:?}, expected `kind = values'",
+                        line_index + 1,
+                        path
+                    );
+                    continue;
+                }
+            };
+            if kind == "name" {
+                description.name = values.to_string();
+                continue;
+            }
+            let values: Vec<&str> = values.split_whitespace().collect();
+            match SampleEntry::parse(kind, &values) {
+                Some(entry) => description.entries.push(entry),
+                None => warn!(
+                    "Ignoring the invalid `{}' entry at the line {} of the sample {:?}",
+                    kind,
+                    line_index + 1,
+                    path
+                ),
+            }
+        }
+        Ok(description)
+    }
+
+    /// Every sample file of the folder, sorted by file name
+    pub fn load_folder(folder: &Path) -> Result<Vec<Self>, EngineError> {
+        let entries = match fs::read_dir(folder) {
+            Ok(entries) => entries,
+            Err(err) => {
+                error!("Failed to list the samples in {:?}: {:?}", folder, err);
+                return Err(EngineError::IO);
+            }
+        };
+        let mut paths: Vec<_> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                path.extension()
+                    .is_some_and(|extension| extension == SAMPLE_FILE_EXTENSION)
+            })
+            .collect();
+        paths.sort();
+        paths.iter().map(|path| Self::load(path)).collect()
+    }
+}
+
+struct SampleSprite {
+    animation: SpriteAnimation,
+    texture: TextureHandle,
+    position: glam::Vec2,
+    size: f32,
+}
+
+/// Renderer and audio resources of the running sample
+#[derive(Default)]
+pub struct Sample {
+    pub name: String,
+    materials: Vec<(String, u32)>,
+    textures: Vec<TextureHandle>,
+    geometries: Vec<u32>,
+    objects: Vec<u32>,
+    /// Cubes with their position and size, rotated by the spin
+    cubes: Vec<(SceneNodeId, glam::Vec3, f32)>,
+    /// Gltf scenes with their position and size, rotated by the spin
+    scenes: Vec<(GltfInstance, glam::Vec3, f32)>,
+    spin_speed: f32,
+    spin_angle: f32,
+    point_lights: Vec<u32>,
+    has_lighting: bool,
+    rects: Vec<(glam::Vec2, glam::Vec2, glam::Vec4)>,
+    sprites: Vec<SampleSprite>,
+    fonts: Vec<u32>,
+    texts: Vec<(glam::Vec2, glam::Vec4, String)>,
+    voices: Vec<u32>,
+}
+
+impl Sample {
+    fn create_cube_geometry() -> Result<u32, EngineError> {
+        let faces = [
+            (glam::Vec3::X, glam::Vec3::NEG_Z),
+            (glam::Vec3::NEG_X, glam::Vec3::Z),
+            (glam::Vec3::Y, glam::Vec3::X),
+            (glam::Vec3::NEG_Y, glam::Vec3::X),
+            (glam::Vec3::Z, glam::Vec3::X),
+            (glam::Vec3::NEG_Z, glam::Vec3::NEG_X),
+        ];
+        let mut vertices = Vec::with_capacity(24);
+        let mut indices = Vec::with_capacity(36);
+        for (face_index, (normal, tangent)) in faces.iter().enumerate() {
+            let bitangent = normal.cross(*tangent);
+            let corners = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+            for (u, v) in corners {
+                let position =
+                    (*normal + *tangent * (u * 2.0 - 1.0) + bitangent * (v * 2.0 - 1.0)) * 0.5;
+                vertices.push(VertexData {
+                    position,
+                    texture: glam::Vec2::new(u, v),
+                    lightmap_texture: glam::Vec2::new(
+                        (face_index as f32 + u) / faces.len() as f32,
+                        v,
+                    ),
+                    normal: *normal,
+                    tangent: tangent.extend(1.0),
+                });
+            }
+            let first = face_index as u32 * 4;
+            indices.extend_from_slice(&[first, first + 1, first + 2, first, first + 2, first + 3]);
+        }
+        renderer_create_geometry(GeometryCreatorParameters {
+            name: "sample cube",
+            vertices: &vertices,
+            indices: &indices,
+        })
+    }
+
+    fn load_texture(&mut self, path: &str) -> Result<TextureHandle, EngineError> {
+        let texture = renderer_load_texture(Path::new(path), path, true)?;
+        self.textures.push(texture);
+        Ok(texture)
+    }
+
+    fn load_entry(&mut self, entry: &SampleEntry) -> Result<(), EngineError> {
+        match entry {
+            SampleEntry::Material { name, texture } => {
+                let texture = self.load_texture(texture)?;
+                let material_id = renderer_acquire_material(MaterialCreatorParameters {
+                    name: &format!("sample {} {}", self.name, name),
+                    auto_release: true,
+                    diffuse_color: glam::Vec4::ONE,
+                    diffuse_texture: Some(texture),
+                    lightmap_texture: None,
+                    normal_texture: None,
+                    specular_texture: None,
+                    specular_strength: MATERIAL_DEFAULT_SPECULAR_STRENGTH,
+                    shininess: MATERIAL_DEFAULT_SHININESS,
+                    render_state: MaterialRenderState::default(),
+                })?;
+                self.materials.push((name.clone(), material_id));
+            }
+            SampleEntry::Cube {
+                material,
+                position,
+                size,
+            } => {
+                let material_id = match self.materials.iter().find(|(name, _)| name == material) {
+                    Some((_, id)) => Some(*id),
+                    None if material == "default" => None,
+                    None => {
+                        error!(
+                            "The cube of the sample `{}' uses the undeclared material `{}'",
+                            self.name, material
+                        );
+                        return Err(EngineError::InvalidValue);
+                    }
+                };
+                if self.geometries.is_empty() {
+                    self.geometries.push(Self::create_cube_geometry()?);
+                }
+                let node = renderer_scene_create_node(None)?;
+                let object_id = renderer_acquire_object(
+                    &format!("{} cube {}", self.name, self.cubes.len()),
+                    Some(node),
+                )?;
+                self.objects.push(object_id);
+                self.cubes.push((node, *position, *size));
+                renderer_scene_set_renderable(
+                    node,
+                    Some(SceneRenderable {
+                        object_id,
+                        geometry_id: self.geometries[0],
+                        material_id,
+                        bounding_sphere: Some(BoundingSphere::new(
+                            glam::Vec3::ZERO,
+                            0.5 * 3f32.sqrt(),
+                        )),
+                    }),
+                )?;
+            }
+            SampleEntry::Scene {
+                path,
+                position,
+                size,
+            } => {
+                let scene = gltf_load(Path::new(path))?;
+                let instance = renderer_instantiate_gltf(&scene, None)?;
+                self.scenes.push((instance, *position, *size));
+            }
+            SampleEntry::Spin(speed) => self.spin_speed = *speed,
+            SampleEntry::Ambient(color) => {
+                self.has_lighting = true;
+                renderer_set_ambient_light(color.extend(1.0))?;
+            }
+            SampleEntry::Sun(light) => {
+                self.has_lighting = true;
+                renderer_set_directional_light(Some(*light))?;
+            }
+            SampleEntry::PointLight(light) => {
+                self.has_lighting = true;
+                self.point_lights.push(renderer_add_point_light(*light)?);
+            }
+            SampleEntry::Rect {
+                position,
+                size,
+                color,
+            } => self.rects.push((*position, *size, *color)),
+            SampleEntry::Sprite {
+                texture,
+                columns,
+                rows,
+                frames_per_second,
+                position,
+                size,
+            } => {
+                let texture = self.load_texture(texture)?;
+                let animation = SpriteAnimation::new(
+                    self.sprites.len() as u32,
+                    SpriteSheet::from_grid(*columns, *rows)?,
+                )
+                .frames_per_second(*frames_per_second);
+                self.sprites.push(SampleSprite {
+                    animation,
+                    texture,
+                    position: *position,
+                    size: *size,
+                });
+            }
+            SampleEntry::Font(path) => self.fonts.push(renderer_load_font(Path::new(path))?),
+            SampleEntry::Text {
+                position,
+                color,
+                text,
+            } => self.texts.push((*position, *color, text.clone())),
+            SampleEntry::Tone {
+                frequency,
+                duration,
+                volume,
+            } => {
+                let frame_count = (duration * AUDIO_SAMPLE_RATE as f32) as usize;
+                // Short fades at both ends so the tone does not click
+                let fade_frame_count = (frame_count / 10).max(1) as f32;
+                let samples: Vec<f32> = (0..frame_count)
+                    .flat_map(|frame| {
+                        let time = frame as f32 / AUDIO_SAMPLE_RATE as f32;
+                        let fade = (frame as f32 / fade_frame_count)
+                            .min((frame_count - frame) as f32 / fade_frame_count)
+                            .min(1.0);
+                        let value = (time * frequency * std::f32::consts::TAU).sin() * fade;
+                        [value; AUDIO_CHANNEL_COUNT]
+                    })
+                    .collect();
+                self.voices.push(audio_play(
+                    AudioBus::Sfx,
+                    Arc::new(samples),
+                    *volume,
+                    false,
+                )?);
+            }
+        }
+        Ok(())
+    }
+
+    pub fn load(description: &SampleDescription) -> Result<Self, EngineError> {
+        let mut sample = Self {
+            name: description.name.clone(),
+            ..Default::default()
+        };
+        for entry in &description.entries {
+            if let Err(err) = sample.load_entry(entry) {
+                error!(
+                    "Failed to load the entry {:?} of the sample `{}': {:?}",
+                    entry, sample.name, err
+                );
+                // Don't leak what was already created
+                sample.unload()?;
+                return Err(EngineError::InitializationFailed);
+            }
+        }
+        sample.update(0.0)?;
+        Ok(sample)
+    }
+
+    pub fn update(&mut self, delta_time: f64) -> Result<(), EngineError> {
+        self.spin_angle += self.spin_speed * delta_time as f32;
+        let rotation = glam::Quat::from_rotation_y(self.spin_angle);
+        let scene_nodes = self
+            .scenes
+            .iter()
+            .map(|(instance, position, size)| (instance.root, *position, *size));
+        for (node, position, size) in self.cubes.iter().copied().chain(scene_nodes) {
+            renderer_scene_set_transform(
+                node,
+                SceneTransform {
+                    position,
+                    rotation,
+                    scale: glam::Vec3::splat(size),
+                },
+            )?;
+        }
+        for (position, size, color) in &self.rects {
+            renderer_draw_rect(*position, *size, *color)?;
+        }
+        for sprite in &mut self.sprites {
+            sprite.animation.update(delta_time)?;
+            if let Some(frame) = sprite.animation.get_current_frame_rect() {
+                let quad = UiQuad::new(sprite.position, glam::Vec2::splat(sprite.size))
+                    .uv(frame.min, frame.max)
+                    .texture(sprite.texture);
+                renderer_ui_draw_quad(&quad)?;
+            }
+        }
+        for (position, color, text) in &self.texts {
+            renderer_draw_text(position.x, position.y, text, *color)?;
+        }
+        Ok(())
+    }
+
+    /// Remove everything the sample added, in the reverse order of the creation
+    pub fn unload(self) -> Result<(), EngineError> {
+        for voice in self.voices {
+            audio_stop(voice)?;
+        }
+        for light in self.point_lights {
+            renderer_remove_point_light(light)?;
+        }
+        if self.has_lighting {
+            renderer_set_directional_light(None)?;
+            renderer_set_ambient_light(glam::Vec4::ONE)?;
+        }
+        for (instance, _, _) in self.scenes {
+            renderer_unload_gltf(instance)?;
+        }
+        for (node, _, _) in self.cubes {
+            renderer_scene_destroy_node(node)?;
+        }
+        for object_id in self.objects {
+            renderer_release_object(object_id)?;
+        }
+        for geometry_id in self.geometries {
+            renderer_destroy_geometry(geometry_id)?;
+        }
+        for (_, material_id) in self.materials {
+            renderer_release_material(material_id)?;
+        }
+        for font in self.fonts {
+            renderer_unload_font(font)?;
+        }
+        for texture in self.textures {
+            renderer_release_texture(texture)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,125 @@
+use std::path::{Path, PathBuf};
+
+use engine::prelude::{
+    error, renderer_acquire_material, renderer_acquire_object, renderer_create_geometry,
+    renderer_destroy_geometry, renderer_load_texture, renderer_release_material,
+    renderer_release_object, renderer_release_texture, renderer_replace_texture_async,
+    renderer_scene_create_node, renderer_scene_destroy_node, renderer_scene_set_renderable,
+    BoundingSphere, EngineError, GeometryCreatorParameters, MaterialCreatorParameters,
+    MaterialRenderState, SceneNodeId, SceneRenderable, TextureHandle, VertexData,
+    MATERIAL_DEFAULT_SHININESS, MATERIAL_DEFAULT_SPECULAR_STRENGTH,
+};
+
+/// Length of the sides of the quad
+const TEST_QUAD_SIZE: f32 = 10.0;
+
+/// Images cycled through by the texture of the quad
+const TEST_QUAD_TEXTURES: [&str; 2] = [
+    "assets/textures/cobblestone.png",
+    "assets/textures/paving.png",
+];
+
+/// Textured quad in front of the starting camera
+pub struct TestQuad {
+    node: SceneNodeId,
+    object_id: u32,
+    geometry_id: u32,
+    material_id: u32,
+    texture: TextureHandle,
+    /// Index of the image shown, in `TEST_QUAD_TEXTURES`
+    texture_index: usize,
+}
+
+impl TestQuad {
+    fn create_geometry() -> Result<u32, EngineError> {
+        let half_size = 0.5 * TEST_QUAD_SIZE;
+        let vertex = |x: f32, y: f32| VertexData {
+            position: glam::Vec3::new(x * half_size, y * half_size, 0.0),
+            texture: glam::Vec2::new(0.5 * (x + 1.0), 0.5 * (y + 1.0)),
+            lightmap_texture: glam::Vec2::new(0.5 * (x + 1.0), 0.5 * (y + 1.0)),
+            normal: glam::Vec3::Z,
+            tangent: glam::Vec4::new(1.0, 0.0, 0.0, 1.0),
+        };
+        let vertices = [
+            vertex(-1.0, -1.0),
+            vertex(1.0, 1.0),
+            vertex(-1.0, 1.0),
+            vertex(1.0, -1.0),
+        ];
+        let indices = [0, 1, 2, 0, 3, 1];
+        renderer_create_geometry(GeometryCreatorParameters {
+            name: "test quad",
+            vertices: &vertices,
+            indices: &indices,
+        })
+    }
+
+    pub fn create() -> Result<Self, EngineError> {
+        let geometry_id = match Self::create_geometry() {
+            Ok(id) => id,
+            Err(err) => {
+                error!("Failed to create the test quad geometry: {:?}", err);
+                return Err(EngineError::InitializationFailed);
+            }
+        };
+        let texture = renderer_load_texture(Path::new(TEST_QUAD_TEXTURES[0]), "test quad", true)?;
+        let material_id = renderer_acquire_material(MaterialCreatorParameters {
+            name: "test quad",
+            auto_release: true,
+            diffuse_color: glam::Vec4::ONE,
+            diffuse_texture: Some(texture),
+            lightmap_texture: None,
+            normal_texture: None,
+            specular_texture: None,
+            specular_strength: MATERIAL_DEFAULT_SPECULAR_STRENGTH,
+            shininess: MATERIAL_DEFAULT_SHININESS,
+            render_state: MaterialRenderState::default(),
+        })?;
+        let node = renderer_scene_create_node(None)?;
+        let object_id = renderer_acquire_object("test quad", Some(node))?;
+        renderer_scene_set_renderable(
+            node,
+            Some(SceneRenderable {
+                object_id,
+                geometry_id,
+                material_id: Some(material_id),
+                bounding_sphere: Some(BoundingSphere::new(
+                    glam::Vec3::ZERO,
+                    0.5 * TEST_QUAD_SIZE * std::f32::consts::SQRT_2,
+                )),
+            }),
+        )?;
+        Ok(Self {
+            node,
+            object_id,
+            geometry_id,
+            material_id,
+            texture,
+            texture_index: 0,
+        })
+    }
+
+    /// Show the next image, it is decoded on a worker thread so the frame doesn't hitch
+    pub fn swap_texture(&mut self) -> Result<(), EngineError> {
+        self.texture_index = (self.texture_index + 1) % TEST_QUAD_TEXTURES.len();
+        let path = TEST_QUAD_TEXTURES[self.texture_index];
+        renderer_replace_texture_async(self.texture, PathBuf::from(path), move |result| {
+            if let Err(err) = &result {
+                error!(
+                    "Failed to swap the test quad texture to {}: {:?}",
+                    path, err
+                );
+            }
+            result
+        })?;
+        Ok(())
+    }
+
+    pub fn destroy(self) -> Result<(), EngineError> {
+        renderer_scene_destroy_node(self.node)?;
+        renderer_release_object(self.object_id)?;
+        renderer_destroy_geometry(self.geometry_id)?;
+        renderer_release_material(self.material_id)?;
+        renderer_release_texture(self.texture)
+    }
+}
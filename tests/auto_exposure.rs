@@ -0,0 +1,35 @@
+use engine::prelude::*;
+
+/// Auto exposure adapting to a frame whose samples all have the given luminance
+fn update_with_percentiles(low_percentile: f32, high_percentile: f32) -> AutoExposure {
+    let mut auto_exposure = AutoExposure {
+        parameters: AutoExposureParameters::default().percentiles(low_percentile, high_percentile),
+        ..Default::default()
+    };
+    let mut histogram = auto_exposure.create_histogram();
+    for _ in 0..100 {
+        histogram.add_sample(1.0);
+    }
+    auto_exposure.update(&histogram, 1.0);
+    auto_exposure
+}
+
+#[test]
+fn auto_exposure_adapts_between_the_percentiles() {
+    let initial_ev = AutoExposure::default().ev;
+    let auto_exposure = update_with_percentiles(0.1, 0.9);
+    assert!(auto_exposure.ev.is_finite());
+    assert_ne!(auto_exposure.ev, initial_ev);
+}
+
+#[test]
+fn auto_exposure_ignores_invalid_percentiles() {
+    let initial_ev = AutoExposure::default().ev;
+    for (low_percentile, high_percentile) in [(1.5, 0.2), (f32::NAN, 0.9), (0.1, f32::NAN)] {
+        let auto_exposure = update_with_percentiles(low_percentile, high_percentile);
+        assert!(auto_exposure.ev.is_finite());
+        if low_percentile > 1.0 {
+            assert_eq!(auto_exposure.ev, initial_ev);
+        }
+    }
+}
@@ -0,0 +1,73 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc, Mutex,
+};
+
+use engine::prelude::*;
+
+/// Registers closures after the engine listeners and fires the events they leave unhandled
+#[derive(Default)]
+struct ListeningGame {
+    pressed_keys: Arc<Mutex<Vec<u16>>>,
+    typed_characters: Arc<Mutex<Vec<char>>>,
+    quits: Arc<AtomicU32>,
+}
+
+impl Game for ListeningGame {
+    fn on_start(&mut self) -> Result<(), EngineError> {
+        let pressed_keys = Arc::clone(&self.pressed_keys);
+        event_register_fn(EventCode::any_key_pressed(), move |context| {
+            pressed_keys
+                .lock()
+                .unwrap()
+                .push(context.get_key_code().unwrap());
+            false
+        })?;
+        let typed_characters = Arc::clone(&self.typed_characters);
+        event_register_fn(EventCode::any_char_typed(), move |context| {
+            typed_characters
+                .lock()
+                .unwrap()
+                .push(context.get_character().unwrap());
+            false
+        })?;
+        let quits = Arc::clone(&self.quits);
+        event_register_fn(EventCode::ApplicationQuit, move |_| {
+            quits.fetch_add(1, Ordering::SeqCst);
+            false
+        })?;
+
+        event_fire(EventCode::KeyPressed {
+            key_code: Key::A as u16,
+        })?;
+        event_fire(EventCode::CharTyped { character: 'a' })?;
+        Ok(())
+    }
+
+    fn on_update(&mut self, _delta_time: f64) -> Result<(), EngineError> {
+        // Handled by the engine, only the quit it fires reaches the game
+        event_fire(EventCode::KeyPressed {
+            key_code: Key::ESCAPE as u16,
+        })
+    }
+}
+
+/// The engine can only be started once per process, the whole run is checked by a single test
+#[test]
+fn game_closures_receive_the_events_unhandled_by_the_engine() {
+    let game = ListeningGame::default();
+    let (pressed_keys, typed_characters, quits) = (
+        Arc::clone(&game.pressed_keys),
+        Arc::clone(&game.typed_characters),
+        Arc::clone(&game.quits),
+    );
+    let parameters = ApplicationParameters::default()
+        .application_name(String::from("EventListenersTest"))
+        .renderer_backend(RendererBackendType::Null);
+
+    engine_start(parameters, Box::new(game)).expect("Failed to run the engine on the null backend");
+
+    assert_eq!(*pressed_keys.lock().unwrap(), vec![Key::A as u16]);
+    assert_eq!(*typed_characters.lock().unwrap(), vec!['a']);
+    assert_eq!(quits.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,31 @@
+use engine::prelude::*;
+
+#[test]
+fn two_bone_ik_reaches_a_target_in_range() {
+    let (root, mid, end) = (glam::Vec3::ZERO, glam::Vec3::Y, glam::Vec3::Y * 2.0);
+    let target = glam::Vec3::new(1.0, 1.0, 0.0);
+
+    let (solved_mid, solved_end) = solve_two_bone_ik(root, mid, end, target, glam::Vec3::Z);
+
+    assert!(((solved_mid - root).length() - 1.0).abs() < 1e-3);
+    assert!(((solved_end - solved_mid).length() - 1.0).abs() < 1e-3);
+    assert!(solved_end.distance(target) < 1e-3);
+}
+
+#[test]
+fn two_bone_ik_keeps_degenerate_bones_unchanged() {
+    let target = glam::Vec3::new(1.0, 1.0, 0.0);
+    let chains = [
+        // Coincident root and mid joints
+        (glam::Vec3::ZERO, glam::Vec3::ZERO, glam::Vec3::Y),
+        // Coincident mid and end joints
+        (glam::Vec3::ZERO, glam::Vec3::Y, glam::Vec3::Y),
+        // Bones shorter than the epsilon of the solver
+        (glam::Vec3::ZERO, glam::Vec3::Y * 1e-5, glam::Vec3::Y * 2e-5),
+    ];
+
+    for (root, mid, end) in chains {
+        let (solved_mid, solved_end) = solve_two_bone_ik(root, mid, end, target, glam::Vec3::Z);
+        assert_eq!((solved_mid, solved_end), (mid, end));
+    }
+}
@@ -0,0 +1,68 @@
+use std::sync::{
+    atomic::{AtomicU32, Ordering},
+    Arc,
+};
+
+use engine::prelude::*;
+
+const FRAME_COUNT: u32 = 10;
+
+/// Counts the calls of the engine, quits once enough frames are rendered
+#[derive(Default)]
+struct CountingGame {
+    starts: Arc<AtomicU32>,
+    updates: Arc<AtomicU32>,
+    renders: Arc<AtomicU32>,
+    shutdowns: Arc<AtomicU32>,
+}
+
+impl Game for CountingGame {
+    fn on_start(&mut self) -> Result<(), EngineError> {
+        assert_eq!(engine_get_lifecycle_state()?, EngineLifecycleState::Running);
+        self.starts.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn on_update(&mut self, _delta_time: f64) -> Result<(), EngineError> {
+        if self.updates.fetch_add(1, Ordering::SeqCst) + 1 == FRAME_COUNT {
+            event_fire(EventCode::ApplicationQuit)?;
+        }
+        Ok(())
+    }
+
+    fn on_render(&self, _delta_time: f64, _interpolation: f64) -> Result<(), EngineError> {
+        self.renders.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+
+    fn on_shutdown(&mut self) -> Result<(), EngineError> {
+        self.shutdowns.fetch_add(1, Ordering::SeqCst);
+        Ok(())
+    }
+}
+
+/// The engine can only be started once per process, the whole run is checked by a single test
+#[test]
+fn engine_runs_frames_on_the_null_backend() {
+    let game = CountingGame::default();
+    let (starts, updates, renders, shutdowns) = (
+        Arc::clone(&game.starts),
+        Arc::clone(&game.updates),
+        Arc::clone(&game.renders),
+        Arc::clone(&game.shutdowns),
+    );
+    let parameters = ApplicationParameters::default()
+        .application_name(String::from("NullBackendTest"))
+        .renderer_backend(RendererBackendType::Null);
+
+    engine_start(parameters, Box::new(game)).expect("Failed to run the engine on the null backend");
+
+    assert_eq!(starts.load(Ordering::SeqCst), 1);
+    assert_eq!(updates.load(Ordering::SeqCst), FRAME_COUNT);
+    assert!(renders.load(Ordering::SeqCst) >= FRAME_COUNT - 1);
+    assert_eq!(shutdowns.load(Ordering::SeqCst), 1);
+    assert_eq!(
+        engine_get_lifecycle_state().expect("Failed to get the engine state"),
+        EngineLifecycleState::ShutDown
+    );
+}